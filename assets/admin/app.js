@@ -0,0 +1,109 @@
+// Dashboard for /admin: polls /api/v1/admin/overview and offers cancel/reprint actions.
+const REFRESH_MS = 5000;
+
+function authHeaders() {
+    const token = sessionStorage.getItem("adminToken");
+    return token ? { "Authorization": "Bearer " + token } : {};
+}
+
+async function api(method, path) {
+    const response = await fetch("/api/v1/admin" + path, { method, headers: authHeaders() });
+    if (response.status === 401) {
+        const token = prompt("Admin token");
+        if (token === null) {
+            throw new Error("Admin token required");
+        }
+        sessionStorage.setItem("adminToken", token);
+        return api(method, path);
+    }
+    const body = await response.json();
+    if (!response.ok) {
+        throw new Error(body.message || response.statusText);
+    }
+    return body;
+}
+
+function cell(row, text, className) {
+    const td = document.createElement("td");
+    td.textContent = text;
+    if (className) {
+        td.className = className;
+    }
+    row.appendChild(td);
+    return td;
+}
+
+function button(td, label, action) {
+    const element = document.createElement("button");
+    element.textContent = label;
+    element.onclick = async () => {
+        try {
+            const result = await action();
+            alert(result.message);
+        } catch (e) {
+            alert(e.message);
+        }
+        refresh();
+    };
+    td.appendChild(element);
+}
+
+function render(overview) {
+    const pending = Object.values(overview.pending_jobs).reduce((sum, count) => sum + count, 0);
+    document.getElementById("queued").textContent = pending;
+    document.getElementById("in-flight").textContent = overview.jobs_in_flight;
+    document.getElementById("printers-online").textContent =
+        overview.printers.filter((p) => p.state !== "offline").length + " / " + overview.printers.length;
+    document.getElementById("failed").textContent =
+        overview.recent_jobs.filter((job) => job.status === "failed").length;
+
+    const printers = document.getElementById("printers");
+    printers.replaceChildren();
+    for (const printer of overview.printers) {
+        const row = printers.insertRow();
+        cell(row, printer.name + (printer.is_default ? " (default)" : ""));
+        cell(row, printer.state, "state-" + printer.state);
+        cell(row, printer.queued_jobs);
+        cell(row, printer.active_jobs);
+    }
+
+    const agents = document.getElementById("agents");
+    agents.replaceChildren();
+    for (const [agent, count] of Object.entries(overview.pending_jobs)) {
+        const row = agents.insertRow();
+        cell(row, agent);
+        cell(row, count);
+    }
+
+    const jobs = document.getElementById("jobs");
+    jobs.replaceChildren();
+    for (const job of overview.recent_jobs) {
+        const row = jobs.insertRow();
+        cell(row, job.id);
+        cell(row, new Date(job.submitted_at).toLocaleString());
+        cell(row, job.filename);
+        cell(row, job.agent_id ? job.agent_id + " / " + (job.printer_name || "-") : job.printer_name || "-");
+        cell(row, job.pages);
+        cell(row, job.status, "status-" + job.status);
+        cell(row, job.message);
+        const actions = cell(row, "");
+        if (job.status === "printed" || job.status === "queued") {
+            button(actions, "Cancel", () => api("POST", "/jobs/" + job.id + "/cancel"));
+        }
+        if (job.reprintable) {
+            button(actions, "Reprint", () => api("POST", "/jobs/" + job.id + "/reprint"));
+        }
+    }
+    document.getElementById("updated").textContent = "Updated " + new Date().toLocaleTimeString();
+}
+
+async function refresh() {
+    try {
+        render(await api("GET", "/overview"));
+    } catch (e) {
+        document.getElementById("updated").textContent = e.message;
+    }
+}
+
+refresh();
+setInterval(refresh, REFRESH_MS);
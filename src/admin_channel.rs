@@ -0,0 +1,210 @@
+// ----------------------------------------------------------------------
+//                          LOCAL ADMIN CHANNEL
+// ----------------------------------------------------------------------
+
+use crate::{
+    agent::{AgentRegistry, RemoteJobQueue},
+    backend::print_backend,
+    config::{reload_config, SharedConfig, CONFIG_PATH},
+    dispatch::PrintDispatcher,
+    uploads::UploadStore,
+};
+use actix_web::web;
+use std::io::{BufRead, Write};
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::Instant;
+use windows_service::service::SessionChangeReason;
+
+/// สถานะของ Service ที่ช่องทางผู้ดูแลระบบใช้งาน
+#[derive(Clone)]
+pub(crate) struct AdminContext {
+    pub(crate) config: web::Data<SharedConfig>,
+    pub(crate) queue: web::Data<RemoteJobQueue>,
+    pub(crate) registry: web::Data<AgentRegistry>,
+    pub(crate) uploads: web::Data<UploadStore>,
+    pub(crate) dispatcher: web::Data<PrintDispatcher>,
+    pub(crate) started_at: Instant,
+}
+
+/// ข้อมูลสำหรับตรวจสอบปัญหาของ Service
+pub(crate) fn admin_diagnostics(context: &AdminContext) -> serde_json::Value {
+    let config = context.config.get();
+    let printers: Vec<String> = print_backend()
+        .printers()
+        .into_iter()
+        .map(|printer| printer.name)
+        .collect();
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "pid": std::process::id(),
+        "uptime_secs": context.started_at.elapsed().as_secs(),
+        "working_dir": std::env::current_dir().ok().map(|dir| dir.display().to_string()),
+        "default_printer": config.default_printer,
+        "system_default_printer": print_backend().default_printer().map(|printer| printer.name),
+        "print_backend": print_backend().describe(),
+        "printers": printers,
+        "font_fallbacks": config.font_fallbacks.len(),
+        "agent_mode": config.agent.as_ref().map(|agent| &agent.agent_id),
+        "agents": context.registry.sites(&config, &context.queue),
+        "jobs_in_flight": context.queue.in_flight_count(),
+        "active_uploads": context.uploads.active_count(),
+        "user_sessions": context
+            .dispatcher
+            .sessions
+            .list()
+            .into_iter()
+            .map(|(session_id, user)| serde_json::json!({"session_id": session_id, "user": user}))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// ประมวลผลคำสั่งหนึ่งบรรทัดจากช่องทางผู้ดูแลระบบ
+pub(crate) fn handle_admin_command(line: &str, context: &AdminContext) -> serde_json::Value {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    let argument = words.next();
+    let error = |message: String| serde_json::json!({"status": "error", "message": message});
+    match command {
+        "reload-config" => match reload_config(&context.config, "admin channel") {
+            Ok((config, restart_required)) => serde_json::json!({
+                "status": "success",
+                "message": format!("Reloaded {}", CONFIG_PATH),
+                "agents": config.agents.len(),
+                "default_printer": config.default_printer,
+                "restart_required": restart_required,
+            }),
+            Err(e) => error(format!("Failed to reload {}: {}", CONFIG_PATH, e)),
+        },
+        "drain-queue" => {
+            let removed = context.queue.drain(argument);
+            println!("Admin: drained {} pending jobs", removed);
+            serde_json::json!({
+                "status": "success",
+                "message": format!("Removed {} pending jobs", removed),
+                "removed": removed,
+            })
+        }
+        "diagnostics" => admin_diagnostics(context),
+        "help" | "" => serde_json::json!({
+            "status": "success",
+            "commands": ["reload-config", "drain-queue [agent_id]", "diagnostics", "help"],
+        }),
+        _ => error(format!("Unknown command: {}", command)),
+    }
+}
+
+/// control code ที่ผู้ใช้กำหนดเอง (`sc control "Rust Print API" 128`) สำหรับเครื่องที่เรียก HTTP API ไม่ได้
+pub(crate) const SERVICE_CONTROL_DRAIN_QUEUE: u32 = 128;
+pub(crate) const SERVICE_CONTROL_DUMP_DIAGNOSTICS: u32 = 129;
+pub(crate) const DIAGNOSTICS_DIR: &str = "./diagnostics";
+
+/// คำสั่งจาก Windows service control ที่ส่งต่อให้ thread ของ Service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ServiceCommand {
+    /// `sc control ... paramchange`
+    ReloadConfig,
+    /// ลบงานที่รอ agent มาดึงทั้งหมด เหมือนคำสั่ง `drain-queue` ของช่องทางผู้ดูแลระบบ
+    DrainQueue,
+    /// เขียนผลของคำสั่ง `diagnostics` ลงไฟล์ใน `./diagnostics`
+    DumpDiagnostics,
+    /// ผู้ใช้เข้า/ออกจากระบบ Windows (`SERVICE_CONTROL_SESSIONCHANGE`)
+    SessionChange {
+        session_id: u32,
+        reason: SessionChangeReason,
+    },
+}
+
+impl ServiceCommand {
+    /// แปลง control code ที่ผู้ใช้กำหนดเอง (128 - 255) เป็นคำสั่ง
+    pub(crate) fn from_user_code(code: u32) -> Option<Self> {
+        match code {
+            SERVICE_CONTROL_DRAIN_QUEUE => Some(ServiceCommand::DrainQueue),
+            SERVICE_CONTROL_DUMP_DIAGNOSTICS => Some(ServiceCommand::DumpDiagnostics),
+            _ => None,
+        }
+    }
+}
+
+/// เขียนข้อมูลสำหรับตรวจสอบปัญหาลงไฟล์ `./diagnostics/diagnostics-<เวลา>.json` คืนค่า path ของไฟล์
+pub(crate) fn dump_diagnostics(context: &AdminContext) -> std::io::Result<PathBuf> {
+    let now = chrono::Local::now();
+    let mut diagnostics = admin_diagnostics(context);
+    diagnostics["generated_at"] = now.to_rfc3339().into();
+    let path = Path::new(DIAGNOSTICS_DIR)
+        .join(format!("diagnostics-{}.json", now.format("%Y%m%d-%H%M%S")));
+    std::fs::create_dir_all(DIAGNOSTICS_DIR)?;
+    std::fs::write(&path, serde_json::to_vec_pretty(&diagnostics)?)?;
+    Ok(path)
+}
+
+/// ทำคำสั่งที่ได้รับจาก service control ทีละคำสั่ง (ทำงานใน thread แยก)
+pub(crate) fn run_service_commands(
+    commands: mpsc::Receiver<ServiceCommand>,
+    context: AdminContext,
+) {
+    for command in commands {
+        match command {
+            ServiceCommand::ReloadConfig => {
+                let _ = reload_config(&context.config, "service control");
+            }
+            ServiceCommand::DrainQueue => {
+                let removed = context.queue.drain(None);
+                println!("Service control: drained {} pending jobs", removed);
+            }
+            ServiceCommand::DumpDiagnostics => match dump_diagnostics(&context) {
+                Ok(path) => println!("Service control: wrote diagnostics to {}", path.display()),
+                Err(e) => eprintln!("Failed to write diagnostics: {}", e),
+            },
+            ServiceCommand::SessionChange { session_id, reason } => context
+                .dispatcher
+                .sessions
+                .session_changed(session_id, reason),
+        }
+    }
+}
+
+/// รับคำสั่งผู้ดูแลระบบทีละบรรทัดผ่าน TCP ที่ผูกกับ 127.0.0.1 เท่านั้น (ทำงานใน thread แยก)
+///
+/// แต่ละคำสั่งตอบกลับเป็น JSON หนึ่งบรรทัด ใช้งานได้แม้ HTTP API จะถูกจำกัดเฉพาะ client ที่ยืนยันตัวตนแล้ว
+pub(crate) fn run_admin_channel(port: u16, context: AdminContext) {
+    let listener = match std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "Admin channel disabled: failed to bind 127.0.0.1:{}: {}",
+                port, e
+            );
+            return;
+        }
+    };
+    println!("Admin channel listening on 127.0.0.1:{}", port);
+
+    let context = Arc::new(context);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let context = context.clone();
+        std::thread::spawn(move || {
+            let Ok(reader) = stream.try_clone() else {
+                return;
+            };
+            let mut writer = stream;
+            for line in std::io::BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let line = line.trim();
+                if line == "quit" || line == "exit" {
+                    break;
+                }
+                let response = handle_admin_command(line, &context);
+                if writeln!(writer, "{}", response).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
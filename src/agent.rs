@@ -0,0 +1,1221 @@
+// ----------------------------------------------------------------------
+//                          REMOTE PRINT AGENT
+// ----------------------------------------------------------------------
+
+use crate::{
+    backend::print_backend,
+    client_identity::client_address,
+    config::{AgentConfig, AppConfig, SharedConfig},
+    crypto::{read_job_file, write_job_file, AtRestKey},
+    dispatch::PrintDispatcher,
+    history::{JobHistory, JobStatus},
+    hooks::spawn_post_complete_hook,
+    job_settings::{dispatch_print_job, JobSettings},
+    notify::notify_job_expired,
+    sessions::resolve_printer_as,
+};
+use actix_web::{web, HttpRequest};
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// agent ที่ดึงงานไปแล้วแต่ไม่รายงานผลภายในเวลานี้ งานจะถูกส่งให้ดึงใหม่อีกครั้ง
+pub(crate) const AGENT_JOB_LEASE: Duration = Duration::from_secs(300);
+/// agent ที่ไม่ติดต่อมานานเกินเวลานี้จะถูกตั้งสถานะเป็น offline
+pub(crate) const AGENT_OFFLINE_AFTER: Duration = Duration::from_secs(90);
+
+/// งานพิมพ์ที่แปลงเป็น A6 แล้ว รอให้ agent ดึงไปพิมพ์
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub(crate) struct RemoteJob {
+    pub(crate) id: u64,
+    job_name: String,
+    /// ชื่อเครื่องพิมพ์ที่สาขา (ไม่ระบุ = ใช้เครื่องพิมพ์เริ่มต้นของ agent)
+    printer_name: Option<String>,
+    settings: JobSettings,
+    /// ไฟล์ PDF ที่แปลงแล้ว เข้ารหัสแบบ base64
+    data: String,
+    /// งานที่ยังไม่ได้ถูกดึงไปเมื่อถึงเวลานี้จะถูกทิ้ง
+    #[serde(skip)]
+    expires_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+/// โฟลเดอร์เก็บงานในคิวของ agent ไว้บนดิสก์ เพื่อไม่ให้งานหายเมื่อ Service หยุดทำงานหรือเครื่องรีสตาร์ท
+/// (`<job id>.pdf` คือไฟล์งาน และ `<job id>.json` คือข้อมูลงาน)
+pub(crate) const QUEUE_SPOOL_DIR: &str = "./printable_files/.queue";
+
+/// สิ่งที่ทำกับงานที่กู้คืนจากคิวบนดิสก์ตอนเริ่ม Service
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum QueueRecovery {
+    /// ให้ agent ดึงงานต่อได้ทันที
+    #[default]
+    Resume,
+    /// พักงานไว้จนกว่าผู้ดูแลระบบจะสั่ง `release` หรือ `discard`
+    Review,
+}
+
+/// ข้อมูลของงานในคิวที่เก็บไว้ใน `<job id>.json`
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SpooledJob {
+    agent_id: String,
+    job_name: String,
+    printer_name: Option<String>,
+    settings: JobSettings,
+    /// RFC 3339
+    expires_at: Option<String>,
+    /// RFC 3339
+    queued_at: String,
+}
+
+/// งานที่กู้คืนจากคิวบนดิสก์และรอผู้ดูแลระบบตรวจสอบ
+#[derive(Serialize, ToSchema)]
+pub(crate) struct HeldJob {
+    #[schema(example = 42)]
+    job_id: u64,
+    #[schema(example = "branch-01")]
+    agent_id: String,
+    job_name: String,
+    printer_name: Option<String>,
+    /// เวลาที่งานเข้าคิว (RFC 3339)
+    #[schema(example = "2024-05-01T09:30:00+07:00")]
+    queued_at: String,
+}
+
+/// ผลการพิมพ์ที่ agent รายงานกลับมา
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct AgentJobResult {
+    pub(crate) success: bool,
+    #[schema(example = "Sent to printer Office_LaserJet")]
+    pub(crate) message: String,
+}
+
+#[derive(Default)]
+pub(crate) struct RemoteJobQueueState {
+    next_id: u64,
+    pending: HashMap<String, VecDeque<RemoteJob>>,
+    /// งานที่ agent ดึงไปแล้วแต่ยังไม่รายงานผล พร้อม agent id และเวลาที่ดึงไป
+    in_flight: HashMap<u64, (String, Instant, RemoteJob)>,
+    /// เครื่องพิมพ์ของ agent ที่หยุดส่งงานไว้ชั่วคราว (agent id, ชื่อเครื่องพิมพ์)
+    paused: BTreeSet<(String, String)>,
+    /// งานที่กู้คืนตอนเริ่ม Service และรอผู้ดูแลระบบตรวจสอบ (`queue_recovery: "review"`) พร้อมเวลาที่เข้าคิว
+    held: BTreeMap<u64, String>,
+}
+
+/// คิวงานพิมพ์ของแต่ละ agent (เก็บในหน่วยความจำของ central server และใน [`QUEUE_SPOOL_DIR`])
+#[derive(Default)]
+pub(crate) struct RemoteJobQueue {
+    state: Mutex<RemoteJobQueueState>,
+}
+
+/// ลบไฟล์ของงานออกจากคิวบนดิสก์ (งานที่ไม่ได้เก็บลงดิสก์จะไม่มีไฟล์ให้ลบ)
+pub(crate) fn remove_spooled_job(id: u64) {
+    for extension in ["pdf", "json"] {
+        let path = Path::new(QUEUE_SPOOL_DIR).join(format!("{}.{}", id, extension));
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("Error removing {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// อ่านงานหนึ่งงานจากคิวบนดิสก์
+pub(crate) fn read_spooled_job(
+    key: Option<&AtRestKey>,
+    metadata_path: &Path,
+) -> std::io::Result<(u64, SpooledJob, Vec<u8>)> {
+    let id = metadata_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.parse::<u64>().ok())
+        .ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "File name is not a job id")
+        })?;
+    let job: SpooledJob = serde_json::from_slice(&std::fs::read(metadata_path)?)?;
+    let data = read_job_file(key, &metadata_path.with_extension("pdf"))?;
+    Ok((id, job, data))
+}
+
+impl RemoteJobQueue {
+    /// กู้คืนคิวจาก [`QUEUE_SPOOL_DIR`] ตามลำดับ job id
+    ///
+    /// งานที่ agent ดึงไปแล้วแต่ยังไม่รายงานผลก่อน Service หยุดทำงานจะกลับมารอในคิวด้วย
+    /// เพราะไม่รู้ว่าพิมพ์ออกไปแล้วหรือยัง ใช้ `queue_recovery: "review"` หากไม่ต้องการให้พิมพ์ซ้ำโดยอัตโนมัติ
+    pub(crate) fn restore(config: &AppConfig) -> Self {
+        let queue = RemoteJobQueue::default();
+        let Ok(entries) = std::fs::read_dir(QUEUE_SPOOL_DIR) else {
+            return queue;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .collect();
+        paths.sort_by_key(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+        });
+
+        let mut state = queue.state.lock().unwrap();
+        for path in paths {
+            let (id, job, data) = match read_spooled_job(config.at_rest_key.as_ref(), &path) {
+                Ok(job) => job,
+                Err(e) => {
+                    eprintln!("Error restoring queued job {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            state.next_id = state.next_id.max(id);
+            let expires_at = match job
+                .expires_at
+                .as_deref()
+                .map(chrono::DateTime::parse_from_rfc3339)
+            {
+                Some(Ok(expires_at)) => Some(expires_at.with_timezone(&chrono::Local)),
+                Some(Err(e)) => {
+                    eprintln!(
+                        "Error restoring queued job {}: invalid expires_at: {}",
+                        id, e
+                    );
+                    continue;
+                }
+                None => None,
+            };
+            if config.queue_recovery == QueueRecovery::Review {
+                state.held.insert(id, job.queued_at);
+            }
+            state
+                .pending
+                .entry(job.agent_id)
+                .or_default()
+                .push_back(RemoteJob {
+                    id,
+                    job_name: job.job_name,
+                    printer_name: job.printer_name,
+                    settings: job.settings,
+                    data: base64::engine::general_purpose::STANDARD.encode(data),
+                    expires_at,
+                });
+        }
+        let restored: usize = state.pending.values().map(VecDeque::len).sum();
+        if restored > 0 {
+            match config.queue_recovery {
+                QueueRecovery::Resume => println!("Restored {} queued agent jobs", restored),
+                QueueRecovery::Review => println!(
+                    "Restored {} queued agent jobs, held for review until released",
+                    restored
+                ),
+            }
+        }
+        drop(state);
+        queue
+    }
+
+    /// เก็บงาน `job_id` ที่อยู่ในคิวลงดิสก์ เพื่อกู้คืนได้หาก Service หยุดทำงานก่อน agent มาดึงไปพิมพ์
+    pub(crate) fn spool(
+        &self,
+        key: Option<&AtRestKey>,
+        agent_id: &str,
+        job_id: u64,
+        file_data: &[u8],
+    ) -> std::io::Result<()> {
+        let job = {
+            let state = self.state.lock().unwrap();
+            let Some(job) = state
+                .pending
+                .get(agent_id)
+                .and_then(|jobs| jobs.iter().find(|job| job.id == job_id))
+            else {
+                return Ok(());
+            };
+            SpooledJob {
+                agent_id: agent_id.to_string(),
+                job_name: job.job_name.clone(),
+                printer_name: job.printer_name.clone(),
+                settings: job.settings.clone(),
+                expires_at: job.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+                queued_at: chrono::Local::now().to_rfc3339(),
+            }
+        };
+        let dir = Path::new(QUEUE_SPOOL_DIR);
+        std::fs::create_dir_all(dir)?;
+        write_job_file(key, &dir.join(format!("{}.pdf", job_id)), file_data)?;
+        // เขียนข้อมูลงานหลังไฟล์งาน เพราะตอนกู้คืนจะอ่านเฉพาะงานที่มี `<job id>.json`
+        std::fs::write(
+            dir.join(format!("{}.json", job_id)),
+            serde_json::to_vec(&job)?,
+        )
+    }
+
+    /// งานที่กู้คืนและรอผู้ดูแลระบบตรวจสอบ
+    pub(crate) fn held(&self) -> Vec<HeldJob> {
+        let state = self.state.lock().unwrap();
+        let mut held: Vec<HeldJob> = state
+            .pending
+            .iter()
+            .flat_map(|(agent_id, jobs)| jobs.iter().map(move |job| (agent_id, job)))
+            .filter_map(|(agent_id, job)| {
+                state.held.get(&job.id).map(|queued_at| HeldJob {
+                    job_id: job.id,
+                    agent_id: agent_id.clone(),
+                    job_name: job.job_name.clone(),
+                    printer_name: job.printer_name.clone(),
+                    queued_at: queued_at.clone(),
+                })
+            })
+            .collect();
+        held.sort_by_key(|job| job.job_id);
+        held
+    }
+
+    /// ให้ agent ดึงงานที่พักไว้ได้ คืนค่า `false` หากงานนี้ไม่ได้ถูกพักไว้
+    pub(crate) fn release(&self, job_id: u64) -> bool {
+        self.state.lock().unwrap().held.remove(&job_id).is_some()
+    }
+
+    /// ทิ้งงานที่พักไว้ คืนค่า `false` หากงานนี้ไม่ได้ถูกพักไว้
+    pub(crate) fn discard(&self, job_id: u64) -> bool {
+        let is_held = self.state.lock().unwrap().held.contains_key(&job_id);
+        is_held && self.cancel(job_id)
+    }
+
+    /// เพิ่มงานเข้าคิวของ `agent_id` คืนค่า job id
+    pub(crate) fn push(
+        &self,
+        agent_id: &str,
+        job_name: String,
+        printer_name: Option<String>,
+        settings: JobSettings,
+        file_data: &[u8],
+        expires_at: Option<chrono::DateTime<chrono::Local>>,
+    ) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+        state
+            .pending
+            .entry(agent_id.to_string())
+            .or_default()
+            .push_back(RemoteJob {
+                id,
+                job_name,
+                printer_name,
+                settings,
+                data: base64::engine::general_purpose::STANDARD.encode(file_data),
+                expires_at,
+            });
+        id
+    }
+
+    /// ลบงานที่เลยกำหนด `expires_at` ออกจากคิว รวมถึงงานที่ agent ดึงไปแต่ไม่รายงานผลจนหมดเวลา lease
+    /// คืนค่า agent id และ job id ของงานที่ลบ
+    fn expire(&self) -> Vec<(String, u64)> {
+        let now = chrono::Local::now();
+        let is_expired =
+            |job: &RemoteJob| job.expires_at.is_some_and(|expires_at| expires_at <= now);
+        let mut state = self.state.lock().unwrap();
+        let mut expired = Vec::new();
+        for (agent_id, jobs) in &mut state.pending {
+            jobs.retain(|job| {
+                let keep = !is_expired(job);
+                if !keep {
+                    expired.push((agent_id.clone(), job.id));
+                }
+                keep
+            });
+        }
+        state.in_flight.retain(|id, (agent_id, taken_at, job)| {
+            let keep = taken_at.elapsed() <= AGENT_JOB_LEASE || !is_expired(job);
+            if !keep {
+                expired.push((agent_id.clone(), *id));
+            }
+            keep
+        });
+        for (_, id) in &expired {
+            state.held.remove(id);
+            remove_spooled_job(*id);
+        }
+        expired
+    }
+
+    /// ดึงงานถัดไปของ `agent_id` โดยงานที่หมดเวลา lease จะถูกส่งให้อีกครั้งก่อน
+    pub(crate) fn next(&self, agent_id: &str) -> Option<RemoteJob> {
+        let mut state = self.state.lock().unwrap();
+        let expired: Vec<u64> = state
+            .in_flight
+            .iter()
+            .filter(|(_, (owner, taken_at, _))| {
+                owner == agent_id && taken_at.elapsed() > AGENT_JOB_LEASE
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired {
+            if let Some((_, _, job)) = state.in_flight.remove(&id) {
+                println!(
+                    "Requeued job {} for agent {} (no result reported)",
+                    id, agent_id
+                );
+                state
+                    .pending
+                    .entry(agent_id.to_string())
+                    .or_default()
+                    .push_front(job);
+            }
+        }
+
+        // งานของเครื่องพิมพ์ที่หยุดไว้ยังอยู่ในคิวตามลำดับเดิม จนกว่าจะสั่งให้เครื่องพิมพ์ทำงานต่อ
+        let RemoteJobQueueState {
+            pending,
+            paused,
+            held,
+            ..
+        } = &mut *state;
+        let jobs = pending.get_mut(agent_id)?;
+        let position = jobs.iter().position(|job| {
+            !held.contains_key(&job.id)
+                && job.printer_name.as_ref().is_none_or(|printer_name| {
+                    !paused.contains(&(agent_id.to_string(), printer_name.clone()))
+                })
+        })?;
+        let job = jobs.remove(position)?;
+        state
+            .in_flight
+            .insert(job.id, (agent_id.to_string(), Instant::now(), job.clone()));
+        Some(job)
+    }
+
+    /// จำนวนงานที่รอ agent มาดึง
+    pub(crate) fn pending_count(&self, agent_id: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        state.pending.get(agent_id).map_or(0, VecDeque::len)
+    }
+
+    /// ลบงานที่ agent รายงานผลแล้ว คืนค่า `false` หากไม่พบงานของ agent นี้
+    pub(crate) fn complete(&self, agent_id: &str, job_id: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.in_flight.get(&job_id) {
+            Some((owner, _, _)) if owner == agent_id => {
+                state.in_flight.remove(&job_id);
+                remove_spooled_job(job_id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// ลบงานที่รอ agent มาดึงทั้งหมด (หรือเฉพาะของ `agent_id`) คืนค่าจำนวนงานที่ลบ
+    ///
+    /// งานที่ agent ดึงไปแล้วจะไม่ถูกลบ เพราะอาจกำลังพิมพ์อยู่ที่สาขา
+    pub(crate) fn drain(&self, agent_id: Option<&str>) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let removed: Vec<RemoteJob> = match agent_id {
+            Some(agent_id) => state
+                .pending
+                .remove(agent_id)
+                .into_iter()
+                .flatten()
+                .collect(),
+            None => state.pending.drain().flat_map(|(_, jobs)| jobs).collect(),
+        };
+        for job in &removed {
+            state.held.remove(&job.id);
+            remove_spooled_job(job.id);
+        }
+        removed.len()
+    }
+
+    /// ยกเลิกงานที่ยังรอ agent มาดึง คืนค่า `false` หากไม่พบ (หรือ agent ดึงไปแล้ว)
+    pub(crate) fn cancel(&self, job_id: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let removed = state.pending.values_mut().any(|jobs| {
+            jobs.iter()
+                .position(|job| job.id == job_id)
+                .and_then(|position| jobs.remove(position))
+                .is_some()
+        });
+        if removed {
+            state.held.remove(&job_id);
+            remove_spooled_job(job_id);
+        }
+        removed
+    }
+
+    /// หยุดหรือให้ agent ดึงงานของเครื่องพิมพ์ `printer_name` ต่อ คืนค่าจำนวนงานที่รออยู่ของเครื่องพิมพ์นั้น
+    pub(crate) fn set_paused(&self, agent_id: &str, printer_name: &str, paused: bool) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let key = (agent_id.to_string(), printer_name.to_string());
+        if paused {
+            state.paused.insert(key);
+        } else {
+            state.paused.remove(&key);
+        }
+        state.pending.get(agent_id).map_or(0, |jobs| {
+            jobs.iter()
+                .filter(|job| job.printer_name.as_deref() == Some(printer_name))
+                .count()
+        })
+    }
+
+    /// ลบงานที่รอ agent มาดึงของเครื่องพิมพ์ `printer_name` (เฉพาะงานใน `job_ids` เมื่อระบุ) คืนค่า job id ที่ลบ
+    pub(crate) fn purge_printer(
+        &self,
+        agent_id: &str,
+        printer_name: &str,
+        job_ids: Option<&[u64]>,
+    ) -> Vec<u64> {
+        let mut state = self.state.lock().unwrap();
+        let Some(jobs) = state.pending.get_mut(agent_id) else {
+            return Vec::new();
+        };
+        let mut purged = Vec::new();
+        jobs.retain(|job| {
+            let matches = job.printer_name.as_deref() == Some(printer_name)
+                && job_ids.is_none_or(|job_ids| job_ids.contains(&job.id));
+            if matches {
+                purged.push(job.id);
+            }
+            !matches
+        });
+        for id in &purged {
+            state.held.remove(id);
+            remove_spooled_job(*id);
+        }
+        purged
+    }
+
+    /// จำนวนงานที่รอ agent มาดึงของทุก agent
+    pub(crate) fn pending_counts(&self) -> BTreeMap<String, usize> {
+        let state = self.state.lock().unwrap();
+        state
+            .pending
+            .iter()
+            .map(|(agent_id, jobs)| (agent_id.clone(), jobs.len()))
+            .collect()
+    }
+
+    /// จำนวนงานที่ agent ดึงไปแล้วแต่ยังไม่รายงานผล
+    pub(crate) fn in_flight_count(&self) -> usize {
+        self.state.lock().unwrap().in_flight.len()
+    }
+}
+
+/// ข้อมูลที่ agent ส่งมาเมื่อลงทะเบียน
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct AgentRegistration {
+    /// เครื่องพิมพ์ที่ติดตั้งที่สาขา
+    #[schema(example = json!(["Branch_LabelPrinter", "Branch_LaserJet"]))]
+    pub(crate) printers: Vec<String>,
+    /// เวอร์ชันของโปรแกรม agent
+    #[schema(example = "0.1.0")]
+    version: Option<String>,
+}
+
+/// สถานะของสาขาสำหรับแสดงผล
+#[derive(Serialize, ToSchema)]
+pub(crate) struct AgentSite {
+    #[schema(example = "branch-01")]
+    pub(crate) agent_id: String,
+    online: bool,
+    /// เครื่องพิมพ์ที่ agent ลงทะเบียนไว้
+    printers: Vec<String>,
+    version: Option<String>,
+    /// เวลาที่ติดต่อมาครั้งล่าสุด (RFC 3339) หรือ `null` หากยังไม่เคยติดต่อ
+    #[schema(example = "2024-05-01T09:30:00+07:00")]
+    last_seen: Option<String>,
+    /// จำนวนงานที่รอ agent มาดึง
+    pending_jobs: usize,
+}
+
+pub(crate) struct AgentPresence {
+    printers: Vec<String>,
+    version: Option<String>,
+    last_seen: Instant,
+    last_seen_at: chrono::DateTime<chrono::Local>,
+    online: bool,
+}
+
+/// สถานะการเชื่อมต่อของ agent แต่ละสาขา (เก็บในหน่วยความจำของ central server)
+#[derive(Default)]
+pub(crate) struct AgentRegistry {
+    agents: Mutex<HashMap<String, AgentPresence>>,
+}
+
+impl AgentRegistry {
+    /// บันทึกว่า agent ติดต่อเข้ามา (ลงทะเบียน, heartbeat, ดึงงาน หรือรายงานผล)
+    pub(crate) fn touch(&self, agent_id: &str) {
+        let mut agents = self.agents.lock().unwrap();
+        let presence = agents
+            .entry(agent_id.to_string())
+            .or_insert_with(|| AgentPresence {
+                printers: vec![],
+                version: None,
+                last_seen: Instant::now(),
+                last_seen_at: chrono::Local::now(),
+                online: false,
+            });
+        if !presence.online {
+            println!("Agent {} is online", agent_id);
+        }
+        presence.last_seen = Instant::now();
+        presence.last_seen_at = chrono::Local::now();
+        presence.online = true;
+    }
+
+    pub(crate) fn register(&self, agent_id: &str, registration: AgentRegistration) {
+        self.touch(agent_id);
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(presence) = agents.get_mut(agent_id) {
+            presence.printers = registration.printers;
+            presence.version = registration.version;
+        }
+    }
+
+    /// ตั้งสถานะ offline ให้ agent ที่ไม่ได้ติดต่อมานานเกิน `AGENT_OFFLINE_AFTER`
+    /// คืนค่า agent id ที่เพิ่งเปลี่ยนเป็น offline
+    fn mark_offline(&self) -> Vec<String> {
+        let mut agents = self.agents.lock().unwrap();
+        agents
+            .iter_mut()
+            .filter(|(_, presence)| {
+                presence.online && presence.last_seen.elapsed() > AGENT_OFFLINE_AFTER
+            })
+            .map(|(agent_id, presence)| {
+                presence.online = false;
+                agent_id.clone()
+            })
+            .collect()
+    }
+
+    /// agent ที่ offline อยู่ พร้อมเวลาที่ติดต่อมาครั้งล่าสุด
+    pub(crate) fn offline_since(&self) -> Vec<(String, Instant)> {
+        let agents = self.agents.lock().unwrap();
+        agents
+            .iter()
+            .filter(|(_, presence)| !presence.online)
+            .map(|(agent_id, presence)| (agent_id.clone(), presence.last_seen))
+            .collect()
+    }
+
+    pub(crate) fn is_online(&self, agent_id: &str) -> bool {
+        let agents = self.agents.lock().unwrap();
+        agents.get(agent_id).is_some_and(|presence| presence.online)
+    }
+
+    /// เครื่องพิมพ์ที่ agent ลงทะเบียนไว้ (ว่าง = ยังไม่ได้ลงทะเบียน)
+    pub(crate) fn printers(&self, agent_id: &str) -> Vec<String> {
+        let agents = self.agents.lock().unwrap();
+        agents
+            .get(agent_id)
+            .map(|presence| presence.printers.clone())
+            .unwrap_or_default()
+    }
+
+    /// สถานะของทุก agent ที่ตั้งไว้ใน config เรียงตาม agent id
+    pub(crate) fn sites(&self, config: &AppConfig, queue: &RemoteJobQueue) -> Vec<AgentSite> {
+        let agents = self.agents.lock().unwrap();
+        let mut agent_ids: Vec<&String> = config.agents.keys().collect();
+        agent_ids.sort();
+        agent_ids
+            .into_iter()
+            .map(|agent_id| {
+                let presence = agents.get(agent_id);
+                AgentSite {
+                    agent_id: agent_id.clone(),
+                    online: presence.is_some_and(|presence| presence.online),
+                    printers: presence
+                        .map(|presence| presence.printers.clone())
+                        .unwrap_or_default(),
+                    version: presence.and_then(|presence| presence.version.clone()),
+                    last_seen: presence.map(|presence| presence.last_seen_at.to_rfc3339()),
+                    pending_jobs: queue.pending_count(agent_id),
+                }
+            })
+            .collect()
+    }
+}
+
+/// ตรวจสอบสถานะ agent ทุก 10 วินาทีและบันทึก log เมื่อสาขาขาดการติดต่อ
+/// พร้อมยกเลิกงานที่เลยกำหนด `expires_at` (ทำงานใน thread แยก)
+pub(crate) fn monitor_agents(
+    config: web::Data<SharedConfig>,
+    registry: web::Data<AgentRegistry>,
+    queue: web::Data<RemoteJobQueue>,
+    history: web::Data<JobHistory>,
+) {
+    loop {
+        std::thread::sleep(Duration::from_secs(10));
+        for agent_id in registry.mark_offline() {
+            eprintln!(
+                "Agent {} is offline, holding {} queued jobs until it reconnects",
+                agent_id,
+                queue.pending_count(&agent_id)
+            );
+        }
+        discard_expired_jobs(&config.get(), &queue, &history);
+    }
+}
+
+/// ยกเลิกงานที่ยังไม่ได้พิมพ์เมื่อถึง `expires_at` ทั้งงานในคิวของ agent และงานที่ค้างอยู่ใน spooler
+/// (เช่น เครื่องพิมพ์ offline) เพราะงานอย่างบัตรขึ้นเครื่องหรือใบสั่งอาหารต้องไม่ถูกพิมพ์ออกมาช้า
+pub(crate) fn discard_expired_jobs(
+    config: &AppConfig,
+    queue: &RemoteJobQueue,
+    history: &JobHistory,
+) {
+    let mut expired = Vec::new();
+    for (agent_id, job_id) in queue.expire() {
+        let message = format!(
+            "Discarded job {} for agent {}: not printed before its deadline",
+            job_id, agent_id
+        );
+        expired.extend(history.mark_status(
+            JobStatus::Queued,
+            JobStatus::Expired,
+            Some(&agent_id),
+            &[job_id],
+            &message,
+        ));
+    }
+    for (printer_name, job_id) in history.take_overdue() {
+        let Some(printer) = print_backend().printer(&printer_name) else {
+            continue;
+        };
+        if !print_backend()
+            .active_jobs(&printer)
+            .iter()
+            .any(|job| job.id == job_id)
+        {
+            continue;
+        }
+        if let Err(e) = print_backend().cancel(&printer, job_id) {
+            eprintln!("Error cancelling expired spooler job {}: {:?}", job_id, e);
+            continue;
+        }
+        let message = format!(
+            "Cancelled job {} on printer {}: not printed before its deadline",
+            job_id, printer_name
+        );
+        expired.extend(history.mark_status(
+            JobStatus::Printed,
+            JobStatus::Expired,
+            None,
+            &[job_id],
+            &message,
+        ));
+    }
+    for record in &expired {
+        println!("{}", record.message);
+        spawn_post_complete_hook(&config.hooks, record);
+        notify_job_expired(&config.notifications, record);
+    }
+}
+
+/// ตรวจสอบ `X-Agent-Id` และ `Authorization: Bearer <token>` กับ `agents` ใน config คืนค่า agent id
+pub(crate) fn authenticate_agent(request: &HttpRequest, config: &AppConfig) -> Option<String> {
+    let agent_id = request.headers().get("X-Agent-Id")?.to_str().ok()?;
+    let token = request
+        .headers()
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    let expected = config.agents.get(agent_id)?;
+    tokens_match(expected, token).then(|| agent_id.to_string())
+}
+
+/// เทียบ token ครบทุกไบต์ เพื่อไม่ให้เวลาที่ใช้บอกใบ้ตำแหน่งที่ token ผิด
+pub(crate) fn tokens_match(expected: &str, token: &str) -> bool {
+    expected.len() == token.len()
+        && expected
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// ตรวจสิทธิ์ผู้ดูแลระบบ: ใช้ `Authorization: Bearer <admin_token>` เมื่อตั้งค่า `admin_token`
+/// หากไม่ได้ตั้งค่าไว้จะอนุญาตเฉพาะ request จากเครื่องนี้ (loopback)
+pub(crate) fn authenticate_admin(request: &HttpRequest, config: &AppConfig) -> bool {
+    match &config.admin_token {
+        Some(expected) => request
+            .headers()
+            .get("Authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|token| tokens_match(expected, token)),
+        // request ที่ส่งต่อมาจาก reverse proxy ในเครื่องเดียวกันไม่ถือว่ามาจากเครื่องนี้
+        None => client_address(request, config).is_some_and(|(ip, _)| ip.is_loopback()),
+    }
+}
+
+/// URL ของ central server แยกเป็น host, port และ path prefix
+pub(crate) struct ServerUrl {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) prefix: String,
+}
+
+pub(crate) fn parse_server_url(url: &str) -> Result<ServerUrl> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        bail!(
+            "Unsupported server_url {} (only http:// is supported, use a TLS tunnel on this machine for encryption)",
+            url
+        );
+    };
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .with_context(|| format!("Invalid port in server_url {}", url))?,
+        ),
+        None => (authority, 80),
+    };
+    let path = path.trim_matches('/');
+    Ok(ServerUrl {
+        host: host.to_string(),
+        port,
+        prefix: if path.is_empty() {
+            String::new()
+        } else {
+            format!("/{}", path)
+        },
+    })
+}
+
+/// URL ของ central server ของ agent ซึ่งส่ง token และไฟล์งานทุก request โดยไม่เข้ารหัส จึงต้องเป็น
+/// เครื่องนี้ (เช่น stunnel ที่เข้ารหัสต่อไปยัง server) จนกว่าจะรองรับ `https://`
+pub(crate) fn parse_agent_server_url(url: &str) -> Result<ServerUrl> {
+    let server = parse_server_url(url)?;
+    let host = server.host.trim_start_matches('[').trim_end_matches(']');
+    let is_loopback = host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback());
+    if !is_loopback {
+        bail!(
+            "server_url {} must point to this machine (http:// sends the agent token unencrypted, run a TLS tunnel such as stunnel to the central server and use its local port)",
+            url
+        );
+    }
+    Ok(server)
+}
+
+/// ถอดรหัส body แบบ `Transfer-Encoding: chunked`
+pub(crate) fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    loop {
+        let line_end = data
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .context("Invalid chunked body")?;
+        let size_line = String::from_utf8_lossy(&data[..line_end]);
+        // ตัดส่วนขยายหลัง `;` ออก และไม่รับเครื่องหมาย `+` ที่ `from_str_radix` ยอมให้มี
+        let size = size_line.split(';').next().unwrap_or("").trim();
+        if size.is_empty() || !size.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("Invalid chunk size");
+        }
+        let size = usize::from_str_radix(size, 16).context("Invalid chunk size")?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(output);
+        }
+        let chunk = data.get(..size).context("Truncated chunked body")?;
+        if data.get(size..size + 2) != Some(b"\r\n".as_slice()) {
+            bail!("Truncated chunked body");
+        }
+        output.extend_from_slice(chunk);
+        data = &data[size + 2..];
+    }
+}
+
+/// เวลาสูงสุดที่รอเชื่อมต่อกับ server
+pub(crate) const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// เวลาสูงสุดที่รอส่งหรือรับข้อมูลแต่ละครั้ง
+pub(crate) const HTTP_IO_TIMEOUT: Duration = Duration::from_secs(60);
+/// เวลาสูงสุดของการรับ response ทั้งหมด (server ที่ส่งข้อมูลมาทีละน้อยจะไม่ทำให้ค้างนานกว่านี้)
+pub(crate) const HTTP_RESPONSE_TIMEOUT: Duration = Duration::from_secs(300);
+/// ขนาดสูงสุดของ response รวม header (งานของ agent คือไฟล์ที่แปลงแล้วแบบ base64 ใน JSON)
+pub(crate) const MAX_HTTP_RESPONSE_BYTES: usize = 256 * 1024 * 1024;
+
+/// อ่าน response จาก `reader` จนปิดการเชื่อมต่อ โดยไม่เกิน `max_bytes` และไม่เกิน `deadline`
+pub(crate) fn read_http_response(
+    reader: &mut impl Read,
+    max_bytes: usize,
+    deadline: Instant,
+) -> Result<Vec<u8>> {
+    let mut response = Vec::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        if Instant::now() >= deadline {
+            bail!(
+                "Response from server took longer than {} seconds",
+                HTTP_RESPONSE_TIMEOUT.as_secs()
+            );
+        }
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(response);
+        }
+        if response.len() + read > max_bytes {
+            bail!("Response from server is larger than {} bytes", max_bytes);
+        }
+        response.extend_from_slice(&buffer[..read]);
+    }
+}
+
+/// ส่ง HTTP request ไปยัง server พร้อม header เพิ่มเติม แล้วคืนค่า status code และ body
+pub(crate) fn http_request(
+    server: &ServerUrl,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<(u16, Vec<u8>)> {
+    let connect = || -> std::io::Result<TcpStream> {
+        let mut last_error = None;
+        for address in (server.host.as_str(), server.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, HTTP_CONNECT_TIMEOUT) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| std::io::ErrorKind::NotFound.into()))
+    };
+    let mut stream = connect()
+        .with_context(|| format!("Failed to connect to {}:{}", server.host, server.port))?;
+    stream.set_read_timeout(Some(HTTP_IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_IO_TIMEOUT))?;
+
+    let mut head = format!(
+        "{} {}{} HTTP/1.1\r\nHost: {}:{}\r\n",
+        method, server.prefix, path, server.host, server.port
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+
+    let response = read_http_response(
+        &mut stream,
+        MAX_HTTP_RESPONSE_BYTES,
+        Instant::now() + HTTP_RESPONSE_TIMEOUT,
+    )?;
+    let header_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .context("Invalid HTTP response from server")?;
+    let headers = String::from_utf8_lossy(&response[..header_end]).to_ascii_lowercase();
+    let status = headers
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .context("Invalid HTTP status line from server")?;
+    let body = &response[header_end + 4..];
+    let chunked = headers
+        .lines()
+        .any(|line| line.starts_with("transfer-encoding:") && line.contains("chunked"));
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        body.to_vec()
+    };
+    Ok((status, body))
+}
+
+/// ส่ง HTTP request ไปยัง central server ในนามของ agent แล้วคืนค่า status code และ body
+pub(crate) fn agent_request(
+    server: &ServerUrl,
+    agent: &AgentConfig,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> Result<(u16, Vec<u8>)> {
+    let authorization = format!("Bearer {}", agent.token);
+    http_request(
+        server,
+        method,
+        path,
+        &[
+            ("Authorization", authorization.as_str()),
+            ("X-Agent-Id", agent.agent_id.as_str()),
+            ("Content-Type", "application/json"),
+        ],
+        body,
+    )
+}
+
+/// ลงทะเบียน agent พร้อมรายชื่อเครื่องพิมพ์ของสาขากับ central server
+pub(crate) fn register_agent(server: &ServerUrl, agent: &AgentConfig) -> Result<()> {
+    let registration = AgentRegistration {
+        printers: print_backend()
+            .printers()
+            .into_iter()
+            .map(|printer| printer.name)
+            .collect(),
+        version: Some(env!("CARGO_PKG_VERSION").to_string()),
+    };
+    let body = serde_json::to_vec(&registration)?;
+    let (status, response) = agent_request(server, agent, "POST", "/api/v1/agent/register", &body)?;
+    if status != 200 {
+        bail!(
+            "Failed to register agent: HTTP {} {}",
+            status,
+            String::from_utf8_lossy(&response)
+        );
+    }
+    println!(
+        "Registered agent {} with {} printers",
+        agent.agent_id,
+        registration.printers.len()
+    );
+    Ok(())
+}
+
+pub(crate) fn send_heartbeat(server: &ServerUrl, agent: &AgentConfig) -> Result<()> {
+    let (status, response) = agent_request(server, agent, "POST", "/api/v1/agent/heartbeat", &[])?;
+    if status != 200 {
+        bail!(
+            "Heartbeat failed: HTTP {} {}",
+            status,
+            String::from_utf8_lossy(&response)
+        );
+    }
+    Ok(())
+}
+
+/// ดึงงานพิมพ์หนึ่งงานจาก central server มาพิมพ์ คืนค่า `true` หากได้รับงาน
+pub(crate) fn poll_agent_job(
+    server: &ServerUrl,
+    agent: &AgentConfig,
+    config: &AppConfig,
+    dispatcher: &PrintDispatcher,
+) -> Result<bool> {
+    let (status, body) = agent_request(server, agent, "GET", "/api/v1/agent/jobs/next", &[])?;
+    match status {
+        204 => return Ok(false),
+        200 => {}
+        _ => bail!(
+            "Server returned HTTP {}: {}",
+            status,
+            String::from_utf8_lossy(&body)
+        ),
+    }
+    let job: RemoteJob = serde_json::from_slice(&body).context("Invalid job from server")?;
+    println!("Received job {} ({}) from server", job.id, job.job_name);
+
+    let result = base64::engine::general_purpose::STANDARD
+        .decode(&job.data)
+        .map_err(|e| format!("Invalid job data: {}", e))
+        .and_then(|file_data| {
+            let printer = resolve_printer_as(
+                job.printer_name.as_deref(),
+                job.settings.windows_user.as_deref(),
+                config,
+                &dispatcher.sessions,
+            )?;
+            dispatcher
+                .reserve(&printer)
+                .submit(|| {
+                    dispatch_print_job(
+                        &printer,
+                        file_data,
+                        &job.job_name,
+                        &job.settings,
+                        config,
+                        &dispatcher.sessions,
+                    )
+                })
+                .map_err(|e| format!("Failed to send print job: {:?}", e))?;
+            Ok(printer.name)
+        });
+    let report = match result {
+        Ok(printer_name) => {
+            println!("Job {} sent successfully to {}", job.id, printer_name);
+            AgentJobResult {
+                success: true,
+                message: format!("Sent to printer {}", printer_name),
+            }
+        }
+        Err(message) => {
+            eprintln!("Job {} failed: {}", job.id, message);
+            AgentJobResult {
+                success: false,
+                message,
+            }
+        }
+    };
+
+    let body = serde_json::to_vec(&report)?;
+    let path = format!("/api/v1/agent/jobs/{}/result", job.id);
+    let (status, response) = agent_request(server, agent, "POST", &path, &body)?;
+    if status != 200 {
+        bail!(
+            "Failed to report result of job {}: HTTP {} {}",
+            job.id,
+            status,
+            String::from_utf8_lossy(&response)
+        );
+    }
+    Ok(true)
+}
+
+/// วนดึงงานจาก central server ตลอดอายุของ Service (ทำงานใน thread แยก)
+pub(crate) fn run_agent(config: web::Data<SharedConfig>, dispatcher: web::Data<PrintDispatcher>) {
+    let Some(agent) = config.get().agent.clone() else {
+        return;
+    };
+    let server = match parse_agent_server_url(&agent.server_url) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Print agent disabled: {:?}", e);
+            return;
+        }
+    };
+    println!(
+        "Print agent {} polling {} every {}s",
+        agent.agent_id, agent.server_url, agent.poll_interval_secs
+    );
+
+    // ลงทะเบียนใหม่ทุกครั้งหลังเชื่อมต่อไม่สำเร็จ เผื่อ server ถูก restart และลืมรายชื่อเครื่องพิมพ์
+    let mut registered = false;
+    let mut last_heartbeat = Instant::now();
+    let heartbeat_interval = Duration::from_secs(agent.heartbeat_interval_secs.max(1));
+    let mut waiting_for_spooler = false;
+    loop {
+        let result = if !registered {
+            register_agent(&server, &agent).map(|_| {
+                registered = true;
+                last_heartbeat = Instant::now();
+            })
+        } else if last_heartbeat.elapsed() >= heartbeat_interval {
+            send_heartbeat(&server, &agent).map(|_| last_heartbeat = Instant::now())
+        } else {
+            Ok(())
+        };
+
+        // ไม่ดึงงานระหว่างที่ spooler หยุดทำงาน งานจึงรอในคิวของ server แทนที่จะล้มเหลวที่นี่
+        let spooler_outage = dispatcher.spooler.outage();
+        match (&spooler_outage, waiting_for_spooler) {
+            (Some(reason), false) => println!(
+                "Print agent paused while the print spooler is down: {}",
+                reason
+            ),
+            (None, true) => println!("Print agent resumed fetching jobs from the server"),
+            _ => {}
+        }
+        waiting_for_spooler = spooler_outage.is_some();
+        let result = result.and_then(|_| {
+            if waiting_for_spooler {
+                Ok(false)
+            } else {
+                poll_agent_job(&server, &agent, &config.get(), &dispatcher)
+            }
+        });
+        match result {
+            // ได้รับงานแล้ว อาจมีงานถัดไปรออยู่ จึงดึงต่อทันที
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Print agent error: {:?}", e);
+                registered = false;
+            }
+        }
+        std::thread::sleep(Duration::from_secs(agent.poll_interval_secs.max(1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunked_bodies_are_decoded() {
+        assert_eq!(
+            decode_chunked(b"5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n").unwrap(),
+            b"hello, world"
+        );
+        // ตัวพิมพ์ใหญ่ และ trailer หลัง chunk สุดท้าย
+        assert_eq!(
+            decode_chunked(b"A\r\n0123456789\r\n0\r\nX-Trailer: 1\r\n\r\n").unwrap(),
+            b"0123456789"
+        );
+        assert_eq!(decode_chunked(b"0\r\n\r\n").unwrap(), b"");
+    }
+
+    #[test]
+    fn truncated_chunked_bodies_are_rejected() {
+        for (body, message) in [
+            (b"".as_slice(), "Invalid chunked body"),
+            (b"5\r\nhello\r\n", "Invalid chunked body"),
+            (b"5", "Invalid chunked body"),
+            (b"5\r\nhel", "Truncated chunked body"),
+            (b"5\r\nhello", "Truncated chunked body"),
+            (b"5\r\nhelloXX0\r\n\r\n", "Truncated chunked body"),
+            (b"zz\r\nhello\r\n0\r\n\r\n", "Invalid chunk size"),
+            (b"+5\r\nhello\r\n0\r\n\r\n", "Invalid chunk size"),
+            (b"\r\nhello\r\n0\r\n\r\n", "Invalid chunk size"),
+            // ขนาดที่เกินข้อมูลที่มี หรือเกินขนาดของ usize
+            (b"ffffffffffffffff\r\nhello\r\n", "Truncated chunked body"),
+            (b"10000000000000000\r\nhello\r\n", "Invalid chunk size"),
+        ] {
+            let error = decode_chunked(body).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                message,
+                "{:?}",
+                String::from_utf8_lossy(body)
+            );
+        }
+    }
+
+    #[test]
+    fn agent_server_urls_must_be_local() {
+        for url in [
+            "http://127.0.0.1:8443",
+            "http://localhost:8443/print",
+            "http://127.0.0.2:8443",
+            "http://[::1]:8443",
+        ] {
+            assert!(parse_agent_server_url(url).is_ok(), "{}", url);
+        }
+        for url in [
+            "http://print.example.com:8080",
+            "http://10.0.0.5:8080",
+            "https://print.example.com",
+            "ftp://127.0.0.1",
+        ] {
+            assert!(parse_agent_server_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn http_responses_are_capped() {
+        let later = Instant::now() + Duration::from_secs(60);
+        let response = b"HTTP/1.1 200 OK\r\n\r\nhello".to_vec();
+        assert_eq!(
+            read_http_response(&mut response.as_slice(), response.len(), later).unwrap(),
+            response
+        );
+        let error =
+            read_http_response(&mut response.as_slice(), response.len() - 1, later).unwrap_err();
+        assert!(error.to_string().contains("larger than"), "{}", error);
+        let error = read_http_response(&mut response.as_slice(), response.len(), Instant::now())
+            .unwrap_err();
+        assert!(error.to_string().contains("took longer than"), "{}", error);
+    }
+}
@@ -0,0 +1,2 @@
+// API แยกตามเวอร์ชัน เพื่อให้การเปลี่ยนแปลงที่ไม่เข้ากันในเวอร์ชันถัดไปไม่กระทบ client เดิม
+pub mod v1;
@@ -0,0 +1,78 @@
+// ----------------------------------------------------------------------
+//                         API v1 ACCESS CONTROL
+// ----------------------------------------------------------------------
+
+use super::errors::{ApiError, ErrorCode};
+use crate::{
+    agent::{authenticate_admin, authenticate_agent},
+    config::AppConfig,
+    tenants::{authenticate_tenant, Tenant},
+};
+use actix_web::{http::StatusCode, HttpRequest};
+
+/// ตรวจสอบ agent id และ token ของ request จาก agent
+pub(super) fn require_agent(request: &HttpRequest, config: &AppConfig) -> Result<String, ApiError> {
+    authenticate_agent(request, config).ok_or_else(|| ApiError {
+        status: StatusCode::UNAUTHORIZED,
+        code: ErrorCode::Unauthorized,
+        message: "Invalid agent id or token".to_string(),
+        retry_after: None,
+    })
+}
+
+/// ตรวจสอบสิทธิ์ผู้ดูแลระบบ (`admin_token` หรือเรียกจากเครื่องเดียวกันเมื่อไม่ได้ตั้งค่า token)
+pub(super) fn require_admin(request: &HttpRequest, config: &AppConfig) -> Result<(), ApiError> {
+    if authenticate_admin(request, config) {
+        Ok(())
+    } else {
+        Err(ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            code: ErrorCode::Unauthorized,
+            message: "Invalid admin token".to_string(),
+            retry_after: None,
+        })
+    }
+}
+
+/// tenant ของ request จาก API key (`None` เมื่อไม่ได้ตั้งค่า `tenants` ซึ่งไม่ต้องใช้ API key)
+pub(super) fn require_tenant<'a>(
+    request: &HttpRequest,
+    config: &'a AppConfig,
+) -> Result<Option<Tenant<'a>>, ApiError> {
+    if config.tenants.is_empty() {
+        return Ok(None);
+    }
+    authenticate_tenant(request, config)
+        .map(Some)
+        .ok_or_else(|| ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            code: ErrorCode::Unauthorized,
+            message: "Missing or invalid API key".to_string(),
+            retry_after: None,
+        })
+}
+
+/// ตรวจว่า tenant ใช้เครื่องพิมพ์ `name` (และ agent `agent_id`) ได้
+/// เครื่องที่ใช้ไม่ได้จะตอบเหมือนไม่พบ เพื่อไม่ให้รู้ว่ามีเครื่องนั้นอยู่
+pub(super) fn check_printer_access(
+    tenant: Option<Tenant>,
+    name: &str,
+    agent_id: Option<&str>,
+) -> Result<(), ApiError> {
+    let Some(tenant) = tenant else {
+        return Ok(());
+    };
+    if let Some(agent_id) = agent_id.filter(|agent_id| !tenant.allows_agent(agent_id)) {
+        return Err(ApiError::bad_request(
+            ErrorCode::UnknownAgent,
+            format!("Unknown agent: {}", agent_id),
+        ));
+    }
+    if !tenant.allows_printer(name) {
+        return Err(ApiError::not_found(
+            ErrorCode::PrinterNotFound,
+            format!("Printer not found: {}", name),
+        ));
+    }
+    Ok(())
+}
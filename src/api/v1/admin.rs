@@ -0,0 +1,460 @@
+// ----------------------------------------------------------------------
+//                              API v1 ADMIN
+// ----------------------------------------------------------------------
+
+use super::{
+    access::require_admin,
+    errors::{ApiError, ErrorCode, ResponseMessage},
+};
+use crate::{
+    agent::RemoteJobQueue,
+    backend::{print_backend, PrintBackendListing},
+    config::{reload_config, SharedConfig, CONFIG_PATH},
+    dispatch::PrintDispatcher,
+    history::{JobHistory, JobRecord, JobStatus},
+    hooks::spawn_post_complete_hook,
+    sessions::resolve_printer_as,
+    transform_cache::{TransformCache, MAX_TRANSFORM_CACHE_TTL_MINUTES},
+};
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
+
+/// เงื่อนไขของรายการที่ลบจาก cache ผลการแปลงไฟล์ (ระบุหลายค่า = ต้องตรงทุกค่า)
+#[derive(Deserialize, IntoParams)]
+pub(super) struct CacheInvalidateQuery {
+    /// key ของรายการ
+    key: Option<String>,
+    /// ชื่อไฟล์ของงานล่าสุดที่ใช้รายการ
+    filename: Option<String>,
+    /// SHA-256 ของไฟล์ต้นฉบับ (ลบผลการแปลงของไฟล์นี้ทุก option)
+    sha256: Option<String>,
+}
+
+/// อายุใหม่ของผลการแปลงใน cache
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"ttl_minutes": 1440}))]
+pub(super) struct CacheTtlRequest {
+    /// อายุนับจากเวลาที่เก็บ (นาที)
+    ttl_minutes: u64,
+}
+
+/// สถานะของเครื่องพิมพ์ที่ติดตั้งในเครื่องนี้
+#[derive(Serialize, ToSchema)]
+pub(super) struct PrinterStatus {
+    name: String,
+    /// ready, offline, paused, printing หรือ unknown
+    #[schema(example = "ready")]
+    state: String,
+    is_default: bool,
+    /// จำนวนงานที่ยังค้างอยู่ใน spooler
+    active_jobs: usize,
+    /// จำนวนงานที่รับแล้วแต่ยังไม่ถึง spooler
+    queued_jobs: usize,
+}
+
+/// ข้อมูลสรุปสำหรับหน้า /admin
+#[derive(Serialize, ToSchema)]
+pub(super) struct AdminOverview {
+    /// จำนวนงานที่รอในคิวของแต่ละ agent
+    pending_jobs: BTreeMap<String, usize>,
+    /// จำนวนงานที่ agent ดึงไปแล้วแต่ยังไม่รายงานผล
+    jobs_in_flight: usize,
+    printers: Vec<PrinterStatus>,
+    /// งานล่าสุดเรียงจากใหม่ไปเก่า
+    recent_jobs: Vec<JobRecord>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/overview",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "คิวงาน สถานะเครื่องพิมพ์ และงานล่าสุด", body = AdminOverview),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage)
+    )
+)]
+#[get("/admin/overview")]
+pub(super) async fn admin_overview_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    history: web::Data<JobHistory>,
+    dispatcher: web::Data<PrintDispatcher>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&request, &config.get())?;
+
+    let printers = web::block(move || {
+        print_backend()
+            .printers()
+            .into_iter()
+            .map(|printer| {
+                let backlog = dispatcher.backlog(&printer);
+                PrinterStatus {
+                    state: format!("{:?}", printer.state).to_lowercase(),
+                    is_default: printer.is_default,
+                    active_jobs: backlog.spooler_jobs,
+                    queued_jobs: backlog.waiting,
+                    name: printer.name,
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(AdminOverview {
+        pending_jobs: queue.pending_counts(),
+        jobs_in_flight: queue.in_flight_count(),
+        printers,
+        recent_jobs: history.recent(),
+    }))
+}
+
+/// ผลการอ่าน config.json ใหม่
+#[derive(Serialize, ToSchema)]
+pub(super) struct ReloadResponse {
+    status: String,
+    message: String,
+    /// การตั้งค่าที่เปลี่ยนแล้วแต่จะมีผลหลัง restart Service (`agent`, `admin_port`, `server`)
+    restart_required: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/reload",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "อ่าน config.json ใหม่แล้ว งานในคิวยังอยู่ครบ", body = ReloadResponse),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage),
+        (status = 500, description = "config.json ไม่ถูกต้อง ยังใช้การตั้งค่าเดิม", body = ResponseMessage)
+    )
+)]
+#[post("/admin/reload")]
+pub(super) async fn admin_reload_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&request, &config.get())?;
+    let (_, restart_required) = reload_config(&config, "HTTP API").map_err(|e| {
+        ApiError::internal(
+            ErrorCode::ConfigError,
+            format!("Failed to reload {}: {}", CONFIG_PATH, e),
+        )
+    })?;
+    Ok(HttpResponse::Ok().json(ReloadResponse {
+        status: "success".to_string(),
+        message: format!("Reloaded {}", CONFIG_PATH),
+        restart_required: restart_required.into_iter().map(String::from).collect(),
+    }))
+}
+
+pub(super) fn job_not_found(id: u64) -> ApiError {
+    ApiError::not_found(ErrorCode::JobNotFound, format!("Job not found: {}", id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs/{id}/cancel",
+    tag = "Admin",
+    params(("id" = u64, Path, description = "หมายเลขงานในประวัติ")),
+    responses(
+        (status = 200, description = "ยกเลิกงานแล้ว", body = ResponseMessage),
+        (status = 400, description = "งานนี้ยกเลิกไม่ได้ (เช่น ส่งไม่สำเร็จ หรือ agent ดึงไปพิมพ์แล้ว)", body = ResponseMessage),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage),
+        (status = 404, description = "ไม่พบงาน", body = ResponseMessage),
+        (status = 500, description = "spooler ไม่ยอมยกเลิกงาน", body = ResponseMessage)
+    )
+)]
+#[post("/admin/jobs/{id}/cancel")]
+pub(super) async fn admin_cancel_job_handler(
+    request: HttpRequest,
+    id: web::Path<u64>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    history: web::Data<JobHistory>,
+    dispatcher: web::Data<PrintDispatcher>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    require_admin(&request, &config)?;
+    let id = id.into_inner();
+    let record = history.get(id).ok_or_else(|| job_not_found(id))?;
+
+    let message = match (record.status, record.job_id) {
+        (JobStatus::Queued, Some(job_id)) => {
+            if !queue.cancel(job_id) {
+                return Err(ApiError::bad_request(
+                    ErrorCode::InvalidRequest,
+                    format!("Job {} was already fetched by its agent", id),
+                ));
+            }
+            format!("Removed job {} from the queue", id)
+        }
+        (JobStatus::Printed, Some(job_id)) => {
+            let windows_user = record.settings.windows_user.as_deref();
+            let printer = resolve_printer_as(
+                record.printer_name.as_deref(),
+                windows_user,
+                &config,
+                &dispatcher.sessions,
+            )
+            .map_err(|e| ApiError::not_found(ErrorCode::PrinterNotFound, e))?;
+            // งานที่ส่งในนามของผู้ใช้อยู่บนเครื่องพิมพ์ที่ผู้ใช้เชื่อมต่อไว้ จึงยกเลิกด้วยสิทธิ์ของผู้ใช้
+            let cancelled = match windows_user {
+                Some(user) => dispatcher
+                    .sessions
+                    .run_as(&config, user, || print_backend().cancel(&printer, job_id))
+                    .unwrap_or_else(|e| Err(anyhow::Error::msg(e))),
+                None => print_backend().cancel(&printer, job_id),
+            };
+            cancelled.map_err(|e| {
+                eprintln!("Error cancelling spooler job {}: {:?}", job_id, e);
+                ApiError::internal(
+                    ErrorCode::SpoolerError,
+                    format!("Failed to cancel job {}: {}", id, e),
+                )
+            })?;
+            format!("Cancelled job {} on printer {}", id, printer.name)
+        }
+        _ => {
+            return Err(ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                format!("Job {} cannot be cancelled", id),
+            ))
+        }
+    };
+
+    println!("{}", message);
+    history.set_status(id, JobStatus::Cancelled, message.clone());
+    spawn_post_complete_hook(
+        &config.hooks,
+        &JobRecord {
+            status: JobStatus::Cancelled,
+            message: message.clone(),
+            ..record
+        },
+    );
+    Ok(HttpResponse::Ok().json(ResponseMessage {
+        status: "success".to_string(),
+        error_code: None,
+        message,
+        detail: None,
+        warnings: vec![],
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/queue/held",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "งานในคิวของ agent ที่กู้คืนตอนเริ่ม Service และพักไว้รอตรวจสอบ (`queue_recovery: \"review\"`)", body = [HeldJob]),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage)
+    )
+)]
+#[get("/admin/queue/held")]
+pub(super) async fn admin_held_jobs_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&request, &config.get())?;
+    Ok(HttpResponse::Ok().json(queue.held()))
+}
+
+pub(super) fn held_job_not_found(job_id: u64) -> ApiError {
+    ApiError::not_found(
+        ErrorCode::JobNotFound,
+        format!("Job {} is not held for review", job_id),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/queue/{job_id}/release",
+    tag = "Admin",
+    params(("job_id" = u64, Path, description = "job id ในคิวของ agent")),
+    responses(
+        (status = 200, description = "agent ดึงงานไปพิมพ์ได้แล้ว", body = ResponseMessage),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage),
+        (status = 404, description = "ไม่พบงานที่พักไว้", body = ResponseMessage)
+    )
+)]
+#[post("/admin/queue/{job_id}/release")]
+pub(super) async fn admin_release_job_handler(
+    request: HttpRequest,
+    job_id: web::Path<u64>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&request, &config.get())?;
+    let job_id = job_id.into_inner();
+    if !queue.release(job_id) {
+        return Err(held_job_not_found(job_id));
+    }
+    let message = format!("Released queued job {}", job_id);
+    println!("{}", message);
+    Ok(HttpResponse::Ok().json(ResponseMessage {
+        status: "success".to_string(),
+        error_code: None,
+        message,
+        detail: None,
+        warnings: vec![],
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/queue/{job_id}/discard",
+    tag = "Admin",
+    params(("job_id" = u64, Path, description = "job id ในคิวของ agent")),
+    responses(
+        (status = 200, description = "ลบงานออกจากคิวแล้ว", body = ResponseMessage),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage),
+        (status = 404, description = "ไม่พบงานที่พักไว้", body = ResponseMessage)
+    )
+)]
+#[post("/admin/queue/{job_id}/discard")]
+pub(super) async fn admin_discard_job_handler(
+    request: HttpRequest,
+    job_id: web::Path<u64>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&request, &config.get())?;
+    let job_id = job_id.into_inner();
+    if !queue.discard(job_id) {
+        return Err(held_job_not_found(job_id));
+    }
+    let message = format!("Discarded queued job {}", job_id);
+    println!("{}", message);
+    Ok(HttpResponse::Ok().json(ResponseMessage {
+        status: "success".to_string(),
+        error_code: None,
+        message,
+        detail: None,
+        warnings: vec![],
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/cache",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "ผลการแปลงไฟล์ที่เก็บไว้ใช้ซ้ำ (`transform_cache`) พร้อมจำนวนครั้งที่ใช้", body = TransformCacheListing),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage)
+    )
+)]
+#[get("/admin/cache")]
+pub(super) async fn admin_cache_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+    transform_cache: web::Data<TransformCache>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    require_admin(&request, &config)?;
+    Ok(HttpResponse::Ok().json(transform_cache.listing(config.transform_cache.is_some())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/print-backend",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "ระบบพิมพ์ที่เลือกด้วย `print_backend` และงานที่เครื่องพิมพ์จำลองได้รับ", body = PrintBackendListing),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage)
+    )
+)]
+#[get("/admin/print-backend")]
+pub(super) async fn admin_print_backend_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&request, &config.get())?;
+    Ok(HttpResponse::Ok().json(PrintBackendListing {
+        backend: print_backend().describe(),
+        jobs: print_backend().recorded_jobs(),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/cache",
+    tag = "Admin",
+    params(CacheInvalidateQuery),
+    responses(
+        (status = 200, description = "ลบผลการแปลงที่ตรงกับเงื่อนไขแล้ว (ไม่ระบุเงื่อนไข = ลบทั้งหมด) งานถัดไปของไฟล์นั้นจะแปลงใหม่", body = ResponseMessage),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage)
+    )
+)]
+#[delete("/admin/cache")]
+pub(super) async fn admin_invalidate_cache_handler(
+    request: HttpRequest,
+    query: web::Query<CacheInvalidateQuery>,
+    config: web::Data<SharedConfig>,
+    transform_cache: web::Data<TransformCache>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&request, &config.get())?;
+    let removed = transform_cache.invalidate(
+        query.key.as_deref(),
+        query.filename.as_deref(),
+        query.sha256.as_deref(),
+    );
+    let message = format!("Removed {} cached transforms", removed);
+    println!("{}", message);
+    Ok(HttpResponse::Ok().json(ResponseMessage {
+        status: "success".to_string(),
+        error_code: None,
+        message,
+        detail: None,
+        warnings: vec![],
+    }))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/admin/cache/{key}",
+    tag = "Admin",
+    params(("key" = String, Path, description = "key ของรายการจาก `GET /api/v1/admin/cache`")),
+    request_body = CacheTtlRequest,
+    responses(
+        (status = 200, description = "เปลี่ยนอายุของรายการแล้ว (อายุที่สั้นจนหมดอายุไปแล้วจะลบรายการทันที)", body = CachedTransformInfo),
+        (status = 400, description = "`ttl_minutes` ไม่ถูกต้อง (`error_code`: `INVALID_REQUEST`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage),
+        (status = 404, description = "ไม่พบรายการ (`error_code`: `CACHE_ENTRY_NOT_FOUND`)", body = ResponseMessage)
+    )
+)]
+#[patch("/admin/cache/{key}")]
+pub(super) async fn admin_cache_ttl_handler(
+    request: HttpRequest,
+    key: web::Path<String>,
+    req: web::Json<CacheTtlRequest>,
+    config: web::Data<SharedConfig>,
+    transform_cache: web::Data<TransformCache>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&request, &config.get())?;
+    if !(1..=MAX_TRANSFORM_CACHE_TTL_MINUTES).contains(&req.ttl_minutes) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid ttl_minutes value: {} (expected 1 to {})",
+                req.ttl_minutes, MAX_TRANSFORM_CACHE_TTL_MINUTES
+            ),
+        ));
+    }
+    let info = transform_cache
+        .set_ttl(&key, req.ttl_minutes)
+        .ok_or_else(|| {
+            ApiError::not_found(
+                ErrorCode::CacheEntryNotFound,
+                format!("Cached transform not found: {}", key),
+            )
+        })?;
+    println!(
+        "Set TTL of cached transform {} ({}) to {} minutes",
+        key, info.filename, req.ttl_minutes
+    );
+    Ok(HttpResponse::Ok().json(info))
+}
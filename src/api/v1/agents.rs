@@ -0,0 +1,213 @@
+// ----------------------------------------------------------------------
+//                             API v1 AGENTS
+// ----------------------------------------------------------------------
+
+use super::{
+    access::{require_agent, require_tenant},
+    errors::{ApiError, ErrorCode, ResponseMessage},
+};
+use crate::{
+    agent::{AgentJobResult, AgentRegistration, AgentRegistry, AgentSite, RemoteJobQueue},
+    config::SharedConfig,
+    history::{JobHistory, JobStatus},
+    hooks::spawn_post_complete_hook,
+    notify::notify_job_failed,
+};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/agent/jobs/next",
+    tag = "Agents",
+    params(
+        ("X-Agent-Id" = String, Header, description = "agent id ตามที่ตั้งไว้ใน `agents` ของ config.json"),
+        ("Authorization" = String, Header, description = "`Bearer <token>`")
+    ),
+    responses(
+        (status = 200, description = "งานพิมพ์ถัดไปของ agent", body = RemoteJob),
+        (status = 204, description = "ไม่มีงานรออยู่"),
+        (status = 401, description = "agent id หรือ token ไม่ถูกต้อง", body = ResponseMessage)
+    )
+)]
+#[get("/agent/jobs/next")]
+pub(super) async fn agent_next_job_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    registry: web::Data<AgentRegistry>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let agent_id = require_agent(&request, &config)?;
+    registry.touch(&agent_id);
+
+    match queue.next(&agent_id) {
+        Some(job) => {
+            println!("Agent {} pulled job {}", agent_id, job.id);
+            Ok(HttpResponse::Ok().json(job))
+        }
+        None => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/agent/jobs/{id}/result",
+    tag = "Agents",
+    params(
+        ("id" = u64, Path, description = "job id"),
+        ("X-Agent-Id" = String, Header, description = "agent id ตามที่ตั้งไว้ใน `agents` ของ config.json"),
+        ("Authorization" = String, Header, description = "`Bearer <token>`")
+    ),
+    request_body = AgentJobResult,
+    responses(
+        (status = 200, description = "บันทึกผลการพิมพ์แล้ว", body = ResponseMessage),
+        (status = 401, description = "agent id หรือ token ไม่ถูกต้อง", body = ResponseMessage),
+        (status = 404, description = "ไม่พบงานที่ agent นี้ดึงไป", body = ResponseMessage)
+    )
+)]
+#[post("/agent/jobs/{id}/result")]
+pub(super) async fn agent_job_result_handler(
+    request: HttpRequest,
+    job_id: web::Path<u64>,
+    result: web::Json<AgentJobResult>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    registry: web::Data<AgentRegistry>,
+    history: web::Data<JobHistory>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let agent_id = require_agent(&request, &config)?;
+    registry.touch(&agent_id);
+
+    let job_id = job_id.into_inner();
+    if !queue.complete(&agent_id, job_id) {
+        return Err(ApiError::not_found(
+            ErrorCode::JobNotFound,
+            format!("Job {} was not pulled by agent {}", job_id, agent_id),
+        ));
+    }
+    if let Some(record) = history.agent_result(&agent_id, job_id, &result) {
+        spawn_post_complete_hook(&config.hooks, &record);
+        if record.status == JobStatus::Failed {
+            notify_job_failed(&config.notifications, &history, &record);
+        }
+    }
+
+    if result.success {
+        println!(
+            "Agent {} completed job {}: {}",
+            agent_id, job_id, result.message
+        );
+    } else {
+        eprintln!(
+            "Agent {} failed job {}: {}",
+            agent_id, job_id, result.message
+        );
+    }
+    Ok(HttpResponse::Ok().json(ResponseMessage {
+        status: "success".to_string(),
+        error_code: None,
+        message: format!("Recorded result of job {}", job_id),
+        detail: None,
+        warnings: vec![],
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/agent/register",
+    tag = "Agents",
+    params(
+        ("X-Agent-Id" = String, Header, description = "agent id ตามที่ตั้งไว้ใน `agents` ของ config.json"),
+        ("Authorization" = String, Header, description = "`Bearer <token>`")
+    ),
+    request_body = AgentRegistration,
+    responses(
+        (status = 200, description = "ลงทะเบียนสำเร็จ", body = ResponseMessage),
+        (status = 401, description = "agent id หรือ token ไม่ถูกต้อง", body = ResponseMessage)
+    )
+)]
+#[post("/agent/register")]
+pub(super) async fn agent_register_handler(
+    request: HttpRequest,
+    registration: web::Json<AgentRegistration>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    registry: web::Data<AgentRegistry>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let agent_id = require_agent(&request, &config)?;
+
+    let printer_count = registration.printers.len();
+    registry.register(&agent_id, registration.into_inner());
+    println!("Agent {} registered {} printers", agent_id, printer_count);
+    Ok(HttpResponse::Ok().json(ResponseMessage {
+        status: "success".to_string(),
+        error_code: None,
+        message: format!(
+            "Registered agent {} with {} printers, {} jobs pending",
+            agent_id,
+            printer_count,
+            queue.pending_count(&agent_id)
+        ),
+        detail: None,
+        warnings: vec![],
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/agent/heartbeat",
+    tag = "Agents",
+    params(
+        ("X-Agent-Id" = String, Header, description = "agent id ตามที่ตั้งไว้ใน `agents` ของ config.json"),
+        ("Authorization" = String, Header, description = "`Bearer <token>`")
+    ),
+    responses(
+        (status = 200, description = "บันทึก heartbeat แล้ว", body = ResponseMessage),
+        (status = 401, description = "agent id หรือ token ไม่ถูกต้อง", body = ResponseMessage)
+    )
+)]
+#[post("/agent/heartbeat")]
+pub(super) async fn agent_heartbeat_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+    registry: web::Data<AgentRegistry>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let agent_id = require_agent(&request, &config)?;
+
+    registry.touch(&agent_id);
+    Ok(HttpResponse::Ok().json(ResponseMessage {
+        status: "success".to_string(),
+        error_code: None,
+        message: format!("Heartbeat received from agent {}", agent_id),
+        detail: None,
+        warnings: vec![],
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/agents",
+    tag = "Agents",
+    responses(
+        (status = 200, description = "สถานะของทุกสาขาที่ตั้งไว้ใน `agents` ของ config.json", body = [AgentSite])
+    )
+)]
+#[get("/agents")]
+pub(super) async fn list_agents_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    registry: web::Data<AgentRegistry>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let sites: Vec<AgentSite> = registry
+        .sites(&config, &queue)
+        .into_iter()
+        .filter(|site| tenant.is_none_or(|tenant| tenant.allows_agent(&site.agent_id)))
+        .collect();
+    Ok(HttpResponse::Ok().json(sites))
+}
@@ -0,0 +1,105 @@
+// ----------------------------------------------------------------------
+//                             API v1 BILLING
+// ----------------------------------------------------------------------
+
+use super::{
+    access::{require_admin, require_tenant},
+    errors::{ApiError, ErrorCode},
+    files::attachment_disposition,
+};
+use crate::{
+    agent::authenticate_admin,
+    billing::{billing_csv, billing_openmetrics},
+    config::SharedConfig,
+    history::JobHistory,
+};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+/// รูปแบบของ `GET /api/v1/billing`
+#[derive(Deserialize, ToSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum BillingFormat {
+    Json,
+    Csv,
+    /// OpenMetrics text format สำหรับ Prometheus
+    Openmetrics,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub(super) struct BillingQuery {
+    /// เดือนตามเวลาของ server เช่น `2026-10` (ไม่ระบุ = ทุกเดือนที่เก็บไว้)
+    month: Option<String>,
+    /// `json`, `csv` หรือ `openmetrics` (ไม่ระบุ = `openmetrics` เมื่อ `Accept` เป็น
+    /// `application/openmetrics-text` นอกนั้น `json`)
+    #[param(inline)]
+    format: Option<BillingFormat>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/billing",
+    tag = "Admin",
+    params(BillingQuery),
+    responses(
+        (status = 200, description = "จำนวนงานและหน้าที่พิมพ์สำเร็จรายเดือน แยกตาม tenant, API key และศูนย์ต้นทุน (ผู้ดูแลระบบเห็นทุก tenant, API key ของ tenant เห็นเฉพาะ tenant ของตัวเอง) เป็น JSON, `text/csv` หรือ `application/openmetrics-text` ตาม `format`", body = Vec<BillingLine>),
+        (status = 400, description = "`month` ไม่ถูกต้อง (`error_code`: `INVALID_REQUEST`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบหรือ API key ไม่ถูกต้อง", body = ResponseMessage)
+    )
+)]
+#[get("/billing")]
+pub(super) async fn billing_handler(
+    request: HttpRequest,
+    query: web::Query<BillingQuery>,
+    config: web::Data<SharedConfig>,
+    history: web::Data<JobHistory>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    // ผู้ดูแลระบบเห็นทุก tenant ส่วน API key ของ tenant เห็นเฉพาะ tenant ของตัวเอง
+    let tenant = if authenticate_admin(&request, &config) {
+        None
+    } else if let Some(tenant) = require_tenant(&request, &config)? {
+        Some(tenant.id)
+    } else {
+        require_admin(&request, &config)?;
+        None
+    };
+    if let Some(month) = query.month.as_deref().filter(|month| {
+        month.len() != 7
+            || chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").is_err()
+    }) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!("Invalid month value: {} (expected YYYY-MM)", month),
+        ));
+    }
+
+    let lines = history.billing.lines(query.month.as_deref(), tenant);
+    let wants_openmetrics = request
+        .headers()
+        .get("Accept")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/openmetrics-text"));
+    let format = query.format.unwrap_or(if wants_openmetrics {
+        BillingFormat::Openmetrics
+    } else {
+        BillingFormat::Json
+    });
+    Ok(match format {
+        BillingFormat::Json => HttpResponse::Ok().json(lines),
+        BillingFormat::Csv => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                "Content-Disposition",
+                attachment_disposition(&format!(
+                    "billing-{}.csv",
+                    query.month.as_deref().unwrap_or("all")
+                )),
+            ))
+            .body(billing_csv(&lines)),
+        BillingFormat::Openmetrics => HttpResponse::Ok()
+            .content_type("application/openmetrics-text; version=1.0.0; charset=utf-8")
+            .body(billing_openmetrics(&lines)),
+    })
+}
@@ -0,0 +1,55 @@
+// ----------------------------------------------------------------------
+//                            API v1 COUNTERS
+// ----------------------------------------------------------------------
+
+use super::{
+    access::require_tenant,
+    errors::{ApiError, ErrorCode},
+};
+use crate::{
+    config::SharedConfig,
+    counters::{validate_counter_name, CounterStore, CounterValue},
+};
+use actix_web::{post, web, HttpRequest, HttpResponse};
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/counters/{name}/next",
+    tag = "Counters",
+    params(("name" = String, Path, description = "ชื่อตัวนับ เช่น `queue` หรือ `tax_invoice` (ตัวอักษรภาษาอังกฤษ ตัวเลข `-` และ `_`)")),
+    responses(
+        (status = 200, description = "เลขที่ถัดไปของตัวนับ ซึ่งบันทึกลงดิสก์แล้วและจะไม่ถูกออกซ้ำ", body = CounterValue),
+        (status = 400, description = "ชื่อตัวนับไม่ถูกต้อง (`error_code`: `INVALID_REQUEST`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 500, description = "บันทึกค่าของตัวนับไม่สำเร็จ (`error_code`: `FILE_IO_ERROR`)", body = ResponseMessage)
+    )
+)]
+#[post("/counters/{name}/next")]
+pub(super) async fn next_counter_handler(
+    request: HttpRequest,
+    path: web::Path<String>,
+    config: web::Data<SharedConfig>,
+    counters: web::Data<CounterStore>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?.map(|tenant| tenant.id.to_string());
+    let name = path.into_inner();
+    validate_counter_name(&name)
+        .map_err(|message| ApiError::bad_request(ErrorCode::InvalidRequest, message))?;
+    // การบันทึกลงดิสก์อาจรอ lock ของ request อื่น จึงไม่ทำบน worker ที่รับ request
+    let value = web::block(move || {
+        counters
+            .next(tenant.as_deref(), &name)
+            .map(|value| (name, value))
+    })
+    .await
+    .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?;
+    let (name, value) = value.map_err(|e| {
+        eprintln!("Error saving counter: {:?}", e);
+        ApiError::internal(
+            ErrorCode::FileIoError,
+            format!("Failed to save the counter: {}", e),
+        )
+    })?;
+    Ok(HttpResponse::Ok().json(CounterValue { name, value }))
+}
@@ -0,0 +1,628 @@
+// ----------------------------------------------------------------------
+//                            API v1 DOCUMENTS
+// ----------------------------------------------------------------------
+
+use super::{
+    access::require_tenant,
+    errors::{ApiError, ErrorCode},
+    print::{
+        require_source_file, source_file, transform_source, validate_print_options, PaperSize,
+        PrintRequest,
+    },
+};
+use crate::{
+    backend::print_backend,
+    config::SharedConfig,
+    estimate::{
+        estimate_sheets, is_supported_n_up, supported_n_up, SheetEstimate, DEFAULT_PAPER_GSM,
+    },
+    job_settings::{resolve_printer, Duplex, MAX_COPIES},
+    locale::Language,
+    multipart::parse_multipart,
+    pdf::{FitPolicy, PdfPasswordError, PTS_PER_MM},
+    preflight::validate_pdf,
+    proof::soft_proof,
+    storage::Storage,
+    tenants::find_tenant,
+    text::{extract_pdf_text, PageOutOfRange},
+    transform::PaperName,
+    transform_cache::TransformCache,
+};
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// โครงสร้างสำหรับรับข้อมูลการตรวจสอบไฟล์ PDF
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"filename": "invoice_original.pdf"}))]
+pub(super) struct ValidateRequest {
+    /// ชื่อไฟล์ PDF ที่จะค้นหาในโฟลเดอร์ ./printable_files (หรือในโฟลเดอร์ของ `source`)
+    filename: String,
+    /// ชื่อแหล่งไฟล์ใน `sources` ของ config.json (ไม่ระบุ = ./printable_files)
+    #[serde(default)]
+    source: Option<String>,
+    /// รหัสผ่านสำหรับเปิดไฟล์ PDF ที่ถูกเข้ารหัส
+    #[serde(default)]
+    pdf_password: Option<String>,
+    /// ตรวจตาม `fit_policy` ที่จะใช้สั่งพิมพ์ (หน้าที่เล็กกว่า A6 จะเป็นคำเตือนแทนข้อผิดพลาดเมื่อไม่ใช่ `error`)
+    #[serde(default)]
+    fit_policy: FitPolicy,
+}
+
+/// โครงสร้างสำหรับดึงข้อความจากไฟล์ PDF ที่อยู่บน server
+#[derive(Deserialize, Default, ToSchema)]
+#[schema(example = json!({"filename": "invoice_original.pdf", "pages": [1, 2]}))]
+pub(super) struct TextRequest {
+    /// ชื่อไฟล์ PDF ที่จะค้นหาในโฟลเดอร์ ./printable_files (หรือในโฟลเดอร์ของ `source`)
+    #[serde(default)]
+    filename: String,
+    /// ชื่อแหล่งไฟล์ใน `sources` ของ config.json (ไม่ระบุ = ./printable_files)
+    #[serde(default)]
+    source: Option<String>,
+    /// รหัสผ่านสำหรับเปิดไฟล์ PDF ที่ถูกเข้ารหัส
+    #[serde(default)]
+    pdf_password: Option<String>,
+    /// หมายเลขหน้าที่ต้องการ เริ่มที่ 1 (ไม่ระบุ = ทุกหน้า)
+    #[serde(default)]
+    pages: Vec<u32>,
+}
+
+/// โครงสร้างสำหรับตรวจไฟล์ก่อนพิมพ์ (soft proof) รับ option เดียวกับ `/api/v1/print`
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"filename": "invoice_original.pdf", "printer_name": "Office_LaserJet", "margin_mm": 3}))]
+pub(super) struct ProofRequest {
+    #[serde(flatten)]
+    print: PrintRequest,
+    /// ความละเอียดของเครื่องพิมพ์ที่ใช้ตรวจเส้นบาง (ไม่ระบุ = อ่านจาก driver)
+    #[serde(default)]
+    #[schema(example = 600)]
+    dpi: Option<u32>,
+    /// ขอบที่เครื่องพิมพ์พิมพ์ไม่ถึงทุกด้าน (มม.) (ไม่ระบุ = อ่านจาก driver)
+    #[serde(default)]
+    #[schema(example = 4.23)]
+    hardware_margin_mm: Option<f32>,
+}
+
+/// โครงสร้างสำหรับประมาณจำนวนกระดาษ รับ option เดียวกับ `/api/v1/print`
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"filename": "invoice_original.pdf", "copies": 2, "papers": ["A6", "A4"], "n_up": [1, 4]}))]
+pub(super) struct EstimateRequest {
+    #[serde(flatten)]
+    print: PrintRequest,
+    /// ขนาดกระดาษที่เปรียบเทียบ (ไม่ระบุ = A6, A5 และ A4)
+    #[serde(default)]
+    papers: Vec<PaperName>,
+    /// จำนวนหน้าต่อด้านที่เปรียบเทียบ: 1, 2, 4, 6, 8, 9 หรือ 16 (ไม่ระบุ = 1, 2 และ 4)
+    #[serde(default)]
+    #[schema(example = json!([1, 2, 4]))]
+    n_up: Vec<u32>,
+    /// น้ำหนักกระดาษ (แกรม/ตร.ม., ไม่ระบุ = 80)
+    #[serde(default)]
+    #[schema(example = 80.0)]
+    paper_gsm: Option<f32>,
+}
+
+/// จำนวนกระดาษที่งานจะใช้ด้วยการตั้งค่าของ request และการตั้งค่าอื่นที่เปรียบเทียบ
+#[derive(Serialize, ToSchema)]
+pub(super) struct EstimateResponse {
+    filename: String,
+    /// จำนวนหน้าของไฟล์ต้นฉบับ
+    source_pages: usize,
+    /// จำนวนหน้าที่ส่งพิมพ์ต่อหนึ่งชุด (หลังเลือกหน้าและแปลงไฟล์)
+    output_pages: usize,
+    copies: u32,
+    /// ขนาดหน้าของไฟล์ที่ส่งพิมพ์
+    page_size: PaperSize,
+    /// การตั้งค่าของ request: หนึ่งหน้าต่อด้านบนกระดาษขนาดเดียวกับหน้า และพิมพ์สองหน้าตาม `duplex`
+    current: SheetEstimate,
+    /// การตั้งค่าที่เปรียบเทียบ เรียงจากใช้กระดาษน้อยที่สุด
+    scenarios: Vec<SheetEstimate>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/pdf/validate",
+    tag = "Printing",
+    request_body = ValidateRequest,
+    responses(
+        (status = 200, description = "ผลการตรวจสอบไฟล์ (ดู `valid` และ `errors` ว่าไฟล์พิมพ์ได้หรือไม่)", body = PdfValidationReport),
+        (status = 400, description = "ไม่พบไฟล์ (`error_code`: `FILE_NOT_FOUND`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 500, description = "อ่านไฟล์ไม่สำเร็จ (`error_code`: `FILE_IO_ERROR`)", body = ResponseMessage)
+    )
+)]
+#[post("/pdf/validate")]
+pub(super) async fn validate_pdf_handler(
+    request: HttpRequest,
+    req: web::Json<ValidateRequest>,
+    config: web::Data<SharedConfig>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let file_path = source_file(
+        &config,
+        tenant.map(|tenant| tenant.id),
+        req.source.as_deref(),
+        &req.filename,
+    )?;
+    require_source_file(&file_path, storage.get_ref(), &req.filename)?;
+
+    let data = file_path
+        .read(storage.get_ref(), config.at_rest_key.as_ref())
+        .map_err(|e| {
+            ApiError::internal(
+                ErrorCode::FileIoError,
+                format!("Failed to read PDF file {}: {}", req.filename, e),
+            )
+        })?;
+    let report = validate_pdf(
+        data,
+        &req.filename,
+        req.pdf_password.as_deref(),
+        &config.font_fallbacks,
+        req.fit_policy,
+    );
+    println!(
+        "Validated {}: valid={}, {} pages, {} errors, {} warnings",
+        req.filename,
+        report.valid,
+        report.page_count,
+        report.errors.len(),
+        report.warnings.len()
+    );
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// ดึงข้อความจาก `data` แล้วแปลงข้อผิดพลาดเป็น `ApiError`
+pub(super) fn pdf_text_response(
+    data: Vec<u8>,
+    filename: &str,
+    options: &TextRequest,
+) -> Result<HttpResponse, ApiError> {
+    let text = extract_pdf_text(
+        data,
+        filename,
+        options.pdf_password.as_deref(),
+        &options.pages,
+    )
+    .map_err(|e| {
+        if let Some(password_error) = e.downcast_ref::<PdfPasswordError>() {
+            let code = match password_error {
+                PdfPasswordError::Required => ErrorCode::PdfPasswordRequired,
+                PdfPasswordError::Invalid => ErrorCode::PdfPasswordInvalid,
+            };
+            return ApiError::bad_request(code, format!("{}: {}", password_error, filename));
+        }
+        if e.downcast_ref::<PageOutOfRange>().is_some() {
+            return ApiError::bad_request(ErrorCode::InvalidRequest, e.to_string());
+        }
+        ApiError::internal(
+            ErrorCode::PdfParseError,
+            format!("Failed to extract text from {}: {:#}", filename, e),
+        )
+    })?;
+    println!(
+        "Extracted text from {}: {} of {} pages, {} characters",
+        filename,
+        text.pages.len(),
+        text.page_count,
+        text.pages
+            .iter()
+            .map(|page| page.text.chars().count())
+            .sum::<usize>()
+    );
+    Ok(HttpResponse::Ok().json(text))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/pdf/text",
+    tag = "Files",
+    request_body = TextRequest,
+    responses(
+        (status = 200, description = "ข้อความในไฟล์แยกตามหน้า", body = PdfText),
+        (status = 400, description = "ไม่พบไฟล์ หมายเลขหน้าไม่ถูกต้อง หรือไฟล์ต้องใช้รหัสผ่าน (`error_code`: `FILE_NOT_FOUND`, `UNKNOWN_SOURCE`, `INVALID_REQUEST`, `PDF_PASSWORD_REQUIRED`, `PDF_PASSWORD_INVALID`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 500, description = "อ่านไฟล์ไม่สำเร็จหรือไฟล์ PDF เสียหาย (`error_code`: `FILE_IO_ERROR`, `PDF_PARSE_ERROR`)", body = ResponseMessage)
+    )
+)]
+#[post("/pdf/text")]
+pub(super) async fn pdf_text_handler(
+    request: HttpRequest,
+    req: web::Json<TextRequest>,
+    config: web::Data<SharedConfig>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let file_path = source_file(
+        &config,
+        tenant.map(|tenant| tenant.id),
+        req.source.as_deref(),
+        &req.filename,
+    )?;
+    require_source_file(&file_path, storage.get_ref(), &req.filename)?;
+    let data = file_path
+        .read(storage.get_ref(), config.at_rest_key.as_ref())
+        .map_err(|e| {
+            ApiError::internal(
+                ErrorCode::FileIoError,
+                format!("Failed to read {}: {}", req.filename, e),
+            )
+        })?;
+    pdf_text_response(data, &req.filename, &req)
+}
+
+/// รูปแบบ body ของ `POST /pdf/text/upload` (ใช้สำหรับเอกสาร OpenAPI)
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub(super) struct UploadTextForm {
+    /// ไฟล์ PDF ที่จะดึงข้อความ (ไม่ถูกบันทึกลงดิสก์)
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+    /// ตัวเลือกแบบ JSON เหมือน `TextRequest` โดยไม่ต้องระบุ `filename` และ `source`
+    #[schema(value_type = Object, example = json!({"pages": [1]}))]
+    options: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/pdf/text/upload",
+    tag = "Files",
+    request_body(content = UploadTextForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "ข้อความในไฟล์แยกตามหน้า", body = PdfText),
+        (status = 400, description = "body ไม่ถูกต้อง ไม่มี part `file` หมายเลขหน้าไม่ถูกต้อง หรือไฟล์ต้องใช้รหัสผ่าน (`error_code`: `INVALID_REQUEST`, `PDF_PASSWORD_REQUIRED`, `PDF_PASSWORD_INVALID`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 500, description = "ไฟล์ PDF เสียหาย (`error_code`: `PDF_PARSE_ERROR`)", body = ResponseMessage)
+    )
+)]
+#[post("/pdf/text/upload")]
+pub(super) async fn upload_pdf_text_handler(
+    request: HttpRequest,
+    body: web::Bytes,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, ApiError> {
+    require_tenant(&request, &config.get())?;
+    let content_type = request
+        .headers()
+        .get("Content-Type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let mut parts = parse_multipart(content_type, &body)
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidRequest, e.to_string()))?;
+    let options: TextRequest = match parts.iter().find(|part| part.name == "options") {
+        Some(part) => serde_json::from_slice(&part.data).map_err(|e| {
+            ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                format!("Invalid options part: {}", e),
+            )
+        })?,
+        None => TextRequest::default(),
+    };
+    let Some(file_index) = parts.iter().position(|part| part.name == "file") else {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            "Missing multipart part: file",
+        ));
+    };
+    let file = parts.swap_remove(file_index);
+    let filename = file.filename.unwrap_or_else(|| "upload.pdf".to_string());
+    pdf_text_response(file.data, &filename, &options)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/pdf/proof",
+    tag = "Printing",
+    request_body = ProofRequest,
+    responses(
+        (status = 200, description = "ผลการตรวจไฟล์หลังแปลงตาม option ของงานพิมพ์: พื้นที่หมึก ส่วนที่ล้ำขอบที่พิมพ์ได้ และเส้นบาง", body = SoftProofReport),
+        (status = 400, description = "ไม่พบไฟล์หรือเครื่องพิมพ์ หรือ option ไม่ถูกต้อง (`error_code`: `FILE_NOT_FOUND`, `PRINTER_NOT_FOUND`, `INVALID_REQUEST`, `PDF_PASSWORD_REQUIRED`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 500, description = "อ่านหรือแปลงไฟล์ไม่สำเร็จ (`error_code`: `FILE_IO_ERROR`, `PDF_PARSE_ERROR`, `PDF_TRANSFORM_ERROR`)", body = ResponseMessage)
+    )
+)]
+#[post("/pdf/proof")]
+pub(super) async fn proof_handler(
+    request: HttpRequest,
+    req: web::Json<ProofRequest>,
+    config: web::Data<SharedConfig>,
+    transform_cache: web::Data<TransformCache>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let mut req = req.into_inner();
+    req.print.tenant = require_tenant(&request, &config)?.map(|tenant| tenant.id.to_string());
+    req.print.language = Language::of_request(request.headers(), &config);
+    let print = &req.print;
+    let tenant = find_tenant(&config, print.tenant.as_deref());
+    validate_print_options(print, &config)?;
+    if req.dpi == Some(0) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            "Invalid dpi value: 0 (must be greater than 0)".to_string(),
+        ));
+    }
+    if let Some(margin) = req
+        .hardware_margin_mm
+        .filter(|margin| !(0.0..50.0).contains(margin))
+    {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid hardware_margin_mm value: {} (expected 0 to less than 50)",
+                margin
+            ),
+        ));
+    }
+
+    let file_path = source_file(
+        &config,
+        print.tenant.as_deref(),
+        print.source.as_deref(),
+        &print.filename,
+    )?;
+    require_source_file(&file_path, storage.get_ref(), &print.filename)?;
+
+    // profile ของเครื่องพิมพ์: ค่าใน request > driver > ค่าเริ่มต้น
+    let mut warnings = Vec::new();
+    let explicit_printer = print
+        .printer_name
+        .clone()
+        .or_else(|| tenant.and_then(|tenant| tenant.config.default_printer.clone()));
+    if let Some(name) = &explicit_printer {
+        if !tenant.is_none_or(|tenant| tenant.allows_printer(name)) {
+            return Err(ApiError::bad_request(
+                ErrorCode::PrinterNotFound,
+                format!("Printer not found: {}", name),
+            ));
+        }
+    }
+    let (printer_name, driver_profile) = if let Some(agent_id) = &print.agent_id {
+        warnings.push(format!(
+            "Printers of agent {} cannot be queried from the server; set dpi and hardware_margin_mm to match the branch printer",
+            agent_id
+        ));
+        (explicit_printer, None)
+    } else {
+        match resolve_printer(explicit_printer.as_deref(), &config) {
+            Ok(printer) => match print_backend().profile(&printer) {
+                Ok(profile) => (Some(printer.name), Some(profile)),
+                Err(e) => {
+                    warnings.push(format!(
+                        "Failed to read the profile of printer {} ({}); using the default printer profile",
+                        printer.name, e
+                    ));
+                    (Some(printer.name), None)
+                }
+            },
+            Err(message) if print.printer_name.is_some() => {
+                return Err(ApiError::bad_request(ErrorCode::PrinterNotFound, message));
+            }
+            Err(message) => {
+                warnings.push(format!("{}; using the default printer profile", message));
+                (None, None)
+            }
+        }
+    };
+    let mut profile_source = if driver_profile.is_some() {
+        "driver"
+    } else {
+        "default"
+    };
+    let mut profile = driver_profile.unwrap_or_default();
+    if let Some(dpi) = req.dpi {
+        profile.dpi = dpi;
+        profile_source = "request";
+    }
+    if let Some(margin) = req.hardware_margin_mm {
+        profile.margins_mm = [margin; 4];
+        profile_source = "request";
+    }
+
+    // ตรวจไฟล์หลังแปลงแบบเดียวกับที่จะส่งพิมพ์ โดยไม่เขียนไฟล์ลงดิสก์
+    let source = file_path
+        .read(storage.get_ref(), config.at_rest_key.as_ref())
+        .map_err(|e| {
+            ApiError::internal(
+                ErrorCode::FileIoError,
+                format!("Failed to read {}: {}", print.filename, e),
+            )
+        })?;
+    let (file_data, transform_report) = transform_source(
+        print,
+        &config,
+        printer_name.as_deref(),
+        source,
+        None,
+        &transform_cache,
+    )?;
+    let mut report = soft_proof(&file_data, &profile).map_err(|e| {
+        ApiError::internal(
+            ErrorCode::PdfParseError,
+            format!("Failed to analyze {}: {}", print.filename, e),
+        )
+    })?;
+    report.printer_name = printer_name;
+    report.profile_source = profile_source.to_string();
+    warnings.extend(transform_report.warnings);
+    warnings.append(&mut report.warnings);
+    report.warnings = warnings;
+    println!(
+        "Soft-proofed {}: {} pages, {:.1}% average ink coverage, {} warnings",
+        print.filename,
+        report.pages.len(),
+        report.average_ink_coverage_percent,
+        report.warnings.len()
+    );
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/estimate",
+    tag = "Printing",
+    request_body = EstimateRequest,
+    responses(
+        (status = 200, description = "จำนวนแผ่น พื้นที่ และน้ำหนักกระดาษที่งานจะใช้ เทียบระหว่างพิมพ์หน้าเดียว/สองหน้า n-up และขนาดกระดาษ โดยไม่ส่งงานพิมพ์", body = EstimateResponse),
+        (status = 400, description = "ไม่พบไฟล์หรือ option ไม่ถูกต้อง (`error_code`: `FILE_NOT_FOUND`, `INVALID_REQUEST`, `PDF_PASSWORD_REQUIRED`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 500, description = "อ่านหรือแปลงไฟล์ไม่สำเร็จ (`error_code`: `FILE_IO_ERROR`, `PDF_PARSE_ERROR`, `PDF_TRANSFORM_ERROR`)", body = ResponseMessage)
+    )
+)]
+#[post("/estimate")]
+pub(super) async fn estimate_handler(
+    request: HttpRequest,
+    req: web::Json<EstimateRequest>,
+    config: web::Data<SharedConfig>,
+    transform_cache: web::Data<TransformCache>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let mut req = req.into_inner();
+    let tenant = require_tenant(&request, &config)?;
+    req.print.tenant = tenant.map(|tenant| tenant.id.to_string());
+    req.print.language = Language::of_request(request.headers(), &config);
+    let print = &req.print;
+    validate_print_options(print, &config)?;
+    if !(1..=MAX_COPIES).contains(&print.copies) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid copies value: {} (expected 1 to {})",
+                print.copies, MAX_COPIES
+            ),
+        ));
+    }
+    if let Some(n_up) = req.n_up.iter().find(|n_up| !is_supported_n_up(**n_up)) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid n_up value: {} (expected one of {})",
+                n_up,
+                supported_n_up()
+            ),
+        ));
+    }
+    let paper_gsm = req.paper_gsm.unwrap_or(DEFAULT_PAPER_GSM);
+    if !(paper_gsm > 0.0 && paper_gsm <= 1000.0) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid paper_gsm value: {} (expected greater than 0 up to 1000)",
+                paper_gsm
+            ),
+        ));
+    }
+
+    let file_path = source_file(
+        &config,
+        print.tenant.as_deref(),
+        print.source.as_deref(),
+        &print.filename,
+    )?;
+    require_source_file(&file_path, storage.get_ref(), &print.filename)?;
+    let source = file_path
+        .read(storage.get_ref(), config.at_rest_key.as_ref())
+        .map_err(|e| {
+            ApiError::internal(
+                ErrorCode::FileIoError,
+                format!("Failed to read {}: {}", print.filename, e),
+            )
+        })?;
+    // ลายน้ำของเครื่องพิมพ์อาจเพิ่มหน้าไม่ได้ แต่ใช้เครื่องพิมพ์เดียวกับที่จะพิมพ์เพื่อให้ได้ไฟล์เดียวกัน
+    let printer_name = print
+        .printer_name
+        .clone()
+        .or_else(|| tenant.and_then(|tenant| tenant.config.default_printer.clone()))
+        .or_else(|| config.default_printer.clone());
+    let (_, report) = transform_source(
+        print,
+        &config,
+        printer_name.as_deref(),
+        source,
+        None,
+        &transform_cache,
+    )?;
+
+    let page_mm = (
+        report.page_width_pts / PTS_PER_MM,
+        report.page_height_pts / PTS_PER_MM,
+    );
+    let duplex = matches!(print.duplex, Some(Duplex::LongEdge | Duplex::ShortEdge));
+    let mut warnings = report.warnings;
+    if print.duplex.is_none() {
+        warnings.push(
+            "duplex is not set; current assumes single-sided printing (the printer default may differ)"
+                .to_string(),
+        );
+    }
+    let estimate = |paper: &str, sheet_mm: (f32, f32), n_up: u32, duplex: bool| {
+        estimate_sheets(
+            report.output_pages,
+            print.copies,
+            page_mm,
+            paper,
+            sheet_mm,
+            n_up,
+            duplex,
+            paper_gsm,
+        )
+    };
+    let Some(current) = estimate(report.paper_name, page_mm, 1, duplex) else {
+        return Err(ApiError::internal(
+            ErrorCode::InternalError,
+            "Failed to estimate the current settings",
+        ));
+    };
+    let papers = if req.papers.is_empty() {
+        vec![PaperName::A6, PaperName::A5, PaperName::A4]
+    } else {
+        req.papers.clone()
+    };
+    let n_ups = if req.n_up.is_empty() {
+        vec![1, 2, 4]
+    } else {
+        req.n_up.clone()
+    };
+    let mut scenarios = Vec::new();
+    for paper in papers {
+        let (width, height) = paper.size_pts();
+        let sheet_mm = (width / PTS_PER_MM, height / PTS_PER_MM);
+        for &n_up in &n_ups {
+            for duplex in [false, true] {
+                scenarios.extend(estimate(paper.name(), sheet_mm, n_up, duplex));
+            }
+        }
+    }
+    for scenario in &mut scenarios {
+        if current.paper_grams > 0.0 {
+            let saving = (1.0 - scenario.paper_grams / current.paper_grams) * 100.0;
+            scenario.saving_percent = ((saving * 10.0).round() / 10.0) as f32;
+        }
+    }
+    scenarios.sort_by(|a, b| {
+        a.paper_grams
+            .total_cmp(&b.paper_grams)
+            .then(a.sheets.cmp(&b.sheets))
+            .then(b.scale.total_cmp(&a.scale))
+    });
+    Ok(HttpResponse::Ok().json(EstimateResponse {
+        filename: print.filename.clone(),
+        source_pages: report.source_pages,
+        output_pages: report.output_pages,
+        copies: print.copies,
+        page_size: PaperSize {
+            name: report.paper_name.to_string(),
+            width_mm: (page_mm.0 * 10.0).round() / 10.0,
+            height_mm: (page_mm.1 * 10.0).round() / 10.0,
+        },
+        current,
+        scenarios,
+        warnings,
+    }))
+}
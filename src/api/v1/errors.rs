@@ -0,0 +1,417 @@
+// ----------------------------------------------------------------------
+//                             API v1 ERRORS
+// ----------------------------------------------------------------------
+
+use crate::{
+    config::SharedConfig,
+    locale::Language,
+    pdf::{EmptyPageSelection, PdfPasswordError},
+    sessions::UserContextError,
+    uploads::UploadError,
+};
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    web, HttpResponse, ResponseError,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// โครงสร้างสำหรับ Response ที่ส่งกลับไปให้ Client
+#[derive(Serialize, ToSchema)]
+pub(super) struct ResponseMessage {
+    pub(super) status: String,
+    /// รหัสข้อผิดพลาดสำหรับให้ Client ตรวจสอบ (มีเฉพาะ Response ที่เป็น Error)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) error_code: Option<ErrorCode>,
+    pub(super) message: String,
+    /// ข้อความเดิมภาษาอังกฤษพร้อมรายละเอียด เมื่อ `message` ถูกแปลตาม `Accept-Language`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) detail: Option<String>,
+    /// คำเตือนที่พบระหว่างประมวลผล เช่น ฟอนต์ที่ไม่ได้ฝังมาในไฟล์
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(super) warnings: Vec<String>,
+}
+
+/// รหัสข้อผิดพลาดที่ส่งกลับในฟิลด์ `error_code`
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(super) enum ErrorCode {
+    /// ค่าใน request ไม่ถูกต้อง เช่น JSON ผิดรูปแบบ หรือ `rotate`, `margin_mm`, `tray` ไม่ถูกต้อง
+    InvalidRequest,
+    /// ไม่พบไฟล์ต้นฉบับในโฟลเดอร์ ./printable_files
+    FileNotFound,
+    /// อ่านหรือเขียนไฟล์ไม่สำเร็จ
+    FileIoError,
+    /// ไม่พบเครื่องพิมพ์ หรือไม่ได้ระบุ `printer_name` และไม่มีเครื่องพิมพ์เริ่มต้น
+    PrinterNotFound,
+    /// ไฟล์ PDF ถูกเข้ารหัส แต่ไม่ได้ส่ง `pdf_password` มา
+    PdfPasswordRequired,
+    /// `pdf_password` ที่ส่งมาไม่ถูกต้อง
+    PdfPasswordInvalid,
+    /// ไฟล์ PDF เสียหายหรือไม่ใช่ไฟล์ PDF
+    PdfParseError,
+    /// แปลงไฟล์เป็น A6 ไม่สำเร็จ เช่น หน้าต้นฉบับเล็กกว่า A6
+    PdfTransformError,
+    /// ระบบพิมพ์ (Windows spooler/CUPS) ไม่รับงานพิมพ์
+    SpoolerError,
+    /// Print Spooler (Windows) หรือ CUPS scheduler ของเครื่องนี้หยุดทำงาน งานไม่ถูกพิมพ์ (ลองใหม่เมื่อ spooler กลับมาทำงาน)
+    SpoolerUnavailable,
+    /// อ่านความสามารถของเครื่องพิมพ์จาก driver ไม่สำเร็จ
+    DriverError,
+    /// ค้นหาเครื่องพิมพ์ในเครือข่ายไม่สำเร็จ
+    DiscoveryError,
+    /// `agent_id` ไม่อยู่ใน `agents` ของ config.json
+    UnknownAgent,
+    /// `source` ไม่อยู่ใน `sources` ของ config.json หรือ tenant ใช้แหล่งไฟล์นี้ไม่ได้
+    UnknownSource,
+    /// agent id, token หรือ API key ของ tenant ไม่ถูกต้อง
+    Unauthorized,
+    /// ไม่พบงานพิมพ์ตาม job id
+    JobNotFound,
+    /// ไม่พบ upload id หรือ upload หมดอายุแล้ว
+    UploadNotFound,
+    /// ไม่พบผลการแปลงไฟล์ใน cache (หรือหมดอายุแล้ว)
+    CacheEntryNotFound,
+    /// `Upload-Offset` ไม่ตรงกับจำนวนไบต์ที่ server ได้รับแล้ว
+    UploadOffsetMismatch,
+    /// SHA-256 ของไฟล์ที่อัปโหลดครบแล้วไม่ตรงกับที่แจ้งไว้
+    ChecksumMismatch,
+    /// hook `pre_submit` ไม่อนุญาตให้ส่งงาน
+    JobRejected,
+    /// อ่าน config.json ใหม่ไม่สำเร็จ (ยังใช้การตั้งค่าเดิม)
+    ConfigError,
+    /// tenant ส่งงานครบ `daily_job_quota` ของวันนี้แล้ว
+    QuotaExceeded,
+    /// งานค้างในคิวหรือที่เครื่องพิมพ์เกิน `backpressure` (ลองใหม่หลัง `Retry-After`)
+    QueueFull,
+    /// เลยกำหนด `expires_at` ของงานแล้ว
+    JobExpired,
+    /// เอกสารเดียวกันเพิ่งถูกส่งไปยังเครื่องพิมพ์เดิม (ส่ง `force: true` เพื่อพิมพ์ซ้ำ)
+    DuplicateJob,
+    /// token พิมพ์ไม่ถูกต้อง หรือ tenant ที่ออก token ถูกลบออกจาก config.json แล้ว
+    PrintTokenInvalid,
+    /// token พิมพ์หมดอายุแล้ว
+    PrintTokenExpired,
+    /// token พิมพ์แบบใช้ครั้งเดียวถูกใช้ไปแล้ว
+    PrintTokenUsed,
+    /// ผู้ใช้ใน `windows_user` ไม่อยู่ใน `windows_users` ของ tenant
+    WindowsUserNotAllowed,
+    /// ผู้ใช้ใน `windows_user` ไม่ได้เข้าสู่ระบบ Windows อยู่ในเครื่องที่พิมพ์
+    UserNotLoggedOn,
+    /// พื้นที่ว่างบนดิสก์ของ server ต่ำกว่า `min_free_disk_mb` หรือดิสก์เต็ม
+    InsufficientStorage,
+    /// ข้อผิดพลาดภายในอื่นๆ
+    InternalError,
+}
+
+/// ข้อผิดพลาดของ API ที่แปลงเป็น `ResponseMessage` พร้อม HTTP status และ `error_code`
+#[derive(Debug)]
+pub(super) struct ApiError {
+    pub(super) status: StatusCode,
+    pub(super) code: ErrorCode,
+    pub(super) message: String,
+    /// วินาทีที่ client ควรรอก่อนส่งใหม่ ส่งเป็น header `Retry-After`
+    pub(super) retry_after: Option<u64>,
+}
+
+impl ApiError {
+    pub(super) fn bad_request(code: ErrorCode, message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            code,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    pub(super) fn not_found(code: ErrorCode, message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::NOT_FOUND,
+            code,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    pub(super) fn internal(code: ErrorCode, message: impl Into<String>) -> Self {
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        self.response(Language::En)
+    }
+}
+
+impl ApiError {
+    /// Response ในภาษา `language` (ข้อความเดิมภาษาอังกฤษย้ายไปอยู่ใน `detail`)
+    fn response(&self, language: Language) -> HttpResponse {
+        let (message, detail) = match self.code.localized(language) {
+            Some(message) => (message.to_string(), Some(self.message.clone())),
+            None => (self.message.clone(), None),
+        };
+        let mut response = HttpResponse::build(self.status);
+        if let Some(secs) = self.retry_after {
+            response.insert_header((actix_web::http::header::RETRY_AFTER, secs));
+        }
+        response.json(ResponseMessage {
+            status: "error".to_string(),
+            error_code: Some(self.code),
+            message,
+            detail,
+            warnings: vec![],
+        })
+    }
+}
+
+impl ErrorCode {
+    /// คำอธิบายของข้อผิดพลาดในภาษา `language` (`None` สำหรับภาษาอังกฤษ ซึ่งใช้ข้อความเดิมที่มีรายละเอียด)
+    fn localized(self, language: Language) -> Option<&'static str> {
+        if language != Language::Th {
+            return None;
+        }
+        Some(match self {
+            ErrorCode::InvalidRequest => "ค่าใน request ไม่ถูกต้อง",
+            ErrorCode::FileNotFound => "ไม่พบไฟล์",
+            ErrorCode::FileIoError => "อ่านหรือเขียนไฟล์ไม่สำเร็จ",
+            ErrorCode::PrinterNotFound => "ไม่พบเครื่องพิมพ์",
+            ErrorCode::PdfPasswordRequired => "ไฟล์ PDF ถูกเข้ารหัส กรุณาระบุรหัสผ่าน",
+            ErrorCode::PdfPasswordInvalid => "รหัสผ่านของไฟล์ PDF ไม่ถูกต้อง",
+            ErrorCode::PdfParseError => "ไฟล์ PDF เสียหายหรือไม่ใช่ไฟล์ PDF",
+            ErrorCode::PdfTransformError => "แปลงไฟล์เอกสารไม่สำเร็จ",
+            ErrorCode::SpoolerError => "ระบบพิมพ์ไม่รับงานพิมพ์",
+            ErrorCode::SpoolerUnavailable => {
+                "ระบบพิมพ์ (Print Spooler) ของเครื่องนี้หยุดทำงาน กรุณาลองใหม่อีกครั้ง"
+            }
+            ErrorCode::DriverError => "อ่านความสามารถของเครื่องพิมพ์จาก driver ไม่สำเร็จ",
+            ErrorCode::DiscoveryError => "ค้นหาเครื่องพิมพ์ในเครือข่ายไม่สำเร็จ",
+            ErrorCode::UnknownAgent => "ไม่พบสาขา (agent) ที่ระบุ",
+            ErrorCode::UnknownSource => "ไม่พบแหล่งไฟล์ที่ระบุ",
+            ErrorCode::Unauthorized => "ยืนยันตัวตนไม่สำเร็จ",
+            ErrorCode::JobNotFound => "ไม่พบงานพิมพ์",
+            ErrorCode::UploadNotFound => "ไม่พบการอัปโหลด หรือการอัปโหลดหมดอายุแล้ว",
+            ErrorCode::CacheEntryNotFound => "ไม่พบผลการแปลงไฟล์ใน cache",
+            ErrorCode::UploadOffsetMismatch => "ตำแหน่งของข้อมูลไม่ตรงกับที่ได้รับไว้แล้ว",
+            ErrorCode::ChecksumMismatch => "ไฟล์ที่อัปโหลดไม่ตรงกับ SHA-256 ที่แจ้งไว้",
+            ErrorCode::JobRejected => "งานพิมพ์ถูกปฏิเสธโดยระบบที่เชื่อมต่อ",
+            ErrorCode::ConfigError => "อ่านการตั้งค่าใหม่ไม่สำเร็จ ยังใช้การตั้งค่าเดิม",
+            ErrorCode::QuotaExceeded => "ส่งงานพิมพ์ครบโควตาของวันนี้แล้ว",
+            ErrorCode::QueueFull => "มีงานพิมพ์รออยู่มาก กรุณาลองใหม่ภายหลัง",
+            ErrorCode::JobExpired => "เลยกำหนดเวลาของงานพิมพ์แล้ว",
+            ErrorCode::DuplicateJob => {
+                "เอกสารนี้เพิ่งถูกส่งไปยังเครื่องพิมพ์เดิม (ส่ง \"force\": true เพื่อพิมพ์ซ้ำ)"
+            }
+            ErrorCode::PrintTokenInvalid => "token สำหรับสั่งพิมพ์ไม่ถูกต้อง",
+            ErrorCode::PrintTokenExpired => "token สำหรับสั่งพิมพ์หมดอายุแล้ว",
+            ErrorCode::PrintTokenUsed => "token สำหรับสั่งพิมพ์ถูกใช้ไปแล้ว",
+            ErrorCode::WindowsUserNotAllowed => "ไม่มีสิทธิ์ส่งงานในนามของผู้ใช้ Windows นี้",
+            ErrorCode::UserNotLoggedOn => "ผู้ใช้ไม่ได้เข้าสู่ระบบ Windows อยู่ในเครื่องที่พิมพ์",
+            ErrorCode::InsufficientStorage => "พื้นที่ว่างบนดิสก์ของ server ไม่พอ",
+            ErrorCode::InternalError => "เกิดข้อผิดพลาดภายในระบบ",
+        })
+    }
+}
+
+/// ข้อความของ Response ที่สำเร็จ ซึ่งแปลตามภาษาของ request เหมือนข้อผิดพลาด
+#[derive(Clone, Copy)]
+pub(super) enum SuccessMessage<'a> {
+    Printed {
+        printer: &'a str,
+    },
+    Queued {
+        agent_id: &'a str,
+        job_id: u64,
+    },
+    PrinterPaused {
+        printer: &'a str,
+        agent_id: Option<&'a str>,
+    },
+    PrinterResumed {
+        printer: &'a str,
+        agent_id: Option<&'a str>,
+    },
+    Purged {
+        printer: &'a str,
+        count: usize,
+    },
+    TestPageSent {
+        printer: &'a str,
+    },
+    UploadCancelled {
+        upload_id: &'a str,
+    },
+}
+
+impl SuccessMessage<'_> {
+    /// ข้อความในภาษา `language` (`None` สำหรับภาษาอังกฤษ ซึ่งใช้ข้อความเดิมที่มีรายละเอียด)
+    fn localized(self, language: Language) -> Option<String> {
+        if language != Language::Th {
+            return None;
+        }
+        let at_agent = |agent_id: Option<&str>| {
+            agent_id
+                .map(|agent_id| format!(" ของสาขา {}", agent_id))
+                .unwrap_or_default()
+        };
+        Some(match self {
+            SuccessMessage::Printed { printer } => {
+                format!("ส่งงานพิมพ์ไปยังเครื่องพิมพ์ {} แล้ว", printer)
+            }
+            SuccessMessage::Queued { agent_id, job_id } => {
+                format!("เก็บงานพิมพ์ไว้ในคิวของสาขา {} แล้ว (งานที่ {})", agent_id, job_id)
+            }
+            SuccessMessage::PrinterPaused { printer, agent_id } => {
+                format!("หยุดส่งงานไปยังเครื่องพิมพ์ {}{} แล้ว", printer, at_agent(agent_id))
+            }
+            SuccessMessage::PrinterResumed { printer, agent_id } => {
+                format!("เครื่องพิมพ์ {}{} ทำงานต่อแล้ว", printer, at_agent(agent_id))
+            }
+            SuccessMessage::Purged { printer, count } => {
+                format!("ยกเลิกงานพิมพ์ {} งานของเครื่องพิมพ์ {} แล้ว", count, printer)
+            }
+            SuccessMessage::TestPageSent { printer } => {
+                format!("ส่งหน้าทดสอบไปยังเครื่องพิมพ์ {} แล้ว", printer)
+            }
+            SuccessMessage::UploadCancelled { upload_id } => {
+                format!("ยกเลิกการอัปโหลด {} แล้ว", upload_id)
+            }
+        })
+    }
+
+    /// `message` และ `detail` ของ Response ในภาษา `language` (ข้อความเดิม `english` ย้ายไปอยู่ใน `detail`)
+    pub(super) fn text(self, language: Language, english: String) -> (String, Option<String>) {
+        match self.localized(language) {
+            Some(message) => (message, Some(english)),
+            None => (english, None),
+        }
+    }
+}
+
+impl ResponseMessage {
+    /// Response ที่สำเร็จในภาษา `language`
+    pub(super) fn success(
+        kind: SuccessMessage,
+        language: Language,
+        english: String,
+        warnings: Vec<String>,
+    ) -> Self {
+        let (message, detail) = kind.text(language, english);
+        ResponseMessage {
+            status: "success".to_string(),
+            error_code: None,
+            message,
+            detail,
+            warnings,
+        }
+    }
+}
+
+/// แปลข้อความของข้อผิดพลาดตามภาษาของ request (`Accept-Language` หรือ `language` ใน config.json)
+pub async fn localize_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let language = match req.app_data::<web::Data<SharedConfig>>() {
+        Some(config) => Language::of_request(req.headers(), &config.get()),
+        None => Language::En,
+    };
+    let res = next.call(req).await?;
+    let localized = (language != Language::En)
+        .then(|| res.response().error()?.as_error::<ApiError>())
+        .flatten()
+        .map(|e| e.response(language));
+    Ok(match localized {
+        Some(response) => res.into_response(response),
+        None => res.map_into_boxed_body(),
+    })
+}
+
+/// จัดประเภทข้อผิดพลาดจากขั้นตอนแปลงไฟล์ PDF เป็น `ApiError`
+pub(super) fn transform_error(e: &anyhow::Error, filename: &str) -> ApiError {
+    if let Some(selection_error) = e.downcast_ref::<EmptyPageSelection>() {
+        return ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!("{}: {}", selection_error, filename),
+        );
+    }
+    if let Some(password_error) = e.downcast_ref::<PdfPasswordError>() {
+        let code = match password_error {
+            PdfPasswordError::Required => ErrorCode::PdfPasswordRequired,
+            PdfPasswordError::Invalid => ErrorCode::PdfPasswordInvalid,
+        };
+        return ApiError::bad_request(code, format!("{}: {}", password_error, filename));
+    }
+
+    eprintln!("Error resizing PDF: {:?}", e);
+    let code = if e.downcast_ref::<lopdf::Error>().is_some() {
+        ErrorCode::PdfParseError
+    } else if e.downcast_ref::<std::io::Error>().is_some() {
+        ErrorCode::FileIoError
+    } else {
+        ErrorCode::PdfTransformError
+    };
+    ApiError::internal(code, format!("Failed to resize PDF to A6: {}", e))
+}
+
+/// แปลงข้อผิดพลาดของการส่งงานในนามของผู้ใช้ Windows เป็น `ApiError`
+pub(super) fn user_context_error(e: UserContextError) -> ApiError {
+    let (status, code) = match &e {
+        UserContextError::Disabled | UserContextError::Unsupported => {
+            (StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest)
+        }
+        UserContextError::NotAllowed(_) => {
+            (StatusCode::FORBIDDEN, ErrorCode::WindowsUserNotAllowed)
+        }
+        UserContextError::NotLoggedOn(_) => (StatusCode::CONFLICT, ErrorCode::UserNotLoggedOn),
+        UserContextError::Failed(_) => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::SpoolerError),
+    };
+    ApiError {
+        status,
+        code,
+        message: e.to_string(),
+        retry_after: None,
+    }
+}
+
+/// แปลงข้อผิดพลาดของการอัปโหลดแบบแบ่ง chunk เป็น `ApiError`
+pub(super) fn upload_error(e: UploadError) -> ApiError {
+    let (status, code) = match &e {
+        UploadError::NotFound => (StatusCode::NOT_FOUND, ErrorCode::UploadNotFound),
+        UploadError::OffsetMismatch { .. } => {
+            (StatusCode::CONFLICT, ErrorCode::UploadOffsetMismatch)
+        }
+        UploadError::TooLarge | UploadError::SizeLimit => {
+            (StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest)
+        }
+        UploadError::ChecksumMismatch { .. } => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::ChecksumMismatch,
+        ),
+        UploadError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::FileIoError),
+    };
+    ApiError {
+        status,
+        code,
+        message: e.to_string(),
+        retry_after: None,
+    }
+}
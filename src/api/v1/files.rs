@@ -0,0 +1,213 @@
+// ----------------------------------------------------------------------
+//                              API v1 FILES
+// ----------------------------------------------------------------------
+
+use super::{
+    access::require_tenant,
+    errors::{ApiError, ErrorCode},
+};
+use crate::{
+    config::SharedConfig,
+    storage::{hex_string, list_staged_files, read_staged_file, FileHashCache, Storage},
+    tenants::is_plain_file_name,
+};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// ตรวจว่า header `If-None-Match` ของ request มี `etag` อยู่หรือไม่ (เทียบแบบ weak ตาม RFC 9110)
+pub(super) fn etag_matches(request: &HttpRequest, etag: &str) -> bool {
+    let Some(header) = request
+        .headers()
+        .get("If-None-Match")
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    header.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/files",
+    tag = "Files",
+    params(("If-None-Match" = Option<String>, Header, description = "ETag จาก response ก่อนหน้า")),
+    responses(
+        (status = 200, description = "รายการไฟล์ใน ./printable_files พร้อม header `ETag`", body = [FileInfo]),
+        (status = 304, description = "รายการไฟล์ไม่เปลี่ยนจาก ETag ที่ส่งมา"),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 500, description = "อ่านโฟลเดอร์ไม่สำเร็จ (`error_code`: `FILE_IO_ERROR`)", body = ResponseMessage)
+    )
+)]
+#[get("/files")]
+pub(super) async fn list_files_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+    file_hashes: web::Data<FileHashCache>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?.map(|tenant| tenant.id.to_string());
+    let files = web::block(move || {
+        list_staged_files(
+            &file_hashes,
+            storage.get_ref(),
+            tenant.as_deref(),
+            config.at_rest_key.as_ref(),
+        )
+    })
+    .await
+    .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?
+    .map_err(|e| {
+        ApiError::internal(
+            ErrorCode::FileIoError,
+            format!("Failed to list files: {}", e),
+        )
+    })?;
+
+    // ETag ของรายการคำนวณจากชื่อและ SHA-256 ของทุกไฟล์ จึงเปลี่ยนเมื่อเนื้อหาไฟล์ใดเปลี่ยน
+    let mut hasher = Sha256::new();
+    for file in &files {
+        hasher.update(format!("{}\n{}\n", file.name, file.sha256));
+    }
+    let etag = format!("\"{}\"", hex_string(&hasher.finalize()));
+    if etag_matches(&request, &etag) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(("ETag", etag))
+            .finish());
+    }
+    Ok(HttpResponse::Ok().insert_header(("ETag", etag)).json(files))
+}
+
+/// ชนิดของไฟล์สำหรับ header `Content-Type` ตามนามสกุล
+pub(super) fn content_type_for(name: &str) -> &'static str {
+    match Path::new(name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("pdf") => "application/pdf",
+        Some("json") => "application/json",
+        Some("txt") | Some("log") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// header `Content-Disposition` แบบ attachment ที่รองรับชื่อไฟล์ภาษาไทย (RFC 6266 `filename*`)
+pub(super) fn attachment_disposition(name: &str) -> String {
+    let ascii_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let encoded: String = name
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii_name, encoded
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/files/{name}/download",
+    tag = "Files",
+    params(
+        ("name" = String, Path, description = "ชื่อไฟล์ใน ./printable_files เช่น `invoice_original_a6.pdf`"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag จาก response ก่อนหน้า")
+    ),
+    responses(
+        (status = 200, description = "เนื้อหาไฟล์ตรงตามที่บันทึกไว้ (ไฟล์ `_a6` คือไฟล์ที่ส่งไปยังเครื่องพิมพ์) พร้อม header `ETag`", body = Vec<u8>, content_type = "application/pdf"),
+        (status = 304, description = "ไฟล์ไม่เปลี่ยนจาก ETag ที่ส่งมา"),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 404, description = "ไม่พบไฟล์ (`error_code`: `FILE_NOT_FOUND`)", body = ResponseMessage),
+        (status = 500, description = "อ่านไฟล์ไม่สำเร็จ (`error_code`: `FILE_IO_ERROR`)", body = ResponseMessage)
+    )
+)]
+#[get("/files/{name}/download")]
+pub(super) async fn download_file_handler(
+    request: HttpRequest,
+    path: web::Path<String>,
+    config: web::Data<SharedConfig>,
+    file_hashes: web::Data<FileHashCache>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?.map(|tenant| tenant.id.to_string());
+    let name = path.into_inner();
+    let not_found =
+        || ApiError::not_found(ErrorCode::FileNotFound, format!("File not found: {}", name));
+    if !is_plain_file_name(&name) {
+        return Err(not_found());
+    }
+
+    let io_error = |e: std::io::Error| {
+        ApiError::internal(
+            ErrorCode::FileIoError,
+            format!("Failed to read file {}: {}", name, e),
+        )
+    };
+    let block_error = |e: actix_web::error::BlockingError| {
+        ApiError::internal(ErrorCode::InternalError, e.to_string())
+    };
+
+    let (hash_storage, hash_tenant, hash_name) = (storage.clone(), tenant.clone(), name.clone());
+    let hash_config = config.clone();
+    let sha256 = web::block(move || {
+        let Some(file) = hash_storage.stat(hash_tenant.as_deref(), &hash_name)? else {
+            return Ok(None);
+        };
+        file_hashes
+            .sha256(
+                hash_storage.get_ref(),
+                hash_tenant.as_deref(),
+                &file,
+                hash_config.at_rest_key.as_ref(),
+            )
+            .map(|(sha256, _)| Some(sha256))
+    })
+    .await
+    .map_err(block_error)?
+    .map_err(io_error)?
+    .ok_or_else(not_found)?;
+    let etag = format!("\"{}\"", sha256);
+    if etag_matches(&request, &etag) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(("ETag", etag))
+            .finish());
+    }
+
+    let file_name = name.clone();
+    let data = web::block(move || {
+        read_staged_file(
+            storage.get_ref(),
+            config.at_rest_key.as_ref(),
+            tenant.as_deref(),
+            &file_name,
+        )
+    })
+    .await
+    .map_err(block_error)?
+    .map_err(io_error)?;
+    println!("Serving download of {} ({} bytes)", name, data.len());
+    Ok(HttpResponse::Ok()
+        .insert_header(("ETag", etag))
+        .insert_header(("Content-Disposition", attachment_disposition(&name)))
+        .content_type(content_type_for(&name))
+        .body(data))
+}
@@ -0,0 +1,470 @@
+// ----------------------------------------------------------------------
+//                              API v1 JOBS
+// ----------------------------------------------------------------------
+
+use super::{
+    access::{check_printer_access, require_admin, require_tenant},
+    admin::job_not_found,
+    errors::{user_context_error, ApiError, ErrorCode, ResponseMessage},
+    files::attachment_disposition,
+    print::{
+        check_pre_submit, check_quota, require_queue_capacity, require_spooler, store_job_output,
+        submit_error,
+    },
+};
+use crate::{
+    agent::RemoteJobQueue,
+    archive::find_archived_job,
+    client_identity::{client_identity, resolve_client_hostname, ClientIdentity},
+    config::{AppConfig, SharedConfig},
+    crypto::read_job_file,
+    dispatch::PrintDispatcher,
+    history::{
+        job_artifact_path, JobEvent, JobHistory, JobRecord, JobStage, JobStatus, JobTimeline,
+    },
+    hooks::{spawn_post_complete_hook, HookContext},
+    ipp::IPP_DRIVER_NAME,
+    job_settings::dispatch_print_job,
+    sessions::{resolve_printer_as, UserContextError},
+    tenants::Tenant,
+};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+use utoipa::ToSchema;
+
+/// เลือกเครื่องพิมพ์อื่นสำหรับการพิมพ์ซ้ำ (ไม่ระบุ body = ใช้เครื่องพิมพ์เดิม)
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"printer_name": "Counter2_Receipt"}))]
+pub(super) struct ReprintRequest {
+    /// เครื่องพิมพ์ปลายทาง (งานของ agent จะหาเครื่องพิมพ์นี้ที่สาขาเดิม)
+    #[serde(default)]
+    printer_name: Option<String>,
+}
+
+/// ส่งไฟล์ที่เก็บไว้ของงาน `id` ไปพิมพ์อีกครั้งด้วยค่าเดิม และบันทึกเป็นงานใหม่ในประวัติ
+/// (`tenant` = พิมพ์ซ้ำได้เฉพาะงานของ tenant นั้น, `None` = ผู้ดูแลระบบหรือไม่ได้ตั้งค่า `tenants`)
+/// และบันทึก `client` เป็นเครื่องที่สั่งพิมพ์ซ้ำ
+#[allow(clippy::too_many_arguments)]
+pub(super) fn reprint_job(
+    id: u64,
+    printer_name: Option<String>,
+    config: &AppConfig,
+    queue: &RemoteJobQueue,
+    history: &JobHistory,
+    dispatcher: &PrintDispatcher,
+    tenant: Option<Tenant>,
+    client: Option<ClientIdentity>,
+) -> Result<HttpResponse, ApiError> {
+    let started_at = Instant::now();
+    let record = history
+        .get(id)
+        .filter(|record| tenant.is_none_or(|tenant| record.tenant.as_deref() == Some(tenant.id)))
+        .ok_or_else(|| job_not_found(id))?;
+    if let Some(printer_name) = &printer_name {
+        check_printer_access(tenant, printer_name, None)?;
+    }
+    // `windows_users` ของ tenant อาจถูกเปลี่ยนหลังส่งงานเดิม
+    if let Some(user) = &record.settings.windows_user {
+        if !tenant.is_none_or(|tenant| tenant.allows_windows_user(user)) {
+            return Err(user_context_error(UserContextError::NotAllowed(
+                user.clone(),
+            )));
+        }
+    }
+    check_quota(tenant, history)?;
+    let artifact_path = job_artifact_path(id);
+    let file_data = read_job_file(config.at_rest_key.as_ref(), &artifact_path).map_err(|_| {
+        ApiError::not_found(
+            ErrorCode::FileNotFound,
+            format!("Output of job {} is no longer retained", id),
+        )
+    })?;
+
+    let mut reprint = JobRecord {
+        id: 0,
+        job_id: None,
+        submitted_at: chrono::Local::now().to_rfc3339(),
+        expires_at: None,
+        duplicate_of: None,
+        printer_name: printer_name.or(record.printer_name.clone()),
+        client,
+        timeline: JobTimeline::received(started_at),
+        ..record
+    };
+    let mut spooler_jobs = None;
+    check_pre_submit(
+        config,
+        &HookContext {
+            event: "pre_submit",
+            filename: &reprint.filename,
+            printer_name: reprint.printer_name.as_deref(),
+            agent_id: reprint.agent_id.as_deref(),
+            job_name: &reprint.job_name,
+            pages: reprint.pages,
+            output_file: reprint.output_file.as_deref(),
+            job_id: None,
+            status: None,
+            message: None,
+            metadata: &reprint.metadata,
+            client: reprint.client.as_ref(),
+        },
+    )?;
+    if let Some(agent_id) = &reprint.agent_id {
+        let job_id = queue.push(
+            agent_id,
+            reprint.job_name.clone(),
+            reprint.printer_name.clone(),
+            reprint.settings.clone(),
+            &file_data,
+            None,
+        );
+        if let Err(e) = queue.spool(config.at_rest_key.as_ref(), agent_id, job_id, &file_data) {
+            eprintln!("Error saving queued job {} to disk: {}", job_id, e);
+        }
+        reprint.job_id = Some(job_id);
+        reprint.status = JobStatus::Queued;
+        reprint.message = format!("Reprint of job {} queued for agent {}", id, agent_id);
+        reprint.timeline.push(
+            JobStage::Queued,
+            Some(format!("Job {} for agent {}", job_id, agent_id)),
+        );
+    } else {
+        require_spooler(dispatcher, config, reprint.printer_name.as_deref())?;
+        let printer = resolve_printer_as(
+            reprint.printer_name.as_deref(),
+            reprint.settings.windows_user.as_deref(),
+            config,
+            &dispatcher.sessions,
+        )
+        .map_err(|e| ApiError::not_found(ErrorCode::PrinterNotFound, e))?;
+        check_printer_access(tenant, &printer.name, None)?;
+        require_queue_capacity(dispatcher, config, &printer)?;
+        let submitted = dispatcher.reserve(&printer).submit(|| {
+            dispatch_print_job(
+                &printer,
+                file_data,
+                &reprint.job_name,
+                &reprint.settings,
+                config,
+                &dispatcher.sessions,
+            )
+        });
+        history
+            .timeline
+            .count_job(None, &printer.name, reprint.pages, submitted.is_ok());
+        let job_ids = submitted.map_err(|e| {
+            eprintln!("Error reprinting job {}: {:?}", id, e);
+            submit_error(
+                dispatcher,
+                config,
+                &printer,
+                ApiError::internal(
+                    ErrorCode::SpoolerError,
+                    format!("Failed to reprint job {}: {}", id, e),
+                ),
+            )
+        })?;
+        reprint.job_id = Some(job_ids[0]);
+        reprint.status = JobStatus::Printed;
+        reprint.message = format!("Reprint of job {} sent to printer {}", id, printer.name);
+        reprint.timeline.push(
+            JobStage::Submitted,
+            Some(format!(
+                "Spooler job {} on printer {}",
+                job_ids[0], printer.name
+            )),
+        );
+        if printer.driver_name != IPP_DRIVER_NAME {
+            spooler_jobs = Some((printer.name.clone(), job_ids));
+        }
+        reprint.printer_name = Some(printer.name);
+        spawn_post_complete_hook(&config.hooks, &reprint);
+    }
+
+    let message = reprint.message.clone();
+    let request = serde_json::json!({ "reprint_of": id });
+    let new_id = store_job_output(config, history, reprint, &request, Some(&artifact_path));
+    if let Some((printer_name, job_ids)) = spooler_jobs {
+        history.watch_spooler(new_id, &printer_name, &job_ids);
+    }
+    println!("{} (job {})", message, new_id);
+    Ok(HttpResponse::Ok().json(ResponseMessage {
+        status: "success".to_string(),
+        error_code: None,
+        message: format!("{} as job {}", message, new_id),
+        detail: None,
+        warnings: vec![],
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/jobs/{id}/reprint",
+    tag = "Printing",
+    params(("id" = u64, Path, description = "หมายเลขงานในประวัติ (`id` ใน /admin)")),
+    request_body(content = Option<ReprintRequest>, description = "ไม่ระบุ = พิมพ์ที่เครื่องพิมพ์เดิม"),
+    responses(
+        (status = 200, description = "ส่งงานพิมพ์ซ้ำแล้ว", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 404, description = "ไม่พบงาน เครื่องพิมพ์ หรือไฟล์ของงานถูกลบไปแล้ว", body = ResponseMessage),
+        (status = 422, description = "hook `pre_submit` ไม่อนุญาตให้ส่งงาน", body = ResponseMessage),
+        (status = 429, description = "tenant ส่งงานครบโควตาของวันนี้แล้ว (`error_code`: `QUOTA_EXCEEDED`) หรือมีงานค้างเกิน `backpressure` (`QUEUE_FULL` พร้อม header `Retry-After`)", body = ResponseMessage),
+        (status = 500, description = "ส่งงานไปยังเครื่องพิมพ์ไม่สำเร็จ", body = ResponseMessage),
+        (status = 503, description = "Print Spooler ของเครื่องนี้หยุดทำงาน งานไม่ถูกพิมพ์ (`error_code`: `SPOOLER_UNAVAILABLE`)", body = ResponseMessage)
+    )
+)]
+#[post("/jobs/{id}/reprint")]
+pub(super) async fn reprint_job_handler(
+    request: HttpRequest,
+    id: web::Path<u64>,
+    body: Option<web::Json<ReprintRequest>>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    history: web::Data<JobHistory>,
+    dispatcher: web::Data<PrintDispatcher>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let printer_name = body.and_then(|body| body.into_inner().printer_name);
+    let mut client = client_identity(&request, &config);
+    resolve_client_hostname(&mut client, &config);
+    reprint_job(
+        id.into_inner(),
+        printer_name,
+        &config,
+        &queue,
+        &history,
+        &dispatcher,
+        tenant,
+        client,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/jobs",
+    tag = "Printing",
+    params(
+        ("metadata.{key}" = Option<String>, Query, description = "แสดงเฉพาะงานที่มี `metadata` ตรงกัน เช่น `?metadata.order_id=SO-2024-0042` (ระบุหลาย key = ต้องตรงทุก key)")
+    ),
+    responses(
+        (status = 200, description = "งานล่าสุดเรียงจากใหม่ไปเก่า (เมื่อตั้งค่า `tenants` จะแสดงเฉพาะงานของ tenant ที่เป็นเจ้าของ API key)", body = [JobRecord]),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage)
+    )
+)]
+#[get("/jobs")]
+pub(super) async fn list_jobs_handler(
+    request: HttpRequest,
+    query: web::Query<HashMap<String, String>>,
+    config: web::Data<SharedConfig>,
+    history: web::Data<JobHistory>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let metadata: Vec<(&str, &str)> = query
+        .iter()
+        .filter_map(|(name, value)| Some((name.strip_prefix("metadata.")?, value.as_str())))
+        .collect();
+    let jobs: Vec<JobRecord> = history
+        .recent()
+        .into_iter()
+        .filter(|record| tenant.is_none_or(|tenant| record.tenant.as_deref() == Some(tenant.id)))
+        .filter(|record| {
+            metadata
+                .iter()
+                .all(|(key, value)| record.metadata.get(*key).map(String::as_str) == Some(*value))
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+/// งานพิมพ์หนึ่งงานพร้อมลำดับเหตุการณ์ของงาน
+#[derive(Serialize, ToSchema)]
+pub(super) struct JobDetail {
+    #[serde(flatten)]
+    job: JobRecord,
+    /// เหตุการณ์ตั้งแต่รับ request เรียงตามเวลา `duration_ms` ของแต่ละเหตุการณ์คือเวลาที่งานอยู่ในขั้นตอนก่อนหน้า
+    /// (งานที่ส่งให้ spooler จะมีสถานะใน spooler และ `completed` เมื่องานออกจากคิว)
+    timeline: Vec<JobEvent>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/jobs/{id}",
+    tag = "Printing",
+    params(("id" = u64, Path, description = "หมายเลขงานในประวัติ")),
+    responses(
+        (status = 200, description = "งานในประวัติพร้อม `timeline` ที่บอกว่างานใช้เวลาในแต่ละขั้นตอนเท่าไร", body = JobDetail),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 404, description = "ไม่พบงาน หรืองานหลุดจากประวัติแล้ว (`error_code`: `JOB_NOT_FOUND`)", body = ResponseMessage)
+    )
+)]
+#[get("/jobs/{id}")]
+pub(super) async fn job_detail_handler(
+    request: HttpRequest,
+    id: web::Path<u64>,
+    config: web::Data<SharedConfig>,
+    history: web::Data<JobHistory>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let id = id.into_inner();
+    let mut job = history
+        .get(id)
+        .filter(|record| tenant.is_none_or(|tenant| record.tenant.as_deref() == Some(tenant.id)))
+        .ok_or_else(|| job_not_found(id))?;
+    job.reprintable = job_artifact_path(job.id).exists();
+    let timeline = job.timeline.events().to_vec();
+    Ok(HttpResponse::Ok().json(JobDetail { job, timeline }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs/{id}/reprint",
+    tag = "Admin",
+    params(("id" = u64, Path, description = "หมายเลขงานในประวัติ")),
+    request_body(content = Option<ReprintRequest>, description = "ไม่ระบุ = พิมพ์ที่เครื่องพิมพ์เดิม"),
+    responses(
+        (status = 200, description = "ส่งงานพิมพ์ซ้ำแล้ว", body = ResponseMessage),
+        (status = 401, description = "ไม่มีสิทธิ์ผู้ดูแลระบบ", body = ResponseMessage),
+        (status = 404, description = "ไม่พบงาน เครื่องพิมพ์ หรือไฟล์ของงานถูกลบไปแล้ว", body = ResponseMessage),
+        (status = 500, description = "ส่งงานไปยังเครื่องพิมพ์ไม่สำเร็จ", body = ResponseMessage),
+        (status = 503, description = "Print Spooler ของเครื่องนี้หยุดทำงาน งานไม่ถูกพิมพ์ (`error_code`: `SPOOLER_UNAVAILABLE`)", body = ResponseMessage)
+    )
+)]
+#[post("/admin/jobs/{id}/reprint")]
+pub(super) async fn admin_reprint_job_handler(
+    request: HttpRequest,
+    id: web::Path<u64>,
+    body: Option<web::Json<ReprintRequest>>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    history: web::Data<JobHistory>,
+    dispatcher: web::Data<PrintDispatcher>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    require_admin(&request, &config)?;
+    let printer_name = body.and_then(|body| body.into_inner().printer_name);
+    let mut client = client_identity(&request, &config);
+    resolve_client_hostname(&mut client, &config);
+    reprint_job(
+        id.into_inner(),
+        printer_name,
+        &config,
+        &queue,
+        &history,
+        &dispatcher,
+        None,
+        client,
+    )
+}
+
+/// หาไฟล์ของงาน `id` ในคลัง อ่านไฟล์ที่ `select` เลือกใน thread สำหรับงาน IO
+/// (`tenant` = อ่านได้เฉพาะงานที่ tenant นั้นเป็นผู้ส่ง)
+pub(super) async fn read_archived_job(
+    config: &AppConfig,
+    id: u64,
+    tenant: Option<Tenant<'_>>,
+    select: fn((PathBuf, PathBuf)) -> PathBuf,
+) -> Result<Vec<u8>, ApiError> {
+    let archive = config.archive.clone().ok_or_else(|| {
+        ApiError::not_found(
+            ErrorCode::FileNotFound,
+            "Job archive is not enabled".to_string(),
+        )
+    })?;
+    let tenant = tenant.map(|tenant| tenant.id.to_string());
+    let key = config.at_rest_key.clone();
+    web::block(move || {
+        let paths = find_archived_job(&archive, id)?;
+        if let Some(tenant) = &tenant {
+            let metadata: serde_json::Value = read_job_file(key.as_ref(), &paths.1)
+                .ok()
+                .and_then(|data| serde_json::from_slice(&data).ok())?;
+            if metadata["job"]["tenant"].as_str() != Some(tenant.as_str()) {
+                return None;
+            }
+        }
+        Some(read_job_file(key.as_ref(), &select(paths)))
+    })
+    .await
+    .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?
+    .ok_or_else(|| {
+        ApiError::not_found(
+            ErrorCode::FileNotFound,
+            format!("Job {} is not in the archive", id),
+        )
+    })?
+    .map_err(|e| {
+        ApiError::internal(
+            ErrorCode::FileIoError,
+            format!("Failed to read archive of job {}: {}", id, e),
+        )
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/jobs/{id}/artifact",
+    tag = "Printing",
+    params(("id" = u64, Path, description = "หมายเลขงานในประวัติ")),
+    responses(
+        (status = 200, description = "ไฟล์ที่ส่งไปยังเครื่องพิมพ์ตามที่เก็บไว้ในคลัง", body = Vec<u8>, content_type = "application/pdf"),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 404, description = "ไม่ได้เปิดใช้คลัง หรือไม่มีงานนี้ในคลัง (`error_code`: `FILE_NOT_FOUND`)", body = ResponseMessage),
+        (status = 500, description = "อ่านไฟล์ไม่สำเร็จ", body = ResponseMessage)
+    )
+)]
+#[get("/jobs/{id}/artifact")]
+pub(super) async fn job_artifact_handler(
+    request: HttpRequest,
+    id: web::Path<u64>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let id = id.into_inner();
+    let data = read_archived_job(&config, id, tenant, |(artifact, _)| artifact).await?;
+    println!(
+        "Serving archived output of job {} ({} bytes)",
+        id,
+        data.len()
+    );
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            "Content-Disposition",
+            attachment_disposition(&format!("job-{}.pdf", id)),
+        ))
+        .content_type("application/pdf")
+        .body(data))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/jobs/{id}/artifact/metadata",
+    tag = "Printing",
+    params(("id" = u64, Path, description = "หมายเลขงานในประวัติ")),
+    responses(
+        (status = 200, description = "ข้อมูลของงาน ค่าใน request (ไม่รวม `pdf_password`) และ SHA-256 ของไฟล์ในคลัง", content_type = "application/json"),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 404, description = "ไม่ได้เปิดใช้คลัง หรือไม่มีงานนี้ในคลัง (`error_code`: `FILE_NOT_FOUND`)", body = ResponseMessage),
+        (status = 500, description = "อ่านไฟล์ไม่สำเร็จ", body = ResponseMessage)
+    )
+)]
+#[get("/jobs/{id}/artifact/metadata")]
+pub(super) async fn job_artifact_metadata_handler(
+    request: HttpRequest,
+    id: web::Path<u64>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let select = |(_, metadata)| metadata;
+    let data = read_archived_job(&config, id.into_inner(), tenant, select).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .body(data))
+}
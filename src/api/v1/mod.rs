@@ -0,0 +1,291 @@
+// ----------------------------------------------------------------------
+//                        API v1 SCHEMAS & HANDLERS
+// ----------------------------------------------------------------------
+
+mod access;
+mod admin;
+mod agents;
+mod billing;
+mod counters;
+mod documents;
+mod errors;
+mod files;
+mod jobs;
+mod print;
+mod printers;
+mod tokens;
+mod uploads;
+
+pub use errors::localize_errors;
+
+use self::{
+    admin::{
+        admin_cache_handler, admin_cache_ttl_handler, admin_cancel_job_handler,
+        admin_discard_job_handler, admin_held_jobs_handler, admin_invalidate_cache_handler,
+        admin_overview_handler, admin_print_backend_handler, admin_release_job_handler,
+        admin_reload_handler, AdminOverview, CacheTtlRequest, PrinterStatus, ReloadResponse,
+    },
+    agents::{
+        agent_heartbeat_handler, agent_job_result_handler, agent_next_job_handler,
+        agent_register_handler, list_agents_handler,
+    },
+    billing::{billing_handler, BillingFormat},
+    counters::next_counter_handler,
+    documents::{
+        estimate_handler, pdf_text_handler, proof_handler, upload_pdf_text_handler,
+        validate_pdf_handler, EstimateRequest, EstimateResponse, ProofRequest, TextRequest,
+        UploadTextForm, ValidateRequest,
+    },
+    errors::{ApiError, ErrorCode, ResponseMessage},
+    files::{download_file_handler, list_files_handler},
+    jobs::{
+        admin_reprint_job_handler, job_artifact_handler, job_artifact_metadata_handler,
+        job_detail_handler, list_jobs_handler, reprint_job_handler, JobDetail, ReprintRequest,
+    },
+    print::{
+        print_file_handler, upload_print_handler, PaperSize, PrintRequest, PrintResponse,
+        UploadPrintForm,
+    },
+    printers::{
+        discover_printers_handler, pause_printer_handler, printer_capabilities_handler,
+        printer_history_handler, printer_queue_handler, purge_printer_handler,
+        resume_printer_handler, test_page_handler, PrinterHistory, PrinterQueueDepth,
+        PrinterUsageBucket, QueueDepthReport,
+    },
+    tokens::{
+        issue_print_token_handler, redeem_print_token_handler, PrintTokenRequest,
+        PrintTokenResponse, RedeemPrintTokenRequest,
+    },
+    uploads::{
+        cancel_upload_handler, create_upload_handler, upload_chunk_handler, upload_status_handler,
+        CreateUploadRequest,
+    },
+};
+use crate::{
+    agent::{AgentJobResult, AgentRegistration, AgentSite, HeldJob, RemoteJob},
+    backend::{PrintBackendListing, RecordedJob},
+    billing::BillingLine,
+    capabilities::PrinterCapabilities,
+    client_identity::ClientIdentity,
+    counters::CounterValue,
+    discovery::DiscoveredPrinter,
+    estimate::SheetEstimate,
+    history::{JobEvent, JobRecord, JobStage, JobStatus},
+    hooks::HookContext,
+    job_settings::{Duplex, IppJobAttributes, JobSettings, PunchPosition, StaplePosition},
+    locale::{Digits, Era, Language, LocaleFormat},
+    multipart::MAX_UPLOAD_BYTES,
+    pdf::{FitPolicy, OptimizeOptions, PageSet},
+    preflight::{PdfPageInfo, PdfValidationReport},
+    printer_history::{HistoryBucket, PrinterUsage},
+    proof::{PageProof, PrinterProfile, SoftProofReport},
+    storage::FileInfo,
+    text::{PageText, PdfText},
+    transform::{PageNumberPosition, PaperName, TransformStep},
+    transform_cache::{CachedTransformInfo, TransformCacheListing},
+    uploads::UploadStatus,
+};
+use actix_web::web;
+use utoipa::OpenApi;
+
+/// กำหนดโครงสร้างเอกสาร OpenAPI
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        print::print_file_handler,
+        print::upload_print_handler,
+        documents::validate_pdf_handler,
+        documents::proof_handler,
+        documents::pdf_text_handler,
+        documents::upload_pdf_text_handler,
+        uploads::create_upload_handler,
+        uploads::upload_status_handler,
+        uploads::upload_chunk_handler,
+        uploads::cancel_upload_handler,
+        files::list_files_handler,
+        files::download_file_handler,
+        printers::printer_capabilities_handler,
+        printers::printer_history_handler,
+        printers::test_page_handler,
+        printers::pause_printer_handler,
+        printers::resume_printer_handler,
+        printers::purge_printer_handler,
+        printers::discover_printers_handler,
+        printers::printer_queue_handler,
+        agents::agent_next_job_handler,
+        agents::agent_job_result_handler,
+        agents::agent_register_handler,
+        agents::agent_heartbeat_handler,
+        agents::list_agents_handler,
+        admin::admin_overview_handler,
+        admin::admin_cancel_job_handler,
+        admin::admin_held_jobs_handler,
+        admin::admin_release_job_handler,
+        admin::admin_discard_job_handler,
+        admin::admin_cache_handler,
+        admin::admin_print_backend_handler,
+        admin::admin_invalidate_cache_handler,
+        admin::admin_cache_ttl_handler,
+        jobs::admin_reprint_job_handler,
+        admin::admin_reload_handler,
+        jobs::reprint_job_handler,
+        jobs::list_jobs_handler,
+        jobs::job_detail_handler,
+        jobs::job_artifact_handler,
+        jobs::job_artifact_metadata_handler,
+        documents::estimate_handler,
+        tokens::issue_print_token_handler,
+        tokens::redeem_print_token_handler,
+        counters::next_counter_handler,
+        billing::billing_handler,
+        crate::index,
+        crate::readyz,
+        crate::json_schemas
+    ),
+    components(schemas(
+        RemoteJob,
+        TransformCacheListing,
+        PrintBackendListing,
+        RecordedJob,
+        CachedTransformInfo,
+        CacheTtlRequest,
+        JobSettings,
+        IppJobAttributes,
+        Duplex,
+        AgentJobResult,
+        AgentRegistration,
+        AgentSite,
+        PrintRequest,
+        PrintResponse,
+        UploadPrintForm,
+        CreateUploadRequest,
+        UploadStatus,
+        FileInfo,
+        ErrorCode,
+        PaperSize,
+        ValidateRequest,
+        PdfValidationReport,
+        PdfPageInfo,
+        ProofRequest,
+        SoftProofReport,
+        PrinterProfile,
+        PageProof,
+        TextRequest,
+        UploadTextForm,
+        PdfText,
+        PageText,
+        OptimizeOptions,
+        TransformStep,
+        PaperName,
+        PageNumberPosition,
+        PageSet,
+        LocaleFormat,
+        Era,
+        Digits,
+        Language,
+        FitPolicy,
+        StaplePosition,
+        PunchPosition,
+        ResponseMessage,
+        PrinterCapabilities,
+        HistoryBucket,
+        PrinterUsage,
+        PrinterUsageBucket,
+        PrinterHistory,
+        DiscoveredPrinter,
+        QueueDepthReport,
+        PrinterQueueDepth,
+        ReprintRequest,
+        AdminOverview,
+        HeldJob,
+        ReloadResponse,
+        PrinterStatus,
+        JobRecord,
+        JobDetail,
+        JobEvent,
+        JobStage,
+        ClientIdentity,
+        HookContext,
+        JobStatus,
+        CounterValue,
+        EstimateRequest,
+        EstimateResponse,
+        SheetEstimate,
+        PrintTokenRequest,
+        PrintTokenResponse,
+        RedeemPrintTokenRequest,
+        BillingLine,
+        BillingFormat
+    )),
+    tags(
+        (name = "Printing", description = "Endpoints สำหรับการดำเนินการสั่งพิมพ์ไฟล์และแปลงขนาด"),
+        (name = "Uploads", description = "Endpoints สำหรับอัปโหลดไฟล์ขนาดใหญ่แบบแบ่ง chunk และอัปโหลดต่อได้"),
+        (name = "Files", description = "Endpoints สำหรับตรวจสอบไฟล์ใน ./printable_files"),
+        (name = "Printers", description = "Endpoints สำหรับตรวจสอบเครื่องพิมพ์"),
+        (name = "Agents", description = "Endpoints สำหรับ agent ที่สาขาดึงงานพิมพ์จาก central server"),
+        (name = "Admin", description = "Endpoints สำหรับหน้า Dashboard ของผู้ดูแลระบบ (/admin)"),
+        (name = "Counters", description = "Endpoints สำหรับออกเลขที่ต่อเนื่อง เช่น บัตรคิวและเลขที่ใบกำกับภาษี")
+    )
+)]
+pub struct ApiDoc;
+
+/// ลงทะเบียน endpoint ของ API v1 (ใช้กับ scope `/api/v1` และ `/api` สำหรับ client รุ่นเดิม)
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    // ให้ request ที่อ่านไม่ได้ตอบกลับด้วยรูปแบบเดียวกับ Error อื่นๆ แทนข้อความธรรมดาของ actix-web
+    cfg.app_data(web::PayloadConfig::new(MAX_UPLOAD_BYTES))
+        .app_data(web::JsonConfig::default().error_handler(|e, _| {
+            ApiError::bad_request(ErrorCode::InvalidRequest, e.to_string()).into()
+        }))
+        .app_data(web::QueryConfig::default().error_handler(|e, _| {
+            ApiError::bad_request(ErrorCode::InvalidRequest, e.to_string()).into()
+        }))
+        .app_data(web::PathConfig::default().error_handler(|e, _| {
+            ApiError::bad_request(ErrorCode::InvalidRequest, e.to_string()).into()
+        }))
+        .service(print_file_handler)
+        .service(upload_print_handler)
+        .service(validate_pdf_handler)
+        .service(proof_handler)
+        .service(pdf_text_handler)
+        .service(upload_pdf_text_handler)
+        .service(create_upload_handler)
+        .service(upload_status_handler)
+        .service(upload_chunk_handler)
+        .service(cancel_upload_handler)
+        .service(list_files_handler)
+        .service(download_file_handler)
+        .service(printer_capabilities_handler)
+        .service(printer_history_handler)
+        .service(test_page_handler)
+        .service(pause_printer_handler)
+        .service(resume_printer_handler)
+        .service(purge_printer_handler)
+        .service(discover_printers_handler)
+        .service(printer_queue_handler)
+        .service(agent_next_job_handler)
+        .service(agent_job_result_handler)
+        .service(agent_register_handler)
+        .service(agent_heartbeat_handler)
+        .service(list_agents_handler)
+        .service(admin_overview_handler)
+        .service(admin_cancel_job_handler)
+        .service(admin_held_jobs_handler)
+        .service(admin_release_job_handler)
+        .service(admin_discard_job_handler)
+        .service(admin_cache_handler)
+        .service(admin_print_backend_handler)
+        .service(admin_invalidate_cache_handler)
+        .service(admin_cache_ttl_handler)
+        .service(admin_reprint_job_handler)
+        .service(admin_reload_handler)
+        .service(reprint_job_handler)
+        .service(list_jobs_handler)
+        .service(job_detail_handler)
+        .service(job_artifact_handler)
+        .service(job_artifact_metadata_handler)
+        .service(estimate_handler)
+        .service(issue_print_token_handler)
+        .service(redeem_print_token_handler)
+        .service(next_counter_handler)
+        .service(billing_handler);
+}
@@ -0,0 +1,1555 @@
+// ----------------------------------------------------------------------
+//                            API v1 PRINTING
+// ----------------------------------------------------------------------
+
+use super::{
+    access::require_tenant,
+    errors::{transform_error, user_context_error, ApiError, ErrorCode, SuccessMessage},
+};
+use crate::{
+    agent::{AgentRegistry, RemoteJobQueue},
+    archive::archive_job,
+    client_identity::{client_identity, client_suffix, resolve_client_hostname, ClientIdentity},
+    config::{AppConfig, SharedConfig},
+    counters::{CounterStore, JobCounters},
+    crypto::write_job_file,
+    disk::DiskMonitor,
+    dispatch::{PrintDispatcher, PrinterBacklog},
+    history::{retain_job_artifact, JobHistory, JobRecord, JobStage, JobStatus, JobTimeline},
+    hooks::{pre_submit_hook, spawn_post_complete_hook, HookContext},
+    ipp::IPP_DRIVER_NAME,
+    job_settings::{
+        dispatch_print_job, job_settings, resolve_printer, tray_names, Duplex, Finishing,
+        JobSettings, PunchPosition, StaplePosition, MAX_COPIES, MAX_METADATA_ENTRIES,
+        MAX_METADATA_KEY_CHARS, MAX_METADATA_VALUE_CHARS,
+    },
+    locale::Language,
+    multipart::{parse_multipart, upload_filename},
+    notify::{job_target, notify_job_expired, notify_job_failed},
+    output::{output_target, OutputTarget},
+    pdf::{
+        resize_pdf_to_a6, FitPolicy, OptimizeOptions, PageSet, TransformOptions, TransformReport,
+        A6_HEIGHT_PTS, A6_WIDTH_PTS, PTS_PER_MM,
+    },
+    sessions::{UserContextError, MAX_WINDOWS_USER_CHARS},
+    storage::{hex_string, write_staged_file, SourceFile, Storage},
+    tenants::{files_dir, find_tenant, is_plain_file_name, source_dir, Tenant},
+    transform::{run_transform_pipeline, validate_transform_steps, TransformStep},
+    transform_cache::TransformCache,
+    watermark::watermark_steps,
+};
+use actix_web::{http::StatusCode, post, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// โครงสร้างสำหรับรับข้อมูลจาก HTTP Request (JSON)
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({"filename": "invoice_original.pdf", "printer_name": "Office_LaserJet"}))]
+pub(super) struct PrintRequest {
+    /// ชื่อไฟล์ PDF ต้นฉบับที่จะค้นหาในโฟลเดอร์ ./printable_files (หรือในโฟลเดอร์ของ `source`)
+    pub(super) filename: String,
+    /// ชื่อแหล่งไฟล์ใน `sources` ของ config.json เช่น `invoices` (ไม่ระบุ = ./printable_files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "invoices")]
+    pub(super) source: Option<String>,
+    /// ชื่อผู้สั่งพิมพ์ ใช้แทน `{user}` ใน `watermarks` ของ config.json (ไม่ระบุ = ชื่อ tenant)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "somchai")]
+    user: Option<String>,
+    /// ชื่อเครื่องพิมพ์ปลายทางที่ติดตั้งในระบบ (ไม่ระบุ = ใช้ `default_printer` ใน config.json หรือเครื่องพิมพ์เริ่มต้นของระบบ)
+    #[serde(default)]
+    pub(super) printer_name: Option<String>,
+    /// รหัสผ่านสำหรับเปิดไฟล์ PDF ที่ถูกเข้ารหัส (ไม่ต้องระบุหากไฟล์ไม่ได้เข้ารหัส)
+    #[serde(default, skip_serializing)]
+    pub(super) pdf_password: Option<String>,
+    /// ลดขนาดไฟล์ก่อนส่งไปยังเครื่องพิมพ์ (ไม่ระบุ = ไม่ลดขนาด)
+    #[serde(default)]
+    optimize: Option<OptimizeOptions>,
+    /// แปลงเอกสารเป็นสีเทาก่อนพิมพ์ เพื่อไม่ให้เครื่องพิมพ์สีใช้หมึกสี
+    #[serde(default)]
+    grayscale: bool,
+    /// หมุนหน้าตามเข็มนาฬิกาก่อนย่อขนาด (0, 90, 180 หรือ 270 องศา)
+    #[serde(default)]
+    #[schema(example = 90)]
+    rotate: u16,
+    /// หมุนเพิ่มอีก 90 องศาเฉพาะหน้าที่หมุนแล้วย่อได้ใหญ่กว่า (เช่น หน้าแนวนอนในเอกสารแนวตั้ง)
+    #[serde(default)]
+    auto_rotate: bool,
+    /// ระยะขอบว่างรอบหน้ากระดาษ (มม.) เนื้อหาจะถูกย่อให้อยู่ภายในขอบนี้
+    #[serde(default)]
+    margin_mm: f32,
+    /// เลื่อนเนื้อหาในแนวนอน (มม.) ค่าบวก = เลื่อนไปทางขวา
+    #[serde(default)]
+    offset_x_mm: f32,
+    /// เลื่อนเนื้อหาในแนวตั้ง (มม.) ค่าบวก = เลื่อนขึ้นด้านบน
+    #[serde(default)]
+    offset_y_mm: f32,
+    /// ระยะ bleed (มม.) ที่ขยายเนื้อหาเลยขอบตัด A6 ออกไป สำหรับงานฉลากไดคัท
+    #[serde(default)]
+    bleed_mm: f32,
+    /// วาด crop mark ที่มุมของขอบตัด (หน้ากระดาษจะใหญ่กว่า A6 เพื่อให้มีที่วาด)
+    #[serde(default)]
+    crop_marks: bool,
+    /// ถาดกระดาษที่ต้องการ เช่น `"Tray 2"`, `"Manual"` หรือหมายเลขถาด `"2"` (ไม่ระบุ = ถาดเริ่มต้นของเครื่อง)
+    #[serde(default)]
+    #[schema(example = "Tray 2")]
+    tray: Option<String>,
+    /// จำนวนชุดที่พิมพ์ (1 ถึง 999)
+    #[serde(default = "default_copies")]
+    #[schema(example = 3)]
+    pub(super) copies: u32,
+    /// เรียงชุดเมื่อพิมพ์หลายชุด (ไม่ระบุ = ค่าเริ่มต้นของเครื่องพิมพ์)
+    #[serde(default)]
+    collate: Option<bool>,
+    /// ตำแหน่งเย็บกระดาษ (เครื่องพิมพ์ต้องมี finisher)
+    #[serde(default)]
+    staple: Option<StaplePosition>,
+    /// เจาะรูกระดาษ (เครื่องพิมพ์ต้องมี finisher)
+    #[serde(default)]
+    punch: Option<PunchPosition>,
+    /// พิมพ์สองหน้า: `off`, `long_edge` หรือ `short_edge` (ไม่ระบุ = ค่าเริ่มต้นของเครื่องพิมพ์)
+    #[serde(default)]
+    pub(super) duplex: Option<Duplex>,
+    /// option เพิ่มเติมที่ส่งให้ระบบพิมพ์โดยตรง เช่น `{"print-quality": "5"}` (CUPS เท่านั้น)
+    #[serde(default)]
+    raw_properties: HashMap<String, String>,
+    /// ส่งงานไปพิมพ์ที่ agent ของสาขาแทนเครื่องพิมพ์ในเครื่องนี้ (ต้องตรงกับ key ใน `agents` ของ config.json)
+    #[serde(default)]
+    #[schema(example = "branch-01")]
+    pub(super) agent_id: Option<String>,
+    /// ส่งงานในนามของผู้ใช้ Windows ที่เข้าสู่ระบบอยู่ (`DOMAIN\user` หรือ `user`) เพื่อใช้เครื่องพิมพ์ที่ผู้ใช้เชื่อมต่อไว้
+    /// และให้งานใน spooler เป็นของผู้ใช้ (ต้องเปิด `user_context_printing` ใน config.json)
+    #[serde(default)]
+    #[schema(example = "CONTOSO\\somchai")]
+    windows_user: Option<String>,
+    /// พิมพ์เฉพาะหน้าคี่ (`odd`) หรือหน้าคู่ (`even`) ของไฟล์ต้นฉบับ สำหรับพิมพ์สองหน้าด้วยมือ
+    /// กับเครื่องพิมพ์ที่ไม่มีชุดพิมพ์สองหน้า (ไม่ระบุ = `all`)
+    #[serde(default)]
+    page_set: PageSet,
+    /// พิมพ์จากหน้าสุดท้ายไปหน้าแรก (ทำหลังเลือกหน้าตาม `page_set`)
+    #[serde(default)]
+    reverse: bool,
+    /// ขั้นตอนแปลงไฟล์ที่ทำตามลำดับแทนการแปลงเป็น A6 แบบเดิม เช่น
+    /// `[{"op": "rotate", "deg": 90}, {"op": "resize", "paper": "A6"}, {"op": "stamp", "text": "COPY"}]`
+    /// (ใช้ร่วมกับ `grayscale`, `rotate`, `auto_rotate`, `margin_mm`, `offset_*`, `bleed_mm` และ `crop_marks` ไม่ได้)
+    #[serde(default)]
+    transforms: Vec<TransformStep>,
+    /// แปลงและส่งพิมพ์ในหน่วยความจำทั้งหมด โดยไม่เขียนไฟล์ผลลัพธ์ลงดิสก์
+    /// (งานนี้จะไม่ถูกเก็บไว้สำหรับพิมพ์ซ้ำหรือในคลัง เหมาะกับเอกสารที่เป็นความลับ)
+    #[serde(default)]
+    pub(super) in_memory: bool,
+    /// วิธีจัดการหน้าที่เล็กกว่า A6: `error` (ค่าเริ่มต้น ปฏิเสธงาน), `shrink_only` (วางขนาดเดิมไว้กลางหน้า)
+    /// หรือ `allow_upscale` (ขยายให้พอดี)
+    #[serde(default)]
+    fit_policy: FitPolicy,
+    /// กำหนดเวลาที่ต้องพิมพ์ (RFC 3339) งานที่ยังไม่ได้พิมพ์เมื่อถึงเวลานี้จะถูกยกเลิกด้วยสถานะ `expired`
+    #[schema(example = "2024-05-01T09:45:00+07:00")]
+    expires_at: Option<String>,
+    /// พิมพ์แม้เป็นเอกสารเดียวกับงานที่เพิ่งส่งไปยังเครื่องพิมพ์เดิม (เมื่อตั้งค่า `duplicates.reject`)
+    #[serde(default)]
+    force: bool,
+    /// ข้อมูลที่เก็บไว้กับงาน ส่งให้ hook และค้นหาได้ใน `GET /api/v1/jobs?metadata.<key>=<value>`
+    /// (ค่าเป็นข้อความเท่านั้น สูงสุด 32 key)
+    #[serde(default)]
+    #[schema(example = json!({"order_id": "SO-2024-0042", "branch": "BKK-03"}))]
+    pub(super) metadata: BTreeMap<String, String>,
+    /// tenant ที่ส่งงาน (กำหนดจาก API key ไม่ได้อ่านจาก body)
+    #[serde(skip)]
+    pub(super) tenant: Option<String>,
+    /// 8 ตัวแรกของ SHA-256 ของ API key ที่ส่งงาน สำหรับแยกยอดใน `GET /api/v1/billing`
+    #[serde(skip)]
+    pub(super) api_key: Option<String>,
+    /// เครื่องที่ส่งงาน (กำหนดจาก connection และ `trusted_proxies` ไม่ได้อ่านจาก body)
+    #[serde(skip)]
+    pub(super) client: Option<ClientIdentity>,
+    /// ภาษาของ request สำหรับวันที่ที่พิมพ์ลงบนเอกสาร (จาก `Accept-Language`)
+    #[serde(skip)]
+    pub(super) language: Language,
+}
+
+pub(super) fn default_copies() -> u32 {
+    1
+}
+
+/// ขนาดกระดาษของไฟล์ที่ส่งพิมพ์
+#[derive(Serialize, ToSchema)]
+pub(super) struct PaperSize {
+    #[schema(example = "A6")]
+    pub(super) name: String,
+    /// ความกว้าง (มม.) รวม bleed และพื้นที่ crop mark
+    #[schema(example = 105.0)]
+    pub(super) width_mm: f32,
+    /// ความสูง (มม.) รวม bleed และพื้นที่ crop mark
+    #[schema(example = 148.0)]
+    pub(super) height_mm: f32,
+}
+
+/// Response เมื่อแปลงไฟล์และส่งงานพิมพ์ (หรือเข้าคิวของ agent) สำเร็จ
+#[derive(Serialize, ToSchema)]
+pub(super) struct PrintResponse {
+    /// `success` หรือ `queued` เมื่อส่งงานเข้าคิวของ agent
+    #[schema(example = "success")]
+    status: String,
+    message: String,
+    /// ข้อความเดิมภาษาอังกฤษพร้อมรายละเอียด เมื่อ `message` ถูกแปลตาม `Accept-Language`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    /// job id ของระบบพิมพ์ (Windows spooler/CUPS) หรือ job id ในคิวของ agent เมื่อระบุ `agent_id`
+    #[schema(example = 42)]
+    job_id: u64,
+    /// เครื่องพิมพ์ที่ใช้จริง (`null` เมื่อส่งงานให้ agent โดยไม่ได้ระบุ `printer_name`)
+    #[schema(example = "Office_LaserJet")]
+    printer_name: Option<String>,
+    /// agent ที่รับงานไปพิมพ์
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent_id: Option<String>,
+    /// job id ของทุกส่วนตามลำดับ เมื่องานถูกแบ่งตาม `job_split` ใน config.json (`job_id` = ส่วนแรก)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[schema(example = json!([42, 43, 44]))]
+    split_job_ids: Vec<u64>,
+    /// จำนวนหน้าของไฟล์ต้นฉบับ
+    source_pages: usize,
+    /// จำนวนหน้าของไฟล์ที่ส่งพิมพ์
+    output_pages: usize,
+    paper_size: PaperSize,
+    /// เวลาที่ใช้ตั้งแต่รับ request จนส่งงานเสร็จ (มิลลิวินาที)
+    #[schema(example = 350)]
+    processing_ms: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/print",
+    tag = "Printing",
+    request_body = PrintRequest,
+    responses(
+        (status = 200, description = "แปลงและส่งคำสั่งพิมพ์สำเร็จ", body = PrintResponse),
+        (status = 202, description = "แปลงสำเร็จและเก็บงานไว้ในคิวของ agent (เมื่อระบุ `agent_id`)", body = PrintResponse),
+        (status = 400, description = "ค่าใน request ไม่ถูกต้อง (`error_code`: `INVALID_REQUEST`, `FILE_NOT_FOUND`, `PRINTER_NOT_FOUND`, `UNKNOWN_AGENT`, `PDF_PASSWORD_REQUIRED`, `PDF_PASSWORD_INVALID`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 403, description = "ผู้ใช้ใน `windows_user` ไม่อยู่ใน `windows_users` ของ tenant (`error_code`: `WINDOWS_USER_NOT_ALLOWED`)", body = ResponseMessage),
+        (status = 409, description = "เอกสารเดียวกันเพิ่งถูกส่งไปยังเครื่องพิมพ์เดิม (`error_code`: `DUPLICATE_JOB`, ส่ง `force: true` เพื่อพิมพ์ซ้ำ) หรือผู้ใช้ใน `windows_user` ไม่ได้เข้าสู่ระบบอยู่ (`USER_NOT_LOGGED_ON`)", body = ResponseMessage),
+        (status = 410, description = "เลยกำหนด `expires_at` แล้ว งานจะไม่ถูกพิมพ์ (`error_code`: `JOB_EXPIRED`)", body = ResponseMessage),
+        (status = 429, description = "tenant ส่งงานครบโควตาของวันนี้แล้ว (`error_code`: `QUOTA_EXCEEDED`) หรือมีงานค้างเกิน `backpressure` (`QUEUE_FULL` พร้อม header `Retry-After`)", body = ResponseMessage),
+        (status = 500, description = "เกิดข้อผิดพลาดในการประมวลผลหรือสั่งพิมพ์ (`error_code`: `PDF_PARSE_ERROR`, `PDF_TRANSFORM_ERROR`, `FILE_IO_ERROR`, `SPOOLER_ERROR`)", body = ResponseMessage),
+        (status = 503, description = "Print Spooler ของเครื่องนี้หยุดทำงาน งานไม่ถูกพิมพ์ (`error_code`: `SPOOLER_UNAVAILABLE`)", body = ResponseMessage),
+        (status = 507, description = "พื้นที่ว่างบนดิสก์ของ server ไม่พอ (`error_code`: `INSUFFICIENT_STORAGE`)", body = ResponseMessage)
+    )
+)]
+#[post("/print")]
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn print_file_handler(
+    request: HttpRequest,
+    req: web::Json<PrintRequest>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    registry: web::Data<AgentRegistry>,
+    history: web::Data<JobHistory>,
+    counters: web::Data<CounterStore>,
+    disk: web::Data<DiskMonitor>,
+    dispatcher: web::Data<PrintDispatcher>,
+    transform_cache: web::Data<TransformCache>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let started_at = Instant::now();
+    let config = config.get();
+    let mut req = req.into_inner();
+    let tenant = require_tenant(&request, &config)?;
+    req.tenant = tenant.map(|tenant| tenant.id.to_string());
+    req.api_key = tenant.and_then(|tenant| tenant.api_key_id());
+    req.client = client_identity(&request, &config);
+    if !req.in_memory {
+        require_disk_space(&disk)?;
+    }
+    req.language = Language::of_request(request.headers(), &config);
+    // งานที่รอคิวของเครื่องพิมพ์ไม่ควรกัน worker ที่รับ request ของเครื่องพิมพ์อื่น
+    web::block(move || {
+        resolve_client_hostname(&mut req.client, &config);
+        print_document(
+            &req,
+            &config,
+            &queue,
+            &registry,
+            &history,
+            &counters,
+            &transform_cache,
+            storage.get_ref(),
+            &dispatcher,
+            false,
+            started_at,
+        )
+    })
+    .await
+    .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?
+    .map(|(status, response)| HttpResponse::build(status).json(response))
+}
+
+/// แปลงไฟล์ `req.filename` เป็น A6 แล้วส่งพิมพ์ หรือเก็บไว้ในคิวของ agent และบันทึกผลในประวัติงาน
+/// (`discard_output` = ลบไฟล์ที่ส่งพิมพ์ทิ้งหลังส่งงาน แม้ไม่ได้เปิด `output.temp_files`)
+#[allow(clippy::too_many_arguments)]
+pub(super) fn print_document(
+    req: &PrintRequest,
+    config: &AppConfig,
+    queue: &RemoteJobQueue,
+    registry: &AgentRegistry,
+    history: &JobHistory,
+    counters: &CounterStore,
+    transform_cache: &TransformCache,
+    storage: &dyn Storage,
+    dispatcher: &PrintDispatcher,
+    discard_output: bool,
+    started_at: Instant,
+) -> Result<(StatusCode, PrintResponse), ApiError> {
+    let id = history.reserve_id();
+    history.start_trace(id, started_at);
+    let output = (!req.in_memory).then(|| {
+        let dir = files_dir(req.tenant.as_deref());
+        // ตั้งชื่อไฟล์ผลลัพธ์จากชื่อไฟล์เท่านั้น จึงเขียนได้เฉพาะในโฟลเดอร์ของ tenant
+        // (ไฟล์จากแหล่งไฟล์อื่นเขียนผลลัพธ์ไว้ใน ./printable_files เช่นกัน)
+        let filename = req.filename.rsplit(['/', '\\']).next().unwrap_or_default();
+        output_target(&config.output, &dir, filename, id)
+    });
+    let result = submit_document(
+        req,
+        config,
+        queue,
+        registry,
+        history,
+        counters,
+        transform_cache,
+        storage,
+        dispatcher,
+        id,
+        output.as_ref(),
+        started_at,
+    );
+    if let Some(output) = output.filter(|output| discard_output || output.name.is_none()) {
+        if output.path.exists() {
+            if let Err(e) = std::fs::remove_file(&output.path) {
+                eprintln!("Failed to remove {}: {:?}", output.path.display(), e);
+            }
+        }
+    }
+    if let Err(e) = &result {
+        let record = JobRecord {
+            id,
+            job_id: None,
+            filename: req.filename.clone(),
+            printer_name: req.printer_name.clone(),
+            agent_id: req.agent_id.clone(),
+            tenant: req.tenant.clone(),
+            api_key: req.api_key.clone(),
+            client: req.client.clone(),
+            status: if matches!(e.code, ErrorCode::JobExpired) {
+                JobStatus::Expired
+            } else {
+                JobStatus::Failed
+            },
+            submitted_at: chrono::Local::now().to_rfc3339(),
+            expires_at: req.expires_at.clone(),
+            pages: 0,
+            message: e.message.clone(),
+            content_sha256: None,
+            duplicate_of: None,
+            output_file: None,
+            reprintable: false,
+            metadata: req.metadata.clone(),
+            job_name: String::new(),
+            settings: JobSettings::default(),
+            timeline: JobTimeline::default(),
+        };
+        history.trace(
+            id,
+            JobStage::of_status(record.status),
+            Some(e.message.clone()),
+        );
+        spawn_post_complete_hook(&config.hooks, &record);
+        history.record(record.clone());
+        // คำขอที่ไม่ถูกต้องเป็นปัญหาของผู้ส่งงาน จึงแจ้งเตือนเฉพาะความผิดพลาดฝั่ง server และเครื่องพิมพ์
+        if record.status == JobStatus::Expired {
+            notify_job_expired(&config.notifications, &record);
+        } else if e.status.is_server_error() || matches!(e.code, ErrorCode::PrinterNotFound) {
+            notify_job_failed(&config.notifications, history, &record);
+        }
+    }
+    result
+}
+
+/// บันทึกงานในประวัติ แล้วเก็บไฟล์ที่ส่งพิมพ์ไว้สำหรับพิมพ์ซ้ำและในคลัง (เมื่อตั้งค่า `archive`)
+/// `output_path` เป็น `None` สำหรับงานที่ทำในหน่วยความจำ ซึ่งจะไม่เก็บไฟล์ใดๆ
+pub(super) fn store_job_output(
+    config: &AppConfig,
+    history: &JobHistory,
+    mut record: JobRecord,
+    request: &impl Serialize,
+    output_path: Option<&Path>,
+) -> u64 {
+    record.id = history.record(record.clone());
+    let Some(output_path) = output_path else {
+        return record.id;
+    };
+    retain_job_artifact(config, record.id, output_path);
+    if let Some(archive) = &config.archive {
+        if let Err(e) = archive_job(
+            archive,
+            config.at_rest_key.as_ref(),
+            &record,
+            request,
+            output_path,
+        ) {
+            eprintln!("Error archiving job {}: {:?}", record.id, e);
+        }
+    }
+    record.id
+}
+
+/// ให้ hook `pre_submit` ตรวจสอบงานก่อนส่ง
+pub(super) fn check_pre_submit(config: &AppConfig, context: &HookContext) -> Result<(), ApiError> {
+    pre_submit_hook(&config.hooks, context).map_err(|reason| {
+        println!(
+            "Job {} rejected by pre_submit hook: {}",
+            context.job_name, reason
+        );
+        ApiError {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            code: ErrorCode::JobRejected,
+            message: reason,
+            retry_after: None,
+        }
+    })
+}
+
+/// ตรวจ option การแปลงเอกสารของงานพิมพ์ (transforms, rotate, margin_mm และ bleed_mm)
+pub(super) fn validate_print_options(
+    req: &PrintRequest,
+    config: &AppConfig,
+) -> Result<(), ApiError> {
+    if !req.transforms.is_empty() {
+        let uses_fixed_options = req.grayscale
+            || req.rotate != 0
+            || req.auto_rotate
+            || req.margin_mm != 0.0
+            || req.offset_x_mm != 0.0
+            || req.offset_y_mm != 0.0
+            || req.bleed_mm != 0.0
+            || req.crop_marks
+            || req.fit_policy != FitPolicy::Error;
+        if uses_fixed_options {
+            return Err(ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                "transforms cannot be combined with grayscale, rotate, auto_rotate, margin_mm, offset_x_mm, offset_y_mm, bleed_mm, crop_marks or fit_policy (set fit_policy on the resize step)".to_string(),
+            ));
+        }
+        validate_transform_steps(&req.transforms)
+            .map_err(|e| ApiError::bad_request(ErrorCode::InvalidRequest, e))?;
+        if config.stamp_font.is_none() {
+            let tenant_locale = find_tenant(config, req.tenant.as_deref())
+                .and_then(|tenant| tenant.config.locale.as_ref());
+            for (position, step) in req.transforms.iter().enumerate() {
+                let is_text_step = matches!(
+                    step,
+                    TransformStep::Stamp { .. }
+                        | TransformStep::PageNumbers { .. }
+                        | TransformStep::Text { .. }
+                );
+                let locale = step.locale().cloned().unwrap_or_default();
+                if is_text_step && locale.or(tenant_locale).requires_stamp_font(req.language) {
+                    return Err(ApiError::bad_request(
+                        ErrorCode::InvalidRequest,
+                        format!(
+                            "transforms[{}]: Thai digits or month names require stamp_font in config.json",
+                            position
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(user) = &req.windows_user {
+        if !config.user_context_printing {
+            return Err(user_context_error(UserContextError::Disabled));
+        }
+        if user.trim().is_empty()
+            || user.chars().count() > MAX_WINDOWS_USER_CHARS
+            || user.chars().any(char::is_control)
+        {
+            return Err(ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                format!(
+                    "Invalid windows_user: {:?} (expected DOMAIN\\user or user, up to {} characters)",
+                    user, MAX_WINDOWS_USER_CHARS
+                ),
+            ));
+        }
+        if !find_tenant(config, req.tenant.as_deref())
+            .is_none_or(|tenant| tenant.allows_windows_user(user))
+        {
+            return Err(user_context_error(UserContextError::NotAllowed(
+                user.clone(),
+            )));
+        }
+        // agent ตรวจ `user_context_printing` ของตัวเองตอนพิมพ์
+        if req.agent_id.is_none() && !cfg!(windows) {
+            return Err(user_context_error(UserContextError::Unsupported));
+        }
+    }
+
+    if ![0, 90, 180, 270].contains(&req.rotate) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid rotate value: {} (expected 0, 90, 180 or 270)",
+                req.rotate
+            ),
+        ));
+    }
+
+    let max_margin_mm = A6_WIDTH_PTS.min(A6_HEIGHT_PTS) / PTS_PER_MM / 2.0;
+    if !(0.0..max_margin_mm).contains(&req.margin_mm) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid margin_mm value: {} (expected 0 to less than {:.1})",
+                req.margin_mm, max_margin_mm
+            ),
+        ));
+    }
+
+    if req.bleed_mm < 0.0 {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid bleed_mm value: {} (must not be negative)",
+                req.bleed_mm
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// key ของผลการแปลงของงานใน `transform_cache` และ SHA-256 ของไฟล์ต้นฉบับ
+/// (`None` = งานนี้ต้องแปลงใหม่ทุกครั้ง)
+pub(super) fn transform_cache_key(
+    req: &PrintRequest,
+    config: &AppConfig,
+    source: &[u8],
+) -> Option<(String, String)> {
+    // ข้อความที่เปลี่ยนทุกงาน ไฟล์ที่ต้องใช้รหัสผ่าน และงานที่ห้ามเก็บไว้ ไม่ใช้ cache
+    let changes_per_job = req
+        .transforms
+        .iter()
+        .filter_map(TransformStep::text)
+        .any(|text| text.contains("{date}") || text.contains("{counter:"));
+    if changes_per_job || req.pdf_password.is_some() || req.in_memory {
+        return None;
+    }
+    let source_sha256 = hex_string(&Sha256::digest(source));
+    let options = serde_json::json!({
+        "source_sha256": source_sha256,
+        "optimize": req.optimize,
+        "grayscale": req.grayscale,
+        "rotate": req.rotate,
+        "auto_rotate": req.auto_rotate,
+        "margin_mm": req.margin_mm,
+        "offset_x_mm": req.offset_x_mm,
+        "offset_y_mm": req.offset_y_mm,
+        "bleed_mm": req.bleed_mm,
+        "crop_marks": req.crop_marks,
+        "fit_policy": req.fit_policy,
+        "page_set": req.page_set,
+        "reverse": req.reverse,
+        "transforms": req.transforms,
+        "language": req.language,
+        "locale": find_tenant(config, req.tenant.as_deref())
+            .and_then(|tenant| tenant.config.locale.as_ref()),
+        "font_fallbacks": config.font_fallbacks.iter().collect::<BTreeMap<_, _>>(),
+        "stamp_font": config.stamp_font,
+    });
+    let key = hex_string(&Sha256::digest(options.to_string().as_bytes()));
+    Some((key, source_sha256))
+}
+
+/// แปลงไฟล์ต้นฉบับ `source` ตาม option ของงานพิมพ์ (ย่อเป็น A6 หรือ `transforms`)
+/// แล้วตามด้วยขั้นตอนของ `watermarks` ที่บังคับใช้กับเครื่องพิมพ์ `printer_name` หรือ tenant ของงาน
+/// (`counters` = `None` สำหรับการดูตัวอย่าง ซึ่ง `{counter:...}` แสดงเป็น 0 และไม่ใช้เลขที่ของตัวนับ)
+///
+/// เมื่อเปิด `transform_cache` จะใช้ผลการแปลงเดิมของไฟล์และ option เดียวกันจาก `cache` (ก่อนใส่ลายน้ำ)
+pub(super) fn transform_source(
+    req: &PrintRequest,
+    config: &AppConfig,
+    printer_name: Option<&str>,
+    source: Vec<u8>,
+    counters: Option<&CounterStore>,
+    cache: &TransformCache,
+) -> Result<(Vec<u8>, TransformReport), ApiError> {
+    let counters = JobCounters::new(counters, req.tenant.as_deref());
+    let transform_options = TransformOptions {
+        pdf_password: req.pdf_password.as_deref(),
+        optimize: req.optimize.as_ref(),
+        font_fallbacks: &config.font_fallbacks,
+        grayscale: req.grayscale,
+        rotate: req.rotate,
+        auto_rotate: req.auto_rotate,
+        margin_mm: req.margin_mm,
+        offset_x_mm: req.offset_x_mm,
+        offset_y_mm: req.offset_y_mm,
+        bleed_mm: req.bleed_mm,
+        crop_marks: req.crop_marks,
+        fit_policy: req.fit_policy,
+        language: req.language,
+        locale: find_tenant(config, req.tenant.as_deref())
+            .and_then(|tenant| tenant.config.locale.as_ref()),
+        stamp_font: config.stamp_font.as_deref(),
+        page_set: req.page_set,
+        reverse: req.reverse,
+        counters: Some(&counters),
+    };
+    let cached = config.transform_cache.as_ref().and_then(|cache_config| {
+        transform_cache_key(req, config, &source).map(|key| (cache_config, key))
+    });
+    let hit = cached
+        .as_ref()
+        .and_then(|(_, (key, _))| cache.get(key, &req.filename));
+    let (file_data, mut report) = match hit {
+        Some(hit) => {
+            println!("Using cached transform of {}", req.filename);
+            hit
+        }
+        None => {
+            let transformed = if req.transforms.is_empty() {
+                resize_pdf_to_a6(source, &req.filename, &transform_options)
+            } else {
+                run_transform_pipeline(source, &req.filename, &req.transforms, &transform_options)
+            }
+            .map_err(|e| transform_error(&e, &req.filename))?;
+            if let Some((cache_config, (key, source_sha256))) = cached {
+                cache.insert(
+                    cache_config,
+                    key,
+                    &req.filename,
+                    req.tenant.as_deref(),
+                    source_sha256,
+                    &transformed.0,
+                    &transformed.1,
+                );
+            }
+            transformed
+        }
+    };
+
+    let watermarks = watermark_steps(
+        config,
+        printer_name,
+        req.tenant.as_deref(),
+        req.user.as_deref(),
+    );
+    if watermarks.is_empty() {
+        return Ok((file_data, report));
+    }
+    // ทำหลังการแปลงของ request เสมอ เพื่อให้ client ปิดหรือเปลี่ยนลายน้ำไม่ได้
+    let watermark_options = TransformOptions {
+        pdf_password: None,
+        optimize: None,
+        page_set: PageSet::All,
+        reverse: false,
+        ..transform_options
+    };
+    let (file_data, watermark_report) =
+        run_transform_pipeline(file_data, &req.filename, &watermarks, &watermark_options)
+            .map_err(|e| transform_error(&e, &req.filename))?;
+    println!(
+        "Applied {} watermark steps to {} for printer {}",
+        watermarks.len(),
+        req.filename,
+        printer_name.unwrap_or("(agent default)")
+    );
+    report.warnings.extend(watermark_report.warnings);
+    Ok((file_data, report))
+}
+
+/// ไฟล์ต้นฉบับ `filename` ในแหล่งไฟล์ `source` (ไม่ระบุ = ไฟล์ของ tenant ใน storage)
+pub(super) fn source_file(
+    config: &AppConfig,
+    tenant: Option<&str>,
+    source: Option<&str>,
+    filename: &str,
+) -> Result<SourceFile, ApiError> {
+    let Some(source) = source else {
+        // ไม่ให้ชื่อไฟล์พาไปยังโฟลเดอร์ของ tenant อื่นหรือนอก ./printable_files เช่น `../other/file.pdf`
+        if !is_plain_file_name(filename) {
+            return Err(ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                format!("Invalid filename: {}", filename),
+            ));
+        }
+        return Ok(SourceFile::Staged {
+            tenant: tenant.map(str::to_string),
+            name: filename.to_string(),
+        });
+    };
+    let Some(dir) = source_dir(config, tenant, source) else {
+        return Err(ApiError::bad_request(
+            ErrorCode::UnknownSource,
+            format!("Unknown source: {}", source),
+        ));
+    };
+    // ไม่ให้ชื่อไฟล์พาออกนอกโฟลเดอร์ของแหล่งไฟล์ เช่น `..\\other\\file.pdf`
+    let stays_inside = Path::new(filename)
+        .components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)));
+    if !stays_inside {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!("Invalid filename for source {}: {}", source, filename),
+        ));
+    }
+    Ok(SourceFile::Local(dir.join(filename)))
+}
+
+/// ตรวจว่ามีไฟล์ต้นฉบับ `file` อยู่จริง (ไม่พบ = `FILE_NOT_FOUND`)
+pub(super) fn require_source_file(
+    file: &SourceFile,
+    storage: &dyn Storage,
+    filename: &str,
+) -> Result<(), ApiError> {
+    match file.exists(storage) {
+        Ok(true) if !filename.is_empty() => Ok(()),
+        Ok(_) => Err(ApiError::bad_request(
+            ErrorCode::FileNotFound,
+            format!("File not found: {}", filename),
+        )),
+        Err(e) => Err(ApiError::internal(
+            ErrorCode::FileIoError,
+            format!("Failed to read {}: {}", filename, e),
+        )),
+    }
+}
+
+pub(super) fn validate_metadata(metadata: &BTreeMap<String, String>) -> Result<(), ApiError> {
+    if metadata.len() > MAX_METADATA_ENTRIES {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Too many metadata keys: {} (maximum {})",
+                metadata.len(),
+                MAX_METADATA_ENTRIES
+            ),
+        ));
+    }
+    for (key, value) in metadata {
+        if key.is_empty() || key.chars().count() > MAX_METADATA_KEY_CHARS {
+            return Err(ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                format!(
+                    "Invalid metadata key {:?} (expected 1 to {} characters)",
+                    key, MAX_METADATA_KEY_CHARS
+                ),
+            ));
+        }
+        if value.chars().count() > MAX_METADATA_VALUE_CHARS {
+            return Err(ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                format!(
+                    "Metadata value of {} is too long (maximum {} characters)",
+                    key, MAX_METADATA_VALUE_CHARS
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// ส่งงานหมายเลข `id` โดยเขียนไฟล์ที่ส่งพิมพ์ไว้ที่ `output` (`None` = ไม่เขียนลงดิสก์)
+#[allow(clippy::too_many_arguments)]
+pub(super) fn submit_document(
+    req: &PrintRequest,
+    config: &AppConfig,
+    queue: &RemoteJobQueue,
+    registry: &AgentRegistry,
+    history: &JobHistory,
+    counters: &CounterStore,
+    transform_cache: &TransformCache,
+    storage: &dyn Storage,
+    dispatcher: &PrintDispatcher,
+    id: u64,
+    output: Option<&OutputTarget>,
+    started_at: Instant,
+) -> Result<(StatusCode, PrintResponse), ApiError> {
+    let tenant = find_tenant(config, req.tenant.as_deref());
+    let original_file_path = source_file(
+        config,
+        req.tenant.as_deref(),
+        req.source.as_deref(),
+        &req.filename,
+    )?;
+    let output_name = output.and_then(|output| output.name.clone());
+    let output_path = output.map(|output| output.path.as_path());
+
+    validate_print_options(req, config)?;
+
+    if let Some(agent_id) = &req.agent_id {
+        if !config.agents.contains_key(agent_id)
+            || !tenant.is_none_or(|tenant| tenant.allows_agent(agent_id))
+        {
+            return Err(ApiError::bad_request(
+                ErrorCode::UnknownAgent,
+                format!("Unknown agent: {}", agent_id),
+            ));
+        }
+    }
+    // เครื่องพิมพ์ที่ tenant ใช้ไม่ได้ตอบเหมือนไม่พบ เพื่อไม่ให้รู้ว่ามีเครื่องนั้นอยู่
+    let printer_name = req
+        .printer_name
+        .clone()
+        .or_else(|| tenant.and_then(|tenant| tenant.config.default_printer.clone()));
+    if let Some(printer_name) = &printer_name {
+        if !tenant.is_none_or(|tenant| tenant.allows_printer(printer_name)) {
+            return Err(ApiError::bad_request(
+                ErrorCode::PrinterNotFound,
+                format!("Printer not found: {}", printer_name),
+            ));
+        }
+    }
+
+    let expires_at = match &req.expires_at {
+        Some(value) => Some(
+            chrono::DateTime::parse_from_rfc3339(value)
+                .map_err(|e| {
+                    ApiError::bad_request(
+                        ErrorCode::InvalidRequest,
+                        format!("Invalid expires_at {:?}: {}", value, e),
+                    )
+                })?
+                .with_timezone(&chrono::Local),
+        ),
+        None => None,
+    };
+    if let Some(expires_at) = expires_at.filter(|expires_at| *expires_at <= chrono::Local::now()) {
+        return Err(ApiError {
+            status: StatusCode::GONE,
+            code: ErrorCode::JobExpired,
+            message: format!(
+                "Job deadline {} has already passed",
+                expires_at.to_rfc3339()
+            ),
+            retry_after: None,
+        });
+    }
+
+    if !(1..=MAX_COPIES).contains(&req.copies) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid copies value: {} (expected 1 to {})",
+                req.copies, MAX_COPIES
+            ),
+        ));
+    }
+    validate_metadata(&req.metadata)?;
+
+    if let Some(tray) = &req.tray {
+        if tray_names(tray).is_none() {
+            return Err(ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                format!("Invalid tray name: {}", tray),
+            ));
+        }
+    }
+
+    require_source_file(&original_file_path, storage, &req.filename)?;
+    check_quota(tenant, history)?;
+
+    // งานที่พิมพ์ที่เครื่องนี้จองลำดับบนเครื่องพิมพ์ก่อนแปลงไฟล์ เพื่อให้ถึง spooler ตามลำดับที่รับ request
+    let local_printer = match &req.agent_id {
+        Some(_) => None,
+        None => {
+            require_spooler(dispatcher, config, printer_name.as_deref())?;
+            let printer = match &req.windows_user {
+                Some(user) => dispatcher
+                    .sessions
+                    .run_as(config, user, || {
+                        resolve_printer(printer_name.as_deref(), config)
+                    })
+                    .map_err(user_context_error)?,
+                None => resolve_printer(printer_name.as_deref(), config),
+            }
+            .map_err(|message| ApiError::bad_request(ErrorCode::PrinterNotFound, message))?;
+            if !tenant.is_none_or(|tenant| tenant.allows_printer(&printer.name)) {
+                return Err(ApiError::bad_request(
+                    ErrorCode::PrinterNotFound,
+                    format!("Printer not found: {}", printer.name),
+                ));
+            }
+            require_queue_capacity(dispatcher, config, &printer)?;
+            let ticket = dispatcher.reserve(&printer);
+            Some((printer, ticket))
+        }
+    };
+    history.trace(id, JobStage::Validated, None);
+
+    // 1. แปลงขนาด PDF เป็น A6 และบันทึกไฟล์ใหม่
+    let source = original_file_path
+        .read(storage, config.at_rest_key.as_ref())
+        .map_err(|e| {
+            ApiError::internal(
+                ErrorCode::FileIoError,
+                format!("Failed to read {}: {}", req.filename, e),
+            )
+        })?;
+    let content_sha256 = hex_string(&Sha256::digest(&source));
+    let printer_for_policy = match &local_printer {
+        Some((printer, _)) => Some(printer.name.as_str()),
+        None => printer_name.as_deref(),
+    };
+    let (file_data, report) = transform_source(
+        req,
+        config,
+        printer_for_policy,
+        source,
+        Some(counters),
+        transform_cache,
+    )?;
+
+    // 2. บันทึกไฟล์ A6 (ยกเว้นงานในหน่วยความจำ) แล้วส่งข้อมูลชุดเดียวกันไปพิมพ์โดยไม่อ่านไฟล์ซ้ำ
+    if let Some(path) = output_path {
+        let label = output_name
+            .clone()
+            .unwrap_or_else(|| format!("temporary file of job {}", id));
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| write_job_file(config.at_rest_key.as_ref(), path, &file_data))
+            .map_err(|e| {
+                eprintln!("Error writing A6 file {}: {:?}", label, e);
+                // ไม่เก็บไฟล์ที่เขียนไม่ครบไว้ให้กินพื้นที่หรือถูกพิมพ์ซ้ำ
+                if path.exists() {
+                    let _ = std::fs::remove_file(path);
+                }
+                let message = format!("Failed to write A6 file {}. Error: {}", label, e);
+                if e.kind() == std::io::ErrorKind::StorageFull {
+                    ApiError {
+                        status: StatusCode::INSUFFICIENT_STORAGE,
+                        code: ErrorCode::InsufficientStorage,
+                        message,
+                        retry_after: None,
+                    }
+                } else {
+                    ApiError::internal(ErrorCode::FileIoError, message)
+                }
+            })?;
+        println!("PDF successfully resized and saved as {}", label);
+    } else {
+        println!(
+            "PDF successfully resized in memory ({} bytes)",
+            file_data.len()
+        );
+    }
+    history.trace(
+        id,
+        JobStage::Transformed,
+        Some(format!(
+            "{} source pages, {} output pages",
+            report.source_pages, report.output_pages
+        )),
+    );
+
+    let finishing = Finishing {
+        copies: req.copies,
+        collate: req.collate,
+        staple: req.staple,
+        punch: req.punch,
+        duplex: req.duplex,
+    };
+    let media_mm = (
+        report.page_width_pts / PTS_PER_MM,
+        report.page_height_pts / PTS_PER_MM,
+    );
+    let mut settings = job_settings(
+        req.tray.as_deref(),
+        &finishing,
+        media_mm,
+        &req.raw_properties,
+    );
+    settings.windows_user = req.windows_user.clone();
+    let job_name = format!("A6 Print Job - {}", req.filename);
+    let mut transform_summary = if req.transforms.is_empty() {
+        "Resized to A6".to_string()
+    } else {
+        format!("Applied {} transform steps", req.transforms.len())
+    };
+    if let Some(name) = &output_name {
+        transform_summary.push_str(&format!(", saved as {}", name));
+    }
+    let paper_size = PaperSize {
+        name: report.paper_name.to_string(),
+        width_mm: (report.page_width_pts / PTS_PER_MM * 10.0).round() / 10.0,
+        height_mm: (report.page_height_pts / PTS_PER_MM * 10.0).round() / 10.0,
+    };
+
+    check_pre_submit(
+        config,
+        &HookContext {
+            event: "pre_submit",
+            filename: &req.filename,
+            printer_name: printer_name.as_deref(),
+            agent_id: req.agent_id.as_deref(),
+            job_name: &job_name,
+            pages: report.output_pages,
+            output_file: output_name.as_deref(),
+            job_id: None,
+            status: None,
+            message: None,
+            metadata: &req.metadata,
+            client: req.client.as_ref(),
+        },
+    )?;
+
+    // งานของสาขาจะถูกเก็บไว้ในคิวจนกว่า agent จะมาดึงไปพิมพ์
+    if let Some(agent_id) = &req.agent_id {
+        let mut warnings = report.warnings;
+        if !registry.is_online(agent_id) {
+            warnings.push(format!(
+                "Agent {} is offline; the job is held until it reconnects",
+                agent_id
+            ));
+        }
+        let agent_printers = registry.printers(agent_id);
+        if let Some(printer_name) = &printer_name {
+            if !agent_printers.is_empty() && !agent_printers.contains(printer_name) {
+                warnings.push(format!(
+                    "Printer {} is not registered by agent {}",
+                    printer_name, agent_id
+                ));
+            }
+        }
+        let duplicate_of = check_duplicate(
+            config,
+            history,
+            req,
+            &content_sha256,
+            Some(agent_id),
+            printer_name.as_deref(),
+            &mut warnings,
+        )?;
+        let job_id = queue.push(
+            agent_id,
+            job_name.clone(),
+            printer_name.clone(),
+            settings.clone(),
+            &file_data,
+            expires_at,
+        );
+        // งานที่ส่งแบบ `in_memory` อยู่ในคิวเฉพาะในหน่วยความจำ จึงหายไปหาก Service หยุดทำงาน
+        if !req.in_memory {
+            if let Err(e) = queue.spool(config.at_rest_key.as_ref(), agent_id, job_id, &file_data) {
+                eprintln!("Error saving queued job {} to disk: {}", job_id, e);
+                warnings.push(format!(
+                    "Job {} could not be saved to disk and will be lost if the service restarts",
+                    job_id
+                ));
+            }
+        }
+        println!(
+            "Queued job {} for agent {}{}",
+            job_id,
+            agent_id,
+            client_suffix(req.client.as_ref())
+        );
+        history.trace(
+            id,
+            JobStage::Queued,
+            Some(format!("Job {} for agent {}", job_id, agent_id)),
+        );
+        store_job_output(
+            config,
+            history,
+            JobRecord {
+                id,
+                job_id: Some(job_id),
+                filename: req.filename.clone(),
+                printer_name: printer_name.clone(),
+                agent_id: Some(agent_id.clone()),
+                tenant: req.tenant.clone(),
+                api_key: req.api_key.clone(),
+                client: req.client.clone(),
+                status: JobStatus::Queued,
+                submitted_at: chrono::Local::now().to_rfc3339(),
+                expires_at: expires_at.map(|expires_at| expires_at.to_rfc3339()),
+                pages: report.output_pages,
+                message: format!("Queued for agent {}", agent_id),
+                content_sha256: Some(content_sha256),
+                duplicate_of,
+                output_file: output_name.clone(),
+                reprintable: false,
+                metadata: req.metadata.clone(),
+                job_name,
+                settings,
+                timeline: JobTimeline::default(),
+            },
+            req,
+            output_path,
+        );
+        let (message, detail) = SuccessMessage::Queued { agent_id, job_id }.text(
+            req.language,
+            format!(
+                "{}, and queued as job {} for agent {}",
+                transform_summary, job_id, agent_id
+            ),
+        );
+        return Ok((
+            StatusCode::ACCEPTED,
+            PrintResponse {
+                status: "queued".to_string(),
+                message,
+                detail,
+                job_id,
+                split_job_ids: Vec::new(),
+                printer_name,
+                agent_id: Some(agent_id.clone()),
+                source_pages: report.source_pages,
+                output_pages: report.output_pages,
+                paper_size,
+                processing_ms: started_at.elapsed().as_millis() as u64,
+                warnings,
+            },
+        ));
+    }
+
+    let Some((printer, ticket)) = local_printer else {
+        unreachable!("jobs without agent_id reserve a local printer");
+    };
+    let mut warnings = report.warnings;
+    let duplicate_of = check_duplicate(
+        config,
+        history,
+        req,
+        &content_sha256,
+        None,
+        Some(&printer.name),
+        &mut warnings,
+    )?;
+
+    let submitted = ticket.submit(|| {
+        dispatch_print_job(
+            &printer,
+            file_data,
+            &job_name,
+            &settings,
+            config,
+            &dispatcher.sessions,
+        )
+    });
+    history
+        .timeline
+        .count_job(None, &printer.name, report.output_pages, submitted.is_ok());
+    match submitted {
+        Ok(job_ids) => {
+            let job_id = job_ids[0];
+            // งานที่ถูกแบ่งตาม `job_split` แสดง job id ของทุกส่วน
+            let parts = match job_ids.len() {
+                1 => String::new(),
+                count => format!(
+                    " as {} jobs ({})",
+                    count,
+                    job_ids
+                        .iter()
+                        .map(u64::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+            println!(
+                "Print job {} sent successfully to {}{}{}",
+                job_id,
+                printer.name,
+                parts,
+                client_suffix(req.client.as_ref())
+            );
+            if let Some(expires_at) = expires_at {
+                for job_id in &job_ids {
+                    history.watch_deadline(expires_at, printer.name.clone(), *job_id);
+                }
+            }
+            let record = JobRecord {
+                id,
+                job_id: Some(job_id),
+                filename: req.filename.clone(),
+                printer_name: Some(printer.name.clone()),
+                agent_id: None,
+                tenant: req.tenant.clone(),
+                api_key: req.api_key.clone(),
+                client: req.client.clone(),
+                status: JobStatus::Printed,
+                submitted_at: chrono::Local::now().to_rfc3339(),
+                expires_at: expires_at.map(|expires_at| expires_at.to_rfc3339()),
+                pages: report.output_pages,
+                message: format!("Sent to printer {}{}", printer.name, parts),
+                content_sha256: Some(content_sha256),
+                duplicate_of,
+                output_file: output_name.clone(),
+                reprintable: false,
+                metadata: req.metadata.clone(),
+                job_name,
+                settings,
+                timeline: JobTimeline::default(),
+            };
+            history.trace(
+                id,
+                JobStage::Submitted,
+                Some(format!(
+                    "Spooler job {} on printer {}{}",
+                    job_id, printer.name, parts
+                )),
+            );
+            spawn_post_complete_hook(&config.hooks, &record);
+            store_job_output(config, history, record, req, output_path);
+            // เครื่องพิมพ์ IPP ไม่มีคิวของ spooler ให้ติดตาม
+            if printer.driver_name != IPP_DRIVER_NAME {
+                history.watch_spooler(id, &printer.name, &job_ids);
+            }
+            let (message, detail) = SuccessMessage::Printed {
+                printer: &printer.name,
+            }
+            .text(
+                req.language,
+                format!(
+                    "{}, and sent to printer {}{}",
+                    transform_summary, printer.name, parts
+                ),
+            );
+            Ok((
+                StatusCode::OK,
+                PrintResponse {
+                    status: "success".to_string(),
+                    message,
+                    detail,
+                    job_id,
+                    split_job_ids: match job_ids.len() {
+                        1 => Vec::new(),
+                        _ => job_ids,
+                    },
+                    printer_name: Some(printer.name),
+                    agent_id: None,
+                    source_pages: report.source_pages,
+                    output_pages: report.output_pages,
+                    paper_size,
+                    processing_ms: started_at.elapsed().as_millis() as u64,
+                    warnings,
+                },
+            ))
+        }
+        Err(e) => {
+            eprintln!("Error sending print job: {:?}", e);
+            Err(submit_error(
+                dispatcher,
+                config,
+                &printer,
+                ApiError::internal(
+                    ErrorCode::SpoolerError,
+                    format!("Failed to send print job: {:?}", e),
+                ),
+            ))
+        }
+    }
+}
+
+/// รูปแบบ body ของ `POST /print/upload` (ใช้สำหรับเอกสาร OpenAPI)
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub(super) struct UploadPrintForm {
+    /// ไฟล์ PDF ที่จะพิมพ์
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+    /// ตัวเลือกการพิมพ์แบบ JSON เหมือน `PrintRequest` โดยไม่ต้องระบุ `filename`
+    #[schema(value_type = Object, example = json!({"printer_name": "Office_LaserJet", "rotate": 90}))]
+    options: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/print/upload",
+    tag = "Printing",
+    request_body(content = UploadPrintForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "แปลงและส่งคำสั่งพิมพ์สำเร็จ", body = PrintResponse),
+        (status = 202, description = "แปลงสำเร็จและเก็บงานไว้ในคิวของ agent (เมื่อระบุ `agent_id`)", body = PrintResponse),
+        (status = 400, description = "body ไม่ถูกต้อง หรือไม่มี part `file` (`error_code`: `INVALID_REQUEST` และรหัสเดียวกับ `/print`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 403, description = "รหัสเดียวกับ `/print` (`error_code`: `WINDOWS_USER_NOT_ALLOWED`)", body = ResponseMessage),
+        (status = 409, description = "รหัสเดียวกับ `/print` (`error_code`: `DUPLICATE_JOB`, `USER_NOT_LOGGED_ON`)", body = ResponseMessage),
+        (status = 429, description = "tenant ส่งงานครบโควตาของวันนี้แล้ว (`error_code`: `QUOTA_EXCEEDED`) หรือมีงานค้างเกิน `backpressure` (`QUEUE_FULL` พร้อม header `Retry-After`)", body = ResponseMessage),
+        (status = 500, description = "เกิดข้อผิดพลาดในการบันทึกไฟล์ ประมวลผล หรือสั่งพิมพ์", body = ResponseMessage),
+        (status = 503, description = "Print Spooler ของเครื่องนี้หยุดทำงาน งานไม่ถูกพิมพ์ (`error_code`: `SPOOLER_UNAVAILABLE`)", body = ResponseMessage),
+        (status = 507, description = "พื้นที่ว่างบนดิสก์ของ server ไม่พอ (`error_code`: `INSUFFICIENT_STORAGE`)", body = ResponseMessage)
+    )
+)]
+#[post("/print/upload")]
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn upload_print_handler(
+    request: HttpRequest,
+    body: web::Bytes,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    registry: web::Data<AgentRegistry>,
+    history: web::Data<JobHistory>,
+    counters: web::Data<CounterStore>,
+    disk: web::Data<DiskMonitor>,
+    dispatcher: web::Data<PrintDispatcher>,
+    transform_cache: web::Data<TransformCache>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let started_at = Instant::now();
+    let tenant = require_tenant(&request, &config)?;
+    let api_key = tenant.and_then(|tenant| tenant.api_key_id());
+    let tenant = tenant.map(|tenant| tenant.id.to_string());
+    require_disk_space(&disk)?;
+    let content_type = request
+        .headers()
+        .get("Content-Type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let mut parts = parse_multipart(content_type, &body)
+        .map_err(|e| ApiError::bad_request(ErrorCode::InvalidRequest, e.to_string()))?;
+
+    let file_part = parts.iter().position(|part| part.name == "file");
+    let file = file_part.map(|i| parts.swap_remove(i)).ok_or_else(|| {
+        ApiError::bad_request(ErrorCode::InvalidRequest, "Missing multipart part: file")
+    })?;
+    let mut options = match parts.iter().find(|part| part.name == "options") {
+        Some(part) => serde_json::from_slice(&part.data).map_err(|e| {
+            ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                format!("Invalid options part: {}", e),
+            )
+        })?,
+        None => serde_json::json!({}),
+    };
+    let Some(fields) = options.as_object_mut() else {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            "Invalid options part: expected a JSON object",
+        ));
+    };
+
+    // เก็บไฟล์ไว้ชั่วคราวภายใต้ชื่อที่ไม่ซ้ำ และลบทิ้งเมื่อพิมพ์เสร็จ จึงไม่ชนกับงาน cleanup ของไฟล์อื่น
+    let filename = upload_filename(file.filename.as_deref());
+    fields.insert("filename".to_string(), filename.clone().into());
+    fields.remove("source");
+    let mut print_request: PrintRequest = serde_json::from_value(options).map_err(|e| {
+        ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!("Invalid options part: {}", e),
+        )
+    })?;
+    print_request.tenant = tenant;
+    print_request.api_key = api_key;
+    print_request.client = client_identity(&request, &config);
+    print_request.language = Language::of_request(request.headers(), &config);
+
+    let result = web::block(move || {
+        let tenant = print_request.tenant.clone();
+        write_staged_file(
+            storage.get_ref(),
+            config.at_rest_key.as_ref(),
+            tenant.as_deref(),
+            &filename,
+            &file.data,
+        )
+        .map_err(|e| {
+            eprintln!("Error saving uploaded file {}: {:?}", filename, e);
+            ApiError::internal(
+                ErrorCode::FileIoError,
+                format!("Failed to save uploaded file: {}", e),
+            )
+        })?;
+        println!(
+            "Received upload {} ({} bytes) as {}",
+            file.filename.as_deref().unwrap_or("-"),
+            file.data.len(),
+            filename
+        );
+
+        resolve_client_hostname(&mut print_request.client, &config);
+        let result = print_document(
+            &print_request,
+            &config,
+            &queue,
+            &registry,
+            &history,
+            &counters,
+            &transform_cache,
+            storage.get_ref(),
+            &dispatcher,
+            true,
+            started_at,
+        );
+        if let Err(e) = storage.remove(tenant.as_deref(), &filename) {
+            eprintln!("Failed to remove {}: {:?}", filename, e);
+        }
+        result
+    })
+    .await
+    .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?;
+    result.map(|(status, response)| HttpResponse::build(status).json(response))
+}
+
+/// หางานที่ส่งเอกสารเดียวกันไปยังเครื่องพิมพ์เดิมภายใน `window_minutes` ของกลุ่มเครื่องพิมพ์ คืนค่าหมายเลขงานนั้น
+/// และเพิ่มคำเตือน หรือปฏิเสธงานเมื่อตั้งค่า `reject` และไม่ได้ส่ง `force: true`
+pub(super) fn check_duplicate(
+    config: &AppConfig,
+    history: &JobHistory,
+    req: &PrintRequest,
+    content_sha256: &str,
+    agent_id: Option<&str>,
+    printer_name: Option<&str>,
+    warnings: &mut Vec<String>,
+) -> Result<Option<u64>, ApiError> {
+    let Some(duplicates) = &config.duplicates else {
+        return Ok(None);
+    };
+    let policy = duplicates.policy(printer_name);
+    if policy.window_minutes == 0 {
+        return Ok(None);
+    }
+    let window = Duration::from_secs(policy.window_minutes * 60);
+    let Some(previous) = history.find_duplicate(content_sha256, agent_id, printer_name, window)
+    else {
+        return Ok(None);
+    };
+    if policy.reject && !req.force {
+        return Err(ApiError {
+            status: StatusCode::CONFLICT,
+            code: ErrorCode::DuplicateJob,
+            message: format!(
+                "{} was already sent to {} as job {} at {} (duplicate window {} minutes{}); resend with \"force\": true to print it again",
+                req.filename,
+                job_target(&previous),
+                previous.id,
+                previous.submitted_at,
+                policy.window_minutes,
+                policy
+                    .profile
+                    .map(|profile| format!(", profile {}", profile))
+                    .unwrap_or_default()
+            ),
+            retry_after: None,
+        });
+    }
+    warnings.push(format!(
+        "Same document was already sent to {} as job {} at {}",
+        job_target(&previous),
+        previous.id,
+        previous.submitted_at
+    ));
+    Ok(Some(previous.id))
+}
+
+/// ปฏิเสธงานใหม่เมื่อ tenant ส่งงานครบ `daily_job_quota` ของวันนี้แล้ว
+pub(super) fn check_quota(tenant: Option<Tenant>, history: &JobHistory) -> Result<(), ApiError> {
+    let Some((tenant, quota)) =
+        tenant.and_then(|tenant| Some((tenant, tenant.config.daily_job_quota?)))
+    else {
+        return Ok(());
+    };
+    if history.jobs_today(tenant.id) >= quota {
+        return Err(ApiError {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            code: ErrorCode::QuotaExceeded,
+            message: format!(
+                "Tenant {} has reached its daily quota of {} jobs",
+                tenant.id, quota
+            ),
+            retry_after: None,
+        });
+    }
+    Ok(())
+}
+
+/// ปฏิเสธงานของเครื่องพิมพ์ในเครื่องนี้ระหว่างที่ spooler หยุดทำงาน (เครื่องพิมพ์ IPP ไม่ผ่าน spooler จึงส่งได้)
+pub(super) fn require_spooler(
+    dispatcher: &PrintDispatcher,
+    config: &AppConfig,
+    printer_name: Option<&str>,
+) -> Result<(), ApiError> {
+    let is_ipp = printer_name
+        .or(config.default_printer.as_deref())
+        .is_some_and(|name| config.ipp_printers.contains_key(name));
+    match dispatcher.spooler.outage().filter(|_| !is_ipp) {
+        Some(reason) => Err(spooler_unavailable(reason)),
+        None => Ok(()),
+    }
+}
+
+/// ปฏิเสธงานของ `printer` เมื่องานค้างเกิน `backpressure` เพื่อให้ client รอแล้วส่งใหม่
+pub(super) fn require_queue_capacity(
+    dispatcher: &PrintDispatcher,
+    config: &AppConfig,
+    printer: &printers::common::base::printer::Printer,
+) -> Result<(), ApiError> {
+    let Some(backpressure) = &config.backpressure else {
+        return Ok(());
+    };
+    let backlog = match backpressure.max_printer_backlog {
+        Some(_) => dispatcher.backlog(printer),
+        None => PrinterBacklog::default(),
+    };
+    match backpressure.rejection(&dispatcher.queue_depths(), backlog, &printer.name) {
+        Some((reason, retry_after)) => {
+            eprintln!("Rejected print job: {}", reason);
+            Err(ApiError {
+                status: StatusCode::TOO_MANY_REQUESTS,
+                code: ErrorCode::QueueFull,
+                message: format!("{}; retry in {} seconds", reason, retry_after),
+                retry_after: Some(retry_after),
+            })
+        }
+        None => Ok(()),
+    }
+}
+
+pub(super) fn spooler_unavailable(reason: String) -> ApiError {
+    ApiError {
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        code: ErrorCode::SpoolerUnavailable,
+        message: format!(
+            "{}; the job was not printed, try again when it is running",
+            reason
+        ),
+        retry_after: None,
+    }
+}
+
+/// ข้อผิดพลาดของงานที่ส่งไปยัง `printer` ไม่สำเร็จ โดยตรวจสถานะของ spooler ทันที
+/// หาก spooler เพิ่งหยุดทำงานจะตอบ `SPOOLER_UNAVAILABLE` แทน `error`
+pub(super) fn submit_error(
+    dispatcher: &PrintDispatcher,
+    config: &AppConfig,
+    printer: &printers::common::base::printer::Printer,
+    error: ApiError,
+) -> ApiError {
+    dispatcher.spooler.refresh(config);
+    match dispatcher.spooler.blocks(printer) {
+        Some(reason) => spooler_unavailable(reason),
+        None => error,
+    }
+}
+
+/// ปฏิเสธไฟล์และงานพิมพ์ใหม่เมื่อพื้นที่ว่างบนดิสก์ต่ำกว่า `min_free_disk_mb`
+pub(super) fn require_disk_space(disk: &DiskMonitor) -> Result<(), ApiError> {
+    match disk.low_space() {
+        Some(message) => Err(ApiError {
+            status: StatusCode::INSUFFICIENT_STORAGE,
+            code: ErrorCode::InsufficientStorage,
+            message,
+            retry_after: None,
+        }),
+        None => Ok(()),
+    }
+}
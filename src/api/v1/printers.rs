@@ -0,0 +1,648 @@
+// ----------------------------------------------------------------------
+//                            API v1 PRINTERS
+// ----------------------------------------------------------------------
+
+use super::{
+    access::{check_printer_access, require_admin, require_tenant},
+    errors::{ApiError, ErrorCode, ResponseMessage, SuccessMessage},
+    print::{spooler_unavailable, submit_error},
+};
+use crate::{
+    agent::RemoteJobQueue,
+    backend::print_backend,
+    config::{AppConfig, SharedConfig},
+    discovery::{
+        discover_network_printers, DEFAULT_DISCOVERY_TIMEOUT_MS, MAX_DISCOVERY_TIMEOUT_MS,
+    },
+    dispatch::PrintDispatcher,
+    history::{JobHistory, JobStatus},
+    hooks::spawn_post_complete_hook,
+    ipp::ipp_printer,
+    locale::Language,
+    printer_history::{printer_history_days, HistoryBucket, PrinterUsage},
+    test_page::build_test_page,
+};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use printers::common::base::job::PrinterJobOptions;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, IntoParams)]
+pub(super) struct DiscoverQuery {
+    /// เวลารอคำตอบ (มิลลิวินาที) ค่าเริ่มต้น 2000 สูงสุด 10000
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub(super) struct PrinterHistoryQuery {
+    /// จำนวนวันย้อนหลัง ค่าเริ่มต้น 30 สูงสุดตาม `printer_history_days`
+    days: Option<u64>,
+    /// ขนาดช่วงเวลา: `hour`, `day` (ค่าเริ่มต้น), `week` หรือ `weekday_hour`
+    #[serde(default)]
+    #[param(inline)]
+    bucket: HistoryBucket,
+    /// ดูเครื่องพิมพ์ของ agent นี้แทนเครื่องพิมพ์ของเครื่องนี้
+    agent_id: Option<String>,
+}
+
+/// สถิติของเครื่องพิมพ์ในหนึ่งช่วงเวลา
+#[derive(Serialize, ToSchema)]
+pub(super) struct PrinterUsageBucket {
+    /// ช่วงเวลาตามเวลาของ server เช่น `2024-05-03T14:00:00+07:00` (hour), `2024-05-03` (day),
+    /// `2024-W18` (week) หรือ `Fri 14:00` (weekday_hour)
+    #[schema(example = "2024-05-03")]
+    period: String,
+    #[serde(flatten)]
+    usage: PrinterUsage,
+}
+
+/// สถิติการใช้งานและข้อผิดพลาดของเครื่องพิมพ์ตามช่วงเวลา
+#[derive(Serialize, ToSchema)]
+pub(super) struct PrinterHistory {
+    #[schema(example = "Office_LaserJet")]
+    printer_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent_id: Option<String>,
+    #[schema(example = "day")]
+    bucket: String,
+    /// เวลาเริ่มต้นและสิ้นสุดของช่วงที่รายงาน (RFC 3339)
+    from: String,
+    to: String,
+    totals: PrinterUsage,
+    buckets: Vec<PrinterUsageBucket>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub(super) struct PrinterStateQuery {
+    /// หยุด/ทำงานต่อเฉพาะงานในคิวของ agent นี้ แทน spooler ของเครื่องนี้
+    agent_id: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub(super) struct PurgePrinterQuery {
+    /// ลบงานในคิวของ agent นี้ แทน spooler ของเครื่องนี้
+    agent_id: Option<String>,
+    /// ยกเลิกทุกงานของเครื่องพิมพ์ รวมถึงงานของ tenant อื่นและงานที่ไม่ได้ส่งผ่าน API (ต้องใช้สิทธิ์ผู้ดูแลระบบ)
+    #[serde(default)]
+    all: bool,
+}
+
+/// หยุดหรือให้เครื่องพิมพ์ทำงานต่อ: คิวของ agent เมื่อระบุ `agent_id` หรือ spooler ของเครื่องนี้
+pub(super) fn change_printer_state(
+    name: &str,
+    query: &PrinterStateQuery,
+    config: &AppConfig,
+    queue: &RemoteJobQueue,
+    paused: bool,
+    language: Language,
+) -> Result<HttpResponse, ApiError> {
+    let action = if paused { "Paused" } else { "Resumed" };
+    let agent_id = query.agent_id.as_deref();
+    let kind = if paused {
+        SuccessMessage::PrinterPaused {
+            printer: name,
+            agent_id,
+        }
+    } else {
+        SuccessMessage::PrinterResumed {
+            printer: name,
+            agent_id,
+        }
+    };
+
+    let message = match &query.agent_id {
+        Some(agent_id) => {
+            if !config.agents.contains_key(agent_id) {
+                return Err(ApiError::bad_request(
+                    ErrorCode::UnknownAgent,
+                    format!("Unknown agent: {}", agent_id),
+                ));
+            }
+            let pending = queue.set_paused(agent_id, name, paused);
+            format!(
+                "{} printer {} at agent {} ({} jobs waiting in the queue)",
+                action, name, agent_id, pending
+            )
+        }
+        None => {
+            let printer = print_backend().printer(name).ok_or_else(|| {
+                ApiError::not_found(
+                    ErrorCode::PrinterNotFound,
+                    format!("Printer not found: {}", name),
+                )
+            })?;
+            print_backend().set_paused(&printer, paused).map_err(|e| {
+                eprintln!("Error changing state of printer {}: {:?}", name, e);
+                ApiError::internal(
+                    ErrorCode::SpoolerError,
+                    format!("Failed to change state of printer {}: {}", name, e),
+                )
+            })?;
+            format!("{} printer {}", action, name)
+        }
+    };
+
+    println!("{}", message);
+    Ok(HttpResponse::Ok().json(ResponseMessage::success(kind, language, message, vec![])))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/printers/{name}/pause",
+    tag = "Printers",
+    params(("name" = String, Path, description = "ชื่อเครื่องพิมพ์"), PrinterStateQuery),
+    responses(
+        (status = 200, description = "หยุดส่งงานไปยังเครื่องพิมพ์แล้ว (งานใหม่ยังรับเข้าคิวตามปกติ)", body = ResponseMessage),
+        (status = 400, description = "ไม่รู้จัก agent", body = ResponseMessage),
+        (status = 404, description = "ไม่พบเครื่องพิมพ์", body = ResponseMessage),
+        (status = 500, description = "spooler ไม่ยอมหยุดเครื่องพิมพ์", body = ResponseMessage)
+    )
+)]
+#[post("/printers/{name}/pause")]
+pub(super) async fn pause_printer_handler(
+    request: HttpRequest,
+    name: web::Path<String>,
+    query: web::Query<PrinterStateQuery>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    check_printer_access(tenant, &name, query.agent_id.as_deref())?;
+    let language = Language::of_request(request.headers(), &config);
+    change_printer_state(&name, &query, &config, &queue, true, language)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/printers/{name}/resume",
+    tag = "Printers",
+    params(("name" = String, Path, description = "ชื่อเครื่องพิมพ์"), PrinterStateQuery),
+    responses(
+        (status = 200, description = "เครื่องพิมพ์ทำงานต่อแล้ว", body = ResponseMessage),
+        (status = 400, description = "ไม่รู้จัก agent", body = ResponseMessage),
+        (status = 404, description = "ไม่พบเครื่องพิมพ์", body = ResponseMessage),
+        (status = 500, description = "spooler ไม่ยอมให้เครื่องพิมพ์ทำงานต่อ", body = ResponseMessage)
+    )
+)]
+#[post("/printers/{name}/resume")]
+pub(super) async fn resume_printer_handler(
+    request: HttpRequest,
+    name: web::Path<String>,
+    query: web::Query<PrinterStateQuery>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    check_printer_access(tenant, &name, query.agent_id.as_deref())?;
+    let language = Language::of_request(request.headers(), &config);
+    change_printer_state(&name, &query, &config, &queue, false, language)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/printers/{name}/purge",
+    tag = "Printers",
+    params(("name" = String, Path, description = "ชื่อเครื่องพิมพ์"), PurgePrinterQuery),
+    responses(
+        (status = 200, description = "ยกเลิกงานที่ค้างอยู่แล้ว งานที่ยกเลิกไม่สำเร็จจะแสดงใน warnings", body = ResponseMessage),
+        (status = 400, description = "ไม่รู้จัก agent", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง หรือไม่มีสิทธิ์ผู้ดูแลระบบเมื่อระบุ `all=true`", body = ResponseMessage),
+        (status = 404, description = "ไม่พบเครื่องพิมพ์", body = ResponseMessage)
+    )
+)]
+#[post("/printers/{name}/purge")]
+pub(super) async fn purge_printer_handler(
+    request: HttpRequest,
+    name: web::Path<String>,
+    query: web::Query<PurgePrinterQuery>,
+    config: web::Data<SharedConfig>,
+    queue: web::Data<RemoteJobQueue>,
+    history: web::Data<JobHistory>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    // ไม่ระบุ `all` ยกเลิกเฉพาะงานที่ประวัติงานบันทึกว่า tenant นี้ส่งไปยังเครื่องพิมพ์นี้
+    let owned = if query.all {
+        require_admin(&request, &config)?;
+        None
+    } else {
+        let tenant = require_tenant(&request, &config)?;
+        check_printer_access(tenant, &name, query.agent_id.as_deref())?;
+        let (status, agent_id) = match &query.agent_id {
+            Some(agent_id) => (JobStatus::Queued, Some(agent_id.as_str())),
+            None => (JobStatus::Printed, None),
+        };
+        Some(history.tenant_job_ids(status, agent_id, &name, tenant.map(|tenant| tenant.id)))
+    };
+    let mut warnings = Vec::new();
+
+    let (purged, message, count) = match &query.agent_id {
+        Some(agent_id) => {
+            if !config.agents.contains_key(agent_id) {
+                return Err(ApiError::bad_request(
+                    ErrorCode::UnknownAgent,
+                    format!("Unknown agent: {}", agent_id),
+                ));
+            }
+            let purged = queue.purge_printer(agent_id, &name, owned.as_deref());
+            let message = format!(
+                "Purged {} queued jobs of printer {} at agent {}",
+                purged.len(),
+                name,
+                agent_id
+            );
+            (
+                history.mark_status(
+                    JobStatus::Queued,
+                    JobStatus::Cancelled,
+                    Some(agent_id),
+                    &purged,
+                    &message,
+                ),
+                message,
+                purged.len(),
+            )
+        }
+        None => {
+            let printer = print_backend().printer(&name).ok_or_else(|| {
+                ApiError::not_found(
+                    ErrorCode::PrinterNotFound,
+                    format!("Printer not found: {}", name),
+                )
+            })?;
+            let mut cancelled = Vec::new();
+            for job in print_backend()
+                .active_jobs(&printer)
+                .into_iter()
+                .filter(|job| owned.as_ref().is_none_or(|owned| owned.contains(&job.id)))
+            {
+                match print_backend().cancel(&printer, job.id) {
+                    Ok(()) => cancelled.push(job.id),
+                    Err(e) => {
+                        eprintln!("Error cancelling spooler job {}: {:?}", job.id, e);
+                        warnings.push(format!("Failed to cancel job {}: {}", job.id, e));
+                    }
+                }
+            }
+            let message = format!(
+                "Purged {} jobs from the spooler queue of printer {}",
+                cancelled.len(),
+                name
+            );
+            (
+                history.mark_status(
+                    JobStatus::Printed,
+                    JobStatus::Cancelled,
+                    None,
+                    &cancelled,
+                    &message,
+                ),
+                message,
+                cancelled.len(),
+            )
+        }
+    };
+
+    for record in &purged {
+        spawn_post_complete_hook(&config.hooks, record);
+    }
+    println!("{}", message);
+    Ok(HttpResponse::Ok().json(ResponseMessage::success(
+        SuccessMessage::Purged {
+            printer: &name,
+            count,
+        },
+        Language::of_request(request.headers(), &config),
+        message,
+        warnings,
+    )))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/printers/{name}/capabilities",
+    tag = "Printers",
+    params(("name" = String, Path, description = "ชื่อเครื่องพิมพ์")),
+    responses(
+        (status = 200, description = "ความสามารถของเครื่องพิมพ์", body = PrinterCapabilities),
+        (status = 404, description = "ไม่พบเครื่องพิมพ์", body = ResponseMessage),
+        (status = 500, description = "อ่านข้อมูลจาก driver ไม่สำเร็จ", body = ResponseMessage)
+    )
+)]
+#[get("/printers/{name}/capabilities")]
+pub(super) async fn printer_capabilities_handler(
+    request: HttpRequest,
+    name: web::Path<String>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, ApiError> {
+    check_printer_access(require_tenant(&request, &config.get())?, &name, None)?;
+    let printer = print_backend().printer(&name).ok_or_else(|| {
+        ApiError::not_found(
+            ErrorCode::PrinterNotFound,
+            format!("Printer not found: {}", name),
+        )
+    })?;
+
+    match print_backend().capabilities(&printer) {
+        Ok(capabilities) => Ok(HttpResponse::Ok().json(capabilities)),
+        Err(e) => {
+            eprintln!("Error querying printer capabilities: {:?}", e);
+            Err(ApiError::internal(
+                ErrorCode::DriverError,
+                format!("Failed to query capabilities of printer {}: {}", name, e),
+            ))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/printers/{name}/history",
+    tag = "Printers",
+    params(("name" = String, Path, description = "ชื่อเครื่องพิมพ์"), PrinterHistoryQuery),
+    responses(
+        (status = 200, description = "จำนวนงาน ข้อผิดพลาด และเวลาที่ offline ของเครื่องพิมพ์ตามช่วงเวลา", body = PrinterHistory),
+        (status = 400, description = "ไม่รู้จัก agent หรือ `days` ไม่ถูกต้อง", body = ResponseMessage),
+        (status = 404, description = "ไม่พบเครื่องพิมพ์และไม่มีสถิติของเครื่องพิมพ์นี้", body = ResponseMessage)
+    )
+)]
+#[get("/printers/{name}/history")]
+pub(super) async fn printer_history_handler(
+    request: HttpRequest,
+    name: web::Path<String>,
+    query: web::Query<PrinterHistoryQuery>,
+    config: web::Data<SharedConfig>,
+    history: web::Data<JobHistory>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let agent_id = query.agent_id.as_deref();
+    check_printer_access(require_tenant(&request, &config)?, &name, agent_id)?;
+    if let Some(agent_id) = agent_id.filter(|agent_id| !config.agents.contains_key(*agent_id)) {
+        return Err(ApiError::bad_request(
+            ErrorCode::UnknownAgent,
+            format!("Unknown agent: {}", agent_id),
+        ));
+    }
+    let retention_days = printer_history_days(&config);
+    let days = query.days.unwrap_or(30);
+    if !(1..=retention_days).contains(&days) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Invalid days value: {} (expected 1 to {})",
+                days, retention_days
+            ),
+        ));
+    }
+    // เครื่องพิมพ์ที่ถอดออกไปแล้วยังดูสถิติเดิมได้
+    if !history.timeline.contains(agent_id, &name)
+        && (agent_id.is_some() || print_backend().printer(&name).is_none())
+    {
+        return Err(ApiError::not_found(
+            ErrorCode::PrinterNotFound,
+            format!("Printer not found: {}", name),
+        ));
+    }
+
+    let to = chrono::Local::now();
+    let from = to - chrono::Duration::days(days as i64);
+    let buckets: Vec<PrinterUsageBucket> = history
+        .timeline
+        .buckets(
+            agent_id,
+            &name,
+            from.timestamp(),
+            to.timestamp(),
+            query.bucket,
+        )
+        .into_iter()
+        .map(|(period, usage)| PrinterUsageBucket { period, usage })
+        .collect();
+    let mut totals = PrinterUsage::default();
+    for bucket in &buckets {
+        totals.add(&bucket.usage);
+    }
+    Ok(HttpResponse::Ok().json(PrinterHistory {
+        printer_name: name.into_inner(),
+        agent_id: query.agent_id.clone(),
+        bucket: query.bucket.name().to_string(),
+        from: from.to_rfc3339(),
+        to: to.to_rfc3339(),
+        totals,
+        buckets,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/printers/{name}/test",
+    tag = "Printers",
+    params(("name" = String, Path, description = "ชื่อเครื่องพิมพ์")),
+    responses(
+        (status = 200, description = "ส่งหน้าทดสอบไปยังเครื่องพิมพ์สำเร็จ", body = ResponseMessage),
+        (status = 404, description = "ไม่พบเครื่องพิมพ์", body = ResponseMessage),
+        (status = 500, description = "เกิดข้อผิดพลาดในการสร้างหรือสั่งพิมพ์หน้าทดสอบ", body = ResponseMessage),
+        (status = 503, description = "Print Spooler ของเครื่องนี้หยุดทำงาน งานไม่ถูกพิมพ์ (`error_code`: `SPOOLER_UNAVAILABLE`)", body = ResponseMessage)
+    )
+)]
+#[post("/printers/{name}/test")]
+pub(super) async fn test_page_handler(
+    request: HttpRequest,
+    name: web::Path<String>,
+    config: web::Data<SharedConfig>,
+    dispatcher: web::Data<PrintDispatcher>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    check_printer_access(require_tenant(&request, &config)?, &name, None)?;
+    if let Some(reason) = dispatcher.spooler.outage() {
+        return Err(spooler_unavailable(reason));
+    }
+    let printer = print_backend().printer(&name).ok_or_else(|| {
+        ApiError::not_found(
+            ErrorCode::PrinterNotFound,
+            format!("Printer not found: {}", name),
+        )
+    })?;
+
+    let mut file_data = Vec::new();
+    if let Err(e) = build_test_page(&printer.name).and_then(|mut doc| {
+        doc.save_to(&mut file_data)?;
+        Ok(())
+    }) {
+        eprintln!("Error generating test page: {:?}", e);
+        return Err(ApiError::internal(
+            ErrorCode::InternalError,
+            format!("Failed to generate test page: {}", e),
+        ));
+    }
+
+    let options = PrinterJobOptions {
+        name: Some("A6 Test Page"),
+        raw_properties: &[],
+    };
+
+    match dispatcher
+        .reserve(&printer)
+        .submit(|| print_backend().print(&printer, &file_data, options))
+    {
+        Ok(_) => {
+            println!("Test page sent successfully to {}", name);
+            Ok(HttpResponse::Ok().json(ResponseMessage::success(
+                SuccessMessage::TestPageSent { printer: &name },
+                Language::of_request(request.headers(), &config),
+                format!("Test page sent to printer {}", name),
+                vec![],
+            )))
+        }
+        Err(e) => {
+            eprintln!("Error sending test page: {:?}", e);
+            Err(submit_error(
+                &dispatcher,
+                &config,
+                &printer,
+                ApiError::internal(
+                    ErrorCode::SpoolerError,
+                    format!("Failed to send test page: {:?}", e),
+                ),
+            ))
+        }
+    }
+}
+
+/// จำนวนงานค้างของเครื่องพิมพ์หนึ่งเครื่อง
+#[derive(Serialize, ToSchema)]
+pub(super) struct PrinterQueueDepth {
+    #[schema(example = "Label_A6")]
+    printer_name: String,
+    /// งานที่รับแล้วแต่ยังไม่ถึง spooler (รวมงานที่กำลังแปลงไฟล์)
+    waiting: usize,
+    /// งานที่อยู่ในคิวของ spooler และยังพิมพ์ไม่เสร็จ (เครื่องพิมพ์ IPP = 0)
+    spooler_jobs: usize,
+    /// รับงานใหม่ได้หรือไม่ตาม `backpressure`
+    accepting: bool,
+    /// วินาทีที่ควรรอก่อนส่งงานใหม่เมื่อ `accepting` เป็น false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after_secs: Option<u64>,
+}
+
+/// งานค้างของทุกเครื่องพิมพ์ในเครื่องนี้
+#[derive(Serialize, ToSchema)]
+pub(super) struct QueueDepthReport {
+    /// งานที่รอส่งรวมทุกเครื่องพิมพ์
+    total_waiting: usize,
+    /// `backpressure.max_queue_depth` (ไม่มี = ไม่จำกัด)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_queue_depth: Option<usize>,
+    /// `backpressure.max_printer_backlog` (ไม่มี = ไม่จำกัด)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_printer_backlog: Option<usize>,
+    printers: Vec<PrinterQueueDepth>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/printers/queue",
+    tag = "Printers",
+    responses(
+        (status = 200, description = "งานค้างของแต่ละเครื่องพิมพ์ และรับงานใหม่ได้หรือไม่ตาม `backpressure`", body = QueueDepthReport),
+        (status = 401, description = "API key ไม่ถูกต้อง", body = ResponseMessage)
+    )
+)]
+#[get("/printers/queue")]
+pub(super) async fn printer_queue_handler(
+    request: HttpRequest,
+    config: web::Data<SharedConfig>,
+    dispatcher: web::Data<PrintDispatcher>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+
+    let backlogs = {
+        let (config, dispatcher) = (config.clone(), dispatcher.clone());
+        web::block(move || {
+            let mut printers = print_backend().printers();
+            printers.extend(
+                config
+                    .ipp_printers
+                    .iter()
+                    .map(|(name, uri)| ipp_printer(name, uri)),
+            );
+            printers
+                .into_iter()
+                .map(|printer| (printer.name.clone(), dispatcher.backlog(&printer)))
+                .collect::<Vec<_>>()
+        })
+        .await
+        .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?
+    };
+
+    let backpressure = config.backpressure.clone().unwrap_or_default();
+    let queue_depths = dispatcher.queue_depths();
+    let mut printers: Vec<PrinterQueueDepth> = backlogs
+        .into_iter()
+        .filter(|(name, _)| tenant.is_none_or(|tenant| tenant.allows_printer(name)))
+        .map(|(printer_name, backlog)| {
+            let rejection = config
+                .backpressure
+                .as_ref()
+                .and_then(|_| backpressure.rejection(&queue_depths, backlog, &printer_name));
+            PrinterQueueDepth {
+                waiting: backlog.waiting,
+                spooler_jobs: backlog.spooler_jobs,
+                accepting: rejection.is_none(),
+                retry_after_secs: rejection.map(|(_, retry_after)| retry_after),
+                printer_name,
+            }
+        })
+        .collect();
+    printers.sort_by(|a, b| a.printer_name.cmp(&b.printer_name));
+
+    Ok(HttpResponse::Ok().json(QueueDepthReport {
+        total_waiting: queue_depths.values().sum(),
+        max_queue_depth: backpressure.max_queue_depth,
+        max_printer_backlog: backpressure.max_printer_backlog,
+        printers,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/printers/discover",
+    tag = "Printers",
+    params(DiscoverQuery),
+    responses(
+        (status = 200, description = "เครื่องพิมพ์ที่พบในเครือข่าย", body = [DiscoveredPrinter]),
+        (status = 500, description = "ไม่สามารถส่งคำขอค้นหาในเครือข่ายได้", body = ResponseMessage)
+    )
+)]
+#[get("/printers/discover")]
+pub(super) async fn discover_printers_handler(
+    request: HttpRequest,
+    query: web::Query<DiscoverQuery>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, ApiError> {
+    require_tenant(&request, &config.get())?;
+    let timeout = Duration::from_millis(
+        query
+            .timeout_ms
+            .unwrap_or(DEFAULT_DISCOVERY_TIMEOUT_MS)
+            .min(MAX_DISCOVERY_TIMEOUT_MS),
+    );
+
+    let discovery_error = |e: &dyn std::fmt::Display| {
+        ApiError::internal(
+            ErrorCode::DiscoveryError,
+            format!("Failed to discover network printers: {}", e),
+        )
+    };
+    let found = web::block(move || discover_network_printers(timeout))
+        .await
+        .map_err(|e| discovery_error(&e))?
+        .map_err(|e| {
+            eprintln!("Error discovering network printers: {:?}", e);
+            discovery_error(&e)
+        })?;
+    Ok(HttpResponse::Ok().json(found))
+}
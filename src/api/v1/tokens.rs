@@ -0,0 +1,461 @@
+// ----------------------------------------------------------------------
+//                          API v1 PRINT TOKENS
+// ----------------------------------------------------------------------
+
+use super::{
+    access::{check_printer_access, require_tenant},
+    errors::{ApiError, ErrorCode},
+    print::{
+        print_document, require_disk_space, require_source_file, source_file, validate_metadata,
+        validate_print_options, PrintRequest,
+    },
+};
+use crate::{
+    agent::{tokens_match, AgentRegistry, RemoteJobQueue},
+    client_identity::{client_identity, client_suffix, resolve_client_hostname},
+    config::{AppConfig, SharedConfig},
+    counters::CounterStore,
+    disk::DiskMonitor,
+    dispatch::PrintDispatcher,
+    history::JobHistory,
+    locale::Language,
+    print_tokens::{
+        PrintTokenConfig, PrintTokenError, PrintTokenStore, DEFAULT_PRINT_TOKEN_TTL_MINUTES,
+    },
+    storage::{hex_string, Storage},
+    tenants::find_tenant,
+    transform_cache::TransformCache,
+};
+use actix_web::{http::StatusCode, post, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use utoipa::{IntoParams, ToSchema};
+
+/// โครงสร้างสำหรับออก token พิมพ์ รับ option เดียวกับ `/api/v1/print` (ยกเว้น `pdf_password`)
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"filename": "invoice_original.pdf", "printer_name": "Office_LaserJet", "copies": 2, "ttl_minutes": 30}))]
+pub(super) struct PrintTokenRequest {
+    #[serde(flatten)]
+    print: PrintRequest,
+    /// อายุของ token (นาที, ไม่ระบุ = 60, สูงสุดตาม `print_tokens.max_ttl_minutes` ใน config.json)
+    #[serde(default)]
+    #[schema(example = 30)]
+    ttl_minutes: Option<u64>,
+    /// ใช้ token ซ้ำได้จนหมดอายุ (ไม่ระบุ = ใช้ได้ครั้งเดียว)
+    #[serde(default)]
+    reusable: bool,
+}
+
+/// token พิมพ์ที่ออกให้ ผู้ที่มี token สั่งพิมพ์งานนี้ได้โดยไม่ต้องใช้ API key
+#[derive(Serialize, ToSchema)]
+pub(super) struct PrintTokenResponse {
+    /// token สำหรับ `POST /api/print/token` (ใส่ใน QR code หรือลิงก์ได้)
+    token: String,
+    /// เวลาหมดอายุของ token (RFC 3339)
+    #[schema(example = "2026-10-14T10:30:00+07:00")]
+    expires_at: String,
+    /// ใช้ token ซ้ำได้จนหมดอายุ
+    reusable: bool,
+}
+
+/// โครงสร้างสำหรับสั่งพิมพ์ด้วย token ส่งใน body หรือ `?token=` ก็ได้
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub(super) struct RedeemPrintTokenRequest {
+    /// token จาก `POST /api/v1/print/tokens`
+    token: String,
+}
+
+/// ข้อมูลที่ลงลายมือชื่อไว้ใน token พิมพ์
+#[derive(Serialize, Deserialize)]
+pub(super) struct PrintTokenClaims {
+    /// id สุ่มของ token สำหรับจำว่า token แบบใช้ครั้งเดียวถูกใช้ไปแล้ว
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    api_key: Option<String>,
+    /// API key หรือ client certificate ที่ออก token (จาก `PrintTokenStore::credential_id`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    credential: Option<String>,
+    /// เวลาหมดอายุ (unix timestamp)
+    expires_at: i64,
+    #[serde(default)]
+    reusable: bool,
+    print: PrintRequest,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/print/tokens",
+    tag = "Printing",
+    request_body = PrintTokenRequest,
+    responses(
+        (status = 200, description = "ออก token สำหรับสั่งพิมพ์ไฟล์นี้ด้วย option ที่ระบุ", body = PrintTokenResponse),
+        (status = 400, description = "ค่าใน request ไม่ถูกต้อง หรือระบุ `pdf_password` (`error_code`: `INVALID_REQUEST`, `FILE_NOT_FOUND`, `PRINTER_NOT_FOUND`, `UNKNOWN_AGENT`, `UNKNOWN_SOURCE`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage)
+    )
+)]
+#[post("/print/tokens")]
+pub(super) async fn issue_print_token_handler(
+    request: HttpRequest,
+    req: web::Json<PrintTokenRequest>,
+    config: web::Data<SharedConfig>,
+    print_tokens: web::Data<PrintTokenStore>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let PrintTokenRequest {
+        mut print,
+        ttl_minutes,
+        reusable,
+    } = req.into_inner();
+    let tenant = require_tenant(&request, &config)?;
+    print.tenant = tenant.map(|tenant| tenant.id.to_string());
+    print.api_key = tenant.and_then(|tenant| tenant.api_key_id());
+    print.language = Language::of_request(request.headers(), &config);
+    // token อ่านได้โดยไม่ต้องมีกุญแจ (มีเพียงลายมือชื่อ) จึงเก็บรหัสผ่านไว้ใน token ไม่ได้
+    if print.pdf_password.is_some() {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            "pdf_password cannot be stored in a print token",
+        ));
+    }
+    let max_ttl = config.print_tokens.max_ttl_minutes();
+    let ttl = ttl_minutes.unwrap_or(DEFAULT_PRINT_TOKEN_TTL_MINUTES.min(max_ttl));
+    if ttl == 0 || ttl > max_ttl {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!("ttl_minutes must be between 1 and {}", max_ttl),
+        ));
+    }
+    validate_print_options(&print, &config)?;
+    validate_metadata(&print.metadata)?;
+    // ผูก token กับเครื่องพิมพ์ที่ใช้ตอนออก token ไม่ให้เปลี่ยนตาม `default_printer` ในภายหลัง
+    if print.agent_id.is_none() {
+        print.printer_name = print
+            .printer_name
+            .or_else(|| tenant.and_then(|tenant| tenant.config.default_printer.clone()))
+            .or_else(|| config.default_printer.clone());
+    }
+    if let Some(printer_name) = &print.printer_name {
+        check_printer_access(tenant, printer_name, print.agent_id.as_deref())?;
+    }
+    if let Some(agent_id) = &print.agent_id {
+        if !config.agents.contains_key(agent_id)
+            || !tenant.is_none_or(|tenant| tenant.allows_agent(agent_id))
+        {
+            return Err(ApiError::bad_request(
+                ErrorCode::UnknownAgent,
+                format!("Unknown agent: {}", agent_id),
+            ));
+        }
+    }
+    let file_path = source_file(
+        &config,
+        print.tenant.as_deref(),
+        print.source.as_deref(),
+        &print.filename,
+    )?;
+    require_source_file(&file_path, storage.get_ref(), &print.filename)?;
+
+    let mut id = [0u8; 16];
+    getrandom::fill(&mut id)
+        .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?;
+    let expires_at = chrono::Local::now() + chrono::Duration::minutes(ttl as i64);
+    let claims = PrintTokenClaims {
+        id: hex_string(&id),
+        tenant: print.tenant.clone(),
+        api_key: print.api_key.clone(),
+        credential: tenant
+            .and_then(|tenant| tenant.credential())
+            .map(|credential| print_tokens.credential_id(&config.print_tokens, &credential)),
+        expires_at: expires_at.timestamp(),
+        reusable,
+        print,
+    };
+    let token = print_tokens
+        .sign(&config.print_tokens, &claims)
+        .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?;
+    println!(
+        "Issued print token {} for {} (expires {}){}",
+        claims.id,
+        claims.print.filename,
+        expires_at.to_rfc3339(),
+        client_suffix(client_identity(&request, &config).as_ref())
+    );
+    Ok(HttpResponse::Ok().json(PrintTokenResponse {
+        token,
+        expires_at: expires_at.to_rfc3339(),
+        reusable,
+    }))
+}
+
+/// แปลงผลการตรวจ token พิมพ์เป็น `ApiError`
+pub(super) fn print_token_error(e: PrintTokenError) -> ApiError {
+    let (status, code, message) = match e {
+        PrintTokenError::Invalid => (
+            StatusCode::UNAUTHORIZED,
+            ErrorCode::PrintTokenInvalid,
+            "Invalid print token",
+        ),
+        PrintTokenError::Expired => (
+            StatusCode::GONE,
+            ErrorCode::PrintTokenExpired,
+            "Print token has expired",
+        ),
+        PrintTokenError::Used => (
+            StatusCode::CONFLICT,
+            ErrorCode::PrintTokenUsed,
+            "Print token has already been used",
+        ),
+    };
+    ApiError {
+        status,
+        code,
+        message: message.to_string(),
+        retry_after: None,
+    }
+}
+
+/// ตรวจลายมือชื่อของ `token` และคืน claims เมื่อยังไม่หมดอายุ ณ เวลา `now` (unix timestamp)
+pub(super) fn verify_print_token(
+    print_tokens: &PrintTokenStore,
+    config: &PrintTokenConfig,
+    token: &str,
+    now: i64,
+) -> Result<PrintTokenClaims, PrintTokenError> {
+    let claims: PrintTokenClaims = print_tokens.verify(config, token)?;
+    if claims.expires_at <= now {
+        return Err(PrintTokenError::Expired);
+    }
+    Ok(claims)
+}
+
+/// token ใช้ได้เมื่อ tenant ที่ออก token ยังอยู่ใน config และ API key หรือ client certificate
+/// ที่ใช้ออก token ยังไม่ถูกลบออก
+fn token_issuer_exists(
+    print_tokens: &PrintTokenStore,
+    config: &AppConfig,
+    claims: &PrintTokenClaims,
+) -> bool {
+    let Some(tenant_id) = claims.tenant.as_deref() else {
+        return config.tenants.is_empty();
+    };
+    let (Some(tenant), Some(credential)) = (
+        find_tenant(config, Some(tenant_id)),
+        claims.credential.as_deref(),
+    ) else {
+        return false;
+    };
+    tenant.config.credentials().any(|current| {
+        tokens_match(
+            &print_tokens.credential_id(&config.print_tokens, &current),
+            credential,
+        )
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/print/token",
+    tag = "Printing",
+    request_body(content = RedeemPrintTokenRequest, description = "token ใน body (หรือส่งใน `?token=` แทน)"),
+    params(RedeemPrintTokenRequest),
+    responses(
+        (status = 200, description = "แปลงและส่งคำสั่งพิมพ์ตาม token สำเร็จ", body = PrintResponse),
+        (status = 202, description = "แปลงสำเร็จและเก็บงานไว้ในคิวของ agent", body = PrintResponse),
+        (status = 400, description = "ไม่ได้ส่ง token หรือค่าใน token ใช้กับการตั้งค่าปัจจุบันไม่ได้ (`error_code`: `INVALID_REQUEST`, `FILE_NOT_FOUND`, `PRINTER_NOT_FOUND`)", body = ResponseMessage),
+        (status = 401, description = "token ไม่ถูกต้อง (`error_code`: `PRINT_TOKEN_INVALID`)", body = ResponseMessage),
+        (status = 409, description = "token แบบใช้ครั้งเดียวถูกใช้ไปแล้ว (`error_code`: `PRINT_TOKEN_USED`) หรือเอกสารเพิ่งถูกส่งไปยังเครื่องพิมพ์เดิม", body = ResponseMessage),
+        (status = 410, description = "token หมดอายุแล้ว (`error_code`: `PRINT_TOKEN_EXPIRED`)", body = ResponseMessage),
+        (status = 429, description = "มีงานค้างเกิน `backpressure` (`error_code`: `QUEUE_FULL` พร้อม header `Retry-After`, token แบบใช้ครั้งเดียวยังใช้ลองใหม่ได้)", body = ResponseMessage),
+        (status = 500, description = "เกิดข้อผิดพลาดในการประมวลผลหรือสั่งพิมพ์ (token แบบใช้ครั้งเดียวยังใช้ลองใหม่ได้)", body = ResponseMessage),
+        (status = 503, description = "Print Spooler ของเครื่องนี้หยุดทำงาน งานไม่ถูกพิมพ์ (`error_code`: `SPOOLER_UNAVAILABLE`)", body = ResponseMessage)
+    )
+)]
+#[post("/print/token")]
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn redeem_print_token_handler(
+    request: HttpRequest,
+    query: Option<web::Query<RedeemPrintTokenRequest>>,
+    body: Option<web::Json<RedeemPrintTokenRequest>>,
+    config: web::Data<SharedConfig>,
+    print_tokens: web::Data<PrintTokenStore>,
+    queue: web::Data<RemoteJobQueue>,
+    registry: web::Data<AgentRegistry>,
+    history: web::Data<JobHistory>,
+    counters: web::Data<CounterStore>,
+    disk: web::Data<DiskMonitor>,
+    dispatcher: web::Data<PrintDispatcher>,
+    transform_cache: web::Data<TransformCache>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let started_at = Instant::now();
+    let config = config.get();
+    let token = body
+        .map(|body| body.into_inner().token)
+        .or_else(|| query.map(|query| query.into_inner().token))
+        .ok_or_else(|| ApiError::bad_request(ErrorCode::InvalidRequest, "Missing token"))?;
+    let claims = verify_print_token(
+        &print_tokens,
+        &config.print_tokens,
+        &token,
+        chrono::Utc::now().timestamp(),
+    )
+    .map_err(print_token_error)?;
+    // token ของ tenant หรือ API key ที่ถูกลบออกจาก config.json แล้วใช้ไม่ได้อีก
+    if !token_issuer_exists(&print_tokens, &config, &claims) {
+        return Err(print_token_error(PrintTokenError::Invalid));
+    }
+    let PrintTokenClaims {
+        id,
+        tenant,
+        api_key,
+        credential: _,
+        expires_at,
+        reusable,
+        print: mut req,
+    } = claims;
+    req.tenant = tenant;
+    req.api_key = api_key;
+    req.client = client_identity(&request, &config);
+    if !req.in_memory {
+        require_disk_space(&disk)?;
+    }
+    req.language = Language::of_request(request.headers(), &config);
+    web::block(move || {
+        if !reusable {
+            print_tokens
+                .begin(&id, expires_at)
+                .map_err(print_token_error)?;
+        }
+        resolve_client_hostname(&mut req.client, &config);
+        println!(
+            "Print token {} redeemed for {}{}",
+            id,
+            req.filename,
+            client_suffix(req.client.as_ref())
+        );
+        let result = print_document(
+            &req,
+            &config,
+            &queue,
+            &registry,
+            &history,
+            &counters,
+            &transform_cache,
+            storage.get_ref(),
+            &dispatcher,
+            false,
+            started_at,
+        );
+        // งานที่ส่งไม่สำเร็จไม่นับว่าใช้ token ไปแล้ว เพื่อให้ลองใหม่ด้วย token เดิมได้
+        if result.is_err() && !reusable {
+            print_tokens.release(&id);
+        }
+        result
+    })
+    .await
+    .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))?
+    .map(|(status, response)| HttpResponse::build(status).json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    #[test]
+    fn print_tokens_expire() {
+        let path = std::env::temp_dir().join(format!(
+            "rust-print-api-test-{}-print_tokens.json",
+            std::process::id()
+        ));
+        let store = PrintTokenStore::load(&path).unwrap();
+        let config = PrintTokenConfig::default();
+        let claims = |expires_at| PrintTokenClaims {
+            id: "t1".to_string(),
+            tenant: None,
+            api_key: None,
+            credential: None,
+            expires_at,
+            reusable: false,
+            print: serde_json::from_value(serde_json::json!({"filename": "invoice.pdf"})).unwrap(),
+        };
+        let token = store.sign(&config, &claims(1_000)).unwrap();
+        let verified = verify_print_token(&store, &config, &token, 999).unwrap();
+        assert_eq!(verified.print.filename, "invoice.pdf");
+        for now in [1_000, 1_001] {
+            assert_eq!(
+                verify_print_token(&store, &config, &token, now).err(),
+                Some(PrintTokenError::Expired)
+            );
+        }
+        // ลายมือชื่อถูกตรวจก่อนเวลาหมดอายุ แก้ `expires_at` ใน token ไม่ได้
+        let (_, mac) = token.split_once('.').unwrap();
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let extended = engine.encode(serde_json::to_vec(&claims(i64::MAX)).unwrap());
+        assert_eq!(
+            verify_print_token(&store, &config, &format!("{}.{}", extended, mac), 1_001).err(),
+            Some(PrintTokenError::Invalid)
+        );
+    }
+
+    #[test]
+    fn tokens_stop_working_when_the_issuing_credential_is_removed() {
+        let path = std::env::temp_dir().join(format!(
+            "rust-print-api-test-{}-print_tokens_credential.json",
+            std::process::id()
+        ));
+        let store = PrintTokenStore::load(&path).unwrap();
+        let app_config = |api_keys: &[&str], subjects: &[&str]| AppConfig {
+            tenants: serde_json::from_value(serde_json::json!({
+                "office": {"api_keys": api_keys, "client_cert_subjects": subjects},
+            }))
+            .unwrap(),
+            trusted_proxies: vec!["127.0.0.1".to_string()],
+            client_cert_header: Some("X-SSL-Client-S-DN".to_string()),
+            ..Default::default()
+        };
+        let issue = |config: &AppConfig, header: (&str, &str)| {
+            let request = actix_web::test::TestRequest::default()
+                .peer_addr("127.0.0.1:50000".parse().unwrap())
+                .append_header(header)
+                .to_http_request();
+            let tenant = crate::tenants::authenticate_tenant(&request, config).unwrap();
+            PrintTokenClaims {
+                id: "t1".to_string(),
+                tenant: Some(tenant.id.to_string()),
+                api_key: tenant.api_key_id(),
+                credential: tenant
+                    .credential()
+                    .map(|credential| store.credential_id(&config.print_tokens, &credential)),
+                expires_at: i64::MAX,
+                reusable: false,
+                print: serde_json::from_value(serde_json::json!({"filename": "invoice.pdf"}))
+                    .unwrap(),
+            }
+        };
+        let subject = "CN=press-line-07, O=Acme";
+        let config = app_config(&["key-old", "key-new"], &[subject]);
+        let by_key = issue(&config, ("Authorization", "Bearer key-old"));
+        let by_cert = issue(&config, ("X-SSL-Client-S-DN", "cn=press-line-07,o=acme"));
+        assert!(token_issuer_exists(&store, &config, &by_key));
+        assert!(token_issuer_exists(&store, &config, &by_cert));
+
+        // ถอน key หรือ certificate ที่ออก token แล้ว token ใช้ไม่ได้ แม้ tenant ยังมี credential อื่น
+        let rotated = app_config(&["key-new"], &[subject]);
+        assert!(!token_issuer_exists(&store, &rotated, &by_key));
+        assert!(token_issuer_exists(&store, &rotated, &by_cert));
+        let revoked = app_config(&["key-old", "key-new"], &[]);
+        assert!(token_issuer_exists(&store, &revoked, &by_key));
+        assert!(!token_issuer_exists(&store, &revoked, &by_cert));
+
+        // token ของ tenant ที่ไม่มี credential อยู่ใน token ถือว่าไม่ถูกต้อง
+        let unbound = PrintTokenClaims {
+            credential: None,
+            ..issue(&config, ("Authorization", "Bearer key-new"))
+        };
+        assert!(!token_issuer_exists(&store, &config, &unbound));
+    }
+}
@@ -0,0 +1,211 @@
+// ----------------------------------------------------------------------
+//                        API v1 RESUMABLE UPLOADS
+// ----------------------------------------------------------------------
+
+use super::{
+    access::require_tenant,
+    errors::{upload_error, ApiError, ErrorCode, ResponseMessage, SuccessMessage},
+    print::require_disk_space,
+};
+use crate::{
+    config::SharedConfig,
+    disk::DiskMonitor,
+    locale::Language,
+    storage::Storage,
+    tenants::is_plain_file_name,
+    uploads::{UploadError, UploadStore},
+};
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+/// โครงสร้างสำหรับเริ่มอัปโหลดไฟล์แบบแบ่ง chunk
+#[derive(Deserialize, ToSchema)]
+#[schema(example = json!({"filename": "scan_batch_01.pdf", "size": 41943040, "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"}))]
+pub(super) struct CreateUploadRequest {
+    /// ชื่อไฟล์ที่จะบันทึกใน ./printable_files เมื่ออัปโหลดครบ
+    filename: String,
+    /// ขนาดไฟล์ทั้งหมด (ไบต์)
+    size: u64,
+    /// SHA-256 ของไฟล์ทั้งไฟล์ (hex) ใช้ตรวจสอบก่อนให้ไฟล์พร้อมพิมพ์
+    sha256: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/uploads",
+    tag = "Uploads",
+    request_body = CreateUploadRequest,
+    responses(
+        (status = 201, description = "เริ่ม upload แล้ว ส่ง chunk ต่อด้วย `PATCH /api/v1/uploads/{id}`", body = UploadStatus),
+        (status = 400, description = "ชื่อไฟล์หรือ SHA-256 ไม่ถูกต้อง หรือ `size` เกิน 64 MB (`error_code`: `INVALID_REQUEST`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 507, description = "พื้นที่ว่างบนดิสก์ของ server ไม่พอ (`error_code`: `INSUFFICIENT_STORAGE`)", body = ResponseMessage)
+    )
+)]
+#[post("/uploads")]
+pub(super) async fn create_upload_handler(
+    request: HttpRequest,
+    req: web::Json<CreateUploadRequest>,
+    config: web::Data<SharedConfig>,
+    uploads: web::Data<UploadStore>,
+    disk: web::Data<DiskMonitor>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    require_disk_space(&disk)?;
+    if !is_plain_file_name(&req.filename) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!("Invalid filename: {}", req.filename),
+        ));
+    }
+    if req.sha256.len() != 64 || !req.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            "sha256 must be 64 hexadecimal characters",
+        ));
+    }
+
+    let status = uploads
+        .create(
+            &req.filename,
+            req.size,
+            &req.sha256,
+            tenant.map(|tenant| tenant.id),
+            config.at_rest_key.as_ref(),
+            storage.get_ref(),
+        )
+        .map_err(upload_error)?;
+    println!(
+        "Started upload {} for {} ({} bytes)",
+        status.upload_id, req.filename, req.size
+    );
+    Ok(HttpResponse::Created().json(status))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/uploads/{id}",
+    tag = "Uploads",
+    params(("id" = String, Path, description = "upload id")),
+    responses(
+        (status = 200, description = "สถานะ upload (`offset` คือตำแหน่งที่ต้องส่ง chunk ถัดไป)", body = UploadStatus),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 404, description = "ไม่พบ upload (`error_code`: `UPLOAD_NOT_FOUND`)", body = ResponseMessage)
+    )
+)]
+#[get("/uploads/{id}")]
+pub(super) async fn upload_status_handler(
+    request: HttpRequest,
+    path: web::Path<String>,
+    config: web::Data<SharedConfig>,
+    uploads: web::Data<UploadStore>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    let status = uploads
+        .status(&path, tenant.map(|tenant| tenant.id))
+        .ok_or_else(|| upload_error(UploadError::NotFound))?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/uploads/{id}",
+    tag = "Uploads",
+    params(
+        ("id" = String, Path, description = "upload id"),
+        ("Upload-Offset" = u64, Header, description = "ตำแหน่งเริ่มต้นของ chunk นี้ (ต้องเท่ากับ `offset` ปัจจุบัน)")
+    ),
+    request_body(content = Vec<u8>, content_type = "application/offset+octet-stream"),
+    responses(
+        (status = 200, description = "รับ chunk แล้ว เมื่อ `complete` เป็น `true` ไฟล์พร้อมใช้กับ `POST /api/v1/print`", body = UploadStatus),
+        (status = 400, description = "ไม่มี `Upload-Offset` หรือ chunk เกินขนาดที่แจ้งไว้ (`error_code`: `INVALID_REQUEST`)", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 404, description = "ไม่พบ upload (`error_code`: `UPLOAD_NOT_FOUND`)", body = ResponseMessage),
+        (status = 409, description = "`Upload-Offset` ไม่ตรงกับ server ให้อ่าน `offset` ใหม่จาก `GET` (`error_code`: `UPLOAD_OFFSET_MISMATCH`)", body = ResponseMessage),
+        (status = 422, description = "SHA-256 ไม่ตรง ไฟล์ถูกลบแล้ว (`error_code`: `CHECKSUM_MISMATCH`)", body = ResponseMessage),
+        (status = 507, description = "พื้นที่ว่างบนดิสก์ของ server ไม่พอ (`error_code`: `INSUFFICIENT_STORAGE`)", body = ResponseMessage)
+    )
+)]
+#[patch("/uploads/{id}")]
+pub(super) async fn upload_chunk_handler(
+    request: HttpRequest,
+    path: web::Path<String>,
+    body: web::Bytes,
+    config: web::Data<SharedConfig>,
+    uploads: web::Data<UploadStore>,
+    disk: web::Data<DiskMonitor>,
+    storage: web::Data<dyn Storage>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    require_disk_space(&disk)?;
+    let offset = request
+        .headers()
+        .get("Upload-Offset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .ok_or_else(|| {
+            ApiError::bad_request(
+                ErrorCode::InvalidRequest,
+                "Missing or invalid Upload-Offset header",
+            )
+        })?;
+
+    let tenant = tenant.map(|tenant| tenant.id.to_string());
+    let upload_id = path.into_inner();
+    let status = web::block(move || {
+        uploads
+            .append(
+                &upload_id,
+                tenant.as_deref(),
+                offset,
+                &body,
+                config.at_rest_key.as_ref(),
+                storage.get_ref(),
+            )
+            .map_err(|e| {
+                eprintln!("Upload {} failed at offset {}: {}", upload_id, offset, e);
+                upload_error(e)
+            })
+    })
+    .await
+    .map_err(|e| ApiError::internal(ErrorCode::InternalError, e.to_string()))??;
+    Ok(HttpResponse::Ok()
+        .insert_header(("Upload-Offset", status.offset.to_string()))
+        .json(status))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/uploads/{id}",
+    tag = "Uploads",
+    params(("id" = String, Path, description = "upload id")),
+    responses(
+        (status = 200, description = "ยกเลิก upload และลบไฟล์ที่อัปโหลดไว้บางส่วนแล้ว", body = ResponseMessage),
+        (status = 401, description = "ไม่มี API key หรือ API key ไม่ถูกต้อง (เมื่อตั้งค่า `tenants`)", body = ResponseMessage),
+        (status = 404, description = "ไม่พบ upload (`error_code`: `UPLOAD_NOT_FOUND`)", body = ResponseMessage)
+    )
+)]
+#[delete("/uploads/{id}")]
+pub(super) async fn cancel_upload_handler(
+    request: HttpRequest,
+    path: web::Path<String>,
+    config: web::Data<SharedConfig>,
+    uploads: web::Data<UploadStore>,
+) -> Result<HttpResponse, ApiError> {
+    let config = config.get();
+    let tenant = require_tenant(&request, &config)?;
+    if !uploads.cancel(&path, tenant.map(|tenant| tenant.id)) {
+        return Err(upload_error(UploadError::NotFound));
+    }
+    Ok(HttpResponse::Ok().json(ResponseMessage::success(
+        SuccessMessage::UploadCancelled { upload_id: &path },
+        Language::of_request(request.headers(), &config),
+        format!("Cancelled upload {}", path),
+        vec![],
+    )))
+}
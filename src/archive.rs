@@ -0,0 +1,128 @@
+// ----------------------------------------------------------------------
+//                              JOB ARCHIVE
+// ----------------------------------------------------------------------
+
+use crate::{
+    config::AppConfig,
+    crypto::{read_job_file, write_job_file, AtRestKey},
+    history::{JobRecord, JOB_ARTIFACTS_DIR},
+    storage::hex_string,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// คลังเก็บไฟล์ที่ส่งพิมพ์และข้อมูลของ request สำหรับตรวจสอบย้อนหลัง
+#[derive(Deserialize, Clone)]
+pub(crate) struct ArchiveConfig {
+    /// โฟลเดอร์ของคลัง แยกโฟลเดอร์ย่อยตามวันที่ (`YYYY-MM-DD`)
+    #[serde(default = "default_archive_dir")]
+    pub(crate) dir: PathBuf,
+    /// จำนวนวันที่เก็บไว้ก่อนลบ
+    #[serde(default = "default_archive_retention_days")]
+    retention_days: u64,
+}
+
+pub(crate) fn default_archive_dir() -> PathBuf {
+    PathBuf::from("./archive")
+}
+
+pub(crate) fn default_archive_retention_days() -> u64 {
+    90
+}
+
+/// บันทึกไฟล์ที่ส่งพิมพ์ของงานเป็น `<id>.pdf` และข้อมูลของงานเป็น `<id>.json` ในโฟลเดอร์ของวันนี้
+pub(crate) fn archive_job(
+    archive: &ArchiveConfig,
+    key: Option<&AtRestKey>,
+    record: &JobRecord,
+    request: &impl Serialize,
+    source: &Path,
+) -> Result<()> {
+    let day_dir = archive
+        .dir
+        .join(chrono::Local::now().format("%Y-%m-%d").to_string());
+    std::fs::create_dir_all(&day_dir)
+        .with_context(|| format!("Failed to create {}", day_dir.display()))?;
+
+    let data = read_job_file(key, source)
+        .with_context(|| format!("Failed to read {}", source.display()))?;
+    let metadata = serde_json::json!({
+        "job": record,
+        "job_name": record.job_name,
+        "settings": record.settings,
+        "request": request,
+        "sha256": hex_string(&Sha256::digest(&data)),
+        "size": data.len(),
+    });
+    write_job_file(key, &day_dir.join(format!("{}.pdf", record.id)), &data)?;
+    write_job_file(
+        key,
+        &day_dir.join(format!("{}.json", record.id)),
+        &serde_json::to_vec_pretty(&metadata)?,
+    )?;
+    Ok(())
+}
+
+/// หาไฟล์ของงาน `id` ในคลัง คืนค่าพาธของ `<id>.pdf` และ `<id>.json`
+pub(crate) fn find_archived_job(archive: &ArchiveConfig, id: u64) -> Option<(PathBuf, PathBuf)> {
+    std::fs::read_dir(&archive.dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .map(|dir| {
+            (
+                dir.join(format!("{}.pdf", id)),
+                dir.join(format!("{}.json", id)),
+            )
+        })
+        .find(|(artifact, metadata)| artifact.is_file() && metadata.is_file())
+}
+
+/// หมายเลขงานสูงสุดที่เคยเก็บไฟล์ไว้ (ในคลังและในโฟลเดอร์สำหรับพิมพ์ซ้ำ)
+/// เพื่อให้หมายเลขงานหลัง restart ไม่ซ้ำกับงานเดิม
+pub(crate) fn last_stored_job_id(config: &AppConfig) -> u64 {
+    let mut dirs = vec![PathBuf::from(JOB_ARTIFACTS_DIR)];
+    if let Some(archive) = &config.archive {
+        if let Ok(entries) = std::fs::read_dir(&archive.dir) {
+            dirs.extend(entries.flatten().map(|entry| entry.path()));
+        }
+    }
+    dirs.iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| {
+            entry
+                .path()
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// ลบโฟลเดอร์ของวันที่เก็บไว้เกิน `retention_days` คืนค่าจำนวนโฟลเดอร์ที่ลบ
+pub(crate) fn purge_expired_archives(archive: &ArchiveConfig) -> usize {
+    let today = chrono::Local::now().date_naive();
+    let Ok(entries) = std::fs::read_dir(&archive.dir) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let expired = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| chrono::NaiveDate::parse_from_str(name, "%Y-%m-%d").ok())
+            .is_some_and(|day| (today - day).num_days() > archive.retention_days as i64);
+        if expired {
+            match std::fs::remove_dir_all(entry.path()) {
+                Ok(()) => removed += 1,
+                Err(e) => eprintln!("Error removing archive {}: {:?}", entry.path().display(), e),
+            }
+        }
+    }
+    removed
+}
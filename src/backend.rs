@@ -0,0 +1,505 @@
+// ----------------------------------------------------------------------
+//                             PRINT BACKEND
+// ----------------------------------------------------------------------
+
+use crate::{
+    capabilities::{query_printer_capabilities, query_printer_profile, PrinterCapabilities},
+    history::{cancel_spooler_job, set_spooler_paused},
+    proof::PrinterProfile,
+    storage::hex_string,
+};
+use anyhow::{bail, Context, Result};
+use printers::{self, common::base::job::PrinterJobOptions};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use utoipa::ToSchema;
+
+/// ระบบพิมพ์ที่ใช้กับเครื่องพิมพ์ในเครื่องนี้ (`print_backend` ใน config.json)
+#[derive(Deserialize, Clone, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum PrintBackendConfig {
+    /// เครื่องพิมพ์ที่ติดตั้งใน Windows (spooler) หรือ CUPS (ค่าเริ่มต้น)
+    #[default]
+    System,
+    /// เครื่องพิมพ์จำลองที่เก็บไฟล์ที่ส่งพิมพ์ไว้แทนการพิมพ์จริง สำหรับทดสอบและสาธิตบนเครื่องที่ไม่มีเครื่องพิมพ์
+    Mock(MockBackendConfig),
+}
+
+/// เครื่องพิมพ์จำลองของ `print_backend` แบบ `mock`
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct MockBackendConfig {
+    /// ชื่อเครื่องพิมพ์จำลอง (เครื่องแรกเป็นเครื่องพิมพ์เริ่มต้นของระบบ)
+    printers: Vec<String>,
+    /// เวลาที่ใช้ส่งงานแต่ละงาน (มิลลิวินาที)
+    latency_ms: u64,
+    /// เวลาที่งานอยู่ในคิวของเครื่องพิมพ์ก่อนพิมพ์เสร็จ ระหว่างนี้ยกเลิกงานได้ (มิลลิวินาที)
+    print_ms: u64,
+    /// ให้งานที่ N ทุกงานส่งไม่สำเร็จ เช่น 3 = งานที่ 3, 6, 9, ... (ไม่ระบุ = ไม่จำลองข้อผิดพลาด)
+    fail_every: Option<u64>,
+    /// เครื่องพิมพ์ที่ส่งงานไม่สำเร็จทุกงาน
+    fail_printers: Vec<String>,
+    /// เครื่องพิมพ์ที่มีสถานะ offline
+    offline_printers: Vec<String>,
+    /// โฟลเดอร์ที่บันทึกข้อมูลที่ส่งพิมพ์ของแต่ละงานเป็น `<job id>.prn` (ไม่ระบุ = ไม่บันทึก)
+    record_dir: Option<PathBuf>,
+}
+
+impl Default for MockBackendConfig {
+    fn default() -> Self {
+        MockBackendConfig {
+            printers: vec!["Mock Printer".to_string()],
+            latency_ms: 0,
+            print_ms: 0,
+            fail_every: None,
+            fail_printers: vec![],
+            offline_printers: vec![],
+            record_dir: None,
+        }
+    }
+}
+
+pub(crate) fn validate_print_backend_config(backend: &PrintBackendConfig) -> Result<()> {
+    let PrintBackendConfig::Mock(mock) = backend else {
+        return Ok(());
+    };
+    if mock.printers.is_empty() || mock.printers.iter().any(|name| name.trim().is_empty()) {
+        bail!("print_backend.printers must list at least one printer name");
+    }
+    if mock.fail_every == Some(0) {
+        bail!("print_backend.fail_every must be greater than 0");
+    }
+    for name in mock.fail_printers.iter().chain(&mock.offline_printers) {
+        if !mock.printers.contains(name) {
+            bail!("print_backend refers to unknown mock printer {}", name);
+        }
+    }
+    Ok(())
+}
+
+/// ระบบพิมพ์ของเครื่องนี้ ใช้กับทุกเครื่องพิมพ์ยกเว้น `ipp_printers` ที่ส่งงานด้วย IPP โดยตรง
+pub(crate) trait PrintBackend: Send + Sync {
+    /// คำอธิบายสำหรับ log ตอนเริ่ม Service
+    fn describe(&self) -> String;
+    fn printers(&self) -> Vec<printers::common::base::printer::Printer>;
+    fn printer(&self, name: &str) -> Option<printers::common::base::printer::Printer>;
+    fn default_printer(&self) -> Option<printers::common::base::printer::Printer>;
+    /// ส่งข้อมูลที่พร้อมพิมพ์แล้วไปยังคิวของเครื่องพิมพ์ คืนค่า job id
+    fn print(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+        data: &[u8],
+        options: PrinterJobOptions,
+    ) -> std::result::Result<u64, &'static str>;
+    fn active_jobs(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> Vec<printers::common::base::job::PrinterJob>;
+    fn cancel(&self, printer: &printers::common::base::printer::Printer, job_id: u64)
+        -> Result<()>;
+    fn set_paused(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+        paused: bool,
+    ) -> Result<()>;
+    fn capabilities(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> Result<PrinterCapabilities>;
+    fn profile(&self, printer: &printers::common::base::printer::Printer)
+        -> Result<PrinterProfile>;
+    /// ส่งงานผ่าน Print Spooler ของ Windows หรือไม่ (ไม่ใช่ = ไม่ต้องหยุดรับงานเมื่อ spooler หยุดทำงาน)
+    fn uses_spooler(&self) -> bool {
+        true
+    }
+    /// งานที่บันทึกไว้ล่าสุด (เฉพาะเครื่องพิมพ์จำลอง)
+    fn recorded_jobs(&self) -> Vec<RecordedJob> {
+        vec![]
+    }
+}
+
+pub(crate) static PRINT_BACKEND: std::sync::OnceLock<Box<dyn PrintBackend>> =
+    std::sync::OnceLock::new();
+
+/// เลือกระบบพิมพ์ตาม `print_backend` ครั้งเดียวตอนเริ่ม Service
+pub(crate) fn install_print_backend(config: &PrintBackendConfig) {
+    let backend: Box<dyn PrintBackend> = match config {
+        PrintBackendConfig::System => Box::new(SystemBackend),
+        PrintBackendConfig::Mock(mock) => Box::new(MockBackend::new(mock.clone())),
+    };
+    println!("Printing through {}", backend.describe());
+    if PRINT_BACKEND.set(backend).is_err() {
+        eprintln!("Print backend is already selected; ignoring print_backend");
+    }
+}
+
+/// ระบบพิมพ์ที่เลือกไว้ (ยังไม่เลือก = เครื่องพิมพ์ของระบบ)
+pub(crate) fn print_backend() -> &'static dyn PrintBackend {
+    PRINT_BACKEND
+        .get_or_init(|| Box::new(SystemBackend))
+        .as_ref()
+}
+
+/// เครื่องพิมพ์ที่ติดตั้งในระบบผ่าน crate `printers`
+pub(crate) struct SystemBackend;
+
+impl PrintBackend for SystemBackend {
+    fn describe(&self) -> String {
+        "the system print spooler".to_string()
+    }
+
+    fn printers(&self) -> Vec<printers::common::base::printer::Printer> {
+        printers::get_printers()
+    }
+
+    fn printer(&self, name: &str) -> Option<printers::common::base::printer::Printer> {
+        printers::get_printer_by_name(name)
+    }
+
+    fn default_printer(&self) -> Option<printers::common::base::printer::Printer> {
+        printers::get_default_printer()
+    }
+
+    fn print(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+        data: &[u8],
+        options: PrinterJobOptions,
+    ) -> std::result::Result<u64, &'static str> {
+        printer.print(data, options)
+    }
+
+    fn active_jobs(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> Vec<printers::common::base::job::PrinterJob> {
+        printer.get_active_jobs()
+    }
+
+    fn cancel(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+        job_id: u64,
+    ) -> Result<()> {
+        cancel_spooler_job(printer, job_id)
+    }
+
+    fn set_paused(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+        paused: bool,
+    ) -> Result<()> {
+        set_spooler_paused(printer, paused)
+    }
+
+    fn capabilities(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> Result<PrinterCapabilities> {
+        query_printer_capabilities(printer)
+    }
+
+    fn profile(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> Result<PrinterProfile> {
+        query_printer_profile(printer)
+    }
+}
+
+/// `driver_name` ของเครื่องพิมพ์จำลอง
+pub(crate) const MOCK_DRIVER_NAME: &str = "Mock Printer Driver";
+/// จำนวนงานล่าสุดที่เครื่องพิมพ์จำลองเก็บไว้ในหน่วยความจำ
+pub(crate) const MOCK_JOB_LIMIT: usize = 200;
+
+/// งานที่ส่งไปยังเครื่องพิมพ์จำลอง
+#[derive(Serialize, Clone, ToSchema)]
+pub(crate) struct RecordedJob {
+    #[schema(example = 1)]
+    id: u64,
+    #[schema(example = "Mock Printer")]
+    printer_name: String,
+    job_name: String,
+    /// ขนาดข้อมูลที่ส่งพิมพ์ (byte)
+    size: u64,
+    /// SHA-256 ของข้อมูลที่ส่งพิมพ์ (hex)
+    sha256: String,
+    /// option ที่ส่งไปกับงาน เช่นถาดกระดาษ
+    raw_properties: BTreeMap<String, String>,
+    /// เวลาที่ส่งงาน (RFC 3339)
+    submitted_at: String,
+    /// `printed`, `queued`, `failed` หรือ `cancelled`
+    #[schema(example = "printed")]
+    status: String,
+}
+
+/// ระบบพิมพ์ที่ใช้อยู่และงานที่เครื่องพิมพ์จำลองได้รับ
+#[derive(Serialize, ToSchema)]
+pub(crate) struct PrintBackendListing {
+    #[schema(example = "mock printers Mock Printer")]
+    pub(crate) backend: String,
+    /// งานล่าสุดก่อน (ระบบพิมพ์ของเครื่องจริงไม่มีรายการนี้)
+    pub(crate) jobs: Vec<RecordedJob>,
+}
+
+pub(crate) struct MockJob {
+    record: RecordedJob,
+    created_at: SystemTime,
+    completes_at: Instant,
+}
+
+#[derive(Default)]
+pub(crate) struct MockState {
+    jobs: VecDeque<MockJob>,
+    paused: BTreeSet<String>,
+    submitted: u64,
+}
+
+/// เครื่องพิมพ์จำลองที่รับงานทุกงานโดยไม่พิมพ์จริง ตามการตั้งค่าใน `MockBackendConfig`
+pub(crate) struct MockBackend {
+    config: MockBackendConfig,
+    state: Mutex<MockState>,
+}
+
+impl MockBackend {
+    fn new(config: MockBackendConfig) -> Self {
+        MockBackend {
+            config,
+            state: Mutex::new(MockState::default()),
+        }
+    }
+
+    fn mock_printer(
+        &self,
+        name: &str,
+        state: &MockState,
+    ) -> printers::common::base::printer::Printer {
+        use printers::common::base::printer::PrinterState;
+        let printer_state = if self
+            .config
+            .offline_printers
+            .iter()
+            .any(|offline| offline == name)
+        {
+            PrinterState::OFFLINE
+        } else if state.paused.contains(name) {
+            PrinterState::PAUSED
+        } else {
+            PrinterState::READY
+        };
+        printers::common::base::printer::Printer {
+            name: name.to_string(),
+            system_name: name.to_string(),
+            driver_name: MOCK_DRIVER_NAME.to_string(),
+            uri: format!("mock://{}", name),
+            port_name: "MOCK:".to_string(),
+            processor: String::new(),
+            data_type: "application/pdf".to_string(),
+            description: "Mock printer (print_backend)".to_string(),
+            location: String::new(),
+            is_default: self.config.printers.first().map(String::as_str) == Some(name),
+            is_shared: false,
+            state: printer_state,
+            state_reasons: vec![],
+        }
+    }
+
+    fn status(job: &MockJob, now: Instant) -> &'static str {
+        match job.record.status.as_str() {
+            "queued" if now >= job.completes_at => "printed",
+            "queued" => "queued",
+            "failed" => "failed",
+            "cancelled" => "cancelled",
+            _ => "printed",
+        }
+    }
+}
+
+impl PrintBackend for MockBackend {
+    fn describe(&self) -> String {
+        format!("mock printers {}", self.config.printers.join(", "))
+    }
+
+    fn printers(&self) -> Vec<printers::common::base::printer::Printer> {
+        let state = self.state.lock().unwrap();
+        self.config
+            .printers
+            .iter()
+            .map(|name| self.mock_printer(name, &state))
+            .collect()
+    }
+
+    fn printer(&self, name: &str) -> Option<printers::common::base::printer::Printer> {
+        let state = self.state.lock().unwrap();
+        self.config
+            .printers
+            .iter()
+            .find(|printer| printer.as_str() == name)
+            .map(|name| self.mock_printer(name, &state))
+    }
+
+    fn default_printer(&self) -> Option<printers::common::base::printer::Printer> {
+        let state = self.state.lock().unwrap();
+        self.config
+            .printers
+            .first()
+            .map(|name| self.mock_printer(name, &state))
+    }
+
+    fn print(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+        data: &[u8],
+        options: PrinterJobOptions,
+    ) -> std::result::Result<u64, &'static str> {
+        if self.config.latency_ms > 0 {
+            std::thread::sleep(Duration::from_millis(self.config.latency_ms));
+        }
+        let mut state = self.state.lock().unwrap();
+        state.submitted += 1;
+        let id = state.submitted;
+        let failed = self.config.fail_printers.contains(&printer.name)
+            || self
+                .config
+                .fail_every
+                .is_some_and(|every| id.is_multiple_of(every));
+        let record = RecordedJob {
+            id,
+            printer_name: printer.name.clone(),
+            job_name: options.name.unwrap_or_default().to_string(),
+            size: data.len() as u64,
+            sha256: hex_string(&Sha256::digest(data)),
+            raw_properties: options
+                .raw_properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            submitted_at: chrono::Local::now().to_rfc3339(),
+            status: if failed { "failed" } else { "queued" }.to_string(),
+        };
+        if state.jobs.len() == MOCK_JOB_LIMIT {
+            state.jobs.pop_front();
+        }
+        state.jobs.push_back(MockJob {
+            record,
+            created_at: SystemTime::now(),
+            completes_at: Instant::now() + Duration::from_millis(self.config.print_ms),
+        });
+        drop(state);
+        if failed {
+            eprintln!(
+                "Mock printer {} rejected job {} (simulated failure)",
+                printer.name, id
+            );
+            return Err("Simulated print failure (mock print backend)");
+        }
+        if let Some(dir) = &self.config.record_dir {
+            let path = dir.join(format!("{}.prn", id));
+            if let Err(e) = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, data)) {
+                eprintln!("Error recording mock job to {}: {}", path.display(), e);
+            }
+        }
+        Ok(id)
+    }
+
+    fn active_jobs(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> Vec<printers::common::base::job::PrinterJob> {
+        use printers::common::base::job::{PrinterJob, PrinterJobState};
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        state
+            .jobs
+            .iter()
+            .filter(|job| {
+                job.record.printer_name == printer.name && Self::status(job, now) == "queued"
+            })
+            .map(|job| PrinterJob {
+                id: job.record.id,
+                name: job.record.job_name.clone(),
+                state: match state.paused.contains(&printer.name) {
+                    true => PrinterJobState::PAUSED,
+                    false => PrinterJobState::PENDING,
+                },
+                media_type: printer.data_type.clone(),
+                created_at: job.created_at,
+                processed_at: None,
+                completed_at: None,
+                printer_name: printer.name.clone(),
+            })
+            .collect()
+    }
+
+    fn cancel(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+        job_id: u64,
+    ) -> Result<()> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let job = state
+            .jobs
+            .iter_mut()
+            .find(|job| job.record.id == job_id && job.record.printer_name == printer.name)
+            .filter(|job| Self::status(job, now) == "queued")
+            .with_context(|| format!("Job {} is not queued on printer {}", job_id, printer.name))?;
+        job.record.status = "cancelled".to_string();
+        Ok(())
+    }
+
+    fn set_paused(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+        paused: bool,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match paused {
+            true => state.paused.insert(printer.name.clone()),
+            false => state.paused.remove(&printer.name),
+        };
+        Ok(())
+    }
+
+    fn capabilities(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> Result<PrinterCapabilities> {
+        Ok(PrinterCapabilities {
+            printer_name: printer.name.clone(),
+            paper_sizes: vec!["A4".to_string(), "A5".to_string(), "A6".to_string()],
+            trays: vec!["Auto".to_string(), "Tray1".to_string(), "Tray2".to_string()],
+            duplex: true,
+            resolutions: vec!["300x300dpi".to_string(), "600x600dpi".to_string()],
+        })
+    }
+
+    fn profile(
+        &self,
+        _printer: &printers::common::base::printer::Printer,
+    ) -> Result<PrinterProfile> {
+        Ok(PrinterProfile::default())
+    }
+
+    fn uses_spooler(&self) -> bool {
+        false
+    }
+
+    fn recorded_jobs(&self) -> Vec<RecordedJob> {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        state
+            .jobs
+            .iter()
+            .rev()
+            .map(|job| RecordedJob {
+                status: Self::status(job, now).to_string(),
+                ..job.record.clone()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,208 @@
+// ----------------------------------------------------------------------
+//                          TRANSFORM BENCHMARK
+// ----------------------------------------------------------------------
+
+use crate::{
+    locale::Language,
+    pdf::{resize_pdf_to_a6, FitPolicy, PageSet, TransformOptions, A4_HEIGHT_PTS, A4_WIDTH_PTS},
+    test_page::text_operations,
+};
+use anyhow::{bail, Context, Result};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub(crate) const BENCH_USAGE: &str = "Usage: rust-print-api bench [--pages <count>] [--iterations <count>] [--file <path>] [--grayscale]
+
+  --pages       pages of the generated A4 report (default 500, ignored with --file)
+  --iterations  runs per thread count, the median is reported (default 3)
+  --file        benchmark an existing PDF instead of a generated report";
+
+/// สร้างรายงาน A4 จำลองจำนวน `pages` หน้า แต่ละหน้ามีตารางข้อความและเส้นหลายร้อย operation
+pub(crate) fn build_benchmark_document(pages: usize) -> Result<Vec<u8>> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+
+    let mut kids = Vec::with_capacity(pages);
+    for page in 1..=pages {
+        let mut operations = Vec::new();
+        let mut y = A4_HEIGHT_PTS - 40.0;
+        for row in 1..=60 {
+            operations.push(Operation::new(
+                "rg",
+                vec![Object::Real(0.2), Object::Real(0.4), Object::Real(0.8)],
+            ));
+            operations.push(Operation::new(
+                "re",
+                vec![
+                    Object::Real(30.0),
+                    Object::Real(y - 3.0),
+                    Object::Real(A4_WIDTH_PTS - 60.0),
+                    Object::Real(0.5),
+                ],
+            ));
+            operations.push(Operation::new("f", vec![]));
+            operations.extend(text_operations(
+                "F1",
+                8.0,
+                32.0,
+                y,
+                &format!(
+                    "Page {} row {}: item {:06} qty {} amount {:.2}",
+                    page,
+                    row,
+                    page * 100 + row,
+                    row % 7 + 1,
+                    row as f32 * 12.5
+                ),
+            ));
+            y -= 13.0;
+        }
+        let content = Content { operations };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode()?));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), A4_WIDTH_PTS.into(), A4_HEIGHT_PTS.into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! {
+                "Font" => dictionary! { "F1" => font_id },
+            },
+        });
+        kids.push(page_id.into());
+    }
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => pages as i64,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let mut data = Vec::new();
+    doc.save_to(&mut data)?;
+    Ok(data)
+}
+
+/// เวลามัธยฐานของการแปลง `input` เป็น A6 ด้วย thread pool ขนาด `threads`
+pub(crate) fn benchmark_resize(
+    input: &[u8],
+    threads: usize,
+    iterations: usize,
+    options: &TransformOptions,
+) -> Result<Duration> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    let mut timings = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let started_at = Instant::now();
+        pool.install(|| resize_pdf_to_a6(input.to_vec(), "benchmark", options))?;
+        timings.push(started_at.elapsed());
+    }
+    timings.sort();
+    Ok(timings[timings.len() / 2])
+}
+
+/// คำสั่ง `bench`: เปรียบเทียบเวลาแปลงเป็น A6 แบบ thread เดียวกับการแปลงทุกหน้าพร้อมกัน
+pub(crate) fn bench_transform(args: &[String]) -> Result<()> {
+    let mut pages = 500;
+    let mut iterations = 3;
+    let mut file = None;
+    let mut grayscale = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .with_context(|| format!("Missing value for {}\n\n{}", arg, BENCH_USAGE))
+        };
+        match arg.as_str() {
+            "--pages" => pages = value()?.parse().context("Invalid --pages")?,
+            "--iterations" => iterations = value()?.parse().context("Invalid --iterations")?,
+            "--file" => file = Some(PathBuf::from(value()?)),
+            "--grayscale" => grayscale = true,
+            _ => bail!("Unknown option {}\n\n{}", arg, BENCH_USAGE),
+        }
+    }
+    if pages == 0 || iterations == 0 {
+        bail!("--pages and --iterations must be greater than 0");
+    }
+
+    let input = match &file {
+        Some(path) => {
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?
+        }
+        None => build_benchmark_document(pages)?,
+    };
+    let font_fallbacks = HashMap::new();
+    let options = TransformOptions {
+        pdf_password: None,
+        optimize: None,
+        font_fallbacks: &font_fallbacks,
+        grayscale,
+        rotate: 0,
+        auto_rotate: false,
+        margin_mm: 0.0,
+        offset_x_mm: 0.0,
+        offset_y_mm: 0.0,
+        bleed_mm: 0.0,
+        crop_marks: false,
+        fit_policy: FitPolicy::Error,
+        language: Language::En,
+        locale: None,
+        stamp_font: None,
+        page_set: PageSet::All,
+        reverse: false,
+        counters: None,
+    };
+    let source = file.as_ref().map_or_else(
+        || format!("generated report of {} pages", pages),
+        |path| path.display().to_string(),
+    );
+    println!(
+        "Benchmarking A6 resize of {} ({} bytes), median of {} runs",
+        source,
+        input.len(),
+        iterations
+    );
+
+    let threads = rayon::current_num_threads();
+    let sequential = benchmark_resize(&input, 1, iterations, &options)?;
+    println!("  1 thread : {:>8.1} ms", sequential.as_secs_f64() * 1000.0);
+    let parallel = benchmark_resize(&input, threads, iterations, &options)?;
+    println!(
+        "  {} threads: {:>8.1} ms",
+        threads,
+        parallel.as_secs_f64() * 1000.0
+    );
+    println!(
+        "  speedup  : {:.2}x",
+        sequential.as_secs_f64() / parallel.as_secs_f64()
+    );
+    Ok(())
+}
+
+/// จุดเริ่มต้นของโหมด `bench` คืนค่า exit code ของโปรแกรม
+pub(crate) fn run_benchmark(args: &[String]) -> i32 {
+    match bench_transform(args) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,274 @@
+// ----------------------------------------------------------------------
+//                               BILLING
+// ----------------------------------------------------------------------
+
+use crate::{history::JobRecord, storage::hex_string};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+pub(crate) const BILLING_PATH: &str = "./printable_files/.billing.json";
+pub(crate) const DEFAULT_BILLING_RETENTION_MONTHS: u32 = 24;
+pub(crate) const DEFAULT_COST_CENTER_KEY: &str = "cost_center";
+
+/// การตั้งค่าของ `billing` ใน config.json
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct BillingConfig {
+    /// key ใน `metadata` ของงานที่ระบุศูนย์ต้นทุน (ไม่ระบุ = `cost_center`)
+    cost_center_key: Option<String>,
+    /// จำนวนเดือนที่เก็บข้อมูลย้อนหลัง (ไม่ระบุ = 24)
+    pub(crate) retention_months: Option<u32>,
+    /// โฟลเดอร์ที่เขียนไฟล์ `billing-YYYY-MM.csv` ของเดือนที่แล้วเมื่อขึ้นเดือนใหม่ (ไม่ระบุ = ไม่เขียน)
+    export_dir: Option<PathBuf>,
+}
+
+impl BillingConfig {
+    pub(crate) fn cost_center_key(&self) -> &str {
+        self.cost_center_key
+            .as_deref()
+            .unwrap_or(DEFAULT_COST_CENTER_KEY)
+    }
+}
+
+/// เดือน, tenant, API key และศูนย์ต้นทุนของงาน
+pub(crate) type BillingKey = (String, Option<String>, Option<String>, Option<String>);
+
+/// จำนวนงานและหน้าที่พิมพ์สำเร็จของหนึ่งหน่วยงานในหนึ่งเดือน
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub(crate) struct BillingLine {
+    /// เดือนตามเวลาของ server
+    #[schema(example = "2026-10")]
+    month: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    /// 8 ตัวแรกของ SHA-256 ของ API key ที่ส่งงาน (ไม่เปิดเผย key จริง)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "3f2a9c1e")]
+    api_key: Option<String>,
+    /// ค่าของ `metadata.<billing.cost_center_key>` ของงาน
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "CC-1020")]
+    cost_center: Option<String>,
+    jobs: u64,
+    /// จำนวนหน้าที่พิมพ์รวมทุกชุด (`copies`)
+    pages: u64,
+}
+
+/// ยอดรายเดือนของทุกหน่วยงาน บันทึกลงไฟล์ทุกนาทีพร้อมสถิติของเครื่องพิมพ์
+/// (ประวัติงานเก็บเพียงงานล่าสุด จึงใช้คิดยอดทั้งเดือนไม่ได้)
+#[derive(Default)]
+pub(crate) struct BillingLedger {
+    months: Mutex<BTreeMap<BillingKey, (u64, u64)>>,
+    /// มีข้อมูลที่ยังไม่ได้บันทึกลงไฟล์
+    dirty: AtomicBool,
+}
+
+impl BillingLedger {
+    /// อ่านยอดจากไฟล์ (ไม่มีไฟล์หรือไฟล์เสียหาย = เริ่มใหม่)
+    pub(crate) fn load(path: &Path) -> Self {
+        let ledger = BillingLedger::default();
+        let lines: Vec<BillingLine> = match std::fs::read(path) {
+            Ok(data) => match serde_json::from_slice(&data) {
+                Ok(lines) => lines,
+                Err(e) => {
+                    eprintln!("Ignoring invalid billing data {}: {}", path.display(), e);
+                    return ledger;
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return ledger,
+            Err(e) => {
+                eprintln!("Failed to read billing data {}: {}", path.display(), e);
+                return ledger;
+            }
+        };
+        let mut months = ledger.months.lock().unwrap();
+        for line in lines {
+            months.insert(
+                (line.month, line.tenant, line.api_key, line.cost_center),
+                (line.jobs, line.pages),
+            );
+        }
+        drop(months);
+        ledger
+    }
+
+    /// นับงานที่พิมพ์สำเร็จเข้าในยอดของเดือนปัจจุบัน
+    pub(crate) fn count(&self, record: &JobRecord, cost_center_key: &str) {
+        let key = (
+            chrono::Local::now().format("%Y-%m").to_string(),
+            record.tenant.clone(),
+            record.api_key.clone(),
+            record.metadata.get(cost_center_key).cloned(),
+        );
+        let copies = record.settings.ipp_attributes.copies.max(1) as u64;
+        let mut months = self.months.lock().unwrap();
+        let (jobs, pages) = months.entry(key).or_default();
+        *jobs += 1;
+        *pages += record.pages as u64 * copies;
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// ยอดของเดือน `month` (`None` = ทุกเดือนที่เก็บไว้) เฉพาะของ `tenant` เมื่อระบุ
+    pub(crate) fn lines(&self, month: Option<&str>, tenant: Option<&str>) -> Vec<BillingLine> {
+        self.months
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((line_month, line_tenant, _, _), _)| {
+                month.is_none_or(|month| line_month == month)
+                    && tenant.is_none_or(|tenant| line_tenant.as_deref() == Some(tenant))
+            })
+            .map(
+                |((month, tenant, api_key, cost_center), (jobs, pages))| BillingLine {
+                    month: month.clone(),
+                    tenant: tenant.clone(),
+                    api_key: api_key.clone(),
+                    cost_center: cost_center.clone(),
+                    jobs: *jobs,
+                    pages: *pages,
+                },
+            )
+            .collect()
+    }
+
+    /// ลบเดือนที่เก่ากว่า `retention_months` แล้วบันทึกลงไฟล์ (เฉพาะเมื่อมีข้อมูลใหม่)
+    pub(crate) fn save(&self, path: &Path, retention_months: u32) -> std::io::Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let oldest = billing_month_before(chrono::Local::now().date_naive(), retention_months);
+        let lines = {
+            let mut months = self.months.lock().unwrap();
+            months.retain(|(month, _, _, _), _| *month >= oldest);
+            drop(months);
+            self.lines(None, None)
+        };
+        // เขียนไฟล์ชั่วคราวก่อนแล้วจึงแทนที่ ไฟล์เดิมจึงไม่เสียหายหากเขียนไม่สำเร็จ
+        let temp_path = path.with_extension("json.tmp");
+        let result = serde_json::to_vec(&lines)
+            .map_err(std::io::Error::from)
+            .and_then(|data| std::fs::write(&temp_path, data))
+            .and_then(|_| std::fs::rename(&temp_path, path));
+        if result.is_err() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+/// 8 ตัวแรกของ SHA-256 ของ API key สำหรับแยกยอดตาม key โดยไม่เก็บ key จริง
+pub(crate) fn api_key_id(key: &str) -> String {
+    hex_string(&Sha256::digest(key.as_bytes()))[..8].to_string()
+}
+
+/// เดือน (`YYYY-MM`) ที่อยู่ก่อนเดือนของ `day` ไป `months` เดือน
+pub(crate) fn billing_month_before(day: chrono::NaiveDate, months: u32) -> String {
+    use chrono::Datelike;
+    day.with_day(1)
+        .and_then(|first| first.checked_sub_months(chrono::Months::new(months)))
+        .unwrap_or(day)
+        .format("%Y-%m")
+        .to_string()
+}
+
+/// ยอดในรูปแบบ CSV: `month,tenant,api_key,cost_center,jobs,pages`
+pub(crate) fn billing_csv(lines: &[BillingLine]) -> String {
+    let field = |value: &Option<String>| {
+        let value = value.as_deref().unwrap_or_default();
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    let mut csv = String::from("month,tenant,api_key,cost_center,jobs,pages\r\n");
+    for line in lines {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\r\n",
+            line.month,
+            field(&line.tenant),
+            field(&line.api_key),
+            field(&line.cost_center),
+            line.jobs,
+            line.pages
+        ));
+    }
+    csv
+}
+
+/// ยอดในรูปแบบ OpenMetrics (Prometheus text exposition)
+pub(crate) fn billing_openmetrics(lines: &[BillingLine]) -> String {
+    let escape = |value: &str| {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    };
+    let labels = |line: &BillingLine| {
+        let mut labels = vec![format!("month=\"{}\"", line.month)];
+        for (name, value) in [
+            ("tenant", &line.tenant),
+            ("api_key", &line.api_key),
+            ("cost_center", &line.cost_center),
+        ] {
+            if let Some(value) = value {
+                labels.push(format!("{}=\"{}\"", name, escape(value)));
+            }
+        }
+        labels.join(",")
+    };
+    let mut metrics = String::new();
+    for (name, help, value) in [
+        (
+            "print_billing_jobs",
+            "Jobs printed in the calendar month.",
+            (|line: &BillingLine| line.jobs) as fn(&BillingLine) -> u64,
+        ),
+        (
+            "print_billing_pages",
+            "Pages printed in the calendar month, including copies.",
+            |line: &BillingLine| line.pages,
+        ),
+    ] {
+        metrics.push_str(&format!(
+            "# TYPE {} counter\n# HELP {} {}\n",
+            name, name, help
+        ));
+        for line in lines {
+            metrics.push_str(&format!(
+                "{}_total{{{}}} {}\n",
+                name,
+                labels(line),
+                value(line)
+            ));
+        }
+    }
+    metrics.push_str("# EOF\n");
+    metrics
+}
+
+/// เขียน `billing-YYYY-MM.csv` ของเดือนที่แล้วลงใน `billing.export_dir` หากยังไม่มีไฟล์
+pub(crate) fn export_previous_billing_month(billing: &BillingConfig, ledger: &BillingLedger) {
+    let Some(dir) = &billing.export_dir else {
+        return;
+    };
+    let month = billing_month_before(chrono::Local::now().date_naive(), 1);
+    let path = dir.join(format!("billing-{}.csv", month));
+    if path.exists() {
+        return;
+    }
+    let csv = billing_csv(&ledger.lines(Some(&month), None));
+    let temp_path = path.with_extension("csv.tmp");
+    match std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&temp_path, csv))
+        .and_then(|_| std::fs::rename(&temp_path, &path))
+    {
+        Ok(()) => println!("Exported billing of {} to {}", month, path.display()),
+        Err(e) => eprintln!("Failed to export billing to {}: {}", path.display(), e),
+    }
+}
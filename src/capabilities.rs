@@ -0,0 +1,283 @@
+// ----------------------------------------------------------------------
+//                         PRINTER CAPABILITIES
+// ----------------------------------------------------------------------
+
+use crate::proof::PrinterProfile;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// ความสามารถของเครื่องพิมพ์ที่อ่านได้จาก driver
+#[derive(Serialize, ToSchema, Default)]
+pub(crate) struct PrinterCapabilities {
+    #[schema(example = "Your_Printer_Name")]
+    pub(crate) printer_name: String,
+    /// ชื่อขนาดกระดาษที่ driver รองรับ
+    #[schema(example = json!(["A4", "A6", "Letter"]))]
+    pub(crate) paper_sizes: Vec<String>,
+    /// ชื่อถาดกระดาษ ใช้เป็นค่า `tray` ของ `/api/print` ได้
+    #[schema(example = json!(["Auto", "Tray1", "Manual"]))]
+    pub(crate) trays: Vec<String>,
+    /// พิมพ์สองหน้าได้หรือไม่
+    pub(crate) duplex: bool,
+    /// ความละเอียดที่รองรับ
+    #[schema(example = json!(["300x300dpi", "600x600dpi"]))]
+    pub(crate) resolutions: Vec<String>,
+}
+
+#[cfg(windows)]
+#[link(name = "winspool")]
+extern "system" {
+    fn DeviceCapabilitiesW(
+        device: *const u16,
+        port: *const u16,
+        capability: u16,
+        output: *mut u16,
+        dev_mode: *const std::ffi::c_void,
+    ) -> i32;
+}
+
+/// อ่านรายการชื่อจาก DeviceCapabilities ซึ่งแต่ละชื่อมีความยาวคงที่ `width` ตัวอักษร
+#[cfg(windows)]
+pub(crate) fn device_capability_names(
+    device: &[u16],
+    port: &[u16],
+    capability: u16,
+    width: usize,
+) -> Vec<String> {
+    let count = unsafe {
+        DeviceCapabilitiesW(
+            device.as_ptr(),
+            port.as_ptr(),
+            capability,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+        )
+    };
+    if count <= 0 {
+        return vec![];
+    }
+    let mut buffer = vec![0u16; count as usize * width];
+    unsafe {
+        DeviceCapabilitiesW(
+            device.as_ptr(),
+            port.as_ptr(),
+            capability,
+            buffer.as_mut_ptr(),
+            std::ptr::null(),
+        );
+    }
+    buffer
+        .chunks(width)
+        .map(|name| {
+            let end = name.iter().position(|&c| c == 0).unwrap_or(width);
+            String::from_utf16_lossy(&name[..end])
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// อ่านความสามารถของเครื่องพิมพ์ด้วย DeviceCapabilitiesW ของ winspool
+#[cfg(windows)]
+pub(crate) fn query_printer_capabilities(
+    printer: &printers::common::base::printer::Printer,
+) -> Result<PrinterCapabilities> {
+    const DC_DUPLEX: u16 = 7;
+    const DC_BINNAMES: u16 = 12;
+    const DC_ENUMRESOLUTIONS: u16 = 13;
+    const DC_PAPERNAMES: u16 = 16;
+
+    let to_wide = |value: &str| {
+        value
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect::<Vec<u16>>()
+    };
+    let device = to_wide(&printer.system_name);
+    let port = to_wide(&printer.port_name);
+
+    let duplex = unsafe {
+        DeviceCapabilitiesW(
+            device.as_ptr(),
+            port.as_ptr(),
+            DC_DUPLEX,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+        )
+    };
+    if duplex < 0 {
+        bail!("DeviceCapabilities failed for printer {}", printer.name);
+    }
+
+    // DC_ENUMRESOLUTIONS คืนค่าเป็นคู่ของ LONG (แนวนอน, แนวตั้ง)
+    let mut resolutions = Vec::new();
+    let count = unsafe {
+        DeviceCapabilitiesW(
+            device.as_ptr(),
+            port.as_ptr(),
+            DC_ENUMRESOLUTIONS,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+        )
+    };
+    if count > 0 {
+        let mut buffer = vec![0i32; count as usize * 2];
+        unsafe {
+            DeviceCapabilitiesW(
+                device.as_ptr(),
+                port.as_ptr(),
+                DC_ENUMRESOLUTIONS,
+                buffer.as_mut_ptr() as *mut u16,
+                std::ptr::null(),
+            );
+        }
+        resolutions = buffer
+            .chunks(2)
+            .map(|pair| format!("{}x{}dpi", pair[0], pair[1]))
+            .collect();
+    }
+
+    Ok(PrinterCapabilities {
+        printer_name: printer.name.clone(),
+        paper_sizes: device_capability_names(&device, &port, DC_PAPERNAMES, 64),
+        trays: device_capability_names(&device, &port, DC_BINNAMES, 24),
+        duplex: duplex == 1,
+        resolutions,
+    })
+}
+
+/// อ่านความสามารถของเครื่องพิมพ์จาก PPD ผ่านคำสั่ง `lpoptions -l` ของ CUPS
+#[cfg(not(windows))]
+pub(crate) fn query_printer_capabilities(
+    printer: &printers::common::base::printer::Printer,
+) -> Result<PrinterCapabilities> {
+    let output = std::process::Command::new("lpoptions")
+        .args(["-p", &printer.system_name, "-l"])
+        .output()
+        .context("Failed to run lpoptions")?;
+    if !output.status.success() {
+        bail!(
+            "lpoptions failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    // แต่ละบรรทัดมีรูปแบบ "PageSize/Media Size: *A4 Letter A6" โดย * คือค่าเริ่มต้น
+    let mut capabilities = PrinterCapabilities {
+        printer_name: printer.name.clone(),
+        ..Default::default()
+    };
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((key, values)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.split('/').next().unwrap_or(key).trim();
+        let values: Vec<String> = values
+            .split_whitespace()
+            .map(|value| value.trim_start_matches('*').to_string())
+            .collect();
+        match key {
+            "PageSize" | "media" => capabilities.paper_sizes = values,
+            "InputSlot" => capabilities.trays = values,
+            "Duplex" | "sides" => {
+                capabilities.duplex = values
+                    .iter()
+                    .any(|value| !matches!(value.as_str(), "None" | "Off" | "one-sided"))
+            }
+            "Resolution" => capabilities.resolutions = values,
+            _ => {}
+        }
+    }
+    Ok(capabilities)
+}
+
+#[cfg(windows)]
+#[link(name = "gdi32")]
+extern "system" {
+    fn CreateDCW(
+        driver: *const u16,
+        device: *const u16,
+        port: *const u16,
+        dev_mode: *const std::ffi::c_void,
+    ) -> *mut std::ffi::c_void;
+    fn GetDeviceCaps(dc: *mut std::ffi::c_void, index: i32) -> i32;
+    fn DeleteDC(dc: *mut std::ffi::c_void) -> i32;
+}
+
+/// อ่านความละเอียดและขอบที่พิมพ์ไม่ถึงด้วย GetDeviceCaps ของ device context ของเครื่องพิมพ์
+#[cfg(windows)]
+pub(crate) fn query_printer_profile(
+    printer: &printers::common::base::printer::Printer,
+) -> Result<PrinterProfile> {
+    const HORZRES: i32 = 8;
+    const VERTRES: i32 = 10;
+    const LOGPIXELSX: i32 = 88;
+    const LOGPIXELSY: i32 = 90;
+    const PHYSICALWIDTH: i32 = 110;
+    const PHYSICALHEIGHT: i32 = 111;
+    const PHYSICALOFFSETX: i32 = 112;
+    const PHYSICALOFFSETY: i32 = 113;
+
+    let driver: Vec<u16> = "WINSPOOL\0".encode_utf16().collect();
+    let device: Vec<u16> = printer
+        .system_name
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    let dc = unsafe {
+        CreateDCW(
+            driver.as_ptr(),
+            device.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    if dc.is_null() {
+        bail!("CreateDC failed for printer {}", printer.name);
+    }
+    let caps = |index| unsafe { GetDeviceCaps(dc, index) };
+    let (dpi_x, dpi_y) = (caps(LOGPIXELSX), caps(LOGPIXELSY));
+    let (width, height) = (caps(PHYSICALWIDTH), caps(PHYSICALHEIGHT));
+    let (offset_x, offset_y) = (caps(PHYSICALOFFSETX), caps(PHYSICALOFFSETY));
+    let (printable_width, printable_height) = (caps(HORZRES), caps(VERTRES));
+    unsafe { DeleteDC(dc) };
+    if dpi_x <= 0 || dpi_y <= 0 {
+        bail!("Printer {} did not report its resolution", printer.name);
+    }
+
+    // ค่าจาก GetDeviceCaps เป็นจุดของเครื่องพิมพ์ ตามกระดาษที่ตั้งไว้ใน driver
+    let to_mm =
+        |dots: i32, dpi: i32| (dots.max(0) as f32 / dpi as f32 * 25.4 * 100.0).round() / 100.0;
+    Ok(PrinterProfile {
+        dpi: dpi_x.min(dpi_y) as u32,
+        margins_mm: [
+            to_mm(offset_x, dpi_x),
+            to_mm(height - offset_y - printable_height, dpi_y),
+            to_mm(width - offset_x - printable_width, dpi_x),
+            to_mm(offset_y, dpi_y),
+        ],
+    })
+}
+
+/// CUPS ไม่บอกขอบที่พิมพ์ไม่ถึงผ่าน lpoptions จึงใช้ความละเอียดสูงสุดที่รองรับกับขอบเริ่มต้น
+#[cfg(not(windows))]
+pub(crate) fn query_printer_profile(
+    printer: &printers::common::base::printer::Printer,
+) -> Result<PrinterProfile> {
+    let dpi = query_printer_capabilities(printer)?
+        .resolutions
+        .iter()
+        .filter_map(|resolution| {
+            let digits: String = resolution
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            digits.parse::<u32>().ok()
+        })
+        .max()
+        .context("Printer does not report its resolution")?;
+    Ok(PrinterProfile {
+        dpi,
+        ..Default::default()
+    })
+}
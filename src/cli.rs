@@ -0,0 +1,154 @@
+// ----------------------------------------------------------------------
+//                          COMMAND-LINE CLIENT
+// ----------------------------------------------------------------------
+
+use crate::agent::{http_request, parse_server_url};
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) const CLIENT_USAGE: &str = "Usage: rust-print-api client print --file <path> [--printer <name>] [--server <url>]
+                                  [--tray <tray>] [--rotate <0|90|180|270>] [--grayscale]
+                                  [--password <pdf password>] [--agent <agent id>] [--options <json>]
+
+  --server   URL of the print API (default http://127.0.0.1:8080)
+  --options  extra PrintRequest fields as a JSON object, e.g. '{\"margin_mm\": 3}'";
+
+/// สร้าง body แบบ `multipart/form-data` ที่มี part `file` และ `options` สำหรับ `POST /api/v1/print/upload`
+pub(crate) fn build_upload_body(
+    boundary: &str,
+    filename: &str,
+    data: &[u8],
+    options: &str,
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len() + options.len() + 512);
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/pdf\r\n\r\n",
+            boundary,
+            filename.replace('"', "_")
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(
+        format!(
+            "\r\n--{}\r\nContent-Disposition: form-data; name=\"options\"\r\nContent-Type: application/json\r\n\r\n{}\r\n--{}--\r\n",
+            boundary, options, boundary
+        )
+        .as_bytes(),
+    );
+    body
+}
+
+/// คำสั่ง `client print`: อัปโหลดไฟล์และสั่งพิมพ์ผ่าน REST API ใน request เดียว
+pub(crate) fn client_print(args: &[String]) -> Result<()> {
+    let mut server_url = "http://127.0.0.1:8080".to_string();
+    let mut file = None;
+    let mut options = serde_json::Map::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .with_context(|| format!("Missing value for {}\n\n{}", arg, CLIENT_USAGE))
+        };
+        match arg.as_str() {
+            "--file" => file = Some(PathBuf::from(value()?)),
+            "--server" => server_url = value()?,
+            "--printer" => {
+                options.insert("printer_name".to_string(), value()?.into());
+            }
+            "--tray" => {
+                options.insert("tray".to_string(), value()?.into());
+            }
+            "--password" => {
+                options.insert("pdf_password".to_string(), value()?.into());
+            }
+            "--agent" => {
+                options.insert("agent_id".to_string(), value()?.into());
+            }
+            "--rotate" => {
+                let rotate: u16 = value()?.parse().context("Invalid --rotate value")?;
+                options.insert("rotate".to_string(), rotate.into());
+            }
+            "--grayscale" => {
+                options.insert("grayscale".to_string(), true.into());
+            }
+            "--options" => {
+                let extra: serde_json::Map<String, serde_json::Value> =
+                    serde_json::from_str(&value()?).context("--options must be a JSON object")?;
+                options.extend(extra);
+            }
+            _ => bail!("Unknown argument: {}\n\n{}", arg, CLIENT_USAGE),
+        }
+    }
+    let file = file.with_context(|| format!("--file is required\n\n{}", CLIENT_USAGE))?;
+
+    let server = parse_server_url(&server_url)?;
+    let data =
+        std::fs::read(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let filename = file
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document.pdf".to_string());
+    let boundary = format!(
+        "rust-print-api-{:x}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    );
+    let body = build_upload_body(
+        &boundary,
+        &filename,
+        &data,
+        &serde_json::Value::Object(options).to_string(),
+    );
+    let content_type = format!("multipart/form-data; boundary={}", boundary);
+    let (status, response) = http_request(
+        &server,
+        "POST",
+        "/api/v1/print/upload",
+        &[("Content-Type", content_type.as_str())],
+        &body,
+    )?;
+
+    let response: serde_json::Value = serde_json::from_slice(&response)
+        .with_context(|| format!("Unexpected response from server (HTTP {})", status))?;
+    let message = response["message"].as_str().unwrap_or_default();
+    if !(200..300).contains(&status) {
+        bail!(
+            "Print failed (HTTP {}, {}): {}",
+            status,
+            response["error_code"].as_str().unwrap_or("UNKNOWN"),
+            message
+        );
+    }
+    println!("{}", message);
+    if let Some(job_id) = response["job_id"].as_u64() {
+        println!("Job id: {}", job_id);
+    }
+    for warning in response["warnings"].as_array().into_iter().flatten() {
+        println!("Warning: {}", warning.as_str().unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// จุดเริ่มต้นของโหมด `client` คืนค่า exit code ของโปรแกรม
+pub(crate) fn run_client(args: &[String]) -> i32 {
+    let result = match args.first().map(String::as_str) {
+        Some("print") => client_print(&args[1..]),
+        _ => {
+            eprintln!("{}", CLIENT_USAGE);
+            return 2;
+        }
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            1
+        }
+    }
+}
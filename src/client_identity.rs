@@ -0,0 +1,558 @@
+// ----------------------------------------------------------------------
+//                           CLIENT IDENTITY
+// ----------------------------------------------------------------------
+
+use crate::config::AppConfig;
+use actix_web::HttpRequest;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// ระยะเวลาที่จำชื่อเครื่องจาก reverse DNS ของแต่ละ IP
+pub(crate) const CLIENT_HOSTNAME_TTL: Duration = Duration::from_secs(600);
+/// จำนวน IP สูงสุดที่จำชื่อเครื่องไว้
+pub(crate) const MAX_CLIENT_HOSTNAMES: usize = 1024;
+
+/// เครื่องที่ส่งงาน บันทึกในประวัติงาน คลังงาน และส่งให้ hook
+#[derive(Clone, Serialize, Deserialize, ToSchema, Debug, PartialEq)]
+pub(crate) struct ClientIdentity {
+    /// IP ของ client (จาก `X-Forwarded-For`/`Forwarded` เมื่อ request มาจาก `trusted_proxies`)
+    #[schema(example = "10.20.1.15")]
+    ip: String,
+    /// ชื่อเครื่องจาก reverse DNS (เมื่อเปิด `resolve_client_hostnames`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "ACC-PC-07.corp.local")]
+    hostname: Option<String>,
+    /// IP ของ reverse proxy ที่ส่ง request ต่อมา (ไม่มี = client เชื่อมต่อโดยตรง)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "127.0.0.1")]
+    proxy: Option<String>,
+}
+
+impl std::fmt::Display for ClientIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ip)?;
+        if let Some(hostname) = &self.hostname {
+            write!(f, " ({})", hostname)?;
+        }
+        if let Some(proxy) = &self.proxy {
+            write!(f, " via {}", proxy)?;
+        }
+        Ok(())
+    }
+}
+
+/// อ่าน IP หรือช่วง IP เช่น `10.0.0.0/8` คืนค่า (IP, จำนวนบิตของ prefix)
+pub(crate) fn parse_ip_range(value: &str) -> Result<(IpAddr, u8)> {
+    let (ip, prefix) = match value.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (value, None),
+    };
+    let ip: IpAddr = ip
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid trusted_proxies entry {:?}", value))?;
+    let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => match prefix.trim().parse::<u8>() {
+            Ok(prefix) if prefix <= max_prefix => prefix,
+            _ => bail!(
+                "Invalid prefix length in trusted_proxies entry {:?} (expected 0 to {})",
+                value,
+                max_prefix
+            ),
+        },
+        None => max_prefix,
+    };
+    Ok((ip.to_canonical(), prefix))
+}
+
+/// `ip` อยู่ในช่วง `range` หรือไม่
+pub(crate) fn ip_in_range(ip: IpAddr, (network, prefix): (IpAddr, u8)) -> bool {
+    let mask = |bits: u32, width: u32| match bits {
+        0 => 0u128,
+        bits => u128::MAX << (width - bits),
+    };
+    match (ip.to_canonical(), network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = mask(prefix as u32, 32) as u32;
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = mask(prefix as u32, 128);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn is_trusted_proxy(config: &AppConfig, ip: IpAddr) -> bool {
+    config
+        .trusted_proxies
+        .iter()
+        .filter_map(|proxy| parse_ip_range(proxy).ok())
+        .any(|range| ip_in_range(ip, range))
+}
+
+/// IP ตามลำดับจาก client ไปยัง proxy ตัวสุดท้ายใน `Forwarded` (RFC 7239) หรือ `X-Forwarded-For`
+/// ค่าที่ไม่ใช่ IP (เช่น `unknown` หรือชื่อที่ซ่อนไว้) เป็น `None`
+pub(crate) fn forwarded_chain(request: &HttpRequest) -> Vec<Option<IpAddr>> {
+    let values = |name: &str| -> Vec<String> {
+        request
+            .headers()
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect()
+    };
+    let parse = |value: &str| -> Option<IpAddr> {
+        let value = value.trim().trim_matches('"');
+        // `[2001:db8::1]:4711`, `192.0.2.1:8080` หรือ IP อย่างเดียว
+        let host = match value.strip_prefix('[') {
+            Some(rest) => rest.split(']').next().unwrap_or_default(),
+            None if value.matches(':').count() == 1 => value.split(':').next().unwrap_or_default(),
+            None => value,
+        };
+        host.parse::<IpAddr>().ok().map(|ip| ip.to_canonical())
+    };
+    let forwarded = values("Forwarded");
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, value)| parse(value))
+            })
+            .collect();
+    }
+    values("X-Forwarded-For")
+        .iter()
+        .map(|value| parse(value))
+        .collect()
+}
+
+/// ตรวจว่า `client_cert_header` เป็นชื่อ header ที่ถูกต้อง และมี `trusted_proxies` ที่ส่ง header นี้มาได้
+pub(crate) fn validate_client_cert_header(config: &AppConfig) -> Result<()> {
+    let Some(header) = &config.client_cert_header else {
+        return Ok(());
+    };
+    if actix_web::http::header::HeaderName::from_bytes(header.as_bytes()).is_err() {
+        bail!("Invalid client_cert_header {:?}", header);
+    }
+    if config.trusted_proxies.is_empty() {
+        bail!("client_cert_header requires trusted_proxies (the TLS proxy that sets it)");
+    }
+    Ok(())
+}
+
+/// subject ในรูปที่ใช้เทียบกัน: ตัดช่องว่างรอบ `,` และไม่สนตัวพิมพ์เล็ก/ใหญ่
+/// เช่น `CN=line-07, OU=Press, O=Acme` และ `cn=LINE-07,ou=press,o=acme` เป็น subject เดียวกัน
+pub(crate) fn normalize_subject(subject: &str) -> String {
+    subject
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(",")
+        .to_lowercase()
+}
+
+/// subject ของ client certificate ที่ reverse proxy ตรวจแล้วและส่งมาใน header `client_cert_header`
+/// อ่านเฉพาะเมื่อเชื่อมต่อมาจาก `trusted_proxies` โดยตรง (client ที่ต่อตรงปลอม header นี้ไม่ได้)
+/// header ที่ว่างหรือมีหลายค่าถือว่าไม่มี certificate
+pub(crate) fn client_cert_subject(request: &HttpRequest, config: &AppConfig) -> Option<String> {
+    let header = config.client_cert_header.as_deref()?;
+    let peer = request.peer_addr()?.ip().to_canonical();
+    if !is_trusted_proxy(config, peer) {
+        return None;
+    }
+    let mut values = request.headers().get_all(header);
+    let value = values.next()?;
+    if values.next().is_some() {
+        return None;
+    }
+    let subject = normalize_subject(value.to_str().ok()?);
+    (!subject.is_empty()).then_some(subject)
+}
+
+/// IP ของ client และของ proxy ที่ส่งต่อมา (`None` = client เชื่อมต่อโดยตรง)
+/// เชื่อ header ของ proxy เฉพาะเมื่อเชื่อมต่อมาจาก `trusted_proxies` และไล่จาก proxy ตัวสุดท้าย
+/// ย้อนไปจนเจอ IP แรกที่ไม่ใช่ proxy ที่เชื่อถือ เพื่อไม่ให้ client ปลอม IP ด้วยการส่ง header มาเอง
+pub(crate) fn client_address(
+    request: &HttpRequest,
+    config: &AppConfig,
+) -> Option<(IpAddr, Option<IpAddr>)> {
+    let peer = request.peer_addr()?.ip().to_canonical();
+    if !is_trusted_proxy(config, peer) {
+        return Some((peer, None));
+    }
+    let mut client = None;
+    for hop in forwarded_chain(request).into_iter().rev() {
+        let Some(ip) = hop else {
+            break;
+        };
+        client = Some(ip);
+        if !is_trusted_proxy(config, ip) {
+            break;
+        }
+    }
+    match client {
+        Some(client) if client != peer => Some((client, Some(peer))),
+        _ => Some((peer, None)),
+    }
+}
+
+/// เครื่องที่ส่ง `request` (ยังไม่ค้นหาชื่อเครื่อง ดู `resolve_client_hostname`)
+pub(crate) fn client_identity(request: &HttpRequest, config: &AppConfig) -> Option<ClientIdentity> {
+    client_address(request, config).map(|(ip, proxy)| ClientIdentity {
+        ip: ip.to_string(),
+        hostname: None,
+        proxy: proxy.map(|proxy| proxy.to_string()),
+    })
+}
+
+/// เติมชื่อเครื่องจาก reverse DNS เมื่อเปิด `resolve_client_hostnames` (อาจใช้เวลา จึงเรียกนอก worker
+/// ที่รับ request) โดยจำผลไว้ `CLIENT_HOSTNAME_TTL` เพื่อไม่ให้ค้นหาทุกงาน
+pub(crate) fn resolve_client_hostname(client: &mut Option<ClientIdentity>, config: &AppConfig) {
+    static HOSTNAMES: Mutex<BTreeMap<IpAddr, (Instant, Option<String>)>> =
+        Mutex::new(BTreeMap::new());
+    let Some(client) = client.as_mut().filter(|_| config.resolve_client_hostnames) else {
+        return;
+    };
+    let Ok(ip) = client.ip.parse::<IpAddr>() else {
+        return;
+    };
+    let cached = HOSTNAMES
+        .lock()
+        .unwrap()
+        .get(&ip)
+        .filter(|(resolved_at, _)| resolved_at.elapsed() < CLIENT_HOSTNAME_TTL)
+        .map(|(_, hostname)| hostname.clone());
+    client.hostname = match cached {
+        Some(hostname) => hostname,
+        None => {
+            let hostname = reverse_lookup(ip);
+            let mut hostnames = HOSTNAMES.lock().unwrap();
+            if hostnames.len() >= MAX_CLIENT_HOSTNAMES {
+                hostnames.retain(|_, (resolved_at, _)| resolved_at.elapsed() < CLIENT_HOSTNAME_TTL);
+            }
+            hostnames.insert(ip, (Instant::now(), hostname.clone()));
+            hostname
+        }
+    };
+}
+
+/// `sockaddr_in` ของ Windows และ Linux
+#[repr(C)]
+pub(crate) struct SockAddrV4 {
+    family: u16,
+    port: u16,
+    addr: [u8; 4],
+    zero: [u8; 8],
+}
+
+/// `sockaddr_in6` ของ Windows และ Linux
+#[repr(C)]
+pub(crate) struct SockAddrV6 {
+    family: u16,
+    port: u16,
+    flowinfo: u32,
+    addr: [u8; 16],
+    scope_id: u32,
+}
+
+/// ชื่อเครื่องของ `ip` จาก reverse DNS ด้วย `getnameinfo` (`None` = ไม่มีชื่อ)
+#[cfg(any(windows, target_os = "linux"))]
+pub(crate) fn reverse_lookup(ip: IpAddr) -> Option<String> {
+    #[cfg(windows)]
+    #[link(name = "ws2_32")]
+    extern "system" {
+        fn getnameinfo(
+            addr: *const std::ffi::c_void,
+            addr_len: i32,
+            host: *mut u8,
+            host_len: u32,
+            service: *mut u8,
+            service_len: u32,
+            flags: i32,
+        ) -> i32;
+    }
+    #[cfg(target_os = "linux")]
+    extern "C" {
+        fn getnameinfo(
+            addr: *const std::ffi::c_void,
+            addr_len: u32,
+            host: *mut u8,
+            host_len: u32,
+            service: *mut u8,
+            service_len: u32,
+            flags: i32,
+        ) -> i32;
+    }
+    const AF_INET: u16 = 2;
+    #[cfg(windows)]
+    const AF_INET6: u16 = 23;
+    #[cfg(not(windows))]
+    const AF_INET6: u16 = 10;
+    #[cfg(windows)]
+    const NI_NAMEREQD: i32 = 0x04;
+    #[cfg(not(windows))]
+    const NI_NAMEREQD: i32 = 0x08;
+
+    let v4;
+    let v6;
+    let (addr, addr_len) = match ip {
+        IpAddr::V4(ip) => {
+            v4 = SockAddrV4 {
+                family: AF_INET,
+                port: 0,
+                addr: ip.octets(),
+                zero: [0; 8],
+            };
+            (
+                &v4 as *const SockAddrV4 as *const std::ffi::c_void,
+                std::mem::size_of::<SockAddrV4>(),
+            )
+        }
+        IpAddr::V6(ip) => {
+            v6 = SockAddrV6 {
+                family: AF_INET6,
+                port: 0,
+                flowinfo: 0,
+                addr: ip.octets(),
+                scope_id: 0,
+            };
+            (
+                &v6 as *const SockAddrV6 as *const std::ffi::c_void,
+                std::mem::size_of::<SockAddrV6>(),
+            )
+        }
+    };
+    // NI_MAXHOST
+    let mut host = [0u8; 1025];
+    let result = unsafe {
+        getnameinfo(
+            addr,
+            addr_len as _,
+            host.as_mut_ptr(),
+            host.len() as u32,
+            std::ptr::null_mut(),
+            0,
+            NI_NAMEREQD,
+        )
+    };
+    if result != 0 {
+        return None;
+    }
+    let len = host.iter().position(|b| *b == 0).unwrap_or(host.len());
+    std::str::from_utf8(&host[..len])
+        .ok()
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub(crate) fn reverse_lookup(_ip: IpAddr) -> Option<String> {
+    None
+}
+
+/// ` from <client>` สำหรับต่อท้ายข้อความใน log (ว่างเมื่อไม่ทราบเครื่องที่ส่งงาน)
+pub(crate) fn client_suffix(client: Option<&ClientIdentity>) -> String {
+    client.map_or_else(String::new, |client| format!(" from {}", client))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_ranges_match_by_prefix() {
+        let range = |value: &str| parse_ip_range(value).unwrap();
+        let ip = |value: &str| value.parse::<IpAddr>().unwrap();
+        assert!(ip_in_range(ip("10.1.2.3"), range("10.0.0.0/8")));
+        assert!(!ip_in_range(ip("11.0.0.1"), range("10.0.0.0/8")));
+        // ไม่สนใจบิตหลัง prefix ของ network
+        assert!(ip_in_range(ip("192.168.1.77"), range(" 192.168.1.200/24 ")));
+        assert!(ip_in_range(ip("192.168.1.77"), range("192.168.1.77")));
+        assert!(!ip_in_range(ip("192.168.1.78"), range("192.168.1.77")));
+        assert!(ip_in_range(ip("8.8.8.8"), range("0.0.0.0/0")));
+        assert!(ip_in_range(ip("fe80::1"), range("fe80::/10")));
+        assert!(!ip_in_range(ip("fe80::1"), range("0.0.0.0/0")));
+        // IPv4 ที่อยู่ในรูป IPv6 (dual-stack socket)
+        assert!(ip_in_range(ip("::ffff:10.0.0.1"), range("10.0.0.0/8")));
+        assert!(ip_in_range(ip("10.0.0.1"), range("::ffff:10.0.0.0/8")));
+
+        for value in [
+            "10.0.0.0/33",
+            "::/129",
+            "10.0.0.0/",
+            "10.0.0.0/x",
+            "10.0.0",
+            "",
+            "proxy.local",
+        ] {
+            assert!(parse_ip_range(value).is_err(), "{:?}", value);
+        }
+    }
+
+    fn proxied_request(peer: &str, headers: &[(&str, &str)]) -> HttpRequest {
+        let mut request = actix_web::test::TestRequest::default().peer_addr(peer.parse().unwrap());
+        for header in headers {
+            request = request.append_header(*header);
+        }
+        request.to_http_request()
+    }
+
+    fn client_of(peer: &str, headers: &[(&str, &str)]) -> Option<(String, Option<String>)> {
+        let config = AppConfig {
+            trusted_proxies: vec!["10.0.0.0/8".to_string(), "2001:db8::/32".to_string()],
+            ..Default::default()
+        };
+        client_address(&proxied_request(peer, headers), &config)
+            .map(|(ip, proxy)| (ip.to_string(), proxy.map(|proxy| proxy.to_string())))
+    }
+
+    fn via(ip: &str, proxy: &str) -> Option<(String, Option<String>)> {
+        Some((ip.to_string(), Some(proxy.to_string())))
+    }
+
+    #[test]
+    fn forwarded_headers_are_parsed() {
+        let chain = |headers: &[(&str, &str)]| {
+            forwarded_chain(&proxied_request("10.0.0.1:80", headers))
+                .into_iter()
+                .map(|ip| ip.map(|ip| ip.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let ip = |value: &str| Some(value.to_string());
+        assert_eq!(
+            chain(&[(
+                "Forwarded",
+                r#"for=192.0.2.60;proto=http;by=203.0.113.43, For="[2001:db8:cafe::17]:4711""#
+            )]),
+            [ip("192.0.2.60"), ip("2001:db8:cafe::17")]
+        );
+        // header หลายบรรทัดต่อกันตามลำดับ และค่าที่ไม่ใช่ IP
+        assert_eq!(
+            chain(&[
+                ("Forwarded", "for=unknown"),
+                ("Forwarded", "for=_hidden, by=10.0.0.2"),
+                ("Forwarded", "for=\"198.51.100.17:8080\""),
+            ]),
+            [None, None, None, ip("198.51.100.17")]
+        );
+        // ใช้ `Forwarded` ก่อน `X-Forwarded-For`
+        assert_eq!(
+            chain(&[
+                ("X-Forwarded-For", "203.0.113.9"),
+                ("Forwarded", "for=192.0.2.1")
+            ]),
+            [ip("192.0.2.1")]
+        );
+        assert_eq!(
+            chain(&[(
+                "X-Forwarded-For",
+                "203.0.113.9:5000, 2001:db8::1,, ::ffff:10.0.0.7"
+            )]),
+            [ip("203.0.113.9"), ip("2001:db8::1"), ip("10.0.0.7")]
+        );
+        assert!(chain(&[]).is_empty());
+    }
+
+    #[test]
+    fn forwarded_headers_are_trusted_only_from_proxies() {
+        // client ต่อตรงส่ง header ปลอมมาเอง
+        assert_eq!(
+            client_of("203.0.113.5:5000", &[("X-Forwarded-For", "10.0.0.9")]),
+            Some(("203.0.113.5".to_string(), None))
+        );
+        assert_eq!(
+            client_of("10.0.0.1:80", &[("X-Forwarded-For", "203.0.113.5")]),
+            via("203.0.113.5", "10.0.0.1")
+        );
+        // IP ที่ client ใส่ไว้หน้าค่าของ proxy ไม่ถูกใช้
+        assert_eq!(
+            client_of(
+                "10.0.0.1:80",
+                &[("X-Forwarded-For", "198.51.100.1, 203.0.113.5, 10.0.0.8")]
+            ),
+            via("203.0.113.5", "10.0.0.1")
+        );
+        // ค่าที่ไม่ใช่ IP หยุดการไล่ย้อน
+        assert_eq!(
+            client_of(
+                "10.0.0.1:80",
+                &[("Forwarded", "for=203.0.113.5, for=_hidden")]
+            ),
+            Some(("10.0.0.1".to_string(), None))
+        );
+        // ทุก hop เป็น proxy ที่เชื่อถือ
+        assert_eq!(
+            client_of(
+                "[2001:db8::2]:80",
+                &[("Forwarded", "for=10.0.0.7, for=10.0.0.8")]
+            ),
+            via("10.0.0.7", "2001:db8::2")
+        );
+        assert_eq!(
+            client_of("10.0.0.1:80", &[]),
+            Some(("10.0.0.1".to_string(), None))
+        );
+        // chain ยาวมาก (เช่น header ที่ถูกต่อซ้ำหลายครั้ง)
+        let long_chain = format!("{}203.0.113.5, 10.0.0.8", "198.51.100.1, ".repeat(2000));
+        assert_eq!(
+            client_of("10.0.0.1:80", &[("X-Forwarded-For", &long_chain)]),
+            via("203.0.113.5", "10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn client_cert_subjects_are_read_only_from_proxies() {
+        let config_with = |header: Option<&str>, proxies: &[&str]| AppConfig {
+            trusted_proxies: proxies.iter().map(|proxy| proxy.to_string()).collect(),
+            client_cert_header: header.map(str::to_string),
+            ..Default::default()
+        };
+        let config = config_with(Some("X-SSL-Client-S-DN"), &["10.0.0.0/8"]);
+        let subject = |peer: &str, headers: &[(&str, &str)]| {
+            client_cert_subject(&proxied_request(peer, headers), &config)
+        };
+        let header = "X-SSL-Client-S-DN";
+        assert_eq!(
+            subject("10.0.0.1:80", &[(header, "CN=Press-07, OU=Factory,O=Acme")]),
+            Some("cn=press-07,ou=factory,o=acme".to_string())
+        );
+        // client ที่ต่อตรงส่ง header ปลอมมาเอง
+        assert_eq!(
+            subject("203.0.113.5:5000", &[(header, "CN=press-07")]),
+            None
+        );
+        assert_eq!(subject("10.0.0.1:80", &[]), None);
+        assert_eq!(subject("10.0.0.1:80", &[(header, " , ")]), None);
+        assert_eq!(
+            subject(
+                "10.0.0.1:80",
+                &[(header, "CN=press-07"), (header, "CN=press-08")]
+            ),
+            None
+        );
+        let request = proxied_request("10.0.0.1:80", &[(header, "CN=press-07")]);
+        assert_eq!(
+            client_cert_subject(&request, &config_with(None, &["10.0.0.0/8"])),
+            None
+        );
+
+        assert!(validate_client_cert_header(&config).is_ok());
+        assert!(validate_client_cert_header(&config_with(Some(header), &[])).is_err());
+        assert!(validate_client_cert_header(&config_with(Some("X SSL"), &["10.0.0.0/8"])).is_err());
+    }
+}
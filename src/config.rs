@@ -0,0 +1,364 @@
+// ----------------------------------------------------------------------
+//                             CONFIGURATION
+// ----------------------------------------------------------------------
+
+use crate::{
+    agent::QueueRecovery,
+    archive::ArchiveConfig,
+    backend::{validate_print_backend_config, PrintBackendConfig},
+    billing::BillingConfig,
+    client_identity::{parse_ip_range, validate_client_cert_header},
+    crypto::{load_at_rest_key, AtRestKey, EncryptionConfig},
+    dispatch::{validate_backpressure_config, BackpressureConfig},
+    history::{validate_duplicate_profiles, DuplicateConfig},
+    hooks::HooksConfig,
+    ipp::validate_ipp_printers,
+    job_settings::JobSplitConfig,
+    locale::Language,
+    notify::{validate_email_config, NotificationsConfig},
+    output::{validate_output_template, OutputConfig},
+    print_tokens::{validate_print_token_config, PrintTokenConfig},
+    raster::{validate_printer_profiles, PrinterProfileConfig},
+    self_test::StartupCheckConfig,
+    stamp_font::StampFont,
+    storage::{validate_storage_config, StorageConfig},
+    tenants::{validate_sources, validate_tenants, TenantConfig},
+    transform_cache::TransformCacheConfig,
+    watermark::{validate_watermarks, WatermarkPolicy},
+};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+pub(crate) const CONFIG_PATH: &str = "./config.json";
+
+/// การตั้งค่าของ Service อ่านจากไฟล์ ./config.json (หากไม่มีไฟล์จะใช้ค่าเริ่มต้นทั้งหมด)
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct AppConfig {
+    /// ฟอนต์สำรองสำหรับฝังแทนฟอนต์ที่ไม่ได้ฝังมาในไฟล์ โดยใช้ชื่อฟอนต์ (BaseFont) เป็น key
+    /// และพาธไฟล์ .ttf/.otf เป็นค่า เช่น `{"Tahoma": "C:\\Windows\\Fonts\\tahoma.ttf"}`
+    pub(crate) font_fallbacks: HashMap<String, PathBuf>,
+    /// ฟอนต์ .ttf ที่มีอักษรไทยสำหรับวันที่และตัวเลขที่ไม่ใช่ ASCII ใน `stamp`, `page_numbers` และ `text`
+    /// เช่น `"C:\\Windows\\Fonts\\tahoma.ttf"` (ไม่ระบุ = พิมพ์ได้เฉพาะตัวอักษร ASCII)
+    pub(crate) stamp_font: Option<PathBuf>,
+    /// เครื่องพิมพ์ที่ใช้เมื่อ request ไม่ได้ระบุ `printer_name` (ไม่ระบุ = ใช้เครื่องพิมพ์เริ่มต้นของระบบ)
+    pub(crate) default_printer: Option<String>,
+    /// (ฝั่ง central server) agent ที่อนุญาตให้ดึงงานพิมพ์ โดยใช้ agent id เป็น key และ token เป็นค่า
+    pub(crate) agents: HashMap<String, String>,
+    /// (ฝั่ง agent) เมื่อระบุ Service จะดึงงานพิมพ์จาก central server มาพิมพ์ที่เครื่องนี้
+    pub(crate) agent: Option<AgentConfig>,
+    /// พอร์ตของช่องทางผู้ดูแลระบบที่รับเฉพาะการเชื่อมต่อจาก 127.0.0.1 (ไม่ระบุ = ปิด)
+    pub(crate) admin_port: Option<u16>,
+    /// token สำหรับหน้า /admin และ `/api/v1/admin/...` (ไม่ระบุ = ใช้ได้เฉพาะจากเครื่องนี้)
+    pub(crate) admin_token: Option<String>,
+    /// IP หรือช่วง IP (CIDR) ของ reverse proxy ที่เชื่อถือ `X-Forwarded-For`/`Forwarded` ได้
+    /// เช่น `["127.0.0.1", "10.0.0.0/8"]` (ไม่ระบุ = ใช้ IP ที่เชื่อมต่อเข้ามาเสมอ)
+    pub(crate) trusted_proxies: Vec<String>,
+    /// header ที่ reverse proxy ใส่ subject ของ client certificate ที่ตรวจแล้ว เช่น `X-SSL-Client-S-DN`
+    /// (อ่านเฉพาะ request จาก `trusted_proxies`, ไม่ระบุ = ไม่ใช้ client certificate)
+    pub(crate) client_cert_header: Option<String>,
+    /// ค้นหาชื่อเครื่องของ client จาก IP (reverse DNS) เพื่อบันทึกในประวัติงาน
+    pub(crate) resolve_client_hostnames: bool,
+    /// คำสั่งภายนอกที่เรียกก่อนส่งงานและหลังงานเสร็จ
+    pub(crate) hooks: HooksConfig,
+    /// ระยะเวลาที่เก็บไฟล์ที่ส่งพิมพ์ไว้สำหรับพิมพ์ซ้ำ (ชั่วโมง, ไม่ระบุ = 72, 0 = ไม่เก็บ)
+    pub(crate) job_retention_hours: Option<u64>,
+    /// ระยะเวลาที่เก็บสถิติรายชั่วโมงของเครื่องพิมพ์สำหรับ `/printers/{name}/history` (วัน, ไม่ระบุ = 90)
+    pub(crate) printer_history_days: Option<u64>,
+    /// สรุปจำนวนงานและหน้าที่พิมพ์รายเดือนสำหรับเรียกเก็บเงินแต่ละหน่วยงาน (`GET /api/v1/billing`)
+    pub(crate) billing: BillingConfig,
+    /// token ที่ลงลายมือชื่อสำหรับสั่งพิมพ์ไฟล์ที่กำหนดผ่าน `POST /api/print/token` โดยไม่ต้องใช้ API key
+    pub(crate) print_tokens: PrintTokenConfig,
+    /// อนุญาตให้ส่งงานในนามของผู้ใช้ Windows ที่เข้าสู่ระบบอยู่ด้วย `windows_user` (ค่าเริ่มต้น false)
+    pub(crate) user_context_printing: bool,
+    /// เก็บผลการแปลงไฟล์ไว้ใช้ซ้ำกับงานที่ไฟล์และ option เหมือนเดิม (ไม่ระบุ = ไม่เก็บ)
+    pub(crate) transform_cache: Option<TransformCacheConfig>,
+    /// ตรวจว่าเครื่องพิมพ์พร้อมใช้งานตอนเริ่ม Service และพิมพ์ใบ warm-up (ไม่ระบุ = ไม่ตรวจ)
+    pub(crate) startup_check: Option<StartupCheckConfig>,
+    /// สิ่งที่ทำกับงานในคิวของ agent ที่กู้คืนจาก ./printable_files/.queue ตอนเริ่ม Service
+    /// (`resume` = ส่งต่อตามปกติ (ค่าเริ่มต้น), `review` = พักไว้จนกว่าผู้ดูแลระบบจะสั่งส่งต่อหรือทิ้ง)
+    pub(crate) queue_recovery: QueueRecovery,
+    /// ข้อความหรือลายน้ำที่บังคับพิมพ์ลงบนงานของเครื่องพิมพ์หรือ tenant ที่กำหนด โดยไม่ขึ้นกับ request
+    pub(crate) watermarks: Vec<WatermarkPolicy>,
+    /// การตั้งค่าเฉพาะของเครื่องพิมพ์ โดยใช้ชื่อเครื่องพิมพ์เป็น key
+    /// เช่น `{"Cheap_Label": {"rasterize": {"dpi": 203}}}`
+    pub(crate) printer_profiles: HashMap<String, PrinterProfileConfig>,
+    /// พาธของโปรแกรม Ghostscript สำหรับ `rasterize` (ไม่ระบุ = `gswin64c` บน Windows, `gs` บนระบบอื่น)
+    pub(crate) ghostscript: Option<PathBuf>,
+    /// แบ่งงานที่มีจำนวนหน้ามากเป็นหลายงานใน spooler สำหรับเครื่องพิมพ์ที่รับงานใหญ่ไม่ได้ (ไม่ระบุ = ไม่แบ่ง)
+    pub(crate) job_split: Option<JobSplitConfig>,
+    /// เครื่องพิมพ์ที่ส่งงานผ่าน IPP โดยตรงโดยไม่ผ่าน driver ใช้ชื่อเครื่องพิมพ์เป็น key และ URI เป็นค่า
+    /// เช่น `{"Label_A6": "ipp://192.168.1.20/ipp/print"}`
+    pub(crate) ipp_printers: HashMap<String, String>,
+    /// ระบบพิมพ์ของเครื่องพิมพ์อื่นนอกจาก `ipp_printers` เช่น `{"type": "mock", ...}` (ไม่ระบุ = เครื่องพิมพ์ของระบบ)
+    pub(crate) print_backend: PrintBackendConfig,
+    /// เก็บไฟล์ที่ส่งพิมพ์และข้อมูลของ request ไว้ในคลังสำหรับตรวจสอบย้อนหลัง (ไม่ระบุ = ไม่เก็บ)
+    pub(crate) archive: Option<ArchiveConfig>,
+    /// การตั้งชื่อไฟล์ที่ส่งพิมพ์และการใช้ไฟล์ชั่วคราว
+    pub(crate) output: OutputConfig,
+    /// พอร์ตและการปรับแต่ง HTTP server (มีผลหลัง restart Service)
+    pub(crate) server: ServerConfig,
+    /// แจ้งเตือนทาง Slack, Microsoft Teams หรือ LINE เมื่องานล้มเหลวหรือเครื่องพิมพ์ offline
+    pub(crate) notifications: NotificationsConfig,
+    /// แยกไฟล์ งาน และเครื่องพิมพ์ตาม tenant โดยใช้ชื่อ tenant เป็น key
+    /// (ไม่ระบุ = ไม่ต้องใช้ API key และทุก client ใช้ ./printable_files ร่วมกัน)
+    pub(crate) tenants: HashMap<String, TenantConfig>,
+    /// โฟลเดอร์ไฟล์ต้นฉบับเพิ่มเติมที่ request เลือกได้ด้วย `source` โดยใช้ชื่อแหล่งไฟล์เป็น key
+    /// เช่น `{"invoices": "D:\\erp\\out", "labels": "\\\\nas\\labels"}`
+    pub(crate) sources: HashMap<String, PathBuf>,
+    /// ตรวจจับเอกสารเดียวกันที่ส่งไปยังเครื่องพิมพ์เดิมซ้ำในช่วงเวลาสั้นๆ (ไม่ระบุ = ไม่ตรวจ)
+    pub(crate) duplicates: Option<DuplicateConfig>,
+    /// ภาษาของข้อความข้อผิดพลาดและวันที่บนเอกสาร เมื่อ request ไม่ได้ส่ง `Accept-Language` ที่รองรับ
+    pub(crate) language: Language,
+    /// พื้นที่ว่างขั้นต่ำของดิสก์ที่เก็บไฟล์งาน ต่ำกว่านี้จะไม่รับไฟล์และงานพิมพ์ใหม่ (MB, ไม่ระบุ = 200, 0 = ไม่ตรวจ)
+    pub(crate) min_free_disk_mb: Option<u64>,
+    /// ปฏิเสธงานใหม่ด้วย HTTP 429 เมื่องานค้างเกินที่กำหนด (ไม่ระบุ = ไม่จำกัด)
+    pub(crate) backpressure: Option<BackpressureConfig>,
+    /// ที่เก็บไฟล์ต้นฉบับที่ client อัปโหลด เช่น `{"type": "s3", ...}` (ไม่ระบุ = ./printable_files)
+    pub(crate) storage: StorageConfig,
+    /// เข้ารหัสไฟล์งานที่เก็บบนดิสก์ด้วย AES-256-GCM (ไม่ระบุ = เก็บแบบไม่เข้ารหัส)
+    pub(crate) encryption: Option<EncryptionConfig>,
+    /// กุญแจที่อ่านจาก `encryption` ตอนโหลด config.json
+    #[serde(skip)]
+    pub(crate) at_rest_key: Option<AtRestKey>,
+}
+
+/// การเชื่อมต่อจาก agent ที่สาขาไปยัง central server
+#[derive(Deserialize, Clone, PartialEq)]
+pub(crate) struct AgentConfig {
+    /// URL ของ central server เช่น `http://print.example.com:8080`
+    pub(crate) server_url: String,
+    /// ชื่อ agent ต้องตรงกับ key ใน `agents` ของ central server
+    pub(crate) agent_id: String,
+    /// token ที่ตั้งไว้ใน `agents` ของ central server
+    pub(crate) token: String,
+    /// ระยะเวลาระหว่างการดึงงาน (วินาที)
+    #[serde(default = "default_poll_interval_secs")]
+    pub(crate) poll_interval_secs: u64,
+    /// ระยะเวลาระหว่างการส่ง heartbeat (วินาที)
+    #[serde(default = "default_heartbeat_interval_secs")]
+    pub(crate) heartbeat_interval_secs: u64,
+}
+
+pub(crate) fn default_poll_interval_secs() -> u64 {
+    5
+}
+
+pub(crate) fn default_heartbeat_interval_secs() -> u64 {
+    30
+}
+
+/// พอร์ตของ HTTP API เมื่อไม่ได้ระบุ `server.port`
+pub(crate) const DEFAULT_HTTP_PORT: u16 = 8080;
+
+/// การปรับแต่ง HTTP server สำหรับ client จำนวนมากที่เปิดการเชื่อมต่อสั้นๆ พร้อมกัน เช่น kiosk ตอนเริ่มกะ
+/// (ไม่ระบุ = ใช้ค่าเริ่มต้นของ actix-web)
+#[derive(Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub(crate) struct ServerConfig {
+    /// พอร์ตที่รับ request บน 127.0.0.1 (ไม่ระบุ = 8080)
+    pub(crate) port: Option<u16>,
+    /// จำนวน worker thread (ไม่ระบุ = จำนวน CPU)
+    pub(crate) workers: Option<usize>,
+    /// เวลาที่เก็บการเชื่อมต่อที่ไม่มี request ไว้ใช้ต่อ (วินาที, ไม่ระบุ = 5, 0 = ปิด keep-alive)
+    pub(crate) keep_alive_secs: Option<u64>,
+    /// เวลาที่รอให้ client ส่ง header ของ request แรกครบ มิฉะนั้นตอบ 408 (มิลลิวินาที, ไม่ระบุ = 5000, 0 = ไม่จำกัด)
+    pub(crate) client_request_timeout_ms: Option<u64>,
+    /// เวลาที่รอให้ปิดการเชื่อมต่อเสร็จ (มิลลิวินาที, ไม่ระบุ = 5000, 0 = ไม่จำกัด)
+    pub(crate) client_disconnect_timeout_ms: Option<u64>,
+    /// จำนวนการเชื่อมต่อพร้อมกันสูงสุดต่อ worker (ไม่ระบุ = 25000)
+    pub(crate) max_connections: Option<usize>,
+    /// จำนวนการเชื่อมต่อที่รอรับได้ก่อน client จะเชื่อมต่อไม่สำเร็จ (ไม่ระบุ = 2048)
+    pub(crate) backlog: Option<u32>,
+    /// รับ HTTP/2 แบบไม่เข้ารหัส (h2c) บนพอร์ตเดียวกับ HTTP/1.1
+    pub(crate) http2: bool,
+}
+
+pub(crate) fn validate_server_config(server: &ServerConfig) -> Result<()> {
+    if server.port == Some(0) {
+        bail!("server.port must be greater than 0");
+    }
+    if server.workers == Some(0) {
+        bail!("server.workers must be greater than 0");
+    }
+    if server.max_connections == Some(0) {
+        bail!("server.max_connections must be greater than 0");
+    }
+    if server.backlog == Some(0) {
+        bail!("server.backlog must be greater than 0");
+    }
+    Ok(())
+}
+
+/// อ่านการตั้งค่าจาก ./config.json หากไม่มีไฟล์จะใช้ค่าเริ่มต้น
+pub(crate) fn load_config() -> std::io::Result<AppConfig> {
+    let config_path = Path::new(CONFIG_PATH);
+    if !config_path.exists() {
+        return Ok(AppConfig::default());
+    }
+
+    let data = std::fs::read_to_string(config_path)?;
+    let mut config: AppConfig = serde_json::from_str(&data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    validate_output_template(&config.output.name_template)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    validate_tenants(&config.tenants)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    validate_sources(&config)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    validate_watermarks(&config.watermarks)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    validate_ipp_printers(&config.ipp_printers)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:#}", e)))?;
+    validate_server_config(&config.server)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    validate_printer_profiles(&config.printer_profiles)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    validate_print_token_config(&config.print_tokens)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    if let Some(Err(e)) = config
+        .trusted_proxies
+        .iter()
+        .map(|proxy| parse_ip_range(proxy))
+        .find(Result::is_err)
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            e.to_string(),
+        ));
+    }
+    validate_client_cert_header(&config)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    if config
+        .job_split
+        .as_ref()
+        .is_some_and(|split| split.max_pages == 0)
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "job_split.max_pages must be greater than 0",
+        ));
+    }
+    if let Some(path) = &config.stamp_font {
+        StampFont::load(path).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:#}", e))
+        })?;
+    }
+    if let Some(duplicates) = &config.duplicates {
+        validate_duplicate_profiles(duplicates)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    }
+    validate_storage_config(&config.storage)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    validate_print_backend_config(&config.print_backend)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    if let Some(backpressure) = &config.backpressure {
+        validate_backpressure_config(backpressure)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    }
+    if let Some(encryption) = &config.encryption {
+        config.at_rest_key = Some(load_at_rest_key(encryption).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:#}", e))
+        })?);
+    }
+    if let Some(email) = &config.notifications.email {
+        validate_email_config(email).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:#}", e))
+        })?;
+    }
+    println!("Loaded configuration from {}", CONFIG_PATH);
+    Ok(config)
+}
+
+/// การตั้งค่าที่ใช้ร่วมกันระหว่าง handler ซึ่งอ่านใหม่จาก ./config.json ได้โดยไม่ต้อง restart Service
+pub(crate) struct SharedConfig {
+    current: RwLock<Arc<AppConfig>>,
+}
+
+impl SharedConfig {
+    pub(crate) fn new(config: AppConfig) -> Self {
+        SharedConfig {
+            current: RwLock::new(Arc::new(config)),
+        }
+    }
+
+    /// การตั้งค่าปัจจุบัน (request ที่กำลังทำงานจะใช้ค่าเดิมจนจบ แม้มีการอ่านใหม่ระหว่างนั้น)
+    pub(crate) fn get(&self) -> Arc<AppConfig> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// อ่าน ./config.json ใหม่ หากไฟล์ไม่ถูกต้องจะคงค่าเดิมไว้
+    fn reload(&self) -> std::io::Result<Arc<AppConfig>> {
+        let config = Arc::new(load_config()?);
+        *self.current.write().unwrap() = config.clone();
+        Ok(config)
+    }
+}
+
+/// การตั้งค่าที่อ่านครั้งเดียวตอนเริ่ม Service ซึ่งเปลี่ยนไปแล้วแต่จะมีผลหลัง restart
+pub(crate) fn settings_requiring_restart(
+    previous: &AppConfig,
+    current: &AppConfig,
+) -> Vec<&'static str> {
+    let mut settings = vec![];
+    if previous.agent != current.agent {
+        settings.push("agent");
+    }
+    if previous.admin_port != current.admin_port {
+        settings.push("admin_port");
+    }
+    if previous.server != current.server {
+        settings.push("server");
+    }
+    if previous.billing.cost_center_key() != current.billing.cost_center_key() {
+        settings.push("billing.cost_center_key");
+    }
+    if previous.storage != current.storage {
+        settings.push("storage");
+    }
+    if previous.print_backend != current.print_backend {
+        settings.push("print_backend");
+    }
+    settings
+}
+
+/// อ่าน ./config.json ใหม่ตามคำสั่งจาก `source` (เช่น HTTP API, SIGHUP) โดยไม่กระทบงานในคิว
+/// คืนค่าการตั้งค่าใหม่และการตั้งค่าที่ต้อง restart จึงจะมีผล
+pub(crate) fn reload_config(
+    config: &SharedConfig,
+    source: &str,
+) -> std::io::Result<(Arc<AppConfig>, Vec<&'static str>)> {
+    let previous = config.get();
+    let current = config.reload().map_err(|e| {
+        eprintln!("Failed to reload {} ({}): {}", CONFIG_PATH, source, e);
+        e
+    })?;
+    println!("Configuration reloaded from {} ({})", CONFIG_PATH, source);
+    let restart_required = settings_requiring_restart(&previous, &current);
+    if !restart_required.is_empty() {
+        eprintln!(
+            "Restart the service to apply changes to: {}",
+            restart_required.join(", ")
+        );
+    }
+    Ok((current, restart_required))
+}
+
+/// อ่าน config ใหม่เมื่อได้รับ SIGHUP (เช่น `kill -HUP <pid>`)
+#[cfg(unix)]
+pub(crate) async fn reload_on_hangup(config: actix_web::web::Data<SharedConfig>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            eprintln!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        let _ = reload_config(&config, "SIGHUP");
+    }
+}
@@ -0,0 +1,190 @@
+// ----------------------------------------------------------------------
+//                               COUNTERS
+// ----------------------------------------------------------------------
+
+use crate::locale::LocaleFormat;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+/// ไฟล์เก็บค่าล่าสุดของตัวนับเลขที่ (บัตรคิว, เลขที่ใบกำกับภาษี)
+pub(crate) const COUNTERS_PATH: &str = "./printable_files/.counters.json";
+/// ความยาวสูงสุดของชื่อตัวนับ
+pub(crate) const MAX_COUNTER_NAME_CHARS: usize = 64;
+/// จำนวนหลักสูงสุดของ `{counter:<name>:<width>}`
+pub(crate) const MAX_COUNTER_WIDTH: usize = 20;
+
+/// ตัวนับเลขที่ที่เพิ่มขึ้นทีละหนึ่งและบันทึกลงไฟล์ก่อนคืนค่าทุกครั้ง
+/// เลขที่ที่คืนไปแล้วจะไม่ถูกใช้ซ้ำแม้ server จะปิดหรือ request ล้มเหลวหลังจากนั้น (เลขอาจข้ามได้ แต่ไม่ซ้ำ)
+pub(crate) struct CounterStore {
+    path: PathBuf,
+    /// ค่าล่าสุดที่คืนไปแล้วของแต่ละตัวนับ (key = `<tenant>:<name>` หรือ `<name>`)
+    values: Mutex<BTreeMap<String, u64>>,
+}
+
+/// ค่าที่ได้จาก `POST /api/v1/counters/{name}/next`
+#[derive(Serialize, ToSchema)]
+pub(crate) struct CounterValue {
+    /// ชื่อตัวนับ
+    pub(crate) name: String,
+    /// เลขที่ถัดไป (เริ่มจาก 1)
+    pub(crate) value: u64,
+}
+
+impl CounterStore {
+    /// อ่านค่าจากไฟล์ `path` (ไม่มีไฟล์ = เริ่มตัวนับทุกตัวจาก 1)
+    /// ไฟล์ที่อ่านไม่ได้ทำให้ server ไม่เริ่มทำงาน เพื่อไม่ให้ออกเลขที่ซ้ำกับที่เคยออกไปแล้ว
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let values = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("Invalid counter file {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(CounterStore {
+            path: path.to_path_buf(),
+            values: Mutex::new(values),
+        })
+    }
+
+    /// เลขที่ถัดไปของตัวนับ `name` ของ `tenant` โดยบันทึกลงไฟล์ก่อนคืนค่า
+    /// request ที่เรียกพร้อมกันจะได้เลขที่ต่างกันและเรียงตามลำดับที่ได้ lock
+    pub(crate) fn next(&self, tenant: Option<&str>, name: &str) -> std::io::Result<u64> {
+        let key = match tenant {
+            Some(tenant) => format!("{}:{}", tenant, name),
+            None => name.to_string(),
+        };
+        let mut values = self.values.lock().unwrap();
+        let value = values.get(&key).copied().unwrap_or(0) + 1;
+        let mut updated = values.clone();
+        updated.insert(key, value);
+        // เขียนไฟล์ชั่วคราวและ flush ลงดิสก์ก่อนแทนที่ ไฟล์เดิมจึงไม่เสียหายหากเขียนไม่สำเร็จ
+        let temp_path = self.path.with_extension("json.tmp");
+        let data = serde_json::to_vec_pretty(&updated)?;
+        std::fs::File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(&data)?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&temp_path, &self.path))?;
+        *values = updated;
+        Ok(value)
+    }
+}
+
+/// ตรวจชื่อตัวนับ: ตัวอักษรภาษาอังกฤษ ตัวเลข `-` และ `_` ไม่เกิน 64 ตัวอักษร
+pub(crate) fn validate_counter_name(name: &str) -> std::result::Result<(), String> {
+    if name.is_empty()
+        || name.len() > MAX_COUNTER_NAME_CHARS
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid counter name: {} (expected 1 to {} letters, digits, - or _)",
+            name, MAX_COUNTER_NAME_CHARS
+        ));
+    }
+    Ok(())
+}
+
+/// `{counter:<name>}` หรือ `{counter:<name>:<width>}` หนึ่งตำแหน่งในข้อความ
+pub(crate) struct CounterPlaceholder<'a> {
+    /// ช่วงของ placeholder ในข้อความ รวม `{` และ `}`
+    range: std::ops::Range<usize>,
+    name: &'a str,
+    /// จำนวนหลักขั้นต่ำ โดยเติม 0 ด้านหน้า (0 = ไม่เติม)
+    width: usize,
+}
+
+/// ตำแหน่งของ `{counter:...}` ทั้งหมดใน `text`
+pub(crate) fn counter_placeholders(
+    text: &str,
+) -> std::result::Result<Vec<CounterPlaceholder<'_>>, String> {
+    let mut placeholders = Vec::new();
+    let mut rest = 0;
+    while let Some(start) = text[rest..].find("{counter:").map(|i| rest + i) {
+        let body_start = start + "{counter:".len();
+        let Some(end) = text[body_start..].find('}').map(|i| body_start + i) else {
+            return Err(format!("unclosed {{counter:...}} in {}", text));
+        };
+        let body = &text[body_start..end];
+        let (name, width) = match body.split_once(':') {
+            Some((name, width)) => match width.parse::<usize>() {
+                Ok(width) if width <= MAX_COUNTER_WIDTH => (name, width),
+                _ => {
+                    return Err(format!(
+                        "invalid counter width {} (expected 0 to {})",
+                        width, MAX_COUNTER_WIDTH
+                    ))
+                }
+            },
+            None => (body, 0),
+        };
+        validate_counter_name(name)?;
+        placeholders.push(CounterPlaceholder {
+            range: start..end + 1,
+            name,
+            width,
+        });
+        rest = end + 1;
+    }
+    Ok(placeholders)
+}
+
+/// เลขที่ของตัวนับที่ใช้ในงานพิมพ์หนึ่งงาน ตัวนับเดียวกันในหลายขั้นตอนของงานเดียวกันได้เลขที่เดียวกัน
+pub(crate) struct JobCounters<'a> {
+    store: Option<&'a CounterStore>,
+    tenant: Option<&'a str>,
+    drawn: Mutex<HashMap<String, u64>>,
+}
+
+impl<'a> JobCounters<'a> {
+    /// `store` = `None` สำหรับการดูตัวอย่าง (เช่น `/pdf/proof`) ซึ่งแสดงเลข 0 และไม่ใช้เลขที่ของตัวนับ
+    pub(crate) fn new(store: Option<&'a CounterStore>, tenant: Option<&'a str>) -> Self {
+        JobCounters {
+            store,
+            tenant,
+            drawn: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// แทน `{counter:...}` ใน `text` ด้วยเลขที่ของตัวนับ โดยแปลงตัวเลขตาม `locale`
+    pub(crate) fn expand(&self, text: &str, locale: &LocaleFormat) -> Result<String> {
+        let placeholders = counter_placeholders(text).map_err(|e| anyhow::anyhow!(e))?;
+        let mut expanded = String::with_capacity(text.len());
+        let mut last = 0;
+        for CounterPlaceholder { range, name, width } in placeholders {
+            let value = match self.store {
+                Some(store) => {
+                    let mut drawn = self.drawn.lock().unwrap();
+                    match drawn.get(name) {
+                        Some(value) => *value,
+                        None => {
+                            let value = store.next(self.tenant, name).with_context(|| {
+                                format!("Failed to save the next value of counter {}", name)
+                            })?;
+                            println!("Counter {} issued {}", name, value);
+                            drawn.insert(name.to_string(), value);
+                            value
+                        }
+                    }
+                }
+                None => 0,
+            };
+            expanded.push_str(&text[last..range.start]);
+            expanded.push_str(&locale.localize_digits(&format!(
+                "{:0width$}",
+                value,
+                width = width
+            )));
+            last = range.end;
+        }
+        expanded.push_str(&text[last..]);
+        Ok(expanded)
+    }
+}
@@ -0,0 +1,555 @@
+// ----------------------------------------------------------------------
+//                          ENCRYPTION AT REST
+// ----------------------------------------------------------------------
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes256;
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde::Deserialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// ไบต์แรกของไฟล์ที่เข้ารหัสแล้ว (ไฟล์ที่ไม่ขึ้นต้นด้วยค่านี้ เช่น ไฟล์ที่วางไว้ก่อนเปิดการเข้ารหัส อ่านได้ตามเดิม)
+pub(crate) const SEALED_FILE_MAGIC: &[u8; 8] = b"RPAENC1\n";
+pub(crate) const GCM_NONCE_LEN: usize = 12;
+pub(crate) const GCM_TAG_LEN: usize = 16;
+
+/// การเข้ารหัสไฟล์งานบนดิสก์ (ไฟล์ที่อัปโหลด ไฟล์ที่ส่งพิมพ์ ไฟล์ที่เก็บไว้พิมพ์ซ้ำ และคลัง)
+/// ระบุ `key` หรือ `key_file` อย่างใดอย่างหนึ่ง
+#[derive(Deserialize, Clone)]
+pub(crate) struct EncryptionConfig {
+    /// กุญแจ AES-256 ขนาด 32 ไบต์ในรูปแบบ base64
+    #[serde(default)]
+    key: Option<String>,
+    /// ไฟล์กุญแจที่ป้องกันด้วย Windows DPAPI ของบัญชีที่รัน Service (สร้างกุญแจใหม่ให้เมื่อยังไม่มีไฟล์)
+    #[serde(default)]
+    key_file: Option<PathBuf>,
+}
+
+/// กุญแจ AES-256-GCM ของไฟล์งานบนดิสก์
+#[derive(Clone)]
+pub(crate) struct AtRestKey {
+    cipher: Aes256,
+    /// H = E(K, 0¹²⁸) ที่ใช้ใน GHASH
+    hash_key: u128,
+}
+
+impl AtRestKey {
+    fn new(key: &[u8; 32]) -> Self {
+        let cipher = Aes256::new(key.into());
+        let mut key = AtRestKey {
+            cipher,
+            hash_key: 0,
+        };
+        key.hash_key = key.encrypt_block(0);
+        key
+    }
+
+    fn encrypt_block(&self, block: u128) -> u128 {
+        let mut block = block.to_be_bytes().into();
+        self.cipher.encrypt_block(&mut block);
+        u128::from_be_bytes(block.into())
+    }
+
+    /// เข้ารหัสหรือถอดรหัสแบบ CTR ของ GCM โดย `data` อยู่ที่ตำแหน่งไบต์ `offset` ของข้อมูลทั้งหมด
+    /// (ใช้กับข้อมูลที่เขียนทีละส่วนได้ เพราะขนาดข้อมูลที่เข้ารหัสเท่ากับต้นฉบับ)
+    pub(crate) fn apply_keystream(
+        &self,
+        nonce: &[u8; GCM_NONCE_LEN],
+        offset: u64,
+        data: &mut [u8],
+    ) {
+        let mut counter_block = [0u8; 16];
+        counter_block[..GCM_NONCE_LEN].copy_from_slice(nonce);
+        let prefix = u128::from_be_bytes(counter_block);
+        let mut position = offset;
+        let mut i = 0;
+        while i < data.len() {
+            // counter 1 ใช้กับ tag ข้อมูลจึงเริ่มที่ counter 2
+            let counter = (position / 16 + 2) as u32;
+            let keystream = self.encrypt_block(prefix | counter as u128).to_be_bytes();
+            let skip = (position % 16) as usize;
+            let len = (16 - skip).min(data.len() - i);
+            for (byte, key) in data[i..i + len].iter_mut().zip(&keystream[skip..]) {
+                *byte ^= key;
+            }
+            i += len;
+            position += len as u64;
+        }
+    }
+
+    /// authentication tag ของ `ciphertext` (ไม่มี additional data)
+    fn tag(&self, nonce: &[u8; GCM_NONCE_LEN], ciphertext: &[u8]) -> [u8; GCM_TAG_LEN] {
+        let mut hash = Ghash::default();
+        hash.update(self.hash_key, ciphertext);
+        hash.finish(self, nonce)
+    }
+
+    /// เข้ารหัส `data` เป็น magic + nonce + ciphertext + tag
+    pub(crate) fn seal(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let sealed =
+            Vec::with_capacity(SEALED_FILE_MAGIC.len() + GCM_NONCE_LEN + data.len() + GCM_TAG_LEN);
+        let mut writer = SealWriter::new(self, sealed)?;
+        writer.write_all(data)?;
+        writer.finish()
+    }
+
+    /// ถอดรหัสข้อมูลจาก `seal` (ผิดพลาดเมื่อข้อมูลถูกแก้ไขหรือเข้ารหัสด้วยกุญแจอื่น)
+    fn open(&self, sealed: &[u8]) -> Option<Vec<u8>> {
+        let body = sealed.strip_prefix(SEALED_FILE_MAGIC.as_slice())?;
+        if body.len() < GCM_NONCE_LEN + GCM_TAG_LEN {
+            return None;
+        }
+        let (nonce, rest) = body.split_at(GCM_NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - GCM_TAG_LEN);
+        let nonce: &[u8; GCM_NONCE_LEN] = nonce.try_into().ok()?;
+        let expected = self.tag(nonce, ciphertext);
+        // เทียบทุกไบต์โดยไม่หยุดเมื่อเจอไบต์ที่ต่าง
+        if expected
+            .iter()
+            .zip(tag)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            != 0
+        {
+            return None;
+        }
+        let mut data = ciphertext.to_vec();
+        self.apply_keystream(nonce, 0, &mut data);
+        Some(data)
+    }
+}
+
+/// GHASH ที่รับ ciphertext ทีละส่วน (เก็บส่วนที่ยังไม่เต็ม block ไว้รอข้อมูลถัดไป)
+#[derive(Default)]
+struct Ghash {
+    hash: u128,
+    block: [u8; 16],
+    buffered: usize,
+    len: u64,
+}
+
+impl Ghash {
+    fn update(&mut self, hash_key: u128, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            let len = (16 - self.buffered).min(data.len());
+            self.block[self.buffered..self.buffered + len].copy_from_slice(&data[..len]);
+            self.buffered += len;
+            data = &data[len..];
+            if self.buffered == 16 {
+                self.absorb(hash_key);
+            }
+        }
+    }
+
+    fn absorb(&mut self, hash_key: u128) {
+        self.hash = gf128_mul(self.hash ^ u128::from_be_bytes(self.block), hash_key);
+        self.block = [0; 16];
+        self.buffered = 0;
+    }
+
+    fn finish(mut self, key: &AtRestKey, nonce: &[u8; GCM_NONCE_LEN]) -> [u8; GCM_TAG_LEN] {
+        if self.buffered > 0 {
+            self.absorb(key.hash_key);
+        }
+        let hash = gf128_mul(self.hash ^ (self.len as u128 * 8), key.hash_key);
+        let mut j0 = [0u8; 16];
+        j0[..GCM_NONCE_LEN].copy_from_slice(nonce);
+        j0[15] = 1;
+        (key.encrypt_block(u128::from_be_bytes(j0)) ^ hash).to_be_bytes()
+    }
+}
+
+/// เขียนข้อมูลรูปแบบเดียวกับ `seal` ลง `out` ทีละส่วน โดยไม่ต้องมีข้อมูลทั้งหมดในหน่วยความจำ
+pub(crate) struct SealWriter<'a, W: Write> {
+    key: &'a AtRestKey,
+    nonce: [u8; GCM_NONCE_LEN],
+    hash: Ghash,
+    out: W,
+}
+
+impl<'a, W: Write> SealWriter<'a, W> {
+    pub(crate) fn new(key: &'a AtRestKey, out: W) -> std::io::Result<Self> {
+        Self::with_nonce(key, random_nonce()?, out)
+    }
+
+    fn with_nonce(
+        key: &'a AtRestKey,
+        nonce: [u8; GCM_NONCE_LEN],
+        mut out: W,
+    ) -> std::io::Result<Self> {
+        out.write_all(SEALED_FILE_MAGIC)?;
+        out.write_all(&nonce)?;
+        Ok(SealWriter {
+            key,
+            nonce,
+            hash: Ghash::default(),
+            out,
+        })
+    }
+
+    pub(crate) fn write_all(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let mut encrypted = data.to_vec();
+        self.key
+            .apply_keystream(&self.nonce, self.hash.len, &mut encrypted);
+        self.hash.update(self.key.hash_key, &encrypted);
+        self.out.write_all(&encrypted)
+    }
+
+    /// เขียน tag ต่อท้ายแล้วคืน `out`
+    pub(crate) fn finish(mut self) -> std::io::Result<W> {
+        let tag = self.hash.finish(self.key, &self.nonce);
+        self.out.write_all(&tag)?;
+        Ok(self.out)
+    }
+}
+
+/// คูณใน GF(2¹²⁸) ตาม NIST SP 800-38D (ไม่มี branch ที่ขึ้นกับข้อมูล)
+pub(crate) fn gf128_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xE1 << 120;
+    let mut z = 0;
+    let mut v = y;
+    for i in 0..128 {
+        z ^= v & 0u128.wrapping_sub((x >> (127 - i)) & 1);
+        v = (v >> 1) ^ (R & 0u128.wrapping_sub(v & 1));
+    }
+    z
+}
+
+pub(crate) fn random_nonce() -> std::io::Result<[u8; GCM_NONCE_LEN]> {
+    let mut nonce = [0u8; GCM_NONCE_LEN];
+    getrandom::fill(&mut nonce).map_err(std::io::Error::other)?;
+    Ok(nonce)
+}
+
+/// อ่านกุญแจจาก `encryption` ใน config.json
+pub(crate) fn load_at_rest_key(encryption: &EncryptionConfig) -> Result<AtRestKey> {
+    let key = match (&encryption.key, &encryption.key_file) {
+        (Some(key), None) => base64::engine::general_purpose::STANDARD
+            .decode(key.trim())
+            .context("encryption.key must be base64")?,
+        (None, Some(path)) => load_dpapi_key_file(path)?,
+        _ => bail!("Set exactly one of encryption.key or encryption.key_file"),
+    };
+    let key: [u8; 32] = key.try_into().map_err(|key: Vec<u8>| {
+        anyhow::anyhow!("Encryption key must be 32 bytes, got {}", key.len())
+    })?;
+    Ok(AtRestKey::new(&key))
+}
+
+/// อ่านกุญแจจากไฟล์ที่ป้องกันด้วย DPAPI หรือสร้างกุญแจสุ่มใหม่เมื่อยังไม่มีไฟล์
+#[cfg(windows)]
+pub(crate) fn load_dpapi_key_file(path: &Path) -> Result<Vec<u8>> {
+    if path.exists() {
+        let protected =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        return dpapi_transform(&protected, false)
+            .with_context(|| format!("Failed to unprotect key file {}", path.display()));
+    }
+    let mut key = vec![0u8; 32];
+    getrandom::fill(&mut key).map_err(|e| anyhow::anyhow!("Failed to generate key: {}", e))?;
+    let protected = dpapi_transform(&key, true)?;
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, protected)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Created encryption key file {}", path.display());
+    Ok(key)
+}
+
+/// ป้องกัน (`protect`) หรือถอดข้อมูลด้วย CryptProtectData/CryptUnprotectData ของบัญชีปัจจุบัน
+#[cfg(windows)]
+pub(crate) fn dpapi_transform(data: &[u8], protect: bool) -> Result<Vec<u8>> {
+    #[repr(C)]
+    struct DataBlob {
+        len: u32,
+        data: *mut u8,
+    }
+    #[link(name = "crypt32")]
+    extern "system" {
+        fn CryptProtectData(
+            data_in: *const DataBlob,
+            description: *const u16,
+            entropy: *const DataBlob,
+            reserved: *mut std::ffi::c_void,
+            prompt: *mut std::ffi::c_void,
+            flags: u32,
+            data_out: *mut DataBlob,
+        ) -> i32;
+        fn CryptUnprotectData(
+            data_in: *const DataBlob,
+            description: *mut *mut u16,
+            entropy: *const DataBlob,
+            reserved: *mut std::ffi::c_void,
+            prompt: *mut std::ffi::c_void,
+            flags: u32,
+            data_out: *mut DataBlob,
+        ) -> i32;
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn LocalFree(memory: *mut std::ffi::c_void) -> *mut std::ffi::c_void;
+    }
+    const CRYPTPROTECT_UI_FORBIDDEN: u32 = 0x1;
+
+    let input = DataBlob {
+        len: data.len() as u32,
+        data: data.as_ptr() as *mut u8,
+    };
+    let mut output = DataBlob {
+        len: 0,
+        data: std::ptr::null_mut(),
+    };
+    let ok = unsafe {
+        if protect {
+            CryptProtectData(
+                &input,
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        } else {
+            CryptUnprotectData(
+                &input,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        }
+    };
+    if ok == 0 {
+        bail!("DPAPI failed: {}", std::io::Error::last_os_error());
+    }
+    let result = unsafe { std::slice::from_raw_parts(output.data, output.len as usize) }.to_vec();
+    unsafe { LocalFree(output.data.cast()) };
+    Ok(result)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn load_dpapi_key_file(_path: &Path) -> Result<Vec<u8>> {
+    bail!("encryption.key_file uses Windows DPAPI and is only supported on Windows; use encryption.key")
+}
+
+/// อ่านไฟล์งานและถอดรหัสในหน่วยความจำเมื่อไฟล์ถูกเข้ารหัสไว้
+pub(crate) fn read_job_file(key: Option<&AtRestKey>, path: &Path) -> std::io::Result<Vec<u8>> {
+    open_job_data(key, std::fs::read(path)?, &path.display())
+}
+
+/// ถอดรหัสข้อมูลไฟล์งาน `data` ที่อ่านมาจาก `label` เมื่อข้อมูลถูกเข้ารหัสไว้
+pub(crate) fn open_job_data(
+    key: Option<&AtRestKey>,
+    data: Vec<u8>,
+    label: &dyn std::fmt::Display,
+) -> std::io::Result<Vec<u8>> {
+    if !data.starts_with(SEALED_FILE_MAGIC) {
+        return Ok(data);
+    }
+    let key = key.ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} is encrypted but no encryption key is configured", label),
+        )
+    })?;
+    key.open(&data).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Failed to decrypt {}: file was modified or encrypted with a different key",
+                label
+            ),
+        )
+    })
+}
+
+/// เขียนไฟล์งาน โดยเข้ารหัสเมื่อตั้งค่า `encryption` ใน config.json
+pub(crate) fn write_job_file(
+    key: Option<&AtRestKey>,
+    path: &Path,
+    data: &[u8],
+) -> std::io::Result<()> {
+    match key {
+        Some(key) => std::fs::write(path, key.seal(data)?),
+        None => std::fs::write(path, data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// (key, nonce, plaintext, ciphertext, tag) จาก test case 13-15 ของ GCM specification
+    /// (AES-256, nonce 96 bit, ไม่มี additional data) กรณีที่ block สุดท้ายไม่เต็ม 16 ไบต์
+    /// และ Count 0 ของ PTlen 128 / AADlen 0 ใน gcmEncryptExtIV256.rsp ของ NIST CAVS
+    const GCM_VECTORS: [(&str, &str, &str, &str, &str); 5] = [
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "",
+            "530f8afbc74536b9a963b4f1c4cb738b",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "000000000000000000000000",
+            "00000000000000000000000000000000",
+            "cea7403d4d606b6e074ec5d3baf39d18",
+            "d0d1c8a799996bf0265b98b5d48ab919",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+             8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662898015ad",
+            "b094dac5d93471bdec1a502270e3cc6c",
+        ),
+        (
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+             8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662",
+            "eb9f796c8d356fc31a8433884b696f4f",
+        ),
+        (
+            "31bdadd96698c204aa9ce1448ea94ae1fb4a9a0b3c9d773b51bb1822666b8f22",
+            "0d18e06c7c725ac9e362e1ce",
+            "2db5168e932556f8089a0622981d017d",
+            "fa4362189661d163fcd6a56d8bf0405a",
+            "d636ac1bbedd5cc3ee727dc2ab4a9489",
+        ),
+    ];
+
+    fn at_rest_key(key: &str) -> AtRestKey {
+        AtRestKey::new(&hex(key).try_into().unwrap())
+    }
+
+    fn sealed(nonce: &[u8], ciphertext: &[u8], tag: &[u8]) -> Vec<u8> {
+        [SEALED_FILE_MAGIC.as_slice(), nonce, ciphertext, tag].concat()
+    }
+
+    #[test]
+    fn gcm_known_answer_vectors() {
+        for (key, nonce, plaintext, ciphertext, tag) in GCM_VECTORS {
+            let key = at_rest_key(key);
+            let nonce: [u8; GCM_NONCE_LEN] = hex(nonce).try_into().unwrap();
+            let (plaintext, ciphertext, tag) = (hex(plaintext), hex(ciphertext), hex(tag));
+
+            let mut encrypted = plaintext.clone();
+            key.apply_keystream(&nonce, 0, &mut encrypted);
+            assert_eq!(encrypted, ciphertext);
+            assert_eq!(key.tag(&nonce, &ciphertext).as_slice(), tag.as_slice());
+            assert_eq!(
+                key.open(&sealed(&nonce, &ciphertext, &tag)),
+                Some(plaintext)
+            );
+        }
+    }
+
+    #[test]
+    fn seal_writer_matches_known_answer_vectors() {
+        for (key, nonce, plaintext, ciphertext, tag) in GCM_VECTORS {
+            let key = at_rest_key(key);
+            let nonce: [u8; GCM_NONCE_LEN] = hex(nonce).try_into().unwrap();
+            let plaintext = hex(plaintext);
+            let expected = sealed(&nonce, &hex(ciphertext), &hex(tag));
+            // ขนาดชิ้นที่ไม่ตรงกับ block ต้องได้ผลเหมือนเขียนครั้งเดียว
+            for chunk in [1, 5, 16, 64] {
+                let mut writer = SealWriter::with_nonce(&key, nonce, Vec::new()).unwrap();
+                for part in plaintext.chunks(chunk) {
+                    writer.write_all(part).unwrap();
+                }
+                assert_eq!(writer.finish().unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn gcm_keystream_matches_at_any_offset() {
+        let key = at_rest_key(GCM_VECTORS[2].0);
+        let nonce = [7u8; GCM_NONCE_LEN];
+        let data: Vec<u8> = (0..100u8).collect();
+        let mut whole = data.clone();
+        key.apply_keystream(&nonce, 0, &mut whole);
+        // เหมือน upload ที่ส่งเป็น chunk ขนาดไม่ตรงกับ block
+        let mut chunked = data.clone();
+        for (i, chunk) in chunked.chunks_mut(7).enumerate() {
+            key.apply_keystream(&nonce, (i * 7) as u64, chunk);
+        }
+        assert_eq!(chunked, whole);
+    }
+
+    #[test]
+    fn sealed_data_round_trips() {
+        let key = at_rest_key(GCM_VECTORS[2].0);
+        for len in [0, 1, 15, 16, 17, 1000] {
+            let data = vec![0x5a; len];
+            let sealed = key.seal(&data).unwrap();
+            assert!(sealed.starts_with(SEALED_FILE_MAGIC));
+            assert_eq!(
+                sealed.len(),
+                SEALED_FILE_MAGIC.len() + GCM_NONCE_LEN + len + GCM_TAG_LEN
+            );
+            assert_eq!(key.open(&sealed), Some(data));
+        }
+        // nonce สุ่มใหม่ทุกครั้ง
+        assert_ne!(key.seal(b"same").unwrap(), key.seal(b"same").unwrap());
+    }
+
+    #[test]
+    fn seal_writer_accepts_data_in_any_chunk_size() {
+        let key = at_rest_key(GCM_VECTORS[2].0);
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        for chunk in [1, 7, 16, 33, 1000] {
+            let mut writer = SealWriter::new(&key, Vec::new()).unwrap();
+            for part in data.chunks(chunk) {
+                writer.write_all(part).unwrap();
+            }
+            let sealed = writer.finish().unwrap();
+            assert_eq!(key.open(&sealed).as_deref(), Some(data.as_slice()));
+        }
+    }
+
+    #[test]
+    fn open_rejects_modified_or_foreign_data() {
+        let key = at_rest_key(GCM_VECTORS[2].0);
+        let sealed = key.seal(b"payroll for October").unwrap();
+        let flipped = |i: usize| {
+            let mut data = sealed.clone();
+            data[i] ^= 1;
+            data
+        };
+        let header = SEALED_FILE_MAGIC.len();
+        // nonce, ciphertext และ tag
+        assert_eq!(key.open(&flipped(header)), None);
+        assert_eq!(key.open(&flipped(header + GCM_NONCE_LEN)), None);
+        assert_eq!(key.open(&flipped(sealed.len() - 1)), None);
+        assert_eq!(key.open(&sealed[..sealed.len() - 1]), None);
+        assert_eq!(key.open(&sealed[..header + GCM_NONCE_LEN]), None);
+        assert_eq!(key.open(&sealed[header..]), None);
+
+        let other = at_rest_key(GCM_VECTORS[0].0);
+        assert_eq!(other.open(&sealed), None);
+        assert_eq!(key.open(&sealed), Some(b"payroll for October".to_vec()));
+    }
+}
@@ -0,0 +1,486 @@
+// ----------------------------------------------------------------------
+//                      NETWORK PRINTER DISCOVERY
+// ----------------------------------------------------------------------
+
+use crate::backend::print_backend;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+pub(crate) const MDNS_ADDR: &str = "224.0.0.251:5353";
+pub(crate) const WS_DISCOVERY_ADDR: &str = "239.255.255.250:3702";
+// บริการ DNS-SD ของเครื่องพิมพ์: IPP, IPP over TLS, LPD และ RAW (port 9100)
+pub(crate) const MDNS_PRINTER_SERVICES: [&str; 4] = [
+    "_ipp._tcp.local",
+    "_ipps._tcp.local",
+    "_printer._tcp.local",
+    "_pdl-datastream._tcp.local",
+];
+pub(crate) const DEFAULT_DISCOVERY_TIMEOUT_MS: u64 = 2000;
+pub(crate) const MAX_DISCOVERY_TIMEOUT_MS: u64 = 10000;
+
+/// เครื่องพิมพ์ที่พบในเครือข่าย
+#[derive(Serialize, ToSchema)]
+pub(crate) struct DiscoveredPrinter {
+    /// ชื่อที่เครื่องพิมพ์ประกาศ (mDNS) หรือ endpoint address (WS-Discovery)
+    #[schema(example = "HP LaserJet M404")]
+    name: String,
+    /// IP address ของเครื่องพิมพ์
+    #[schema(example = "192.168.1.20")]
+    address: String,
+    /// วิธีที่พบ: `mdns` หรือ `ws-discovery`
+    #[schema(example = "mdns")]
+    protocol: String,
+    /// ชนิดบริการ เช่น `_ipp._tcp` หรือ `wprt:PrintDeviceType`
+    #[schema(example = "_ipp._tcp")]
+    service: String,
+    /// URI สำหรับเชื่อมต่อ เช่น `ipp://192.168.1.20:631/ipp/print`
+    #[schema(example = "ipp://192.168.1.20:631/ipp/print")]
+    uri: Option<String>,
+    /// มีเครื่องพิมพ์ที่ติดตั้งในระบบชี้ไปยัง address นี้แล้วหรือไม่
+    installed: bool,
+}
+
+/// ตำแหน่งของ resource record ใน DNS packet
+pub(crate) struct DnsRecord {
+    name: String,
+    kind: u16,
+    data_offset: usize,
+    length: usize,
+}
+
+/// เข้ารหัสชื่อโดเมนเป็น label ของ DNS
+pub(crate) fn encode_dns_name(name: &str, output: &mut Vec<u8>) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        output.push(label.len() as u8);
+        output.extend_from_slice(label.as_bytes());
+    }
+    output.push(0);
+}
+
+/// อ่านชื่อโดเมนที่ตำแหน่ง `offset` (รองรับ compression pointer) คืนค่าชื่อและตำแหน่งถัดไป
+pub(crate) fn read_dns_name(packet: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // จำกัดจำนวนรอบเพื่อป้องกัน pointer ที่วนซ้ำ
+    for _ in 0..128 {
+        let length = *packet.get(offset)? as usize;
+        if length == 0 {
+            return Some((labels.join("."), end.unwrap_or(offset + 1)));
+        }
+        if length & 0xC0 == 0xC0 {
+            let pointer = ((length & 0x3F) << 8) | *packet.get(offset + 1)? as usize;
+            end.get_or_insert(offset + 2);
+            offset = pointer;
+            continue;
+        }
+        // label ยาวได้ไม่เกิน 63 byte ส่วน prefix 0x40 และ 0x80 ยังไม่มีการกำหนดใช้
+        if length > 63 {
+            return None;
+        }
+        let label = packet.get(offset + 1..offset + 1 + length)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        offset += 1 + length;
+    }
+    None
+}
+
+/// อ่าน resource record ทั้งหมด (answer, authority และ additional) จาก DNS packet
+pub(crate) fn parse_dns_records(packet: &[u8]) -> Option<Vec<DnsRecord>> {
+    let count =
+        |at: usize| Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]) as usize);
+    let questions = count(4)?;
+    let records = count(6)? + count(8)? + count(10)?;
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        let (_, next) = read_dns_name(packet, offset)?;
+        offset = next + 4;
+    }
+    // จำนวน record มาจาก packet จึงจองพื้นที่ไม่เกินที่ packet จะมีได้จริง (record ละอย่างน้อย 11 byte)
+    let mut result = Vec::with_capacity(records.min(packet.len() / 11));
+    for _ in 0..records {
+        let (name, next) = read_dns_name(packet, offset)?;
+        let header = packet.get(next..next + 10)?;
+        let kind = u16::from_be_bytes([header[0], header[1]]);
+        let length = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let data_offset = next + 10;
+        packet.get(data_offset..data_offset + length)?;
+        result.push(DnsRecord {
+            name,
+            kind,
+            data_offset,
+            length,
+        });
+        offset = data_offset + length;
+    }
+    Some(result)
+}
+
+/// อ่านค่าของ `key` จาก TXT record ซึ่งเก็บเป็นรายการ "key=value"
+pub(crate) fn txt_value(data: &[u8], key: &str) -> Option<String> {
+    let mut offset = 0;
+    while let Some(&length) = data.get(offset) {
+        let entry = data.get(offset + 1..offset + 1 + length as usize)?;
+        if let Some((name, value)) = String::from_utf8_lossy(entry).split_once('=') {
+            if name.eq_ignore_ascii_case(key) {
+                return Some(value.to_string());
+            }
+        }
+        offset += 1 + length as usize;
+    }
+    None
+}
+
+/// รับ packet จาก socket จนกว่าจะครบเวลา `timeout`
+pub(crate) fn receive_until(socket: &UdpSocket, timeout: Duration) -> Vec<(Vec<u8>, SocketAddr)> {
+    let deadline = Instant::now() + timeout;
+    let mut packets = Vec::new();
+    let mut buffer = [0u8; 9000];
+    while let Some(remaining) = deadline
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+    {
+        if socket.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+        match socket.recv_from(&mut buffer) {
+            Ok((length, source)) => packets.push((buffer[..length].to_vec(), source)),
+            Err(_) => break,
+        }
+    }
+    packets
+}
+
+/// ค้นหาเครื่องพิมพ์ที่ประกาศตัวผ่าน mDNS/DNS-SD (Bonjour, AirPrint)
+pub(crate) fn discover_mdns(timeout: Duration) -> std::io::Result<Vec<DiscoveredPrinter>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let mut query = vec![
+        0,
+        0,
+        0,
+        0,
+        0,
+        MDNS_PRINTER_SERVICES.len() as u8,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ];
+    for service in MDNS_PRINTER_SERVICES {
+        encode_dns_name(service, &mut query);
+        // type PTR, class IN พร้อมขอคำตอบแบบ unicast
+        query.extend_from_slice(&[0, 12, 0x80, 1]);
+    }
+    socket.send_to(&query, MDNS_ADDR)?;
+
+    let mut found = Vec::new();
+    for (packet, source) in receive_until(&socket, timeout) {
+        let Some(records) = parse_dns_records(&packet) else {
+            continue;
+        };
+        let find = |kind: u16, name: &str| {
+            records
+                .iter()
+                .find(|record| record.kind == kind && record.name.eq_ignore_ascii_case(name))
+        };
+        for pointer in records.iter().filter(|record| record.kind == 12) {
+            let Some(service) = MDNS_PRINTER_SERVICES
+                .iter()
+                .find(|service| pointer.name.eq_ignore_ascii_case(service))
+            else {
+                continue;
+            };
+            let Some((instance, _)) = read_dns_name(&packet, pointer.data_offset) else {
+                continue;
+            };
+
+            // SRV record: priority, weight, port แล้วตามด้วยชื่อ host
+            let srv = find(33, &instance).filter(|record| record.length >= 7);
+            let port = srv.map(|record| {
+                u16::from_be_bytes([
+                    packet[record.data_offset + 4],
+                    packet[record.data_offset + 5],
+                ])
+            });
+            let address = srv
+                .and_then(|record| read_dns_name(&packet, record.data_offset + 6))
+                .and_then(|(host, _)| find(1, &host).filter(|record| record.length == 4))
+                .map(|record| {
+                    let ip = &packet[record.data_offset..record.data_offset + 4];
+                    Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]).to_string()
+                })
+                .unwrap_or_else(|| source.ip().to_string());
+            // "rp" ใน TXT record คือ resource path ของเครื่องพิมพ์ เช่น "ipp/print"
+            let resource_path = find(16, &instance)
+                .and_then(|record| {
+                    txt_value(
+                        &packet[record.data_offset..record.data_offset + record.length],
+                        "rp",
+                    )
+                })
+                .unwrap_or_default();
+
+            let service = service.trim_end_matches(".local");
+            let uri = port.map(|port| match service {
+                "_ipp._tcp" => format!("ipp://{}:{}/{}", address, port, resource_path),
+                "_ipps._tcp" => format!("ipps://{}:{}/{}", address, port, resource_path),
+                "_printer._tcp" => format!("lpd://{}:{}/{}", address, port, resource_path),
+                _ => format!("socket://{}:{}", address, port),
+            });
+            let name = instance
+                .strip_suffix(&format!(".{}.local", service))
+                .unwrap_or(&instance)
+                .to_string();
+            found.push(DiscoveredPrinter {
+                name,
+                address,
+                protocol: "mdns".to_string(),
+                service: service.to_string(),
+                uri,
+                installed: false,
+            });
+        }
+    }
+    Ok(found)
+}
+
+/// อ่านข้อความภายใน element แรกที่ชื่อ `tag` (ไม่สนใจ namespace prefix)
+pub(crate) fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!(":{}>", tag)).map(|i| i + tag.len() + 2)?;
+    let end = xml[start..].find('<')? + start;
+    Some(xml[start..end].trim())
+}
+
+/// ค้นหาเครื่องพิมพ์ที่รองรับ WS-Discovery (Web Services for Devices ของ Windows)
+pub(crate) fn discover_ws(timeout: Duration) -> std::io::Result<Vec<DiscoveredPrinter>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let probe = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:wsd="http://schemas.xmlsoap.org/ws/2005/04/discovery" xmlns:wprt="http://schemas.microsoft.com/windows/2006/08/wdp/print">
+<soap:Header><wsa:To>urn:schemas-xmlsoap-org:ws:2005:04:discovery</wsa:To><wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe</wsa:Action><wsa:MessageID>urn:uuid:00000000-0000-4000-8000-{:012x}</wsa:MessageID></soap:Header>
+<soap:Body><wsd:Probe><wsd:Types>wprt:PrintDeviceType</wsd:Types></wsd:Probe></soap:Body>
+</soap:Envelope>"#,
+        nanos & 0xFFFF_FFFF_FFFF
+    );
+    socket.send_to(probe.as_bytes(), WS_DISCOVERY_ADDR)?;
+
+    let mut found = Vec::new();
+    for (packet, source) in receive_until(&socket, timeout) {
+        let xml = String::from_utf8_lossy(&packet);
+        if !xml.contains("ProbeMatch") {
+            continue;
+        }
+        // XAddrs อาจมีหลาย URL คั่นด้วยช่องว่าง ใช้ URL แรก
+        let uri = xml_text(&xml, "XAddrs")
+            .and_then(|addrs| addrs.split_whitespace().next())
+            .map(str::to_string);
+        let address = uri
+            .as_deref()
+            .and_then(|uri| uri.split("://").nth(1))
+            .and_then(|rest| rest.split(['/', ':']).next())
+            .filter(|host| !host.is_empty() && !host.starts_with('['))
+            .map(str::to_string)
+            .unwrap_or_else(|| source.ip().to_string());
+        found.push(DiscoveredPrinter {
+            name: xml_text(&xml, "Address").unwrap_or(&address).to_string(),
+            address,
+            protocol: "ws-discovery".to_string(),
+            service: "wprt:PrintDeviceType".to_string(),
+            uri,
+            installed: false,
+        });
+    }
+    Ok(found)
+}
+
+/// ค้นหาเครื่องพิมพ์ในเครือข่ายด้วย mDNS และ WS-Discovery พร้อมกัน
+/// แล้วระบุว่าเครื่องใดถูกติดตั้งในระบบแล้ว
+pub(crate) fn discover_network_printers(
+    timeout: Duration,
+) -> std::io::Result<Vec<DiscoveredPrinter>> {
+    let (mdns, ws) = std::thread::scope(|scope| {
+        let mdns = scope.spawn(|| discover_mdns(timeout));
+        let ws = discover_ws(timeout);
+        (mdns.join().unwrap_or_else(|_| Ok(vec![])), ws)
+    });
+
+    let mut found = Vec::new();
+    let mut last_error = None;
+    for result in [mdns, ws] {
+        match result {
+            Ok(printers) => found.extend(printers),
+            Err(e) => {
+                eprintln!("Printer discovery failed: {:?}", e);
+                last_error = Some(e);
+            }
+        }
+    }
+    if found.is_empty() {
+        if let Some(e) = last_error {
+            return Err(e);
+        }
+    }
+
+    // เครื่องพิมพ์หนึ่งเครื่องอาจตอบกลับมาหลายครั้ง (หลาย network interface)
+    let mut seen = BTreeSet::new();
+    found.retain(|printer| {
+        seen.insert((
+            printer.protocol.clone(),
+            printer.service.clone(),
+            printer.address.clone(),
+            printer.name.clone(),
+        ))
+    });
+
+    // เทียบ address กับ URI/port ของเครื่องพิมพ์ที่ติดตั้งไว้ เช่น "IP_192.168.1.20" หรือ "ipp://192.168.1.20/ipp"
+    let installed: Vec<String> = print_backend()
+        .printers()
+        .into_iter()
+        .map(|printer| format!("{} {}", printer.uri, printer.port_name))
+        .collect();
+    for printer in &mut found {
+        printer.installed = installed.iter().any(|target| {
+            target
+                .split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+                .any(|token| token.eq_ignore_ascii_case(&printer.address))
+        });
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// สร้าง DNS response ที่มี answer record ตามลำดับ `(name, type, data)`
+    fn dns_response(records: &[(&str, u16, &[u8])]) -> Vec<u8> {
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, records.len() as u8, 0, 0, 0, 0];
+        for (name, kind, data) in records {
+            encode_dns_name(name, &mut packet);
+            packet.extend_from_slice(&kind.to_be_bytes());
+            packet.extend_from_slice(&[0, 1, 0, 0, 0x11, 0x94]);
+            packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            packet.extend_from_slice(data);
+        }
+        packet
+    }
+
+    #[test]
+    fn dns_names_follow_compression_pointers() {
+        let mut packet = vec![0; 12];
+        encode_dns_name("_ipp._tcp.local", &mut packet);
+        // "Office._ipp._tcp.local" โดยชี้ไปยังชื่อก่อนหน้า
+        packet.extend_from_slice(&[6, b'O', b'f', b'f', b'i', b'c', b'e', 0xC0, 12]);
+        assert_eq!(
+            read_dns_name(&packet, 12),
+            Some(("_ipp._tcp.local".to_string(), 29))
+        );
+        assert_eq!(
+            read_dns_name(&packet, 29),
+            Some(("Office._ipp._tcp.local".to_string(), 38))
+        );
+        // pointer ไปข้างหน้าก็อ่านได้
+        let mut forward = vec![0xC0, 2];
+        encode_dns_name("printer.local", &mut forward);
+        assert_eq!(
+            read_dns_name(&forward, 0),
+            Some(("printer.local".to_string(), 2))
+        );
+    }
+
+    #[test]
+    fn malformed_dns_names_are_rejected() {
+        for packet in [
+            // pointer ที่ชี้ตัวเองและวนไปมาระหว่างกัน
+            &[0xC0, 0][..],
+            &[0xC0, 2, 0xC0, 0],
+            // label หรือ pointer ที่ขาดหาย
+            &[5, b'a', b'b'],
+            &[3, b'a', b'b', b'c'],
+            &[0xC0],
+            &[0xC0, 40],
+            &[],
+            // prefix 0x40 และ 0x80 ที่ยังไม่ได้กำหนด
+            &[0x41, 0],
+            &[0x80, 0],
+        ] {
+            assert_eq!(read_dns_name(packet, 0), None, "{:?}", packet);
+        }
+        // ชื่อที่มี label มากเกินกำหนด
+        let mut long = Vec::new();
+        encode_dns_name(&"a.".repeat(200), &mut long);
+        assert_eq!(read_dns_name(&long, 0), None);
+    }
+
+    #[test]
+    fn dns_records_are_located_in_packets() {
+        let packet = dns_response(&[
+            ("_ipp._tcp.local", 12, b"\x06Office\xc0\x0c"),
+            ("printer.local", 1, &[192, 168, 1, 20]),
+        ]);
+        let records = parse_dns_records(&packet).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            (records[0].name.as_str(), records[0].kind),
+            ("_ipp._tcp.local", 12)
+        );
+        assert_eq!(
+            read_dns_name(&packet, records[0].data_offset).unwrap().0,
+            "Office._ipp._tcp.local"
+        );
+        assert_eq!(
+            (records[1].name.as_str(), records[1].kind),
+            ("printer.local", 1)
+        );
+        let address = &packet[records[1].data_offset..][..records[1].length];
+        assert_eq!(address, [192, 168, 1, 20]);
+
+        // คำถามที่ถูกส่งกลับมาพร้อมคำตอบจะถูกข้ามไป
+        let mut question = Vec::new();
+        encode_dns_name("_ipp._tcp.local", &mut question);
+        question.extend_from_slice(&[0, 12, 0, 1]);
+        let mut with_question = packet.clone();
+        with_question[5] = 1;
+        with_question.splice(12..12, question);
+        assert_eq!(parse_dns_records(&with_question).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn truncated_dns_packets_are_rejected() {
+        let packet = dns_response(&[("printer.local", 1, &[192, 168, 1, 20])]);
+        // ตัดทุกตำแหน่งตั้งแต่ header จนถึงข้อมูลของ record
+        for length in 0..packet.len() {
+            assert!(parse_dns_records(&packet[..length]).is_none(), "{}", length);
+        }
+        assert!(parse_dns_records(&packet).is_some());
+
+        // จำนวน record ที่มากเกินจริงต้องไม่ทำให้จองหน่วยความจำตามตัวเลขนั้น
+        let mut inflated = packet.clone();
+        inflated[6..12].copy_from_slice(&[0xFF; 6]);
+        assert!(parse_dns_records(&inflated).is_none());
+        let mut questions = packet;
+        questions[4..6].copy_from_slice(&[0xFF; 2]);
+        assert!(parse_dns_records(&questions).is_none());
+    }
+
+    #[test]
+    fn txt_records_are_read_by_key() {
+        let data = b"\x09txtvers=1\x0cRP=ipp/print\x04note\x06empty=";
+        assert_eq!(txt_value(data, "rp").as_deref(), Some("ipp/print"));
+        assert_eq!(txt_value(data, "empty").as_deref(), Some(""));
+        assert_eq!(txt_value(data, "note"), None);
+        assert_eq!(txt_value(data, "missing"), None);
+        assert_eq!(txt_value(b"", "rp"), None);
+        // entry สุดท้ายยาวเกินข้อมูลที่มี
+        assert_eq!(txt_value(b"\x09txtvers=1\x0erp=ipp", "rp"), None);
+    }
+}
@@ -0,0 +1,158 @@
+// ----------------------------------------------------------------------
+//                              DISK SPACE
+// ----------------------------------------------------------------------
+
+use crate::{
+    config::{AppConfig, SharedConfig},
+    notify::{send_email, send_notification},
+};
+use actix_web::web;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// พื้นที่ว่างขั้นต่ำเริ่มต้นของดิสก์ที่เก็บไฟล์งาน (MB)
+pub(crate) const DEFAULT_MIN_FREE_DISK_MB: u64 = 200;
+
+/// โฟลเดอร์ที่ต้องมีพื้นที่ว่างพอสำหรับรับไฟล์และเขียนไฟล์ที่ส่งพิมพ์
+pub(crate) fn guarded_dirs(config: &AppConfig) -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("./printable_files")];
+    if config.output.temp_files {
+        dirs.push(config.output.work_dir.clone());
+    }
+    if let Some(archive) = &config.archive {
+        dirs.push(archive.dir.clone());
+    }
+    dirs
+}
+
+/// พื้นที่ว่างที่โปรแกรมนี้ใช้ได้บนดิสก์ของ `path` (ไบต์)
+#[cfg(windows)]
+pub(crate) fn free_disk_bytes(path: &Path) -> std::io::Result<u64> {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_bytes_available: *mut u64,
+            total_bytes: *mut u64,
+            total_free_bytes: *mut u64,
+        ) -> i32;
+    }
+
+    let directory: Vec<u16> = path.to_string_lossy().encode_utf16().chain([0]).collect();
+    let mut free_bytes = 0;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            directory.as_ptr(),
+            &mut free_bytes,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(free_bytes)
+}
+
+/// พื้นที่ว่างที่โปรแกรมนี้ใช้ได้บนดิสก์ของ `path` (ไบต์) จากคำสั่ง `df`
+#[cfg(not(windows))]
+pub(crate) fn free_disk_bytes(path: &Path) -> std::io::Result<u64> {
+    let output = std::process::Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .output()?;
+    // บรรทัดที่สองคือ Filesystem, 1024-blocks, Used, Available, Capacity, Mounted on
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .nth(1)
+        .and_then(|line| line.split_whitespace().nth(3))
+        .and_then(|available| available.parse::<u64>().ok())
+        .map(|kilobytes| kilobytes * 1024)
+        .ok_or_else(|| {
+            std::io::Error::other(format!(
+                "Unexpected df output: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        })
+}
+
+/// ข้อความของโฟลเดอร์แรกที่พื้นที่ว่างต่ำกว่า `min_free_disk_mb` (`None` = พื้นที่พอหรือปิดการตรวจ)
+pub(crate) fn low_disk_space(config: &AppConfig) -> Option<String> {
+    let min_free_mb = config.min_free_disk_mb.unwrap_or(DEFAULT_MIN_FREE_DISK_MB);
+    if min_free_mb == 0 {
+        return None;
+    }
+    for dir in guarded_dirs(config) {
+        // โฟลเดอร์ที่ยังไม่ถูกสร้างใช้พื้นที่ของโฟลเดอร์แม่ที่มีอยู่
+        let existing = dir
+            .ancestors()
+            .find(|path| path.exists())
+            .unwrap_or(Path::new("."));
+        match free_disk_bytes(existing) {
+            Ok(free_bytes) if free_bytes / (1024 * 1024) < min_free_mb => {
+                return Some(format!(
+                    "Low disk space on {}: {} MB free (minimum {} MB)",
+                    dir.display(),
+                    free_bytes / (1024 * 1024),
+                    min_free_mb
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!(
+                "Error checking free disk space of {}: {:?}",
+                dir.display(),
+                e
+            ),
+        }
+    }
+    None
+}
+
+/// สถานะพื้นที่ว่างของดิสก์ล่าสุด ตรวจเมื่อเริ่มทำงานและทุก 30 วินาที
+#[derive(Default)]
+pub(crate) struct DiskMonitor {
+    low_space: RwLock<Option<String>>,
+}
+
+impl DiskMonitor {
+    /// ตรวจพื้นที่ว่างใหม่ บันทึก log และแจ้งเตือนเมื่อสถานะเปลี่ยน
+    pub(crate) fn refresh(&self, config: &AppConfig) {
+        let low_space = low_disk_space(config);
+        let mut current = self.low_space.write().unwrap();
+        match (current.is_some(), &low_space) {
+            (false, Some(message)) => {
+                eprintln!("{}; rejecting uploads and print jobs", message);
+                send_notification(&config.notifications, message.clone());
+                send_email(
+                    &config.notifications,
+                    "Low disk space".to_string(),
+                    format!(
+                        "{}. Uploads and print jobs are rejected until space is freed.",
+                        message
+                    ),
+                );
+            }
+            (true, None) => {
+                let message = "Disk space has recovered; accepting uploads and print jobs again";
+                println!("{}", message);
+                send_notification(&config.notifications, message.to_string());
+            }
+            _ => {}
+        }
+        *current = low_space;
+    }
+
+    /// ข้อความเมื่อพื้นที่ว่างต่ำกว่าที่กำหนด (`None` = พื้นที่พอ)
+    pub(crate) fn low_space(&self) -> Option<String> {
+        self.low_space.read().unwrap().clone()
+    }
+}
+
+/// ตรวจพื้นที่ว่างของดิสก์ทุก 30 วินาที (ทำงานใน thread แยก)
+pub(crate) fn monitor_disk_space(config: web::Data<SharedConfig>, disk: web::Data<DiskMonitor>) {
+    loop {
+        std::thread::sleep(Duration::from_secs(30));
+        disk.refresh(&config.get());
+    }
+}
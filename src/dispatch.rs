@@ -0,0 +1,377 @@
+// ----------------------------------------------------------------------
+//                            PRINT DISPATCH
+// ----------------------------------------------------------------------
+
+use crate::{
+    backend::print_backend, ipp::IPP_DRIVER_NAME, sessions::UserSessions, spooler::SpoolerMonitor,
+};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// ลำดับงานของเครื่องพิมพ์หนึ่งเครื่อง งานได้หมายเลขตามลำดับที่รับ request และส่งไปยัง spooler ทีละงานตามหมายเลข
+#[derive(Default)]
+pub(crate) struct PrinterLane {
+    state: Mutex<LaneState>,
+    turn: std::sync::Condvar,
+}
+
+#[derive(Default)]
+pub(crate) struct LaneState {
+    next_ticket: u64,
+    /// หมายเลขของงานที่ส่งได้ในตอนนี้
+    serving: u64,
+    /// หมายเลขที่ถูกยกเลิกก่อนถึงคิว (เช่น แปลงไฟล์ไม่สำเร็จ) ซึ่งจะถูกข้ามไป
+    abandoned: BTreeSet<u64>,
+}
+
+/// ส่งงานไปยังเครื่องพิมพ์เดียวกันทีละงานตามลำดับที่รับ ส่วนงานของเครื่องพิมพ์ต่างกันส่งพร้อมกันได้
+///
+/// เช่น ใบหยิบสินค้าสองใบที่ส่งมาติดกันจะไม่สลับลำดับกันที่เครื่องพิมพ์ลาเบล แม้ใบหลังจะแปลงไฟล์เสร็จก่อน
+#[derive(Default)]
+pub(crate) struct PrintDispatcher {
+    lanes: Mutex<HashMap<String, Arc<PrinterLane>>>,
+    /// สถานะของ spooler ที่งานของเครื่องพิมพ์ในเครื่องนี้ต้องผ่าน
+    pub(crate) spooler: SpoolerMonitor,
+    /// ผู้ใช้ Windows ที่เข้าสู่ระบบอยู่ สำหรับงานที่ระบุ `windows_user`
+    pub(crate) sessions: UserSessions,
+}
+
+impl PrintDispatcher {
+    /// จองลำดับของงานบนเครื่องพิมพ์ `printer` ควรเรียกทันทีที่รับ request ก่อนแปลงไฟล์
+    pub(crate) fn reserve(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> DispatchTicket {
+        let lane = self
+            .lanes
+            .lock()
+            .unwrap()
+            .entry(printer.system_name.clone())
+            .or_default()
+            .clone();
+        let number = {
+            let mut state = lane.state.lock().unwrap();
+            state.next_ticket += 1;
+            state.next_ticket - 1
+        };
+        DispatchTicket {
+            lane,
+            number,
+            printer_name: printer.name.clone(),
+        }
+    }
+}
+
+impl PrintDispatcher {
+    /// จำนวนงานที่จองลำดับไว้แล้วแต่ยังส่งไม่เสร็จของแต่ละเครื่องพิมพ์ (ตามชื่อในระบบ) เฉพาะที่มีงานค้าง
+    pub(crate) fn queue_depths(&self) -> BTreeMap<String, usize> {
+        self.lanes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, lane)| (name.clone(), lane.depth()))
+            .filter(|(_, depth)| *depth > 0)
+            .collect()
+    }
+
+    /// งานค้างของ `printer`: งานที่รอส่งในเครื่องนี้และงานที่ยังอยู่ในคิวของ spooler
+    pub(crate) fn backlog(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> PrinterBacklog {
+        let waiting = self
+            .lanes
+            .lock()
+            .unwrap()
+            .get(&printer.system_name)
+            .map_or(0, |lane| lane.depth());
+        // เครื่องพิมพ์ IPP รับงานโดยตรงจึงไม่มีคิวของ spooler ให้นับ
+        let spooler_jobs = match printer.driver_name == IPP_DRIVER_NAME {
+            true => 0,
+            false => print_backend().active_jobs(printer).len(),
+        };
+        PrinterBacklog {
+            waiting,
+            spooler_jobs,
+        }
+    }
+}
+
+impl PrinterLane {
+    fn depth(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        (state.next_ticket - state.serving) as usize - state.abandoned.len()
+    }
+}
+
+/// งานค้างของเครื่องพิมพ์หนึ่งเครื่อง
+#[derive(Clone, Copy, Default)]
+pub(crate) struct PrinterBacklog {
+    /// งานที่รับแล้วแต่ยังไม่ถึง spooler (รวมงานที่กำลังแปลงไฟล์)
+    pub(crate) waiting: usize,
+    /// งานที่อยู่ในคิวของ spooler และยังพิมพ์ไม่เสร็จ
+    pub(crate) spooler_jobs: usize,
+}
+
+impl PrinterBacklog {
+    fn total(self) -> usize {
+        self.waiting + self.spooler_jobs
+    }
+}
+
+/// การปฏิเสธงานใหม่เมื่อมีงานค้างมากเกินไป (`backpressure` ใน config.json)
+///
+/// client ได้ HTTP 429 พร้อม `Retry-After` แทนการส่งงานเพิ่มไปยังเครื่องพิมพ์ที่กระดาษติดหรือช้า
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct BackpressureConfig {
+    /// จำนวนงานที่รอส่งในเครื่องนี้รวมทุกเครื่องพิมพ์สูงสุด (ไม่ระบุ = ไม่จำกัด)
+    pub(crate) max_queue_depth: Option<usize>,
+    /// จำนวนงานค้างของเครื่องพิมพ์หนึ่งเครื่องสูงสุด นับงานที่รอส่งและงานในคิวของ spooler (ไม่ระบุ = ไม่จำกัด)
+    pub(crate) max_printer_backlog: Option<usize>,
+    /// เวลาโดยประมาณที่เครื่องพิมพ์ใช้พิมพ์หนึ่งงาน ใช้คำนวณ `Retry-After` (วินาที)
+    seconds_per_job: u64,
+    /// `Retry-After` สูงสุด (วินาที)
+    max_retry_after_secs: u64,
+}
+
+impl Default for BackpressureConfig {
+    fn default() -> Self {
+        BackpressureConfig {
+            max_queue_depth: None,
+            max_printer_backlog: None,
+            seconds_per_job: 10,
+            max_retry_after_secs: 300,
+        }
+    }
+}
+
+pub(crate) fn validate_backpressure_config(backpressure: &BackpressureConfig) -> Result<()> {
+    if backpressure.max_queue_depth == Some(0) {
+        bail!("backpressure.max_queue_depth must be greater than 0");
+    }
+    if backpressure.max_printer_backlog == Some(0) {
+        bail!("backpressure.max_printer_backlog must be greater than 0");
+    }
+    if backpressure.seconds_per_job == 0 || backpressure.max_retry_after_secs == 0 {
+        bail!("backpressure.seconds_per_job and max_retry_after_secs must be greater than 0");
+    }
+    Ok(())
+}
+
+impl BackpressureConfig {
+    /// เหตุที่ยังรับงานของ `printer` ไม่ได้ และจำนวนวินาทีที่ควรรอ (`None` = รับงานได้)
+    pub(crate) fn rejection(
+        &self,
+        queue_depths: &BTreeMap<String, usize>,
+        backlog: PrinterBacklog,
+        printer_name: &str,
+    ) -> Option<(String, u64)> {
+        let total: usize = queue_depths.values().sum();
+        if let Some(limit) = self.max_queue_depth.filter(|limit| total >= *limit) {
+            // เครื่องพิมพ์ต่างกันส่งงานพร้อมกันได้ คิวจึงลดลงเร็วขึ้นตามจำนวนเครื่องพิมพ์ที่มีงาน
+            let excess = (total + 1 - limit) as u64;
+            let lanes = queue_depths.len().max(1) as u64;
+            return Some((
+                format!("{} jobs are waiting to be printed (limit {})", total, limit),
+                self.retry_after(excess.div_ceil(lanes)),
+            ));
+        }
+        let limit = self
+            .max_printer_backlog
+            .filter(|limit| backlog.total() >= *limit)?;
+        Some((
+            format!(
+                "Printer {} has {} jobs waiting ({} in the spooler, limit {})",
+                printer_name,
+                backlog.total(),
+                backlog.spooler_jobs,
+                limit
+            ),
+            self.retry_after((backlog.total() + 1 - limit) as u64),
+        ))
+    }
+
+    /// เวลาที่ควรรอให้งานค้าง `jobs` งานพิมพ์เสร็จ
+    fn retry_after(&self, jobs: u64) -> u64 {
+        (jobs * self.seconds_per_job).clamp(1, self.max_retry_after_secs)
+    }
+}
+
+/// ลำดับที่จองไว้บนเครื่องพิมพ์ หาก drop โดยไม่ได้ส่งงาน งานถัดไปจะได้ส่งแทน
+pub(crate) struct DispatchTicket {
+    lane: Arc<PrinterLane>,
+    number: u64,
+    printer_name: String,
+}
+
+impl DispatchTicket {
+    /// รอจนงานที่จองก่อนหน้าบนเครื่องพิมพ์เดียวกันส่งเสร็จ แล้วส่งงานนี้ด้วย `submit`
+    pub(crate) fn submit<T>(self, submit: impl FnOnce() -> T) -> T {
+        let mut state = self.lane.state.lock().unwrap();
+        if state.serving != self.number {
+            println!(
+                "Waiting for {} earlier jobs on printer {}",
+                self.number - state.serving,
+                self.printer_name
+            );
+        }
+        while state.serving != self.number {
+            state = self.lane.turn.wait(state).unwrap();
+        }
+        drop(state);
+        submit()
+    }
+}
+
+impl Drop for DispatchTicket {
+    fn drop(&mut self) {
+        let mut state = self.lane.state.lock().unwrap();
+        if state.serving != self.number {
+            state.abandoned.insert(self.number);
+            return;
+        }
+        state.serving += 1;
+        loop {
+            let next = state.serving;
+            if !state.abandoned.remove(&next) {
+                break;
+            }
+            state.serving += 1;
+        }
+        self.lane.turn.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipp::ipp_printer;
+    use std::time::Duration;
+
+    fn printer(name: &str) -> printers::common::base::printer::Printer {
+        ipp_printer(name, &format!("ipp://192.0.2.10/{}", name))
+    }
+
+    #[test]
+    fn jobs_on_one_printer_are_sent_in_the_order_received() {
+        let dispatcher = PrintDispatcher::default();
+        let label = printer("Label");
+        let tickets: Vec<_> = (0..3).map(|_| dispatcher.reserve(&label)).collect();
+        assert_eq!(
+            dispatcher.queue_depths(),
+            BTreeMap::from([("Label".to_string(), 3)])
+        );
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut tickets = tickets.into_iter();
+        let first = tickets.next().unwrap();
+        // งานหลังแปลงไฟล์เสร็จก่อนแต่ต้องรองานแรก
+        let later: Vec<_> = tickets
+            .enumerate()
+            .rev()
+            .map(|(i, ticket)| {
+                let sent = sent.clone();
+                std::thread::spawn(move || ticket.submit(|| sent.lock().unwrap().push(i + 1)))
+            })
+            .collect();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(sent.lock().unwrap().is_empty());
+
+        // เครื่องพิมพ์อื่นไม่ต้องรอคิวของ Label
+        dispatcher
+            .reserve(&printer("Office"))
+            .submit(|| sent.lock().unwrap().push(100));
+        first.submit(|| sent.lock().unwrap().push(0));
+        for thread in later {
+            thread.join().unwrap();
+        }
+        assert_eq!(*sent.lock().unwrap(), [100, 0, 1, 2]);
+        assert!(dispatcher.queue_depths().is_empty());
+    }
+
+    #[test]
+    fn abandoned_tickets_are_skipped() {
+        let dispatcher = PrintDispatcher::default();
+        let label = printer("Label");
+        let first = dispatcher.reserve(&label);
+        let failed = dispatcher.reserve(&label);
+        let last = dispatcher.reserve(&label);
+        // แปลงไฟล์ไม่สำเร็จ ticket จึงถูก drop โดยไม่ได้ส่งงาน
+        drop(failed);
+        assert_eq!(dispatcher.backlog(&label).waiting, 2);
+
+        let waiting = std::thread::spawn(move || last.submit(|| "last"));
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiting.is_finished());
+        assert_eq!(first.submit(|| "first"), "first");
+        assert_eq!(waiting.join().unwrap(), "last");
+        assert_eq!(dispatcher.backlog(&label).waiting, 0);
+
+        // ticket ที่ถึงคิวแล้วแต่ถูก drop ส่งต่อคิวให้งานถัดไปทันที
+        let head = dispatcher.reserve(&label);
+        let next = dispatcher.reserve(&label);
+        drop(head);
+        assert_eq!(next.submit(|| 1), 1);
+        assert!(dispatcher.queue_depths().is_empty());
+    }
+
+    #[test]
+    fn full_queues_reject_new_jobs_with_retry_after() {
+        let dispatcher = PrintDispatcher::default();
+        let label = printer("Label");
+        let backpressure = BackpressureConfig {
+            max_queue_depth: Some(2),
+            max_printer_backlog: Some(3),
+            ..Default::default()
+        };
+        let first = dispatcher.reserve(&label);
+        let backlog = dispatcher.backlog(&label);
+        assert_eq!(
+            backpressure.rejection(&dispatcher.queue_depths(), backlog, "Label"),
+            None
+        );
+
+        let _second = dispatcher.reserve(&label);
+        let backlog = dispatcher.backlog(&label);
+        assert_eq!(
+            backpressure.rejection(&dispatcher.queue_depths(), backlog, "Label"),
+            Some(("2 jobs are waiting to be printed (limit 2)".to_string(), 10))
+        );
+        // เมื่องานแรกส่งเสร็จ คิวต่ำกว่า limit และรับงานได้อีก
+        first.submit(|| ());
+        let backlog = dispatcher.backlog(&label);
+        assert_eq!(
+            backpressure.rejection(&dispatcher.queue_depths(), backlog, "Label"),
+            None
+        );
+
+        // งานในคิวของ spooler นับรวมใน backlog ของเครื่องพิมพ์
+        let backlog = PrinterBacklog {
+            waiting: 1,
+            spooler_jobs: 3,
+        };
+        assert_eq!(
+            backpressure.rejection(&BTreeMap::new(), backlog, "Label"),
+            Some((
+                "Printer Label has 4 jobs waiting (3 in the spooler, limit 3)".to_string(),
+                20
+            ))
+        );
+        // คิวที่กระจายหลายเครื่องพิมพ์ลดลงเร็วกว่า และ Retry-After ไม่เกิน max_retry_after_secs
+        let depths = BTreeMap::from([("Label".to_string(), 3), ("Office".to_string(), 2)]);
+        assert_eq!(
+            backpressure.rejection(&depths, PrinterBacklog::default(), "Label"),
+            Some(("5 jobs are waiting to be printed (limit 2)".to_string(), 20))
+        );
+        let depths = BTreeMap::from([("Label".to_string(), 1000)]);
+        assert_eq!(
+            backpressure
+                .rejection(&depths, PrinterBacklog::default(), "Label")
+                .map(|(_, retry_after)| retry_after),
+            Some(300)
+        );
+    }
+}
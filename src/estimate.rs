@@ -0,0 +1,105 @@
+// ----------------------------------------------------------------------
+//                            PRINT ESTIMATE
+// ----------------------------------------------------------------------
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// น้ำหนักกระดาษเมื่อ request ไม่ได้ระบุ `paper_gsm` (แกรม/ตร.ม.)
+pub(crate) const DEFAULT_PAPER_GSM: f32 = 80.0;
+/// จำนวนหน้าต่อด้านที่รองรับ และจำนวนคอลัมน์ × แถวของช่องบนกระดาษแนวตั้ง
+pub(crate) const N_UP_LAYOUTS: [(u32, (u32, u32)); 7] = [
+    (1, (1, 1)),
+    (2, (1, 2)),
+    (4, (2, 2)),
+    (6, (2, 3)),
+    (8, (2, 4)),
+    (9, (3, 3)),
+    (16, (4, 4)),
+];
+
+/// จำนวนกระดาษที่ใช้เมื่อพิมพ์งานด้วยการตั้งค่าหนึ่ง
+#[derive(Serialize, ToSchema, Clone, Debug)]
+pub(crate) struct SheetEstimate {
+    /// ขนาดกระดาษ ("Custom" = ขนาดเดียวกับหน้าของไฟล์ที่ส่งพิมพ์)
+    #[schema(example = "A4")]
+    paper: String,
+    /// จำนวนหน้าของเอกสารต่อหนึ่งด้านของกระดาษ
+    #[schema(example = 4)]
+    n_up: u32,
+    /// พิมพ์สองหน้า
+    duplex: bool,
+    /// จำนวนแผ่นทั้งหมด รวมทุกชุด
+    #[schema(example = 3)]
+    pub(crate) sheets: u64,
+    /// อัตราย่อของแต่ละหน้าเพื่อให้พอดีช่อง (1 = ขนาดจริง ไม่ขยายหน้าที่เล็กกว่าช่อง)
+    #[schema(example = 1.0)]
+    pub(crate) scale: f32,
+    /// พื้นที่กระดาษทั้งหมด (ตร.ม.)
+    #[schema(example = 0.1871)]
+    area_m2: f64,
+    /// น้ำหนักกระดาษทั้งหมด (กรัม) ตาม `paper_gsm`
+    #[schema(example = 15.0)]
+    pub(crate) paper_grams: f64,
+    /// น้ำหนักกระดาษที่ลดลงเมื่อเทียบกับการตั้งค่าของ request (%, ค่าลบ = ใช้กระดาษมากขึ้น)
+    #[schema(example = 62.5)]
+    pub(crate) saving_percent: f32,
+}
+
+pub(crate) fn is_supported_n_up(n_up: u32) -> bool {
+    N_UP_LAYOUTS.iter().any(|(n, _)| *n == n_up)
+}
+
+/// จำนวนหน้าต่อด้านที่รองรับ สำหรับข้อความ Error
+pub(crate) fn supported_n_up() -> String {
+    N_UP_LAYOUTS
+        .iter()
+        .map(|(n_up, _)| n_up.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// อัตราย่อสูงสุดที่วางหน้าขนาด `page_mm` ลงในช่องของ `n_up` บนกระดาษ `sheet_mm` ได้
+/// โดยหมุนกระดาษหรือหน้าได้ (`None` = ไม่รองรับ `n_up` นี้)
+pub(crate) fn n_up_scale(page_mm: (f32, f32), sheet_mm: (f32, f32), n_up: u32) -> Option<f32> {
+    let (_, (columns, rows)) = N_UP_LAYOUTS.iter().find(|(n, _)| *n == n_up)?;
+    let mut best = 0.0f32;
+    for (sheet_width, sheet_height) in [sheet_mm, (sheet_mm.1, sheet_mm.0)] {
+        let cell_width = sheet_width / *columns as f32;
+        let cell_height = sheet_height / *rows as f32;
+        for (page_width, page_height) in [page_mm, (page_mm.1, page_mm.0)] {
+            best = best.max((cell_width / page_width).min(cell_height / page_height));
+        }
+    }
+    Some(best.min(1.0))
+}
+
+/// ประมาณจำนวนแผ่นของงาน `pages` หน้า `copies` ชุด เมื่อพิมพ์ `n_up` หน้าต่อด้านบนกระดาษ `sheet_mm`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn estimate_sheets(
+    pages: usize,
+    copies: u32,
+    page_mm: (f32, f32),
+    paper: &str,
+    sheet_mm: (f32, f32),
+    n_up: u32,
+    duplex: bool,
+    paper_gsm: f32,
+) -> Option<SheetEstimate> {
+    let scale = n_up_scale(page_mm, sheet_mm, n_up)?;
+    // หน้าที่เหลือไม่เต็มด้านยังใช้กระดาษหนึ่งด้าน และแต่ละชุดเริ่มแผ่นใหม่
+    let sides = pages.max(1).div_ceil(n_up as usize) as u64;
+    let sheets_per_copy = if duplex { sides.div_ceil(2) } else { sides };
+    let sheets = sheets_per_copy * u64::from(copies);
+    let area_m2 = sheets as f64 * f64::from(sheet_mm.0) * f64::from(sheet_mm.1) / 1_000_000.0;
+    Some(SheetEstimate {
+        paper: paper.to_string(),
+        n_up,
+        duplex,
+        sheets,
+        scale: (scale * 100.0).round() / 100.0,
+        area_m2: (area_m2 * 10_000.0).round() / 10_000.0,
+        paper_grams: (area_m2 * f64::from(paper_gsm) * 10.0).round() / 10.0,
+        saving_percent: 0.0,
+    })
+}
@@ -0,0 +1,90 @@
+// ----------------------------------------------------------------------
+//                              EVENT LOG
+// ----------------------------------------------------------------------
+
+/// ระดับของเหตุการณ์ที่บันทึกลง Windows Event Log
+#[derive(Clone, Copy)]
+pub(crate) enum EventLevel {
+    Information,
+    Warning,
+    Error,
+}
+
+/// บันทึกเหตุการณ์ลง Application log ของ Windows Event Log (source คือ [`SERVICE_NAME`](crate::SERVICE_NAME))
+/// และพิมพ์ข้อความเดียวกันลง console
+#[cfg(windows)]
+pub(crate) fn report_event(level: EventLevel, message: &str) {
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn RegisterEventSourceW(server: *const u16, source: *const u16) -> *mut std::ffi::c_void;
+        fn ReportEventW(
+            handle: *mut std::ffi::c_void,
+            event_type: u16,
+            category: u16,
+            event_id: u32,
+            user_sid: *const std::ffi::c_void,
+            string_count: u16,
+            data_size: u32,
+            strings: *const *const u16,
+            data: *const std::ffi::c_void,
+        ) -> i32;
+        fn DeregisterEventSource(handle: *mut std::ffi::c_void) -> i32;
+    }
+    const EVENTLOG_ERROR_TYPE: u16 = 0x0001;
+    const EVENTLOG_WARNING_TYPE: u16 = 0x0002;
+    const EVENTLOG_INFORMATION_TYPE: u16 = 0x0004;
+
+    let event_type = match level {
+        EventLevel::Information => {
+            println!("{}", message);
+            EVENTLOG_INFORMATION_TYPE
+        }
+        EventLevel::Warning => {
+            eprintln!("{}", message);
+            EVENTLOG_WARNING_TYPE
+        }
+        EventLevel::Error => {
+            eprintln!("{}", message);
+            EVENTLOG_ERROR_TYPE
+        }
+    };
+    let source: Vec<u16> = crate::SERVICE_NAME.encode_utf16().chain([0]).collect();
+    let text: Vec<u16> = message.encode_utf16().chain([0]).collect();
+    let strings = [text.as_ptr()];
+    unsafe {
+        let handle = RegisterEventSourceW(std::ptr::null(), source.as_ptr());
+        if handle.is_null() {
+            eprintln!(
+                "Failed to open the Event Log: {}",
+                std::io::Error::last_os_error()
+            );
+            return;
+        }
+        if ReportEventW(
+            handle,
+            event_type,
+            0,
+            0,
+            std::ptr::null(),
+            1,
+            0,
+            strings.as_ptr(),
+            std::ptr::null(),
+        ) == 0
+        {
+            eprintln!(
+                "Failed to write to the Event Log: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        DeregisterEventSource(handle);
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn report_event(level: EventLevel, message: &str) {
+    match level {
+        EventLevel::Information => println!("{}", message),
+        EventLevel::Warning | EventLevel::Error => eprintln!("{}", message),
+    }
+}
@@ -0,0 +1,939 @@
+// ----------------------------------------------------------------------
+//                              JOB HISTORY
+// ----------------------------------------------------------------------
+
+use crate::{
+    agent::AgentJobResult,
+    archive::purge_expired_archives,
+    backend::print_backend,
+    billing::BillingLedger,
+    client_identity::ClientIdentity,
+    config::{AppConfig, SharedConfig},
+    job_settings::JobSettings,
+    notify::job_target,
+    printer_history::PrinterTimeline,
+};
+use actix_web::web;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// จำนวนงานล่าสุดที่เก็บไว้แสดงในหน้า /admin
+pub(crate) const JOB_HISTORY_LIMIT: usize = 200;
+/// โฟลเดอร์เก็บไฟล์ที่ส่งพิมพ์ของแต่ละงานสำหรับพิมพ์ซ้ำ (ชื่อไฟล์คือหมายเลขในประวัติ)
+pub(crate) const JOB_ARTIFACTS_DIR: &str = "./printable_files/.jobs";
+pub(crate) const DEFAULT_JOB_RETENTION_HOURS: u64 = 72;
+
+/// การตรวจจับงานซ้ำ เช่น ชุดใบแจ้งหนี้ที่ถูกส่งพิมพ์สองครั้ง
+#[derive(Deserialize, Clone)]
+pub(crate) struct DuplicateConfig {
+    /// ช่วงเวลาที่ถือว่าเป็นงานซ้ำนับจากงานก่อนหน้า (นาที)
+    #[serde(default = "default_duplicate_window_minutes")]
+    window_minutes: u64,
+    /// `true` = ปฏิเสธงานซ้ำจนกว่าจะส่ง `force: true`, `false` = พิมพ์และแจ้งใน `warnings`
+    #[serde(default)]
+    reject: bool,
+    /// นโยบายแยกตามกลุ่มเครื่องพิมพ์ โดยใช้ชื่อกลุ่มเป็น key เช่น `receipt` หรือ `document`
+    /// (เครื่องพิมพ์ที่ไม่อยู่ในกลุ่มใดใช้ `window_minutes` และ `reject` ด้านบน)
+    #[serde(default)]
+    profiles: HashMap<String, DuplicateProfile>,
+}
+
+/// นโยบายตรวจงานซ้ำของกลุ่มเครื่องพิมพ์ ค่าที่ไม่ระบุใช้ค่าของ `duplicates`
+#[derive(Deserialize, Clone)]
+pub(crate) struct DuplicateProfile {
+    /// ชื่อเครื่องพิมพ์ในกลุ่มนี้
+    printers: Vec<String>,
+    /// ช่วงเวลาที่ถือว่าเป็นงานซ้ำ (นาที, 0 = ไม่ตรวจ เช่น เครื่องพิมพ์ใบเสร็จที่พิมพ์ซ้ำได้ทันที)
+    #[serde(default)]
+    window_minutes: Option<u64>,
+    /// ปฏิเสธงานซ้ำของกลุ่มนี้จนกว่าจะส่ง `force: true`
+    #[serde(default)]
+    reject: Option<bool>,
+}
+
+/// นโยบายตรวจงานซ้ำที่ใช้กับเครื่องพิมพ์หนึ่งเครื่อง
+pub(crate) struct DuplicatePolicy<'a> {
+    /// ชื่อกลุ่มใน `duplicates.profiles` (`None` = ใช้ค่าเริ่มต้นของ `duplicates`)
+    pub(crate) profile: Option<&'a str>,
+    pub(crate) window_minutes: u64,
+    pub(crate) reject: bool,
+}
+
+impl DuplicateConfig {
+    /// นโยบายของเครื่องพิมพ์ `printer_name` ตามกลุ่มที่เครื่องพิมพ์อยู่
+    pub(crate) fn policy(&self, printer_name: Option<&str>) -> DuplicatePolicy<'_> {
+        let profile = printer_name.and_then(|name| {
+            self.profiles
+                .iter()
+                .find(|(_, profile)| profile.printers.iter().any(|p| p == name))
+        });
+        DuplicatePolicy {
+            profile: profile.map(|(id, _)| id.as_str()),
+            window_minutes: profile
+                .and_then(|(_, profile)| profile.window_minutes)
+                .unwrap_or(self.window_minutes),
+            reject: profile
+                .and_then(|(_, profile)| profile.reject)
+                .unwrap_or(self.reject),
+        }
+    }
+}
+
+/// ตรวจว่าเครื่องพิมพ์แต่ละเครื่องอยู่ใน `duplicates.profiles` ได้เพียงกลุ่มเดียว
+pub(crate) fn validate_duplicate_profiles(duplicates: &DuplicateConfig) -> Result<()> {
+    let mut printers = HashMap::new();
+    for (id, profile) in &duplicates.profiles {
+        for printer in &profile.printers {
+            if let Some(other) = printers.insert(printer.as_str(), id.as_str()) {
+                bail!(
+                    "Printer {} is in both duplicate profiles {} and {}",
+                    printer,
+                    other,
+                    id
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn default_duplicate_window_minutes() -> u64 {
+    10
+}
+
+/// สถานะของงานพิมพ์
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum JobStatus {
+    /// ส่งให้ระบบพิมพ์ (spooler/CUPS) แล้ว
+    Printed,
+    /// อยู่ในคิวของ agent
+    Queued,
+    Failed,
+    Cancelled,
+    /// ยังไม่ได้พิมพ์เมื่อถึง `expires_at` จึงถูกยกเลิก
+    Expired,
+}
+
+/// ขั้นตอนของงานพิมพ์ใน `timeline` ของ `GET /api/v1/jobs/{id}`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum JobStage {
+    /// รับ request แล้ว
+    Received,
+    /// ตรวจค่าในงาน ไฟล์ต้นฉบับ โควต้า และจองลำดับบนเครื่องพิมพ์แล้ว
+    Validated,
+    /// แปลงไฟล์ (ปรับขนาดเป็น A6 หรือ `transforms`) เสร็จแล้ว
+    Transformed,
+    /// เข้าคิวของ agent
+    Queued,
+    /// ส่งให้ spooler (หรือเครื่องพิมพ์ IPP) แล้ว
+    Submitted,
+    /// สถานะของงานใน spooler เปลี่ยน เช่น `pending`, `processing` หรือ `paused`
+    Spooler,
+    /// พิมพ์เสร็จ (ออกจากคิวของ spooler แล้ว หรือ agent รายงานว่าพิมพ์สำเร็จ)
+    Completed,
+    Failed,
+    Cancelled,
+    Expired,
+}
+
+impl JobStage {
+    /// ขั้นตอนที่งานไปถึงเมื่อสถานะเปลี่ยนเป็น `status`
+    pub(crate) fn of_status(status: JobStatus) -> JobStage {
+        match status {
+            JobStatus::Printed => JobStage::Completed,
+            JobStatus::Queued => JobStage::Queued,
+            JobStatus::Failed => JobStage::Failed,
+            JobStatus::Cancelled => JobStage::Cancelled,
+            JobStatus::Expired => JobStage::Expired,
+        }
+    }
+}
+
+/// เหตุการณ์หนึ่งใน `timeline` ของงาน
+#[derive(Clone, Serialize, ToSchema)]
+pub(crate) struct JobEvent {
+    stage: JobStage,
+    /// เวลาที่เกิด (RFC 3339)
+    at: String,
+    /// เวลาตั้งแต่รับงาน (มิลลิวินาที)
+    elapsed_ms: u64,
+    /// เวลาตั้งแต่เหตุการณ์ก่อนหน้า คือเวลาที่งานอยู่ในขั้นตอนก่อนหน้า (มิลลิวินาที)
+    duration_ms: u64,
+    /// รายละเอียด เช่น spooler job id หรือเหตุที่งานล้มเหลว
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(skip)]
+    time: chrono::DateTime<chrono::Local>,
+}
+
+/// ลำดับเหตุการณ์ของงานตั้งแต่รับ request จนพิมพ์เสร็จหรือล้มเหลว
+#[derive(Clone, Default)]
+pub(crate) struct JobTimeline {
+    events: Vec<JobEvent>,
+}
+
+impl JobTimeline {
+    /// เริ่ม timeline ด้วยเหตุการณ์ `received` ณ เวลาที่รับ request (`started_at`)
+    pub(crate) fn received(started_at: Instant) -> Self {
+        let mut timeline = JobTimeline::default();
+        let started = chrono::Local::now()
+            - chrono::Duration::from_std(started_at.elapsed()).unwrap_or_default();
+        timeline.push_at(JobStage::Received, None, started);
+        timeline
+    }
+
+    pub(crate) fn push(&mut self, stage: JobStage, detail: Option<String>) {
+        self.push_at(stage, detail, chrono::Local::now());
+    }
+
+    fn push_at(
+        &mut self,
+        stage: JobStage,
+        detail: Option<String>,
+        time: chrono::DateTime<chrono::Local>,
+    ) {
+        let elapsed_ms = self.events.first().map_or(0, |first| {
+            (time - first.time).num_milliseconds().max(0) as u64
+        });
+        let duration_ms = self
+            .events
+            .last()
+            .map_or(0, |last| elapsed_ms.saturating_sub(last.elapsed_ms));
+        self.events.push(JobEvent {
+            stage,
+            at: time.to_rfc3339(),
+            elapsed_ms,
+            duration_ms,
+            detail,
+            time,
+        });
+    }
+
+    pub(crate) fn events(&self) -> &[JobEvent] {
+        &self.events
+    }
+}
+
+/// ประวัติของงานพิมพ์หนึ่งงาน
+#[derive(Clone, Serialize, ToSchema)]
+pub(crate) struct JobRecord {
+    /// หมายเลขในประวัติงาน (ใช้กับ `/api/v1/admin/jobs/{id}/...`)
+    pub(crate) id: u64,
+    /// job id ของ spooler หรือของคิว agent (ไม่มีเมื่อส่งงานไม่สำเร็จ)
+    #[schema(nullable)]
+    pub(crate) job_id: Option<u64>,
+    pub(crate) filename: String,
+    #[schema(nullable)]
+    pub(crate) printer_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) agent_id: Option<String>,
+    /// tenant ที่ส่งงาน (มีเฉพาะเมื่อตั้งค่า `tenants`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) tenant: Option<String>,
+    /// 8 ตัวแรกของ SHA-256 ของ API key ที่ส่งงาน (มีเฉพาะเมื่อตั้งค่า `tenants`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) api_key: Option<String>,
+    /// เครื่องที่ส่งงาน (ไม่มีเมื่อส่งผ่านช่องทางอื่นที่ไม่ใช่ HTTP)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) client: Option<ClientIdentity>,
+    pub(crate) status: JobStatus,
+    /// เวลาที่รับงาน (RFC 3339)
+    pub(crate) submitted_at: String,
+    /// กำหนดเวลาที่ต้องพิมพ์ (RFC 3339) เลยกำหนดแล้วงานที่ยังไม่ได้พิมพ์จะถูกยกเลิก
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) expires_at: Option<String>,
+    pub(crate) pages: usize,
+    pub(crate) message: String,
+    /// SHA-256 ของไฟล์ต้นฉบับ (ใช้ตรวจจับงานซ้ำ)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) content_sha256: Option<String>,
+    /// หมายเลขงานก่อนหน้าที่ส่งเอกสารเดียวกันไปยังเครื่องพิมพ์เดิมภายใน `duplicates.window_minutes`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) duplicate_of: Option<u64>,
+    /// ไฟล์ที่ส่งพิมพ์ใน ./printable_files (ใช้สำหรับพิมพ์ซ้ำ)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) output_file: Option<String>,
+    /// ยังเก็บไฟล์ที่ส่งพิมพ์ไว้ สั่งพิมพ์ซ้ำได้
+    pub(crate) reprintable: bool,
+    /// ข้อมูลที่ client แนบมากับงาน เช่น เลขที่คำสั่งซื้อ สำหรับจับคู่งานพิมพ์กับระบบอื่น
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(example = json!({"order_id": "SO-2024-0042", "branch": "BKK-03"}))]
+    pub(crate) metadata: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub(crate) job_name: String,
+    #[serde(skip)]
+    pub(crate) settings: JobSettings,
+    /// ลำดับเหตุการณ์ของงาน (แสดงเฉพาะใน `GET /api/v1/jobs/{id}`)
+    #[serde(skip)]
+    pub(crate) timeline: JobTimeline,
+}
+
+/// สถิติงานของเครื่องพิมพ์หนึ่งเครื่องสำหรับรายงานประจำวัน
+#[derive(Default, Clone)]
+pub(crate) struct PrinterStatistics {
+    /// งานที่พิมพ์สำเร็จหรือล้มเหลว
+    pub(crate) jobs: usize,
+    /// จำนวนหน้าของงานที่พิมพ์สำเร็จ
+    pub(crate) pages: usize,
+    pub(crate) failed: usize,
+    /// งานที่ล้มเหลวติดต่อกันล่าสุด (ไม่ล้างเมื่อส่งรายงานประจำวัน)
+    consecutive_failures: u32,
+}
+
+/// สถิติตั้งแต่ส่งรายงานประจำวันครั้งล่าสุด แยกตามเครื่องพิมพ์ (ดู `job_target`)
+pub(crate) struct JobStatistics {
+    pub(crate) since: chrono::DateTime<chrono::Local>,
+    pub(crate) printers: BTreeMap<String, PrinterStatistics>,
+}
+
+impl Default for JobStatistics {
+    fn default() -> Self {
+        JobStatistics {
+            since: chrono::Local::now(),
+            printers: BTreeMap::new(),
+        }
+    }
+}
+
+/// spooler job ที่ติดตามสถานะไว้บันทึกลง `timeline` ของงาน
+pub(crate) struct SpoolerWatch {
+    /// หมายเลขในประวัติงาน
+    id: u64,
+    printer_name: String,
+    job_id: u64,
+    /// สถานะล่าสุดที่บันทึกไว้
+    state: Option<String>,
+}
+
+/// ประวัติงานพิมพ์ล่าสุด (เก็บในหน่วยความจำ)
+#[derive(Default)]
+pub(crate) struct JobHistory {
+    state: Mutex<(u64, VecDeque<JobRecord>)>,
+    statistics: Mutex<JobStatistics>,
+    /// จำนวนงานที่ส่งสำเร็จของแต่ละ tenant ในวันนั้น สำหรับ `daily_job_quota`
+    tenant_usage: Mutex<HashMap<String, (chrono::NaiveDate, u32)>>,
+    /// งานที่ส่งให้ spooler ของเครื่องนี้แล้วและมี `expires_at` (กำหนดเวลา, เครื่องพิมพ์, spooler job id)
+    deadlines: Mutex<Vec<(chrono::DateTime<chrono::Local>, String, u64)>>,
+    /// timeline ของงานที่กำลังรับและยังไม่ได้บันทึกลงประวัติ
+    traces: Mutex<HashMap<u64, JobTimeline>>,
+    /// งานใน spooler ของเครื่องนี้ที่ติดตามสถานะไว้บันทึกลง timeline
+    spooler_jobs: Mutex<Vec<SpoolerWatch>>,
+    /// สถิติรายชั่วโมงของเครื่องพิมพ์ที่เก็บไว้นานกว่าประวัติงาน
+    pub(crate) timeline: PrinterTimeline,
+    /// ยอดรายเดือนของแต่ละหน่วยงานสำหรับเรียกเก็บเงิน
+    pub(crate) billing: BillingLedger,
+    /// key ใน `metadata` ที่ระบุศูนย์ต้นทุน (`billing.cost_center_key`)
+    cost_center_key: String,
+}
+
+impl JobHistory {
+    /// ประวัติว่างที่เริ่มนับหมายเลขงานต่อจาก `last_id` พร้อม `timeline` ของเครื่องพิมพ์และยอด
+    /// `billing` ที่บันทึกไว้
+    pub(crate) fn starting_after(
+        last_id: u64,
+        timeline: PrinterTimeline,
+        billing: BillingLedger,
+        cost_center_key: &str,
+    ) -> Self {
+        JobHistory {
+            state: Mutex::new((last_id, VecDeque::new())),
+            statistics: Mutex::default(),
+            tenant_usage: Mutex::default(),
+            deadlines: Mutex::default(),
+            traces: Mutex::default(),
+            spooler_jobs: Mutex::default(),
+            timeline,
+            billing,
+            cost_center_key: cost_center_key.to_string(),
+        }
+    }
+
+    /// ติดตามงานใน spooler เพื่อยกเลิกหากยังไม่ได้พิมพ์เมื่อถึง `expires_at`
+    pub(crate) fn watch_deadline(
+        &self,
+        expires_at: chrono::DateTime<chrono::Local>,
+        printer_name: String,
+        job_id: u64,
+    ) {
+        let mut deadlines = self.deadlines.lock().unwrap();
+        deadlines.push((expires_at, printer_name, job_id));
+    }
+
+    /// งานใน spooler ที่ถึงกำหนดแล้ว (เครื่องพิมพ์, spooler job id) และเลิกติดตามงานเหล่านั้น
+    pub(crate) fn take_overdue(&self) -> Vec<(String, u64)> {
+        let now = chrono::Local::now();
+        let mut overdue = Vec::new();
+        self.deadlines
+            .lock()
+            .unwrap()
+            .retain(|(expires_at, printer_name, job_id)| {
+                let due = *expires_at <= now;
+                if due {
+                    overdue.push((printer_name.clone(), *job_id));
+                }
+                !due
+            });
+        overdue
+    }
+
+    /// เริ่มบันทึก timeline ของงาน `id` ที่รับ request เมื่อ `started_at`
+    pub(crate) fn start_trace(&self, id: u64, started_at: Instant) {
+        let timeline = JobTimeline::received(started_at);
+        self.traces.lock().unwrap().insert(id, timeline);
+    }
+
+    /// เพิ่มเหตุการณ์ใน timeline ของงาน `id` ทั้งงานที่กำลังรับและงานที่อยู่ในประวัติแล้ว
+    pub(crate) fn trace(&self, id: u64, stage: JobStage, detail: Option<String>) {
+        if let Some(timeline) = self.traces.lock().unwrap().get_mut(&id) {
+            timeline.push(stage, detail);
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if let Some(record) = state.1.iter_mut().find(|record| record.id == id) {
+            record.timeline.push(stage, detail);
+        }
+    }
+
+    /// ติดตามสถานะของ spooler job `job_ids` ของงาน `id` จนกว่างานจะออกจากคิวของ spooler
+    pub(crate) fn watch_spooler(&self, id: u64, printer_name: &str, job_ids: &[u64]) {
+        let mut watches = self.spooler_jobs.lock().unwrap();
+        watches.extend(job_ids.iter().map(|job_id| SpoolerWatch {
+            id,
+            printer_name: printer_name.to_string(),
+            job_id: *job_id,
+            state: None,
+        }));
+        // งานที่ค้างใน spooler นานจนหลุดจากประวัติแล้วไม่ต้องติดตามต่อ
+        let excess = watches.len().saturating_sub(JOB_HISTORY_LIMIT);
+        watches.drain(..excess);
+    }
+
+    /// ตรวจสถานะของงานที่ติดตามไว้ใน spooler แล้วบันทึกลง timeline เมื่อสถานะเปลี่ยน
+    /// หรือเมื่องานออกจากคิว (งานที่ถูกยกเลิกหรือหมดเวลาไปแล้วเลิกติดตาม)
+    fn refresh_spooler_jobs(&self) {
+        let mut watches = std::mem::take(&mut *self.spooler_jobs.lock().unwrap());
+        let mut active = HashMap::new();
+        let mut left = Vec::new();
+        watches.retain_mut(|watch| {
+            if !self
+                .get(watch.id)
+                .is_some_and(|record| record.status == JobStatus::Printed)
+            {
+                return false;
+            }
+            let jobs = active.entry(watch.printer_name.clone()).or_insert_with(|| {
+                print_backend()
+                    .printer(&watch.printer_name)
+                    .map(|printer| print_backend().active_jobs(&printer))
+            });
+            // เครื่องพิมพ์ถูกลบไปแล้ว ไม่รู้ว่างานพิมพ์เสร็จหรือไม่
+            let Some(jobs) = jobs else {
+                return false;
+            };
+            match jobs.iter().find(|job| job.id == watch.job_id) {
+                Some(job) => {
+                    let state = format!("{:?}", job.state).to_lowercase();
+                    if watch.state.as_ref() != Some(&state) {
+                        self.trace(
+                            watch.id,
+                            JobStage::Spooler,
+                            Some(format!("Spooler job {} is {}", watch.job_id, state)),
+                        );
+                        watch.state = Some(state);
+                    }
+                    true
+                }
+                None => {
+                    left.push((watch.id, watch.job_id));
+                    false
+                }
+            }
+        });
+        let mut current = self.spooler_jobs.lock().unwrap();
+        watches.append(&mut current);
+        for (id, job_id) in left {
+            // งานที่ถูกแบ่งตาม `job_split` พิมพ์เสร็จเมื่อทุกส่วนออกจากคิวแล้ว
+            let stage = match watches.iter().any(|watch| watch.id == id) {
+                true => JobStage::Spooler,
+                false => JobStage::Completed,
+            };
+            self.trace(
+                id,
+                stage,
+                Some(format!("Spooler job {} left the queue", job_id)),
+            );
+        }
+        *current = watches;
+    }
+
+    /// จำนวนงานที่ `tenant` ส่งพิมพ์หรือเข้าคิวแล้ววันนี้
+    pub(crate) fn jobs_today(&self, tenant: &str) -> u32 {
+        let today = chrono::Local::now().date_naive();
+        match self.tenant_usage.lock().unwrap().get(tenant) {
+            Some((day, jobs)) if *day == today => *jobs,
+            _ => 0,
+        }
+    }
+
+    /// นับงานที่พิมพ์สำเร็จหรือล้มเหลวเข้าในสถิติ (งานในคิว agent นับเมื่อ agent รายงานผล)
+    fn count(&self, record: &JobRecord) {
+        let mut statistics = self.statistics.lock().unwrap();
+        let printer = statistics.printers.entry(job_target(record)).or_default();
+        match record.status {
+            JobStatus::Printed => {
+                printer.jobs += 1;
+                printer.pages += record.pages;
+                printer.consecutive_failures = 0;
+                self.billing.count(record, &self.cost_center_key);
+            }
+            JobStatus::Failed => {
+                printer.jobs += 1;
+                printer.failed += 1;
+                printer.consecutive_failures += 1;
+            }
+            JobStatus::Queued | JobStatus::Cancelled | JobStatus::Expired => {}
+        }
+    }
+
+    /// จำนวนงานที่ล้มเหลวติดต่อกันของเครื่องพิมพ์ที่ใช้กับ `record`
+    pub(crate) fn consecutive_failures(&self, record: &JobRecord) -> u32 {
+        let statistics = self.statistics.lock().unwrap();
+        statistics
+            .printers
+            .get(&job_target(record))
+            .map_or(0, |printer| printer.consecutive_failures)
+    }
+
+    /// คืนค่าสถิติตั้งแต่รายงานครั้งล่าสุด แล้วเริ่มนับใหม่
+    pub(crate) fn take_statistics(&self) -> JobStatistics {
+        let mut statistics = self.statistics.lock().unwrap();
+        let printers = statistics
+            .printers
+            .iter_mut()
+            .map(|(name, printer)| {
+                let taken = printer.clone();
+                *printer = PrinterStatistics {
+                    consecutive_failures: printer.consecutive_failures,
+                    ..Default::default()
+                };
+                (name.clone(), taken)
+            })
+            .filter(|(_, printer)| printer.jobs > 0)
+            .collect();
+        JobStatistics {
+            since: std::mem::replace(&mut statistics.since, chrono::Local::now()),
+            printers,
+        }
+    }
+
+    /// จองหมายเลขงานไว้ก่อนบันทึก (เช่น เพื่อใช้ตั้งชื่อไฟล์ของงาน)
+    pub(crate) fn reserve_id(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        state.0
+    }
+
+    /// บันทึกงานใหม่ (หาก `id` ของ `record` เป็น 0 จะกำหนดหมายเลขใหม่ให้) คืนค่าหมายเลขในประวัติ
+    pub(crate) fn record(&self, mut record: JobRecord) -> u64 {
+        self.count(&record);
+        if let Some(timeline) = self.traces.lock().unwrap().remove(&record.id) {
+            if record.timeline.events().is_empty() {
+                record.timeline = timeline;
+            }
+        }
+        if let (Some(tenant), JobStatus::Printed | JobStatus::Queued) =
+            (&record.tenant, record.status)
+        {
+            let today = chrono::Local::now().date_naive();
+            let mut usage = self.tenant_usage.lock().unwrap();
+            let (day, jobs) = usage.entry(tenant.clone()).or_insert((today, 0));
+            if *day != today {
+                *day = today;
+                *jobs = 0;
+            }
+            *jobs += 1;
+        }
+        let mut state = self.state.lock().unwrap();
+        if record.id == 0 {
+            state.0 += 1;
+            record.id = state.0;
+        }
+        let id = record.id;
+        state.1.push_front(record);
+        state.1.truncate(JOB_HISTORY_LIMIT);
+        id
+    }
+
+    pub(crate) fn get(&self, id: u64) -> Option<JobRecord> {
+        let state = self.state.lock().unwrap();
+        state.1.iter().find(|record| record.id == id).cloned()
+    }
+
+    /// งานล่าสุดที่ส่งเอกสาร `content_sha256` ไปยังเครื่องพิมพ์เดิมสำเร็จภายใน `window`
+    pub(crate) fn find_duplicate(
+        &self,
+        content_sha256: &str,
+        agent_id: Option<&str>,
+        printer_name: Option<&str>,
+        window: Duration,
+    ) -> Option<JobRecord> {
+        let since = chrono::Local::now() - chrono::Duration::from_std(window).ok()?;
+        let state = self.state.lock().unwrap();
+        state
+            .1
+            .iter()
+            .find(|record| {
+                matches!(record.status, JobStatus::Printed | JobStatus::Queued)
+                    && record.content_sha256.as_deref() == Some(content_sha256)
+                    && record.agent_id.as_deref() == agent_id
+                    && record.printer_name.as_deref() == printer_name
+                    && chrono::DateTime::parse_from_rfc3339(&record.submitted_at)
+                        .is_ok_and(|submitted_at| submitted_at >= since)
+            })
+            .cloned()
+    }
+
+    /// งานล่าสุดเรียงจากใหม่ไปเก่า
+    pub(crate) fn recent(&self) -> Vec<JobRecord> {
+        let mut records: Vec<JobRecord> = self.state.lock().unwrap().1.iter().cloned().collect();
+        for record in &mut records {
+            record.reprintable = job_artifact_path(record.id).exists();
+        }
+        records
+    }
+
+    pub(crate) fn set_status(&self, id: u64, status: JobStatus, message: String) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(record) = state.1.iter_mut().find(|record| record.id == id) {
+            record
+                .timeline
+                .push(JobStage::of_status(status), Some(message.clone()));
+            record.status = status;
+            record.message = message;
+        }
+    }
+
+    /// job id ของ spooler หรือของคิว agent `agent_id` ที่ `tenant` ส่งไปยัง `printer_name` และยังมีสถานะ `status`
+    pub(crate) fn tenant_job_ids(
+        &self,
+        status: JobStatus,
+        agent_id: Option<&str>,
+        printer_name: &str,
+        tenant: Option<&str>,
+    ) -> Vec<u64> {
+        let state = self.state.lock().unwrap();
+        state
+            .1
+            .iter()
+            .filter(|record| {
+                record.status == status
+                    && record.agent_id.as_deref() == agent_id
+                    && record.printer_name.as_deref() == Some(printer_name)
+                    && record.tenant.as_deref() == tenant
+            })
+            .filter_map(|record| record.job_id)
+            .collect()
+    }
+
+    /// เปลี่ยนงานที่มีสถานะ `status` ของ `agent_id` (ไม่ระบุ = งานที่ส่งให้ spooler ของเครื่องนี้)
+    /// และมี job id อยู่ใน `job_ids` เป็น `new_status` คืนค่างานที่ถูกเปลี่ยน
+    pub(crate) fn mark_status(
+        &self,
+        status: JobStatus,
+        new_status: JobStatus,
+        agent_id: Option<&str>,
+        job_ids: &[u64],
+        message: &str,
+    ) -> Vec<JobRecord> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .1
+            .iter_mut()
+            .filter(|record| {
+                record.status == status
+                    && record.agent_id.as_deref() == agent_id
+                    && record
+                        .job_id
+                        .is_some_and(|job_id| job_ids.contains(&job_id))
+            })
+            .map(|record| {
+                record.status = new_status;
+                record.message = message.to_string();
+                record
+                    .timeline
+                    .push(JobStage::of_status(new_status), Some(message.to_string()));
+                record.clone()
+            })
+            .collect()
+    }
+
+    /// ปรับสถานะงานในคิวตามผลที่ agent รายงานกลับมา
+    pub(crate) fn agent_result(
+        &self,
+        agent_id: &str,
+        job_id: u64,
+        result: &AgentJobResult,
+    ) -> Option<JobRecord> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(record) = state.1.iter_mut().find(|record| {
+            record.status == JobStatus::Queued
+                && record.job_id == Some(job_id)
+                && record.agent_id.as_deref() == Some(agent_id)
+        }) {
+            record.status = if result.success {
+                JobStatus::Printed
+            } else {
+                JobStatus::Failed
+            };
+            record.message = result.message.clone();
+            record.timeline.push(
+                JobStage::of_status(record.status),
+                Some(result.message.clone()),
+            );
+            let record = record.clone();
+            drop(state);
+            self.count(&record);
+            if let Some(printer_name) = &record.printer_name {
+                self.timeline
+                    .count_job(Some(agent_id), printer_name, record.pages, result.success);
+            }
+            return Some(record);
+        }
+        None
+    }
+}
+
+/// ระยะเวลาระหว่างการตรวจสถานะของงานใน spooler สำหรับ timeline ของงาน
+pub(crate) const SPOOLER_JOB_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// บันทึกสถานะของงานใน spooler ลง timeline ของงานทุก 2 วินาที (ทำงานใน thread แยก)
+pub(crate) fn track_spooler_jobs(history: web::Data<JobHistory>) {
+    loop {
+        std::thread::sleep(SPOOLER_JOB_CHECK_INTERVAL);
+        history.refresh_spooler_jobs();
+    }
+}
+
+pub(crate) fn job_retention(config: &AppConfig) -> Duration {
+    Duration::from_secs(
+        config
+            .job_retention_hours
+            .unwrap_or(DEFAULT_JOB_RETENTION_HOURS)
+            * 3600,
+    )
+}
+
+pub(crate) fn job_artifact_path(id: u64) -> PathBuf {
+    Path::new(JOB_ARTIFACTS_DIR).join(format!("{}.pdf", id))
+}
+
+/// คัดลอกไฟล์ที่ส่งพิมพ์ของงาน `id` ไว้สำหรับพิมพ์ซ้ำ (ไม่เก็บเมื่อ `job_retention_hours` เป็น 0)
+pub(crate) fn retain_job_artifact(config: &AppConfig, id: u64, source: &Path) {
+    if job_retention(config).is_zero() {
+        return;
+    }
+    let result = std::fs::create_dir_all(JOB_ARTIFACTS_DIR)
+        .and_then(|_| std::fs::copy(source, job_artifact_path(id)));
+    if let Err(e) = result {
+        eprintln!("Error retaining output of job {}: {:?}", id, e);
+    }
+}
+
+/// ลบไฟล์ของงานที่เก็บไว้เกิน `job_retention_hours` และคลังที่เกิน `retention_days` ทุกชั่วโมง
+/// (ทำงานใน thread แยก)
+pub(crate) fn run_retention_cleanup(config: web::Data<SharedConfig>) {
+    loop {
+        let current = config.get();
+        if let Some(archive) = &current.archive {
+            let removed = purge_expired_archives(archive);
+            if removed > 0 {
+                println!("Removed {} expired archive days", removed);
+            }
+        }
+        let retention = job_retention(&current);
+        let mut removed = 0;
+        if let Ok(entries) = std::fs::read_dir(JOB_ARTIFACTS_DIR) {
+            for entry in entries.flatten() {
+                let expired = entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .is_ok_and(|modified| modified.elapsed().unwrap_or_default() >= retention);
+                if expired && std::fs::remove_file(entry.path()).is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+        if removed > 0 {
+            println!("Removed {} expired job outputs", removed);
+        }
+        std::thread::sleep(Duration::from_secs(3600));
+    }
+}
+
+/// ยกเลิกงานที่ยังอยู่ใน spooler ของเครื่องพิมพ์
+#[cfg(windows)]
+pub(crate) fn cancel_spooler_job(
+    printer: &printers::common::base::printer::Printer,
+    job_id: u64,
+) -> Result<()> {
+    #[link(name = "winspool")]
+    extern "system" {
+        fn OpenPrinterW(
+            name: *const u16,
+            handle: *mut *mut std::ffi::c_void,
+            defaults: *const std::ffi::c_void,
+        ) -> i32;
+        fn SetJobW(
+            handle: *mut std::ffi::c_void,
+            job_id: u32,
+            level: u32,
+            job: *const u8,
+            command: u32,
+        ) -> i32;
+        fn ClosePrinter(handle: *mut std::ffi::c_void) -> i32;
+    }
+    const JOB_CONTROL_DELETE: u32 = 5;
+
+    let name: Vec<u16> = printer.system_name.encode_utf16().chain([0]).collect();
+    let mut handle = std::ptr::null_mut();
+    if unsafe { OpenPrinterW(name.as_ptr(), &mut handle, std::ptr::null()) } == 0 {
+        bail!(
+            "Failed to open printer {}: {}",
+            printer.name,
+            std::io::Error::last_os_error()
+        );
+    }
+    let deleted = unsafe {
+        SetJobW(
+            handle,
+            job_id as u32,
+            0,
+            std::ptr::null(),
+            JOB_CONTROL_DELETE,
+        )
+    };
+    let error = std::io::Error::last_os_error();
+    unsafe { ClosePrinter(handle) };
+    if deleted == 0 {
+        bail!(
+            "Failed to cancel job {} on {}: {}",
+            job_id,
+            printer.name,
+            error
+        );
+    }
+    Ok(())
+}
+
+/// ยกเลิกงานที่ยังอยู่ในคิวของ CUPS
+#[cfg(not(windows))]
+pub(crate) fn cancel_spooler_job(
+    printer: &printers::common::base::printer::Printer,
+    job_id: u64,
+) -> Result<()> {
+    let output = std::process::Command::new("cancel")
+        .arg(format!("{}-{}", printer.system_name, job_id))
+        .output()
+        .context("Failed to run cancel")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to cancel job {} on {}: {}",
+            job_id,
+            printer.name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// หยุดหรือให้ spooler ส่งงานไปยังเครื่องพิมพ์ต่อ (งานที่ส่งเข้ามาระหว่างหยุดยังรับไว้ในคิวตามปกติ)
+#[cfg(windows)]
+pub(crate) fn set_spooler_paused(
+    printer: &printers::common::base::printer::Printer,
+    paused: bool,
+) -> Result<()> {
+    #[link(name = "winspool")]
+    extern "system" {
+        fn OpenPrinterW(
+            name: *const u16,
+            handle: *mut *mut std::ffi::c_void,
+            defaults: *const PrinterDefaults,
+        ) -> i32;
+        fn SetPrinterW(
+            handle: *mut std::ffi::c_void,
+            level: u32,
+            printer: *const u8,
+            command: u32,
+        ) -> i32;
+        fn ClosePrinter(handle: *mut std::ffi::c_void) -> i32;
+    }
+    #[repr(C)]
+    struct PrinterDefaults {
+        datatype: *const u16,
+        dev_mode: *const std::ffi::c_void,
+        desired_access: u32,
+    }
+    // การหยุดเครื่องพิมพ์ต้องเปิดด้วยสิทธิ์ผู้ดูแลเครื่องพิมพ์
+    const PRINTER_ALL_ACCESS: u32 = 0x000F_000C;
+    const PRINTER_CONTROL_PAUSE: u32 = 1;
+    const PRINTER_CONTROL_RESUME: u32 = 2;
+
+    let name: Vec<u16> = printer.system_name.encode_utf16().chain([0]).collect();
+    let defaults = PrinterDefaults {
+        datatype: std::ptr::null(),
+        dev_mode: std::ptr::null(),
+        desired_access: PRINTER_ALL_ACCESS,
+    };
+    let mut handle = std::ptr::null_mut();
+    if unsafe { OpenPrinterW(name.as_ptr(), &mut handle, &defaults) } == 0 {
+        bail!(
+            "Failed to open printer {}: {}",
+            printer.name,
+            std::io::Error::last_os_error()
+        );
+    }
+    let command = if paused {
+        PRINTER_CONTROL_PAUSE
+    } else {
+        PRINTER_CONTROL_RESUME
+    };
+    let changed = unsafe { SetPrinterW(handle, 0, std::ptr::null(), command) };
+    let error = std::io::Error::last_os_error();
+    unsafe { ClosePrinter(handle) };
+    if changed == 0 {
+        bail!(
+            "Failed to change state of printer {}: {}",
+            printer.name,
+            error
+        );
+    }
+    Ok(())
+}
+
+/// หยุดหรือให้ CUPS ส่งงานไปยังเครื่องพิมพ์ต่อ (`cupsdisable` ยังรับงานเข้าคิวตามปกติ)
+#[cfg(not(windows))]
+pub(crate) fn set_spooler_paused(
+    printer: &printers::common::base::printer::Printer,
+    paused: bool,
+) -> Result<()> {
+    let command = if paused { "cupsdisable" } else { "cupsenable" };
+    let output = std::process::Command::new(command)
+        .arg(&printer.system_name)
+        .output()
+        .with_context(|| format!("Failed to run {}", command))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to change state of printer {}: {}",
+            printer.name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
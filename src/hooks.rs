@@ -0,0 +1,287 @@
+// ----------------------------------------------------------------------
+//                              JOB HOOKS
+// ----------------------------------------------------------------------
+
+use crate::{
+    client_identity::ClientIdentity,
+    history::{JobRecord, JobStatus},
+};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// คำสั่งภายนอกที่เรียกก่อนส่งงานหรือหลังงานเสร็จ
+#[derive(Deserialize, Clone)]
+pub(crate) struct HookCommand {
+    /// โปรแกรมที่จะเรียก เช่น `C:\\scripts\\check_order.exe` หรือ `/usr/local/bin/notify.sh`
+    pub(crate) command: String,
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+    /// เวลาสูงสุดที่รอให้คำสั่งทำงานเสร็จ (วินาที) เกินเวลาจะถูก kill
+    #[serde(default = "default_hook_timeout_secs")]
+    pub(crate) timeout_secs: u64,
+}
+
+pub(crate) fn default_hook_timeout_secs() -> u64 {
+    10
+}
+
+/// hook ของงานพิมพ์ (ไม่ระบุ = ไม่เรียก)
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct HooksConfig {
+    /// เรียกหลังแปลงไฟล์เสร็จและก่อนส่งงาน หากคำสั่งจบด้วย exit code อื่นนอกจาก 0 งานจะถูกปฏิเสธ
+    pre_submit: Option<HookCommand>,
+    /// เรียกเมื่องานพิมพ์สำเร็จ ล้มเหลว หรือถูกยกเลิก (ผลของคำสั่งไม่มีผลกับงาน)
+    post_complete: Option<HookCommand>,
+}
+
+/// ข้อมูลของงานที่ส่งให้ hook ทาง stdin เป็น JSON หนึ่งบรรทัด
+#[derive(Serialize, ToSchema)]
+pub(crate) struct HookContext<'a> {
+    /// `pre_submit` หรือ `post_complete`
+    #[schema(example = "post_complete")]
+    pub(crate) event: &'static str,
+    pub(crate) filename: &'a str,
+    pub(crate) printer_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) agent_id: Option<&'a str>,
+    pub(crate) job_name: &'a str,
+    pub(crate) pages: usize,
+    /// ไฟล์ที่ส่งพิมพ์ใน ./printable_files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) output_file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) job_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) status: Option<JobStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) message: Option<&'a str>,
+    /// `metadata` ของ request
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) metadata: &'a BTreeMap<String, String>,
+    /// เครื่องที่ส่งงาน
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) client: Option<&'a ClientIdentity>,
+}
+
+impl<'a> HookContext<'a> {
+    fn post_complete(record: &'a JobRecord) -> Self {
+        HookContext {
+            event: "post_complete",
+            filename: &record.filename,
+            printer_name: record.printer_name.as_deref(),
+            agent_id: record.agent_id.as_deref(),
+            job_name: &record.job_name,
+            pages: record.pages,
+            output_file: record.output_file.as_deref(),
+            job_id: record.job_id,
+            status: Some(record.status),
+            message: Some(&record.message),
+            metadata: &record.metadata,
+            client: record.client.as_ref(),
+        }
+    }
+}
+
+/// เรียกคำสั่งของ hook โดยส่ง `input` ทาง stdin คืนค่า exit code เป็น 0 หรือไม่ และข้อความจาก stdout
+pub(crate) fn run_hook(hook: &HookCommand, event: &str, input: &str) -> Result<(bool, String)> {
+    let (status, stdout) = run_command(
+        hook,
+        &[("PRINT_HOOK_EVENT", event)],
+        format!("{}\n", input).into_bytes(),
+        u64::MAX,
+    )?;
+    Ok((
+        status.success(),
+        String::from_utf8_lossy(&stdout).trim().to_string(),
+    ))
+}
+
+/// เรียกคำสั่ง `hook` โดยส่ง `input` ทาง stdin และคืน exit status กับ stdout
+/// เกิน `timeout_secs` จะถูก kill และ stdout ที่ยาวเกิน `max_output_bytes` เป็นข้อผิดพลาด
+pub(crate) fn run_command(
+    hook: &HookCommand,
+    envs: &[(&str, &str)],
+    input: Vec<u8>,
+    max_output_bytes: u64,
+) -> Result<(std::process::ExitStatus, Vec<u8>)> {
+    let mut child = std::process::Command::new(&hook.command)
+        .args(&hook.args)
+        .envs(envs.iter().copied())
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start hook {}", hook.command))?;
+
+    // เขียน stdin และอ่าน stdout/stderr ใน thread แยก เพื่อไม่ให้ hook ที่เขียนผลลัพธ์มาก
+    // หรือไม่อ่าน stdin ค้างรอ pipe เต็มจนถูก kill ว่าเกินเวลา
+    if let Some(mut stdin) = child.stdin.take() {
+        std::thread::spawn(move || {
+            // hook ที่ไม่อ่าน stdin จะปิด pipe ก่อน ไม่ถือเป็นข้อผิดพลาด
+            let _ = stdin.write_all(&input);
+        });
+    }
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| read_pipe(pipe, max_output_bytes));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| read_pipe(pipe, MAX_HOOK_STDERR_BYTES));
+
+    let deadline = Instant::now() + Duration::from_secs(hook.timeout_secs);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "Hook {} did not finish within {} seconds",
+                hook.command,
+                hook.timeout_secs
+            );
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    let collect = |reader: Option<PipeReader>| {
+        reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    let (stderr, _) = collect(stderr);
+    let stderr = String::from_utf8_lossy(&stderr);
+    if !stderr.trim().is_empty() {
+        eprintln!("Hook {} stderr: {}", hook.command, stderr.trim());
+    }
+    let (stdout, truncated) = collect(stdout);
+    if truncated {
+        bail!(
+            "Hook {} wrote more than {} bytes to stdout",
+            hook.command,
+            max_output_bytes
+        );
+    }
+    Ok((status, stdout))
+}
+
+/// ส่วนที่เก็บไว้ของ stderr ของ hook สำหรับ log
+pub(crate) const MAX_HOOK_STDERR_BYTES: u64 = 64 * 1024;
+
+/// thread ที่อ่าน pipe ของ hook คืนข้อมูลที่อ่านได้ และ `true` เมื่อมีข้อมูลเกินที่เก็บไว้
+pub(crate) type PipeReader = std::thread::JoinHandle<(Vec<u8>, bool)>;
+
+/// อ่าน pipe ของ hook จนปิดใน thread แยก โดยเก็บไว้ไม่เกิน `limit` ไบต์และอ่านส่วนที่เหลือทิ้ง
+pub(crate) fn read_pipe(mut pipe: impl Read + Send + 'static, limit: u64) -> PipeReader {
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = (&mut pipe).take(limit).read_to_end(&mut output);
+        let rest = std::io::copy(&mut pipe, &mut std::io::sink()).unwrap_or(0);
+        (output, rest > 0)
+    })
+}
+
+/// เรียก hook `pre_submit` คืนค่าเหตุผลที่ปฏิเสธงาน หาก hook ไม่อนุญาตหรือเรียกไม่สำเร็จ
+pub(crate) fn pre_submit_hook(
+    hooks: &HooksConfig,
+    context: &HookContext,
+) -> std::result::Result<(), String> {
+    let Some(hook) = &hooks.pre_submit else {
+        return Ok(());
+    };
+    let input = serde_json::to_string(context).map_err(|e| e.to_string())?;
+    match run_hook(hook, context.event, &input) {
+        Ok((true, _)) => Ok(()),
+        Ok((false, stdout)) if !stdout.is_empty() => Err(stdout),
+        Ok((false, _)) => Err("Job rejected by pre_submit hook".to_string()),
+        Err(e) => {
+            eprintln!("Error running pre_submit hook: {:?}", e);
+            Err(format!("pre_submit hook failed: {}", e))
+        }
+    }
+}
+
+/// เรียก hook `post_complete` ใน thread แยก เพื่อไม่ให้ request หรือ agent ต้องรอ
+pub(crate) fn spawn_post_complete_hook(hooks: &HooksConfig, record: &JobRecord) {
+    let Some(hook) = hooks.post_complete.clone() else {
+        return;
+    };
+    let input = match serde_json::to_string(&HookContext::post_complete(record)) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("Error serializing post_complete hook context: {:?}", e);
+            return;
+        }
+    };
+    std::thread::spawn(move || match run_hook(&hook, "post_complete", &input) {
+        Ok((true, _)) => {}
+        Ok((false, stdout)) => eprintln!("post_complete hook exited with an error: {}", stdout),
+        Err(e) => eprintln!("Error running post_complete hook: {:?}", e),
+    });
+}
+
+// hook ใน test เรียกผ่าน `sh -c`
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn shell_hook(script: &str, timeout_secs: u64) -> HookCommand {
+        HookCommand {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            timeout_secs,
+        }
+    }
+
+    #[test]
+    fn hooks_return_exit_status_and_stdout() {
+        let hook = shell_hook("cat", 5);
+        assert_eq!(
+            run_hook(&hook, "pre_submit", "{}").unwrap(),
+            (true, "{}".to_string())
+        );
+        let hook = shell_hook("echo rejected; echo details >&2; exit 1", 5);
+        assert_eq!(
+            run_hook(&hook, "pre_submit", "{}").unwrap(),
+            (false, "rejected".to_string())
+        );
+    }
+
+    #[test]
+    fn hooks_with_large_output_are_not_killed() {
+        // มากกว่าขนาด pipe buffer หลายเท่า
+        let hook = shell_hook(
+            "head -c 1000000 /dev/zero | tr '\\0' e >&2; head -c 1000000 /dev/zero | tr '\\0' o",
+            5,
+        );
+        let (success, stdout) = run_hook(&hook, "post_complete", "{}").unwrap();
+        assert!(success);
+        assert_eq!(stdout.len(), 1_000_000);
+    }
+
+    #[test]
+    fn hooks_that_ignore_stdin_still_time_out() {
+        let started = Instant::now();
+        let input = "x".repeat(1_000_000);
+        let hook = shell_hook("sleep 30", 1);
+        let error = run_hook(&hook, "pre_submit", &input).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("did not finish within 1 seconds"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let hook = shell_hook("echo ok", 5);
+        assert_eq!(
+            run_hook(&hook, "pre_submit", &input).unwrap(),
+            (true, "ok".to_string())
+        );
+    }
+}
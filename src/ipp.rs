@@ -0,0 +1,458 @@
+// ----------------------------------------------------------------------
+//                             IPP PRINTERS
+// ----------------------------------------------------------------------
+
+use crate::{
+    agent::{http_request, parse_server_url, ServerUrl},
+    job_settings::IppJobAttributes,
+    SERVICE_NAME,
+};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+
+/// `driver_name` ของเครื่องพิมพ์ใน `ipp_printers` ซึ่งส่งงานด้วย IPP Print-Job แทน spooler
+pub(crate) const IPP_DRIVER_NAME: &str = "IPP Everywhere";
+pub(crate) const IPP_DEFAULT_PORT: u16 = 631;
+
+/// เครื่องพิมพ์ใน `ipp_printers` ในรูปแบบเดียวกับเครื่องพิมพ์ที่ติดตั้งในระบบ
+pub(crate) fn ipp_printer(name: &str, uri: &str) -> printers::common::base::printer::Printer {
+    printers::common::base::printer::Printer {
+        name: name.to_string(),
+        system_name: name.to_string(),
+        driver_name: IPP_DRIVER_NAME.to_string(),
+        uri: uri.to_string(),
+        port_name: uri.to_string(),
+        processor: String::new(),
+        data_type: "application/pdf".to_string(),
+        description: String::new(),
+        location: String::new(),
+        is_default: false,
+        is_shared: false,
+        state: printers::common::base::printer::PrinterState::UNKNOWN,
+        state_reasons: vec![],
+    }
+}
+
+/// แยก host, port (ค่าเริ่มต้น 631) และ path จาก URI เช่น `ipp://192.168.1.20/ipp/print`
+pub(crate) fn parse_ipp_uri(uri: &str) -> Result<ServerUrl> {
+    let Some(rest) = uri.strip_prefix("ipp://") else {
+        bail!(
+            "Unsupported IPP printer URI {} (only ipp:// is supported, use a TLS tunnel for ipps://)",
+            uri
+        );
+    };
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let authority = match authority.contains(':') {
+        true => authority.to_string(),
+        false => format!("{}:{}", authority, IPP_DEFAULT_PORT),
+    };
+    let mut server = parse_server_url(&format!("http://{}/{}", authority, path))
+        .with_context(|| format!("Invalid IPP printer URI {}", uri))?;
+    if server.host.is_empty() {
+        bail!("Invalid IPP printer URI {}: missing host", uri);
+    }
+    if server.prefix.is_empty() {
+        server.prefix = "/".to_string();
+    }
+    Ok(server)
+}
+
+/// ตรวจ URI ของ `ipp_printers` ตอนโหลด config.json
+pub(crate) fn validate_ipp_printers(ipp_printers: &HashMap<String, String>) -> Result<()> {
+    for (name, uri) in ipp_printers {
+        parse_ipp_uri(uri).with_context(|| format!("ipp_printers.{}", name))?;
+    }
+    Ok(())
+}
+
+/// ชื่อ media ตาม PWG 5101.1 ของกระดาษมาตรฐาน (คลาดเคลื่อนได้ 1 มม.)
+pub(crate) fn pwg_media_name(width_mm: f32, height_mm: f32) -> Option<&'static str> {
+    const MEDIA: [(&str, f32, f32); 4] = [
+        ("iso_a4_210x297mm", 210.0, 297.0),
+        ("iso_a5_148x210mm", 148.0, 210.0),
+        ("iso_a6_105x148mm", 105.0, 148.0),
+        ("na_letter_8.5x11in", 215.9, 279.4),
+    ];
+    let (short, long) = (width_mm.min(height_mm), width_mm.max(height_mm));
+    MEDIA
+        .iter()
+        .find(|(_, w, h)| (short - w).abs() <= 1.0 && (long - h).abs() <= 1.0)
+        .map(|(name, _, _)| *name)
+}
+
+/// ความยาวสูงสุดของค่าชนิด name เช่น `job-name`
+pub(crate) const MAX_IPP_NAME_BYTES: usize = 255;
+
+/// เขียน attribute หนึ่งค่าตามรูปแบบของ IPP (RFC 8010): tag, ความยาวชื่อ, ชื่อ, ความยาวค่า, ค่า
+pub(crate) fn push_ipp_attribute(request: &mut Vec<u8>, tag: u8, name: &str, value: &[u8]) {
+    request.push(tag);
+    request.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    request.extend_from_slice(name.as_bytes());
+    request.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    request.extend_from_slice(value);
+}
+
+/// สร้าง request `Print-Job` พร้อม job attribute จาก `attributes` ตามด้วยไฟล์ PDF
+pub(crate) fn ipp_print_job_request(
+    uri: &str,
+    job_name: &str,
+    attributes: &IppJobAttributes,
+    document: &[u8],
+) -> Vec<u8> {
+    const OPERATION_ATTRIBUTES: u8 = 0x01;
+    const JOB_ATTRIBUTES: u8 = 0x02;
+    const END_OF_ATTRIBUTES: u8 = 0x03;
+    const INTEGER: u8 = 0x21;
+    const ENUM: u8 = 0x23;
+    const BEGIN_COLLECTION: u8 = 0x34;
+    const END_COLLECTION: u8 = 0x37;
+    const NAME: u8 = 0x42;
+    const KEYWORD: u8 = 0x44;
+    const URI: u8 = 0x45;
+    const CHARSET: u8 = 0x47;
+    const NATURAL_LANGUAGE: u8 = 0x48;
+    const MIME_MEDIA_TYPE: u8 = 0x49;
+    const MEMBER_NAME: u8 = 0x4a;
+
+    // version 2.0, operation Print-Job (0x0002), request-id 1
+    let mut request = vec![0x02, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01];
+    request.push(OPERATION_ATTRIBUTES);
+    push_ipp_attribute(&mut request, CHARSET, "attributes-charset", b"utf-8");
+    push_ipp_attribute(
+        &mut request,
+        NATURAL_LANGUAGE,
+        "attributes-natural-language",
+        b"en",
+    );
+    push_ipp_attribute(&mut request, URI, "printer-uri", uri.as_bytes());
+    push_ipp_attribute(
+        &mut request,
+        NAME,
+        "requesting-user-name",
+        SERVICE_NAME.as_bytes(),
+    );
+    // name ของ IPP ยาวได้ไม่เกิน 255 ไบต์ (RFC 8011)
+    let mut job_name_len = job_name.len().min(MAX_IPP_NAME_BYTES);
+    while !job_name.is_char_boundary(job_name_len) {
+        job_name_len -= 1;
+    }
+    push_ipp_attribute(
+        &mut request,
+        NAME,
+        "job-name",
+        &job_name.as_bytes()[..job_name_len],
+    );
+    push_ipp_attribute(
+        &mut request,
+        MIME_MEDIA_TYPE,
+        "document-format",
+        attributes
+            .document_format
+            .unwrap_or("application/pdf")
+            .as_bytes(),
+    );
+
+    request.push(JOB_ATTRIBUTES);
+    if attributes.copies > 1 {
+        push_ipp_attribute(
+            &mut request,
+            INTEGER,
+            "copies",
+            &attributes.copies.to_be_bytes(),
+        );
+    }
+    if let Some(collate) = attributes.collate {
+        let handling = match collate {
+            true => "separate-documents-collated-copies",
+            false => "separate-documents-uncollated-copies",
+        };
+        push_ipp_attribute(
+            &mut request,
+            KEYWORD,
+            "multiple-document-handling",
+            handling.as_bytes(),
+        );
+    }
+    if let Some(duplex) = attributes.duplex {
+        push_ipp_attribute(
+            &mut request,
+            KEYWORD,
+            "sides",
+            duplex.ipp_sides().as_bytes(),
+        );
+    }
+    for (i, finishing) in attributes.finishings.iter().enumerate() {
+        // ค่าถัดไปของ attribute เดียวกันใช้ชื่อว่าง (1setOf)
+        let name = if i == 0 { "finishings" } else { "" };
+        push_ipp_attribute(
+            &mut request,
+            ENUM,
+            name,
+            &u32::from(*finishing).to_be_bytes(),
+        );
+    }
+    if let Some((width_mm, height_mm)) = attributes.media_mm {
+        match pwg_media_name(width_mm, height_mm) {
+            Some(media) => push_ipp_attribute(&mut request, KEYWORD, "media", media.as_bytes()),
+            // กระดาษที่ไม่ใช่ขนาดมาตรฐาน (เช่น มี bleed) ระบุขนาดผ่าน media-col หน่วย 1/100 มม.
+            None => {
+                let hundredths = |mm: f32| ((mm * 100.0).round() as u32).to_be_bytes();
+                push_ipp_attribute(&mut request, BEGIN_COLLECTION, "media-col", b"");
+                push_ipp_attribute(&mut request, MEMBER_NAME, "", b"media-size");
+                push_ipp_attribute(&mut request, BEGIN_COLLECTION, "", b"");
+                push_ipp_attribute(&mut request, MEMBER_NAME, "", b"x-dimension");
+                push_ipp_attribute(&mut request, INTEGER, "", &hundredths(width_mm));
+                push_ipp_attribute(&mut request, MEMBER_NAME, "", b"y-dimension");
+                push_ipp_attribute(&mut request, INTEGER, "", &hundredths(height_mm));
+                push_ipp_attribute(&mut request, END_COLLECTION, "", b"");
+                push_ipp_attribute(&mut request, END_COLLECTION, "", b"");
+            }
+        }
+    }
+    request.push(END_OF_ATTRIBUTES);
+    request.extend_from_slice(document);
+    request
+}
+
+/// attribute หนึ่งค่าใน message ของ IPP (tag, ชื่อ, ค่า) ชื่อว่าง = ค่าถัดไปของ attribute ก่อนหน้า
+pub(crate) type IppAttribute<'a> = (u8, &'a [u8], &'a [u8]);
+
+/// อ่าน attribute ทั้งหมดของ message ของ IPP (ต่อจาก header 8 ไบต์) จนถึง end-of-attributes
+/// คืนค่า attribute ตามลำดับและข้อมูลที่ตามมา (เช่น ไฟล์ของ `Print-Job`)
+pub(crate) fn read_ipp_attributes(message: &[u8]) -> Result<(Vec<IppAttribute<'_>>, &[u8])> {
+    /// ความยาว 2 ไบต์ตามด้วยข้อมูล คืนค่าข้อมูลและส่วนที่เหลือ
+    fn field(data: &[u8]) -> Option<(&[u8], &[u8])> {
+        let length = u16::from_be_bytes([*data.first()?, *data.get(1)?]) as usize;
+        let data = data.get(2..)?;
+        Some((data.get(..length)?, &data[length..]))
+    }
+
+    let mut rest = message.get(8..).context("Truncated IPP message")?;
+    let mut attributes = Vec::new();
+    loop {
+        let (&tag, after_tag) = rest
+            .split_first()
+            .context("IPP message has no end-of-attributes tag")?;
+        // 0x03 = end-of-attributes, tag อื่นที่ไม่เกิน 0x0f คือจุดเริ่มของกลุ่ม attribute
+        if tag == 0x03 {
+            return Ok((attributes, after_tag));
+        }
+        if tag <= 0x0f {
+            rest = after_tag;
+            continue;
+        }
+        let (name, after_name) = field(after_tag).context("Truncated IPP attribute")?;
+        let (value, after_value) = field(after_name).context("Truncated IPP attribute")?;
+        attributes.push((tag, name, value));
+        rest = after_value;
+    }
+}
+
+/// อ่าน status code, `job-id` และ `status-message` จาก response ของ IPP
+pub(crate) fn parse_ipp_response(response: &[u8]) -> Result<(u16, Option<u32>, Option<String>)> {
+    let (attributes, _) = read_ipp_attributes(response)?;
+    let status = u16::from_be_bytes([response[2], response[3]]);
+    let mut job_id = None;
+    let mut message = None;
+    for (tag, name, value) in attributes {
+        match (name, tag) {
+            (b"job-id", 0x21) if value.len() == 4 => {
+                job_id = Some(u32::from_be_bytes([value[0], value[1], value[2], value[3]]));
+            }
+            (b"status-message", _) => message = Some(String::from_utf8_lossy(value).into_owned()),
+            _ => {}
+        }
+    }
+    Ok((status, job_id, message))
+}
+
+/// ส่งไฟล์ PDF ไปยังเครื่องพิมพ์ IPP โดยตรงด้วย `Print-Job` คืนค่า `job-id` ของเครื่องพิมพ์
+pub(crate) fn ipp_print_job(
+    uri: &str,
+    document: &[u8],
+    job_name: &str,
+    attributes: &IppJobAttributes,
+) -> Result<u64> {
+    let server = parse_ipp_uri(uri)?;
+    let request = ipp_print_job_request(uri, job_name, attributes, document);
+    let (http_status, response) = http_request(
+        &server,
+        "POST",
+        "",
+        &[("Content-Type", "application/ipp")],
+        &request,
+    )?;
+    if http_status != 200 {
+        bail!("HTTP {} from {}", http_status, uri);
+    }
+    let (status, job_id, message) = parse_ipp_response(&response)?;
+    let message = message.unwrap_or_default();
+    // 0x0000 = successful-ok, 0x0001/0x0002 = รับงานแต่ไม่ใช้บาง attribute
+    match status {
+        0x0000 => {}
+        0x0001 | 0x0002 => println!(
+            "IPP printer {} accepted the job but ignored or substituted some attributes (0x{:04x}) {}",
+            uri, status, message
+        ),
+        _ => bail!("IPP printer {} rejected the job (0x{:04x}) {}", uri, status, message),
+    }
+    Ok(job_id.unwrap_or_default().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job_settings::Duplex;
+
+    /// ชื่อและค่าของ attribute ใน message ของ IPP ที่อ่านได้
+    fn ipp_names(message: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let (attributes, _) = read_ipp_attributes(message).unwrap();
+        attributes
+            .into_iter()
+            .map(|(_, name, value)| (String::from_utf8_lossy(name).into_owned(), value.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn ipp_print_job_requests_carry_job_attributes() {
+        let attributes = IppJobAttributes {
+            copies: 2,
+            duplex: Some(Duplex::LongEdge),
+            media_mm: Some((105.0, 148.0)),
+            finishings: vec![4, 5],
+            ..Default::default()
+        };
+        let uri = "ipp://192.168.1.20/ipp/print";
+        let request = ipp_print_job_request(uri, "A6 Print Job", &attributes, b"%PDF-1.5");
+        // Print-Job, request-id 1
+        assert_eq!(request[..8], [2, 0, 0, 2, 0, 0, 0, 1]);
+        let (_, document) = read_ipp_attributes(&request).unwrap();
+        assert_eq!(document, b"%PDF-1.5");
+        let names = ipp_names(&request);
+        let value = |name: &str| {
+            names
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.as_slice())
+        };
+        assert_eq!(value("printer-uri"), Some(uri.as_bytes()));
+        assert_eq!(value("job-name"), Some(b"A6 Print Job".as_slice()));
+        assert_eq!(
+            value("document-format"),
+            Some(b"application/pdf".as_slice())
+        );
+        assert_eq!(value("copies"), Some(2u32.to_be_bytes().as_slice()));
+        assert_eq!(value("sides"), Some(b"two-sided-long-edge".as_slice()));
+        assert_eq!(value("media"), Some(b"iso_a6_105x148mm".as_slice()));
+        // ค่าที่สองของ finishings ใช้ชื่อว่าง
+        let finishings = names.iter().position(|(n, _)| n == "finishings").unwrap();
+        assert_eq!(
+            names[finishings + 1],
+            (String::new(), 5u32.to_be_bytes().to_vec())
+        );
+
+        // ขนาดที่ไม่ใช่มาตรฐานส่งเป็น media-col หน่วย 1/100 มม.
+        let custom = IppJobAttributes {
+            copies: 1,
+            media_mm: Some((100.0, 150.5)),
+            ..Default::default()
+        };
+        let names = ipp_names(&ipp_print_job_request(uri, "label", &custom, b""));
+        let start = names.iter().position(|(n, _)| n == "media-col").unwrap();
+        let values: Vec<&[u8]> = names[start + 1..]
+            .iter()
+            .map(|(_, v)| v.as_slice())
+            .collect();
+        assert_eq!(
+            values,
+            [
+                b"media-size".as_slice(),
+                b"",
+                b"x-dimension",
+                &10000u32.to_be_bytes(),
+                b"y-dimension",
+                &15050u32.to_be_bytes(),
+                b"",
+                b"",
+            ]
+        );
+        assert!(!names.iter().any(|(n, _)| n == "copies" || n == "media"));
+    }
+
+    #[test]
+    fn ipp_job_names_are_truncated_on_character_boundaries() {
+        let job_name = format!("a{}", "ก".repeat(100));
+        let request = ipp_print_job_request(
+            "ipp://printer/",
+            &job_name,
+            &IppJobAttributes::default(),
+            b"",
+        );
+        let names = ipp_names(&request);
+        let (_, value) = names.iter().find(|(n, _)| n == "job-name").unwrap();
+        assert_eq!(value.len(), 253);
+        assert!(job_name.starts_with(std::str::from_utf8(value).unwrap()));
+    }
+
+    /// response ของ IPP ที่มี `status` และ attribute ในกลุ่ม job ตามด้วย end-of-attributes
+    fn ipp_response(status: u16, attributes: &[(u8, &str, &[u8])]) -> Vec<u8> {
+        let mut response = vec![2, 0];
+        response.extend_from_slice(&status.to_be_bytes());
+        response.extend_from_slice(&[0, 0, 0, 1, 0x01]);
+        push_ipp_attribute(&mut response, 0x47, "attributes-charset", b"utf-8");
+        response.push(0x02);
+        for (tag, name, value) in attributes {
+            push_ipp_attribute(&mut response, *tag, name, value);
+        }
+        response.push(0x03);
+        response
+    }
+
+    #[test]
+    fn ipp_responses_report_status_and_job_id() {
+        let response = ipp_response(
+            0x0000,
+            &[
+                (0x21, "job-id", &42u32.to_be_bytes()),
+                (0x41, "status-message", b"successful-ok"),
+            ],
+        );
+        assert_eq!(
+            parse_ipp_response(&response).unwrap(),
+            (0, Some(42), Some("successful-ok".to_string()))
+        );
+        // job-id ที่ไม่ใช่ integer ไม่ถูกใช้
+        let response = ipp_response(0x0400, &[(0x42, "job-id", b"42")]);
+        assert_eq!(parse_ipp_response(&response).unwrap(), (0x0400, None, None));
+    }
+
+    #[test]
+    fn truncated_ipp_responses_are_rejected() {
+        let complete = ipp_response(0, &[(0x21, "job-id", &42u32.to_be_bytes())]);
+        let error = |response: &[u8]| parse_ipp_response(response).unwrap_err().to_string();
+        assert_eq!(error(&complete[..6]), "Truncated IPP message");
+        // end-of-attributes หายไป
+        assert_eq!(
+            error(&complete[..complete.len() - 1]),
+            "IPP message has no end-of-attributes tag"
+        );
+        assert_eq!(
+            error(&complete[..8]),
+            "IPP message has no end-of-attributes tag"
+        );
+        // ตัดกลางชื่อ ค่า และความยาวของ attribute
+        for end in [complete.len() - 3, complete.len() - 8, complete.len() - 13] {
+            assert_eq!(
+                error(&complete[..end]),
+                "Truncated IPP attribute",
+                "{}",
+                end
+            );
+        }
+        // ความยาวที่เกินข้อมูลที่มี
+        let mut oversized = complete.clone();
+        let value_length = oversized.len() - 7;
+        oversized[value_length] = 0xff;
+        assert_eq!(error(&oversized), "Truncated IPP attribute");
+    }
+}
@@ -0,0 +1,513 @@
+// ----------------------------------------------------------------------
+//                          PRINT JOB SETTINGS
+// ----------------------------------------------------------------------
+
+use crate::{
+    backend::print_backend,
+    config::AppConfig,
+    ipp::{ipp_print_job, ipp_printer, IPP_DRIVER_NAME},
+    raster::{raster_dpi, rasterize_pdf},
+    sessions::UserSessions,
+};
+use anyhow::{Context, Result};
+use lopdf::Document;
+use printers::{self, common::base::job::PrinterJobOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// ค่าที่ส่งไปกับงานพิมพ์นอกเหนือจากตัวไฟล์
+#[derive(Default, Clone, Serialize, Deserialize, ToSchema)]
+pub(crate) struct JobSettings {
+    /// option ของ CUPS ที่ส่งผ่าน `PrinterJobOptions.raw_properties` (Linux/macOS)
+    raw_properties: Vec<(String, String)>,
+    /// คำสั่ง `@PJL SET` สำหรับ Windows ซึ่ง `printers` ส่งไฟล์แบบ RAW โดยไม่ผ่าน driver (DEVMODE)
+    pjl_settings: Vec<(String, String)>,
+    /// attribute ของงานสำหรับเครื่องพิมพ์ใน `ipp_printers`
+    #[serde(default)]
+    pub(crate) ipp_attributes: IppJobAttributes,
+    /// ผู้ใช้ Windows ที่ส่งงานในนาม (`DOMAIN\user`) เมื่อเปิด `user_context_printing`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) windows_user: Option<String>,
+}
+
+/// ค่าของงานที่ส่งเป็น job attribute ของ IPP (media, sides, copies) แทนค่าเริ่มต้นของ driver
+#[derive(Default, Clone, Serialize, Deserialize, ToSchema)]
+pub(crate) struct IppJobAttributes {
+    pub(crate) copies: u32,
+    #[serde(default)]
+    pub(crate) collate: Option<bool>,
+    #[serde(default)]
+    pub(crate) duplex: Option<Duplex>,
+    /// ขนาดกระดาษของไฟล์ที่ส่งพิมพ์ (กว้าง, สูง) หน่วย มม.
+    #[serde(default)]
+    pub(crate) media_mm: Option<(f32, f32)>,
+    /// `document-format` ของไฟล์ที่ส่ง (ไม่ระบุ = `application/pdf`) กำหนดเมื่อแปลงเป็น raster ก่อนส่ง
+    #[serde(skip)]
+    pub(crate) document_format: Option<&'static str>,
+    /// ค่า `finishings` ของงานเย็บและเจาะรู
+    #[serde(default)]
+    pub(crate) finishings: Vec<u16>,
+}
+
+/// การพิมพ์สองหน้า
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Duplex {
+    /// พิมพ์หน้าเดียว
+    Off,
+    /// พิมพ์สองหน้า พลิกตามขอบยาว (แบบหนังสือ)
+    LongEdge,
+    /// พิมพ์สองหน้า พลิกตามขอบสั้น (แบบปฏิทิน)
+    ShortEdge,
+}
+
+impl Duplex {
+    /// ค่า `sides` ของ IPP/CUPS
+    pub(crate) fn ipp_sides(self) -> &'static str {
+        match self {
+            Duplex::Off => "one-sided",
+            Duplex::LongEdge => "two-sided-long-edge",
+            Duplex::ShortEdge => "two-sided-short-edge",
+        }
+    }
+}
+
+/// จำนวนชุดสูงสุดที่สั่งพิมพ์ได้ในงานเดียว
+pub(crate) const MAX_COPIES: u32 = 999;
+/// จำนวน key สูงสุดใน `metadata` ของงานหนึ่งงาน
+pub(crate) const MAX_METADATA_ENTRIES: usize = 32;
+/// ความยาวสูงสุดของ key และค่าใน `metadata` (จำนวนตัวอักษร)
+pub(crate) const MAX_METADATA_KEY_CHARS: usize = 64;
+pub(crate) const MAX_METADATA_VALUE_CHARS: usize = 512;
+
+/// ตำแหน่งเย็บกระดาษ
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StaplePosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    /// เย็บสองจุดที่ขอบซ้าย
+    DualLeft,
+    /// เย็บสองจุดที่ขอบบน
+    DualTop,
+}
+
+impl StaplePosition {
+    /// ค่า `finishings` ของ IPP/CUPS (PWG 5100.1)
+    fn ipp_finishing(self) -> u16 {
+        match self {
+            StaplePosition::TopLeft => 20,
+            StaplePosition::BottomLeft => 21,
+            StaplePosition::TopRight => 22,
+            StaplePosition::BottomRight => 23,
+            StaplePosition::DualLeft => 28,
+            StaplePosition::DualTop => 29,
+        }
+    }
+
+    /// ค่าของ `@PJL SET STAPLE`
+    fn pjl_name(self) -> &'static str {
+        match self {
+            StaplePosition::TopLeft => "TOPLEFT",
+            StaplePosition::TopRight => "TOPRIGHT",
+            StaplePosition::BottomLeft => "BOTTOMLEFT",
+            StaplePosition::BottomRight => "BOTTOMRIGHT",
+            StaplePosition::DualLeft => "DUALLEFT",
+            StaplePosition::DualTop => "DUALTOP",
+        }
+    }
+}
+
+/// จำนวนและตำแหน่งรูเจาะกระดาษ
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PunchPosition {
+    DualLeft,
+    DualTop,
+    TripleLeft,
+    TripleTop,
+    QuadLeft,
+    QuadTop,
+}
+
+impl PunchPosition {
+    /// ค่า `finishings` ของ IPP/CUPS (PWG 5100.1)
+    fn ipp_finishing(self) -> u16 {
+        match self {
+            PunchPosition::DualLeft => 74,
+            PunchPosition::DualTop => 75,
+            PunchPosition::TripleLeft => 78,
+            PunchPosition::TripleTop => 79,
+            PunchPosition::QuadLeft => 82,
+            PunchPosition::QuadTop => 83,
+        }
+    }
+
+    /// ค่าของ `@PJL SET PUNCH`
+    fn pjl_name(self) -> &'static str {
+        match self {
+            PunchPosition::DualLeft => "DUALLEFT",
+            PunchPosition::DualTop => "DUALTOP",
+            PunchPosition::TripleLeft => "TRIPLELEFT",
+            PunchPosition::TripleTop => "TRIPLETOP",
+            PunchPosition::QuadLeft => "QUADLEFT",
+            PunchPosition::QuadTop => "QUADTOP",
+        }
+    }
+}
+
+/// จำนวนชุดและการเก็บงาน (finishing) ของเครื่องพิมพ์สำนักงาน
+#[derive(Clone, Copy)]
+pub(crate) struct Finishing {
+    pub(crate) copies: u32,
+    /// เรียงชุด (1,2,3 1,2,3) แทนการพิมพ์หน้าเดียวกันติดกัน (ไม่ระบุ = ค่าเริ่มต้นของเครื่อง)
+    pub(crate) collate: Option<bool>,
+    pub(crate) staple: Option<StaplePosition>,
+    pub(crate) punch: Option<PunchPosition>,
+    /// พิมพ์สองหน้า (ไม่ระบุ = ค่าเริ่มต้นของเครื่อง)
+    pub(crate) duplex: Option<Duplex>,
+}
+
+impl Default for Finishing {
+    fn default() -> Self {
+        Finishing {
+            copies: 1,
+            collate: None,
+            staple: None,
+            punch: None,
+            duplex: None,
+        }
+    }
+}
+
+/// แปลงชื่อถาดกระดาษ เช่น "Tray 2" หรือ "2" เป็นชื่อที่ใช้กับ CUPS (`InputSlot`) และ PJL (`MEDIASOURCE`)
+///
+/// คืนค่า `None` หากชื่อถาดมีตัวอักษรที่ไม่อนุญาต
+pub(crate) fn tray_names(tray: &str) -> Option<(String, String)> {
+    let compact: String = tray.split_whitespace().collect();
+    if compact.is_empty()
+        || !compact
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+    if compact.chars().all(|c| c.is_ascii_digit()) {
+        Some((format!("Tray{}", compact), format!("TRAY{}", compact)))
+    } else {
+        Some((compact.clone(), compact.to_uppercase()))
+    }
+}
+
+/// หาเครื่องพิมพ์ปลายทาง: ชื่อที่ระบุใน request, `default_printer` ใน config หรือเครื่องพิมพ์เริ่มต้นของระบบ ตามลำดับ
+///
+/// คืนค่าข้อความ Error หากไม่พบเครื่องพิมพ์
+pub(crate) fn resolve_printer(
+    printer_name: Option<&str>,
+    config: &AppConfig,
+) -> std::result::Result<printers::common::base::printer::Printer, String> {
+    match printer_name.or(config.default_printer.as_deref()) {
+        Some(name) => match config.ipp_printers.get(name) {
+            Some(uri) => Ok(ipp_printer(name, uri)),
+            None => print_backend()
+                .printer(name)
+                .ok_or_else(|| format!("Printer not found: {}", name)),
+        },
+        None => print_backend().default_printer().ok_or_else(|| {
+            "No printer_name given and no default printer is configured".to_string()
+        }),
+    }
+}
+
+/// รวบรวมค่าที่ต้องส่งไปกับงานพิมพ์จากถาดกระดาษ finishing ขนาดกระดาษ (มม.) และ option ที่ผู้ใช้ระบุ
+pub(crate) fn job_settings(
+    tray: Option<&str>,
+    finishing: &Finishing,
+    media_mm: (f32, f32),
+    raw_properties: &HashMap<String, String>,
+) -> JobSettings {
+    let mut settings = JobSettings::default();
+    if let Some((cups_name, pjl_name)) = tray.and_then(tray_names) {
+        settings
+            .raw_properties
+            .push(("InputSlot".to_string(), cups_name));
+        settings
+            .pjl_settings
+            .push(("MEDIASOURCE".to_string(), pjl_name));
+    }
+    if finishing.copies > 1 {
+        settings
+            .raw_properties
+            .push(("copies".to_string(), finishing.copies.to_string()));
+        // PJL ใช้ QTY สำหรับการพิมพ์แบบเรียงชุด และ COPIES สำหรับการพิมพ์หน้าเดียวกันติดกัน
+        let key = if finishing.collate == Some(false) {
+            "COPIES"
+        } else {
+            "QTY"
+        };
+        settings
+            .pjl_settings
+            .push((key.to_string(), finishing.copies.to_string()));
+    }
+    if let Some(collate) = finishing.collate {
+        settings
+            .raw_properties
+            .push(("collate".to_string(), collate.to_string()));
+    }
+    let finishings: Vec<String> = finishing
+        .staple
+        .map(StaplePosition::ipp_finishing)
+        .into_iter()
+        .chain(finishing.punch.map(PunchPosition::ipp_finishing))
+        .map(|value| value.to_string())
+        .collect();
+    if !finishings.is_empty() {
+        settings
+            .raw_properties
+            .push(("finishings".to_string(), finishings.join(",")));
+    }
+    if let Some(staple) = finishing.staple {
+        settings
+            .pjl_settings
+            .push(("STAPLE".to_string(), staple.pjl_name().to_string()));
+    }
+    if let Some(punch) = finishing.punch {
+        settings
+            .pjl_settings
+            .push(("PUNCH".to_string(), punch.pjl_name().to_string()));
+    }
+    if let Some(duplex) = finishing.duplex {
+        settings
+            .raw_properties
+            .push(("sides".to_string(), duplex.ipp_sides().to_string()));
+        let (mode, binding) = match duplex {
+            Duplex::Off => ("OFF", None),
+            Duplex::LongEdge => ("ON", Some("LONGEDGE")),
+            Duplex::ShortEdge => ("ON", Some("SHORTEDGE")),
+        };
+        settings
+            .pjl_settings
+            .push(("DUPLEX".to_string(), mode.to_string()));
+        if let Some(binding) = binding {
+            settings
+                .pjl_settings
+                .push(("BINDING".to_string(), binding.to_string()));
+        }
+    }
+    settings.ipp_attributes = IppJobAttributes {
+        copies: finishing.copies,
+        collate: finishing.collate,
+        duplex: finishing.duplex,
+        media_mm: Some(media_mm),
+        document_format: None,
+        finishings: finishing
+            .staple
+            .map(StaplePosition::ipp_finishing)
+            .into_iter()
+            .chain(finishing.punch.map(PunchPosition::ipp_finishing))
+            .collect(),
+    };
+    // ใส่ไว้หลังสุดเพื่อให้ค่าที่ผู้ใช้ระบุเองมีผลแทนค่าที่ API สร้างขึ้น
+    settings.raw_properties.extend(
+        raw_properties
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone())),
+    );
+    settings
+}
+
+/// ครอบไฟล์ PDF ด้วย PJL job header เพื่อให้เครื่องพิมพ์ใช้ค่าที่กำหนดกับงาน RAW
+pub(crate) fn wrap_with_pjl(data: Vec<u8>, pjl_settings: &[(String, String)]) -> Vec<u8> {
+    const UEL: &[u8] = b"\x1b%-12345X";
+    let mut output = Vec::with_capacity(data.len() + 256);
+    output.extend_from_slice(UEL);
+    output.extend_from_slice(b"@PJL\r\n");
+    for (key, value) in pjl_settings {
+        output.extend_from_slice(format!("@PJL SET {}={}\r\n", key, value).as_bytes());
+    }
+    output.extend_from_slice(b"@PJL ENTER LANGUAGE=PDF\r\n");
+    output.extend(data);
+    output.extend_from_slice(UEL);
+    output
+}
+
+/// การแบ่งงานที่มีจำนวนหน้ามากเป็นหลายงานใน spooler ตามลำดับ
+#[derive(Deserialize, Clone)]
+pub(crate) struct JobSplitConfig {
+    /// จำนวนหน้าสูงสุดต่องาน งานที่มีหน้ามากกว่านี้จะถูกแบ่งเป็นส่วนละไม่เกินจำนวนนี้
+    pub(crate) max_pages: usize,
+    /// เครื่องพิมพ์ที่แบ่งงาน (ไม่ระบุ = ทุกเครื่อง)
+    #[serde(default)]
+    printers: Vec<String>,
+}
+
+impl JobSplitConfig {
+    fn applies_to(&self, printer: &str) -> bool {
+        self.printers.is_empty() || self.printers.iter().any(|p| p == printer)
+    }
+}
+
+/// แบ่งไฟล์ PDF เป็นไฟล์ละไม่เกิน `max_pages` หน้าตามลำดับหน้า
+/// (ไฟล์ที่มีหน้าไม่เกิน `max_pages` คืนค่ารายการว่าง เพื่อส่งไฟล์เดิมโดยไม่ต้องเขียนใหม่)
+pub(crate) fn split_pdf(file_data: &[u8], max_pages: usize) -> Result<Vec<Vec<u8>>> {
+    let doc = Document::load_mem(file_data).context("Failed to read PDF for splitting")?;
+    let page_count = doc.get_pages().len();
+    if page_count <= max_pages {
+        return Ok(Vec::new());
+    }
+    let numbers: Vec<u32> = (1..=page_count as u32).collect();
+    numbers
+        .chunks(max_pages)
+        .map(|chunk| {
+            let mut part = doc.clone();
+            let removed: Vec<u32> = numbers
+                .iter()
+                .filter(|number| !chunk.contains(number))
+                .copied()
+                .collect();
+            part.delete_pages(&removed);
+            part.prune_objects();
+            let mut output = Vec::new();
+            part.save_to(&mut output)
+                .context("Failed to write a part of the split PDF")?;
+            Ok(output)
+        })
+        .collect()
+}
+
+/// ส่งไฟล์ไปยังเครื่องพิมพ์เหมือน `submit_print_job` ตามการตั้งค่าของเครื่องพิมพ์ใน config.json
+/// คืนค่า job id ของทุกงานตามลำดับ
+///
+/// *   แบ่งเป็นหลายงานตามลำดับเมื่อจำนวนหน้าเกิน `job_split.max_pages` โดยต่อท้ายชื่องานด้วย
+///     `(part 1 of 3)` หากส่งส่วนใดไม่สำเร็จจะหยุดส่งส่วนที่เหลือ (ส่วนที่ส่งไปแล้วยังอยู่ใน spooler)
+/// *   แปลงแต่ละงานเป็นภาพตาม `printer_profiles.<name>.rasterize` ก่อนส่ง
+/// *   ส่งงานด้วยสิทธิ์ของ `settings.windows_user` เมื่อระบุ (แปลงไฟล์ด้วยสิทธิ์ของ Service)
+pub(crate) fn dispatch_print_job(
+    printer: &printers::common::base::printer::Printer,
+    file_data: Vec<u8>,
+    job_name: &str,
+    settings: &JobSettings,
+    config: &AppConfig,
+    sessions: &UserSessions,
+) -> std::result::Result<Vec<u64>, &'static str> {
+    let raster = config
+        .printer_profiles
+        .get(&printer.name)
+        .and_then(|profile| profile.rasterize.as_ref());
+    let mut raster_settings;
+    let (settings, prepare) = match raster {
+        Some(raster) => {
+            let dpi = raster_dpi(printer, raster);
+            raster_settings = settings.clone();
+            raster_settings.ipp_attributes.document_format = raster.format.document_format();
+            let prepare = move |data: Vec<u8>| {
+                rasterize_pdf(&data, raster, dpi, config.ghostscript.as_deref()).map_err(|e| {
+                    eprintln!(
+                        "Error rasterizing {} for {}: {:#}",
+                        job_name, printer.name, e
+                    );
+                    "Failed to rasterize the job for the printer"
+                })
+            };
+            (&raster_settings, Some(prepare))
+        }
+        None => (settings, None),
+    };
+    let submit = |data: Vec<u8>, name: &str| {
+        let data = match &prepare {
+            Some(prepare) => prepare(data)?,
+            None => data,
+        };
+        match &settings.windows_user {
+            Some(user) => sessions
+                .run_as(config, user, || {
+                    submit_print_job(printer, data, name, settings)
+                })
+                .unwrap_or_else(|e| {
+                    eprintln!("Error sending {} as {}: {}", name, user, e);
+                    Err("Failed to send the job as the Windows user")
+                }),
+            None => submit_print_job(printer, data, name, settings),
+        }
+    };
+
+    let max_pages = config
+        .job_split
+        .as_ref()
+        .filter(|split| split.applies_to(&printer.name))
+        .map(|split| split.max_pages);
+    let parts = match max_pages {
+        Some(max_pages) => split_pdf(&file_data, max_pages).map_err(|e| {
+            eprintln!("Error splitting {}: {:#}", job_name, e);
+            "Failed to split the job into parts"
+        })?,
+        None => Vec::new(),
+    };
+    if parts.is_empty() {
+        return submit(file_data, job_name).map(|id| vec![id]);
+    }
+
+    let count = parts.len();
+    let mut job_ids = Vec::with_capacity(count);
+    for (i, part) in parts.into_iter().enumerate() {
+        let part_name = format!("{} (part {} of {})", job_name, i + 1, count);
+        match submit(part, &part_name) {
+            Ok(job_id) => job_ids.push(job_id),
+            Err(e) => {
+                eprintln!(
+                    "Failed to send {} to {} after {} parts: {}",
+                    part_name,
+                    printer.name,
+                    job_ids.len(),
+                    e
+                );
+                return Err(e);
+            }
+        }
+    }
+    println!(
+        "Split {} into {} jobs of up to {} pages on printer {}",
+        job_name,
+        count,
+        max_pages.unwrap_or_default(),
+        printer.name
+    );
+    Ok(job_ids)
+}
+
+/// ส่งไฟล์ไปยังเครื่องพิมพ์พร้อมค่าที่กำหนดใน `settings` คืนค่า job id ของระบบพิมพ์
+pub(crate) fn submit_print_job(
+    printer: &printers::common::base::printer::Printer,
+    file_data: Vec<u8>,
+    job_name: &str,
+    settings: &JobSettings,
+) -> std::result::Result<u64, &'static str> {
+    if printer.driver_name == IPP_DRIVER_NAME {
+        return ipp_print_job(&printer.uri, &file_data, job_name, &settings.ipp_attributes)
+            .map_err(|e| {
+                eprintln!("Error sending job to IPP printer {}: {:#}", printer.name, e);
+                "Failed to send the job to the IPP printer"
+            });
+    }
+    // PJL `ENTER LANGUAGE=PDF` ใช้ได้เฉพาะไฟล์ PDF ไม่ใช่ raster จาก `rasterize`
+    let is_pdf = settings.ipp_attributes.document_format.is_none();
+    let file_data = if cfg!(windows) && is_pdf && !settings.pjl_settings.is_empty() {
+        wrap_with_pjl(file_data, &settings.pjl_settings)
+    } else {
+        file_data
+    };
+    let raw_properties: Vec<(&str, &str)> = settings
+        .raw_properties
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+
+    let options = PrinterJobOptions {
+        name: Some(job_name),
+        raw_properties: &raw_properties,
+    };
+    print_backend().print(printer, &file_data, options)
+}
@@ -0,0 +1,355 @@
+// ----------------------------------------------------------------------
+//                             LOCALIZATION
+// ----------------------------------------------------------------------
+
+use crate::config::AppConfig;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// ภาษาของข้อความใน Response และวันที่ที่พิมพ์ลงบนเอกสาร
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Language {
+    #[default]
+    En,
+    Th,
+}
+
+impl Language {
+    /// ภาษาแรกที่รองรับใน `Accept-Language` ตามค่า `q` (ไม่มีภาษาที่รองรับ = `default`)
+    fn from_accept_language(header: Option<&str>, default: Language) -> Language {
+        let mut ranges: Vec<(&str, f32)> = header
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges
+            .into_iter()
+            .find_map(|(tag, _)| {
+                let primary = tag.split('-').next().unwrap_or(tag);
+                match primary.to_ascii_lowercase().as_str() {
+                    "en" => Some(Language::En),
+                    "th" => Some(Language::Th),
+                    "*" => Some(default),
+                    _ => None,
+                }
+            })
+            .unwrap_or(default)
+    }
+
+    /// ภาษาของ request จาก header `Accept-Language` หรือ `language` ใน config.json
+    pub(crate) fn of_request(
+        headers: &actix_web::http::header::HeaderMap,
+        config: &AppConfig,
+    ) -> Language {
+        let header = headers
+            .get(actix_web::http::header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok());
+        Language::from_accept_language(header, config.language)
+    }
+}
+
+/// ศักราชของปีในวันที่ที่พิมพ์ลงบนเอกสาร
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Era {
+    /// คริสต์ศักราช เช่น 2026
+    Gregorian,
+    /// พุทธศักราช เช่น 2569
+    Buddhist,
+}
+
+/// ตัวเลขที่ใช้พิมพ์วันที่และเลขหน้า
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Digits {
+    /// 0-9
+    Arabic,
+    /// ๐-๙ (ต้องตั้ง `stamp_font` ใน config.json)
+    Thai,
+}
+
+/// ตัวแปรที่ใช้ได้ใน `date_format`
+pub(crate) const DATE_FORMAT_FIELDS: [&str; 11] =
+    ["d", "-d", "m", "-m", "B", "b", "A", "a", "Y", "y", "%"];
+
+/// ความยาวสูงสุดของ `date_format`
+pub(crate) const MAX_DATE_FORMAT_CHARS: usize = 64;
+
+pub(crate) const THAI_MONTHS: [&str; 12] = [
+    "มกราคม",
+    "กุมภาพันธ์",
+    "มีนาคม",
+    "เมษายน",
+    "พฤษภาคม",
+    "มิถุนายน",
+    "กรกฎาคม",
+    "สิงหาคม",
+    "กันยายน",
+    "ตุลาคม",
+    "พฤศจิกายน",
+    "ธันวาคม",
+];
+
+pub(crate) const THAI_MONTHS_SHORT: [&str; 12] = [
+    "ม.ค.",
+    "ก.พ.",
+    "มี.ค.",
+    "เม.ย.",
+    "พ.ค.",
+    "มิ.ย.",
+    "ก.ค.",
+    "ส.ค.",
+    "ก.ย.",
+    "ต.ค.",
+    "พ.ย.",
+    "ธ.ค.",
+];
+
+/// ชื่อวันเริ่มจากวันจันทร์ ตาม `Weekday::num_days_from_monday`
+pub(crate) const THAI_WEEKDAYS: [&str; 7] =
+    ["จันทร์", "อังคาร", "พุธ", "พฤหัสบดี", "ศุกร์", "เสาร์", "อาทิตย์"];
+
+pub(crate) const THAI_WEEKDAYS_SHORT: [&str; 7] = ["จ.", "อ.", "พ.", "พฤ.", "ศ.", "ส.", "อา."];
+
+/// รูปแบบวันที่และตัวเลขที่พิมพ์ลงบนเอกสาร (`{date}` และเลขหน้า) ระบุได้ใน `locale` ของขั้นตอน
+/// `stamp`, `page_numbers` หรือ `text` และใน `locale` ของ tenant เป็นค่าเริ่มต้น
+///
+/// ค่าที่ไม่ระบุใน `locale` ของขั้นตอนจะใช้ค่าของ tenant และค่าตามภาษาของ request ตามลำดับ
+#[derive(Serialize, Deserialize, ToSchema, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub(crate) struct LocaleFormat {
+    /// ศักราช (ไม่ระบุ = `buddhist` เมื่อภาษาไทย, `gregorian` เมื่อภาษาอังกฤษ)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    era: Option<Era>,
+    /// ตัวเลขของวันที่และเลขหน้า (ไม่ระบุ = `arabic`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digits: Option<Digits>,
+    /// รูปแบบวันที่ เช่น `"%-d %B %Y"` โดยใช้ `%d`/`%-d` (วัน), `%m`/`%-m` (เดือน),
+    /// `%B`/`%b` (ชื่อเดือนเต็ม/ย่อ), `%A`/`%a` (ชื่อวันเต็ม/ย่อ), `%Y`/`%y` (ปีตาม `era`) และ `%%`
+    /// (ไม่ระบุ = `%d/%m/%Y` เมื่อภาษาไทย, `%d %b %Y` เมื่อภาษาอังกฤษ)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "%-d %B %Y")]
+    date_format: Option<String>,
+    /// ภาษาของชื่อเดือนและชื่อวัน (ไม่ระบุ = ภาษาของ request)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<Language>,
+}
+
+impl LocaleFormat {
+    /// ค่าของ `self` โดยใช้ค่าจาก `fallback` สำหรับค่าที่ไม่ได้ระบุ
+    pub(crate) fn or(&self, fallback: Option<&LocaleFormat>) -> LocaleFormat {
+        let Some(fallback) = fallback else {
+            return self.clone();
+        };
+        LocaleFormat {
+            era: self.era.or(fallback.era),
+            digits: self.digits.or(fallback.digits),
+            date_format: self
+                .date_format
+                .clone()
+                .or_else(|| fallback.date_format.clone()),
+            language: self.language.or(fallback.language),
+        }
+    }
+
+    /// ตรวจว่า `date_format` ใช้เฉพาะตัวแปรที่รู้จัก
+    pub(crate) fn validate(&self) -> std::result::Result<(), String> {
+        let Some(format) = &self.date_format else {
+            return Ok(());
+        };
+        if format.trim().is_empty()
+            || format.chars().count() > MAX_DATE_FORMAT_CHARS
+            || format.chars().any(char::is_control)
+        {
+            return Err(format!(
+                "date_format must be 1 to {} printable characters",
+                MAX_DATE_FORMAT_CHARS
+            ));
+        }
+        let mut rest = format.as_str();
+        while let Some(start) = rest.find('%') {
+            rest = &rest[start + 1..];
+            let Some(field) = DATE_FORMAT_FIELDS
+                .iter()
+                .find(|field| rest.starts_with(**field))
+            else {
+                return Err(format!(
+                    "unknown date_format specifier in {:?} (expected one of %{})",
+                    format,
+                    DATE_FORMAT_FIELDS.join(", %")
+                ));
+            };
+            rest = &rest[field.len()..];
+        }
+        Ok(())
+    }
+
+    /// วันที่ `date` ตามรูปแบบนี้ โดย `language` คือภาษาของ request
+    pub(crate) fn format_date(&self, language: Language, date: chrono::NaiveDate) -> String {
+        use chrono::Datelike;
+        let names = self.language.unwrap_or(language);
+        let era = self.era.unwrap_or(match language {
+            Language::En => Era::Gregorian,
+            Language::Th => Era::Buddhist,
+        });
+        let format = self.date_format.as_deref().unwrap_or(match language {
+            Language::En => "%d %b %Y",
+            Language::Th => "%d/%m/%Y",
+        });
+        let year = match era {
+            Era::Gregorian => date.year(),
+            Era::Buddhist => date.year() + 543,
+        };
+        let month = date.month0() as usize;
+        let weekday = date.weekday().num_days_from_monday() as usize;
+
+        let mut output = String::new();
+        let mut rest = format;
+        while let Some(start) = rest.find('%') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            let Some(field) = DATE_FORMAT_FIELDS
+                .iter()
+                .find(|field| rest.starts_with(**field))
+            else {
+                output.push('%');
+                continue;
+            };
+            rest = &rest[field.len()..];
+            match (*field, names) {
+                ("d", _) => output.push_str(&format!("{:02}", date.day())),
+                ("-d", _) => output.push_str(&date.day().to_string()),
+                ("m", _) => output.push_str(&format!("{:02}", date.month())),
+                ("-m", _) => output.push_str(&date.month().to_string()),
+                ("Y", _) => output.push_str(&year.to_string()),
+                ("y", _) => output.push_str(&format!("{:02}", year.rem_euclid(100))),
+                ("B", Language::Th) => output.push_str(THAI_MONTHS[month]),
+                ("b", Language::Th) => output.push_str(THAI_MONTHS_SHORT[month]),
+                ("A", Language::Th) => output.push_str(THAI_WEEKDAYS[weekday]),
+                ("a", Language::Th) => output.push_str(THAI_WEEKDAYS_SHORT[weekday]),
+                ("%", _) => output.push('%'),
+                (field, _) => output.push_str(&date.format(&format!("%{}", field)).to_string()),
+            }
+        }
+        output.push_str(rest);
+        self.localize_digits(&output)
+    }
+
+    /// แปลงตัวเลขใน `text` เป็นเลขไทยเมื่อ `digits` เป็น `thai`
+    pub(crate) fn localize_digits(&self, text: &str) -> String {
+        match self.digits {
+            Some(Digits::Thai) => text
+                .chars()
+                .map(|c| match c.is_ascii_digit() {
+                    true => char::from_u32('๐' as u32 + (c as u32 - '0' as u32)).unwrap_or(c),
+                    false => c,
+                })
+                .collect(),
+            _ => text.to_string(),
+        }
+    }
+
+    /// วันที่หรือตัวเลขตามรูปแบบนี้มีตัวอักษรที่ไม่ใช่ ASCII ซึ่งต้องพิมพ์ด้วย `stamp_font`
+    pub(crate) fn requires_stamp_font(&self, language: Language) -> bool {
+        self.digits == Some(Digits::Thai)
+            || !self
+                .format_date(language, chrono::Local::now().date_naive())
+                .is_ascii()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_language_picks_the_best_supported_language() {
+        let parse = |header| Language::from_accept_language(header, Language::En);
+        assert_eq!(parse(None), Language::En);
+        assert_eq!(parse(Some("th-TH,th;q=0.9,en;q=0.8")), Language::Th);
+        assert_eq!(parse(Some("en-US, th;q=0.5")), Language::En);
+        assert_eq!(parse(Some("en;q=0.2, TH;q=0.9")), Language::Th);
+        // ภาษาที่ไม่รองรับข้ามไป และไม่มีภาษาที่รองรับเลยใช้ค่าเริ่มต้น
+        assert_eq!(parse(Some("fr-FR, de;q=0.8, th;q=0.3")), Language::Th);
+        assert_eq!(parse(Some("fr-FR, de;q=0.8")), Language::En);
+        assert_eq!(parse(Some("x-klingon, ,;q=1")), Language::En);
+        // q=0 คือไม่ต้องการ และค่า q ที่อ่านไม่ออกข้ามไปทั้งรายการ
+        assert_eq!(parse(Some("th;q=0, en;q=0.1")), Language::En);
+        assert_eq!(parse(Some("th;q=high")), Language::En);
+        assert_eq!(
+            Language::from_accept_language(Some("*"), Language::Th),
+            Language::Th
+        );
+        assert_eq!(
+            Language::from_accept_language(Some("fr, *;q=0.5, en;q=0.1"), Language::Th),
+            Language::Th
+        );
+    }
+
+    #[test]
+    fn requests_without_a_supported_language_use_the_configured_default() {
+        let config = AppConfig {
+            language: Language::Th,
+            ..Default::default()
+        };
+        let mut headers = actix_web::http::header::HeaderMap::new();
+        assert_eq!(Language::of_request(&headers, &config), Language::Th);
+        headers.insert(
+            actix_web::http::header::ACCEPT_LANGUAGE,
+            "ja-JP".parse().unwrap(),
+        );
+        assert_eq!(Language::of_request(&headers, &config), Language::Th);
+        headers.insert(
+            actix_web::http::header::ACCEPT_LANGUAGE,
+            "en-GB".parse().unwrap(),
+        );
+        assert_eq!(Language::of_request(&headers, &config), Language::En);
+    }
+
+    #[test]
+    fn step_locale_falls_back_to_the_tenant_locale() {
+        let date = chrono::NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let locale = |value| serde_json::from_value::<LocaleFormat>(value).unwrap();
+        let tenant = locale(serde_json::json!({"digits": "thai", "date_format": "%-d %B %Y"}));
+        let step = locale(serde_json::json!({"era": "gregorian"}));
+        assert_eq!(
+            step.or(Some(&tenant)).format_date(Language::Th, date),
+            "๑๔ ตุลาคม ๒๐๒๖"
+        );
+        // ค่าที่ขั้นตอนระบุใช้ก่อนค่าของ tenant
+        let step = locale(serde_json::json!({"digits": "arabic", "language": "en"}));
+        assert_eq!(
+            step.or(Some(&tenant)).format_date(Language::Th, date),
+            "14 October 2569"
+        );
+        // ไม่มี locale ทั้งสองระดับใช้ค่าตามภาษาของ request
+        let none = LocaleFormat::default().or(None);
+        assert_eq!(none.format_date(Language::Th, date), "14/10/2569");
+        assert_eq!(none.format_date(Language::En, date), "14 Oct 2026");
+    }
+
+    #[test]
+    fn unknown_locale_values_are_rejected() {
+        let locale = |value| serde_json::from_value::<LocaleFormat>(value);
+        assert!(locale(serde_json::json!({"language": "fr"})).is_err());
+        assert!(locale(serde_json::json!({"era": "japanese"})).is_err());
+        let unknown = locale(serde_json::json!({"date_format": "%d %Q"})).unwrap();
+        assert!(unknown.validate().unwrap_err().contains("%d %Q"));
+        assert!(locale(serde_json::json!({"date_format": " "}))
+            .unwrap()
+            .validate()
+            .is_err());
+        // รูปแบบที่ไม่ผ่าน `validate` พิมพ์ตัวแปรที่ไม่รู้จักตามตัวอักษร
+        let date = chrono::NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        assert_eq!(unknown.format_date(Language::En, date), "14 %Q");
+    }
+}
@@ -1,15 +1,31 @@
-use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
-use anyhow::{bail, Context, Result};
-use lopdf::content::{Content, Operation};
-use lopdf::{Document, Object};
-use printers::{self, common::base::job::PrinterJobOptions};
-use serde::{Deserialize, Serialize};
-
+use crate::{
+    admin_channel::{run_admin_channel, run_service_commands, AdminContext, ServiceCommand},
+    agent::{monitor_agents, run_agent, AgentRegistry, RemoteJobQueue},
+    archive::last_stored_job_id,
+    backend::install_print_backend,
+    bench::run_benchmark,
+    billing::{BillingLedger, BILLING_PATH},
+    cli::run_client,
+    config::{load_config, SharedConfig, DEFAULT_HTTP_PORT},
+    counters::{CounterStore, COUNTERS_PATH},
+    disk::{monitor_disk_space, DiskMonitor},
+    dispatch::PrintDispatcher,
+    history::{run_retention_cleanup, track_spooler_jobs, JobHistory},
+    notify::{monitor_printer_health, run_daily_summary},
+    print_tokens::{PrintTokenStore, PRINT_TOKENS_PATH},
+    printer_history::{PrinterTimeline, PRINTER_HISTORY_PATH},
+    self_test::{run_startup_check, PrinterReadiness},
+    spooler::monitor_spooler,
+    storage::{open_storage, FileHashCache, Storage},
+    transform_cache::TransformCache,
+    uploads::{UploadStore, UPLOAD_PARTS_DIR},
+};
+use actix_web::{get, web, App, HttpResponse, HttpServer};
 use std::ffi::OsString;
 use std::path::Path;
 use std::sync::mpsc;
-use std::time::Duration;
-use utoipa::{OpenApi, ToSchema};
+use std::time::{Duration, Instant};
+use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 use windows_service::{
     define_windows_service,
@@ -21,106 +37,58 @@ use windows_service::{
     service_dispatcher,
 };
 
+mod admin_channel;
+mod agent;
+mod api;
+mod archive;
+mod backend;
+mod bench;
+mod billing;
+mod capabilities;
+mod cli;
+mod client_identity;
+mod config;
+mod counters;
+mod crypto;
+mod discovery;
+mod disk;
+mod dispatch;
+mod estimate;
+mod event_log;
+mod history;
+mod hooks;
+mod ipp;
+mod job_settings;
+mod locale;
+mod multipart;
+mod notify;
+mod output;
+mod pdf;
+mod preflight;
+mod print_tokens;
+mod printer_history;
+mod proof;
+mod raster;
+mod self_test;
+mod sessions;
+mod spooler;
+mod stamp_font;
+mod storage;
+mod tenants;
+mod test_page;
+mod text;
+mod transform;
+mod transform_cache;
+mod uploads;
+mod watermark;
+
 const SERVICE_NAME: &str = "rust-print-api";
 const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
 
-// --- ค่าคงที่สำหรับขนาดกระดาษในหน่วย PostScript Points (1 point = 1/72 inch) ---
-
-// A6: 105mm x 148mm
-const A6_WIDTH_PTS: f32 = 297.64;
-const A6_HEIGHT_PTS: f32 = 419.53;
-
-// A4: 210mm x 297mm (ใช้เป็นขนาดอ้างอิงของเอกสารต้นฉบับ)
-const A4_WIDTH_PTS: f32 = 595.28;
-const A4_HEIGHT_PTS: f32 = 841.89;
-
-/// โครงสร้างสำหรับรับข้อมูลจาก HTTP Request (JSON)
-#[derive(Deserialize, ToSchema)]
-#[schema(example = json!({"filename": "invoice_original.pdf", "printer_name": "Office_LaserJet"}))]
-struct PrintRequest {
-    /// ชื่อไฟล์ PDF ต้นฉบับที่จะค้นหาในโฟลเดอร์ ./printable_files
-    filename: String,
-    /// ชื่อเครื่องพิมพ์ปลายทางที่ติดตั้งในระบบ
-    printer_name: String,
-}
-
-/// โครงสร้างสำหรับ Response ที่ส่งกลับไปให้ Client
-#[derive(Serialize, ToSchema)]
-struct ResponseMessage {
-    status: String,
-    message: String,
-}
-
-// ----------------------------------------------------------------------
-//                        PDF RESIZING LOGIC (WITH SCALING)
-// ----------------------------------------------------------------------
-
-/// แปลงขนาด PDF จากไฟล์ต้นฉบับเป็น A6 และปรับมาตราส่วนเนื้อหา
-fn resize_pdf_to_a6(input_path: &Path, output_path: &Path) -> Result<()> {
-    let mut doc = Document::load(input_path)
-        .context(format!("Failed to load PDF file: {}", input_path.display()))?;
-
-    // คำนวณ Scale Factor (สมมติ A4 เป็นขนาดตั้งต้น)
-    let scale_x = A6_WIDTH_PTS / A4_WIDTH_PTS;
-    let scale_y = A6_HEIGHT_PTS / A4_HEIGHT_PTS;
-    let scale_factor = scale_x.min(scale_y);
-
-    if scale_factor > 1.0 {
-        bail!("Scaling up is not handled, only scaling down to A6.");
-    }
-
-    for (_, page_id) in doc.get_pages() {
-        // Modify MediaBox
-        if let Ok(page) = doc.get_dictionary_mut(page_id) {
-            let new_media_box = vec![
-                Object::Real(0.0),
-                Object::Real(0.0),
-                Object::Real(A6_WIDTH_PTS),
-                Object::Real(A6_HEIGHT_PTS),
-            ];
-            page.set("MediaBox", Object::Array(new_media_box));
-        }
-
-        // Modify content stream
-        let content_data = doc.get_page_content(page_id)?;
-        let mut content = Content::decode(&content_data)?;
-
-        let matrix_op = Operation::new(
-            "cm",
-            vec![
-                Object::Real(scale_factor),
-                Object::Real(0.0),
-                Object::Real(0.0),
-                Object::Real(scale_factor),
-                Object::Real(0.0),
-                Object::Real(0.0),
-            ],
-        );
-        content.operations.insert(0, matrix_op);
-
-        let new_content = content.encode()?;
-        doc.change_page_content(page_id, new_content)?;
-    }
-
-    doc.save(output_path)
-        .context(format!("Failed to save new A6 PDF file: {}", output_path.display()))?;
-
-    Ok(())
-}
-
 // ----------------------------------------------------------------------
 //                           API HANDLER (UPDATED)
 // ----------------------------------------------------------------------
 
-/// กำหนดโครงสร้างเอกสาร OpenAPI
-#[derive(OpenApi)]
-#[openapi(
-    paths(print_file_handler, index),
-    components(schemas(PrintRequest, ResponseMessage)),
-    tags((name = "Printing", description = "Endpoints สำหรับการดำเนินการสั่งพิมพ์ไฟล์และแปลงขนาด"))
-)]
-struct ApiDoc;
-
 #[utoipa::path(
     get,
     path = "/",
@@ -191,128 +159,296 @@ async fn index() -> HttpResponse {
     HttpResponse::Ok().content_type("text/html").body(html_content)
 }
 
+/// พร้อมรับงานหรือไม่ สำหรับ load balancer และระบบ monitor (HTTP 503 พร้อมเหตุผลเมื่อไม่พร้อม)
 #[utoipa::path(
-    post,
-    path = "/api/print",
-    tag = "Printing",
-    request_body = PrintRequest,
+    get,
+    path = "/readyz",
     responses(
-        (status = 200, description = "แปลงและส่งคำสั่งพิมพ์สำเร็จ", body = ResponseMessage),
-        (status = 400, description = "เกิดข้อผิดพลาดในการจัดการไฟล์", body = ResponseMessage),
-        (status = 500, description = "เกิดข้อผิดพลาดในการประมวลผลหรือสั่งพิมพ์", body = ResponseMessage)
+        (status = 200, description = "พร้อมรับงาน `degraded_printers` คือเครื่องพิมพ์ที่ `startup_check` ตรวจแล้วใช้งานไม่ได้", body = Object, example = json!({"status": "ready", "degraded_printers": {"Office_LaserJet": "Printer Office_LaserJet is offline"}})),
+        (status = 503, description = "ยังไม่พร้อมรับงาน เช่น พื้นที่ว่างบนดิสก์ต่ำกว่า `min_free_disk_mb` หรือ Print Spooler หยุดทำงาน", body = Object, example = json!({"status": "not_ready", "reasons": ["Low disk space on ./printable_files: 120 MB free (minimum 200 MB)"], "degraded_printers": {}}))
     )
 )]
-#[post("/api/print")]
-async fn print_file_handler(req: web::Json<PrintRequest>) -> impl Responder {
-    let base_dir = Path::new("./printable_files");
-    let original_file_path = base_dir.join(&req.filename);
-
-    // --- โค้ดที่เปลี่ยน: สร้างชื่อไฟล์ A6 ถาวร โดยมี _a6 ต่อท้าย ---
-    let original_filename = &req.filename;
-    let a6_filename = original_filename.rfind('.').map_or_else(
-        || format!("{}_a6", original_filename), // กรณีไม่มีนามสกุล
-        |i| {
-            let (name, ext) = original_filename.split_at(i);
-            format!("{}_a6{}", name, ext) // กรณีมีนามสกุล เช่น "invoice.pdf" -> "invoice_a6.pdf"
-        },
-    );
-    let a6_file_path = base_dir.join(&a6_filename);
-    // ---------------------------------------------------------------------
-
-    if !original_file_path.exists() {
-        return HttpResponse::BadRequest().json(ResponseMessage {
-            status: "error".to_string(),
-            message: format!("File not found: {}", req.filename),
-        });
+#[get("/readyz")]
+async fn readyz(
+    disk: web::Data<DiskMonitor>,
+    readiness: web::Data<PrinterReadiness>,
+    dispatcher: web::Data<PrintDispatcher>,
+) -> HttpResponse {
+    let degraded_printers = readiness.degraded();
+    let reasons: Vec<String> = disk
+        .low_space()
+        .into_iter()
+        .chain(dispatcher.spooler.outage())
+        .collect();
+    if reasons.is_empty() {
+        HttpResponse::Ok().json(serde_json::json!({
+            "status": "ready",
+            "degraded_printers": degraded_printers,
+        }))
+    } else {
+        HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "status": "not_ready",
+            "reasons": reasons,
+            "degraded_printers": degraded_printers,
+        }))
     }
+}
 
-    // 1. แปลงขนาด PDF เป็น A6 และบันทึกไฟล์ใหม่
-    match resize_pdf_to_a6(&original_file_path, &a6_file_path) {
-        Ok(_) => println!("PDF successfully resized and saved as {}", a6_filename),
-        Err(e) => {
-            eprintln!("Error resizing PDF: {:?}", e);
-            return HttpResponse::InternalServerError().json(ResponseMessage {
-                status: "error".to_string(),
-                message: format!("Failed to resize PDF to A6: {}", e),
-            });
+/// แปลง schema ของ OpenAPI 3.0 หนึ่งตัวเป็น JSON Schema (draft 2020-12)
+/// (`nullable` เป็น `"null"` ใน `type`, `example` เป็น `examples` และ `$ref` ชี้ไปที่ `$defs`)
+fn json_schema(schema: serde_json::Value) -> serde_json::Value {
+    let serde_json::Value::Object(mut map) = schema else {
+        return schema;
+    };
+    if let Some(serde_json::Value::String(reference)) = map.get_mut("$ref") {
+        *reference = reference.replace("#/components/schemas/", "#/$defs/");
+    }
+    if let Some(serde_json::Value::Object(properties)) = map.get_mut("properties") {
+        for property in properties.values_mut() {
+            *property = json_schema(property.take());
         }
     }
-
-    // 2. อ่านไฟล์ A6 ที่สร้างขึ้นใหม่ และสั่งพิมพ์
-    let file_data = match std::fs::read(&a6_file_path) {
-        Ok(data) => data,
-        Err(e) => {
-            eprintln!("Error reading A6 file {}: {:?}", a6_filename, e);
-            return HttpResponse::InternalServerError().json(ResponseMessage {
-                status: "error".to_string(),
-                message: format!("Failed to read A6 file {}. Error: {}", a6_filename, e),
-            });
+    for keyword in ["items", "additionalProperties", "not"] {
+        if let Some(subschema) = map.get_mut(keyword).filter(|value| value.is_object()) {
+            *subschema = json_schema(subschema.take());
         }
-    };
-
-    println!("Successfully read A6 file: {}", a6_filename);
-
-    let printer = match printers::get_printer_by_name(&req.printer_name) {
-        Some(p) => p,
-        None => {
-            return HttpResponse::BadRequest().json(ResponseMessage {
-                status: "error".to_string(),
-                message: format!("Printer not found: {}", req.printer_name),
-            });
+    }
+    for keyword in ["allOf", "oneOf", "anyOf"] {
+        if let Some(serde_json::Value::Array(subschemas)) = map.get_mut(keyword) {
+            for subschema in subschemas.iter_mut() {
+                *subschema = json_schema(subschema.take());
+            }
         }
-    };
+    }
+    if let Some(example) = map.remove("example") {
+        map.insert("examples".to_string(), serde_json::json!([example]));
+    }
+    // ใช้เลือกชนิดใน `oneOf` ของ OpenAPI เท่านั้น ส่วน JSON Schema ตรวจจาก `enum` ของ `op` ในแต่ละชนิดอยู่แล้ว
+    map.remove("discriminator");
+    if map.remove("nullable") != Some(serde_json::Value::Bool(true)) {
+        return serde_json::Value::Object(map);
+    }
+    match map.get("type").cloned() {
+        Some(serde_json::Value::String(kind)) => {
+            map.insert("type".to_string(), serde_json::json!([kind, "null"]));
+            serde_json::Value::Object(map)
+        }
+        _ => serde_json::json!({ "anyOf": [map, { "type": "null" }] }),
+    }
+}
 
-    let options = PrinterJobOptions {
-        name: Some(&format!("A6 Print Job - {}", req.filename)),
-        raw_properties: &[],
-    };
+/// JSON Schema ของทุกชนิดข้อมูลใน API สำหรับสร้าง client (TypeScript, C# ฯลฯ)
+/// โดยสร้างจาก schema เดียวกับ /api-docs/openapi.json
+#[utoipa::path(
+    get,
+    path = "/api-docs/schemas.json",
+    responses(
+        (status = 200, description = "JSON Schema (draft 2020-12) ที่มีทุกชนิดข้อมูลใน `$defs` เช่น `PrintRequest`, `JobRecord` และ `HookContext` (ข้อมูลที่ส่งให้ hook)", body = Object)
+    )
+)]
+#[get("/api-docs/schemas.json")]
+async fn json_schemas(openapi: web::Data<utoipa::openapi::OpenApi>) -> HttpResponse {
+    let definitions: serde_json::Map<String, serde_json::Value> = openapi
+        .components
+        .iter()
+        .flat_map(|components| &components.schemas)
+        .map(|(name, schema)| {
+            let schema = serde_json::to_value(schema).unwrap_or_default();
+            (name.clone(), json_schema(schema))
+        })
+        .collect();
+    HttpResponse::Ok().json(serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "/api-docs/schemas.json",
+        "title": openapi.info.title,
+        "version": openapi.info.version,
+        "$defs": definitions,
+    }))
+}
 
-    match printer.print(&file_data, options) {
-        Ok(_) => {
-            println!("Print job sent successfully to {}", req.printer_name);
-            HttpResponse::Ok().json(ResponseMessage {
-                status: "success".to_string(),
-                message: format!(
-                    "Resized to A6, saved as {}, and sent to printer {}",
-                    a6_filename, req.printer_name
-                ),
-            })
-        }
-        Err(e) => {
-            eprintln!("Error sending print job: {:?}", e);
-            HttpResponse::InternalServerError().json(ResponseMessage {
-                status: "error".to_string(),
-                message: format!("Failed to send print job: {:?}", e),
-            })
-        }
+/// หน้า Dashboard สำหรับผู้ดูแลระบบ (ข้อมูลดึงผ่าน /api/v1/admin/* ซึ่งต้องยืนยันตัวตน)
+#[get("/admin")]
+async fn admin_dashboard() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(include_str!("../assets/admin/index.html"))
+}
+
+#[get("/admin/{asset}")]
+async fn admin_asset(asset: web::Path<String>) -> HttpResponse {
+    match asset.as_str() {
+        "style.css" => HttpResponse::Ok()
+            .content_type("text/css; charset=utf-8")
+            .body(include_str!("../assets/admin/style.css")),
+        "app.js" => HttpResponse::Ok()
+            .content_type("application/javascript; charset=utf-8")
+            .body(include_str!("../assets/admin/app.js")),
+        _ => HttpResponse::NotFound().finish(),
     }
 }
 
-async fn run_app() -> std::io::Result<()> {
+/// `service_commands` รับคำสั่งจาก service control (`sc control ... paramchange` และ control code 128 - 129)
+async fn run_app(service_commands: Option<mpsc::Receiver<ServiceCommand>>) -> std::io::Result<()> {
     let base_dir = Path::new("./printable_files");
     if !base_dir.exists() {
         std::fs::create_dir(base_dir)?;
         println!("Created directory: ./printable_files");
     }
 
-    let openapi = web::Data::new(ApiDoc::openapi());
+    let config = web::Data::new(SharedConfig::new(load_config()?));
+    install_print_backend(&config.get().print_backend);
+    let queue = web::Data::new(RemoteJobQueue::restore(&config.get()));
+    let registry = web::Data::new(AgentRegistry::default());
+    let uploads = web::Data::new(UploadStore::open(Path::new(UPLOAD_PARTS_DIR)));
+    let file_hashes = web::Data::new(FileHashCache::default());
+    let storage: web::Data<dyn Storage> = web::Data::from(open_storage(&config.get().storage));
+    println!("Storing uploaded files in {}", storage.describe());
+    let counters =
+        web::Data::new(CounterStore::load(Path::new(COUNTERS_PATH)).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:#}", e))
+        })?);
+    let print_tokens = web::Data::new(
+        PrintTokenStore::load(Path::new(PRINT_TOKENS_PATH)).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{:#}", e))
+        })?,
+    );
+    let history = web::Data::new(JobHistory::starting_after(
+        last_stored_job_id(&config.get()),
+        PrinterTimeline::load(Path::new(PRINTER_HISTORY_PATH)),
+        BillingLedger::load(Path::new(BILLING_PATH)),
+        config.get().billing.cost_center_key(),
+    ));
+    let openapi = web::Data::new(api::v1::ApiDoc::openapi());
+    let disk = web::Data::new(DiskMonitor::default());
+    disk.refresh(&config.get());
+    let dispatcher = web::Data::new(PrintDispatcher::default());
+    dispatcher.spooler.refresh(&config.get());
+    dispatcher.sessions.refresh();
+    let transform_cache = web::Data::new(TransformCache::default());
+    let readiness = web::Data::new(PrinterReadiness::default());
+
+    // ทำงานเสมอแม้ยังไม่มี agent เพราะอาจเพิ่ม `agents` ภายหลังด้วยการอ่าน config ใหม่
+    {
+        let (config, registry) = (config.clone(), registry.clone());
+        let (queue, history) = (queue.clone(), history.clone());
+        std::thread::spawn(move || monitor_agents(config, registry, queue, history));
+    }
+    let admin_context = AdminContext {
+        config: config.clone(),
+        queue: queue.clone(),
+        registry: registry.clone(),
+        uploads: uploads.clone(),
+        dispatcher: dispatcher.clone(),
+        started_at: Instant::now(),
+    };
+    if let Some(service_commands) = service_commands {
+        let context = admin_context.clone();
+        std::thread::spawn(move || run_service_commands(service_commands, context));
+    }
+    #[cfg(unix)]
+    tokio::spawn(crate::config::reload_on_hangup(config.clone()));
+    {
+        let config = config.clone();
+        std::thread::spawn(move || run_retention_cleanup(config));
+    }
+    {
+        let history = history.clone();
+        std::thread::spawn(move || track_spooler_jobs(history));
+    }
+    {
+        let (config, disk) = (config.clone(), disk.clone());
+        std::thread::spawn(move || monitor_disk_space(config, disk));
+    }
+    {
+        let (config, dispatcher) = (config.clone(), dispatcher.clone());
+        std::thread::spawn(move || monitor_spooler(config, dispatcher));
+    }
+    {
+        let (config, registry, history) = (config.clone(), registry.clone(), history.clone());
+        std::thread::spawn(move || monitor_printer_health(config, registry, history));
+    }
+    {
+        let (config, history) = (config.clone(), history.clone());
+        std::thread::spawn(move || run_daily_summary(config, history));
+    }
+    {
+        let (config, readiness, dispatcher) =
+            (config.clone(), readiness.clone(), dispatcher.clone());
+        std::thread::spawn(move || run_startup_check(config, readiness, dispatcher));
+    }
+    if config.get().agent.is_some() {
+        let (agent_config, dispatcher) = (config.clone(), dispatcher.clone());
+        std::thread::spawn(move || run_agent(agent_config, dispatcher));
+    }
+    if let Some(port) = config.get().admin_port {
+        std::thread::spawn(move || run_admin_channel(port, admin_context));
+    }
 
-    println!("Starting server at http://127.0.0.1:8080");
-    println!("Swagger UI available at: http://127.0.0.1:8080/swagger-ui/");
+    let tuning = config.get().server.clone();
+    let port = tuning.port.unwrap_or(DEFAULT_HTTP_PORT);
+    println!("Starting server at http://127.0.0.1:{}", port);
+    println!(
+        "Swagger UI available at: http://127.0.0.1:{}/swagger-ui/",
+        port
+    );
 
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         App::new()
+            .app_data(config.clone())
+            .app_data(queue.clone())
+            .app_data(registry.clone())
+            .app_data(uploads.clone())
+            .app_data(file_hashes.clone())
+            .app_data(storage.clone())
+            .app_data(history.clone())
+            .app_data(counters.clone())
+            .app_data(print_tokens.clone())
             .app_data(openapi.clone())
+            .app_data(disk.clone())
+            .app_data(dispatcher.clone())
+            .app_data(transform_cache.clone())
+            .app_data(readiness.clone())
+            .wrap(actix_web::middleware::from_fn(api::v1::localize_errors))
             .service(index)
-            .service(print_file_handler)
+            .service(readyz)
+            .service(json_schemas)
+            .service(admin_dashboard)
+            .service(admin_asset)
+            .service(web::scope("/api/v1").configure(api::v1::configure))
+            // เส้นทางเดิมก่อนแยกเวอร์ชัน (เช่น /api/print) ยังใช้งานได้ เพื่อไม่ให้ kiosk ที่ติดตั้งไปแล้วใช้งานไม่ได้
+            .service(web::scope("/api").configure(api::v1::configure))
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
                     .url("/api-docs/openapi.json", openapi.get_ref().clone()),
             )
-    })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    });
+    if let Some(workers) = tuning.workers {
+        server = server.workers(workers);
+    }
+    if let Some(secs) = tuning.keep_alive_secs {
+        server = server.keep_alive(match secs {
+            0 => actix_web::http::KeepAlive::Disabled,
+            secs => actix_web::http::KeepAlive::Timeout(Duration::from_secs(secs)),
+        });
+    }
+    if let Some(ms) = tuning.client_request_timeout_ms {
+        server = server.client_request_timeout(Duration::from_millis(ms));
+    }
+    if let Some(ms) = tuning.client_disconnect_timeout_ms {
+        server = server.client_disconnect_timeout(Duration::from_millis(ms));
+    }
+    if let Some(max_connections) = tuning.max_connections {
+        server = server.max_connections(max_connections);
+    }
+    // ต้องตั้งก่อน bind เพราะ backlog ใช้ตอนสร้าง socket
+    if let Some(backlog) = tuning.backlog {
+        server = server.backlog(backlog);
+    }
+    let server = match tuning.http2 {
+        true => server.bind_auto_h2c(("127.0.0.1", port))?,
+        false => server.bind(("127.0.0.1", port))?,
+    };
+    server.run().await
 }
 
 fn my_service_main(_arguments: Vec<OsString>) {
@@ -323,6 +459,7 @@ fn my_service_main(_arguments: Vec<OsString>) {
 
 fn run_service() -> windows_service::Result<()> {
     let (shutdown_tx, shutdown_rx) = mpsc::channel();
+    let (command_tx, command_rx) = mpsc::channel();
 
     let event_handler = move |control_event| -> ServiceControlHandlerResult {
         match control_event {
@@ -330,6 +467,28 @@ fn run_service() -> windows_service::Result<()> {
                 shutdown_tx.send(()).unwrap();
                 ServiceControlHandlerResult::NoError
             }
+            // `sc control "Rust Print API" paramchange`
+            ServiceControl::ParamChange => {
+                let _ = command_tx.send(ServiceCommand::ReloadConfig);
+                ServiceControlHandlerResult::NoError
+            }
+            // `sc control "Rust Print API" 128` (ล้างคิว) และ `129` (เขียน diagnostics ลงไฟล์)
+            ServiceControl::UserEvent(code) => {
+                match ServiceCommand::from_user_code(code.to_raw()) {
+                    Some(command) => {
+                        let _ = command_tx.send(command);
+                        ServiceControlHandlerResult::NoError
+                    }
+                    None => ServiceControlHandlerResult::NotImplemented,
+                }
+            }
+            ServiceControl::SessionChange(change) => {
+                let _ = command_tx.send(ServiceCommand::SessionChange {
+                    session_id: change.notification.session_id,
+                    reason: change.reason,
+                });
+                ServiceControlHandlerResult::NoError
+            }
             ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
             _ => ServiceControlHandlerResult::NotImplemented,
         }
@@ -340,7 +499,9 @@ fn run_service() -> windows_service::Result<()> {
     status_handle.set_service_status(ServiceStatus {
         service_type: SERVICE_TYPE,
         current_state: ServiceState::Running,
-        controls_accepted: ServiceControlAccept::STOP,
+        controls_accepted: ServiceControlAccept::STOP
+            | ServiceControlAccept::PARAM_CHANGE
+            | ServiceControlAccept::SESSION_CHANGE,
         exit_code: ServiceExitCode::Win32(0),
         checkpoint: 0,
         wait_hint: Duration::default(),
@@ -350,7 +511,7 @@ fn run_service() -> windows_service::Result<()> {
     let rt = tokio::runtime::Runtime::new().unwrap();
     std::thread::spawn(move || {
         rt.block_on(async {
-            if let Err(e) = run_app().await {
+            if let Err(e) = run_app(Some(command_rx)).await {
                 eprintln!("Server failed to start: {}", e);
             }
         });
@@ -381,9 +542,16 @@ define_windows_service!(ffi_service_main, my_service_main);
 
 fn main() -> windows_service::Result<()> {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 && args[1] == "--console" {
+    if args.len() > 1 && args[1] == "client" {
+        std::process::exit(run_client(&args[2..]));
+    } else if args.len() > 1 && args[1] == "bench" {
+        std::process::exit(run_benchmark(&args[2..]));
+    } else if args.len() > 1 && args[1] == "--console" {
         // Run in console mode
-        if let Err(e) = tokio::runtime::Runtime::new().unwrap().block_on(run_app()) {
+        if let Err(e) = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(run_app(None))
+        {
             eprintln!("Failed to run in console mode: {}", e);
         }
     } else {
@@ -0,0 +1,236 @@
+// ----------------------------------------------------------------------
+//                           MULTIPART UPLOAD
+// ----------------------------------------------------------------------
+
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// ขนาดสูงสุดของ request ที่อัปโหลดไฟล์ (ไบต์)
+pub(crate) const MAX_UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+/// ส่วนหนึ่งของ body แบบ `multipart/form-data`
+pub(crate) struct MultipartPart {
+    /// ชื่อ field จาก `Content-Disposition: form-data; name="..."`
+    pub(crate) name: String,
+    /// ชื่อไฟล์ที่ client ส่งมา (มีเฉพาะ part ที่เป็นไฟล์)
+    pub(crate) filename: Option<String>,
+    pub(crate) data: Vec<u8>,
+}
+
+/// อ่านค่า parameter เช่น `boundary=...` หรือ `name="..."` จาก header ที่คั่นด้วย `;`
+pub(crate) fn header_parameter(header: &str, key: &str) -> Option<String> {
+    // `;` ในค่าที่อยู่ในเครื่องหมายคำพูด เช่น `filename="a;b.pdf"` ไม่ใช่ตัวคั่น
+    let mut quoted = false;
+    let separator = |c: char| {
+        quoted ^= c == '"';
+        c == ';' && !quoted
+    };
+    header.split(separator).skip(1).find_map(|param| {
+        let (name, value) = param.trim().split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case(key)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// แยก body แบบ `multipart/form-data` ออกเป็นแต่ละ part ตาม boundary ใน `Content-Type`
+pub(crate) fn parse_multipart(content_type: &str, body: &[u8]) -> Result<Vec<MultipartPart>> {
+    if !content_type
+        .trim_start()
+        .to_ascii_lowercase()
+        .starts_with("multipart/form-data")
+    {
+        bail!("Expected multipart/form-data, got {}", content_type);
+    }
+    let boundary = header_parameter(content_type, "boundary")
+        .filter(|b| !b.is_empty())
+        .context("Missing multipart boundary")?;
+    let delimiter = format!("\r\n--{}", boundary).into_bytes();
+    // boundary ต้องตามด้วย `--` (part สุดท้าย) หรือจบบรรทัด (หลังช่องว่างได้ตาม RFC 2046)
+    let is_delimiter = |rest: &[u8]| {
+        let padding = rest
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count();
+        rest.starts_with(b"--") || rest[padding..].starts_with(b"\r\n")
+    };
+    let find = |data: &[u8], from: usize| {
+        data[from..]
+            .windows(delimiter.len())
+            .enumerate()
+            .filter(|(_, w)| *w == delimiter.as_slice())
+            .map(|(i, _)| from + i)
+            .find(|&i| is_delimiter(&data[i + delimiter.len()..]))
+    };
+
+    // ใส่ CRLF ไว้หน้า body เพื่อให้ boundary แรกค้นหาได้แบบเดียวกับ boundary อื่น
+    let mut data = b"\r\n".to_vec();
+    data.extend_from_slice(body);
+    let mut position = find(&data, 0).context("Multipart body has no boundary")?;
+    let mut parts = Vec::new();
+    loop {
+        let start = position + delimiter.len();
+        if data[start..].starts_with(b"--") {
+            break;
+        }
+        let next = find(&data, start).context("Multipart body is not terminated")?;
+        let part = &data[start..next];
+        let header_end = part
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .context("Multipart part has no header")?;
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let disposition = headers
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-disposition"))
+            .map(|(_, value)| value.trim().to_string())
+            .context("Multipart part has no Content-Disposition")?;
+        parts.push(MultipartPart {
+            name: header_parameter(&disposition, "name").unwrap_or_default(),
+            filename: header_parameter(&disposition, "filename"),
+            data: part[header_end + 4..].to_vec(),
+        });
+        position = next;
+    }
+    Ok(parts)
+}
+
+/// สร้างชื่อไฟล์ชั่วคราวที่ไม่ซ้ำกันสำหรับไฟล์ที่อัปโหลด โดยเก็บชื่อเดิมที่ปลอดภัยไว้ท้ายชื่อ
+pub(crate) fn upload_filename(original: Option<&str>) -> String {
+    static UPLOAD_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let original = original
+        .and_then(|name| Path::new(name).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let safe: String = original
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = safe
+        .strip_suffix(".pdf")
+        .or_else(|| safe.strip_suffix(".PDF"))
+        .unwrap_or(&safe);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!(
+        "upload_{}_{}_{}.pdf",
+        timestamp,
+        UPLOAD_COUNTER.fetch_add(1, Ordering::Relaxed),
+        if stem.is_empty() { "document" } else { stem }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTIPART_TYPE: &str = "multipart/form-data; boundary=\"XyZ\"";
+
+    fn multipart_error(content_type: &str, body: &[u8]) -> String {
+        match parse_multipart(content_type, body) {
+            Ok(parts) => panic!("Parsed {} parts from an invalid body", parts.len()),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn multipart_parts_are_split_on_boundaries() {
+        // ไฟล์ที่มี CRLF และข้อความคล้าย boundary ที่ไม่ได้ขึ้นต้นบรรทัด
+        let mut file = b"%PDF-1.5\r\n--XyZ inside\r\n".to_vec();
+        file.extend((0..=255u8).cycle().take(300_000));
+        let mut body = b"preamble\r\n--XyZ\r\n\
+            Content-Disposition: form-data; name=\"options\"\r\n\r\n\
+            {\"copies\":2}\r\n--XyZ\r\n\
+            content-disposition: form-data; name=\"file\"; filename=\"a;b.pdf\"\r\n\
+            Content-Type: application/pdf\r\n\r\n"
+            .to_vec();
+        body.extend_from_slice(&file);
+        body.extend_from_slice(b"\r\n--XyZ--\r\nepilogue");
+
+        let parts = parse_multipart(MULTIPART_TYPE, &body).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(
+            (parts[0].name.as_str(), parts[0].filename.as_deref()),
+            ("options", None)
+        );
+        assert_eq!(parts[0].data, br#"{"copies":2}"#);
+        assert_eq!(
+            (parts[1].name.as_str(), parts[1].filename.as_deref()),
+            ("file", Some("a;b.pdf"))
+        );
+        assert_eq!(parts[1].data, file);
+    }
+
+    #[test]
+    fn multipart_rejects_missing_boundaries() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n--XyZ--";
+        assert_eq!(
+            multipart_error("application/json", body),
+            "Expected multipart/form-data, got application/json"
+        );
+        for content_type in ["multipart/form-data", "multipart/form-data; boundary=\"\""] {
+            assert_eq!(
+                multipart_error(content_type, body),
+                "Missing multipart boundary"
+            );
+        }
+        assert_eq!(
+            multipart_error("multipart/form-data; boundary=Other", body),
+            "Multipart body has no boundary"
+        );
+        for body in [b"".as_slice(), b"--XyZ", b"--XyZ-"] {
+            assert_eq!(
+                multipart_error(MULTIPART_TYPE, body),
+                "Multipart body has no boundary"
+            );
+        }
+    }
+
+    #[test]
+    fn multipart_rejects_unterminated_bodies() {
+        for body in [
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1".as_slice(),
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n--Xy",
+            // boundary ปิดต้องขึ้นต้นบรรทัด
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1--XyZ--",
+        ] {
+            assert_eq!(
+                multipart_error(MULTIPART_TYPE, body),
+                "Multipart body is not terminated"
+            );
+        }
+    }
+
+    #[test]
+    fn multipart_rejects_truncated_headers() {
+        assert_eq!(
+            multipart_error(
+                MULTIPART_TYPE,
+                b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n--XyZ--"
+            ),
+            "Multipart part has no header"
+        );
+        assert_eq!(
+            multipart_error(
+                MULTIPART_TYPE,
+                b"--XyZ\r\nContent-Type: application/pdf\r\n\r\n1\r\n--XyZ--"
+            ),
+            "Multipart part has no Content-Disposition"
+        );
+        // body ว่างที่ปิดทันทีไม่มี part
+        assert!(parse_multipart(MULTIPART_TYPE, b"--XyZ--")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,517 @@
+// ----------------------------------------------------------------------
+//                           NOTIFICATIONS
+// ----------------------------------------------------------------------
+
+use crate::{
+    agent::AgentRegistry,
+    backend::print_backend,
+    billing::{export_previous_billing_month, BILLING_PATH, DEFAULT_BILLING_RETENTION_MONTHS},
+    config::SharedConfig,
+    history::{JobHistory, JobRecord, JobStatistics},
+    hooks::{run_hook, HookCommand},
+    printer_history::{printer_history_days, PRINTER_HISTORY_PATH},
+    storage::{curl_config_line, CurlConfigFile},
+    SERVICE_NAME,
+};
+use actix_web::web;
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// ช่วงเวลาที่ตรวจสถานะเครื่องพิมพ์และ agent สำหรับการแจ้งเตือน
+pub(crate) const PRINTER_HEALTH_INTERVAL: Duration = Duration::from_secs(60);
+pub(crate) const DEFAULT_PRINTER_OFFLINE_MINUTES: u64 = 10;
+/// เวลาสูงสุดที่รอให้ส่งข้อความหนึ่งช่องทางเสร็จ (วินาที)
+pub(crate) const NOTIFICATION_TIMEOUT_SECS: u64 = 15;
+/// ช่วงเวลาที่ตรวจว่าถึงเวลาส่งรายงานประจำวันหรือยัง
+pub(crate) const DAILY_SUMMARY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// ช่องทางที่ส่งข้อความแจ้งเตือน
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum NotificationChannel {
+    /// Incoming Webhook ของ Slack
+    Slack { webhook_url: String },
+    /// Webhook ของ Microsoft Teams (Workflows "when a Teams webhook request is received")
+    Teams { webhook_url: String },
+    /// LINE Messaging API (push message) โดย `to` คือ user id หรือ group id ที่รับข้อความ
+    Line {
+        channel_access_token: String,
+        to: String,
+    },
+}
+
+impl NotificationChannel {
+    fn name(&self) -> &'static str {
+        match self {
+            NotificationChannel::Slack { .. } => "slack",
+            NotificationChannel::Teams { .. } => "teams",
+            NotificationChannel::Line { .. } => "line",
+        }
+    }
+
+    /// URL, header เพิ่มเติม และ body ของ request ที่ส่งข้อความ `text`
+    fn request(&self, text: &str) -> (&str, Option<String>, serde_json::Value) {
+        match self {
+            NotificationChannel::Slack { webhook_url } => {
+                (webhook_url, None, serde_json::json!({ "text": text }))
+            }
+            NotificationChannel::Teams { webhook_url } => (
+                webhook_url,
+                None,
+                serde_json::json!({
+                    "type": "message",
+                    "attachments": [{
+                        "contentType": "application/vnd.microsoft.card.adaptive",
+                        "content": {
+                            "type": "AdaptiveCard",
+                            "version": "1.4",
+                            "body": [{ "type": "TextBlock", "text": text, "wrap": true }],
+                        },
+                    }],
+                }),
+            ),
+            NotificationChannel::Line {
+                channel_access_token,
+                to,
+            } => (
+                "https://api.line.me/v2/bot/message/push",
+                Some(format!("Authorization: Bearer {}", channel_access_token)),
+                serde_json::json!({ "to": to, "messages": [{ "type": "text", "text": text }] }),
+            ),
+        }
+    }
+}
+
+pub(crate) fn default_failure_threshold() -> u32 {
+    3
+}
+
+pub(crate) fn default_require_tls() -> bool {
+    true
+}
+
+/// การส่งอีเมลผ่าน SMTP สำหรับสาขาที่ไม่ได้ใช้ Slack, Teams หรือ LINE
+#[derive(Deserialize, Clone)]
+pub(crate) struct EmailConfig {
+    /// เช่น `smtps://smtp.example.com:465` หรือ `smtp://mail.example.com:587` (ใช้ STARTTLS)
+    smtp_url: String,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    from: String,
+    to: Vec<String>,
+    /// ไม่ส่งอีเมลหากเชื่อมต่อแบบเข้ารหัสไม่ได้ (ปิดเฉพาะ relay ภายในที่ไม่รองรับ TLS)
+    #[serde(default = "default_require_tls")]
+    require_tls: bool,
+    /// ส่งอีเมลเมื่อเครื่องพิมพ์เดียวกันพิมพ์ล้มเหลวติดต่อกันครบจำนวนนี้
+    #[serde(default = "default_failure_threshold")]
+    failure_threshold: u32,
+    /// เวลาที่ส่งรายงานประจำวัน (`HH:MM` ตามเวลาเครื่อง, ไม่ระบุ = ไม่ส่ง)
+    #[serde(default)]
+    daily_summary_at: Option<String>,
+}
+
+/// ตรวจการตั้งค่าอีเมลตอนอ่าน config เพื่อไม่ให้พบข้อผิดพลาดตอนที่ต้องแจ้งเตือน
+pub(crate) fn validate_email_config(email: &EmailConfig) -> Result<()> {
+    if email.to.is_empty() {
+        bail!("notifications.email.to must list at least one recipient");
+    }
+    if email.failure_threshold == 0 {
+        bail!("notifications.email.failure_threshold must be at least 1");
+    }
+    if let Some(at) = &email.daily_summary_at {
+        chrono::NaiveTime::parse_from_str(at, "%H:%M").with_context(|| {
+            format!(
+                "notifications.email.daily_summary_at must be HH:MM, got {:?}",
+                at
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// การแจ้งเตือนเมื่องานล้มเหลวหรือเครื่องพิมพ์ offline นาน (ไม่ระบุ `channels` และ `email` = ไม่แจ้ง)
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct NotificationsConfig {
+    channels: Vec<NotificationChannel>,
+    pub(crate) email: Option<EmailConfig>,
+    /// แจ้งเมื่อเครื่องพิมพ์หรือ agent offline ต่อเนื่องนานกว่านี้ (นาที, 0 = ไม่ตรวจ)
+    printer_offline_minutes: u64,
+    /// โปรแกรม curl ที่ใช้ส่งข้อความผ่าน HTTPS (Windows 10 ขึ้นไปมี curl.exe ในตัว)
+    curl_path: String,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        NotificationsConfig {
+            channels: vec![],
+            email: None,
+            printer_offline_minutes: DEFAULT_PRINTER_OFFLINE_MINUTES,
+            curl_path: "curl".to_string(),
+        }
+    }
+}
+
+pub(crate) fn host_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "unknown host".to_string())
+}
+
+/// ส่ง `text` ไปยังทุกช่องทางใน thread แยก (ขึ้นต้นด้วยชื่อเครื่องเพื่อแยกว่ามาจาก server ใด)
+pub(crate) fn send_notification(notifications: &NotificationsConfig, text: String) {
+    if notifications.channels.is_empty() {
+        return;
+    }
+    let text = format!("[{} @ {}] {}", SERVICE_NAME, host_name(), text);
+    let notifications = notifications.clone();
+    std::thread::spawn(move || {
+        for channel in &notifications.channels {
+            if let Err(e) = deliver_notification(&notifications.curl_path, channel, &text) {
+                eprintln!("Error sending {} notification: {:#}", channel.name(), e);
+            }
+        }
+    });
+}
+
+/// POST ข้อความไปยังช่องทางหนึ่งด้วย curl โดยส่ง URL, header และ body เป็น config ของ curl ทาง stdin
+/// (webhook URL และ token ของช่องทางจึงไม่อยู่ใน argument ที่ผู้ใช้อื่นในเครื่องเห็นได้จากรายการ process)
+pub(crate) fn deliver_notification(
+    curl_path: &str,
+    channel: &NotificationChannel,
+    text: &str,
+) -> Result<()> {
+    let (url, header, body) = channel.request(text);
+    let mut config = curl_config_line("url", url);
+    if let Some(header) = header {
+        config.push_str(&curl_config_line("header", &header));
+    }
+    // `data-raw` ไม่อ่านไฟล์เมื่อ body ขึ้นต้นด้วย `@`
+    config.push_str(&curl_config_line("data-raw", &body.to_string()));
+    let args = [
+        "--silent",
+        "--show-error",
+        "--fail",
+        "--request",
+        "POST",
+        "--header",
+        "Content-Type: application/json",
+        "--config",
+        "-",
+    ]
+    .map(String::from)
+    .to_vec();
+
+    let curl = HookCommand {
+        command: curl_path.to_string(),
+        args,
+        timeout_secs: NOTIFICATION_TIMEOUT_SECS,
+    };
+    let (success, response) = run_hook(&curl, "notification", &config)?;
+    if !success {
+        bail!("{} returned an error {}", channel.name(), response);
+    }
+    Ok(())
+}
+
+/// ส่งอีเมลถึงผู้รับทุกคนใน thread แยก (ไม่ตั้งค่า `email` = ไม่ส่ง)
+pub(crate) fn send_email(notifications: &NotificationsConfig, subject: String, body: String) {
+    let Some(email) = notifications.email.clone() else {
+        return;
+    };
+    let curl_path = notifications.curl_path.clone();
+    let subject = format!("[{} @ {}] {}", SERVICE_NAME, host_name(), subject);
+    std::thread::spawn(move || {
+        if let Err(e) = deliver_email(&curl_path, &email, &subject, &body) {
+            eprintln!("Error sending email notification: {:#}", e);
+        }
+    });
+}
+
+/// ส่งอีเมลด้วย curl (SMTP) โดยส่งข้อความทั้งฉบับทาง stdin
+pub(crate) fn deliver_email(
+    curl_path: &str,
+    email: &EmailConfig,
+    subject: &str,
+    body: &str,
+) -> Result<()> {
+    let mut args: Vec<String> = ["--silent", "--show-error"].map(String::from).to_vec();
+    if email.require_tls {
+        args.push("--ssl-reqd".to_string());
+    }
+    args.extend(["--url".to_string(), email.smtp_url.clone()]);
+    args.extend(["--mail-from".to_string(), email.from.clone()]);
+    for recipient in &email.to {
+        args.extend(["--mail-rcpt".to_string(), recipient.clone()]);
+    }
+    // stdin ใช้ส่งข้อความ จึงส่งรหัสผ่านผ่าน config ในไฟล์ชั่วคราวแทน argument ที่เห็นได้จากรายการ process
+    let credentials = match &email.username {
+        Some(username) => {
+            let password = email.password.as_deref().unwrap_or_default();
+            let user = format!("{}:{}", username, password);
+            let config = CurlConfigFile::create(&[("user", &user)])
+                .context("Failed to write SMTP credentials for curl")?;
+            args.extend(["--config".to_string(), config.path().display().to_string()]);
+            Some(config)
+        }
+        None => None,
+    };
+    args.extend(["--upload-file".to_string(), "-".to_string()]);
+
+    // หัวเรื่องอาจมีชื่อไฟล์ภาษาไทย จึงเข้ารหัสตาม RFC 2047 เสมอ
+    let subject = base64::engine::general_purpose::STANDARD.encode(subject);
+    let message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: =?UTF-8?B?{}?=\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=UTF-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}\r\n",
+        email.from,
+        email.to.join(", "),
+        subject,
+        chrono::Local::now().to_rfc2822(),
+        body.replace('\n', "\r\n"),
+    );
+
+    let curl = HookCommand {
+        command: curl_path.to_string(),
+        args,
+        timeout_secs: NOTIFICATION_TIMEOUT_SECS,
+    };
+    let (success, response) = run_hook(&curl, "notification", &message)?;
+    drop(credentials);
+    if !success {
+        bail!("SMTP server returned an error {}", response);
+    }
+    Ok(())
+}
+
+/// ชื่อเครื่องพิมพ์ของงานสำหรับข้อความแจ้งเตือนและสถิติ
+pub(crate) fn job_target(record: &JobRecord) -> String {
+    match (&record.agent_id, &record.printer_name) {
+        (Some(agent_id), Some(printer)) => format!("{} at agent {}", printer, agent_id),
+        (Some(agent_id), None) => format!("agent {}", agent_id),
+        (None, Some(printer)) => printer.clone(),
+        (None, None) => "the default printer".to_string(),
+    }
+}
+
+/// แจ้งเตือนงานที่ล้มเหลว (ต้องบันทึกงานลง `history` แล้ว) และส่งอีเมลเมื่อเครื่องพิมพ์เดียวกัน
+/// ล้มเหลวติดต่อกันครบ `failure_threshold`
+pub(crate) fn notify_job_failed(
+    notifications: &NotificationsConfig,
+    history: &JobHistory,
+    record: &JobRecord,
+) {
+    let target = job_target(record);
+    send_notification(
+        notifications,
+        format!(
+            "Print job {} ({}) failed on {}: {}",
+            record.id, record.filename, target, record.message
+        ),
+    );
+    let failures = history.consecutive_failures(record);
+    if notifications
+        .email
+        .as_ref()
+        .is_some_and(|email| failures == email.failure_threshold)
+    {
+        send_email(
+            notifications,
+            format!("{} failed {} jobs in a row", target, failures),
+            format!(
+                "The last {} print jobs on {} failed.\n\nLatest: job {} ({}) at {}\n{}",
+                failures, target, record.id, record.filename, record.submitted_at, record.message
+            ),
+        );
+    }
+}
+
+/// แจ้งเตือนงานที่ถูกยกเลิกเพราะเลยกำหนด `expires_at`
+pub(crate) fn notify_job_expired(notifications: &NotificationsConfig, record: &JobRecord) {
+    send_notification(
+        notifications,
+        format!(
+            "Print job {} ({}) on {} expired before printing: {}",
+            record.id,
+            record.filename,
+            job_target(record),
+            record.message
+        ),
+    );
+}
+
+/// ข้อความของรายงานประจำวัน
+pub(crate) fn daily_summary_text(statistics: &JobStatistics) -> String {
+    let jobs: usize = statistics
+        .printers
+        .values()
+        .map(|printer| printer.jobs)
+        .sum();
+    let pages: usize = statistics
+        .printers
+        .values()
+        .map(|printer| printer.pages)
+        .sum();
+    let failed: usize = statistics
+        .printers
+        .values()
+        .map(|printer| printer.failed)
+        .sum();
+    let mut text = format!(
+        "Print summary since {}\n\nTotal: {} jobs, {} pages, {} failed\n",
+        statistics.since.format("%Y-%m-%d %H:%M"),
+        jobs,
+        pages,
+        failed
+    );
+    if !statistics.printers.is_empty() {
+        text.push('\n');
+    }
+    for (name, printer) in &statistics.printers {
+        text.push_str(&format!(
+            "{}: {} jobs, {} pages, {} failed\n",
+            name, printer.jobs, printer.pages, printer.failed
+        ));
+    }
+    text
+}
+
+/// ส่งรายงานประจำวันทางอีเมลตามเวลา `daily_summary_at` (ทำงานใน thread แยก)
+pub(crate) fn run_daily_summary(config: web::Data<SharedConfig>, history: web::Data<JobHistory>) {
+    let started_at = chrono::Local::now();
+    let mut last_sent: Option<chrono::NaiveDate> = None;
+    loop {
+        std::thread::sleep(DAILY_SUMMARY_CHECK_INTERVAL);
+        let current = config.get();
+        let Some(at) = current
+            .notifications
+            .email
+            .as_ref()
+            .and_then(|email| email.daily_summary_at.as_deref())
+            .and_then(|at| chrono::NaiveTime::parse_from_str(at, "%H:%M").ok())
+        else {
+            continue;
+        };
+        let now = chrono::Local::now();
+        let today = now.date_naive();
+        if now.time() < at || last_sent == Some(today) {
+            continue;
+        }
+        last_sent = Some(today);
+        // เริ่มทำงานหลังเวลาส่งของวันนี้ไปแล้ว: รอส่งพรุ่งนี้พร้อมสถิติที่ครบทั้งวัน
+        if today == started_at.date_naive() && started_at.time() > at {
+            continue;
+        }
+        let statistics = history.take_statistics();
+        send_email(
+            &current.notifications,
+            format!("Daily print summary for {}", today.format("%Y-%m-%d")),
+            daily_summary_text(&statistics),
+        );
+        println!("Sent daily print summary");
+    }
+}
+
+/// ตรวจสถานะเครื่องพิมพ์ในเครื่องนี้และ agent ทุกนาที แล้วแจ้งเตือนเมื่อ offline นานกว่า
+/// `printer_offline_minutes` และเมื่อกลับมาใช้งานได้ พร้อมบันทึกเวลาที่ offline ลงใน timeline
+/// ของเครื่องพิมพ์ (ทำงานใน thread แยก)
+pub(crate) fn monitor_printer_health(
+    config: web::Data<SharedConfig>,
+    registry: web::Data<AgentRegistry>,
+    history: web::Data<JobHistory>,
+) {
+    // ชื่อเครื่องพิมพ์หรือ "agent <id>" -> เวลาที่เริ่ม offline และแจ้งเตือนไปแล้วหรือไม่
+    let mut offline: HashMap<String, (Instant, bool)> = HashMap::new();
+    // เครื่องพิมพ์ในเครื่องนี้ที่ offline ในรอบก่อน สำหรับนับช่วงที่ offline ใน timeline
+    let mut offline_printers: BTreeSet<String> = BTreeSet::new();
+    loop {
+        std::thread::sleep(PRINTER_HEALTH_INTERVAL);
+        let current = config.get();
+        let local_down: Vec<String> = print_backend()
+            .printers()
+            .into_iter()
+            .filter(|printer| {
+                matches!(
+                    printer.state,
+                    printers::common::base::printer::PrinterState::OFFLINE
+                )
+            })
+            .map(|printer| printer.name)
+            .collect();
+        for name in &local_down {
+            history
+                .timeline
+                .count_offline(name, !offline_printers.contains(name));
+        }
+        offline_printers = local_down.iter().cloned().collect();
+        if let Err(e) = history.timeline.save(
+            Path::new(PRINTER_HISTORY_PATH),
+            printer_history_days(&current),
+        ) {
+            eprintln!("Failed to save printer history: {}", e);
+        }
+        let retention_months = current
+            .billing
+            .retention_months
+            .unwrap_or(DEFAULT_BILLING_RETENTION_MONTHS)
+            .max(1);
+        if let Err(e) = history
+            .billing
+            .save(Path::new(BILLING_PATH), retention_months)
+        {
+            eprintln!("Failed to save billing data: {}", e);
+        }
+        export_previous_billing_month(&current.billing, &history.billing);
+
+        let notifications = &current.notifications;
+        if (notifications.channels.is_empty() && notifications.email.is_none())
+            || notifications.printer_offline_minutes == 0
+        {
+            offline.clear();
+            continue;
+        }
+        let threshold = Duration::from_secs(notifications.printer_offline_minutes * 60);
+
+        let mut down: Vec<(String, Instant)> = local_down
+            .into_iter()
+            .map(|name| (name, Instant::now()))
+            .collect();
+        down.extend(
+            registry
+                .offline_since()
+                .into_iter()
+                .map(|(agent_id, last_seen)| (format!("agent {}", agent_id), last_seen)),
+        );
+
+        offline.retain(|name, (_, notified)| {
+            let recovered = !down.iter().any(|(down_name, _)| down_name == name);
+            if recovered && *notified {
+                send_notification(notifications, format!("{} is back online", name));
+                send_email(
+                    notifications,
+                    format!("{} is back online", name),
+                    format!("{} is back online.", name),
+                );
+            }
+            !recovered
+        });
+        for (name, since) in down {
+            let (since, notified) = offline.entry(name.clone()).or_insert((since, false));
+            if !*notified && since.elapsed() >= threshold {
+                *notified = true;
+                let text = format!(
+                    "{} has been offline for {} minutes",
+                    name,
+                    since.elapsed().as_secs() / 60
+                );
+                send_notification(notifications, text.clone());
+                send_email(notifications, format!("{} is offline", name), text);
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// ----------------------------------------------------------------------
+//                              OUTPUT FILES
+// ----------------------------------------------------------------------
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// ตัวแปรที่ใช้ได้ใน `output.name_template`
+pub(crate) const OUTPUT_TEMPLATE_FIELDS: [&str; 5] = ["stem", "ext", "id", "date", "time"];
+
+/// การตั้งชื่อและที่เก็บไฟล์ที่ส่งพิมพ์
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct OutputConfig {
+    /// ชื่อไฟล์ใน ./printable_files เช่น `{stem}_a6{ext}` (ใช้ `{stem}`, `{ext}`, `{id}`, `{date}`, `{time}` ได้)
+    pub(crate) name_template: String,
+    /// เขียนไฟล์เป็น `<id>.pdf` ใน `work_dir` แล้วลบทิ้งหลังส่งงาน แทนการเก็บไว้ใน ./printable_files
+    pub(crate) temp_files: bool,
+    /// โฟลเดอร์ของไฟล์ชั่วคราวเมื่อเปิด `temp_files`
+    pub(crate) work_dir: PathBuf,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            name_template: "{stem}_a6{ext}".to_string(),
+            temp_files: false,
+            work_dir: PathBuf::from("./printable_files/.work"),
+        }
+    }
+}
+
+/// ไฟล์ที่ส่งพิมพ์ของงานหนึ่ง
+pub(crate) struct OutputTarget {
+    /// ชื่อไฟล์ที่แสดงในผลลัพธ์ (`None` = ไฟล์ชั่วคราวที่ถูกลบหลังส่งงาน)
+    pub(crate) name: Option<String>,
+    pub(crate) path: PathBuf,
+}
+
+/// ตรวจว่า template อ้างถึงเฉพาะตัวแปรที่รู้จัก และไม่สร้างชื่อที่ออกนอก ./printable_files
+pub(crate) fn validate_output_template(template: &str) -> Result<()> {
+    if template.trim().is_empty() {
+        bail!("output.name_template must not be empty");
+    }
+    if template.contains(['/', '\\']) || template.contains("..") {
+        bail!("output.name_template must not contain path separators or '..'");
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            bail!("Unclosed placeholder in output.name_template: {}", template);
+        };
+        let field = &rest[start + 1..start + end];
+        if !OUTPUT_TEMPLATE_FIELDS.contains(&field) {
+            bail!(
+                "Unknown placeholder {{{}}} in output.name_template (expected one of {})",
+                field,
+                OUTPUT_TEMPLATE_FIELDS.join(", ")
+            );
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+/// สร้างชื่อไฟล์จาก template เช่น "invoice.pdf" กับ `{stem}_a6{ext}` -> "invoice_a6.pdf"
+pub(crate) fn render_output_name(template: &str, filename: &str, id: u64) -> String {
+    let (stem, ext) = filename
+        .rfind('.')
+        .map_or((filename, ""), |i| filename.split_at(i));
+    let now = chrono::Local::now();
+    template
+        .replace("{stem}", stem)
+        .replace("{ext}", ext)
+        .replace("{id}", &id.to_string())
+        .replace("{date}", &now.format("%Y%m%d").to_string())
+        .replace("{time}", &now.format("%H%M%S").to_string())
+}
+
+/// ตำแหน่งไฟล์ที่ส่งพิมพ์ของงาน `id` ที่สร้างจาก `filename` ในโฟลเดอร์ `dir`
+pub(crate) fn output_target(
+    output: &OutputConfig,
+    dir: &Path,
+    filename: &str,
+    id: u64,
+) -> OutputTarget {
+    if output.temp_files {
+        return OutputTarget {
+            name: None,
+            path: output.work_dir.join(format!("{}.pdf", id)),
+        };
+    }
+    let name = render_output_name(&output.name_template, filename, id);
+    OutputTarget {
+        path: dir.join(&name),
+        name: Some(name),
+    }
+}
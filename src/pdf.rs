@@ -0,0 +1,1482 @@
+// ----------------------------------------------------------------------
+//                        PDF RESIZING LOGIC (WITH SCALING)
+// ----------------------------------------------------------------------
+
+use crate::{
+    counters::JobCounters,
+    locale::{Language, LocaleFormat},
+};
+use anyhow::{bail, Context, Result};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+// --- ค่าคงที่สำหรับขนาดกระดาษในหน่วย PostScript Points (1 point = 1/72 inch) ---
+
+// A6: 105mm x 148mm
+pub(crate) const A6_WIDTH_PTS: f32 = 297.64;
+pub(crate) const A6_HEIGHT_PTS: f32 = 419.53;
+
+// A4: 210mm x 297mm (ใช้เป็นขนาดอ้างอิงของเอกสารต้นฉบับเมื่อหน้าไม่มี MediaBox)
+pub(crate) const A4_WIDTH_PTS: f32 = 595.28;
+pub(crate) const A4_HEIGHT_PTS: f32 = 841.89;
+
+// 1 inch = 25.4 mm = 72 points
+pub(crate) const PTS_PER_MM: f32 = 72.0 / 25.4;
+
+// Crop mark: เส้นยาว 5 มม. เริ่มห่างจากขอบ bleed 2 มม.
+pub(crate) const CROP_MARK_LENGTH_MM: f32 = 5.0;
+pub(crate) const CROP_MARK_OFFSET_MM: f32 = 2.0;
+
+// ฟอนต์มาตรฐาน 14 ตัวที่เครื่องพิมพ์และ viewer ทุกตัวต้องมี จึงไม่ต้องฝังในไฟล์
+pub(crate) const STANDARD_14_FONTS: [&str; 14] = [
+    "Courier",
+    "Courier-Bold",
+    "Courier-Oblique",
+    "Courier-BoldOblique",
+    "Helvetica",
+    "Helvetica-Bold",
+    "Helvetica-Oblique",
+    "Helvetica-BoldOblique",
+    "Times-Roman",
+    "Times-Bold",
+    "Times-Italic",
+    "Times-BoldItalic",
+    "Symbol",
+    "ZapfDingbats",
+];
+
+/// ตัวเลือกการลดขนาดไฟล์ PDF ก่อนส่งไปยังเครื่องพิมพ์
+///
+/// เมื่อระบุจะลบ object ที่ไม่ได้ใช้และบีบอัด stream ทั้งหมดเสมอ
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({"target_dpi": 200}))]
+pub(crate) struct OptimizeOptions {
+    /// ความละเอียดสูงสุดของรูปภาพ (DPI) เมื่อพิมพ์บนกระดาษ A6 รูปที่ละเอียดเกินจะถูกย่อลง
+    /// (รองรับรูปภาพ 8-bit แบบ DeviceGray/DeviceRGB/DeviceCMYK ที่ไม่ได้บีบอัดแบบ JPEG)
+    #[serde(default)]
+    target_dpi: Option<f32>,
+}
+
+/// วิธีจัดการหน้าต้นฉบับที่เล็กกว่าพื้นที่บนกระดาษปลายทาง
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FitPolicy {
+    /// ปฏิเสธงาน (ค่าเริ่มต้น)
+    #[default]
+    Error,
+    /// ไม่ขยาย วางหน้าขนาดเดิมไว้กลางพื้นที่
+    ShrinkOnly,
+    /// ขยายหน้าให้พอดีกับพื้นที่
+    AllowUpscale,
+}
+
+/// หน้าที่เลือกพิมพ์ตามหมายเลขหน้าในไฟล์ต้นฉบับ
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PageSet {
+    /// ทุกหน้า (ค่าเริ่มต้น)
+    #[default]
+    All,
+    /// เฉพาะหน้าคี่ (1, 3, 5, ...)
+    Odd,
+    /// เฉพาะหน้าคู่ (2, 4, 6, ...)
+    Even,
+}
+
+/// ตัวเลือกที่ใช้ในขั้นตอนแปลงไฟล์ PDF
+pub(crate) struct TransformOptions<'a> {
+    pub(crate) pdf_password: Option<&'a str>,
+    pub(crate) optimize: Option<&'a OptimizeOptions>,
+    pub(crate) font_fallbacks: &'a HashMap<String, PathBuf>,
+    pub(crate) grayscale: bool,
+    pub(crate) rotate: u16,
+    pub(crate) auto_rotate: bool,
+    pub(crate) margin_mm: f32,
+    pub(crate) offset_x_mm: f32,
+    pub(crate) offset_y_mm: f32,
+    pub(crate) bleed_mm: f32,
+    pub(crate) crop_marks: bool,
+    pub(crate) fit_policy: FitPolicy,
+    /// ภาษาของวันที่ (`{date}`) ที่พิมพ์ลงบนเอกสาร
+    pub(crate) language: Language,
+    /// รูปแบบวันที่และตัวเลขเริ่มต้นของ tenant สำหรับขั้นตอนที่ไม่ได้ระบุ `locale`
+    pub(crate) locale: Option<&'a LocaleFormat>,
+    /// ฟอนต์สำหรับวันที่และตัวเลขที่ไม่ใช่ ASCII (`stamp_font` ใน config.json)
+    pub(crate) stamp_font: Option<&'a Path>,
+    /// หน้าที่เลือกพิมพ์ (ทำก่อนขั้นตอนอื่นทั้งหมด)
+    pub(crate) page_set: PageSet,
+    /// พิมพ์จากหน้าสุดท้ายไปหน้าแรก
+    pub(crate) reverse: bool,
+    /// เลขที่ของ `{counter:...}` ในขั้นตอน `stamp`, `page_numbers` และ `text` (`None` = ไม่มีตัวนับ)
+    pub(crate) counters: Option<&'a JobCounters<'a>>,
+}
+
+/// ผลลัพธ์จากขั้นตอนแปลงไฟล์ PDF
+#[derive(Clone)]
+pub(crate) struct TransformReport {
+    pub(crate) warnings: Vec<String>,
+    pub(crate) source_pages: usize,
+    pub(crate) output_pages: usize,
+    /// ขนาดหน้าของไฟล์ที่แปลงแล้ว รวม bleed และพื้นที่ crop mark
+    pub(crate) page_width_pts: f32,
+    pub(crate) page_height_pts: f32,
+    /// ชื่อขนาดกระดาษ เช่น "A6" ("Custom" เมื่อ `transforms` ไม่มีขั้นตอน `resize`)
+    pub(crate) paper_name: &'static str,
+}
+
+/// ข้อผิดพลาดเมื่อเปิดไฟล์ PDF ที่เข้ารหัสไม่สำเร็จ
+#[derive(Debug)]
+pub(crate) enum PdfPasswordError {
+    /// ไฟล์ถูกเข้ารหัส แต่ไม่ได้ส่ง `pdf_password` มา
+    Required,
+    /// `pdf_password` ที่ส่งมาไม่ถูกต้อง
+    Invalid,
+}
+
+impl std::fmt::Display for PdfPasswordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdfPasswordError::Required => write!(f, "PDF is encrypted and requires a password"),
+            PdfPasswordError::Invalid => write!(f, "The supplied PDF password is incorrect"),
+        }
+    }
+}
+
+impl std::error::Error for PdfPasswordError {}
+
+/// `buffer[i..]` ขึ้นต้นด้วย keyword `keyword` ที่ไม่ได้เป็นส่วนหนึ่งของคำอื่น
+fn is_pdf_keyword_at(buffer: &[u8], i: usize, keyword: &[u8]) -> bool {
+    let is_regular = |i: usize| buffer.get(i).is_some_and(|b| b.is_ascii_alphanumeric());
+    // ชื่อที่ขึ้นต้นด้วย `/` ต่อท้ายคำก่อนหน้าได้ เช่น `/Type/XRef`
+    let starts_word = keyword.first() == Some(&b'/') || i == 0 || !is_regular(i - 1);
+    buffer[i..].starts_with(keyword) && starts_word && !is_regular(i + keyword.len())
+}
+
+/// ตำแหน่งของ keyword `keyword` ตัวแรกใน `buffer[range]`
+fn find_pdf_keyword(buffer: &[u8], range: Range<usize>, keyword: &[u8]) -> Option<usize> {
+    (range.start..range.end.saturating_sub(keyword.len() - 1))
+        .find(|&i| is_pdf_keyword_at(buffer, i, keyword))
+}
+
+/// ช่วงของไฟล์ PDF ที่อยู่นอกข้อมูลระหว่าง `stream` กับ `endstream` และช่วงที่เป็น trailer
+/// (`trailer` ถึง `startxref` หรือ dictionary ของ xref stream) ตามลำดับ
+fn pdf_structure_ranges(buffer: &[u8]) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let mut structure = Vec::new();
+    let mut trailers = Vec::new();
+    let mut start = 0;
+    while start < buffer.len() {
+        let stream = find_pdf_keyword(buffer, start..buffer.len(), b"stream");
+        let end = stream.unwrap_or(buffer.len());
+        let mut from = start;
+        while let Some(trailer) = find_pdf_keyword(buffer, from..end, b"trailer") {
+            from = find_pdf_keyword(buffer, trailer..end, b"startxref").unwrap_or(end);
+            trailers.push(trailer..from);
+        }
+        if let Some(stream) = stream {
+            // dictionary ของ stream อยู่ระหว่าง `obj` ตัวสุดท้ายกับ `stream`
+            let dict_start = (start..stream)
+                .rev()
+                .find(|&i| is_pdf_keyword_at(buffer, i, b"obj"))
+                .unwrap_or(start);
+            if find_pdf_keyword(buffer, dict_start..stream, b"/XRef").is_some() {
+                trailers.push(dict_start..stream);
+            }
+        }
+        structure.push(start..end);
+        let Some(stream) = stream else {
+            break;
+        };
+        start = find_pdf_keyword(buffer, stream + b"stream".len()..buffer.len(), b"endstream")
+            .map_or(buffer.len(), |i| i + b"endstream".len());
+    }
+    (structure, trailers)
+}
+
+/// แทนที่ชื่อ `name` ใน `buffer[range]` ด้วยชื่อที่ยาวเท่ากัน เพื่อไม่ให้ offset ใน xref เปลี่ยน
+fn mask_pdf_name_in(buffer: &mut [u8], range: Range<usize>, name: &[u8], masked: &[u8]) {
+    let mut i = range.start;
+    while i + name.len() <= range.end {
+        let next = buffer.get(i + name.len()).copied();
+        if &buffer[i..i + name.len()] == name && !next.is_some_and(|b| b.is_ascii_alphanumeric()) {
+            buffer[i..i + name.len()].copy_from_slice(masked);
+            i += name.len();
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// แทนที่ชื่อ key ใน dictionary ของ object ในไฟล์ PDF ด้วยชื่อที่ยาวเท่ากัน
+/// โดยไม่แตะข้อมูลใน stream เช่น content ของหน้าที่บังเอิญมีชื่อเดียวกัน
+pub(crate) fn mask_pdf_name(buffer: &mut [u8], name: &[u8], masked: &[u8]) {
+    for range in pdf_structure_ranges(buffer).0 {
+        mask_pdf_name_in(buffer, range, name, masked);
+    }
+}
+
+/// แทนที่ชื่อ key ใน trailer ของไฟล์ PDF (รวม dictionary ของ xref stream) ด้วยชื่อที่ยาวเท่ากัน
+pub(crate) fn mask_pdf_trailer_name(buffer: &mut [u8], name: &[u8], masked: &[u8]) {
+    for range in pdf_structure_ranges(buffer).1 {
+        mask_pdf_name_in(buffer, range, name, masked);
+    }
+}
+
+/// โหลด PDF จากข้อมูลในหน่วยความจำ (`source_name` ใช้ในข้อความแสดงข้อผิดพลาด)
+pub(crate) fn load_pdf_bytes(
+    mut buffer: Vec<u8>,
+    source_name: &str,
+    password: Option<&str>,
+) -> Result<Document> {
+    let mut doc =
+        Document::load_mem(&buffer).context(format!("Failed to load PDF file: {}", source_name))?;
+
+    if !doc.is_encrypted() {
+        return Ok(doc);
+    }
+
+    if doc.encryption_state.is_some() {
+        // lopdf ถอดรหัสด้วยรหัสผ่านว่างให้แล้ว เหลือเพียงลบ /Encrypt ออกเพื่อให้ไฟล์ที่บันทึกเป็นไฟล์ปกติ
+        if let Some(Ok(encrypt_id)) = doc.trailer.remove(b"Encrypt").map(|o| o.as_reference()) {
+            doc.objects.remove(&encrypt_id);
+        }
+        doc.encryption_state = None;
+        return Ok(doc);
+    }
+
+    let password = match password.filter(|p| !p.is_empty()) {
+        Some(p) => p,
+        None => bail!(PdfPasswordError::Required),
+    };
+    if doc.authenticate_password(password).is_err() {
+        bail!(PdfPasswordError::Invalid);
+    }
+
+    // lopdf จะไม่อ่าน object ใดๆ เลยหากเปิดไฟล์ด้วยรหัสผ่านว่างไม่ได้
+    // จึงซ่อน /Encrypt และ /ObjStm ไว้ชั่วคราวเพื่อให้โหลด object ดิบทั้งหมดก่อน แล้วจึงถอดรหัสภายหลัง
+    mask_pdf_trailer_name(&mut buffer, b"/Encrypt", b"/Encryp_");
+    mask_pdf_name(&mut buffer, b"/ObjStm", b"/ObjSt_");
+    let mut doc =
+        Document::load_mem(&buffer).context(format!("Failed to load PDF file: {}", source_name))?;
+
+    let encrypt = doc
+        .trailer
+        .remove(b"Encryp_")
+        .context("Missing /Encrypt in trailer")?;
+    doc.trailer.set("Encrypt", encrypt);
+    for object in doc.objects.values_mut() {
+        if let Ok(stream) = object.as_stream_mut() {
+            if stream.dict.has_type(b"ObjSt_") {
+                stream.dict.set("Type", Object::Name(b"ObjStm".to_vec()));
+            }
+        }
+    }
+
+    doc.decrypt(password)
+        .context(format!("Failed to decrypt PDF file: {}", source_name))?;
+    doc.encryption_state = None;
+
+    Ok(doc)
+}
+
+/// content ของหน้าทุก stream ต่อกัน โดยคั่นด้วยขึ้นบรรทัดใหม่ (`get_page_content` ของ lopdf ต่อ stream
+/// ติดกัน ทำให้ operator ท้าย stream หนึ่งรวมกับตัวแรกของ stream ถัดไป เช่น `Q` + `q` กลายเป็น `Qq`)
+pub(crate) fn page_content(doc: &Document, page_id: ObjectId) -> Vec<u8> {
+    let mut content = Vec::new();
+    for stream_id in doc.get_page_contents(page_id) {
+        let Ok(stream) = doc.get_object(stream_id).and_then(Object::as_stream) else {
+            continue;
+        };
+        if !content.is_empty() {
+            content.push(b'\n');
+        }
+        match stream.decompressed_content() {
+            Ok(data) => content.extend_from_slice(&data),
+            Err(_) => content.extend_from_slice(&stream.content),
+        }
+    }
+    content
+}
+
+/// ทำให้ทุกหน้ามี `/Contents` เป็น stream เดียวที่ไม่ใช้ร่วมกับหน้าอื่น ก่อนแก้ไข content ของแต่ละหน้า
+///
+/// หน้าที่ `/Contents` เป็น array (หรืออ้างอิง array) จะถูกรวมเป็น stream ใหม่ และหน้าที่ใช้ stream
+/// ร่วมกับหน้าก่อนหน้าจะได้สำเนาเป็นของตัวเอง เพื่อไม่ให้การแก้หน้าหนึ่งไปเปลี่ยนหน้าอื่นด้วย
+/// คืนค่าจำนวนหน้าที่ถูกเปลี่ยน
+pub(crate) fn normalize_page_contents(doc: &mut Document) -> Result<usize> {
+    let mut seen = BTreeSet::new();
+    let mut normalized = 0;
+    for (_, page_id) in doc.get_pages() {
+        let streams = doc.get_page_contents(page_id);
+        let single_stream = match doc.get_dictionary(page_id)?.get(b"Contents") {
+            Ok(Object::Reference(id)) => {
+                matches!(doc.objects.get(id), Some(Object::Stream(_))).then_some(*id)
+            }
+            _ => None,
+        };
+        if let Some(stream_id) = single_stream {
+            if seen.insert(stream_id) {
+                continue;
+            }
+        } else if streams.is_empty() {
+            continue;
+        }
+        seen.extend(streams);
+
+        let content = page_content(doc, page_id);
+        let stream_id = doc.add_object(Stream::new(dictionary! {}, content));
+        doc.get_dictionary_mut(page_id)?
+            .set("Contents", Object::Reference(stream_id));
+        seen.insert(stream_id);
+        normalized += 1;
+    }
+    if normalized > 0 {
+        // stream ย่อยของ array ที่ไม่มีหน้าใดใช้แล้ว
+        doc.prune_objects();
+    }
+    Ok(normalized)
+}
+
+/// attribute ของหน้าที่กำหนดไว้ที่ Pages node แล้วหน้าลูกสืบทอดได้
+pub(crate) const INHERITABLE_PAGE_ATTRIBUTES: [&str; 4] =
+    ["Resources", "MediaBox", "CropBox", "Rotate"];
+/// จำนวนชั้นของ Pages tree ที่ค้นหาขึ้นไปมากที่สุด (ป้องกัน `/Parent` ที่วนกลับมาที่เดิม)
+pub(crate) const MAX_PAGE_TREE_DEPTH: usize = 32;
+
+/// ค่า `key` ของหน้า หากหน้าไม่ได้กำหนดไว้จะค้นหาจาก Pages node ที่อยู่เหนือขึ้นไป
+pub(crate) fn page_attribute<'a>(
+    doc: &'a Document,
+    page_id: ObjectId,
+    key: &[u8],
+) -> Option<&'a Object> {
+    let mut node = doc.get_dictionary(page_id).ok()?;
+    for _ in 0..MAX_PAGE_TREE_DEPTH {
+        if let Ok(value) = node.get(key) {
+            return Some(value);
+        }
+        let parent = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = doc.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+/// คัดลอก attribute ที่หน้าสืบทอดมาจาก Pages tree มาเป็นของหน้าเอง เพื่อให้แก้ไขทีละหน้าได้
+/// โดยไม่กระทบหน้าอื่นที่สืบทอดค่าเดียวกัน แล้วลบค่าเหล่านั้นออกจาก Pages node (เช่น เมื่อลบ CropBox
+/// ของหน้าแล้วจะไม่กลับไปใช้ CropBox เดิมของ Pages node) คืนค่าจำนวนหน้าที่ถูกเปลี่ยน
+pub(crate) fn normalize_inherited_attributes(doc: &mut Document) -> Result<usize> {
+    let mut normalized = 0;
+    for (_, page_id) in doc.get_pages() {
+        let page = doc.get_dictionary(page_id)?;
+        let inherited: Vec<(&str, Object)> = INHERITABLE_PAGE_ATTRIBUTES
+            .into_iter()
+            .filter(|key| !page.has(key.as_bytes()))
+            .filter_map(|key| Some((key, page_attribute(doc, page_id, key.as_bytes())?.clone())))
+            .collect();
+        if inherited.is_empty() {
+            continue;
+        }
+        let page = doc.get_dictionary_mut(page_id)?;
+        for (key, value) in inherited {
+            page.set(key, value);
+        }
+        normalized += 1;
+    }
+
+    for object in doc.objects.values_mut() {
+        if let Object::Dictionary(node) = object {
+            if node.has_type(b"Pages") {
+                for key in INHERITABLE_PAGE_ATTRIBUTES {
+                    node.remove(key.as_bytes());
+                }
+            }
+        }
+    }
+    Ok(normalized)
+}
+
+/// เตรียมทุกหน้าก่อนแปลง: คัดลอก attribute ที่สืบทอดมา และแยก content stream ของแต่ละหน้า
+pub(crate) fn normalize_pages(doc: &mut Document) -> Result<()> {
+    let inherited = normalize_inherited_attributes(doc)?;
+    if inherited > 0 {
+        println!("Copied inherited page attributes to {} pages", inherited);
+    }
+    let normalized = normalize_page_contents(doc)?;
+    if normalized > 0 {
+        println!("Normalized content streams of {} pages", normalized);
+    }
+    Ok(())
+}
+
+/// ไม่มีหน้าที่ตรงกับ `page_set` เช่น เลือกหน้าคู่ของเอกสารหน้าเดียว
+#[derive(Debug)]
+pub(crate) struct EmptyPageSelection {
+    page_set: PageSet,
+    page_count: usize,
+}
+
+impl std::fmt::Display for EmptyPageSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let set = match self.page_set {
+            PageSet::All => "",
+            PageSet::Odd => "odd ",
+            PageSet::Even => "even ",
+        };
+        write!(
+            f,
+            "No {}pages to print (the document has {} pages)",
+            set, self.page_count
+        )
+    }
+}
+
+impl std::error::Error for EmptyPageSelection {}
+
+/// เลือกหน้าตาม `page_set` และกลับลำดับหน้าเมื่อ `reverse` (ต้องเรียกหลัง `normalize_pages`)
+///
+/// หน้าที่เหลือถูกย้ายไปอยู่ใต้ Pages node หลักโดยตรง เพราะ attribute ที่สืบทอดจาก
+/// Pages node ย่อยถูกคัดลอกลงแต่ละหน้าแล้ว
+pub(crate) fn select_pages(doc: &mut Document, page_set: PageSet, reverse: bool) -> Result<()> {
+    if page_set == PageSet::All && !reverse {
+        return Ok(());
+    }
+    let pages = doc.get_pages();
+    let page_count = pages.len();
+    let mut selected: Vec<ObjectId> = pages
+        .into_iter()
+        .filter(|(number, _)| match page_set {
+            PageSet::All => true,
+            PageSet::Odd => number % 2 == 1,
+            PageSet::Even => number % 2 == 0,
+        })
+        .map(|(_, page_id)| page_id)
+        .collect();
+    if selected.is_empty() {
+        bail!(EmptyPageSelection {
+            page_set,
+            page_count
+        });
+    }
+    if reverse {
+        selected.reverse();
+    }
+
+    let pages_id = doc
+        .catalog()?
+        .get(b"Pages")
+        .and_then(Object::as_reference)?;
+    for page_id in &selected {
+        doc.get_dictionary_mut(*page_id)?
+            .set("Parent", Object::Reference(pages_id));
+    }
+    let root = doc.get_dictionary_mut(pages_id)?;
+    root.set("Count", selected.len() as i64);
+    root.set(
+        "Kids",
+        selected
+            .iter()
+            .map(|page_id| Object::Reference(*page_id))
+            .collect::<Vec<_>>(),
+    );
+    if selected.len() < page_count {
+        // หน้าที่ไม่ได้เลือกและ Pages node ย่อยที่ไม่มีใครอ้างถึงแล้ว
+        doc.prune_objects();
+        println!(
+            "Selected {} of {} pages ({:?})",
+            selected.len(),
+            page_count,
+            page_set
+        );
+    }
+    Ok(())
+}
+
+/// คืนค่าชื่อฟอนต์และ FontDescriptor หากฟอนต์นี้ไม่ได้ฝังมาในไฟล์
+pub(crate) fn find_non_embedded_font(
+    doc: &Document,
+    font: &Dictionary,
+) -> Option<(String, Option<ObjectId>)> {
+    let subtype = font
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .unwrap_or(b"");
+    if subtype == b"Type3" {
+        // Type3 วาด glyph ด้วยคำสั่ง PDF เองจึงไม่ต้องใช้ไฟล์ฟอนต์
+        return None;
+    }
+    let base_font = font
+        .get(b"BaseFont")
+        .and_then(Object::as_name)
+        .unwrap_or(b"");
+    let base_font = String::from_utf8_lossy(base_font).to_string();
+
+    // ฟอนต์ Type0 (CID) เก็บ FontDescriptor ไว้ใน DescendantFonts
+    let font = if subtype == b"Type0" {
+        let descendant = font
+            .get(b"DescendantFonts")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_array())
+            .ok()?
+            .first()?;
+        doc.dereference(descendant)
+            .and_then(|(_, o)| o.as_dict())
+            .ok()?
+    } else {
+        font
+    };
+
+    let is_embedded = |descriptor: &Dictionary| {
+        [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"]
+            .iter()
+            .any(|key| descriptor.has(key))
+    };
+    match font.get(b"FontDescriptor") {
+        Ok(Object::Reference(id)) => match doc.get_dictionary(*id) {
+            Ok(descriptor) if is_embedded(descriptor) => None,
+            _ => Some((base_font, Some(*id))),
+        },
+        Ok(Object::Dictionary(descriptor)) if is_embedded(descriptor) => None,
+        _ if STANDARD_14_FONTS.contains(&base_font.as_str()) => None,
+        _ => Some((base_font, None)),
+    }
+}
+
+/// ตรวจหาฟอนต์ที่ไม่ได้ฝังมาในไฟล์ และฝังฟอนต์สำรองตาม `font_fallbacks` ใน config
+///
+/// คืนค่ารายการคำเตือนสำหรับฟอนต์ที่ไม่ได้ฝังทั้งหมด
+pub(crate) fn embed_missing_fonts(
+    doc: &mut Document,
+    font_fallbacks: &HashMap<String, PathBuf>,
+) -> Vec<String> {
+    // ชื่อฟอนต์ -> (FontDescriptor ที่ใช้ฟอนต์นี้, หน้าที่ใช้ฟอนต์นี้)
+    let mut missing: BTreeMap<String, (BTreeSet<ObjectId>, BTreeSet<u32>)> = BTreeMap::new();
+    for (page_number, page_id) in doc.get_pages() {
+        let Ok(fonts) = doc.get_page_fonts(page_id) else {
+            continue;
+        };
+        for font in fonts.values() {
+            if let Some((name, descriptor_id)) = find_non_embedded_font(doc, font) {
+                let entry = missing.entry(name).or_default();
+                entry.0.extend(descriptor_id);
+                entry.1.insert(page_number);
+            }
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for (name, (descriptor_ids, pages)) in missing {
+        let pages = pages
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        // ชื่ออย่าง "Tahoma,Bold" ให้ลองหาด้วยชื่อฟอนต์หลักด้วย
+        let fallback = font_fallbacks.get(&name).or_else(|| {
+            name.split(',')
+                .next()
+                .and_then(|base| font_fallbacks.get(base))
+        });
+
+        let embedded = match fallback {
+            Some(path) if !descriptor_ids.is_empty() => match std::fs::read(path) {
+                Ok(data) => {
+                    let is_open_type = path
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("otf"));
+                    let (key, mut font_file) = if is_open_type {
+                        (
+                            "FontFile3",
+                            Stream::new(dictionary! { "Subtype" => "OpenType" }, data),
+                        )
+                    } else {
+                        let length = data.len() as i64;
+                        (
+                            "FontFile2",
+                            Stream::new(dictionary! { "Length1" => length }, data),
+                        )
+                    };
+                    let _ = font_file.compress();
+                    let font_file_id = doc.add_object(font_file);
+                    for descriptor_id in &descriptor_ids {
+                        if let Ok(descriptor) = doc.get_dictionary_mut(*descriptor_id) {
+                            descriptor.set(key, font_file_id);
+                        }
+                    }
+                    Some(path)
+                }
+                Err(e) => {
+                    eprintln!("Failed to read fallback font {}: {:?}", path.display(), e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        warnings.push(match embedded {
+            Some(path) => format!(
+                "Font '{}' (pages {}) was not embedded; embedded fallback font {}",
+                name,
+                pages,
+                path.display()
+            ),
+            None => format!(
+                "Font '{}' (pages {}) is not embedded and may not print correctly",
+                name, pages
+            ),
+        });
+    }
+
+    warnings
+}
+
+/// คืนค่าจำนวน component ของ color space ที่แปลงเป็นสีเทาได้ (DeviceGray = 1, RGB = 3, CMYK = 4)
+pub(crate) fn color_space_components(doc: &Document, color_space: &Object) -> Option<usize> {
+    let (_, color_space) = doc.dereference(color_space).ok()?;
+    match color_space {
+        Object::Name(name) => match name.as_slice() {
+            b"DeviceGray" | b"CalGray" => Some(1),
+            b"DeviceRGB" | b"CalRGB" => Some(3),
+            b"DeviceCMYK" => Some(4),
+            _ => None,
+        },
+        Object::Array(array) => match array.first().and_then(|o| o.as_name().ok())? {
+            b"CalGray" => Some(1),
+            b"CalRGB" => Some(3),
+            b"ICCBased" => {
+                let (_, profile) = doc.dereference(array.get(1)?).ok()?;
+                let components = profile
+                    .as_stream()
+                    .ok()?
+                    .dict
+                    .get(b"N")
+                    .ok()?
+                    .as_i64()
+                    .ok()?;
+                Some(components as usize)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// รวบรวม color space ที่ตั้งชื่อไว้ใน /Resources พร้อมจำนวน component
+pub(crate) fn collect_color_spaces(
+    doc: &Document,
+    resources: &Dictionary,
+    color_spaces: &mut HashMap<Vec<u8>, usize>,
+) {
+    let Ok((_, Object::Dictionary(named))) = resources
+        .get(b"ColorSpace")
+        .and_then(|o| doc.dereference(o))
+    else {
+        return;
+    };
+    for (name, color_space) in named.iter() {
+        if let Some(components) = color_space_components(doc, color_space) {
+            color_spaces.entry(name.clone()).or_insert(components);
+        }
+    }
+}
+
+/// แปลงค่าสี Gray/RGB/CMYK เป็นค่าความสว่างของสีเทา (0.0 - 1.0)
+pub(crate) fn gray_level(values: &[f32]) -> Option<f32> {
+    match *values {
+        [gray] => Some(gray),
+        [r, g, b] => Some(0.299 * r + 0.587 * g + 0.114 * b),
+        [c, m, y, k] => Some(1.0 - (0.3 * c + 0.59 * m + 0.11 * y + k).min(1.0)),
+        _ => None,
+    }
+}
+
+/// แปลงคำสั่งกำหนดสีใน content stream (rg/RG, k/K, cs/CS, sc/scn) ให้เป็นสีเทา
+///
+/// Pattern, Shading, Separation และ Indexed color space จะไม่ถูกแปลง
+pub(crate) fn convert_operations_to_grayscale(
+    operations: &mut [Operation],
+    color_spaces: &HashMap<Vec<u8>, usize>,
+) {
+    // color space ของ fill/stroke ถูกแปลงเป็น DeviceGray แล้วหรือไม่ (เก็บเป็น stack ตาม q/Q)
+    let mut converted = (false, false);
+    let mut saved_states = Vec::new();
+
+    for operation in operations.iter_mut() {
+        let values: Option<Vec<f32>> = operation
+            .operands
+            .iter()
+            .map(|o| o.as_float().ok())
+            .collect();
+        let gray = values.as_deref().and_then(gray_level);
+        match operation.operator.as_str() {
+            "q" => saved_states.push(converted),
+            "Q" => converted = saved_states.pop().unwrap_or_default(),
+            "rg" | "k" => {
+                if let Some(gray) = gray {
+                    *operation = Operation::new("g", vec![Object::Real(gray)]);
+                }
+            }
+            "RG" | "K" => {
+                if let Some(gray) = gray {
+                    *operation = Operation::new("G", vec![Object::Real(gray)]);
+                }
+            }
+            "cs" | "CS" => {
+                let components = operation
+                    .operands
+                    .first()
+                    .and_then(|o| o.as_name().ok())
+                    .and_then(|name| match name {
+                        b"DeviceRGB" => Some(3),
+                        b"DeviceCMYK" => Some(4),
+                        _ => color_spaces.get(name).copied(),
+                    });
+                let convert = matches!(components, Some(3) | Some(4));
+                if convert {
+                    operation.operands = vec![Object::Name(b"DeviceGray".to_vec())];
+                }
+                if operation.operator == "cs" {
+                    converted.0 = convert;
+                } else {
+                    converted.1 = convert;
+                }
+            }
+            "sc" | "scn" if converted.0 => {
+                if let Some(gray) = gray {
+                    operation.operands = vec![Object::Real(gray)];
+                }
+            }
+            "SC" | "SCN" if converted.1 => {
+                if let Some(gray) = gray {
+                    operation.operands = vec![Object::Real(gray)];
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// แปลงรูปภาพและ Form XObject ทั้งหมดในเอกสารเป็นสีเทา (รูปภาพที่บีบอัดแบบ JPEG จะไม่ถูกแปลง)
+pub(crate) fn convert_objects_to_grayscale(doc: &mut Document) -> usize {
+    let mut images = Vec::new();
+    let mut forms = Vec::new();
+    for (id, object) in doc.objects.iter() {
+        let Ok(stream) = object.as_stream() else {
+            continue;
+        };
+        match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+            Ok(b"Image") => {
+                let components = stream
+                    .dict
+                    .get(b"ColorSpace")
+                    .ok()
+                    .and_then(|cs| color_space_components(doc, cs));
+                let is_8_bit = stream
+                    .dict
+                    .get(b"BitsPerComponent")
+                    .and_then(Object::as_i64)
+                    .ok()
+                    == Some(8);
+                if is_8_bit
+                    && !stream.dict.has(b"Decode")
+                    && matches!(components, Some(3) | Some(4))
+                {
+                    images.push((*id, components.unwrap_or_default()));
+                }
+            }
+            Ok(b"Form") => {
+                let mut color_spaces = HashMap::new();
+                if let Ok((_, Object::Dictionary(resources))) = stream
+                    .dict
+                    .get(b"Resources")
+                    .and_then(|o| doc.dereference(o))
+                {
+                    collect_color_spaces(doc, resources, &mut color_spaces);
+                }
+                forms.push((*id, color_spaces));
+            }
+            _ => {}
+        }
+    }
+
+    let mut converted = 0;
+    for (id, components) in images {
+        let Ok(stream) = doc.get_object_mut(id).and_then(Object::as_stream_mut) else {
+            continue;
+        };
+        let Ok(data) = stream.get_plain_content() else {
+            continue;
+        };
+        let gray: Vec<u8> = data
+            .chunks_exact(components)
+            .map(|pixel| {
+                let values: Vec<f32> = pixel.iter().map(|v| *v as f32 / 255.0).collect();
+                (gray_level(&values).unwrap_or_default() * 255.0).round() as u8
+            })
+            .collect();
+        stream.set_plain_content(gray);
+        stream
+            .dict
+            .set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+        let _ = stream.compress();
+        converted += 1;
+    }
+
+    for (id, color_spaces) in forms {
+        let Ok(stream) = doc.get_object_mut(id).and_then(Object::as_stream_mut) else {
+            continue;
+        };
+        let Ok(mut content) = stream
+            .get_plain_content()
+            .and_then(|data| Content::decode(&data))
+        else {
+            continue;
+        };
+        convert_operations_to_grayscale(&mut content.operations, &color_spaces);
+        if let Ok(data) = content.encode() {
+            stream.set_plain_content(data);
+            let _ = stream.compress();
+            converted += 1;
+        }
+    }
+
+    converted
+}
+
+/// ย่อรูปภาพใน stream ให้ด้านที่ยาวที่สุดไม่เกิน `max_pixels` โดยเฉลี่ยค่าสีของพิกเซลในแต่ละช่อง
+///
+/// คืนค่า `false` หากรูปมีขนาดเล็กพออยู่แล้วหรือเป็นรูปแบบที่ไม่รองรับ
+pub(crate) fn downsample_image(stream: &mut Stream, max_pixels: usize) -> Result<bool> {
+    let width = stream.dict.get(b"Width")?.as_i64()? as usize;
+    let height = stream.dict.get(b"Height")?.as_i64()? as usize;
+    if stream.dict.get(b"BitsPerComponent")?.as_i64()? != 8 {
+        return Ok(false);
+    }
+    let components = match stream.dict.get(b"ColorSpace").and_then(Object::as_name) {
+        Ok(b"DeviceGray") => 1,
+        Ok(b"DeviceRGB") => 3,
+        Ok(b"DeviceCMYK") => 4,
+        _ => return Ok(false),
+    };
+
+    let factor = width.max(height).div_ceil(max_pixels.max(1));
+    if factor < 2 {
+        return Ok(false);
+    }
+
+    // decompressed_content จะคืนค่า Err สำหรับ filter ที่ถอดไม่ได้ เช่น DCTDecode (JPEG)
+    let data = stream.get_plain_content()?;
+    if data.len() < width * height * components {
+        return Ok(false);
+    }
+
+    let new_width = width.div_ceil(factor);
+    let new_height = height.div_ceil(factor);
+    let mut output = Vec::with_capacity(new_width * new_height * components);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            for c in 0..components {
+                let mut sum = 0u32;
+                let mut count = 0u32;
+                for sy in (y * factor)..((y + 1) * factor).min(height) {
+                    for sx in (x * factor)..((x + 1) * factor).min(width) {
+                        sum += data[(sy * width + sx) * components + c] as u32;
+                        count += 1;
+                    }
+                }
+                output.push((sum / count) as u8);
+            }
+        }
+    }
+
+    stream.set_plain_content(output);
+    stream.dict.set("Width", new_width as i64);
+    stream.dict.set("Height", new_height as i64);
+    stream.compress()?;
+    Ok(true)
+}
+
+/// ลดขนาดไฟล์ PDF: ย่อรูปภาพ, ลบ object ที่ไม่ได้ใช้ และบีบอัด stream
+pub(crate) fn optimize_pdf(doc: &mut Document, options: &OptimizeOptions) {
+    let mut downsampled = 0;
+    if let Some(target_dpi) = options.target_dpi {
+        // รูปภาพแสดงผลได้ใหญ่ที่สุดเท่ากับขนาดหน้ากระดาษ จึงใช้ด้านที่ยาวที่สุดของหน้าเป็นขอบเขต
+        let max_pixels = (target_dpi * A6_WIDTH_PTS.max(A6_HEIGHT_PTS) / 72.0).ceil() as usize;
+        for object in doc.objects.values_mut() {
+            if let Ok(stream) = object.as_stream_mut() {
+                let is_image = stream
+                    .dict
+                    .get(b"Subtype")
+                    .and_then(Object::as_name)
+                    .is_ok_and(|name| name == b"Image");
+                if is_image && downsample_image(stream, max_pixels).unwrap_or(false) {
+                    downsampled += 1;
+                }
+            }
+        }
+    }
+
+    let removed = doc.prune_objects().len() + doc.delete_zero_length_streams().len();
+    doc.compress();
+
+    println!(
+        "Optimized PDF: downsampled {} images, removed {} unused objects",
+        downsampled, removed
+    );
+}
+
+/// อ่าน MediaBox ของหน้า `[llx, lly, urx, ury]` รวมค่าที่สืบทอดจาก Pages tree (หากไม่มีจะถือว่าเป็น A4)
+pub(crate) fn page_media_box(doc: &Document, page_id: ObjectId) -> [f32; 4] {
+    let media_box = page_attribute(doc, page_id, b"MediaBox")
+        .ok_or(lopdf::Error::DictKey("MediaBox".to_string()))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_array());
+    if let Ok(values) = media_box {
+        let values: Vec<f32> = values.iter().filter_map(|v| v.as_float().ok()).collect();
+        if let [llx, lly, urx, ury] = values[..] {
+            return [llx.min(urx), lly.min(ury), llx.max(urx), lly.max(ury)];
+        }
+    }
+    [0.0, 0.0, A4_WIDTH_PTS, A4_HEIGHT_PTS]
+}
+
+/// อ่าน `/Rotate` ของหน้า (องศาตามเข็มนาฬิกา 0, 90, 180 หรือ 270) รวมค่าที่สืบทอดจาก Pages tree
+/// ค่าติดลบหรือเกิน 360 ถูกปรับด้วย mod 360 และค่าที่ไม่ใช่ผลคูณของ 90 ถือว่าเป็น 0 เหมือน viewer ทั่วไป
+pub(crate) fn page_rotation(doc: &Document, page_id: ObjectId) -> u16 {
+    let rotate = page_attribute(doc, page_id, b"Rotate")
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_i64().ok())
+        .map_or(0, |rotate| rotate.rem_euclid(360));
+    if rotate % 90 == 0 {
+        rotate as u16
+    } else {
+        0
+    }
+}
+
+/// คำนวณ Scale Factor ที่ทำให้หน้าต้นฉบับ (หลังหมุน) พอดีกับพื้นที่ขนาด `fit_width` x `fit_height`
+pub(crate) fn fit_scale(media_box: [f32; 4], rotate: u16, fit_width: f32, fit_height: f32) -> f32 {
+    let (mut width, mut height) = (media_box[2] - media_box[0], media_box[3] - media_box[1]);
+    if rotate == 90 || rotate == 270 {
+        std::mem::swap(&mut width, &mut height);
+    }
+    let scale_x = fit_width / width;
+    let scale_y = fit_height / height;
+    scale_x.min(scale_y)
+}
+
+/// ปรับ `scale` ที่ได้จาก `fit_scale` ตาม `policy` คืนค่า scale ที่ใช้และระยะเลื่อน (x, y)
+/// ที่ต้องเพิ่มให้ matrix เพื่อวางหน้าที่ไม่ได้ขยายไว้กลางพื้นที่ `fit_width` x `fit_height`
+pub(crate) fn apply_fit_policy(
+    policy: FitPolicy,
+    media_box: [f32; 4],
+    rotate: u16,
+    scale: f32,
+    fit_width: f32,
+    fit_height: f32,
+    paper_name: &str,
+) -> Result<(f32, [f32; 2])> {
+    if scale <= 1.0 {
+        return Ok((scale, [0.0, 0.0]));
+    }
+    match policy {
+        FitPolicy::Error => bail!(
+            "Scaling up is not handled, only scaling down to {} (set fit_policy to shrink_only or allow_upscale).",
+            paper_name
+        ),
+        FitPolicy::AllowUpscale => Ok((scale, [0.0, 0.0])),
+        FitPolicy::ShrinkOnly => {
+            let (mut width, mut height) = (media_box[2] - media_box[0], media_box[3] - media_box[1]);
+            if rotate == 90 || rotate == 270 {
+                std::mem::swap(&mut width, &mut height);
+            }
+            Ok((1.0, [(fit_width - width) / 2.0, (fit_height - height) / 2.0]))
+        }
+    }
+}
+
+/// สร้างคำสั่งวาด crop mark ที่มุมทั้งสี่ของกรอบตัด `trim_box`
+pub(crate) fn crop_mark_operations(trim_box: [f32; 4], bleed: f32) -> Vec<Operation> {
+    let [llx, lly, urx, ury] = trim_box;
+    let start = bleed + CROP_MARK_OFFSET_MM * PTS_PER_MM;
+    let end = start + CROP_MARK_LENGTH_MM * PTS_PER_MM;
+
+    let mut operations = vec![
+        Operation::new("q", vec![]),
+        Operation::new("w", vec![Object::Real(0.25)]),
+        Operation::new("G", vec![Object::Real(0.0)]),
+    ];
+    // (x, y, ทิศทางออกจากกรอบตัดในแนวนอน, ทิศทางในแนวตั้ง)
+    for (x, y, dx, dy) in [
+        (llx, lly, -1.0, -1.0),
+        (urx, lly, 1.0, -1.0),
+        (llx, ury, -1.0, 1.0),
+        (urx, ury, 1.0, 1.0),
+    ] {
+        let mut line = |x1: f32, y1: f32, x2: f32, y2: f32| {
+            operations.push(Operation::new(
+                "m",
+                vec![Object::Real(x1), Object::Real(y1)],
+            ));
+            operations.push(Operation::new(
+                "l",
+                vec![Object::Real(x2), Object::Real(y2)],
+            ));
+            operations.push(Operation::new("S", vec![]));
+        };
+        line(x + dx * start, y, x + dx * end, y);
+        line(x, y + dy * start, x, y + dy * end);
+    }
+    operations.push(Operation::new("Q", vec![]));
+    operations
+}
+
+/// สร้าง transformation matrix `[a b c d e f]` ที่ย้ายจุดเริ่มต้นของ MediaBox มาที่ (0, 0),
+/// หมุนตามเข็มนาฬิกา `rotate` องศา แล้วย่อขนาดด้วย `scale`
+pub(crate) fn page_transform_matrix(media_box: [f32; 4], rotate: u16, scale: f32) -> [f32; 6] {
+    let [llx, lly, urx, ury] = media_box;
+    let (width, height) = (urx - llx, ury - lly);
+    // matrix สำหรับหมุนหน้าขนาด width x height ให้อยู่ในพื้นที่บวก
+    let [a, b, c, d, e, f] = match rotate {
+        90 => [0.0, -1.0, 1.0, 0.0, 0.0, width],
+        180 => [-1.0, 0.0, 0.0, -1.0, width, height],
+        270 => [0.0, 1.0, -1.0, 0.0, height, 0.0],
+        _ => [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+    };
+    [
+        scale * a,
+        scale * b,
+        scale * c,
+        scale * d,
+        scale * (e - a * llx - c * lly),
+        scale * (f - b * llx - d * lly),
+    ]
+}
+
+/// แปลงคำสั่งสีใน content stream ของหน้าเป็นสีเทา โดยใช้ color space จาก Resources ของหน้า
+pub(crate) fn convert_page_operations_to_grayscale(
+    doc: &Document,
+    page_id: ObjectId,
+    operations: &mut [Operation],
+) {
+    let mut color_spaces = HashMap::new();
+    if let Ok((resources, resource_ids)) = doc.get_page_resources(page_id) {
+        for resources in resources.into_iter().chain(
+            resource_ids
+                .iter()
+                .filter_map(|id| doc.get_dictionary(*id).ok()),
+        ) {
+            collect_color_spaces(doc, resources, &mut color_spaces);
+        }
+    }
+    convert_operations_to_grayscale(operations, &color_spaces);
+}
+
+/// แปลงขนาด PDF ต้นฉบับ (`input`) เป็น A6 และปรับมาตราส่วนเนื้อหา คืนค่าไฟล์ผลลัพธ์ในหน่วยความจำ
+pub(crate) fn resize_pdf_to_a6(
+    input: Vec<u8>,
+    source_name: &str,
+    options: &TransformOptions,
+) -> Result<(Vec<u8>, TransformReport)> {
+    let mut doc = load_pdf_bytes(input, source_name, options.pdf_password)?;
+    let source_pages = doc.get_pages().len();
+    normalize_pages(&mut doc)?;
+    select_pages(&mut doc, options.page_set, options.reverse)?;
+
+    // Pre-flight: ตรวจฟอนต์ที่ไม่ได้ฝังก่อนแปลงขนาด
+    let warnings = embed_missing_fonts(&mut doc, options.font_fallbacks);
+    for warning in &warnings {
+        println!("Warning: {}", warning);
+    }
+
+    // ขนาดหน้าใหม่: A6 (กรอบตัด) + พื้นที่ bleed และ crop mark รอบด้าน
+    let bleed = options.bleed_mm * PTS_PER_MM;
+    let slug = if options.crop_marks {
+        bleed + (CROP_MARK_OFFSET_MM + CROP_MARK_LENGTH_MM) * PTS_PER_MM
+    } else {
+        bleed
+    };
+    let page_width = A6_WIDTH_PTS + 2.0 * slug;
+    let page_height = A6_HEIGHT_PTS + 2.0 * slug;
+    let trim_box = [slug, slug, slug + A6_WIDTH_PTS, slug + A6_HEIGHT_PTS];
+
+    // เนื้อหาถูกย่อให้อยู่ภายในขอบ margin และขยายเลยขอบตัดออกไปเท่ากับ bleed
+    let margin = options.margin_mm * PTS_PER_MM;
+    let fit_width = A6_WIDTH_PTS - 2.0 * margin + 2.0 * bleed;
+    let fit_height = A6_HEIGHT_PTS - 2.0 * margin + 2.0 * bleed;
+
+    // ขั้นแรกกำหนดขนาดหน้าและคำนวณ matrix ของทุกหน้าตามลำดับ (ใช้เวลาน้อย)
+    let mut page_matrices = Vec::new();
+    for (_, page_id) in doc.get_pages() {
+        // คำนวณ Scale Factor จากขนาดหน้าต้นฉบับหลังหมุน (รวม `/Rotate` ของหน้าที่ viewer ใช้แสดงผล)
+        let media_box = page_media_box(&doc, page_id);
+        let mut rotate = (options.rotate + page_rotation(&doc, page_id)) % 360;
+        let mut scale_factor = fit_scale(media_box, rotate, fit_width, fit_height);
+        if options.auto_rotate {
+            let turned = (rotate + 90) % 360;
+            let turned_scale = fit_scale(media_box, turned, fit_width, fit_height);
+            if turned_scale > scale_factor {
+                rotate = turned;
+                scale_factor = turned_scale;
+            }
+        }
+
+        let (scale_factor, [center_x, center_y]) = apply_fit_policy(
+            options.fit_policy,
+            media_box,
+            rotate,
+            scale_factor,
+            fit_width,
+            fit_height,
+            "A6",
+        )?;
+
+        // Modify MediaBox
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            let new_media_box = vec![
+                Object::Real(0.0),
+                Object::Real(0.0),
+                Object::Real(page_width),
+                Object::Real(page_height),
+            ];
+            page.set("MediaBox", Object::Array(new_media_box.clone()));
+            // การหมุนของหน้าอยู่ใน matrix แล้ว
+            page.remove(b"Rotate");
+
+            if slug > 0.0 {
+                let to_array = |values: [f32; 4]| {
+                    Object::Array(values.into_iter().map(Object::Real).collect())
+                };
+                let [llx, lly, urx, ury] = trim_box;
+                page.set("CropBox", Object::Array(new_media_box));
+                page.set("TrimBox", to_array(trim_box));
+                page.set(
+                    "BleedBox",
+                    to_array([llx - bleed, lly - bleed, urx + bleed, ury + bleed]),
+                );
+            } else {
+                // กรอบเดิม (รวม CropBox ที่สืบทอดมา) อ้างอิงพิกัดของหน้าต้นฉบับ
+                for key in [b"CropBox".as_slice(), b"TrimBox", b"BleedBox", b"ArtBox"] {
+                    page.remove(key);
+                }
+            }
+        }
+
+        let mut matrix = page_transform_matrix(media_box, rotate, scale_factor);
+        matrix[4] += slug - bleed + margin + center_x + options.offset_x_mm * PTS_PER_MM;
+        matrix[5] += slug - bleed + margin + center_y + options.offset_y_mm * PTS_PER_MM;
+        page_matrices.push((page_id, matrix));
+    }
+
+    // แปลง content stream ของแต่ละหน้าพร้อมกันหลาย thread (ส่วนที่ใช้เวลามากในเอกสารหลายร้อยหน้า)
+    // แล้วจึงนำกลับเข้าเอกสารตามลำดับ
+    let page_contents = page_matrices
+        .par_iter()
+        .map(|&(page_id, matrix)| -> Result<(ObjectId, Vec<u8>)> {
+            let content_data = doc.get_page_content(page_id)?;
+            let mut content = Content::decode(&content_data)?;
+
+            if options.grayscale {
+                convert_page_operations_to_grayscale(&doc, page_id, &mut content.operations);
+            }
+
+            let matrix_op = Operation::new("cm", matrix.into_iter().map(Object::Real).collect());
+            content.operations.insert(0, matrix_op);
+
+            if options.crop_marks {
+                // แยก graphics state ของเนื้อหาเดิมออกจาก crop mark ที่วาดในพิกัดของหน้าใหม่
+                content.operations.insert(0, Operation::new("q", vec![]));
+                content.operations.push(Operation::new("Q", vec![]));
+                content
+                    .operations
+                    .extend(crop_mark_operations(trim_box, bleed));
+            }
+
+            Ok((page_id, content.encode()?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (page_id, new_content) in page_contents {
+        doc.change_page_content(page_id, new_content)?;
+    }
+
+    if options.grayscale {
+        let converted = convert_objects_to_grayscale(&mut doc);
+        println!("Converted PDF to grayscale ({} images/forms)", converted);
+    }
+
+    if let Some(optimize) = options.optimize {
+        optimize_pdf(&mut doc, optimize);
+    }
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output)
+        .context(format!("Failed to write new A6 PDF of {}", source_name))?;
+
+    let report = TransformReport {
+        warnings,
+        source_pages,
+        output_pages: doc.get_pages().len(),
+        page_width_pts: page_width,
+        page_height_pts: page_height,
+        paper_name: "A6",
+    };
+    Ok((output, report))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::LazyLock;
+
+    static NO_FONT_FALLBACKS: LazyLock<HashMap<String, PathBuf>> = LazyLock::new(HashMap::new);
+
+    /// เนื้อหาของหน้าใน test: สี่เหลี่ยมสีน้ำเงินและข้อความ Helvetica
+    pub(crate) const TEST_CONTENT: &str =
+        "0.2 0.4 0.8 rg 10 10 100 50 re f BT /F1 12 Tf 20 20 Td (Hello) Tj ET";
+
+    /// สร้างไฟล์ PDF ที่มีหน้าละ `(ขนาด [กว้าง, สูง], /Rotate)` ตาม `pages` (`/Rotate` 0 = ไม่ใส่)
+    /// และใช้ content stream `content` ร่วมกันทุกหน้า
+    pub(crate) fn test_pdf(pages: &[([f32; 2], i64)], content: &str) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.as_bytes().to_vec()));
+        let kids: Vec<Object> = pages
+            .iter()
+            .map(|&([width, height], rotate)| {
+                let mut page = dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "MediaBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+                    "Contents" => content_id,
+                    "Resources" => dictionary! {
+                        "Font" => dictionary! { "F1" => font_id },
+                    },
+                };
+                if rotate != 0 {
+                    page.set("Rotate", rotate);
+                }
+                doc.add_object(page).into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => pages.len() as i64,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        let mut output = Vec::new();
+        doc.save_to(&mut output).unwrap();
+        output
+    }
+
+    /// ค่าเริ่มต้นของ request ที่ไม่ได้ระบุตัวเลือกการแปลงไฟล์
+    pub(crate) fn test_options() -> TransformOptions<'static> {
+        TransformOptions {
+            pdf_password: None,
+            optimize: None,
+            font_fallbacks: &NO_FONT_FALLBACKS,
+            grayscale: false,
+            rotate: 0,
+            auto_rotate: false,
+            margin_mm: 0.0,
+            offset_x_mm: 0.0,
+            offset_y_mm: 0.0,
+            bleed_mm: 0.0,
+            crop_marks: false,
+            fit_policy: FitPolicy::Error,
+            language: Language::En,
+            locale: None,
+            stamp_font: None,
+            page_set: PageSet::All,
+            reverse: false,
+            counters: None,
+        }
+    }
+
+    /// คำสั่ง `cm` แรกใน content stream ของหน้า
+    pub(crate) fn first_matrix(doc: &Document, page_id: ObjectId) -> [f32; 6] {
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        let operation = content
+            .operations
+            .iter()
+            .find(|operation| operation.operator == "cm")
+            .expect("page has no cm operation");
+        let values: Vec<f32> = operation
+            .operands
+            .iter()
+            .map(|o| o.as_float().unwrap())
+            .collect();
+        values.try_into().unwrap()
+    }
+
+    pub(crate) fn assert_close<const N: usize>(actual: [f32; N], expected: [f32; N]) {
+        assert!(
+            actual
+                .iter()
+                .zip(expected)
+                .all(|(a, e)| (a - e).abs() < 0.01),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    /// ไฟล์ผลลัพธ์ของ `resize_pdf_to_a6` และ id ของหน้าแรก
+    fn resize_first_page(input: Vec<u8>, options: &TransformOptions) -> (Document, ObjectId) {
+        let (output, report) = resize_pdf_to_a6(input, "test.pdf", options).unwrap();
+        assert_eq!(report.output_pages, 1);
+        let doc = Document::load_mem(&output).unwrap();
+        let page_id = doc.get_pages()[&1];
+        (doc, page_id)
+    }
+
+    /// ไฟล์ทดสอบที่มี `/Encrypt` ใน trailer และ content ของหน้าที่มีชื่อ `/Encrypt` และ `/ObjStm`
+    fn pdf_with_encrypt_entry(object_streams: bool) -> Vec<u8> {
+        let content = "BT /F1 12 Tf 20 20 Td (/Encrypt /ObjStm) Tj ET /Encrypt gs";
+        let mut doc = Document::load_mem(&test_pdf(&[([595.0, 842.0], 0)], content)).unwrap();
+        let encrypt_id = doc.add_object(dictionary! { "Filter" => "Standard", "V" => 2 });
+        doc.trailer.set("Encrypt", encrypt_id);
+        let mut output = Vec::new();
+        let options = lopdf::SaveOptions::builder()
+            .use_object_streams(object_streams)
+            .build();
+        doc.save_with_options(&mut output, options).unwrap();
+        output
+    }
+
+    fn count(buffer: &[u8], needle: &[u8]) -> usize {
+        buffer
+            .windows(needle.len())
+            .filter(|w| *w == needle)
+            .count()
+    }
+
+    #[test]
+    fn masking_pdf_names_leaves_stream_data_alone() {
+        for object_streams in [false, true] {
+            let mut buffer = pdf_with_encrypt_entry(object_streams);
+            assert_eq!(count(&buffer, b"/Encrypt"), 3);
+            mask_pdf_trailer_name(&mut buffer, b"/Encrypt", b"/Encryp_");
+            mask_pdf_name(&mut buffer, b"/ObjStm", b"/ObjSt_");
+            // เหลือเฉพาะชื่อใน content ของหน้า
+            assert_eq!(count(&buffer, b"/Encrypt"), 2);
+            assert_eq!(count(&buffer, b"/Encryp_"), 1);
+            assert_eq!(count(&buffer, b"/ObjStm"), 1);
+            assert_eq!(
+                count(&buffer, b"/ObjSt_"),
+                usize::from(object_streams),
+                "object_streams: {}",
+                object_streams
+            );
+
+            if !object_streams {
+                let doc = Document::load_mem(&buffer).unwrap();
+                assert!(doc.trailer.has(b"Encryp_"));
+                let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+                assert_eq!(
+                    page_content(&doc, page_id),
+                    b"BT /F1 12 Tf 20 20 Td (/Encrypt /ObjStm) Tj ET /Encrypt gs"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn masking_pdf_names_only_touches_trailers() {
+        // `/Encrypt` ใน dictionary ของ object อื่นไม่ใช่ trailer จึงไม่ถูกแก้
+        let mut buffer = b"1 0 obj\n<< /Encrypt 2 0 R >>\nendobj\n\
+            2 0 obj\n<< /Length 8 >>\nstream\n/Encrypt\nendstream\nendobj\n\
+            trailer\n<< /Root 1 0 R /Encrypt 2 0 R >>\nstartxref\n0\n%%EOF\n"
+            .to_vec();
+        mask_pdf_trailer_name(&mut buffer, b"/Encrypt", b"/Encryp_");
+        assert_eq!(count(&buffer, b"/Encrypt"), 2);
+        assert_eq!(count(&buffer, b"/Root 1 0 R /Encryp_ 2 0 R"), 1);
+    }
+
+    #[test]
+    fn page_rotation_is_normalized_and_inherited() {
+        let rotation = |rotate: i64| {
+            let doc = Document::load_mem(&test_pdf(&[([100.0, 200.0], rotate)], "")).unwrap();
+            page_rotation(&doc, doc.get_pages()[&1])
+        };
+        assert_eq!(rotation(0), 0);
+        assert_eq!(rotation(90), 90);
+        assert_eq!(rotation(-90), 270);
+        assert_eq!(rotation(450), 90);
+        assert_eq!(rotation(45), 0);
+
+        let mut doc = Document::load_mem(&test_pdf(&[([100.0, 200.0], 0)], "")).unwrap();
+        let page_id = doc.get_pages()[&1];
+        let pages_id = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"Parent")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        doc.get_dictionary_mut(pages_id).unwrap().set("Rotate", 180);
+        assert_eq!(page_rotation(&doc, page_id), 180);
+    }
+
+    #[test]
+    fn rotated_landscape_pages_fill_a6_portrait() {
+        // หน้า A4 แนวนอนที่ viewer แสดงเป็นแนวตั้งด้วย /Rotate 90
+        let input = test_pdf(&[([A4_HEIGHT_PTS, A4_WIDTH_PTS], 90)], TEST_CONTENT);
+        let (doc, page_id) = resize_first_page(input, &test_options());
+
+        let page = doc.get_dictionary(page_id).unwrap();
+        assert!(!page.has(b"Rotate"));
+        assert_close(
+            page_media_box(&doc, page_id),
+            [0.0, 0.0, A6_WIDTH_PTS, A6_HEIGHT_PTS],
+        );
+        let scale = (A6_WIDTH_PTS / A4_WIDTH_PTS).min(A6_HEIGHT_PTS / A4_HEIGHT_PTS);
+        assert_close(
+            first_matrix(&doc, page_id),
+            [0.0, -scale, scale, 0.0, 0.0, scale * A4_HEIGHT_PTS],
+        );
+    }
+
+    #[test]
+    fn rotated_portrait_pages_keep_their_displayed_orientation() {
+        // หน้า A4 แนวตั้งที่ viewer แสดงเป็นแนวนอนด้วย /Rotate 90
+        let input = test_pdf(&[([A4_WIDTH_PTS, A4_HEIGHT_PTS], 90)], TEST_CONTENT);
+        let (doc, page_id) = resize_first_page(input, &test_options());
+
+        assert!(!doc.get_dictionary(page_id).unwrap().has(b"Rotate"));
+        let scale = (A6_WIDTH_PTS / A4_HEIGHT_PTS).min(A6_HEIGHT_PTS / A4_WIDTH_PTS);
+        assert_close(
+            first_matrix(&doc, page_id),
+            [0.0, -scale, scale, 0.0, 0.0, scale * A4_WIDTH_PTS],
+        );
+
+        // auto_rotate หมุนต่อจาก /Rotate 270 อีก 90 องศา จึงวางเนื้อหาตามแนวเดิมของหน้า
+        let input = test_pdf(&[([A4_WIDTH_PTS, A4_HEIGHT_PTS], 270)], TEST_CONTENT);
+        let options = TransformOptions {
+            auto_rotate: true,
+            ..test_options()
+        };
+        let (doc, page_id) = resize_first_page(input, &options);
+        assert!(!doc.get_dictionary(page_id).unwrap().has(b"Rotate"));
+        let scale = (A6_WIDTH_PTS / A4_WIDTH_PTS).min(A6_HEIGHT_PTS / A4_HEIGHT_PTS);
+        assert_close(
+            first_matrix(&doc, page_id),
+            [scale, 0.0, 0.0, scale, 0.0, 0.0],
+        );
+    }
+}
@@ -0,0 +1,242 @@
+// ----------------------------------------------------------------------
+//                         PDF PRE-FLIGHT VALIDATION
+// ----------------------------------------------------------------------
+
+use crate::pdf::{
+    embed_missing_fonts, fit_scale, load_pdf_bytes, page_attribute, page_content, page_media_box,
+    FitPolicy, PdfPasswordError, A6_HEIGHT_PTS, A6_WIDTH_PTS, PTS_PER_MM,
+};
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use utoipa::ToSchema;
+
+/// ขนาดของแต่ละหน้าในไฟล์ต้นฉบับ
+#[derive(Serialize, ToSchema)]
+pub(crate) struct PdfPageInfo {
+    /// หมายเลขหน้า (เริ่มที่ 1)
+    page: u32,
+    width_mm: f32,
+    height_mm: f32,
+    /// ค่า `/Rotate` ของหน้า (องศา)
+    rotate: i64,
+}
+
+/// ผลการตรวจสอบไฟล์ PDF ก่อนส่งพิมพ์
+#[derive(Serialize, ToSchema)]
+pub(crate) struct PdfValidationReport {
+    /// `true` เมื่อไม่พบปัญหาที่ทำให้สั่งพิมพ์ไม่สำเร็จ (ดู `errors`)
+    pub(crate) valid: bool,
+    /// เวอร์ชันของไฟล์ เช่น "1.7"
+    #[schema(nullable)]
+    pdf_version: Option<String>,
+    pub(crate) page_count: usize,
+    pages: Vec<PdfPageInfo>,
+    /// ไฟล์ถูกเข้ารหัส
+    encrypted: bool,
+    /// ต้องส่ง `pdf_password` (ที่ถูกต้อง) เพื่อเปิดไฟล์
+    password_required: bool,
+    /// ตาราง xref เสียหาย หรือมี object ที่อ่านจากตำแหน่งใน xref ไม่ได้
+    xref_damaged: bool,
+    /// ปัญหาที่ทำให้สั่งพิมพ์ไม่สำเร็จ
+    pub(crate) errors: Vec<String>,
+    /// ปัญหาที่ยังพิมพ์ได้ แต่ผลลัพธ์อาจไม่ตรงกับที่เห็นบนหน้าจอ เช่น ฟอนต์ไม่ได้ฝัง หรือฟีเจอร์ที่ไม่รองรับ
+    pub(crate) warnings: Vec<String>,
+}
+
+/// ประเภท annotation ที่ไม่ถูกวาดลงบนกระดาษ
+pub(crate) const UNSUPPORTED_ANNOTATIONS: [&str; 5] =
+    ["3D", "RichMedia", "Movie", "Sound", "Screen"];
+
+/// ตรวจหาฟีเจอร์ของ PDF ที่ไม่ถูกพิมพ์ออกมา เช่น JavaScript, ฟอร์ม XFA, ไฟล์แนบ และสื่อมัลติมีเดีย
+pub(crate) fn unsupported_pdf_features(doc: &Document) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let Ok(catalog) = doc.catalog() else {
+        return warnings;
+    };
+    let get_dict = |dict: &Dictionary, key: &[u8]| -> Option<Dictionary> {
+        dict.get(key)
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_dict())
+            .ok()
+            .cloned()
+    };
+
+    let names = get_dict(catalog, b"Names");
+    let open_action_js = get_dict(catalog, b"OpenAction").is_some_and(|action| {
+        action.get(b"S").and_then(Object::as_name).ok() == Some(b"JavaScript")
+    });
+    if open_action_js || names.as_ref().is_some_and(|n| n.has(b"JavaScript")) {
+        warnings
+            .push("Document contains JavaScript, which is not executed when printing".to_string());
+    }
+    if get_dict(catalog, b"AcroForm").is_some_and(|form| form.has(b"XFA")) {
+        warnings.push(
+            "Document contains an XFA form; only the static PDF content will be printed"
+                .to_string(),
+        );
+    }
+    if names.as_ref().is_some_and(|n| n.has(b"EmbeddedFiles")) {
+        warnings.push("Document contains embedded files, which are not printed".to_string());
+    }
+
+    let mut annotations: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+    for (page_number, page_id) in doc.get_pages() {
+        for annotation in doc.get_page_annotations(page_id).unwrap_or_default() {
+            let subtype = annotation
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .unwrap_or(b"");
+            let subtype = String::from_utf8_lossy(subtype).to_string();
+            if UNSUPPORTED_ANNOTATIONS.contains(&subtype.as_str()) {
+                annotations.entry(subtype).or_default().insert(page_number);
+            }
+        }
+    }
+    for (subtype, pages) in annotations {
+        let pages: Vec<String> = pages.iter().map(u32::to_string).collect();
+        warnings.push(format!(
+            "{} annotations on pages {} are not printed",
+            subtype,
+            pages.join(", ")
+        ));
+    }
+
+    warnings
+}
+
+/// ตรวจสอบไฟล์ PDF โดยไม่สั่งพิมพ์: จำนวนหน้า ขนาดหน้า การเข้ารหัส ตาราง xref และฟีเจอร์ที่ไม่รองรับ
+///
+/// ปัญหาของตัวไฟล์ PDF จะอยู่ใน `errors` ของผลลัพธ์
+pub(crate) fn validate_pdf(
+    buffer: Vec<u8>,
+    name: &str,
+    password: Option<&str>,
+    font_fallbacks: &HashMap<String, PathBuf>,
+    fit_policy: FitPolicy,
+) -> PdfValidationReport {
+    let mut report = PdfValidationReport {
+        valid: false,
+        pdf_version: None,
+        page_count: 0,
+        pages: vec![],
+        encrypted: false,
+        password_required: false,
+        xref_damaged: false,
+        errors: vec![],
+        warnings: vec![],
+    };
+
+    let raw = match Document::load_mem(&buffer) {
+        Ok(doc) => doc,
+        Err(e) => {
+            // lopdf ไม่ได้ export `ParseError` จึงเทียบ variant จากชื่อแทน
+            report.xref_damaged = match &e {
+                lopdf::Error::Xref(_) | lopdf::Error::MissingXrefEntry => true,
+                lopdf::Error::Parse(parse_error) => {
+                    matches!(
+                        format!("{:?}", parse_error).as_str(),
+                        "InvalidXref" | "InvalidTrailer"
+                    )
+                }
+                _ => false,
+            };
+            report.errors.push(format!("Failed to parse PDF: {}", e));
+            return report;
+        }
+    };
+    report.pdf_version = Some(raw.version.clone());
+    report.encrypted = raw.is_encrypted();
+
+    let mut doc = if report.encrypted {
+        match load_pdf_bytes(buffer.clone(), name, password) {
+            Ok(doc) => doc,
+            Err(e) => {
+                report.password_required = e.downcast_ref::<PdfPasswordError>().is_some();
+                report.errors.push(e.to_string());
+                return report;
+            }
+        }
+    } else {
+        // object ที่อยู่ใน xref แต่ lopdf อ่านจาก offset นั้นไม่ได้ แสดงว่า xref ชี้ตำแหน่งผิด
+        let unreadable = raw
+            .reference_table
+            .entries
+            .iter()
+            .filter(|(id, entry)| match entry {
+                lopdf::xref::XrefEntry::Normal { generation, .. } => {
+                    !raw.objects.contains_key(&(**id, *generation))
+                }
+                _ => false,
+            })
+            .count();
+        if unreadable > 0 {
+            report.xref_damaged = true;
+            report.warnings.push(format!(
+                "Cross-reference table is damaged: {} objects could not be read",
+                unreadable
+            ));
+        }
+        raw
+    };
+
+    let pages = doc.get_pages();
+    if pages.is_empty() {
+        report.errors.push("Document has no pages".to_string());
+    }
+    for (&page_number, &page_id) in &pages {
+        let media_box = page_media_box(&doc, page_id);
+        let rotate = page_attribute(&doc, page_id, b"Rotate")
+            .and_then(|o| o.as_i64().ok())
+            .unwrap_or(0);
+        report.pages.push(PdfPageInfo {
+            page: page_number,
+            width_mm: (media_box[2] - media_box[0]) / PTS_PER_MM,
+            height_mm: (media_box[3] - media_box[1]) / PTS_PER_MM,
+            rotate,
+        });
+
+        // หน้าที่เล็กกว่า A6 ทั้งแนวตั้งและแนวนอนจะแปลงไม่ได้เมื่อ `fit_policy` เป็น error (ค่าเริ่มต้น)
+        let scale = fit_scale(media_box, 0, A6_WIDTH_PTS, A6_HEIGHT_PTS);
+        let turned_scale = fit_scale(media_box, 90, A6_WIDTH_PTS, A6_HEIGHT_PTS);
+        if scale > 1.0 && turned_scale > 1.0 {
+            match fit_policy {
+                FitPolicy::Error => report.errors.push(format!(
+                    "Page {} is smaller than A6; set fit_policy to shrink_only or allow_upscale",
+                    page_number
+                )),
+                FitPolicy::ShrinkOnly => report.warnings.push(format!(
+                    "Page {} is smaller than A6 and will be centered at its original size",
+                    page_number
+                )),
+                FitPolicy::AllowUpscale => report.warnings.push(format!(
+                    "Page {} is smaller than A6 and will be scaled up",
+                    page_number
+                )),
+            }
+        } else if scale > 1.0 && fit_policy == FitPolicy::Error {
+            report.warnings.push(format!(
+                "Page {} fits A6 only when rotated; use rotate or auto_rotate",
+                page_number
+            ));
+        }
+
+        let content = Content::decode(&page_content(&doc, page_id));
+        if let Err(e) = content {
+            report.errors.push(format!(
+                "Page {} content stream cannot be decoded: {}",
+                page_number, e
+            ));
+        }
+    }
+    report.page_count = pages.len();
+
+    report
+        .warnings
+        .extend(embed_missing_fonts(&mut doc, font_fallbacks));
+    report.warnings.extend(unsupported_pdf_features(&doc));
+    report.valid = report.errors.is_empty();
+    report
+}
@@ -0,0 +1,363 @@
+// ----------------------------------------------------------------------
+//                              PRINT TOKENS
+// ----------------------------------------------------------------------
+
+use crate::agent::tokens_match;
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// ไฟล์เก็บ token พิมพ์แบบใช้ครั้งเดียวที่ใช้ไปแล้วและยังไม่หมดอายุ
+pub(crate) const PRINT_TOKENS_PATH: &str = "./printable_files/.print_tokens.json";
+/// อายุของ token พิมพ์เมื่อ request ไม่ได้ระบุ `ttl_minutes` (นาที)
+pub(crate) const DEFAULT_PRINT_TOKEN_TTL_MINUTES: u64 = 60;
+/// อายุสูงสุดของ token พิมพ์เมื่อไม่ได้ตั้งค่า `print_tokens.max_ttl_minutes` (8 ชั่วโมง)
+pub(crate) const DEFAULT_PRINT_TOKEN_MAX_TTL_MINUTES: u64 = 8 * 60;
+/// ความยาวขั้นต่ำของ `print_tokens.secret`
+pub(crate) const MIN_PRINT_TOKEN_SECRET_CHARS: usize = 32;
+
+/// การตั้งค่าของ `print_tokens` ใน config.json
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct PrintTokenConfig {
+    /// กุญแจสำหรับลงลายมือชื่อ token อย่างน้อย 32 ตัวอักษร
+    /// (ไม่ระบุ = สุ่มใหม่ทุกครั้งที่เริ่ม Service ทำให้ token ที่ออกไปแล้วใช้ไม่ได้หลังเริ่มใหม่)
+    secret: Option<String>,
+    /// อายุสูงสุดที่ขอได้ใน `ttl_minutes` (นาที, ไม่ระบุ = 480)
+    max_ttl_minutes: Option<u64>,
+}
+
+impl PrintTokenConfig {
+    pub(crate) fn max_ttl_minutes(&self) -> u64 {
+        self.max_ttl_minutes
+            .unwrap_or(DEFAULT_PRINT_TOKEN_MAX_TTL_MINUTES)
+    }
+}
+
+/// ตรวจ `print_tokens` ตอนโหลด config
+pub(crate) fn validate_print_token_config(
+    config: &PrintTokenConfig,
+) -> std::result::Result<(), String> {
+    if config
+        .secret
+        .as_ref()
+        .is_some_and(|secret| secret.chars().count() < MIN_PRINT_TOKEN_SECRET_CHARS)
+    {
+        return Err(format!(
+            "print_tokens.secret must be at least {} characters",
+            MIN_PRINT_TOKEN_SECRET_CHARS
+        ));
+    }
+    if config.max_ttl_minutes == Some(0) {
+        return Err("print_tokens.max_ttl_minutes must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+/// HMAC-SHA256 ตาม RFC 2104
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_LEN: usize = 64;
+    let mut block = [0u8; BLOCK_LEN];
+    if key.len() > BLOCK_LEN {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// ผลการตรวจ token พิมพ์ที่ไม่ผ่าน
+#[derive(Debug, PartialEq)]
+pub(crate) enum PrintTokenError {
+    /// รูปแบบหรือลายมือชื่อไม่ถูกต้อง
+    Invalid,
+    /// หมดอายุแล้ว
+    Expired,
+    /// token แบบใช้ครั้งเดียวที่ถูกใช้ไปแล้วหรือกำลังถูกใช้อยู่
+    Used,
+}
+
+/// ออกและตรวจ token พิมพ์ (`<claims base64url>.<HMAC-SHA256 base64url>`)
+/// และจำ id ของ token แบบใช้ครั้งเดียวที่ใช้ไปแล้วจนกว่าจะหมดอายุ
+pub(crate) struct PrintTokenStore {
+    /// กุญแจที่ใช้เมื่อไม่ได้ตั้งค่า `print_tokens.secret`
+    random_secret: [u8; 32],
+    path: PathBuf,
+    /// id ของ token ที่ใช้ไปแล้ว และเวลาหมดอายุ (unix timestamp)
+    redeemed: Mutex<BTreeMap<String, i64>>,
+}
+
+impl PrintTokenStore {
+    /// อ่าน token ที่ใช้ไปแล้วจากไฟล์ `path` (ไม่มีไฟล์ = ยังไม่มี token ที่ใช้ไปแล้ว)
+    /// ไฟล์ที่อ่านไม่ได้ทำให้ server ไม่เริ่มทำงาน เพื่อไม่ให้ token แบบใช้ครั้งเดียวถูกใช้ซ้ำ
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let redeemed = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("Invalid print token file {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let mut random_secret = [0u8; 32];
+        getrandom::fill(&mut random_secret)
+            .map_err(|e| anyhow::anyhow!("Failed to generate print token secret: {}", e))?;
+        Ok(PrintTokenStore {
+            random_secret,
+            path: path.to_path_buf(),
+            redeemed: Mutex::new(redeemed),
+        })
+    }
+
+    fn secret<'a>(&'a self, config: &'a PrintTokenConfig) -> &'a [u8] {
+        config
+            .secret
+            .as_deref()
+            .map_or(&self.random_secret, str::as_bytes)
+    }
+
+    /// ลงลายมือชื่อ `claims` เป็น token
+    pub(crate) fn sign<T: Serialize>(
+        &self,
+        config: &PrintTokenConfig,
+        claims: &T,
+    ) -> Result<String> {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let payload = engine.encode(serde_json::to_vec(claims)?);
+        let mac = hmac_sha256(self.secret(config), payload.as_bytes());
+        Ok(format!("{}.{}", payload, engine.encode(mac)))
+    }
+
+    /// ค่าที่ใส่ใน token แทน API key หรือ client certificate `credential` ที่ออก token
+    /// (อ่าน payload ของ token ได้ จึงใช้ HMAC แทนการเก็บค่าเดิมหรือ hash ที่เดาย้อนกลับได้)
+    pub(crate) fn credential_id(&self, config: &PrintTokenConfig, credential: &str) -> String {
+        let mac = hmac_sha256(
+            self.secret(config),
+            format!("credential\n{}", credential).as_bytes(),
+        );
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(mac)
+    }
+
+    /// ตรวจลายมือชื่อของ `token` และคืน claims ที่อยู่ใน token
+    pub(crate) fn verify<T: serde::de::DeserializeOwned>(
+        &self,
+        config: &PrintTokenConfig,
+        token: &str,
+    ) -> std::result::Result<T, PrintTokenError> {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let (payload, mac) = token
+            .trim()
+            .split_once('.')
+            .ok_or(PrintTokenError::Invalid)?;
+        let expected = engine.encode(hmac_sha256(self.secret(config), payload.as_bytes()));
+        if !tokens_match(&expected, mac) {
+            return Err(PrintTokenError::Invalid);
+        }
+        let claims = engine
+            .decode(payload)
+            .map_err(|_| PrintTokenError::Invalid)?;
+        serde_json::from_slice(&claims).map_err(|_| PrintTokenError::Invalid)
+    }
+
+    /// จอง token `id` แบบใช้ครั้งเดียว โดยบันทึกลงไฟล์ก่อนคืนค่า
+    /// คืน `Used` เมื่อ token นี้ถูกใช้ไปแล้ว
+    pub(crate) fn begin(
+        &self,
+        id: &str,
+        expires_at: i64,
+    ) -> std::result::Result<(), PrintTokenError> {
+        let mut redeemed = self.redeemed.lock().unwrap();
+        if redeemed.contains_key(id) {
+            return Err(PrintTokenError::Used);
+        }
+        let mut updated = redeemed.clone();
+        updated.insert(id.to_string(), expires_at);
+        if let Err(e) = self.save(&mut updated) {
+            // ไม่ยอมให้ใช้ token เมื่อบันทึกไม่ได้ เพราะหลังเริ่ม Service ใหม่ token จะถูกใช้ซ้ำได้
+            eprintln!("Failed to save {}: {}", self.path.display(), e);
+            return Err(PrintTokenError::Used);
+        }
+        *redeemed = updated;
+        Ok(())
+    }
+
+    /// คืน token `id` ที่จองไว้ เมื่อส่งงานพิมพ์ไม่สำเร็จ เพื่อให้ใช้ token เดิมลองใหม่ได้
+    pub(crate) fn release(&self, id: &str) {
+        let mut redeemed = self.redeemed.lock().unwrap();
+        let mut updated = redeemed.clone();
+        if updated.remove(id).is_none() {
+            return;
+        }
+        match self.save(&mut updated) {
+            Ok(()) => *redeemed = updated,
+            Err(e) => eprintln!("Failed to save {}: {}", self.path.display(), e),
+        }
+    }
+
+    /// ลบ token ที่หมดอายุแล้วออกจาก `redeemed` และเขียนลงไฟล์
+    fn save(&self, redeemed: &mut BTreeMap<String, i64>) -> std::io::Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        redeemed.retain(|_, expires_at| *expires_at > now);
+        let temp_path = self.path.with_extension("json.tmp");
+        let data = serde_json::to_vec_pretty(redeemed)?;
+        std::fs::File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(&data)?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&temp_path, &self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::hex_string;
+
+    /// path ใหม่ในโฟลเดอร์ชั่วคราวของเครื่องที่ไม่ซ้ำกับ test อื่น
+    fn temp_path(name: &str) -> PathBuf {
+        let mut bytes = [0u8; 8];
+        getrandom::fill(&mut bytes).unwrap();
+        std::env::temp_dir().join(format!(
+            "rust-print-api-test-{}-{}",
+            hex_string(&bytes),
+            name
+        ))
+    }
+
+    #[test]
+    fn hmac_sha256_matches_rfc_4231() {
+        let long_key = [0xaa; 131];
+        let cases: [(&[u8], &[u8], &str); 6] = [
+            (
+                &[0x0b; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                &[0xaa; 20],
+                &[0xdd; 50],
+                "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            ),
+            (
+                &[
+                    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+                    23, 24, 25,
+                ],
+                &[0xcd; 50],
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            ),
+            (
+                &long_key,
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                &long_key,
+                b"This is a test using a larger than block-size key and a larger than block-size data. \
+                  The key needs to be hashed before being used by the HMAC algorithm.",
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+            ),
+        ];
+        for (key, message, mac) in cases {
+            assert_eq!(hex_string(&hmac_sha256(key, message)), mac);
+        }
+    }
+
+    fn token_config(secret: &str) -> PrintTokenConfig {
+        PrintTokenConfig {
+            secret: Some(secret.to_string()),
+            max_ttl_minutes: None,
+        }
+    }
+
+    #[test]
+    fn print_tokens_verify_only_their_own_signature() {
+        let store = PrintTokenStore::load(&temp_path("print_tokens.json")).unwrap();
+        let config = token_config("0123456789abcdef0123456789abcdef");
+        let claims = serde_json::json!({"id": "t1", "filename": "invoice.pdf"});
+        let token = store.sign(&config, &claims).unwrap();
+        assert_eq!(
+            store.verify::<serde_json::Value>(&config, &token),
+            Ok(claims.clone())
+        );
+
+        let (payload, mac) = token.split_once('.').unwrap();
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let forged = engine.encode(br#"{"id":"t1","filename":"payroll.pdf"}"#);
+        let mut flipped_mac = mac.to_string().into_bytes();
+        flipped_mac[0] = if flipped_mac[0] == b'A' { b'B' } else { b'A' };
+        let flipped_mac = String::from_utf8(flipped_mac).unwrap();
+        for tampered in [
+            format!("{}.{}", forged, mac),
+            format!("{}.{}", payload, flipped_mac),
+            format!("{}.", payload),
+            payload.to_string(),
+            String::new(),
+        ] {
+            assert_eq!(
+                store.verify::<serde_json::Value>(&config, &tampered),
+                Err(PrintTokenError::Invalid)
+            );
+        }
+
+        let other = token_config("fedcba9876543210fedcba9876543210");
+        assert_eq!(
+            store.verify::<serde_json::Value>(&other, &token),
+            Err(PrintTokenError::Invalid)
+        );
+        // ไม่ได้ตั้ง secret = ใช้กุญแจสุ่มของแต่ละ store
+        let random = PrintTokenConfig::default();
+        let token = store.sign(&random, &claims).unwrap();
+        assert!(store.verify::<serde_json::Value>(&random, &token).is_ok());
+        let restarted = PrintTokenStore::load(&temp_path("print_tokens.json")).unwrap();
+        assert_eq!(
+            restarted.verify::<serde_json::Value>(&random, &token),
+            Err(PrintTokenError::Invalid)
+        );
+    }
+
+    #[test]
+    fn single_use_print_tokens_survive_restart() {
+        let path = temp_path("print_tokens.json");
+        let store = PrintTokenStore::load(&path).unwrap();
+        let expires_at = chrono::Utc::now().timestamp() + 600;
+        assert_eq!(store.begin("t1", expires_at), Ok(()));
+        assert_eq!(store.begin("t1", expires_at), Err(PrintTokenError::Used));
+
+        let restarted = PrintTokenStore::load(&path).unwrap();
+        assert_eq!(
+            restarted.begin("t1", expires_at),
+            Err(PrintTokenError::Used)
+        );
+        // งานที่ส่งไม่สำเร็จคืน token ให้ลองใหม่ได้
+        restarted.release("t1");
+        let reloaded = PrintTokenStore::load(&path).unwrap();
+        assert_eq!(reloaded.begin("t1", expires_at), Ok(()));
+        // token ที่หมดอายุแล้วถูกลบออกจากไฟล์
+        reloaded
+            .begin("old", chrono::Utc::now().timestamp() - 1)
+            .unwrap();
+        let saved: BTreeMap<String, i64> =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved.keys().collect::<Vec<_>>(), ["t1"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
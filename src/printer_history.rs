@@ -0,0 +1,270 @@
+// ----------------------------------------------------------------------
+//                            PRINTER HISTORY
+// ----------------------------------------------------------------------
+
+use crate::{config::AppConfig, notify::PRINTER_HEALTH_INTERVAL};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+/// สถิติรายชั่วโมงของเครื่องพิมพ์ (ไฟล์ซ่อนจึงไม่แสดงใน `/api/v1/files`)
+pub(crate) const PRINTER_HISTORY_PATH: &str = "./printable_files/.printer_history.json";
+pub(crate) const DEFAULT_PRINTER_HISTORY_DAYS: u64 = 90;
+
+/// จำนวนงาน ข้อผิดพลาด และเวลาที่ offline ของเครื่องพิมพ์ในช่วงเวลาหนึ่ง
+#[derive(Default, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub(crate) struct PrinterUsage {
+    /// งานที่ส่งไปยังเครื่องพิมพ์ (รวมงานที่ล้มเหลว)
+    jobs: u64,
+    /// จำนวนหน้าของงานที่พิมพ์สำเร็จ
+    pages: u64,
+    /// งานที่ส่งไปยังเครื่องพิมพ์ไม่สำเร็จ
+    failed: u64,
+    /// จำนวนครั้งที่เครื่องพิมพ์เริ่ม offline
+    offline_periods: u64,
+    /// เวลาที่ offline รวม (นาที)
+    offline_minutes: u64,
+}
+
+impl PrinterUsage {
+    pub(crate) fn add(&mut self, other: &PrinterUsage) {
+        self.jobs += other.jobs;
+        self.pages += other.pages;
+        self.failed += other.failed;
+        self.offline_periods += other.offline_periods;
+        self.offline_minutes += other.offline_minutes;
+    }
+}
+
+/// สถิติของเครื่องพิมพ์หนึ่งเครื่องในหนึ่งชั่วโมงตามที่บันทึกลงไฟล์
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PrinterHourEntry {
+    printer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    agent_id: Option<String>,
+    /// เวลาเริ่มต้นของชั่วโมง (Unix timestamp)
+    hour: i64,
+    #[serde(flatten)]
+    usage: PrinterUsage,
+}
+
+/// ขนาดช่วงเวลาของ `GET /api/v1/printers/{name}/history`
+#[derive(Deserialize, ToSchema, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum HistoryBucket {
+    Hour,
+    #[default]
+    Day,
+    /// สัปดาห์ตาม ISO 8601 (เริ่มวันจันทร์)
+    Week,
+    /// รวมทุกสัปดาห์ตามวันในสัปดาห์และชั่วโมง (168 ช่วง) เช่น "Fri 14:00"
+    WeekdayHour,
+}
+
+impl HistoryBucket {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            HistoryBucket::Hour => "hour",
+            HistoryBucket::Day => "day",
+            HistoryBucket::Week => "week",
+            HistoryBucket::WeekdayHour => "weekday_hour",
+        }
+    }
+
+    /// ชื่อช่วงเวลาที่ชั่วโมง `hour` (Unix timestamp) อยู่ ตามเวลาท้องถิ่นของ server
+    fn label(self, hour: i64) -> String {
+        let time = chrono::DateTime::from_timestamp(hour, 0)
+            .unwrap_or_default()
+            .with_timezone(&chrono::Local);
+        match self {
+            HistoryBucket::Hour => time.to_rfc3339(),
+            HistoryBucket::Day => time.format("%Y-%m-%d").to_string(),
+            HistoryBucket::Week => time.format("%G-W%V").to_string(),
+            HistoryBucket::WeekdayHour => time.format("%a %H:00").to_string(),
+        }
+    }
+}
+
+/// (agent id, ชื่อเครื่องพิมพ์) ของเครื่องพิมพ์ใน timeline
+pub(crate) type PrinterKey = (Option<String>, String);
+
+/// สถิติรายชั่วโมงของแต่ละเครื่องพิมพ์ย้อนหลัง `printer_history_days` วัน
+/// (ประวัติงานใน `JobHistory` เก็บเพียงงานล่าสุด จึงใช้ดูแนวโน้มระยะยาวไม่ได้)
+#[derive(Default)]
+pub(crate) struct PrinterTimeline {
+    hours: Mutex<HashMap<PrinterKey, BTreeMap<i64, PrinterUsage>>>,
+    /// มีข้อมูลที่ยังไม่ได้บันทึกลงไฟล์
+    dirty: AtomicBool,
+}
+
+impl PrinterTimeline {
+    /// อ่าน timeline จากไฟล์ (ไม่มีไฟล์หรือไฟล์เสียหาย = เริ่มใหม่)
+    pub(crate) fn load(path: &Path) -> Self {
+        let timeline = PrinterTimeline::default();
+        let entries: Vec<PrinterHourEntry> = match std::fs::read(path) {
+            Ok(data) => match serde_json::from_slice(&data) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    eprintln!("Ignoring invalid printer history {}: {}", path.display(), e);
+                    return timeline;
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return timeline,
+            Err(e) => {
+                eprintln!("Failed to read printer history {}: {}", path.display(), e);
+                return timeline;
+            }
+        };
+        let mut hours = timeline.hours.lock().unwrap();
+        for entry in entries {
+            hours
+                .entry((entry.agent_id, entry.printer))
+                .or_default()
+                .insert(entry.hour, entry.usage);
+        }
+        drop(hours);
+        timeline
+    }
+
+    /// ปรับสถิติของชั่วโมงปัจจุบันของเครื่องพิมพ์ `printer`
+    fn update(
+        &self,
+        agent_id: Option<&str>,
+        printer: &str,
+        update: impl FnOnce(&mut PrinterUsage),
+    ) {
+        let now = chrono::Utc::now().timestamp();
+        let mut hours = self.hours.lock().unwrap();
+        update(
+            hours
+                .entry((agent_id.map(str::to_string), printer.to_string()))
+                .or_default()
+                .entry(now - now.rem_euclid(3600))
+                .or_default(),
+        );
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// นับงานที่ส่งไปยังเครื่องพิมพ์ (`pages` นับเฉพาะงานที่สำเร็จ)
+    pub(crate) fn count_job(
+        &self,
+        agent_id: Option<&str>,
+        printer: &str,
+        pages: usize,
+        success: bool,
+    ) {
+        self.update(agent_id, printer, |usage| {
+            usage.jobs += 1;
+            if success {
+                usage.pages += pages as u64;
+            } else {
+                usage.failed += 1;
+            }
+        });
+    }
+
+    /// นับเวลาที่เครื่องพิมพ์ในเครื่องนี้ offline หนึ่งรอบของการตรวจสถานะ (`started` = เพิ่งเริ่ม offline)
+    pub(crate) fn count_offline(&self, printer: &str, started: bool) {
+        self.update(None, printer, |usage| {
+            usage.offline_periods += started as u64;
+            usage.offline_minutes += PRINTER_HEALTH_INTERVAL.as_secs() / 60;
+        });
+    }
+
+    /// ลบข้อมูลที่เก่ากว่า `retention_days` วัน แล้วบันทึกลงไฟล์ (เฉพาะเมื่อมีข้อมูลใหม่)
+    pub(crate) fn save(&self, path: &Path, retention_days: u64) -> std::io::Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let cutoff = chrono::Utc::now().timestamp() - retention_days as i64 * 86400;
+        let entries: Vec<PrinterHourEntry> = {
+            let mut hours = self.hours.lock().unwrap();
+            hours.retain(|_, printer_hours| {
+                printer_hours.retain(|hour, _| *hour >= cutoff);
+                !printer_hours.is_empty()
+            });
+            hours
+                .iter()
+                .flat_map(|((agent_id, printer), printer_hours)| {
+                    printer_hours.iter().map(|(hour, usage)| PrinterHourEntry {
+                        printer: printer.clone(),
+                        agent_id: agent_id.clone(),
+                        hour: *hour,
+                        usage: *usage,
+                    })
+                })
+                .collect()
+        };
+        // เขียนไฟล์ชั่วคราวก่อนแล้วจึงแทนที่ ไฟล์เดิมจึงไม่เสียหายหากเขียนไม่สำเร็จ
+        let temp_path = path.with_extension("json.tmp");
+        let result = serde_json::to_vec(&entries)
+            .map_err(std::io::Error::from)
+            .and_then(|data| std::fs::write(&temp_path, data))
+            .and_then(|_| std::fs::rename(&temp_path, path));
+        if result.is_err() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// มีสถิติของเครื่องพิมพ์นี้หรือไม่
+    pub(crate) fn contains(&self, agent_id: Option<&str>, printer: &str) -> bool {
+        self.hours
+            .lock()
+            .unwrap()
+            .contains_key(&(agent_id.map(str::to_string), printer.to_string()))
+    }
+
+    /// รวมสถิติตั้งแต่ `from` ถึง `to` (Unix timestamp) ตามช่วง `bucket` รวมช่วงที่ไม่มีข้อมูลด้วย
+    pub(crate) fn buckets(
+        &self,
+        agent_id: Option<&str>,
+        printer: &str,
+        from: i64,
+        to: i64,
+        bucket: HistoryBucket,
+    ) -> Vec<(String, PrinterUsage)> {
+        let first_hour = from - from.rem_euclid(3600);
+        let mut buckets: Vec<(String, PrinterUsage)> = Vec::new();
+        if bucket == HistoryBucket::WeekdayHour {
+            for weekday in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+                for hour in 0..24 {
+                    buckets.push((format!("{} {:02}:00", weekday, hour), Default::default()));
+                }
+            }
+        } else {
+            for hour in (first_hour..=to).step_by(3600) {
+                let label = bucket.label(hour);
+                if buckets.last().is_none_or(|(last, _)| *last != label) {
+                    buckets.push((label, Default::default()));
+                }
+            }
+        }
+        let positions: HashMap<String, usize> = buckets
+            .iter()
+            .enumerate()
+            .map(|(i, (label, _))| (label.clone(), i))
+            .collect();
+
+        let hours = self.hours.lock().unwrap();
+        if let Some(printer_hours) = hours.get(&(agent_id.map(str::to_string), printer.to_string()))
+        {
+            for (hour, usage) in printer_hours.range(first_hour..=to) {
+                if let Some(&i) = positions.get(&bucket.label(*hour)) {
+                    buckets[i].1.add(usage);
+                }
+            }
+        }
+        buckets
+    }
+}
+
+pub(crate) fn printer_history_days(config: &AppConfig) -> u64 {
+    config
+        .printer_history_days
+        .unwrap_or(DEFAULT_PRINTER_HISTORY_DAYS)
+        .max(1)
+}
@@ -0,0 +1,833 @@
+// ----------------------------------------------------------------------
+//                             SOFT PROOF
+// ----------------------------------------------------------------------
+
+use crate::pdf::{color_space_components, gray_level, page_content, page_media_box, PTS_PER_MM};
+use anyhow::{Context, Result};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, Stream};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// ขนาดช่องของตารางที่ใช้ประมาณพื้นที่หมึก (มม.)
+pub(crate) const PROOF_CELL_MM: f32 = 0.5;
+/// สัดส่วนหมึกโดยประมาณในกรอบของข้อความ (ตัวอักษรไม่ได้ทึบเต็มกรอบ)
+pub(crate) const TEXT_INK_RATIO: f32 = 0.25;
+/// ความเข้มที่ใช้กับรูปภาพที่ถอดข้อมูลไม่ได้ เช่น JPEG
+pub(crate) const UNKNOWN_IMAGE_DARKNESS: f32 = 0.5;
+/// พื้นที่หมึกที่มากกว่านี้อาจทำให้กระดาษม้วนหรือหมึกเลอะ (%)
+pub(crate) const HIGH_INK_COVERAGE_PERCENT: f32 = 40.0;
+/// ความละเอียดที่ใช้เมื่ออ่านจาก driver ไม่ได้
+pub(crate) const DEFAULT_PROOF_DPI: u32 = 300;
+/// ขอบที่เครื่องพิมพ์เลเซอร์ส่วนใหญ่พิมพ์ไม่ถึง (1/6 นิ้ว)
+pub(crate) const DEFAULT_HARDWARE_MARGIN_MM: f32 = 4.23;
+/// ความลึกสูงสุดของ Form XObject ที่ซ้อนกัน
+pub(crate) const MAX_FORM_DEPTH: usize = 8;
+
+/// ความละเอียดและขอบที่พิมพ์ไม่ถึงของเครื่องพิมพ์
+#[derive(Serialize, ToSchema, Clone, Copy)]
+pub(crate) struct PrinterProfile {
+    #[schema(example = 600)]
+    pub(crate) dpi: u32,
+    /// ขอบที่พิมพ์ไม่ถึง (มม.) ซ้าย ล่าง ขวา บน
+    #[schema(example = json!([4.23, 4.23, 4.23, 4.23]))]
+    pub(crate) margins_mm: [f32; 4],
+}
+
+impl Default for PrinterProfile {
+    fn default() -> Self {
+        PrinterProfile {
+            dpi: DEFAULT_PROOF_DPI,
+            margins_mm: [DEFAULT_HARDWARE_MARGIN_MM; 4],
+        }
+    }
+}
+
+/// ผลการตรวจก่อนพิมพ์ของหนึ่งหน้า
+#[derive(Serialize, ToSchema)]
+pub(crate) struct PageProof {
+    page: usize,
+    width_mm: f32,
+    height_mm: f32,
+    /// สัดส่วนพื้นที่หน้าที่มีหมึกโดยประมาณ ถ่วงด้วยความเข้มของสี (0 - 100)
+    #[schema(example = 12.5)]
+    ink_coverage_percent: f32,
+    /// จำนวนรูปทรง ข้อความ และรูปภาพที่ล้ำเข้าไปในขอบที่พิมพ์ไม่ถึงหรือเลยขอบกระดาษ
+    outside_printable_area: usize,
+    /// จำนวนเส้นที่บางกว่า 1 จุดของเครื่องพิมพ์ (รวมเส้นความกว้าง 0)
+    hairlines: usize,
+    /// ความกว้างของเส้นที่บางที่สุดบนหน้า (มม.)
+    #[schema(nullable)]
+    thinnest_stroke_mm: Option<f32>,
+    warnings: Vec<String>,
+}
+
+/// ผลการตรวจไฟล์ก่อนพิมพ์ (soft proof)
+#[derive(Serialize, ToSchema)]
+pub(crate) struct SoftProofReport {
+    /// เครื่องพิมพ์ที่ใช้ตรวจ (`null` = ใช้ค่าเริ่มต้น)
+    #[schema(example = "Office_LaserJet", nullable)]
+    pub(crate) printer_name: Option<String>,
+    profile: PrinterProfile,
+    /// ที่มาของ `profile`: `driver`, `request` หรือ `default`
+    #[schema(example = "driver")]
+    pub(crate) profile_source: String,
+    /// พื้นที่หมึกเฉลี่ยทุกหน้า (%)
+    #[schema(example = 8.4)]
+    pub(crate) average_ink_coverage_percent: f32,
+    pub(crate) pages: Vec<PageProof>,
+    pub(crate) warnings: Vec<String>,
+}
+
+/// graphics state ที่ใช้ในการตรวจ
+#[derive(Clone)]
+pub(crate) struct ProofState {
+    ctm: [f32; 6],
+    line_width: f32,
+    fill_darkness: f32,
+    stroke_darkness: f32,
+    /// ค่าสี 1 component ของ Separation/DeviceN เป็นปริมาณหมึก ไม่ใช่ความสว่าง
+    fill_is_tint: bool,
+    stroke_is_tint: bool,
+    font_size: f32,
+    /// font ปัจจุบันเป็น Type0 (ใช้ 2 ไบต์ต่อตัวอักษร)
+    two_byte_font: bool,
+    horizontal_scale: f32,
+    leading: f32,
+    character_spacing: f32,
+    invisible_text: bool,
+}
+
+impl Default for ProofState {
+    fn default() -> Self {
+        ProofState {
+            ctm: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            line_width: 1.0,
+            fill_darkness: 1.0,
+            stroke_darkness: 1.0,
+            fill_is_tint: false,
+            stroke_is_tint: false,
+            font_size: 0.0,
+            two_byte_font: false,
+            horizontal_scale: 1.0,
+            leading: 0.0,
+            character_spacing: 0.0,
+            invisible_text: false,
+        }
+    }
+}
+
+/// คูณ matrix ของ PDF (`m` ก่อนแล้วจึง `n`)
+pub(crate) fn multiply_matrix(m: [f32; 6], n: [f32; 6]) -> [f32; 6] {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+pub(crate) fn transform_point(m: [f32; 6], x: f32, y: f32) -> (f32, f32) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+/// กรอบของสี่เหลี่ยม `[x0, y0, x1, y1]` หลังแปลงด้วย `m`
+pub(crate) fn transform_box(m: [f32; 6], [x0, y0, x1, y1]: [f32; 4]) -> [f32; 4] {
+    let corners = [
+        transform_point(m, x0, y0),
+        transform_point(m, x1, y0),
+        transform_point(m, x0, y1),
+        transform_point(m, x1, y1),
+    ];
+    corners.iter().fold(
+        [f32::MAX, f32::MAX, f32::MIN, f32::MIN],
+        |[llx, lly, urx, ury], &(x, y)| [llx.min(x), lly.min(y), urx.max(x), ury.max(y)],
+    )
+}
+
+/// ความเข้มของสี (0 = ขาว, 1 = ทึบ) จากค่า component ของคำสั่งกำหนดสี
+pub(crate) fn color_darkness(values: &[f32], is_tint: bool) -> f32 {
+    match values {
+        [tint] if is_tint => tint.clamp(0.0, 1.0),
+        _ => gray_level(values).map_or(1.0, |gray| 1.0 - gray.clamp(0.0, 1.0)),
+    }
+}
+
+/// ความเข้มเฉลี่ยของรูปภาพ 8 bit แบบ Gray/RGB/CMYK (`None` = ถอดข้อมูลไม่ได้)
+pub(crate) fn image_darkness(doc: &Document, image: &Stream) -> Option<f32> {
+    if image.dict.get(b"BitsPerComponent").ok()?.as_i64().ok()? != 8 {
+        return None;
+    }
+    let components = color_space_components(doc, image.dict.get(b"ColorSpace").ok()?)?;
+    let data = image.get_plain_content().ok()?;
+    let pixels = data.chunks_exact(components);
+    let count = pixels.len();
+    if count == 0 {
+        return None;
+    }
+    let total: f32 = pixels
+        .map(|pixel| {
+            let values: Vec<f32> = pixel.iter().map(|&v| v as f32 / 255.0).collect();
+            color_darkness(&values, false)
+        })
+        .sum();
+    Some(total / count as f32)
+}
+
+/// หาทรัพยากรชื่อ `name` ในหมวด `category` (เช่น XObject, Font) จาก Resources ที่ใช้อยู่
+pub(crate) fn find_resource<'a>(
+    doc: &'a Document,
+    resources: &[&'a Dictionary],
+    category: &[u8],
+    name: &[u8],
+) -> Option<&'a Object> {
+    resources.iter().find_map(|resources| {
+        let (_, named) = doc.dereference(resources.get(category).ok()?).ok()?;
+        let (_, object) = doc
+            .dereference(named.as_dict().ok()?.get(name).ok()?)
+            .ok()?;
+        Some(object)
+    })
+}
+
+/// ตารางพื้นที่หมึกและผลการตรวจของหนึ่งหน้า
+pub(crate) struct ProofCanvas {
+    columns: usize,
+    rows: usize,
+    cell: f32,
+    cells: Vec<f32>,
+    page: [f32; 4],
+    printable: [f32; 4],
+    /// ขนาด 1 จุดของเครื่องพิมพ์ (point)
+    device_dot: f32,
+    outside: usize,
+    hairlines: usize,
+    thinnest: Option<f32>,
+    shadings: bool,
+}
+
+impl ProofCanvas {
+    fn new(page: [f32; 4], profile: &PrinterProfile) -> Self {
+        let cell = PROOF_CELL_MM * PTS_PER_MM;
+        let columns = (((page[2] - page[0]) / cell).ceil() as usize).max(1);
+        let rows = (((page[3] - page[1]) / cell).ceil() as usize).max(1);
+        let [left, bottom, right, top] = profile.margins_mm.map(|mm| mm * PTS_PER_MM);
+        ProofCanvas {
+            columns,
+            rows,
+            cell,
+            cells: vec![0.0; columns * rows],
+            page,
+            printable: [
+                page[0] + left,
+                page[1] + bottom,
+                page[2] - right,
+                page[3] - top,
+            ],
+            device_dot: 72.0 / profile.dpi.max(1) as f32,
+            outside: 0,
+            hairlines: 0,
+            thinnest: None,
+            shadings: false,
+        }
+    }
+
+    /// ระบายกรอบ `area` (พิกัดของหน้า) ด้วยความเข้ม `darkness` และตรวจว่าล้ำขอบที่พิมพ์ได้หรือไม่
+    fn paint(&mut self, area: [f32; 4], darkness: f32) {
+        if darkness <= 0.01 || area[0] > area[2] || area[1] > area[3] {
+            return;
+        }
+        // เผื่อค่าคลาดเคลื่อนจากการคำนวณ matrix เล็กน้อย
+        const TOLERANCE: f32 = 0.05;
+        if area[0] < self.printable[0] - TOLERANCE
+            || area[1] < self.printable[1] - TOLERANCE
+            || area[2] > self.printable[2] + TOLERANCE
+            || area[3] > self.printable[3] + TOLERANCE
+        {
+            self.outside += 1;
+        }
+
+        let to_cell = |value: f32, origin: f32, count: usize| {
+            (((value - origin) / self.cell).max(0.0) as usize).min(count)
+        };
+        let first_column = to_cell(area[0], self.page[0], self.columns);
+        let last_column = to_cell(area[2], self.page[0], self.columns - 1);
+        let first_row = to_cell(area[1], self.page[1], self.rows);
+        let last_row = to_cell(area[3], self.page[1], self.rows - 1);
+        for row in first_row..=last_row.min(self.rows - 1) {
+            for column in first_column..=last_column.min(self.columns - 1) {
+                // ช่องที่ถูกระบายบางส่วนนับตามสัดส่วนพื้นที่ที่ทับ
+                let x0 = self.page[0] + column as f32 * self.cell;
+                let y0 = self.page[1] + row as f32 * self.cell;
+                let overlap_x = (area[2].min(x0 + self.cell) - area[0].max(x0)).max(0.0);
+                let overlap_y = (area[3].min(y0 + self.cell) - area[1].max(y0)).max(0.0);
+                let fraction = overlap_x * overlap_y / (self.cell * self.cell);
+                let cell = &mut self.cells[row * self.columns + column];
+                *cell = cell.max(darkness * fraction.min(1.0));
+            }
+        }
+    }
+
+    /// ลากเส้นตรงจาก `a` ไป `b` ความกว้าง `width` (point)
+    fn stroke_segment(&mut self, a: (f32, f32), b: (f32, f32), width: f32, darkness: f32) {
+        let length = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+        let steps = ((length / (self.cell / 2.0)).ceil() as usize).max(1);
+        let half = width.max(self.device_dot) / 2.0;
+        // เส้นที่บางกว่าช่องของตารางใช้หมึกแค่บางส่วนของช่อง
+        let darkness = darkness * (2.0 * half / self.cell).min(1.0);
+        let outside = self.outside;
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let (x, y) = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+            self.paint([x - half, y - half, x + half, y + half], darkness);
+        }
+        // นับเส้นที่ล้ำขอบเป็นหนึ่งรายการ ไม่ใช่ตามจำนวนจุดที่สุ่ม
+        self.outside = outside + (self.outside > outside) as usize;
+    }
+
+    fn page_proof(&self, page: usize, profile: &PrinterProfile) -> PageProof {
+        let coverage = self.cells.iter().sum::<f32>() / self.cells.len() as f32 * 100.0;
+        let mut warnings = Vec::new();
+        if self.outside > 0 {
+            warnings.push(format!(
+                "{} elements extend into the unprintable margin or beyond the page edge",
+                self.outside
+            ));
+        }
+        if self.hairlines > 0 {
+            warnings.push(format!(
+                "{} strokes are thinner than one printer dot ({:.3} mm at {} dpi) and may disappear",
+                self.hairlines,
+                self.device_dot / PTS_PER_MM,
+                profile.dpi
+            ));
+        }
+        if coverage > HIGH_INK_COVERAGE_PERCENT {
+            warnings.push(format!(
+                "High ink coverage ({:.0}%) may cause curling, smearing or slow drying",
+                coverage
+            ));
+        }
+        if self.shadings {
+            warnings
+                .push("Shadings (gradients) are not included in the coverage estimate".to_string());
+        }
+        PageProof {
+            page,
+            width_mm: ((self.page[2] - self.page[0]) / PTS_PER_MM * 10.0).round() / 10.0,
+            height_mm: ((self.page[3] - self.page[1]) / PTS_PER_MM * 10.0).round() / 10.0,
+            ink_coverage_percent: (coverage * 10.0).round() / 10.0,
+            outside_printable_area: self.outside,
+            hairlines: self.hairlines,
+            thinnest_stroke_mm: self
+                .thinnest
+                .map(|width| (width / PTS_PER_MM * 1000.0).round() / 1000.0),
+            warnings,
+        }
+    }
+}
+
+/// ไล่คำสั่งใน content stream แล้วระบายลง `canvas` (เรียกซ้ำกับ Form XObject)
+pub(crate) fn proof_operations(
+    doc: &Document,
+    canvas: &mut ProofCanvas,
+    operations: &[Operation],
+    resources: &[&Dictionary],
+    state: &mut ProofState,
+    depth: usize,
+) {
+    let mut stack: Vec<ProofState> = Vec::new();
+    let mut subpaths: Vec<Vec<(f32, f32)>> = Vec::new();
+    let mut text_matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+    let mut line_matrix = text_matrix;
+
+    for operation in operations {
+        let numbers: Vec<f32> = operation
+            .operands
+            .iter()
+            .filter_map(|o| o.as_float().ok())
+            .collect();
+        let point = |i: usize| transform_point(state.ctm, numbers[i], numbers[i + 1]);
+        match (operation.operator.as_str(), numbers.len()) {
+            ("q", _) => stack.push(state.clone()),
+            ("Q", _) => {
+                if let Some(saved) = stack.pop() {
+                    *state = saved;
+                }
+            }
+            ("cm", 6) => {
+                let m = [
+                    numbers[0], numbers[1], numbers[2], numbers[3], numbers[4], numbers[5],
+                ];
+                state.ctm = multiply_matrix(m, state.ctm);
+            }
+            ("w", 1) => state.line_width = numbers[0],
+            ("g" | "rg" | "k", _) => {
+                state.fill_is_tint = false;
+                state.fill_darkness = color_darkness(&numbers, false);
+            }
+            ("G" | "RG" | "K", _) => {
+                state.stroke_is_tint = false;
+                state.stroke_darkness = color_darkness(&numbers, false);
+            }
+            ("cs" | "CS", _) => {
+                let is_tint = operation.operands.first().is_some_and(|name| {
+                    let space = match name.as_name() {
+                        Ok(name) => find_resource(doc, resources, b"ColorSpace", name)
+                            .unwrap_or(&operation.operands[0]),
+                        Err(_) => name,
+                    };
+                    let family = match space {
+                        Object::Array(array) => array.first().and_then(|o| o.as_name().ok()),
+                        other => other.as_name().ok(),
+                    };
+                    matches!(family, Some(b"Separation" | b"DeviceN"))
+                });
+                // สีเริ่มต้นของทุก color space คือสีดำหรือหมึกเต็ม
+                if operation.operator == "cs" {
+                    state.fill_is_tint = is_tint;
+                    state.fill_darkness = 1.0;
+                } else {
+                    state.stroke_is_tint = is_tint;
+                    state.stroke_darkness = 1.0;
+                }
+            }
+            ("sc" | "scn", n) if n > 0 => {
+                state.fill_darkness = color_darkness(&numbers, state.fill_is_tint)
+            }
+            ("SC" | "SCN", n) if n > 0 => {
+                state.stroke_darkness = color_darkness(&numbers, state.stroke_is_tint)
+            }
+            ("m", 2) => subpaths.push(vec![point(0)]),
+            ("l", 2) => match subpaths.last_mut() {
+                Some(subpath) => subpath.push(point(0)),
+                None => subpaths.push(vec![point(0)]),
+            },
+            // เส้นโค้งใช้จุดควบคุมแทนเส้นโค้งจริง ซึ่งอยู่ในกรอบเดียวกัน
+            ("c", 6) | ("v", 4) | ("y", 4) => {
+                let points: Vec<(f32, f32)> = (0..numbers.len()).step_by(2).map(point).collect();
+                match subpaths.last_mut() {
+                    Some(subpath) => subpath.extend(points),
+                    None => subpaths.push(points),
+                }
+            }
+            ("re", 4) => {
+                let (x, y, w, h) = (numbers[0], numbers[1], numbers[2], numbers[3]);
+                let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h), (x, y)];
+                subpaths.push(
+                    corners
+                        .iter()
+                        .map(|&(px, py)| transform_point(state.ctm, px, py))
+                        .collect(),
+                );
+            }
+            ("h", _) => {
+                if let Some(subpath) = subpaths.last_mut() {
+                    if let Some(&first) = subpath.first() {
+                        subpath.push(first);
+                    }
+                }
+            }
+            ("f" | "F" | "f*" | "S" | "s" | "B" | "B*" | "b" | "b*" | "n", _) => {
+                let operator = operation.operator.as_str();
+                let fills = !matches!(operator, "S" | "s" | "n");
+                let strokes = matches!(operator, "S" | "s" | "B" | "B*" | "b" | "b*");
+                if fills {
+                    let area = subpaths.iter().flatten().fold(
+                        [f32::MAX, f32::MAX, f32::MIN, f32::MIN],
+                        |[llx, lly, urx, ury], &(x, y)| {
+                            [llx.min(x), lly.min(y), urx.max(x), ury.max(y)]
+                        },
+                    );
+                    canvas.paint(area, state.fill_darkness);
+                }
+                if strokes && state.stroke_darkness > 0.01 {
+                    let scale = (state.ctm[0] * state.ctm[3] - state.ctm[1] * state.ctm[2])
+                        .abs()
+                        .sqrt();
+                    let width = state.line_width * scale;
+                    if width < canvas.device_dot * 0.999 {
+                        canvas.hairlines += 1;
+                    }
+                    canvas.thinnest = Some(canvas.thinnest.map_or(width, |w: f32| w.min(width)));
+                    let outside = canvas.outside;
+                    for subpath in &subpaths {
+                        for pair in subpath.windows(2) {
+                            canvas.stroke_segment(pair[0], pair[1], width, state.stroke_darkness);
+                        }
+                    }
+                    canvas.outside = outside + (canvas.outside > outside) as usize;
+                }
+                subpaths.clear();
+            }
+            ("BT", _) => {
+                text_matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+                line_matrix = text_matrix;
+            }
+            ("Tf", 1) => {
+                state.font_size = numbers[0];
+                state.two_byte_font = operation
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| find_resource(doc, resources, b"Font", name))
+                    .and_then(|font| font.as_dict().ok())
+                    .and_then(|font| font.get(b"Subtype").ok())
+                    .and_then(|subtype| subtype.as_name().ok())
+                    == Some(b"Type0");
+            }
+            ("Tz", 1) => state.horizontal_scale = numbers[0] / 100.0,
+            ("TL", 1) => state.leading = numbers[0],
+            ("Tc", 1) => state.character_spacing = numbers[0],
+            ("Tr", 1) => state.invisible_text = numbers[0] as i64 == 3 || numbers[0] as i64 == 7,
+            ("Td" | "TD", 2) => {
+                if operation.operator == "TD" {
+                    state.leading = -numbers[1];
+                }
+                line_matrix =
+                    multiply_matrix([1.0, 0.0, 0.0, 1.0, numbers[0], numbers[1]], line_matrix);
+                text_matrix = line_matrix;
+            }
+            ("Tm", 6) => {
+                text_matrix = [
+                    numbers[0], numbers[1], numbers[2], numbers[3], numbers[4], numbers[5],
+                ];
+                line_matrix = text_matrix;
+            }
+            ("T*" | "'" | "\"" | "Tj" | "TJ", _) => {
+                if matches!(operation.operator.as_str(), "T*" | "'" | "\"") {
+                    line_matrix =
+                        multiply_matrix([1.0, 0.0, 0.0, 1.0, 0.0, -state.leading], line_matrix);
+                    text_matrix = line_matrix;
+                }
+                if operation.operator == "T*" {
+                    continue;
+                }
+                // ความกว้างของตัวอักษรประมาณครึ่งหนึ่งของขนาดฟอนต์ (ไม่ได้อ่านตาราง Widths)
+                let mut advance = 0.0;
+                for operand in &operation.operands {
+                    match operand {
+                        Object::String(bytes, _) => {
+                            let glyphs = if state.two_byte_font {
+                                bytes.len() / 2
+                            } else {
+                                bytes.len()
+                            } as f32;
+                            advance += glyphs * (state.font_size * 0.5 + state.character_spacing);
+                        }
+                        Object::Array(items) => {
+                            for item in items {
+                                match item {
+                                    Object::String(bytes, _) => {
+                                        let glyphs = if state.two_byte_font {
+                                            bytes.len() / 2
+                                        } else {
+                                            bytes.len()
+                                        }
+                                            as f32;
+                                        advance += glyphs
+                                            * (state.font_size * 0.5 + state.character_spacing);
+                                    }
+                                    other => {
+                                        if let Ok(adjust) = other.as_float() {
+                                            advance -= adjust / 1000.0 * state.font_size;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                let advance = advance * state.horizontal_scale;
+                if !state.invisible_text && advance > 0.0 {
+                    let text_space = multiply_matrix(text_matrix, state.ctm);
+                    let area = transform_box(
+                        text_space,
+                        [0.0, -0.2 * state.font_size, advance, 0.8 * state.font_size],
+                    );
+                    canvas.paint(area, state.fill_darkness * TEXT_INK_RATIO);
+                }
+                text_matrix = multiply_matrix([1.0, 0.0, 0.0, 1.0, advance, 0.0], text_matrix);
+            }
+            ("BI" | "EI", _) => {
+                canvas.paint(
+                    transform_box(state.ctm, [0.0, 0.0, 1.0, 1.0]),
+                    UNKNOWN_IMAGE_DARKNESS,
+                );
+            }
+            ("sh", _) => canvas.shadings = true,
+            ("Do", _) => {
+                let Some(stream) = operation
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| find_resource(doc, resources, b"XObject", name))
+                    .and_then(|object| object.as_stream().ok())
+                else {
+                    continue;
+                };
+                match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+                    Ok(b"Image") => {
+                        let is_mask = stream
+                            .dict
+                            .get(b"ImageMask")
+                            .and_then(Object::as_bool)
+                            .unwrap_or(false);
+                        let darkness = if is_mask {
+                            state.fill_darkness
+                        } else {
+                            image_darkness(doc, stream).unwrap_or(UNKNOWN_IMAGE_DARKNESS)
+                        };
+                        canvas.paint(transform_box(state.ctm, [0.0, 0.0, 1.0, 1.0]), darkness);
+                    }
+                    Ok(b"Form") if depth < MAX_FORM_DEPTH => {
+                        let Ok(content) = stream
+                            .decompressed_content()
+                            .or_else(|_| Ok::<_, lopdf::Error>(stream.content.clone()))
+                            .and_then(|data| Content::decode(&data))
+                        else {
+                            continue;
+                        };
+                        let matrix = stream
+                            .dict
+                            .get(b"Matrix")
+                            .and_then(Object::as_array)
+                            .ok()
+                            .map(|values| {
+                                values
+                                    .iter()
+                                    .filter_map(|v| v.as_float().ok())
+                                    .collect::<Vec<_>>()
+                            })
+                            .filter(|values| values.len() == 6)
+                            .map_or([1.0, 0.0, 0.0, 1.0, 0.0, 0.0], |v| {
+                                [v[0], v[1], v[2], v[3], v[4], v[5]]
+                            });
+                        let form_resources = stream
+                            .dict
+                            .get(b"Resources")
+                            .and_then(|o| doc.dereference(o))
+                            .and_then(|(_, o)| o.as_dict())
+                            .ok();
+                        let nested: Vec<&Dictionary> = match form_resources {
+                            Some(form_resources) => vec![form_resources],
+                            None => resources.to_vec(),
+                        };
+                        let mut form_state = state.clone();
+                        form_state.ctm = multiply_matrix(matrix, state.ctm);
+                        proof_operations(
+                            doc,
+                            canvas,
+                            &content.operations,
+                            &nested,
+                            &mut form_state,
+                            depth + 1,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// ตรวจไฟล์ PDF `data` ก่อนพิมพ์ด้วยเครื่องพิมพ์ `profile`: ส่วนที่ล้ำขอบที่พิมพ์ได้ พื้นที่หมึกโดยประมาณ
+/// และเส้นที่บางกว่าความละเอียดของเครื่องพิมพ์ (ประมาณจากกรอบของรูปทรง ไม่ได้ render จริง)
+pub(crate) fn soft_proof(data: &[u8], profile: &PrinterProfile) -> Result<SoftProofReport> {
+    let doc = Document::load_mem(data).context("Failed to parse PDF")?;
+    let mut pages = Vec::new();
+    for (page_number, page_id) in doc.get_pages() {
+        let (inherited, resource_ids) = doc.get_page_resources(page_id)?;
+        let resources: Vec<&Dictionary> = inherited
+            .into_iter()
+            .chain(
+                resource_ids
+                    .iter()
+                    .filter_map(|id| doc.get_dictionary(*id).ok()),
+            )
+            .collect();
+        let content = Content::decode(&page_content(&doc, page_id))
+            .with_context(|| format!("Failed to decode content of page {}", page_number))?;
+        let mut canvas = ProofCanvas::new(page_media_box(&doc, page_id), profile);
+        proof_operations(
+            &doc,
+            &mut canvas,
+            &content.operations,
+            &resources,
+            &mut ProofState::default(),
+            0,
+        );
+        pages.push(canvas.page_proof(page_number as usize, profile));
+    }
+
+    let average = pages
+        .iter()
+        .map(|page| page.ink_coverage_percent)
+        .sum::<f32>()
+        / pages.len().max(1) as f32;
+    let mut warnings = Vec::new();
+    let flagged = |count: fn(&PageProof) -> usize| -> Vec<String> {
+        pages
+            .iter()
+            .filter(|page| count(page) > 0)
+            .map(|page| page.page.to_string())
+            .collect()
+    };
+    let outside = flagged(|page| page.outside_printable_area);
+    if !outside.is_empty() {
+        warnings.push(format!(
+            "Content outside the printable area on pages {}",
+            outside.join(", ")
+        ));
+    }
+    let hairlines = flagged(|page| page.hairlines);
+    if !hairlines.is_empty() {
+        warnings.push(format!(
+            "Hairlines below {} dpi on pages {}",
+            profile.dpi,
+            hairlines.join(", ")
+        ));
+    }
+    Ok(SoftProofReport {
+        printer_name: None,
+        profile: *profile,
+        profile_source: "default".to_string(),
+        average_ink_coverage_percent: (average * 10.0).round() / 10.0,
+        pages,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::tests::test_pdf;
+    use lopdf::dictionary;
+
+    const A4: [f32; 2] = [595.0, 842.0];
+
+    fn proof(content: &str) -> SoftProofReport {
+        soft_proof(&test_pdf(&[(A4, 0)], content), &PrinterProfile::default()).unwrap()
+    }
+
+    #[test]
+    fn ink_coverage_follows_area_and_darkness() {
+        let report = proof("0 0 0 rg 100 100 200 200 re f");
+        let page = &report.pages[0];
+        assert_eq!((page.width_mm, page.height_mm), (209.9, 297.0));
+        // 200 x 200 pt บนหน้า A4 = 8% ของพื้นที่
+        assert!(
+            (page.ink_coverage_percent - 8.0).abs() < 0.2,
+            "{}",
+            page.ink_coverage_percent
+        );
+        assert_eq!(page.outside_printable_area, 0);
+        assert_eq!(page.hairlines, 0);
+        assert!(page.warnings.is_empty() && report.warnings.is_empty());
+
+        let gray = proof("0.5 g 100 100 200 200 re f");
+        assert!((gray.pages[0].ink_coverage_percent - 4.0).abs() < 0.2);
+        assert_eq!(
+            proof("1 g 100 100 200 200 re f").pages[0].ink_coverage_percent,
+            0.0
+        );
+
+        let full = proof("0 0 0 rg 0 0 595 842 re f");
+        // ช่องของตารางที่เลยขอบหน้าออกไปไม่ถูกระบาย
+        assert!(full.pages[0].ink_coverage_percent > 99.5);
+        assert!(full.pages[0]
+            .warnings
+            .iter()
+            .any(|w| w.starts_with("High ink coverage")));
+    }
+
+    #[test]
+    fn margins_and_hairlines_are_reported_per_page() {
+        let report = proof("0 0 0 rg 2 2 50 50 re f 0 w 100 400 m 300 400 l S");
+        let page = &report.pages[0];
+        assert_eq!(page.outside_printable_area, 1);
+        assert_eq!(page.hairlines, 1);
+        assert_eq!(page.thinnest_stroke_mm, Some(0.0));
+        assert_eq!(
+            report.warnings,
+            [
+                "Content outside the printable area on pages 1",
+                "Hairlines below 300 dpi on pages 1"
+            ]
+        );
+
+        // เส้นกว้าง 1 pt หนากว่า 1 จุดที่ 300 dpi และขอบที่พิมพ์ไม่ถึงเป็น 0 ตาม profile
+        let profile = PrinterProfile {
+            dpi: 300,
+            margins_mm: [0.0; 4],
+        };
+        let data = test_pdf(
+            &[(A4, 0)],
+            "0 0 0 rg 2 2 50 50 re f 1 w 100 400 m 300 400 l S",
+        );
+        let page = &soft_proof(&data, &profile).unwrap().pages[0];
+        assert_eq!((page.outside_printable_area, page.hairlines), (0, 0));
+        assert_eq!(page.thinnest_stroke_mm, Some(0.353));
+    }
+
+    #[test]
+    fn self_referencing_forms_stop_at_the_depth_limit() {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let form_id = doc.new_object_id();
+        doc.objects.insert(
+            form_id,
+            Object::Stream(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Form",
+                    "BBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+                    "Resources" => dictionary! { "XObject" => dictionary! { "Fx" => form_id } },
+                },
+                b"0 0 0 rg 100 100 200 200 re f /Fx Do".to_vec(),
+            )),
+        );
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"/Fx Do".to_vec()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! { "XObject" => dictionary! { "Fx" => form_id } },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+
+        let report = soft_proof(&data, &PrinterProfile::default()).unwrap();
+        assert!((report.pages[0].ink_coverage_percent - 8.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn unreadable_files_are_errors() {
+        let e = soft_proof(b"not a pdf", &PrinterProfile::default())
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "Failed to parse PDF");
+        for data in [&b""[..], b"%PDF-1.5\n1 0 obj\n<< /Type /Catalog >>\n"] {
+            let e = soft_proof(data, &PrinterProfile::default()).err().unwrap();
+            assert_eq!(e.to_string(), "Failed to parse PDF");
+        }
+        // content ที่อ่านไม่ออกข้ามไปโดยไม่นับหมึก
+        let report = proof("BT (unterminated");
+        assert_eq!(report.pages[0].ink_coverage_percent, 0.0);
+    }
+}
@@ -0,0 +1,200 @@
+// ----------------------------------------------------------------------
+//                           RASTER FALLBACK
+// ----------------------------------------------------------------------
+
+use crate::{backend::print_backend, ipp::IPP_DRIVER_NAME, proof::DEFAULT_PROOF_DPI};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// ระยะเวลาสูงสุดที่รอ Ghostscript แปลงงานหนึ่งงาน
+pub(crate) const RASTERIZE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// การตั้งค่าของเครื่องพิมพ์หนึ่งเครื่องใน `printer_profiles`
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct PrinterProfileConfig {
+    /// แปลงไฟล์ที่ส่งพิมพ์เป็นภาพก่อนส่ง สำหรับเครื่องพิมพ์ที่พิมพ์ PDF แบบ vector ผิดเพี้ยน (ไม่ระบุ = ส่ง PDF เดิม)
+    pub(crate) rasterize: Option<RasterizeConfig>,
+}
+
+/// การแปลงไฟล์ PDF เป็นภาพที่ความละเอียดของเครื่องพิมพ์ด้วย Ghostscript
+#[derive(Deserialize, Clone)]
+pub(crate) struct RasterizeConfig {
+    /// ความละเอียด (DPI) ไม่ระบุ = ความละเอียดของเครื่องพิมพ์จาก driver (อ่านไม่ได้ = 300)
+    #[serde(default)]
+    dpi: Option<u32>,
+    /// รูปแบบไฟล์ที่ส่ง: `pdf` (PDF ที่แต่ละหน้าเป็นภาพ, ค่าเริ่มต้น) หรือ `pwg_raster` (PWG Raster)
+    #[serde(default)]
+    pub(crate) format: RasterFormat,
+    /// แปลงเป็นภาพสี (ไม่ระบุ = สีเทา ซึ่งเหมาะกับเครื่องพิมพ์ฉลากและเล็กกว่า)
+    #[serde(default)]
+    color: bool,
+}
+
+/// รูปแบบไฟล์ที่ได้จาก `rasterize`
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RasterFormat {
+    /// PDF ที่แต่ละหน้าเป็นภาพ (ส่งผ่าน spooler ได้เหมือน PDF ปกติ)
+    #[default]
+    Pdf,
+    /// PWG Raster สำหรับเครื่องพิมพ์ IPP Everywhere หรือ driver ที่รับ raster โดยตรง
+    PwgRaster,
+}
+
+impl RasterFormat {
+    /// `document-format` ของ IPP (`None` = PDF)
+    pub(crate) fn document_format(self) -> Option<&'static str> {
+        match self {
+            RasterFormat::Pdf => None,
+            RasterFormat::PwgRaster => Some("image/pwg-raster"),
+        }
+    }
+}
+
+/// ตรวจค่า `printer_profiles` ตอนโหลด config.json
+pub(crate) fn validate_printer_profiles(
+    profiles: &HashMap<String, PrinterProfileConfig>,
+) -> Result<()> {
+    for (name, profile) in profiles {
+        if let Some(dpi) = profile.rasterize.as_ref().and_then(|raster| raster.dpi) {
+            if !(72..=2400).contains(&dpi) {
+                bail!(
+                    "printer_profiles.{}.rasterize.dpi must be 72 to 2400 (got {})",
+                    name,
+                    dpi
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ความละเอียดที่ใช้แปลงงานของ `printer` ตาม `raster.dpi` หรือความละเอียดจาก driver
+pub(crate) fn raster_dpi(
+    printer: &printers::common::base::printer::Printer,
+    raster: &RasterizeConfig,
+) -> u32 {
+    if let Some(dpi) = raster.dpi {
+        return dpi;
+    }
+    if printer.driver_name == IPP_DRIVER_NAME {
+        return DEFAULT_PROOF_DPI;
+    }
+    match print_backend().profile(printer) {
+        Ok(profile) => profile.dpi,
+        Err(e) => {
+            eprintln!(
+                "Could not read the resolution of {} ({:#}); rasterizing at {} DPI",
+                printer.name, e, DEFAULT_PROOF_DPI
+            );
+            DEFAULT_PROOF_DPI
+        }
+    }
+}
+
+/// แปลงไฟล์ PDF `data` เป็นภาพที่ `dpi` ด้วย Ghostscript โดยส่งไฟล์ทาง stdin และรับผลลัพธ์ทาง stdout
+pub(crate) fn rasterize_pdf(
+    data: &[u8],
+    raster: &RasterizeConfig,
+    dpi: u32,
+    ghostscript: Option<&Path>,
+) -> Result<Vec<u8>> {
+    let program = ghostscript.map_or_else(
+        || OsString::from(if cfg!(windows) { "gswin64c" } else { "gs" }),
+        |path| path.as_os_str().to_owned(),
+    );
+    let device: &[&str] = match (raster.format, raster.color) {
+        (RasterFormat::Pdf, false) => &["-sDEVICE=pdfimage8"],
+        (RasterFormat::Pdf, true) => &["-sDEVICE=pdfimage24"],
+        // cupsColorSpace 18 = sGray, 19 = sRGB
+        (RasterFormat::PwgRaster, false) => &[
+            "-sDEVICE=pwgraster",
+            "-dcupsColorSpace=18",
+            "-dcupsBitsPerColor=8",
+        ],
+        (RasterFormat::PwgRaster, true) => &[
+            "-sDEVICE=pwgraster",
+            "-dcupsColorSpace=19",
+            "-dcupsBitsPerColor=8",
+        ],
+    };
+    let mut child = std::process::Command::new(&program)
+        // stdout ของ PostScript ถูกย้ายไป stderr เพื่อให้ stdout มีเฉพาะไฟล์ผลลัพธ์
+        .args(["-q", "-dSAFER", "-dBATCH", "-dNOPAUSE", "-sstdout=%stderr"])
+        .args(device)
+        .arg(format!("-r{}", dpi))
+        .args(["-sOutputFile=-", "-"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| {
+            format!(
+                "Failed to start Ghostscript ({})",
+                program.to_string_lossy()
+            )
+        })?;
+
+    // อ่านและเขียน pipe ใน thread แยก เพื่อไม่ให้ติดเมื่อ buffer ของ pipe เต็ม
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Ghostscript stdin is not available")?;
+    let input = data.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let mut stdout = child
+        .stdout
+        .take()
+        .context("Ghostscript stdout is not available")?;
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let mut stderr = child
+        .stderr
+        .take()
+        .context("Ghostscript stderr is not available")?;
+    let errors = std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output);
+        output
+    });
+
+    let deadline = Instant::now() + RASTERIZE_TIMEOUT;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "Ghostscript did not finish within {} seconds",
+                RASTERIZE_TIMEOUT.as_secs()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let _ = writer.join();
+    let output = reader
+        .join()
+        .map_err(|_| anyhow::anyhow!("Ghostscript output reader panicked"))??;
+    let errors = errors.join().unwrap_or_default();
+    if !status.success() || output.is_empty() {
+        bail!("Ghostscript failed ({}): {}", status, errors.trim());
+    }
+    println!(
+        "Rasterized {} bytes to {} bytes at {} DPI ({:?})",
+        data.len(),
+        output.len(),
+        dpi,
+        raster.format
+    );
+    Ok(output)
+}
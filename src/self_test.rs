@@ -0,0 +1,277 @@
+// ----------------------------------------------------------------------
+//                         STARTUP SELF-TEST
+// ----------------------------------------------------------------------
+
+use crate::{
+    backend::print_backend,
+    config::{AppConfig, SharedConfig},
+    dispatch::PrintDispatcher,
+    event_log::{report_event, EventLevel},
+    ipp::{parse_ipp_uri, IPP_DRIVER_NAME},
+    job_settings::{resolve_printer, submit_print_job, JobSettings},
+    notify::PRINTER_HEALTH_INTERVAL,
+    pdf::{A6_HEIGHT_PTS, A6_WIDTH_PTS, PTS_PER_MM},
+    test_page::text_operations,
+};
+use actix_web::web;
+use anyhow::Result;
+use lopdf::content::Content;
+use lopdf::{dictionary, Document, Object, Stream};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// เวลาสูงสุดที่รอเชื่อมต่อเครื่องพิมพ์ IPP ตอนตรวจความพร้อม
+pub(crate) const PRINTER_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// การตรวจเครื่องพิมพ์ตอนเริ่ม Service เพื่อให้รู้ว่าเครื่องพิมพ์ใดใช้งานไม่ได้ก่อนงานแรกของวัน
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct StartupCheckConfig {
+    /// เครื่องพิมพ์ที่ตรวจ (ไม่ระบุ = `default_printer`, `ipp_printers` และเครื่องพิมพ์ของทุก tenant
+    /// หรือเครื่องพิมพ์เริ่มต้นของระบบหากไม่ได้ตั้งค่าเครื่องพิมพ์ไว้เลย)
+    printers: Vec<String>,
+    /// พิมพ์ใบ warm-up ขนาด A6 ที่สรุปผลการตรวจไปยังเครื่องพิมพ์ที่ใช้งานได้
+    warm_up: bool,
+}
+
+/// เครื่องพิมพ์ที่ตรวจแล้วใช้งานไม่ได้ (degraded) พร้อมเหตุผล แสดงใน `GET /readyz`
+#[derive(Default)]
+pub(crate) struct PrinterReadiness {
+    degraded: RwLock<BTreeMap<String, String>>,
+}
+
+impl PrinterReadiness {
+    pub(crate) fn degraded(&self) -> BTreeMap<String, String> {
+        self.degraded.read().unwrap().clone()
+    }
+}
+
+/// เครื่องพิมพ์ที่ตรวจตอนเริ่ม Service ตาม `startup_check.printers`
+pub(crate) fn startup_check_printers(
+    config: &AppConfig,
+    check: &StartupCheckConfig,
+) -> Vec<String> {
+    if !check.printers.is_empty() {
+        return check.printers.clone();
+    }
+    let mut names: BTreeSet<String> = config.default_printer.iter().cloned().collect();
+    names.extend(config.ipp_printers.keys().cloned());
+    for tenant in config.tenants.values() {
+        names.extend(tenant.printers.iter().cloned());
+        names.extend(tenant.default_printer.iter().cloned());
+    }
+    if names.is_empty() {
+        names.extend(
+            print_backend()
+                .default_printer()
+                .map(|printer| printer.name),
+        );
+    }
+    names.into_iter().collect()
+}
+
+/// ตรวจว่าเครื่องพิมพ์ `name` รับงานได้หรือไม่ (เครื่องพิมพ์ IPP ตรวจจากการเชื่อมต่อ TCP)
+pub(crate) fn probe_printer(
+    name: &str,
+    config: &AppConfig,
+) -> std::result::Result<printers::common::base::printer::Printer, String> {
+    use printers::common::base::printer::PrinterState;
+
+    let printer = resolve_printer(Some(name), config)?;
+    if printer.driver_name == IPP_DRIVER_NAME {
+        let server = parse_ipp_uri(&printer.uri).map_err(|e| e.to_string())?;
+        let address = (server.host.as_str(), server.port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or_else(|| format!("Failed to resolve {} of printer {}", server.host, name))?;
+        TcpStream::connect_timeout(&address, PRINTER_PROBE_TIMEOUT).map_err(|e| {
+            format!(
+                "Printer {} is unreachable at {}:{}: {}",
+                name, server.host, server.port, e
+            )
+        })?;
+        return Ok(printer);
+    }
+    match printer.state {
+        PrinterState::OFFLINE => Err(format!("Printer {} is offline", name)),
+        PrinterState::PAUSED => Err(format!("Printer {} is paused", name)),
+        _ => Ok(printer),
+    }
+}
+
+/// สร้างใบ warm-up ขนาด A6 ที่มีชื่อเครื่องพิมพ์ เวลา และผลการตรวจเครื่องพิมพ์ทั้งหมด
+pub(crate) fn build_warm_up_slip(
+    printer_name: &str,
+    checked: usize,
+    degraded: &BTreeMap<String, String>,
+) -> Result<Document> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let regular_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+    let bold_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica-Bold",
+    });
+
+    let left = 10.0 * PTS_PER_MM;
+    let mut y = A6_HEIGHT_PTS - 20.0 * PTS_PER_MM;
+    let mut operations = text_operations("F2", 14.0, left, y, "Rust Print API - Warm-up");
+    let mut lines = vec![
+        format!("Printer: {}", printer_name),
+        format!(
+            "Started at: {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+        ),
+        format!("Version: {}", env!("CARGO_PKG_VERSION")),
+        format!(
+            "Printers ready: {} of {}",
+            checked - degraded.len(),
+            checked
+        ),
+    ];
+    lines.extend(degraded.values().map(|reason| format!("- {}", reason)));
+    for line in lines {
+        y -= 16.0;
+        // ข้อความบน PDF ใช้ฟอนต์มาตรฐานซึ่งไม่มีตัวอักษรอื่นนอกจาก ASCII
+        let line: String = line
+            .chars()
+            .map(|c| if c.is_ascii() { c } else { '?' })
+            .collect();
+        operations.extend(text_operations("F1", 8.0, left, y, &line));
+    }
+
+    let content = Content { operations };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode()?));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), A6_WIDTH_PTS.into(), A6_HEIGHT_PTS.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! {
+                "F1" => regular_id,
+                "F2" => bold_id,
+            },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    Ok(doc)
+}
+
+/// ตรวจเครื่องพิมพ์ตาม `startup_check` บันทึกผลลง Event Log และพิมพ์ใบ warm-up
+/// จากนั้นตรวจเครื่องพิมพ์ที่ใช้งานไม่ได้ซ้ำทุกนาทีจนกว่าจะกลับมาใช้งานได้ (ทำงานใน thread แยก)
+pub(crate) fn run_startup_check(
+    config: web::Data<SharedConfig>,
+    readiness: web::Data<PrinterReadiness>,
+    dispatcher: web::Data<PrintDispatcher>,
+) {
+    let current = config.get();
+    let Some(check) = &current.startup_check else {
+        return;
+    };
+    let names = startup_check_printers(&current, check);
+    let mut ready = Vec::new();
+    let mut degraded = BTreeMap::new();
+    for name in &names {
+        match probe_printer(name, &current) {
+            Ok(printer) => ready.push(printer),
+            Err(reason) => {
+                degraded.insert(name.clone(), reason);
+            }
+        }
+    }
+    *readiness.degraded.write().unwrap() = degraded.clone();
+
+    let summary = format!(
+        "Startup self-test: {} of {} printers ready",
+        ready.len(),
+        names.len()
+    );
+    if degraded.is_empty() {
+        report_event(EventLevel::Information, &summary);
+    } else {
+        // ไม่มีเครื่องพิมพ์ใดใช้งานได้เลย = ยังพิมพ์งานไม่ได้
+        let level = match ready.is_empty() {
+            true => EventLevel::Error,
+            false => EventLevel::Warning,
+        };
+        let reasons: Vec<&str> = degraded.values().map(String::as_str).collect();
+        report_event(
+            level,
+            &format!("{}; degraded: {}", summary, reasons.join("; ")),
+        );
+    }
+
+    if check.warm_up {
+        for printer in &ready {
+            let mut file_data = Vec::new();
+            let sent = build_warm_up_slip(&printer.name, names.len(), &degraded)
+                .and_then(|mut doc| Ok(doc.save_to(&mut file_data)?))
+                .map_err(|e| e.to_string())
+                .and_then(|()| {
+                    dispatcher
+                        .reserve(printer)
+                        .submit(|| {
+                            submit_print_job(
+                                printer,
+                                file_data,
+                                "Warm-up Slip",
+                                &JobSettings::default(),
+                            )
+                        })
+                        .map_err(str::to_string)
+                });
+            match sent {
+                Ok(_) => println!("Warm-up slip sent to {}", printer.name),
+                Err(e) => {
+                    report_event(
+                        EventLevel::Warning,
+                        &format!(
+                            "Failed to print the warm-up slip on {}: {}",
+                            printer.name, e
+                        ),
+                    );
+                    readiness.degraded.write().unwrap().insert(
+                        printer.name.clone(),
+                        format!("Printer {} did not accept the warm-up slip", printer.name),
+                    );
+                }
+            }
+        }
+    }
+
+    while !readiness.degraded().is_empty() {
+        std::thread::sleep(PRINTER_HEALTH_INTERVAL);
+        let current = config.get();
+        for name in readiness.degraded().into_keys() {
+            if probe_printer(&name, &current).is_ok() {
+                readiness.degraded.write().unwrap().remove(&name);
+                report_event(
+                    EventLevel::Information,
+                    &format!("Printer {} is ready again", name),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,325 @@
+// ----------------------------------------------------------------------
+//                             USER SESSIONS
+// ----------------------------------------------------------------------
+
+use crate::{config::AppConfig, job_settings::resolve_printer};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use windows_service::service::SessionChangeReason;
+
+/// ความยาวสูงสุดของ `windows_user` (`DOMAIN\user`)
+pub(crate) const MAX_WINDOWS_USER_CHARS: usize = 256;
+
+/// ข้อผิดพลาดของการส่งงานในนามของผู้ใช้ Windows (`windows_user`)
+#[derive(Debug)]
+pub(crate) enum UserContextError {
+    /// ไม่ได้เปิด `user_context_printing` ใน config.json
+    Disabled,
+    /// ระบบปฏิบัติการนี้ไม่รองรับ
+    Unsupported,
+    /// ผู้ใช้ไม่อยู่ใน `windows_users` ของ tenant
+    NotAllowed(String),
+    /// ผู้ใช้ไม่ได้เข้าสู่ระบบอยู่ในเครื่องนี้
+    NotLoggedOn(String),
+    /// สลับไปใช้สิทธิ์ของผู้ใช้ไม่สำเร็จ
+    Failed(String),
+}
+
+impl std::fmt::Display for UserContextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserContextError::Disabled => {
+                write!(
+                    f,
+                    "windows_user requires user_context_printing in config.json"
+                )
+            }
+            UserContextError::Unsupported => {
+                write!(f, "windows_user is only supported on Windows")
+            }
+            UserContextError::NotAllowed(user) => {
+                write!(
+                    f,
+                    "Windows user {} is not in windows_users of this tenant",
+                    user
+                )
+            }
+            UserContextError::NotLoggedOn(user) => {
+                write!(f, "Windows user {} is not logged on", user)
+            }
+            UserContextError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// ผู้ใช้ที่เข้าสู่ระบบ Windows อยู่ในแต่ละ session (`DOMAIN\user`) ตามหมายเลข session
+///
+/// อ่านจาก Remote Desktop Services ตอนเริ่มทำงาน ปรับตาม `SERVICE_CONTROL_SESSIONCHANGE` เมื่อรันเป็น Service
+/// และอ่านใหม่เมื่อหาผู้ใช้ไม่พบ (เช่น รันด้วย `--console` ซึ่งไม่ได้รับแจ้งเมื่อผู้ใช้เข้า/ออกจากระบบ)
+#[derive(Default)]
+pub(crate) struct UserSessions {
+    sessions: RwLock<BTreeMap<u32, String>>,
+}
+
+impl UserSessions {
+    /// อ่านรายชื่อ session ที่มีผู้ใช้เข้าสู่ระบบอยู่ใหม่ทั้งหมด
+    pub(crate) fn refresh(&self) {
+        *self.sessions.write().unwrap() = logged_on_sessions().into_iter().collect();
+    }
+
+    /// ปรับรายชื่อเมื่อ Service ได้รับแจ้งว่า session `session_id` เปลี่ยนแปลง
+    pub(crate) fn session_changed(&self, session_id: u32, reason: SessionChangeReason) {
+        match reason {
+            SessionChangeReason::SessionLogon
+            | SessionChangeReason::ConsoleConnect
+            | SessionChangeReason::RemoteConnect => {
+                let Some(user) = session_user(session_id) else {
+                    return;
+                };
+                let previous = self
+                    .sessions
+                    .write()
+                    .unwrap()
+                    .insert(session_id, user.clone());
+                if previous.as_ref() != Some(&user) {
+                    println!("Windows user {} logged on in session {}", user, session_id);
+                }
+            }
+            SessionChangeReason::SessionLogoff | SessionChangeReason::SessionTerminate => {
+                if let Some(user) = self.sessions.write().unwrap().remove(&session_id) {
+                    println!(
+                        "Windows user {} logged off from session {}",
+                        user, session_id
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// session ของผู้ใช้ `user` (`DOMAIN\user` หรือ `user` ของ domain ใดก็ได้ ไม่สนตัวพิมพ์เล็ก/ใหญ่)
+    /// หากผู้ใช้เข้าสู่ระบบหลาย session จะใช้ session ที่มีหมายเลขน้อยที่สุด
+    fn find(&self, user: &str) -> Option<u32> {
+        let lookup = |sessions: &BTreeMap<u32, String>| {
+            sessions
+                .iter()
+                .find(|(_, name)| {
+                    name.eq_ignore_ascii_case(user)
+                        || (!user.contains('\\')
+                            && name
+                                .rsplit('\\')
+                                .next()
+                                .is_some_and(|name| name.eq_ignore_ascii_case(user)))
+                })
+                .map(|(session_id, _)| *session_id)
+        };
+        if let Some(session_id) = lookup(&self.sessions.read().unwrap()) {
+            return Some(session_id);
+        }
+        self.refresh();
+        lookup(&self.sessions.read().unwrap())
+    }
+
+    /// รายชื่อ session ที่มีผู้ใช้เข้าสู่ระบบอยู่ สำหรับ diagnostics
+    pub(crate) fn list(&self) -> Vec<(u32, String)> {
+        self.sessions
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(session_id, user)| (*session_id, user.clone()))
+            .collect()
+    }
+
+    /// เรียก `f` ใน thread ปัจจุบันด้วยสิทธิ์ของผู้ใช้ `user` ที่เข้าสู่ระบบอยู่
+    /// เพื่อให้เห็นเครื่องพิมพ์ที่ผู้ใช้เชื่อมต่อไว้เอง และงานใน spooler เป็นของผู้ใช้ (เช่น คิว follow-me)
+    pub(crate) fn run_as<T>(
+        &self,
+        config: &AppConfig,
+        user: &str,
+        f: impl FnOnce() -> T,
+    ) -> std::result::Result<T, UserContextError> {
+        if !config.user_context_printing {
+            return Err(UserContextError::Disabled);
+        }
+        if !cfg!(windows) {
+            return Err(UserContextError::Unsupported);
+        }
+        let session_id = self
+            .find(user)
+            .ok_or_else(|| UserContextError::NotLoggedOn(user.to_string()))?;
+        impersonate_session(session_id, f).map_err(|e| {
+            eprintln!(
+                "Failed to impersonate {} in session {}: {}",
+                user, session_id, e
+            );
+            // session อาจจบไปแล้วโดยไม่ได้รับแจ้ง
+            self.refresh();
+            UserContextError::Failed(format!("Failed to run as Windows user {}: {}", user, e))
+        })
+    }
+}
+
+/// หาเครื่องพิมพ์ปลายทางเหมือน `resolve_printer` ในนามของ `windows_user` เมื่อระบุ
+pub(crate) fn resolve_printer_as(
+    printer_name: Option<&str>,
+    windows_user: Option<&str>,
+    config: &AppConfig,
+    sessions: &UserSessions,
+) -> std::result::Result<printers::common::base::printer::Printer, String> {
+    match windows_user {
+        Some(user) => sessions
+            .run_as(config, user, || resolve_printer(printer_name, config))
+            .map_err(|e| e.to_string())?,
+        None => resolve_printer(printer_name, config),
+    }
+}
+
+/// ชื่อผู้ใช้ (`DOMAIN\user`) ที่เข้าสู่ระบบอยู่ใน session `session_id` (`None` = ไม่มีผู้ใช้ เช่น session 0 หรือหน้า logon)
+#[cfg(windows)]
+pub(crate) fn session_user(session_id: u32) -> Option<String> {
+    #[link(name = "wtsapi32")]
+    extern "system" {
+        fn WTSQuerySessionInformationW(
+            server: *mut std::ffi::c_void,
+            session_id: u32,
+            info_class: i32,
+            buffer: *mut *mut u16,
+            bytes: *mut u32,
+        ) -> i32;
+        fn WTSFreeMemory(memory: *mut std::ffi::c_void);
+    }
+    const WTS_USER_NAME: i32 = 5;
+    const WTS_DOMAIN_NAME: i32 = 7;
+
+    let query = |info_class: i32| unsafe {
+        let mut buffer = std::ptr::null_mut();
+        let mut bytes = 0;
+        if WTSQuerySessionInformationW(
+            std::ptr::null_mut(),
+            session_id,
+            info_class,
+            &mut buffer,
+            &mut bytes,
+        ) == 0
+        {
+            return String::new();
+        }
+        let chars = std::slice::from_raw_parts(buffer, bytes as usize / 2);
+        let value = String::from_utf16_lossy(chars)
+            .trim_end_matches('\0')
+            .to_string();
+        WTSFreeMemory(buffer.cast());
+        value
+    };
+    let user = query(WTS_USER_NAME);
+    if user.is_empty() {
+        return None;
+    }
+    match query(WTS_DOMAIN_NAME) {
+        domain if domain.is_empty() => Some(user),
+        domain => Some(format!("{}\\{}", domain, user)),
+    }
+}
+
+/// session ทั้งหมดที่มีผู้ใช้เข้าสู่ระบบอยู่ (รวม session ที่ถูก disconnect)
+#[cfg(windows)]
+pub(crate) fn logged_on_sessions() -> Vec<(u32, String)> {
+    #[repr(C)]
+    struct SessionInfo {
+        session_id: u32,
+        station_name: *mut u16,
+        state: i32,
+    }
+    #[link(name = "wtsapi32")]
+    extern "system" {
+        fn WTSEnumerateSessionsW(
+            server: *mut std::ffi::c_void,
+            reserved: u32,
+            version: u32,
+            sessions: *mut *mut SessionInfo,
+            count: *mut u32,
+        ) -> i32;
+        fn WTSFreeMemory(memory: *mut std::ffi::c_void);
+    }
+
+    let mut sessions = std::ptr::null_mut();
+    let mut count = 0;
+    let ids: Vec<u32> = unsafe {
+        if WTSEnumerateSessionsW(std::ptr::null_mut(), 0, 1, &mut sessions, &mut count) == 0 {
+            eprintln!(
+                "Failed to enumerate Windows sessions: {}",
+                std::io::Error::last_os_error()
+            );
+            return Vec::new();
+        }
+        let ids = std::slice::from_raw_parts(sessions, count as usize)
+            .iter()
+            .map(|session| session.session_id)
+            .collect();
+        WTSFreeMemory(sessions.cast());
+        ids
+    };
+    ids.into_iter()
+        .filter_map(|session_id| session_user(session_id).map(|user| (session_id, user)))
+        .collect()
+}
+
+/// เรียก `f` ด้วย token ของผู้ใช้ใน session `session_id` (ต้องรันด้วย LocalSystem) แล้วกลับไปใช้สิทธิ์เดิม
+#[cfg(windows)]
+pub(crate) fn impersonate_session<T>(session_id: u32, f: impl FnOnce() -> T) -> std::io::Result<T> {
+    #[link(name = "wtsapi32")]
+    extern "system" {
+        fn WTSQueryUserToken(session_id: u32, token: *mut *mut std::ffi::c_void) -> i32;
+    }
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn ImpersonateLoggedOnUser(token: *mut std::ffi::c_void) -> i32;
+        fn RevertToSelf() -> i32;
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CloseHandle(handle: *mut std::ffi::c_void) -> i32;
+    }
+    /// กลับไปใช้สิทธิ์ของ Service เมื่อ drop (รวมถึงเมื่อ `f` panic)
+    struct Impersonation(*mut std::ffi::c_void);
+    impl Drop for Impersonation {
+        fn drop(&mut self) {
+            unsafe {
+                RevertToSelf();
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    let _impersonation = unsafe {
+        let mut token = std::ptr::null_mut();
+        if WTSQueryUserToken(session_id, &mut token) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if ImpersonateLoggedOnUser(token) == 0 {
+            let e = std::io::Error::last_os_error();
+            CloseHandle(token);
+            return Err(e);
+        }
+        Impersonation(token)
+    };
+    Ok(f())
+}
+
+#[cfg(not(windows))]
+pub(crate) fn session_user(_session_id: u32) -> Option<String> {
+    None
+}
+
+#[cfg(not(windows))]
+pub(crate) fn logged_on_sessions() -> Vec<(u32, String)> {
+    Vec::new()
+}
+
+#[cfg(not(windows))]
+pub(crate) fn impersonate_session<T>(
+    _session_id: u32,
+    _f: impl FnOnce() -> T,
+) -> std::io::Result<T> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
@@ -0,0 +1,201 @@
+// ----------------------------------------------------------------------
+//                            SPOOLER MONITOR
+// ----------------------------------------------------------------------
+
+use crate::{
+    backend::print_backend,
+    config::{AppConfig, SharedConfig},
+    dispatch::PrintDispatcher,
+    event_log::{report_event, EventLevel},
+    ipp::IPP_DRIVER_NAME,
+    notify::{send_email, send_notification},
+};
+use actix_web::web;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// ระยะเวลาระหว่างการตรวจสถานะของ spooler
+pub(crate) const SPOOLER_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// ช่วงที่ spooler หยุดทำงาน
+pub(crate) struct SpoolerOutage {
+    reason: String,
+    since: Instant,
+}
+
+/// สถานะของ Print Spooler (Windows) หรือ CUPS scheduler ล่าสุด ตรวจเมื่อเริ่มทำงานและทุก 5 วินาที
+///
+/// ระหว่างที่ spooler หยุดทำงาน งานของเครื่องพิมพ์ในเครื่องนี้จะถูกปฏิเสธด้วย HTTP 503 แทนข้อผิดพลาดของแต่ละงาน
+/// และ agent จะหยุดดึงงานจาก server (งานรอในคิวของ server) จนกว่า spooler จะกลับมาทำงาน
+#[derive(Default)]
+pub(crate) struct SpoolerMonitor {
+    outage: RwLock<Option<SpoolerOutage>>,
+}
+
+impl SpoolerMonitor {
+    /// ตรวจสถานะของ spooler ใหม่ บันทึกลง Event Log และแจ้งเตือนเมื่อสถานะเปลี่ยน
+    pub(crate) fn refresh(&self, config: &AppConfig) {
+        let state = match print_backend().uses_spooler() {
+            true => spooler_state(),
+            false => Ok(()),
+        };
+        let mut current = self.outage.write().unwrap();
+        match (current.as_mut(), state) {
+            (None, Err(reason)) => {
+                let message = format!(
+                    "{}; rejecting print jobs for local printers until it is running again",
+                    reason
+                );
+                report_event(EventLevel::Error, &message);
+                send_notification(&config.notifications, message.clone());
+                send_email(
+                    &config.notifications,
+                    "Print spooler is not running".to_string(),
+                    message,
+                );
+                *current = Some(SpoolerOutage {
+                    reason,
+                    since: Instant::now(),
+                });
+            }
+            (Some(outage), Ok(())) => {
+                let message = format!(
+                    "Print spooler is running again after {} seconds; accepting print jobs",
+                    outage.since.elapsed().as_secs()
+                );
+                report_event(EventLevel::Information, &message);
+                send_notification(&config.notifications, message);
+                *current = None;
+            }
+            // เช่น จาก stopped เป็น start pending ระหว่างที่ Windows เริ่ม service ใหม่
+            (Some(outage), Err(reason)) => outage.reason = reason,
+            (None, Ok(())) => {}
+        }
+    }
+
+    /// เหตุที่ spooler ใช้งานไม่ได้ (`None` = ทำงานปกติ)
+    pub(crate) fn outage(&self) -> Option<String> {
+        self.outage
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|outage| outage.reason.clone())
+    }
+
+    /// เหตุที่ส่งงานไปยัง `printer` ไม่ได้ในตอนนี้ (เครื่องพิมพ์ IPP ไม่ผ่าน spooler จึงส่งได้เสมอ)
+    pub(crate) fn blocks(
+        &self,
+        printer: &printers::common::base::printer::Printer,
+    ) -> Option<String> {
+        (printer.driver_name != IPP_DRIVER_NAME)
+            .then(|| self.outage())
+            .flatten()
+    }
+}
+
+/// สถานะของ service Print Spooler จาก Service Control Manager (`Err` = ข้อความที่บอกว่าหยุดทำงานอย่างไร)
+/// หากอ่านสถานะไม่ได้จะถือว่าทำงานปกติ เพื่อไม่ให้การตรวจที่ผิดพลาดทำให้รับงานไม่ได้
+#[cfg(windows)]
+pub(crate) fn spooler_state() -> std::result::Result<(), String> {
+    #[repr(C)]
+    #[derive(Default)]
+    struct ServiceStatusInfo {
+        service_type: u32,
+        current_state: u32,
+        controls_accepted: u32,
+        win32_exit_code: u32,
+        service_specific_exit_code: u32,
+        check_point: u32,
+        wait_hint: u32,
+    }
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn OpenSCManagerW(
+            machine: *const u16,
+            database: *const u16,
+            access: u32,
+        ) -> *mut std::ffi::c_void;
+        fn OpenServiceW(
+            manager: *mut std::ffi::c_void,
+            name: *const u16,
+            access: u32,
+        ) -> *mut std::ffi::c_void;
+        fn QueryServiceStatus(
+            service: *mut std::ffi::c_void,
+            status: *mut ServiceStatusInfo,
+        ) -> i32;
+        fn CloseServiceHandle(handle: *mut std::ffi::c_void) -> i32;
+    }
+    const SC_MANAGER_CONNECT: u32 = 0x0001;
+    const SERVICE_QUERY_STATUS: u32 = 0x0004;
+    const SERVICE_RUNNING: u32 = 4;
+
+    let name: Vec<u16> = "Spooler".encode_utf16().chain([0]).collect();
+    let mut status = ServiceStatusInfo::default();
+    let queried = unsafe {
+        let manager = OpenSCManagerW(std::ptr::null(), std::ptr::null(), SC_MANAGER_CONNECT);
+        if manager.is_null() {
+            eprintln!(
+                "Failed to open the Service Control Manager: {}",
+                std::io::Error::last_os_error()
+            );
+            return Ok(());
+        }
+        let service = OpenServiceW(manager, name.as_ptr(), SERVICE_QUERY_STATUS);
+        let queried = if service.is_null() {
+            Err(std::io::Error::last_os_error())
+        } else {
+            let result = match QueryServiceStatus(service, &mut status) {
+                0 => Err(std::io::Error::last_os_error()),
+                _ => Ok(()),
+            };
+            CloseServiceHandle(service);
+            result
+        };
+        CloseServiceHandle(manager);
+        queried
+    };
+    if let Err(e) = queried {
+        eprintln!("Failed to query the Print Spooler service: {}", e);
+        return Ok(());
+    }
+    let state = match status.current_state {
+        SERVICE_RUNNING => return Ok(()),
+        1 => "stopped",
+        2 => "starting",
+        3 => "stopping",
+        5 | 7 => "paused",
+        6 => "pausing",
+        _ => "in an unknown state",
+    };
+    Err(format!("Print Spooler service is {}", state))
+}
+
+/// สถานะของ CUPS scheduler จาก `lpstat -r` (ไม่มี `lpstat` = ถือว่าทำงานปกติ)
+#[cfg(not(windows))]
+pub(crate) fn spooler_state() -> std::result::Result<(), String> {
+    let output = match std::process::Command::new("lpstat").arg("-r").output() {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("Failed to run lpstat: {}", e);
+            return Ok(());
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.contains("scheduler is running") {
+        Ok(())
+    } else {
+        Err("CUPS scheduler is not running".to_string())
+    }
+}
+
+/// ตรวจสถานะของ spooler ทุก 5 วินาที (ทำงานใน thread แยก)
+pub(crate) fn monitor_spooler(
+    config: web::Data<SharedConfig>,
+    dispatcher: web::Data<PrintDispatcher>,
+) {
+    loop {
+        std::thread::sleep(SPOOLER_CHECK_INTERVAL);
+        dispatcher.spooler.refresh(&config.get());
+    }
+}
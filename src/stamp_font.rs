@@ -0,0 +1,290 @@
+// ----------------------------------------------------------------------
+//                             STAMP FONT
+// ----------------------------------------------------------------------
+
+use anyhow::{bail, Context, Result};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// ฟอนต์ TrueType จาก `stamp_font` ใน config.json สำหรับข้อความของ `stamp`, `page_numbers` และ `text`
+/// ที่มีตัวอักษรที่ไม่ใช่ ASCII เช่น ชื่อเดือนภาษาไทยและเลขไทย
+///
+/// ฟอนต์ถูกฝังทั้งไฟล์แบบ CIDFontType2 (Identity-H) โดยข้อความถูกเข้ารหัสเป็นหมายเลข glyph
+pub(crate) struct StampFont {
+    name: String,
+    data: Vec<u8>,
+    units_per_em: f32,
+    bbox: [i16; 4],
+    ascent: i16,
+    descent: i16,
+    /// ความกว้างของ glyph ตามลำดับใน `hmtx` (glyph หลังจากนี้ใช้ความกว้างสุดท้าย)
+    advances: Vec<u16>,
+    /// ช่วงของ cmap format 4 (start, end, id_delta, ตำแหน่งของ id_range_offset ในไฟล์)
+    segments: Vec<(u16, u16, u16, usize)>,
+}
+
+pub(crate) fn font_u16(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .context("Unexpected end of font data")
+}
+
+pub(crate) fn font_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .context("Unexpected end of font data")
+}
+
+impl StampFont {
+    pub(crate) fn load(path: &Path) -> Result<StampFont> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read stamp_font {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect::<String>();
+        StampFont::parse(
+            if name.is_empty() {
+                "StampFont".to_string()
+            } else {
+                name
+            },
+            data,
+        )
+        .with_context(|| format!("Invalid stamp_font {}", path.display()))
+    }
+
+    fn parse(name: String, data: Vec<u8>) -> Result<StampFont> {
+        match font_u32(&data, 0)? {
+            0x0001_0000 | 0x7472_7565 => {}
+            0x4F54_544F => bail!("CFF-based OpenType fonts are not supported (use a .ttf font)"),
+            0x7474_6366 => bail!("Font collections (.ttc) are not supported (use a .ttf font)"),
+            _ => bail!("Not a TrueType font"),
+        }
+        let mut tables = HashMap::new();
+        for i in 0..font_u16(&data, 4)? as usize {
+            let record = 12 + i * 16;
+            let tag = data
+                .get(record..record + 4)
+                .context("Truncated table directory")?;
+            tables.insert(tag.to_vec(), font_u32(&data, record + 8)? as usize);
+        }
+        let table = |tag: &[u8]| {
+            tables
+                .get(tag)
+                .copied()
+                .with_context(|| format!("Missing {} table", String::from_utf8_lossy(tag)))
+        };
+
+        let head = table(b"head")?;
+        let units_per_em = font_u16(&data, head + 18)?;
+        if units_per_em == 0 {
+            bail!("Invalid unitsPerEm");
+        }
+        let mut bbox = [0; 4];
+        for (i, value) in bbox.iter_mut().enumerate() {
+            *value = font_u16(&data, head + 36 + i * 2)? as i16;
+        }
+        let hhea = table(b"hhea")?;
+        let ascent = font_u16(&data, hhea + 4)? as i16;
+        let descent = font_u16(&data, hhea + 6)? as i16;
+        let metrics = font_u16(&data, hhea + 34)? as usize;
+        let hmtx = table(b"hmtx")?;
+        let advances = (0..metrics)
+            .map(|glyph| font_u16(&data, hmtx + glyph * 4))
+            .collect::<Result<Vec<_>>>()?;
+
+        // ใช้ cmap ของ Unicode BMP (Windows 3/1 หรือ Unicode 0/*) ที่เป็น format 4
+        let cmap = table(b"cmap")?;
+        let mut subtable = None;
+        for i in 0..font_u16(&data, cmap + 2)? as usize {
+            let record = cmap + 4 + i * 8;
+            let platform = font_u16(&data, record)?;
+            let encoding = font_u16(&data, record + 2)?;
+            let offset = cmap + font_u32(&data, record + 4)? as usize;
+            if (platform == 3 && encoding == 1 || platform == 0) && font_u16(&data, offset)? == 4 {
+                subtable = Some(offset);
+                break;
+            }
+        }
+        let subtable = subtable.context("No Unicode (format 4) cmap")?;
+        let segment_count = font_u16(&data, subtable + 6)? as usize / 2;
+        let ends = subtable + 14;
+        let starts = ends + segment_count * 2 + 2;
+        let deltas = starts + segment_count * 2;
+        let range_offsets = deltas + segment_count * 2;
+        let segments = (0..segment_count)
+            .map(|i| {
+                Ok((
+                    font_u16(&data, starts + i * 2)?,
+                    font_u16(&data, ends + i * 2)?,
+                    font_u16(&data, deltas + i * 2)?,
+                    range_offsets + i * 2,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(StampFont {
+            name,
+            data,
+            units_per_em: units_per_em as f32,
+            bbox,
+            ascent,
+            descent,
+            advances,
+            segments,
+        })
+    }
+
+    /// หมายเลข glyph ของตัวอักษร `c` (ไม่มีในฟอนต์ = `None`)
+    fn glyph(&self, c: char) -> Option<u16> {
+        let code = u16::try_from(c as u32).ok()?;
+        let &(start, _, delta, range_offset_at) = self
+            .segments
+            .iter()
+            .find(|(start, end, ..)| (*start..=*end).contains(&code))?;
+        let range_offset = font_u16(&self.data, range_offset_at).ok()?;
+        let glyph = match range_offset {
+            0 => code.wrapping_add(delta),
+            _ => {
+                let at = range_offset_at + range_offset as usize + (code - start) as usize * 2;
+                match font_u16(&self.data, at).ok()? {
+                    0 => 0,
+                    glyph => glyph.wrapping_add(delta),
+                }
+            }
+        };
+        (glyph != 0).then_some(glyph)
+    }
+
+    /// ความกว้างของ glyph (หน่วย 1/1000 ของขนาดฟอนต์)
+    fn advance(&self, glyph: u16) -> f32 {
+        let advance = self
+            .advances
+            .get(glyph as usize)
+            .or(self.advances.last())
+            .copied()
+            .unwrap_or(0);
+        advance as f32 * 1000.0 / self.units_per_em
+    }
+
+    /// หมายเลข glyph ของทุกตัวอักษรใน `text`
+    fn glyphs(&self, text: &str) -> Result<Vec<(char, u16)>> {
+        text.chars()
+            .map(|c| {
+                self.glyph(c)
+                    .map(|glyph| (c, glyph))
+                    .with_context(|| format!("stamp_font {} has no glyph for {:?}", self.name, c))
+            })
+            .collect()
+    }
+
+    /// ความกว้างของ `text` ที่ขนาดฟอนต์ `font_size` (pt)
+    pub(crate) fn text_width(&self, text: &str, font_size: f32) -> Result<f32> {
+        let width: f32 = self
+            .glyphs(text)?
+            .iter()
+            .map(|(_, glyph)| self.advance(*glyph))
+            .sum();
+        Ok(width * font_size / 1000.0)
+    }
+
+    /// `text` ที่เข้ารหัสแบบ Identity-H สำหรับคำสั่ง `Tj`
+    pub(crate) fn encode(&self, text: &str) -> Result<Object> {
+        let bytes = self
+            .glyphs(text)?
+            .iter()
+            .flat_map(|(_, glyph)| glyph.to_be_bytes())
+            .collect();
+        Ok(Object::String(bytes, lopdf::StringFormat::Hexadecimal))
+    }
+
+    /// ฝังฟอนต์ลงใน `doc` สำหรับข้อความทั้งหมดใน `texts` และคืนค่า object ของฟอนต์ Type0
+    pub(crate) fn add_to(&self, doc: &mut Document, texts: &[String]) -> Result<ObjectId> {
+        let mut used = BTreeMap::new();
+        for text in texts {
+            used.extend(self.glyphs(text)?.into_iter().map(|(c, glyph)| (glyph, c)));
+        }
+        let scale = 1000.0 / self.units_per_em;
+        let scaled = |value: i16| Object::Integer((value as f32 * scale).round() as i64);
+
+        let mut font_file = Stream::new(
+            dictionary! { "Length1" => self.data.len() as i64 },
+            self.data.clone(),
+        );
+        let _ = font_file.compress();
+        let font_file_id = doc.add_object(font_file);
+        let descriptor_id = doc.add_object(dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => Object::Name(self.name.clone().into_bytes()),
+            "Flags" => 32,
+            "FontBBox" => self.bbox.iter().map(|value| scaled(*value)).collect::<Vec<_>>(),
+            "ItalicAngle" => 0,
+            "Ascent" => scaled(self.ascent),
+            "Descent" => scaled(self.descent),
+            "CapHeight" => scaled(self.ascent),
+            "StemV" => 80,
+            "FontFile2" => font_file_id,
+        });
+        let widths = used
+            .keys()
+            .flat_map(|glyph| {
+                [
+                    Object::Integer(*glyph as i64),
+                    Object::Array(vec![Object::Integer(self.advance(*glyph).round() as i64)]),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let cid_font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "BaseFont" => Object::Name(self.name.clone().into_bytes()),
+            "CIDSystemInfo" => dictionary! {
+                "Registry" => Object::string_literal("Adobe"),
+                "Ordering" => Object::string_literal("Identity"),
+                "Supplement" => 0,
+            },
+            "FontDescriptor" => descriptor_id,
+            "DW" => 0,
+            "W" => widths,
+            "CIDToGIDMap" => "Identity",
+        });
+
+        // ToUnicode ทำให้คัดลอกและค้นหาข้อความที่พิมพ์ลงไปได้
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+             1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+        );
+        let entries = used.iter().collect::<Vec<_>>();
+        for chunk in entries.chunks(100) {
+            cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+            for (glyph, c) in chunk {
+                let mut units = [0; 2];
+                let units = c
+                    .encode_utf16(&mut units)
+                    .iter()
+                    .map(|unit| format!("{:04X}", unit))
+                    .collect::<String>();
+                cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, units));
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        let to_unicode_id = doc.add_object(Stream::new(dictionary! {}, cmap.into_bytes()));
+
+        Ok(doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => Object::Name(self.name.clone().into_bytes()),
+            "Encoding" => "Identity-H",
+            "DescendantFonts" => vec![Object::Reference(cid_font_id)],
+            "ToUnicode" => to_unicode_id,
+        }))
+    }
+}
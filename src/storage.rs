@@ -0,0 +1,755 @@
+// ----------------------------------------------------------------------
+//                            STAGING FILES
+// ----------------------------------------------------------------------
+
+use crate::{
+    crypto::{open_job_data, read_job_file, AtRestKey},
+    tenants::{files_dir, is_plain_file_name},
+    SERVICE_NAME,
+};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use utoipa::ToSchema;
+
+/// ที่เก็บไฟล์ต้นฉบับที่ client อัปโหลด (`storage` ใน config.json)
+#[derive(Deserialize, Clone, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum StorageConfig {
+    /// โฟลเดอร์ของ tenant ใน ./printable_files บนดิสก์ของเครื่องนี้ (ค่าเริ่มต้น)
+    #[default]
+    Local,
+    /// bucket ของ object store ที่รองรับ S3 API เช่น MinIO หรือ Amazon S3
+    S3(ObjectStoreConfig),
+    /// หน่วยความจำของ process (ไฟล์หายเมื่อ restart ใช้สำหรับทดสอบ)
+    Memory,
+}
+
+/// การเชื่อมต่อ object store ของ `storage` แบบ `s3`
+#[derive(Deserialize, Clone, PartialEq)]
+pub(crate) struct ObjectStoreConfig {
+    /// URL ของ object store เช่น `http://minio.local:9000` (อ้างถึงไฟล์แบบ `<endpoint>/<bucket>/<key>`)
+    endpoint: String,
+    bucket: String,
+    /// region ที่ใช้ลงลายมือชื่อ request (MinIO ใช้ `us-east-1` เป็นค่าเริ่มต้น)
+    #[serde(default = "default_object_store_region")]
+    region: String,
+    access_key: String,
+    secret_key: String,
+    /// ข้อความนำหน้า key ของทุกไฟล์ เช่น `print/` (ไม่ระบุ = ไม่มี)
+    #[serde(default)]
+    prefix: String,
+    /// โปรแกรม curl ที่ใช้ส่ง request (ต้องรองรับ `--aws-sigv4` คือ curl 7.75 ขึ้นไป)
+    #[serde(default = "default_object_store_curl_path")]
+    curl_path: String,
+    /// เวลาสูงสุดของแต่ละ request (วินาที)
+    #[serde(default = "default_object_store_timeout_secs")]
+    timeout_secs: u64,
+}
+
+pub(crate) fn default_object_store_region() -> String {
+    "us-east-1".to_string()
+}
+
+pub(crate) fn default_object_store_curl_path() -> String {
+    "curl".to_string()
+}
+
+pub(crate) fn default_object_store_timeout_secs() -> u64 {
+    60
+}
+
+pub(crate) fn validate_storage_config(storage: &StorageConfig) -> Result<()> {
+    let StorageConfig::S3(store) = storage else {
+        return Ok(());
+    };
+    if !store.endpoint.starts_with("http://") && !store.endpoint.starts_with("https://") {
+        bail!(
+            "storage.endpoint must start with http:// or https://, got {:?}",
+            store.endpoint
+        );
+    }
+    if store.bucket.is_empty() || store.bucket.contains('/') {
+        bail!(
+            "storage.bucket must be a bucket name, got {:?}",
+            store.bucket
+        );
+    }
+    if store.timeout_secs == 0 {
+        bail!("storage.timeout_secs must be greater than 0");
+    }
+    Ok(())
+}
+
+/// ไฟล์ใน storage (`size` คือขนาดตามที่เก็บจริง ซึ่งรวมส่วนที่เพิ่มจากการเข้ารหัส)
+pub(crate) struct StoredFile {
+    name: String,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// ที่เก็บไฟล์ต้นฉบับของแต่ละ tenant (`tenant` = `None` คือไฟล์ที่ไม่มี tenant)
+///
+/// อ่านและเขียนข้อมูลตามที่เก็บจริง ใช้ `read_staged_file` และ `write_staged_file` เพื่อเข้ารหัสตาม `encryption`
+/// การอ่านไฟล์ที่ไม่มีอยู่ได้ `ErrorKind::NotFound`
+pub(crate) trait Storage: Send + Sync {
+    /// ที่เก็บไฟล์สำหรับแสดงใน log เช่น `./printable_files`
+    fn describe(&self) -> String;
+    fn read(&self, tenant: Option<&str>, name: &str) -> std::io::Result<Vec<u8>>;
+    fn write(&self, tenant: Option<&str>, name: &str, data: &[u8]) -> std::io::Result<()>;
+    fn remove(&self, tenant: Option<&str>, name: &str) -> std::io::Result<()>;
+    /// ข้อมูลของไฟล์ `name` (`None` = ไม่มีไฟล์นี้)
+    fn stat(&self, tenant: Option<&str>, name: &str) -> std::io::Result<Option<StoredFile>>;
+    /// ไฟล์ทั้งหมดของ tenant (ไม่รวมโฟลเดอร์และไฟล์ซ่อน เช่น `.uploads`)
+    fn list(&self, tenant: Option<&str>) -> std::io::Result<Vec<StoredFile>>;
+
+    /// เก็บไฟล์ `path` บนดิสก์ของเครื่องนี้เป็น `name` แล้วลบไฟล์ `path`
+    fn persist(&self, tenant: Option<&str>, name: &str, path: &Path) -> std::io::Result<()> {
+        self.write(tenant, name, &std::fs::read(path)?)?;
+        std::fs::remove_file(path)
+    }
+}
+
+/// สร้างที่เก็บไฟล์ตาม `storage` ใน config.json (ทำครั้งเดียวตอนเริ่ม Service)
+pub(crate) fn open_storage(config: &StorageConfig) -> Arc<dyn Storage> {
+    match config {
+        StorageConfig::Local => Arc::new(LocalStorage),
+        StorageConfig::S3(store) => Arc::new(ObjectStorage {
+            config: store.clone(),
+        }),
+        StorageConfig::Memory => Arc::new(MemoryStorage::default()),
+    }
+}
+
+pub(crate) fn stored_file_not_found(name: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("File not found: {}", name),
+    )
+}
+
+/// ไฟล์ในโฟลเดอร์ของ tenant ใน ./printable_files
+pub(crate) struct LocalStorage;
+
+impl LocalStorage {
+    /// path ของไฟล์ `name` ในโฟลเดอร์ของ tenant ชื่อที่ไม่ใช่ชื่อไฟล์เดียว (เช่น `../other/file.pdf`)
+    /// ได้ `ErrorKind::InvalidInput` แม้ผู้เรียกจะไม่ได้ตรวจชื่อไฟล์มาก่อน
+    fn path(tenant: Option<&str>, name: &str) -> std::io::Result<PathBuf> {
+        if !is_plain_file_name(name) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid filename: {}", name),
+            ));
+        }
+        Ok(files_dir(tenant).join(name))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn describe(&self) -> String {
+        files_dir(None).display().to_string()
+    }
+
+    fn read(&self, tenant: Option<&str>, name: &str) -> std::io::Result<Vec<u8>> {
+        std::fs::read(LocalStorage::path(tenant, name)?)
+    }
+
+    fn write(&self, tenant: Option<&str>, name: &str, data: &[u8]) -> std::io::Result<()> {
+        let path = LocalStorage::path(tenant, name)?;
+        std::fs::create_dir_all(files_dir(tenant))?;
+        std::fs::write(path, data)
+    }
+
+    fn remove(&self, tenant: Option<&str>, name: &str) -> std::io::Result<()> {
+        std::fs::remove_file(LocalStorage::path(tenant, name)?)
+    }
+
+    fn stat(&self, tenant: Option<&str>, name: &str) -> std::io::Result<Option<StoredFile>> {
+        match std::fs::metadata(LocalStorage::path(tenant, name)?) {
+            Ok(metadata) if metadata.is_file() => Ok(Some(StoredFile {
+                name: name.to_string(),
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            })),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn list(&self, tenant: Option<&str>) -> std::io::Result<Vec<StoredFile>> {
+        let mut files = Vec::new();
+        let entries = match std::fs::read_dir(files_dir(tenant)) {
+            Ok(entries) => entries,
+            // โฟลเดอร์ของ tenant ที่ยังไม่มีไฟล์
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let metadata = entry.metadata()?;
+            if name.starts_with('.') || !metadata.is_file() {
+                continue;
+            }
+            files.push(StoredFile {
+                name,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
+        }
+        Ok(files)
+    }
+
+    fn persist(&self, tenant: Option<&str>, name: &str, path: &Path) -> std::io::Result<()> {
+        let target = LocalStorage::path(tenant, name)?;
+        std::fs::create_dir_all(files_dir(tenant))?;
+        std::fs::rename(path, target)
+    }
+}
+
+/// tenant และชื่อของไฟล์ใน storage
+pub(crate) type StoredFileKey = (Option<String>, String);
+
+/// ไฟล์ในหน่วยความจำพร้อมเวลาที่เขียน
+#[derive(Default)]
+pub(crate) struct MemoryStorage {
+    files: Mutex<BTreeMap<StoredFileKey, (Vec<u8>, SystemTime)>>,
+}
+
+impl Storage for MemoryStorage {
+    fn describe(&self) -> String {
+        "memory (files are lost on restart)".to_string()
+    }
+
+    fn read(&self, tenant: Option<&str>, name: &str) -> std::io::Result<Vec<u8>> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(&(tenant.map(str::to_string), name.to_string()))
+            .map(|(data, _)| data.clone())
+            .ok_or_else(|| stored_file_not_found(name))
+    }
+
+    fn write(&self, tenant: Option<&str>, name: &str, data: &[u8]) -> std::io::Result<()> {
+        self.files.lock().unwrap().insert(
+            (tenant.map(str::to_string), name.to_string()),
+            (data.to_vec(), SystemTime::now()),
+        );
+        Ok(())
+    }
+
+    fn remove(&self, tenant: Option<&str>, name: &str) -> std::io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .remove(&(tenant.map(str::to_string), name.to_string()))
+            .map(|_| ())
+            .ok_or_else(|| stored_file_not_found(name))
+    }
+
+    fn stat(&self, tenant: Option<&str>, name: &str) -> std::io::Result<Option<StoredFile>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .get(&(tenant.map(str::to_string), name.to_string()))
+            .map(|(data, modified)| StoredFile {
+                name: name.to_string(),
+                size: data.len() as u64,
+                modified: Some(*modified),
+            }))
+    }
+
+    fn list(&self, tenant: Option<&str>) -> std::io::Result<Vec<StoredFile>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((file_tenant, name), _)| {
+                file_tenant.as_deref() == tenant && !name.starts_with('.')
+            })
+            .map(|((_, name), (data, modified))| StoredFile {
+                name: name.clone(),
+                size: data.len() as u64,
+                modified: Some(*modified),
+            })
+            .collect())
+    }
+}
+
+/// bucket ของ object store ที่รองรับ S3 API โดยส่ง request ด้วย curl (`--aws-sigv4`)
+pub(crate) struct ObjectStorage {
+    config: ObjectStoreConfig,
+}
+
+/// เข้ารหัสข้อความสำหรับใส่ใน URL ตาม RFC 3986 (ไม่เข้ารหัสเฉพาะตัวอักษร unreserved)
+pub(crate) fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+/// ค่าหนึ่งบรรทัดใน config ของ curl (`--config`) เช่น `user = "name:secret"`
+pub(crate) fn curl_config_line(option: &str, value: &str) -> String {
+    let value = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("{} = \"{}\"\n", option, value)
+}
+
+/// config ของ curl ที่เก็บรหัสผ่านหรือ token ไว้ในไฟล์ชั่วคราวแทน argument ของ curl
+/// ซึ่งผู้ใช้อื่นในเครื่องเห็นได้จากรายการ process (ใช้เมื่อ stdin ถูกใช้ส่งข้อมูลแล้ว และลบไฟล์เมื่อ drop)
+///
+/// ไฟล์อยู่ในโฟลเดอร์ชั่วคราวของบัญชี Service ซึ่งบน Windows มีเพียงเจ้าของไฟล์และผู้ดูแลระบบที่อ่านได้
+/// และบน Linux สร้างด้วยสิทธิ์ 0600
+pub(crate) struct CurlConfigFile {
+    path: PathBuf,
+}
+
+impl CurlConfigFile {
+    pub(crate) fn create(options: &[(&str, &str)]) -> std::io::Result<Self> {
+        let mut id = [0u8; 8];
+        getrandom::fill(&mut id).map_err(|e| std::io::Error::other(e.to_string()))?;
+        let path =
+            std::env::temp_dir().join(format!("{}-curl-{}.conf", SERVICE_NAME, hex_string(&id)));
+        let mut open_options = std::fs::OpenOptions::new();
+        open_options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut open_options, 0o600);
+        let mut file = open_options.open(&path)?;
+        // ลบไฟล์แม้เขียนไม่สำเร็จ
+        let config = CurlConfigFile { path };
+        for (option, value) in options {
+            file.write_all(curl_config_line(option, value).as_bytes())?;
+        }
+        Ok(config)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for CurlConfigFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// เนื้อหาของทุก element `<tag>...</tag>` ใน XML ที่ object store ตอบกลับ
+pub(crate) fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut elements = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        elements.push(&rest[..end]);
+        rest = &rest[end + close.len()..];
+    }
+    elements
+}
+
+pub(crate) fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+impl ObjectStorage {
+    /// key ของไฟล์ใน bucket: `<prefix><tenant>/<name>`
+    fn object_key(&self, tenant: Option<&str>, name: &str) -> String {
+        match tenant {
+            Some(tenant) => format!("{}{}/{}", self.config.prefix, tenant, name),
+            None => format!("{}{}", self.config.prefix, name),
+        }
+    }
+
+    fn bucket_url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.config.endpoint.trim_end_matches('/'),
+            uri_encode(&self.config.bucket)
+        )
+    }
+
+    fn object_url(&self, tenant: Option<&str>, name: &str) -> String {
+        let key = self.object_key(tenant, name);
+        let path: Vec<String> = key.split('/').map(uri_encode).collect();
+        format!("{}/{}", self.bucket_url(), path.join("/"))
+    }
+
+    /// ส่ง request ไปยัง object store แล้วคืนค่า status code และ body (`HEAD` คืนค่า header แทน body)
+    fn request(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&[u8]>,
+    ) -> std::io::Result<(u16, Vec<u8>)> {
+        let payload_hash = hex_string(&Sha256::digest(body.unwrap_or_default()));
+        // stdin ใช้ส่งไฟล์ จึงส่ง access key และ secret key ผ่าน config ในไฟล์ชั่วคราว
+        let credentials = format!("{}:{}", self.config.access_key, self.config.secret_key);
+        let curl_config = CurlConfigFile::create(&[("user", &credentials)])?;
+        let mut command = std::process::Command::new(&self.config.curl_path);
+        command
+            .args(["--silent", "--show-error", "--max-time"])
+            .arg(self.config.timeout_secs.to_string())
+            .arg("--aws-sigv4")
+            .arg(format!("aws:amz:{}:s3", self.config.region))
+            .arg("--config")
+            .arg(curl_config.path())
+            .arg("--header")
+            .arg(format!("x-amz-content-sha256: {}", payload_hash))
+            .args(["--write-out", "\n%{http_code}"]);
+        if method == "HEAD" {
+            command.arg("--head");
+        } else {
+            command.args(["--request", method]);
+        }
+        if body.is_some() {
+            command.args([
+                "--header",
+                "Content-Type: application/octet-stream",
+                "--data-binary",
+                "@-",
+            ]);
+        }
+        let mut child = command
+            .arg(url)
+            .stdin(if body.is_some() {
+                std::process::Stdio::piped()
+            } else {
+                std::process::Stdio::null()
+            })
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to start {}: {}", self.config.curl_path, e),
+                )
+            })?;
+        if let (Some(mut stdin), Some(body)) = (child.stdin.take(), body) {
+            stdin.write_all(body)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(std::io::Error::other(format!(
+                "Object store request {} {} failed: {}",
+                method,
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        // `--write-out` ต่อ status code ไว้หลัง body เสมอ
+        let mut response = output.stdout;
+        let split = response
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .unwrap_or_default();
+        let status = String::from_utf8_lossy(&response[split..])
+            .trim()
+            .parse()
+            .map_err(|_| {
+                std::io::Error::other(format!("Invalid response from object store for {}", url))
+            })?;
+        response.truncate(split);
+        Ok((status, response))
+    }
+
+    /// ข้อผิดพลาดเมื่อ object store ตอบกลับด้วย status ที่ไม่ใช่ 2xx
+    fn status_error(&self, method: &str, name: &str, status: u16, body: &[u8]) -> std::io::Error {
+        if status == 404 {
+            return stored_file_not_found(name);
+        }
+        let body = String::from_utf8_lossy(body);
+        let code = xml_elements(&body, "Code")
+            .first()
+            .map(|code| code.to_string());
+        std::io::Error::other(format!(
+            "Object store returned HTTP {} for {} {}{}",
+            status,
+            method,
+            name,
+            code.map(|code| format!(" ({})", code)).unwrap_or_default()
+        ))
+    }
+}
+
+impl Storage for ObjectStorage {
+    fn describe(&self) -> String {
+        format!("{}/{}", self.bucket_url(), self.config.prefix)
+    }
+
+    fn read(&self, tenant: Option<&str>, name: &str) -> std::io::Result<Vec<u8>> {
+        match self.request("GET", &self.object_url(tenant, name), None)? {
+            (200, body) => Ok(body),
+            (status, body) => Err(self.status_error("GET", name, status, &body)),
+        }
+    }
+
+    fn write(&self, tenant: Option<&str>, name: &str, data: &[u8]) -> std::io::Result<()> {
+        match self.request("PUT", &self.object_url(tenant, name), Some(data))? {
+            (200..=299, _) => Ok(()),
+            (status, body) => Err(self.status_error("PUT", name, status, &body)),
+        }
+    }
+
+    fn remove(&self, tenant: Option<&str>, name: &str) -> std::io::Result<()> {
+        match self.request("DELETE", &self.object_url(tenant, name), None)? {
+            (200..=299, _) => Ok(()),
+            (status, body) => Err(self.status_error("DELETE", name, status, &body)),
+        }
+    }
+
+    fn stat(&self, tenant: Option<&str>, name: &str) -> std::io::Result<Option<StoredFile>> {
+        let headers = match self.request("HEAD", &self.object_url(tenant, name), None)? {
+            (200, headers) => String::from_utf8_lossy(&headers).to_string(),
+            (404, _) => return Ok(None),
+            (status, body) => return Err(self.status_error("HEAD", name, status, &body)),
+        };
+        let header = |field: &str| {
+            headers.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(field).then(|| value.trim())
+            })
+        };
+        Ok(Some(StoredFile {
+            name: name.to_string(),
+            size: header("content-length")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_default(),
+            modified: header("last-modified")
+                .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok())
+                .map(SystemTime::from),
+        }))
+    }
+
+    fn list(&self, tenant: Option<&str>) -> std::io::Result<Vec<StoredFile>> {
+        let prefix = self.object_key(tenant, "");
+        let mut files = Vec::new();
+        let mut continuation: Option<String> = None;
+        loop {
+            // query ต้องเรียงตามชื่อเพื่อให้ตรงกับ request ที่ลงลายมือชื่อไว้
+            let mut url = format!("{}?", self.bucket_url());
+            if let Some(token) = &continuation {
+                url.push_str(&format!("continuation-token={}&", uri_encode(token)));
+            }
+            url.push_str(&format!(
+                "delimiter=%2F&list-type=2&prefix={}",
+                uri_encode(&prefix)
+            ));
+            let body = match self.request("GET", &url, None)? {
+                (200, body) => String::from_utf8_lossy(&body).to_string(),
+                (status, body) => return Err(self.status_error("LIST", &prefix, status, &body)),
+            };
+            for contents in xml_elements(&body, "Contents") {
+                let Some(key) = xml_elements(contents, "Key")
+                    .first()
+                    .map(|key| xml_unescape(key))
+                else {
+                    continue;
+                };
+                let Some(name) = key.strip_prefix(&prefix) else {
+                    continue;
+                };
+                if name.is_empty() || name.starts_with('.') || name.contains('/') {
+                    continue;
+                }
+                let field = |tag: &str| xml_elements(contents, tag).first().copied();
+                files.push(StoredFile {
+                    name: name.to_string(),
+                    size: field("Size")
+                        .and_then(|size| size.parse().ok())
+                        .unwrap_or_default(),
+                    modified: field("LastModified")
+                        .and_then(|value| chrono::DateTime::parse_from_rfc3339(value).ok())
+                        .map(SystemTime::from),
+                });
+            }
+            let truncated = xml_elements(&body, "IsTruncated").first() == Some(&"true");
+            continuation = xml_elements(&body, "NextContinuationToken")
+                .first()
+                .map(|token| xml_unescape(token))
+                .filter(|_| truncated);
+            if continuation.is_none() {
+                return Ok(files);
+            }
+        }
+    }
+}
+
+/// อ่านไฟล์ต้นฉบับจาก storage และถอดรหัสในหน่วยความจำเมื่อไฟล์ถูกเข้ารหัสไว้
+pub(crate) fn read_staged_file(
+    storage: &dyn Storage,
+    key: Option<&AtRestKey>,
+    tenant: Option<&str>,
+    name: &str,
+) -> std::io::Result<Vec<u8>> {
+    open_job_data(key, storage.read(tenant, name)?, &name)
+}
+
+/// เขียนไฟล์ต้นฉบับลง storage โดยเข้ารหัสเมื่อตั้งค่า `encryption` ใน config.json
+pub(crate) fn write_staged_file(
+    storage: &dyn Storage,
+    key: Option<&AtRestKey>,
+    tenant: Option<&str>,
+    name: &str,
+    data: &[u8],
+) -> std::io::Result<()> {
+    match key {
+        Some(key) => storage.write(tenant, name, &key.seal(data)?),
+        None => storage.write(tenant, name, data),
+    }
+}
+
+/// ไฟล์ต้นฉบับของงานพิมพ์
+pub(crate) enum SourceFile {
+    /// ไฟล์ใน storage ของ tenant
+    Staged {
+        tenant: Option<String>,
+        name: String,
+    },
+    /// ไฟล์ในโฟลเดอร์ของแหล่งไฟล์ `sources`
+    Local(PathBuf),
+}
+
+impl SourceFile {
+    pub(crate) fn exists(&self, storage: &dyn Storage) -> std::io::Result<bool> {
+        match self {
+            SourceFile::Staged { tenant, name } => {
+                Ok(storage.stat(tenant.as_deref(), name)?.is_some())
+            }
+            SourceFile::Local(path) => Ok(path.is_file()),
+        }
+    }
+
+    pub(crate) fn read(
+        &self,
+        storage: &dyn Storage,
+        key: Option<&AtRestKey>,
+    ) -> std::io::Result<Vec<u8>> {
+        match self {
+            SourceFile::Staged { tenant, name } => {
+                read_staged_file(storage, key, tenant.as_deref(), name)
+            }
+            SourceFile::Local(path) => read_job_file(key, path),
+        }
+    }
+}
+
+/// ข้อมูลไฟล์ใน ./printable_files
+#[derive(Serialize, ToSchema)]
+pub(crate) struct FileInfo {
+    #[schema(example = "invoice_original_a6.pdf")]
+    pub(crate) name: String,
+    /// ขนาดไฟล์ (ไบต์)
+    size: u64,
+    /// เวลาที่แก้ไขไฟล์ล่าสุด (RFC 3339)
+    #[schema(nullable)]
+    modified: Option<String>,
+    /// SHA-256 ของเนื้อหาไฟล์ (hex) ใช้เป็น ETag ของไฟล์
+    pub(crate) sha256: String,
+}
+
+/// เก็บ SHA-256 และขนาดของเนื้อหาไฟล์ไว้ตามขนาดและเวลาแก้ไขใน storage
+/// เพื่อไม่ต้องอ่านไฟล์ใหญ่ซ้ำทุกครั้งที่ client ถามรายการไฟล์
+#[derive(Default)]
+pub(crate) struct FileHashCache {
+    hashes: Mutex<HashMap<StoredFileKey, FileHash>>,
+}
+
+pub(crate) struct FileHash {
+    /// ขนาดและเวลาแก้ไขของไฟล์ใน storage ตอนคำนวณ
+    stored_size: u64,
+    modified: SystemTime,
+    sha256: String,
+    /// ขนาดของเนื้อหาไฟล์ก่อนเข้ารหัส
+    size: u64,
+}
+
+impl FileHashCache {
+    /// SHA-256 และขนาดของเนื้อหาไฟล์ `file` ของ tenant (คำนวณใหม่เฉพาะเมื่อขนาดหรือเวลาแก้ไขเปลี่ยน)
+    /// ไฟล์ที่เข้ารหัสไว้จะถอดรหัสในหน่วยความจำก่อนคำนวณ
+    pub(crate) fn sha256(
+        &self,
+        storage: &dyn Storage,
+        tenant: Option<&str>,
+        file: &StoredFile,
+        key: Option<&AtRestKey>,
+    ) -> std::io::Result<(String, u64)> {
+        let cache_key = (tenant.map(str::to_string), file.name.clone());
+        if let Some(hash) = self.hashes.lock().unwrap().get(&cache_key) {
+            if hash.stored_size == file.size && Some(hash.modified) == file.modified {
+                return Ok((hash.sha256.clone(), hash.size));
+            }
+        }
+
+        let data = read_staged_file(storage, key, tenant, &file.name)?;
+        let hash = hex_string(&Sha256::digest(&data));
+        if let Some(modified) = file.modified {
+            self.hashes.lock().unwrap().insert(
+                cache_key,
+                FileHash {
+                    stored_size: file.size,
+                    modified,
+                    sha256: hash.clone(),
+                    size: data.len() as u64,
+                },
+            );
+        }
+        Ok((hash, data.len() as u64))
+    }
+}
+
+/// แปลงข้อมูลไบต์เป็นตัวอักษร hex ตัวเล็ก
+pub(crate) fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// รายการไฟล์ของ tenant ใน storage เรียงตามชื่อ (tenant ที่ยังไม่มีไฟล์จะได้รายการว่าง)
+pub(crate) fn list_staged_files(
+    cache: &FileHashCache,
+    storage: &dyn Storage,
+    tenant: Option<&str>,
+    key: Option<&AtRestKey>,
+) -> std::io::Result<Vec<FileInfo>> {
+    let mut files = Vec::new();
+    for file in storage.list(tenant)? {
+        let (sha256, size) = cache.sha256(storage, tenant, &file, key)?;
+        files.push(FileInfo {
+            sha256,
+            size,
+            modified: file
+                .modified
+                .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339()),
+            name: file.name,
+        });
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
@@ -0,0 +1,377 @@
+// ----------------------------------------------------------------------
+//                               TENANTS
+// ----------------------------------------------------------------------
+
+use crate::{
+    agent::tokens_match,
+    billing::api_key_id,
+    client_identity::{client_cert_subject, normalize_subject},
+    config::AppConfig,
+    locale::LocaleFormat,
+};
+use actix_web::HttpRequest;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// หน่วยงานที่ใช้ Service ร่วมกัน โดยแต่ละหน่วยงานเห็นเฉพาะไฟล์และงานของตัวเอง
+#[derive(Deserialize, Clone)]
+pub(crate) struct TenantConfig {
+    /// API key ที่ client ส่งมาใน `Authorization: Bearer <api_key>`
+    #[serde(default)]
+    api_keys: Vec<String>,
+    /// subject ของ client certificate ที่ใช้ยืนยันตัวตนแทน API key (ส่งมาจาก reverse proxy ใน
+    /// `client_cert_header`) เช่น `CN=press-line-07,OU=Factory,O=Acme`
+    #[serde(default)]
+    client_cert_subjects: Vec<String>,
+    /// เครื่องพิมพ์ที่ใช้ได้ (ไม่ระบุ = ทุกเครื่อง)
+    #[serde(default)]
+    pub(crate) printers: Vec<String>,
+    /// agent ที่ส่งงานไปได้ (ไม่ระบุ = ทุก agent)
+    #[serde(default)]
+    agents: Vec<String>,
+    /// เครื่องพิมพ์ที่ใช้เมื่อ request ไม่ได้ระบุ `printer_name` (ไม่ระบุ = ใช้ `default_printer`)
+    #[serde(default)]
+    pub(crate) default_printer: Option<String>,
+    /// จำนวนงานสูงสุดต่อวัน (ไม่ระบุ = ไม่จำกัด)
+    #[serde(default)]
+    pub(crate) daily_job_quota: Option<u32>,
+    /// ชื่อใน `sources` ที่ใช้ได้ (ไม่ระบุ = ใช้ได้เฉพาะโฟลเดอร์ของ tenant)
+    #[serde(default)]
+    sources: Vec<String>,
+    /// รูปแบบวันที่และตัวเลขเริ่มต้นของ `stamp`, `page_numbers` และ `text` (รวมลายน้ำ)
+    /// เช่น `{"era": "buddhist", "digits": "thai"}` (ไม่ระบุ = ตามภาษาของ request)
+    #[serde(default)]
+    pub(crate) locale: Option<LocaleFormat>,
+    /// ผู้ใช้ Windows ที่ส่งงานในนามได้ด้วย `windows_user` (`DOMAIN\user` หรือ `user` ตรงตามที่ request ส่งมา
+    /// ไม่สนตัวพิมพ์เล็ก/ใหญ่ ไม่ระบุ = tenant นี้ใช้ `windows_user` ไม่ได้)
+    #[serde(default)]
+    windows_users: Vec<String>,
+}
+
+/// tenant ที่ยืนยันตัวตนด้วย API key แล้ว
+#[derive(Clone, Copy)]
+pub(crate) struct Tenant<'a> {
+    pub(crate) id: &'a str,
+    pub(crate) config: &'a TenantConfig,
+    /// API key ที่ใช้ยืนยันตัวตน (`None` เมื่อหา tenant จากชื่อด้วย `find_tenant`)
+    api_key: Option<&'a str>,
+    /// subject ใน `client_cert_subjects` ที่ตรงกับ client certificate เมื่อยืนยันตัวตนด้วย certificate
+    client_cert_subject: Option<&'a str>,
+}
+
+impl Tenant<'_> {
+    /// 8 ตัวแรกของ SHA-256 ของ API key ที่ใช้ยืนยันตัวตน
+    pub(crate) fn api_key_id(&self) -> Option<String> {
+        self.api_key.map(api_key_id)
+    }
+
+    /// API key หรือ client certificate ที่ใช้ยืนยันตัวตน ในรูปแบบเดียวกับ `TenantConfig::credentials`
+    pub(crate) fn credential(&self) -> Option<String> {
+        match (self.api_key, self.client_cert_subject) {
+            (Some(key), _) => Some(key_credential(key)),
+            (None, Some(subject)) => Some(subject_credential(subject)),
+            (None, None) => None,
+        }
+    }
+
+    pub(crate) fn allows_printer(&self, name: &str) -> bool {
+        self.config.printers.is_empty() || self.config.printers.iter().any(|p| p == name)
+    }
+
+    pub(crate) fn allows_agent(&self, agent_id: &str) -> bool {
+        self.config.agents.is_empty() || self.config.agents.iter().any(|a| a == agent_id)
+    }
+
+    pub(crate) fn allows_windows_user(&self, user: &str) -> bool {
+        self.config
+            .windows_users
+            .iter()
+            .any(|u| u.to_lowercase() == user.to_lowercase())
+    }
+
+    fn allows_source(&self, source: &str) -> bool {
+        self.config.sources.iter().any(|s| s == source)
+    }
+}
+
+fn key_credential(key: &str) -> String {
+    format!("api_key:{}", key)
+}
+
+fn subject_credential(subject: &str) -> String {
+    format!("client_cert:{}", normalize_subject(subject))
+}
+
+impl TenantConfig {
+    /// API key และ client certificate ทั้งหมดที่ยังใช้ยืนยันตัวตนเป็น tenant นี้ได้
+    pub(crate) fn credentials(&self) -> impl Iterator<Item = String> + '_ {
+        self.api_keys.iter().map(|key| key_credential(key)).chain(
+            self.client_cert_subjects
+                .iter()
+                .map(|subject| subject_credential(subject)),
+        )
+    }
+}
+
+/// โฟลเดอร์ไฟล์ต้นฉบับและไฟล์ที่ส่งพิมพ์ของ tenant (ไม่มี tenant = ./printable_files)
+pub(crate) fn files_dir(tenant: Option<&str>) -> PathBuf {
+    let base_dir = Path::new("./printable_files");
+    match tenant {
+        Some(tenant) => base_dir.join(tenant),
+        None => base_dir.to_path_buf(),
+    }
+}
+
+/// `name` เป็นชื่อไฟล์เดียวที่ไม่ใช่ไฟล์ซ่อน (ไม่มีโฟลเดอร์ `..` หรือ path เต็ม) จึงอยู่ในโฟลเดอร์ของ tenant เสมอ
+pub(crate) fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(std::path::Component::Normal(_)))
+        && components.next().is_none()
+        && Path::new(name)
+            .file_name()
+            .is_some_and(|file_name| file_name == name)
+        && !name.starts_with('.')
+}
+
+/// โฟลเดอร์ของแหล่งไฟล์ `source` ที่ tenant ใช้ได้ (`None` = ไม่มีแหล่งไฟล์นี้หรือ tenant ใช้ไม่ได้)
+pub(crate) fn source_dir<'a>(
+    config: &'a AppConfig,
+    tenant: Option<&str>,
+    source: &str,
+) -> Option<&'a Path> {
+    let allowed = match tenant {
+        Some(id) => {
+            find_tenant(config, Some(id)).is_some_and(|tenant| tenant.allows_source(source))
+        }
+        None => true,
+    };
+    config
+        .sources
+        .get(source)
+        .filter(|_| allowed)
+        .map(PathBuf::as_path)
+}
+
+/// ตรวจว่าชื่อแหล่งไฟล์ไม่ว่าง และ tenant อ้างถึงเฉพาะแหล่งไฟล์ที่มีอยู่
+pub(crate) fn validate_sources(config: &AppConfig) -> Result<()> {
+    if let Some(name) = config.sources.keys().find(|name| name.trim().is_empty()) {
+        bail!("Invalid source name {:?}", name);
+    }
+    for (id, tenant) in &config.tenants {
+        if let Some(name) = tenant
+            .sources
+            .iter()
+            .find(|name| !config.sources.contains_key(*name))
+        {
+            bail!("Tenant {} refers to unknown source {:?}", id, name);
+        }
+    }
+    Ok(())
+}
+
+/// ตรวจชื่อ tenant (ใช้เป็นชื่อโฟลเดอร์) และ API key ไม่ให้ว่างหรือซ้ำกันระหว่าง tenant
+pub(crate) fn validate_tenants(tenants: &HashMap<String, TenantConfig>) -> Result<()> {
+    let mut keys = BTreeSet::new();
+    let mut subjects = BTreeSet::new();
+    for (id, tenant) in tenants {
+        let is_plain_name = !id.is_empty()
+            && !id.starts_with('.')
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        if !is_plain_name {
+            bail!(
+                "Invalid tenant name {:?} (use letters, digits, '-', '_' or '.')",
+                id
+            );
+        }
+        if tenant.api_keys.is_empty() && tenant.client_cert_subjects.is_empty() {
+            bail!("Tenant {} has no api_keys or client_cert_subjects", id);
+        }
+        for key in &tenant.api_keys {
+            if key.is_empty() || !keys.insert(key.as_str()) {
+                bail!("Tenant {} has an empty or duplicate API key", id);
+            }
+        }
+        for subject in &tenant.client_cert_subjects {
+            let subject = normalize_subject(subject);
+            if subject.is_empty() || !subjects.insert(subject) {
+                bail!(
+                    "Tenant {} has an empty or duplicate client certificate subject",
+                    id
+                );
+            }
+        }
+        if let Some(Err(e)) = tenant.locale.as_ref().map(LocaleFormat::validate) {
+            bail!("Tenant {} has an invalid locale: {}", id, e);
+        }
+    }
+    Ok(())
+}
+
+/// หา tenant จาก `Authorization: Bearer <api_key>` หรือจาก client certificate เมื่อไม่ได้ส่ง API key
+pub(crate) fn authenticate_tenant<'a>(
+    request: &HttpRequest,
+    config: &'a AppConfig,
+) -> Option<Tenant<'a>> {
+    let token = request
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let Some(token) = token else {
+        return certificate_tenant(request, config);
+    };
+    // เทียบทุก key โดยไม่หยุดเมื่อเจอ เพื่อไม่ให้เวลาที่ใช้บอกใบ้ว่า key อยู่ลำดับใด
+    let mut found = None;
+    for (id, tenant) in &config.tenants {
+        for key in &tenant.api_keys {
+            if tokens_match(key, token) {
+                found = Some(Tenant {
+                    id,
+                    config: tenant,
+                    api_key: Some(key),
+                    client_cert_subject: None,
+                });
+            }
+        }
+    }
+    found
+}
+
+/// หา tenant จาก subject ของ client certificate ที่ reverse proxy ตรวจแล้ว (ดู `client_cert_subject`)
+fn certificate_tenant<'a>(request: &HttpRequest, config: &'a AppConfig) -> Option<Tenant<'a>> {
+    let subject = client_cert_subject(request, config)?;
+    config.tenants.iter().find_map(|(id, tenant)| {
+        let matched = tenant
+            .client_cert_subjects
+            .iter()
+            .find(|s| normalize_subject(s) == subject)?;
+        Some(Tenant {
+            id,
+            config: tenant,
+            api_key: None,
+            client_cert_subject: Some(matched),
+        })
+    })
+}
+
+/// tenant ที่ชื่อ `id` ใน config ชุดเดียวกับที่ตรวจ API key
+pub(crate) fn find_tenant<'a>(config: &'a AppConfig, id: Option<&str>) -> Option<Tenant<'a>> {
+    let (id, tenant) = config.tenants.get_key_value(id?)?;
+    Some(Tenant {
+        id,
+        config: tenant,
+        api_key: None,
+        client_cert_subject: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_users_must_match_the_allowlist() {
+        let config: TenantConfig = serde_json::from_value(serde_json::json!({
+            "api_keys": ["key-a"],
+            "windows_users": ["CONTOSO\\somchai"],
+        }))
+        .unwrap();
+        let tenant = Tenant {
+            id: "a",
+            config: &config,
+            api_key: None,
+            client_cert_subject: None,
+        };
+        assert!(tenant.allows_windows_user("CONTOSO\\somchai"));
+        assert!(tenant.allows_windows_user("contoso\\SOMCHAI"));
+        // ชื่อที่ไม่มี domain จะตรงกับผู้ใช้ของ domain ใดก็ได้ที่เข้าสู่ระบบอยู่ จึงต้องอยู่ในรายชื่อเอง
+        assert!(!tenant.allows_windows_user("somchai"));
+        assert!(!tenant.allows_windows_user("CONTOSO\\malee"));
+
+        let config: TenantConfig =
+            serde_json::from_value(serde_json::json!({"api_keys": ["key-b"]})).unwrap();
+        let tenant = Tenant {
+            id: "b",
+            config: &config,
+            api_key: None,
+            client_cert_subject: None,
+        };
+        assert!(!tenant.allows_windows_user("CONTOSO\\somchai"));
+    }
+
+    fn certificate_config() -> AppConfig {
+        let tenants = serde_json::from_value(serde_json::json!({
+            "press": {"client_cert_subjects": ["CN=press-line-07, OU=Factory, O=Acme"]},
+            "office": {"api_keys": ["key-office"]},
+        }))
+        .unwrap();
+        AppConfig {
+            tenants,
+            trusted_proxies: vec!["127.0.0.1".to_string()],
+            client_cert_header: Some("X-SSL-Client-S-DN".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn tenant_of(config: &AppConfig, peer: &str, headers: &[(&str, &str)]) -> Option<String> {
+        let mut request = actix_web::test::TestRequest::default().peer_addr(peer.parse().unwrap());
+        for header in headers {
+            request = request.append_header(*header);
+        }
+        authenticate_tenant(&request.to_http_request(), config).map(|tenant| tenant.id.to_string())
+    }
+
+    #[test]
+    fn client_certificates_map_to_tenants() {
+        let config = certificate_config();
+        validate_tenants(&config.tenants).unwrap();
+        let subject = ("X-SSL-Client-S-DN", "cn=press-line-07,ou=factory,o=acme");
+        assert_eq!(
+            tenant_of(&config, "127.0.0.1:50000", &[subject]),
+            Some("press".to_string())
+        );
+        assert_eq!(tenant_of(&config, "192.0.2.10:50000", &[subject]), None);
+        assert_eq!(
+            tenant_of(
+                &config,
+                "127.0.0.1:50000",
+                &[("X-SSL-Client-S-DN", "CN=press-line-08,OU=Factory,O=Acme")]
+            ),
+            None
+        );
+        // API key ที่ส่งมาใช้ก่อน certificate และ key ที่ผิดไม่ย้อนไปใช้ certificate
+        assert_eq!(
+            tenant_of(
+                &config,
+                "127.0.0.1:50000",
+                &[subject, ("Authorization", "Bearer key-office")]
+            ),
+            Some("office".to_string())
+        );
+        assert_eq!(
+            tenant_of(
+                &config,
+                "127.0.0.1:50000",
+                &[subject, ("Authorization", "Bearer wrong")]
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn tenants_need_a_key_or_certificate_subject() {
+        let tenants = |value: serde_json::Value| -> HashMap<String, TenantConfig> {
+            serde_json::from_value(value).unwrap()
+        };
+        assert!(validate_tenants(&tenants(serde_json::json!({"a": {}}))).is_err());
+        assert!(validate_tenants(&tenants(serde_json::json!({
+            "a": {"client_cert_subjects": ["CN=press-07, O=Acme"]},
+            "b": {"client_cert_subjects": ["cn=press-07,o=acme"]},
+        })))
+        .is_err());
+    }
+}
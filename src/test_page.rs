@@ -0,0 +1,211 @@
+// ----------------------------------------------------------------------
+//                              TEST PAGE
+// ----------------------------------------------------------------------
+
+use crate::pdf::{A6_HEIGHT_PTS, A6_WIDTH_PTS, PTS_PER_MM};
+use anyhow::Result;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+
+/// รูปแบบแท่งของ Code 39 (แท่งดำ/ช่องว่างสลับกัน เริ่มจากแท่งดำ) `n` = แคบ, `w` = กว้าง
+pub(crate) const CODE39_PATTERNS: [(char, &str); 11] = [
+    ('0', "nnnwwnwnn"),
+    ('1', "wnnwnnnnw"),
+    ('2', "nnwwnnnnw"),
+    ('3', "wnwwnnnnn"),
+    ('4', "nnnwwnnnw"),
+    ('5', "wnnwwnnnn"),
+    ('6', "nnwwwnnnn"),
+    ('7', "nnnwnnwnw"),
+    ('8', "wnnwnnwnn"),
+    ('9', "nnwwnnwnn"),
+    ('*', "nwnnwnwnn"),
+];
+
+/// สร้างคำสั่งวาดบาร์โค้ด Code 39 ของตัวเลข `digits` โดยมุมซ้ายล่างอยู่ที่ (x, y)
+pub(crate) fn code39_operations(
+    digits: &str,
+    x: f32,
+    y: f32,
+    narrow: f32,
+    height: f32,
+) -> Vec<Operation> {
+    let mut operations = vec![Operation::new("g", vec![Object::Real(0.0)])];
+    let mut cursor = x;
+    for c in format!("*{}*", digits).chars() {
+        let Some((_, pattern)) = CODE39_PATTERNS.iter().find(|(code, _)| *code == c) else {
+            continue;
+        };
+        for (i, element) in pattern.chars().enumerate() {
+            let width = if element == 'w' { narrow * 3.0 } else { narrow };
+            if i % 2 == 0 {
+                operations.push(Operation::new(
+                    "re",
+                    vec![
+                        Object::Real(cursor),
+                        Object::Real(y),
+                        Object::Real(width),
+                        Object::Real(height),
+                    ],
+                ));
+            }
+            cursor += width;
+        }
+        // ช่องว่างคั่นระหว่างตัวอักษร
+        cursor += narrow;
+    }
+    operations.push(Operation::new("f", vec![]));
+    operations
+}
+
+/// สร้างคำสั่งวาดข้อความหนึ่งบรรทัดด้วยฟอนต์ `font` ขนาด `size`
+pub(crate) fn text_operations(font: &str, size: f32, x: f32, y: f32, text: &str) -> Vec<Operation> {
+    vec![
+        Operation::new("BT", vec![]),
+        Operation::new(
+            "Tf",
+            vec![Object::Name(font.as_bytes().to_vec()), Object::Real(size)],
+        ),
+        Operation::new("Td", vec![Object::Real(x), Object::Real(y)]),
+        Operation::new("Tj", vec![Object::string_literal(text)]),
+        Operation::new("ET", vec![]),
+    ]
+}
+
+/// สร้างคำสั่งวาดไม้บรรทัดหน่วยมิลลิเมตรตามขอบซ้ายและขอบล่างของหน้า
+pub(crate) fn ruler_operations(width: f32, height: f32) -> Vec<Operation> {
+    let mut operations = vec![
+        Operation::new("w", vec![Object::Real(0.3)]),
+        Operation::new("G", vec![Object::Real(0.0)]),
+    ];
+    let mut line = |x1: f32, y1: f32, x2: f32, y2: f32| {
+        operations.push(Operation::new(
+            "m",
+            vec![Object::Real(x1), Object::Real(y1)],
+        ));
+        operations.push(Operation::new(
+            "l",
+            vec![Object::Real(x2), Object::Real(y2)],
+        ));
+    };
+    let tick_length = |mm: u32| match mm {
+        mm if mm % 10 == 0 => 4.0,
+        mm if mm % 5 == 0 => 2.5,
+        _ => 1.5,
+    } * PTS_PER_MM;
+
+    let mut labels = Vec::new();
+    let width_mm = (width / PTS_PER_MM) as u32;
+    for mm in 1..width_mm {
+        let x = mm as f32 * PTS_PER_MM;
+        line(x, 0.0, x, tick_length(mm));
+        if mm % 10 == 0 {
+            labels.push((x - 2.0, 4.5 * PTS_PER_MM, mm / 10));
+        }
+    }
+    let height_mm = (height / PTS_PER_MM) as u32;
+    for mm in 1..height_mm {
+        let y = mm as f32 * PTS_PER_MM;
+        line(0.0, y, tick_length(mm), y);
+        if mm % 10 == 0 {
+            labels.push((4.5 * PTS_PER_MM, y - 2.0, mm / 10));
+        }
+    }
+    operations.push(Operation::new("S", vec![]));
+
+    // ตัวเลขกำกับทุก 1 เซนติเมตร
+    for (x, y, cm) in labels {
+        operations.extend(text_operations("F1", 5.0, x, y, &cm.to_string()));
+    }
+    operations
+}
+
+/// สร้างหน้าทดสอบขนาด A6 ที่มีชื่อเครื่องพิมพ์ เวลาที่พิมพ์ ไม้บรรทัด และบาร์โค้ด
+pub(crate) fn build_test_page(printer_name: &str) -> Result<Document> {
+    let now = chrono::Local::now();
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+
+    let regular_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+    let bold_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica-Bold",
+    });
+
+    let (width, height) = (A6_WIDTH_PTS, A6_HEIGHT_PTS);
+    let left = 10.0 * PTS_PER_MM;
+    let mut operations = ruler_operations(width, height);
+
+    // กรอบห่างจากขอบกระดาษ 5 มม. สำหรับตรวจสอบการจัดตำแหน่ง
+    let inset = 5.0 * PTS_PER_MM;
+    operations.push(Operation::new(
+        "re",
+        vec![
+            Object::Real(inset),
+            Object::Real(inset),
+            Object::Real(width - inset * 2.0),
+            Object::Real(height - inset * 2.0),
+        ],
+    ));
+    operations.push(Operation::new("S", vec![]));
+
+    let mut y = height - 20.0 * PTS_PER_MM;
+    operations.extend(text_operations(
+        "F2",
+        14.0,
+        left,
+        y,
+        "Rust Print API - Test Page",
+    ));
+    for line in [
+        format!("Printer: {}", printer_name),
+        format!("Printed at: {}", now.format("%Y-%m-%d %H:%M:%S")),
+        "Paper: A6 (105 x 148 mm)".to_string(),
+        "Frame is 5 mm from each edge, ruler ticks every 1 mm".to_string(),
+    ] {
+        y -= 16.0;
+        operations.extend(text_operations("F1", 8.0, left, y, &line));
+    }
+
+    operations.extend(code39_operations(
+        &now.format("%Y%m%d%H%M%S").to_string(),
+        left,
+        30.0 * PTS_PER_MM,
+        0.7,
+        15.0 * PTS_PER_MM,
+    ));
+
+    let content = Content { operations };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode()?));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! {
+                "F1" => regular_id,
+                "F2" => bold_id,
+            },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    Ok(doc)
+}
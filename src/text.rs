@@ -0,0 +1,91 @@
+// ----------------------------------------------------------------------
+//                           TEXT EXTRACTION
+// ----------------------------------------------------------------------
+
+use crate::pdf::load_pdf_bytes;
+use anyhow::{bail, Result};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// ข้อความของหนึ่งหน้า
+#[derive(Serialize, ToSchema)]
+pub(crate) struct PageText {
+    #[schema(example = 1)]
+    page: u32,
+    #[schema(example = "INVOICE\nNo. INV-0001\n")]
+    pub(crate) text: String,
+}
+
+/// ข้อความในไฟล์ PDF แยกตามหน้า สำหรับระบบทำดัชนีเอกสาร
+#[derive(Serialize, ToSchema)]
+pub(crate) struct PdfText {
+    pub(crate) page_count: usize,
+    pub(crate) pages: Vec<PageText>,
+    /// ส่วนที่อ่านข้อความไม่ได้ เช่น ฟอนต์ที่ไม่มี encoding หรือ `ToUnicode` ที่รองรับ
+    warnings: Vec<String>,
+}
+
+/// ดึงข้อความจากหน้า `pages` (ว่าง = ทุกหน้า) ตามลำดับของคำสั่งข้อความใน content stream
+///
+/// ข้อความในรูปภาพ (เอกสารสแกน) และใน Form XObject จะไม่ถูกอ่าน
+pub(crate) fn extract_pdf_text(
+    data: Vec<u8>,
+    source_name: &str,
+    password: Option<&str>,
+    pages: &[u32],
+) -> Result<PdfText> {
+    let doc = load_pdf_bytes(data, source_name, password)?;
+    let page_count = doc.get_pages().len();
+    if let Some(page) = pages
+        .iter()
+        .find(|page| **page == 0 || **page as usize > page_count)
+    {
+        bail!(PageOutOfRange {
+            page: *page,
+            page_count
+        });
+    }
+    let pages: Vec<u32> = match pages.is_empty() {
+        true => (1..=page_count as u32).collect(),
+        false => pages.to_vec(),
+    };
+    let mut warnings = Vec::new();
+    let pages = pages
+        .into_iter()
+        .map(|page| {
+            let mut text = String::new();
+            for chunk in doc.extract_text_chunks(&[page]) {
+                match chunk {
+                    Ok(chunk) => text.push_str(&chunk),
+                    Err(e) => warnings.push(format!("Page {}: {}", page, e)),
+                }
+            }
+            PageText { page, text }
+        })
+        .collect();
+    warnings.dedup();
+    Ok(PdfText {
+        page_count,
+        pages,
+        warnings,
+    })
+}
+
+/// หมายเลขหน้าที่ขอไม่อยู่ในไฟล์
+#[derive(Debug)]
+pub(crate) struct PageOutOfRange {
+    page: u32,
+    page_count: usize,
+}
+
+impl std::fmt::Display for PageOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Page {} is out of range (the document has {} pages)",
+            self.page, self.page_count
+        )
+    }
+}
+
+impl std::error::Error for PageOutOfRange {}
@@ -0,0 +1,846 @@
+// ----------------------------------------------------------------------
+//                          TRANSFORM PIPELINE
+// ----------------------------------------------------------------------
+
+use crate::{
+    counters::{counter_placeholders, JobCounters},
+    locale::LocaleFormat,
+    pdf::{
+        apply_fit_policy, convert_objects_to_grayscale, convert_page_operations_to_grayscale,
+        embed_missing_fonts, fit_scale, load_pdf_bytes, normalize_pages, optimize_pdf,
+        page_media_box, page_rotation, page_transform_matrix, select_pages, FitPolicy,
+        TransformOptions, TransformReport, A4_HEIGHT_PTS, A4_WIDTH_PTS, A6_HEIGHT_PTS,
+        A6_WIDTH_PTS, PTS_PER_MM,
+    },
+    stamp_font::StampFont,
+};
+use anyhow::{Context, Result};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use utoipa::ToSchema;
+
+/// ขนาดกระดาษที่ใช้กับขั้นตอน `resize`
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug)]
+pub(crate) enum PaperName {
+    A4,
+    A5,
+    A6,
+    Letter,
+}
+
+impl PaperName {
+    /// ขนาดกระดาษแนวตั้ง (กว้าง, สูง) ในหน่วย points
+    pub(crate) fn size_pts(self) -> (f32, f32) {
+        match self {
+            PaperName::A4 => (A4_WIDTH_PTS, A4_HEIGHT_PTS),
+            PaperName::A5 => (419.53, 595.28),
+            PaperName::A6 => (A6_WIDTH_PTS, A6_HEIGHT_PTS),
+            PaperName::Letter => (612.0, 792.0),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            PaperName::A4 => "A4",
+            PaperName::A5 => "A5",
+            PaperName::A6 => "A6",
+            PaperName::Letter => "Letter",
+        }
+    }
+}
+
+pub(crate) fn default_stamp_font_size() -> f32 {
+    48.0
+}
+
+pub(crate) fn default_stamp_angle() -> f32 {
+    45.0
+}
+
+pub(crate) fn default_page_number_format() -> String {
+    "Page {page} of {pages}".to_string()
+}
+
+pub(crate) fn default_page_number_font_size() -> f32 {
+    9.0
+}
+
+pub(crate) fn default_page_number_margin_mm() -> f32 {
+    5.0
+}
+
+/// ตำแหน่งของเลขหน้าบนกระดาษ
+#[derive(Clone, Copy, Default, Serialize, Deserialize, ToSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PageNumberPosition {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomCenter,
+    BottomRight,
+}
+
+/// ขั้นตอนแปลงไฟล์หนึ่งขั้นใน `transforms` ซึ่งทำงานตามลำดับที่ระบุ
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum TransformStep {
+    /// หมุนทุกหน้าตามเข็มนาฬิกา (ขนาดหน้าจะสลับด้านเมื่อหมุน 90 หรือ 270 องศา)
+    Rotate { deg: u16 },
+    /// ย่อเนื้อหาให้พอดีกับกระดาษ `paper`
+    Resize {
+        paper: PaperName,
+        /// ระยะขอบว่างรอบหน้ากระดาษ (มม.)
+        #[serde(default)]
+        margin_mm: f32,
+        /// หมุน 90 องศาเฉพาะหน้าที่หมุนแล้วย่อได้ใหญ่กว่า
+        #[serde(default)]
+        auto_rotate: bool,
+        /// วิธีจัดการหน้าที่เล็กกว่ากระดาษ `paper`
+        #[serde(default)]
+        fit_policy: FitPolicy,
+    },
+    /// พิมพ์ข้อความสีเทาโปร่งแสงทับกลางหน้า เช่น `"COPY"` (รองรับเฉพาะตัวอักษร ASCII)
+    /// `{date}` = วันที่พิมพ์ตามภาษาของ request และ `locale`
+    Stamp {
+        text: String,
+        #[serde(default = "default_stamp_font_size")]
+        font_size: f32,
+        /// มุมของข้อความ (องศา ทวนเข็มนาฬิกา)
+        #[serde(default = "default_stamp_angle")]
+        angle: f32,
+        /// รูปแบบของ `{date}` (ไม่ระบุ = ค่าของ tenant หรือตามภาษาของ request)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        locale: Option<LocaleFormat>,
+    },
+    /// พิมพ์เลขหน้าลงบนทุกหน้า เช่น `"Page 1 of 3"` (รองรับเฉพาะตัวอักษร ASCII)
+    PageNumbers {
+        /// รูปแบบข้อความ `{page}` = หน้าปัจจุบัน, `{pages}` = จำนวนหน้าทั้งหมด, `{date}` = วันที่พิมพ์
+        #[serde(default = "default_page_number_format")]
+        format: String,
+        #[serde(default)]
+        position: PageNumberPosition,
+        #[serde(default = "default_page_number_font_size")]
+        font_size: f32,
+        /// ระยะจากขอบกระดาษ (มม.)
+        #[serde(default = "default_page_number_margin_mm")]
+        margin_mm: f32,
+        /// รูปแบบของ `{date}` และตัวเลขของ `{page}`/`{pages}`
+        /// (ไม่ระบุ = ค่าของ tenant หรือตามภาษาของ request)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        locale: Option<LocaleFormat>,
+    },
+    /// พิมพ์ข้อความสีดำลงบนทุกหน้า เช่น footer `"CONFIDENTIAL"` (รองรับเฉพาะตัวอักษร ASCII)
+    /// `{page}`, `{pages}` และ `{date}` ใช้ได้เหมือน `page_numbers`
+    Text {
+        text: String,
+        #[serde(default)]
+        position: PageNumberPosition,
+        #[serde(default = "default_page_number_font_size")]
+        font_size: f32,
+        /// ระยะจากขอบกระดาษ (มม.)
+        #[serde(default = "default_page_number_margin_mm")]
+        margin_mm: f32,
+        /// รูปแบบของ `{date}` และตัวเลขของ `{page}`/`{pages}`
+        /// (ไม่ระบุ = ค่าของ tenant หรือตามภาษาของ request)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        locale: Option<LocaleFormat>,
+    },
+    /// แปลงเอกสารเป็นสีเทา
+    Grayscale,
+}
+
+impl TransformStep {
+    /// ข้อความของขั้นตอน `stamp`, `page_numbers` และ `text`
+    pub(crate) fn text(&self) -> Option<&str> {
+        match self {
+            TransformStep::Stamp { text, .. }
+            | TransformStep::PageNumbers { format: text, .. }
+            | TransformStep::Text { text, .. } => Some(text),
+            _ => None,
+        }
+    }
+
+    /// `locale` ของขั้นตอน `stamp`, `page_numbers` และ `text`
+    pub(crate) fn locale(&self) -> Option<&LocaleFormat> {
+        match self {
+            TransformStep::Stamp { locale, .. }
+            | TransformStep::PageNumbers { locale, .. }
+            | TransformStep::Text { locale, .. } => locale.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// ตรวจสอบค่าของแต่ละขั้นตอนก่อนเริ่มแปลงไฟล์ คืนค่าข้อความ Error ของขั้นตอนแรกที่ไม่ถูกต้อง
+pub(crate) fn validate_transform_steps(steps: &[TransformStep]) -> std::result::Result<(), String> {
+    for (position, step) in steps.iter().enumerate() {
+        let invalid = |message: String| Err(format!("transforms[{}]: {}", position, message));
+        if let Some(Err(e)) = step.locale().map(LocaleFormat::validate) {
+            return invalid(format!("locale.{}", e));
+        }
+        if let Some(Err(e)) = step.text().map(counter_placeholders) {
+            return invalid(e);
+        }
+        match step {
+            TransformStep::Rotate { deg } if ![0, 90, 180, 270].contains(deg) => {
+                return invalid(format!(
+                    "invalid deg value {} (expected 0, 90, 180 or 270)",
+                    deg
+                ));
+            }
+            TransformStep::Resize {
+                paper, margin_mm, ..
+            } => {
+                let (width, height) = paper.size_pts();
+                let max_margin_mm = width.min(height) / PTS_PER_MM / 2.0;
+                if !(0.0..max_margin_mm).contains(margin_mm) {
+                    return invalid(format!(
+                        "invalid margin_mm value {} (expected 0 to less than {:.1})",
+                        margin_mm, max_margin_mm
+                    ));
+                }
+            }
+            TransformStep::Stamp {
+                text, font_size, ..
+            } => {
+                if text.is_empty() || !text.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+                    return invalid("stamp text must be non-empty printable ASCII".to_string());
+                }
+                if !(1.0..=200.0).contains(font_size) {
+                    return invalid(format!(
+                        "invalid font_size value {} (expected 1 to 200)",
+                        font_size
+                    ));
+                }
+            }
+            TransformStep::PageNumbers {
+                format,
+                font_size,
+                margin_mm,
+                ..
+            } => {
+                if !format.contains("{page}")
+                    || !format
+                        .chars()
+                        .all(|c| c.is_ascii() && !c.is_ascii_control())
+                {
+                    return invalid(
+                        "page number format must be printable ASCII containing {page}".to_string(),
+                    );
+                }
+                if !(1.0..=72.0).contains(font_size) {
+                    return invalid(format!(
+                        "invalid font_size value {} (expected 1 to 72)",
+                        font_size
+                    ));
+                }
+                if !(0.0..=50.0).contains(margin_mm) {
+                    return invalid(format!(
+                        "invalid margin_mm value {} (expected 0 to 50)",
+                        margin_mm
+                    ));
+                }
+            }
+            TransformStep::Text {
+                text,
+                font_size,
+                margin_mm,
+                ..
+            } => {
+                if text.is_empty() || !text.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+                    return invalid("text must be non-empty printable ASCII".to_string());
+                }
+                if !(1.0..=72.0).contains(font_size) {
+                    return invalid(format!(
+                        "invalid font_size value {} (expected 1 to 72)",
+                        font_size
+                    ));
+                }
+                if !(0.0..=50.0).contains(margin_mm) {
+                    return invalid(format!(
+                        "invalid margin_mm value {} (expected 0 to 50)",
+                        margin_mm
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// วาดเนื้อหาเดิมของหน้าผ่าน `matrix` ลงบนหน้าใหม่ขนาด `width` x `height`
+pub(crate) fn redraw_page(
+    doc: &mut Document,
+    page_id: ObjectId,
+    matrix: [f32; 6],
+    width: f32,
+    height: f32,
+) -> Result<()> {
+    if let Ok(page) = doc.get_dictionary_mut(page_id) {
+        page.set(
+            "MediaBox",
+            vec![
+                Object::Real(0.0),
+                Object::Real(0.0),
+                Object::Real(width),
+                Object::Real(height),
+            ],
+        );
+        // กรอบอื่นอ้างอิงพิกัดเดิมซึ่งไม่ตรงกับหน้าใหม่แล้ว และการหมุนของหน้าอยู่ใน `matrix`
+        for key in [
+            b"CropBox".as_slice(),
+            b"TrimBox",
+            b"BleedBox",
+            b"ArtBox",
+            b"Rotate",
+        ] {
+            page.remove(key);
+        }
+    }
+
+    let mut content = Content::decode(&doc.get_page_content(page_id)?)?;
+    content.operations.insert(
+        0,
+        Operation::new("cm", matrix.into_iter().map(Object::Real).collect()),
+    );
+    // แยก graphics state ของเนื้อหาเดิม เพื่อให้ขั้นตอนถัดไปวาดในพิกัดของหน้าใหม่ได้
+    content.operations.insert(0, Operation::new("q", vec![]));
+    content.operations.push(Operation::new("Q", vec![]));
+    doc.change_page_content(page_id, content.encode()?)?;
+    Ok(())
+}
+
+pub(crate) fn rotate_pages(doc: &mut Document, deg: u16) -> Result<()> {
+    for (_, page_id) in doc.get_pages() {
+        let media_box = page_media_box(doc, page_id);
+        let rotate = (deg + page_rotation(doc, page_id)) % 360;
+        let (mut width, mut height) = (media_box[2] - media_box[0], media_box[3] - media_box[1]);
+        if rotate == 90 || rotate == 270 {
+            std::mem::swap(&mut width, &mut height);
+        }
+        let matrix = page_transform_matrix(media_box, rotate, 1.0);
+        redraw_page(doc, page_id, matrix, width, height)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn fit_pages(
+    doc: &mut Document,
+    paper: PaperName,
+    margin_mm: f32,
+    auto_rotate: bool,
+    fit_policy: FitPolicy,
+) -> Result<()> {
+    let (page_width, page_height) = paper.size_pts();
+    let margin = margin_mm * PTS_PER_MM;
+    let fit_width = page_width - 2.0 * margin;
+    let fit_height = page_height - 2.0 * margin;
+
+    for (_, page_id) in doc.get_pages() {
+        let media_box = page_media_box(doc, page_id);
+        let mut rotate = page_rotation(doc, page_id);
+        let mut scale_factor = fit_scale(media_box, rotate, fit_width, fit_height);
+        if auto_rotate {
+            let turned = (rotate + 90) % 360;
+            let turned_scale = fit_scale(media_box, turned, fit_width, fit_height);
+            if turned_scale > scale_factor {
+                rotate = turned;
+                scale_factor = turned_scale;
+            }
+        }
+
+        let (scale_factor, [center_x, center_y]) = apply_fit_policy(
+            fit_policy,
+            media_box,
+            rotate,
+            scale_factor,
+            fit_width,
+            fit_height,
+            paper.name(),
+        )?;
+
+        let mut matrix = page_transform_matrix(media_box, rotate, scale_factor);
+        matrix[4] += margin + center_x;
+        matrix[5] += margin + center_y;
+        redraw_page(doc, page_id, matrix, page_width, page_height)?;
+    }
+    Ok(())
+}
+
+/// เพิ่ม resource `name` ในหมวด `category` (เช่น Font, ExtGState) ให้กับหน้า
+///
+/// หากหน้าใช้ Resources ที่สืบทอดมาจาก Pages จะคัดลอกมาเป็นของหน้านี้ก่อน เพื่อไม่ให้ resource เดิมหายไป
+pub(crate) fn add_page_resource(
+    doc: &mut Document,
+    page_id: ObjectId,
+    category: &[u8],
+    name: &str,
+    object_id: ObjectId,
+) -> Result<()> {
+    let page = doc.get_dictionary(page_id)?;
+    let mut resources = match page.get(b"Resources") {
+        Ok(Object::Reference(id)) => doc.get_dictionary(*id)?.clone(),
+        Ok(Object::Dictionary(resources)) => resources.clone(),
+        _ => {
+            let (_, inherited) = doc.get_page_resources(page_id)?;
+            match inherited.first() {
+                Some(id) => doc.get_dictionary(*id)?.clone(),
+                None => Dictionary::new(),
+            }
+        }
+    };
+
+    let mut entries = match resources.get(category) {
+        Ok(Object::Reference(id)) => doc.get_dictionary(*id)?.clone(),
+        Ok(Object::Dictionary(entries)) => entries.clone(),
+        _ => Dictionary::new(),
+    };
+    entries.set(name, Object::Reference(object_id));
+    resources.set(category, Object::Dictionary(entries));
+
+    // เขียนเป็น Resources ของหน้านี้โดยตรง เพราะ object เดิมอาจใช้ร่วมกับหน้าอื่น
+    doc.get_dictionary_mut(page_id)?
+        .set("Resources", Object::Dictionary(resources));
+    Ok(())
+}
+
+/// ฟอนต์สำหรับพิมพ์ `texts` (ข้อความ ASCII ทั้งหมด = `None` เพื่อใช้ฟอนต์มาตรฐานของ PDF)
+pub(crate) fn stamp_font_for(
+    texts: &[String],
+    stamp_font: Option<&Path>,
+) -> Result<Option<StampFont>> {
+    if texts.iter().all(|text| text.is_ascii()) {
+        return Ok(None);
+    }
+    let path = stamp_font
+        .context("Printing Thai digits or month names requires stamp_font in config.json")?;
+    StampFont::load(path).map(Some)
+}
+
+pub(crate) fn stamp_pages(
+    doc: &mut Document,
+    text: &str,
+    font_size: f32,
+    angle: f32,
+    stamp_font: Option<&Path>,
+) -> Result<()> {
+    let texts = [text.to_string()];
+    let (font_id, width, encoded) = match stamp_font_for(&texts, stamp_font)? {
+        Some(font) => (
+            font.add_to(doc, &texts)?,
+            font.text_width(text, font_size)?,
+            font.encode(text)?,
+        ),
+        None => {
+            let font_id = doc.add_object(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => "Helvetica-Bold",
+                "Encoding" => "WinAnsiEncoding",
+            });
+            // ความกว้างโดยประมาณของ Helvetica-Bold
+            let width = text.len() as f32 * font_size * 0.6;
+            (font_id, width, Object::string_literal(text))
+        }
+    };
+    let state_id = doc.add_object(dictionary! {
+        "Type" => "ExtGState",
+        "ca" => Object::Real(0.35),
+    });
+
+    let (sin, cos) = angle.to_radians().sin_cos();
+    // ครึ่งหนึ่งของความกว้างข้อความ และครึ่งหนึ่งของความสูงตัวอักษร
+    let half_width = width / 2.0;
+    let half_height = font_size * 0.36;
+
+    for (_, page_id) in doc.get_pages() {
+        add_page_resource(doc, page_id, b"Font", "StampFont", font_id)?;
+        add_page_resource(doc, page_id, b"ExtGState", "StampState", state_id)?;
+
+        let [llx, lly, urx, ury] = page_media_box(doc, page_id);
+        let (center_x, center_y) = ((llx + urx) / 2.0, (lly + ury) / 2.0);
+        let x = center_x - cos * half_width + sin * half_height;
+        let y = center_y - sin * half_width - cos * half_height;
+
+        let mut content = Content::decode(&doc.get_page_content(page_id)?)?;
+        content.operations.insert(0, Operation::new("q", vec![]));
+        content.operations.push(Operation::new("Q", vec![]));
+        content.operations.extend([
+            Operation::new("q", vec![]),
+            Operation::new("gs", vec![Object::Name(b"StampState".to_vec())]),
+            Operation::new("g", vec![Object::Real(0.5)]),
+            Operation::new("BT", vec![]),
+            Operation::new(
+                "Tf",
+                vec![Object::Name(b"StampFont".to_vec()), Object::Real(font_size)],
+            ),
+            Operation::new(
+                "Tm",
+                [cos, sin, -sin, cos, x, y]
+                    .into_iter()
+                    .map(Object::Real)
+                    .collect(),
+            ),
+            Operation::new("Tj", vec![encoded.clone()]),
+            Operation::new("ET", vec![]),
+            Operation::new("Q", vec![]),
+        ]);
+        doc.change_page_content(page_id, content.encode()?)?;
+    }
+    Ok(())
+}
+
+/// พิมพ์ข้อความ `format` ลงบนทุกหน้า โดยแทน `{page}` และ `{pages}` ด้วยตัวเลขตาม `locale`
+pub(crate) fn number_pages(
+    doc: &mut Document,
+    format: &str,
+    position: PageNumberPosition,
+    font_size: f32,
+    margin_mm: f32,
+    locale: &LocaleFormat,
+    stamp_font: Option<&Path>,
+) -> Result<()> {
+    let margin = margin_mm * PTS_PER_MM;
+    let pages = doc.get_pages();
+    let total = locale.localize_digits(&pages.len().to_string());
+    let texts = pages
+        .keys()
+        .map(|number| {
+            format
+                .replace("{pages}", &total)
+                .replace("{page}", &locale.localize_digits(&number.to_string()))
+        })
+        .collect::<Vec<_>>();
+    let font = stamp_font_for(&texts, stamp_font)?;
+    let font_id = match &font {
+        Some(font) => font.add_to(doc, &texts)?,
+        None => doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        }),
+    };
+
+    for ((_, page_id), text) in pages.into_iter().zip(texts) {
+        add_page_resource(doc, page_id, b"Font", "PageNumberFont", font_id)?;
+
+        let (width, encoded) = match &font {
+            Some(font) => (font.text_width(&text, font_size)?, font.encode(&text)?),
+            // ความกว้างโดยประมาณของ Helvetica (ตัวเลขกว้าง 0.556 em)
+            None => (
+                text.len() as f32 * font_size * 0.52,
+                Object::string_literal(text),
+            ),
+        };
+        let [llx, lly, urx, ury] = page_media_box(doc, page_id);
+        let x = match position {
+            PageNumberPosition::TopLeft | PageNumberPosition::BottomLeft => llx + margin,
+            PageNumberPosition::TopCenter | PageNumberPosition::BottomCenter => {
+                (llx + urx - width) / 2.0
+            }
+            PageNumberPosition::TopRight | PageNumberPosition::BottomRight => urx - margin - width,
+        };
+        let y = match position {
+            PageNumberPosition::TopLeft
+            | PageNumberPosition::TopCenter
+            | PageNumberPosition::TopRight => ury - margin - font_size * 0.72,
+            _ => lly + margin,
+        };
+
+        let mut content = Content::decode(&doc.get_page_content(page_id)?)?;
+        content.operations.insert(0, Operation::new("q", vec![]));
+        content.operations.push(Operation::new("Q", vec![]));
+        content.operations.extend([
+            Operation::new("q", vec![]),
+            Operation::new("g", vec![Object::Real(0.0)]),
+            Operation::new("BT", vec![]),
+            Operation::new(
+                "Tf",
+                vec![
+                    Object::Name(b"PageNumberFont".to_vec()),
+                    Object::Real(font_size),
+                ],
+            ),
+            Operation::new("Td", vec![Object::Real(x), Object::Real(y)]),
+            Operation::new("Tj", vec![encoded]),
+            Operation::new("ET", vec![]),
+            Operation::new("Q", vec![]),
+        ]);
+        doc.change_page_content(page_id, content.encode()?)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn grayscale_pages(doc: &mut Document) -> Result<()> {
+    for (_, page_id) in doc.get_pages() {
+        let mut content = Content::decode(&doc.get_page_content(page_id)?)?;
+        convert_page_operations_to_grayscale(doc, page_id, &mut content.operations);
+        doc.change_page_content(page_id, content.encode()?)?;
+    }
+    let converted = convert_objects_to_grayscale(doc);
+    println!("Converted PDF to grayscale ({} images/forms)", converted);
+    Ok(())
+}
+
+/// แปลงไฟล์ PDF ตามขั้นตอนใน `steps` ตามลำดับ แทนขั้นตอนแปลงเป็น A6 แบบเดิม
+///
+/// ใช้เฉพาะ `pdf_password`, `optimize`, `font_fallbacks`, `language`, `locale`, `stamp_font`,
+/// `page_set` และ `reverse` จาก `options`
+pub(crate) fn run_transform_pipeline(
+    input: Vec<u8>,
+    source_name: &str,
+    steps: &[TransformStep],
+    options: &TransformOptions,
+) -> Result<(Vec<u8>, TransformReport)> {
+    let mut doc = load_pdf_bytes(input, source_name, options.pdf_password)?;
+    let source_pages = doc.get_pages().len();
+    normalize_pages(&mut doc)?;
+    select_pages(&mut doc, options.page_set, options.reverse)?;
+
+    let warnings = embed_missing_fonts(&mut doc, options.font_fallbacks);
+    for warning in &warnings {
+        println!("Warning: {}", warning);
+    }
+
+    let today = chrono::Local::now().date_naive();
+    // รูปแบบวันที่และตัวเลขของขั้นตอน โดยใช้ค่าเริ่มต้นของ tenant สำหรับค่าที่ไม่ได้ระบุ
+    let locale_of =
+        |locale: &Option<LocaleFormat>| locale.clone().unwrap_or_default().or(options.locale);
+    let expand_counters = |text: &str, locale: &LocaleFormat| match options.counters {
+        Some(counters) => counters.expand(text, locale),
+        None => JobCounters::new(None, None).expand(text, locale),
+    };
+    let mut paper_name = None;
+    for step in steps {
+        match step {
+            TransformStep::Rotate { deg } => rotate_pages(&mut doc, *deg)?,
+            TransformStep::Resize {
+                paper,
+                margin_mm,
+                auto_rotate,
+                fit_policy,
+            } => {
+                fit_pages(&mut doc, *paper, *margin_mm, *auto_rotate, *fit_policy)?;
+                paper_name = Some(paper.name());
+            }
+            TransformStep::Stamp {
+                text,
+                font_size,
+                angle,
+                locale,
+            } => {
+                let locale = locale_of(locale);
+                let date = locale.format_date(options.language, today);
+                stamp_pages(
+                    &mut doc,
+                    &expand_counters(&text.replace("{date}", &date), &locale)?,
+                    *font_size,
+                    *angle,
+                    options.stamp_font,
+                )?
+            }
+            TransformStep::PageNumbers {
+                format: text,
+                position,
+                font_size,
+                margin_mm,
+                locale,
+            }
+            | TransformStep::Text {
+                text,
+                position,
+                font_size,
+                margin_mm,
+                locale,
+            } => {
+                let locale = locale_of(locale);
+                let date = locale.format_date(options.language, today);
+                number_pages(
+                    &mut doc,
+                    &expand_counters(&text.replace("{date}", &date), &locale)?,
+                    *position,
+                    *font_size,
+                    *margin_mm,
+                    &locale,
+                    options.stamp_font,
+                )?
+            }
+            TransformStep::Grayscale => grayscale_pages(&mut doc)?,
+        }
+    }
+
+    if let Some(optimize) = options.optimize {
+        optimize_pdf(&mut doc, optimize);
+    }
+
+    // ขนาดกระดาษในรายงานใช้หน้าแรก (ขั้นตอน rotate อย่างเดียวอาจทำให้แต่ละหน้ามีขนาดต่างกัน)
+    let [llx, lly, urx, ury] = doc
+        .get_pages()
+        .values()
+        .next()
+        .map(|page_id| page_media_box(&doc, *page_id))
+        .unwrap_or([0.0, 0.0, A4_WIDTH_PTS, A4_HEIGHT_PTS]);
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output).context(format!(
+        "Failed to write transformed PDF of {}",
+        source_name
+    ))?;
+
+    let report = TransformReport {
+        warnings,
+        source_pages,
+        output_pages: doc.get_pages().len(),
+        page_width_pts: urx - llx,
+        page_height_pts: ury - lly,
+        paper_name: paper_name.unwrap_or("Custom"),
+    };
+    Ok((output, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::tests::{assert_close, first_matrix, test_options, test_pdf, TEST_CONTENT};
+
+    const A4: [f32; 2] = [595.0, 842.0];
+
+    fn steps(value: serde_json::Value) -> Vec<TransformStep> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// ไฟล์ผลลัพธ์ของ `run_transform_pipeline` รายงาน และ id ของหน้าแรก
+    fn transform(
+        page: [f32; 2],
+        value: serde_json::Value,
+    ) -> Result<(Document, ObjectId, TransformReport)> {
+        let input = test_pdf(&[(page, 0)], TEST_CONTENT);
+        let (output, report) =
+            run_transform_pipeline(input, "test.pdf", &steps(value), &test_options())?;
+        let doc = Document::load_mem(&output).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        Ok((doc, page_id, report))
+    }
+
+    #[test]
+    fn steps_run_in_the_order_given() {
+        // หมุนก่อนแล้วย่อ: หน้าแนวนอนถูกย่อลงกระดาษ A6 แนวตั้ง
+        let (doc, page_id, report) = transform(
+            A4,
+            serde_json::json!([{"op": "rotate", "deg": 90}, {"op": "resize", "paper": "A6"}]),
+        )
+        .unwrap();
+        let scale = (A6_WIDTH_PTS / 842.0).min(A6_HEIGHT_PTS / 595.0);
+        assert_close(
+            first_matrix(&doc, page_id),
+            [scale, 0.0, 0.0, scale, 0.0, 0.0],
+        );
+        assert_close(
+            page_media_box(&doc, page_id),
+            [0.0, 0.0, A6_WIDTH_PTS, A6_HEIGHT_PTS],
+        );
+        assert_eq!(report.paper_name, "A6");
+
+        // ย่อก่อนแล้วหมุน: ได้กระดาษ A6 แนวนอน และคำสั่ง cm แรกเป็นของขั้นตอนสุดท้าย
+        let (doc, page_id, report) = transform(
+            A4,
+            serde_json::json!([{"op": "resize", "paper": "A6"}, {"op": "rotate", "deg": 90}]),
+        )
+        .unwrap();
+        assert_close(
+            first_matrix(&doc, page_id),
+            [0.0, -1.0, 1.0, 0.0, 0.0, A6_WIDTH_PTS],
+        );
+        assert_close(
+            [report.page_width_pts, report.page_height_pts],
+            [A6_HEIGHT_PTS, A6_WIDTH_PTS],
+        );
+        assert_eq!((report.source_pages, report.output_pages), (1, 1));
+
+        // ไม่มีขั้นตอน resize จึงไม่มีชื่อขนาดกระดาษ
+        let (_, _, report) =
+            transform(A4, serde_json::json!([{"op": "rotate", "deg": 180}])).unwrap();
+        assert_eq!(report.paper_name, "Custom");
+        assert_close([report.page_width_pts, report.page_height_pts], A4);
+    }
+
+    #[test]
+    fn resize_fits_pages_inside_the_margin() {
+        let (doc, page_id, _) = transform(
+            A4,
+            serde_json::json!([{"op": "resize", "paper": "A6", "margin_mm": 5.0}]),
+        )
+        .unwrap();
+        let margin = 5.0 * PTS_PER_MM;
+        let scale =
+            ((A6_WIDTH_PTS - 2.0 * margin) / 595.0).min((A6_HEIGHT_PTS - 2.0 * margin) / 842.0);
+        assert_close(
+            first_matrix(&doc, page_id),
+            [scale, 0.0, 0.0, scale, margin, margin],
+        );
+    }
+
+    #[test]
+    fn resize_applies_the_fit_policy_to_small_pages() {
+        let small = [200.0, 300.0];
+        let e = transform(small, serde_json::json!([{"op": "resize", "paper": "A6"}]))
+            .err()
+            .unwrap();
+        assert!(
+            e.to_string().starts_with("Scaling up is not handled"),
+            "{}",
+            e
+        );
+
+        let (doc, page_id, _) = transform(
+            small,
+            serde_json::json!([{"op": "resize", "paper": "A6", "fit_policy": "shrink_only"}]),
+        )
+        .unwrap();
+        assert_close(
+            first_matrix(&doc, page_id),
+            [
+                1.0,
+                0.0,
+                0.0,
+                1.0,
+                (A6_WIDTH_PTS - 200.0) / 2.0,
+                (A6_HEIGHT_PTS - 300.0) / 2.0,
+            ],
+        );
+
+        let (doc, page_id, _) = transform(
+            small,
+            serde_json::json!([{"op": "resize", "paper": "A6", "fit_policy": "allow_upscale"}]),
+        )
+        .unwrap();
+        let scale = (A6_WIDTH_PTS / 200.0).min(A6_HEIGHT_PTS / 300.0);
+        assert_close(
+            first_matrix(&doc, page_id),
+            [scale, 0.0, 0.0, scale, 0.0, 0.0],
+        );
+    }
+
+    #[test]
+    fn grayscale_converts_page_colors() {
+        let (doc, page_id, _) = transform(A4, serde_json::json!([{"op": "grayscale"}])).unwrap();
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        assert!(!content.operations.iter().any(|o| o.operator == "rg"));
+        let gray = content
+            .operations
+            .iter()
+            .find(|o| o.operator == "g")
+            .expect("fill color was not converted");
+        let level = gray.operands[0].as_float().unwrap();
+        assert!((level - (0.299 * 0.2 + 0.587 * 0.4 + 0.114 * 0.8)).abs() < 0.001);
+        // ข้อความและรูปทรงเดิมยังอยู่ครบ
+        assert!(content.operations.iter().any(|o| o.operator == "Tj"));
+        assert!(content.operations.iter().any(|o| o.operator == "re"));
+    }
+}
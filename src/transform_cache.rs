@@ -0,0 +1,263 @@
+// ----------------------------------------------------------------------
+//                            TRANSFORM CACHE
+// ----------------------------------------------------------------------
+
+use crate::pdf::TransformReport;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+/// อายุของผลการแปลงใน cache เมื่อไม่ได้ตั้งค่า `transform_cache.ttl_minutes` (นาที)
+pub(crate) const DEFAULT_TRANSFORM_CACHE_TTL_MINUTES: u64 = 60;
+/// จำนวนรายการสูงสุดเมื่อไม่ได้ตั้งค่า `transform_cache.max_entries`
+pub(crate) const DEFAULT_TRANSFORM_CACHE_MAX_ENTRIES: usize = 100;
+/// ขนาดรวมสูงสุดเมื่อไม่ได้ตั้งค่า `transform_cache.max_mb` (MB)
+pub(crate) const DEFAULT_TRANSFORM_CACHE_MAX_MB: u64 = 256;
+/// อายุสูงสุดที่ตั้งให้รายการได้ด้วย `PATCH /api/v1/admin/cache/{key}` (1 ปี)
+pub(crate) const MAX_TRANSFORM_CACHE_TTL_MINUTES: u64 = 525_600;
+
+/// การตั้งค่าของ `transform_cache` ใน config.json
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub(crate) struct TransformCacheConfig {
+    /// อายุของผลการแปลงแต่ละรายการ (นาที, ไม่ระบุ = 60) ปรับรายรายการได้ด้วย `PATCH /api/v1/admin/cache/{key}`
+    ttl_minutes: Option<u64>,
+    /// จำนวนรายการสูงสุด (ไม่ระบุ = 100)
+    max_entries: Option<usize>,
+    /// ขนาดรวมสูงสุดของไฟล์ที่แปลงแล้ว (MB, ไม่ระบุ = 256)
+    max_mb: Option<u64>,
+}
+
+impl TransformCacheConfig {
+    fn ttl_minutes(&self) -> u64 {
+        self.ttl_minutes
+            .unwrap_or(DEFAULT_TRANSFORM_CACHE_TTL_MINUTES)
+    }
+
+    fn max_entries(&self) -> usize {
+        self.max_entries
+            .unwrap_or(DEFAULT_TRANSFORM_CACHE_MAX_ENTRIES)
+    }
+
+    fn max_bytes(&self) -> u64 {
+        self.max_mb.unwrap_or(DEFAULT_TRANSFORM_CACHE_MAX_MB) * 1024 * 1024
+    }
+}
+
+/// ผลการแปลงไฟล์หนึ่งรายการใน cache
+pub(crate) struct CachedTransform {
+    filename: String,
+    tenant: Option<String>,
+    source_sha256: String,
+    data: Vec<u8>,
+    report: TransformReport,
+    created_at: chrono::DateTime<chrono::Local>,
+    ttl_minutes: u64,
+    hits: u64,
+    last_hit_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl CachedTransform {
+    fn expires_at(&self) -> chrono::DateTime<chrono::Local> {
+        self.created_at + chrono::Duration::minutes(self.ttl_minutes.min(i64::MAX as u64) as i64)
+    }
+
+    /// เวลาที่ใช้ล่าสุด สำหรับเลือกรายการที่ลบเมื่อ cache เต็ม
+    fn last_used_at(&self) -> chrono::DateTime<chrono::Local> {
+        self.last_hit_at.unwrap_or(self.created_at)
+    }
+
+    fn info(&self, key: &str) -> CachedTransformInfo {
+        CachedTransformInfo {
+            key: key.to_string(),
+            filename: self.filename.clone(),
+            tenant: self.tenant.clone(),
+            source_sha256: self.source_sha256.clone(),
+            bytes: self.data.len() as u64,
+            output_pages: self.report.output_pages,
+            hits: self.hits,
+            created_at: self.created_at.to_rfc3339(),
+            last_hit_at: self.last_hit_at.map(|at| at.to_rfc3339()),
+            ttl_minutes: self.ttl_minutes,
+            expires_at: self.expires_at().to_rfc3339(),
+        }
+    }
+}
+
+/// ข้อมูลของผลการแปลงหนึ่งรายการใน cache (ไม่รวมตัวไฟล์)
+#[derive(Serialize, ToSchema)]
+pub(crate) struct CachedTransformInfo {
+    /// key ของรายการ (SHA-256 ของไฟล์ต้นฉบับและ option ที่ใช้แปลง)
+    key: String,
+    /// ชื่อไฟล์ของงานล่าสุดที่ใช้รายการนี้
+    pub(crate) filename: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    /// SHA-256 ของไฟล์ต้นฉบับ
+    source_sha256: String,
+    /// ขนาดของไฟล์ที่แปลงแล้ว
+    bytes: u64,
+    output_pages: usize,
+    /// จำนวนงานที่ใช้ผลการแปลงนี้แทนการแปลงใหม่
+    hits: u64,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_hit_at: Option<String>,
+    ttl_minutes: u64,
+    expires_at: String,
+}
+
+/// สรุปของ cache ผลการแปลงไฟล์
+#[derive(Serialize, ToSchema)]
+pub(crate) struct TransformCacheListing {
+    /// เปิด `transform_cache` ใน config.json อยู่หรือไม่
+    enabled: bool,
+    /// จำนวนงานที่ใช้ผลจาก cache และที่ต้องแปลงใหม่ นับตั้งแต่เริ่ม Service
+    hits: u64,
+    misses: u64,
+    /// ขนาดรวมของไฟล์ใน cache
+    bytes: u64,
+    entries: Vec<CachedTransformInfo>,
+}
+
+#[derive(Default)]
+pub(crate) struct TransformCacheState {
+    entries: HashMap<String, CachedTransform>,
+    hits: u64,
+    misses: u64,
+}
+
+/// ผลการแปลงไฟล์ที่ใช้ซ้ำได้ เมื่อไฟล์ต้นฉบับ (ตาม SHA-256) และ option ที่ใช้แปลงเหมือนเดิม
+///
+/// เก็บในหน่วยความจำเท่านั้นและหายเมื่อ restart ลายน้ำตาม `watermarks` ใส่ใหม่ทุกงานหลังอ่านจาก cache
+/// ส่วนงานที่มี `{date}` หรือ `{counter:...}`, `pdf_password` หรือ `in_memory` จะแปลงใหม่ทุกครั้งและไม่ถูกเก็บ
+#[derive(Default)]
+pub(crate) struct TransformCache {
+    state: Mutex<TransformCacheState>,
+}
+
+impl TransformCache {
+    /// ผลการแปลงของ `key` ที่ยังไม่หมดอายุ (นับเป็น hit หรือ miss)
+    pub(crate) fn get(&self, key: &str, filename: &str) -> Option<(Vec<u8>, TransformReport)> {
+        let mut state = self.state.lock().unwrap();
+        let now = chrono::Local::now();
+        state.entries.retain(|_, entry| entry.expires_at() > now);
+        let Some(entry) = state.entries.get_mut(key) else {
+            state.misses += 1;
+            return None;
+        };
+        entry.hits += 1;
+        entry.last_hit_at = Some(now);
+        entry.filename = filename.to_string();
+        let hit = (entry.data.clone(), entry.report.clone());
+        state.hits += 1;
+        Some(hit)
+    }
+
+    /// เก็บผลการแปลง แล้วลบรายการที่ใช้นานที่สุดจนไม่เกิน `max_entries` และ `max_mb`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn insert(
+        &self,
+        config: &TransformCacheConfig,
+        key: String,
+        filename: &str,
+        tenant: Option<&str>,
+        source_sha256: String,
+        data: &[u8],
+        report: &TransformReport,
+    ) {
+        let (max_entries, max_bytes) = (config.max_entries(), config.max_bytes());
+        if max_entries == 0 || data.len() as u64 > max_bytes {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.entries.insert(
+            key,
+            CachedTransform {
+                filename: filename.to_string(),
+                tenant: tenant.map(str::to_string),
+                source_sha256,
+                data: data.to_vec(),
+                report: report.clone(),
+                created_at: chrono::Local::now(),
+                ttl_minutes: config.ttl_minutes(),
+                hits: 0,
+                last_hit_at: None,
+            },
+        );
+        loop {
+            let bytes: u64 = state
+                .entries
+                .values()
+                .map(|entry| entry.data.len() as u64)
+                .sum();
+            if state.entries.len() <= max_entries && bytes <= max_bytes {
+                break;
+            }
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used_at())
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+    }
+
+    /// รายการทั้งหมดที่ยังไม่หมดอายุ เรียงตามเวลาที่ใช้ล่าสุด
+    pub(crate) fn listing(&self, enabled: bool) -> TransformCacheListing {
+        let mut state = self.state.lock().unwrap();
+        let now = chrono::Local::now();
+        state.entries.retain(|_, entry| entry.expires_at() > now);
+        let mut entries: Vec<(&String, &CachedTransform)> = state.entries.iter().collect();
+        entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.last_used_at()));
+        TransformCacheListing {
+            enabled,
+            hits: state.hits,
+            misses: state.misses,
+            bytes: entries
+                .iter()
+                .map(|(_, entry)| entry.data.len() as u64)
+                .sum(),
+            entries: entries
+                .into_iter()
+                .map(|(key, entry)| entry.info(key))
+                .collect(),
+        }
+    }
+
+    /// ลบรายการที่ตรงกับ key, ชื่อไฟล์หรือ SHA-256 ของไฟล์ต้นฉบับ (ไม่ระบุทั้งหมด = ลบทุกรายการ)
+    /// คืนค่าจำนวนรายการที่ลบ
+    pub(crate) fn invalidate(
+        &self,
+        key: Option<&str>,
+        filename: Option<&str>,
+        sha256: Option<&str>,
+    ) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let before = state.entries.len();
+        state.entries.retain(|entry_key, entry| {
+            let matches = key.is_none_or(|key| entry_key.eq_ignore_ascii_case(key))
+                && filename.is_none_or(|filename| entry.filename == filename)
+                && sha256.is_none_or(|sha256| entry.source_sha256.eq_ignore_ascii_case(sha256));
+            !matches
+        });
+        before - state.entries.len()
+    }
+
+    /// เปลี่ยนอายุของรายการ `key` (นับจากเวลาที่เก็บ) คืนค่าข้อมูลของรายการ หรือ `None` หากไม่พบ
+    /// อายุที่สั้นจนหมดอายุไปแล้วจะลบรายการทันที
+    pub(crate) fn set_ttl(&self, key: &str, ttl_minutes: u64) -> Option<CachedTransformInfo> {
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entries.get_mut(key)?;
+        entry.ttl_minutes = ttl_minutes;
+        let info = entry.info(key);
+        if entry.expires_at() <= chrono::Local::now() {
+            state.entries.remove(key);
+        }
+        Some(info)
+    }
+}
@@ -0,0 +1,726 @@
+// ----------------------------------------------------------------------
+//                          RESUMABLE UPLOADS
+// ----------------------------------------------------------------------
+
+use crate::{
+    crypto::{random_nonce, AtRestKey, SealWriter, GCM_NONCE_LEN},
+    multipart::MAX_UPLOAD_BYTES,
+    storage::{hex_string, Storage},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use utoipa::ToSchema;
+
+/// โฟลเดอร์เก็บไฟล์ที่ยังอัปโหลดไม่ครบ (ไฟล์จะย้ายไป ./printable_files เมื่อตรวจ SHA-256 ผ่าน)
+pub(crate) const UPLOAD_PARTS_DIR: &str = "./printable_files/.uploads";
+/// ลบ upload ที่ไม่มี chunk ใหม่เข้ามานานเกินกว่านี้
+pub(crate) const UPLOAD_SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// ขนาดข้อมูลที่อ่านจากไฟล์ `.part` ต่อครั้งตอนตรวจ SHA-256 ของไฟล์ที่ได้รับครบ
+const UPLOAD_READ_BUFFER: usize = 1024 * 1024;
+/// เมื่อตั้งค่า `encryption` แต่ละ chunk ในไฟล์ `.part` ขึ้นต้นด้วย nonce ของ chunk นั้นและความยาว (u32 big-endian)
+const PART_CHUNK_HEADER_LEN: usize = GCM_NONCE_LEN + 4;
+
+/// สถานะของการอัปโหลดแบบแบ่ง chunk
+#[derive(Serialize, ToSchema)]
+pub(crate) struct UploadStatus {
+    #[schema(example = "3f9c2a7e1b6d48a09c5e2f71d4b8a630")]
+    pub(crate) upload_id: String,
+    /// ชื่อไฟล์ที่จะใช้กับ `POST /print` เมื่ออัปโหลดครบ
+    filename: String,
+    /// ขนาดไฟล์ทั้งหมด (ไบต์)
+    size: u64,
+    /// จำนวนไบต์ที่ได้รับแล้ว (ใช้เป็น `Upload-Offset` ของ chunk ถัดไป)
+    pub(crate) offset: u64,
+    /// อัปโหลดครบและตรวจ SHA-256 ผ่านแล้ว
+    complete: bool,
+}
+
+/// ข้อผิดพลาดของการอัปโหลดแบบแบ่ง chunk
+#[derive(Debug)]
+pub(crate) enum UploadError {
+    /// ไม่พบ upload id (หรือหมดอายุแล้ว)
+    NotFound,
+    /// `Upload-Offset` ไม่ตรงกับจำนวนไบต์ที่ server ได้รับแล้ว
+    OffsetMismatch {
+        expected: u64,
+    },
+    /// chunk ทำให้ไฟล์ใหญ่กว่า `size` ที่แจ้งไว้
+    TooLarge,
+    /// `size` ที่แจ้งไว้เกินขนาดสูงสุดของไฟล์ที่อัปโหลดได้
+    SizeLimit,
+    /// SHA-256 ของไฟล์ที่ประกอบแล้วไม่ตรงกับที่แจ้งไว้ (ไฟล์ถูกลบแล้ว ต้องอัปโหลดใหม่)
+    ChecksumMismatch {
+        actual: String,
+    },
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::NotFound => write!(f, "Upload not found or expired"),
+            UploadError::OffsetMismatch { expected } => {
+                write!(f, "Upload-Offset does not match, expected {}", expected)
+            }
+            UploadError::TooLarge => write!(f, "Chunk exceeds the declared upload size"),
+            UploadError::SizeLimit => write!(
+                f,
+                "Upload size exceeds the limit of {} bytes",
+                MAX_UPLOAD_BYTES
+            ),
+            UploadError::ChecksumMismatch { actual } => write!(
+                f,
+                "SHA-256 of the assembled file does not match (got {})",
+                actual
+            ),
+            UploadError::Io(e) => write!(f, "Upload I/O error: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for UploadError {
+    fn from(e: std::io::Error) -> Self {
+        UploadError::Io(e)
+    }
+}
+
+/// upload ที่ยังไม่ครบจะเขียนข้อมูลนี้เป็น `<upload id>.json` ข้างไฟล์ `.part` จึงส่ง chunk ต่อได้หลัง restart
+#[derive(Serialize, Deserialize)]
+pub(crate) struct UploadSession {
+    filename: String,
+    /// tenant ที่เริ่ม upload (tenant อื่นจะไม่เห็น upload นี้)
+    tenant: Option<String>,
+    size: u64,
+    sha256: String,
+    offset: u64,
+    /// ขนาดไฟล์ `.part` ที่มีข้อมูลถึง `offset` (มากกว่า `offset` เมื่อเข้ารหัส chunk)
+    part_len: u64,
+    #[serde(skip)]
+    complete: bool,
+    updated_at: SystemTime,
+    /// upload ถูกยกเลิกหรือหมดอายุระหว่างที่ request อื่นรอ lock ของ upload นี้
+    #[serde(skip)]
+    removed: bool,
+}
+
+impl UploadSession {
+    fn status(&self, upload_id: &str) -> UploadStatus {
+        UploadStatus {
+            upload_id: upload_id.to_string(),
+            filename: self.filename.clone(),
+            size: self.size,
+            offset: self.offset,
+            complete: self.complete,
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.updated_at
+            .elapsed()
+            .is_ok_and(|age| age > UPLOAD_SESSION_TTL)
+    }
+}
+
+/// upload ที่กำลังดำเนินการ (ไฟล์บางส่วนและข้อมูลของ upload เก็บใน `dir`)
+///
+/// แต่ละ upload มี lock ของตัวเอง จึงเขียน chunk ของหลาย upload พร้อมกันได้
+/// ระหว่างถือ lock ของ `sessions` ห้ามรอ lock ของ upload (ใช้ `try_lock` เท่านั้น)
+pub(crate) struct UploadStore {
+    dir: PathBuf,
+    sessions: Mutex<HashMap<String, Arc<Mutex<UploadSession>>>>,
+}
+
+/// upload id เป็นเลขสุ่ม 128 บิตในรูปแบบ hex
+fn new_upload_id() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(std::io::Error::other)?;
+    Ok(hex_string(&bytes))
+}
+
+fn is_upload_id(value: &str) -> bool {
+    value.len() == 32
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+impl UploadStore {
+    /// อ่าน upload ที่ยังไม่ครบและยังไม่หมดอายุจาก `dir` แล้วลบไฟล์อื่นที่ไม่มี upload อ้างถึง
+    /// (เรียกตอนเริ่ม Service)
+    pub(crate) fn open(dir: &Path) -> Self {
+        let store = UploadStore {
+            dir: dir.to_path_buf(),
+            sessions: Mutex::default(),
+        };
+        let files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    eprintln!("Failed to read {}: {}", dir.display(), e);
+                }
+                return store;
+            }
+        };
+        let upload_id = |path: &Path, extension: &str| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(extension))
+                .filter(|upload_id| is_upload_id(upload_id))
+                .map(String::from)
+        };
+
+        let mut sessions = HashMap::new();
+        for path in &files {
+            if let Some(upload_id) = upload_id(path, ".json") {
+                if let Some(session) = store.restore(&upload_id) {
+                    sessions.insert(upload_id, Arc::new(Mutex::new(session)));
+                }
+            }
+        }
+        let mut removed = 0;
+        for path in files {
+            let used = upload_id(&path, ".json")
+                .or_else(|| upload_id(&path, ".part"))
+                .is_some_and(|upload_id| sessions.contains_key(&upload_id));
+            if !used && path.is_file() {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => removed += 1,
+                    Err(e) => eprintln!("Failed to remove {}: {}", path.display(), e),
+                }
+            }
+        }
+        if !sessions.is_empty() || removed > 0 {
+            println!(
+                "Restored {} unfinished uploads, removed {} stale files from {}",
+                sessions.len(),
+                removed,
+                dir.display()
+            );
+        }
+        *store.sessions.lock().unwrap() = sessions;
+        store
+    }
+
+    /// อ่านข้อมูลของ upload ที่ยังใช้ต่อได้ (ไม่หมดอายุและไฟล์ `.part` มีข้อมูลครบตาม `offset`)
+    fn restore(&self, upload_id: &str) -> Option<UploadSession> {
+        let metadata = std::fs::read(self.metadata_path(upload_id)).ok()?;
+        let session: UploadSession = serde_json::from_slice(&metadata).ok()?;
+        let part_len = std::fs::metadata(self.part_path(upload_id)).ok()?.len();
+        (!session.expired() && session.offset <= session.size && part_len >= session.part_len)
+            .then_some(session)
+    }
+
+    fn part_path(&self, upload_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.part", upload_id))
+    }
+
+    fn metadata_path(&self, upload_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", upload_id))
+    }
+
+    fn save_metadata(&self, upload_id: &str, session: &UploadSession) -> std::io::Result<()> {
+        let path = self.metadata_path(upload_id);
+        let temp_path = path.with_extension("json.tmp");
+        let data = serde_json::to_vec(session)?;
+        std::fs::File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(&data)?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&temp_path, &path))
+    }
+
+    fn remove_files(&self, upload_id: &str) {
+        let _ = std::fs::remove_file(self.part_path(upload_id));
+        let _ = std::fs::remove_file(self.metadata_path(upload_id));
+    }
+
+    fn session(&self, upload_id: &str) -> Option<Arc<Mutex<UploadSession>>> {
+        self.sessions.lock().unwrap().get(upload_id).cloned()
+    }
+
+    /// ลบ upload ที่ไม่มี chunk ใหม่เข้ามาเกิน `UPLOAD_SESSION_TTL` (upload ที่กำลังรับ chunk อยู่ไม่ถูกลบ)
+    fn remove_expired(&self) {
+        let mut expired = Vec::new();
+        self.sessions.lock().unwrap().retain(|upload_id, session| {
+            let Ok(mut session) = session.try_lock() else {
+                return true;
+            };
+            if !session.expired() {
+                return true;
+            }
+            session.removed = true;
+            expired.push((upload_id.clone(), session.complete));
+            false
+        });
+        for (upload_id, complete) in expired {
+            println!("Removing expired upload {}", upload_id);
+            if !complete {
+                self.remove_files(&upload_id);
+            }
+        }
+    }
+
+    /// เริ่ม upload ใหม่ของไฟล์ `filename` ขนาด `size` ไบต์ ที่ต้องมี SHA-256 เท่ากับ `sha256`
+    pub(crate) fn create(
+        &self,
+        filename: &str,
+        size: u64,
+        sha256: &str,
+        tenant: Option<&str>,
+        key: Option<&AtRestKey>,
+        storage: &dyn Storage,
+    ) -> Result<UploadStatus, UploadError> {
+        if size > MAX_UPLOAD_BYTES as u64 {
+            return Err(UploadError::SizeLimit);
+        }
+        self.remove_expired();
+
+        let upload_id = new_upload_id()?;
+        let session = UploadSession {
+            filename: filename.to_string(),
+            tenant: tenant.map(String::from),
+            size,
+            sha256: sha256.to_ascii_lowercase(),
+            offset: 0,
+            part_len: 0,
+            complete: false,
+            updated_at: SystemTime::now(),
+            removed: false,
+        };
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::File::create(self.part_path(&upload_id))?;
+        self.save_metadata(&upload_id, &session)?;
+        let status = session.status(&upload_id);
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(upload_id, Arc::new(Mutex::new(session)));
+        if size == 0 {
+            return self.append(&status.upload_id, tenant, 0, &[], key, storage);
+        }
+        Ok(status)
+    }
+
+    /// จำนวน upload ที่ยังไม่ครบ
+    pub(crate) fn active_count(&self) -> usize {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .values()
+            // upload ที่กำลังรับ chunk อยู่นับว่ายังไม่ครบ
+            .filter(|session| session.try_lock().map_or(true, |session| !session.complete))
+            .count()
+    }
+
+    pub(crate) fn status(&self, upload_id: &str, tenant: Option<&str>) -> Option<UploadStatus> {
+        let session = self.session(upload_id)?;
+        let session = session.lock().unwrap();
+        (!session.removed && session.tenant.as_deref() == tenant).then(|| session.status(upload_id))
+    }
+
+    /// ต่อ chunk `data` ที่ตำแหน่ง `offset` เมื่อได้รับครบจะตรวจ SHA-256 แล้วย้ายไฟล์ไปเก็บใน `storage` ของ tenant
+    /// (เมื่อมี `key` chunk จะถูกเข้ารหัสด้วย nonce ใหม่ทุกครั้ง รวมทั้ง chunk ที่ส่งซ้ำหลังเขียนไม่สำเร็จ
+    /// จึงไม่มี keystream ที่ถูกใช้ซ้ำกับข้อมูลต่างกัน)
+    pub(crate) fn append(
+        &self,
+        upload_id: &str,
+        tenant: Option<&str>,
+        offset: u64,
+        data: &[u8],
+        key: Option<&AtRestKey>,
+        storage: &dyn Storage,
+    ) -> Result<UploadStatus, UploadError> {
+        let session = self.session(upload_id).ok_or(UploadError::NotFound)?;
+        let mut session = session.lock().unwrap();
+        if session.removed || session.tenant.as_deref() != tenant {
+            return Err(UploadError::NotFound);
+        }
+        if session.complete || offset != session.offset {
+            return Err(UploadError::OffsetMismatch {
+                expected: session.offset,
+            });
+        }
+        if offset + data.len() as u64 > session.size {
+            return Err(UploadError::TooLarge);
+        }
+
+        let mut part = std::fs::OpenOptions::new()
+            .append(true)
+            .open(self.part_path(upload_id))?;
+        // ตัดข้อมูลที่อาจค้างจาก chunk ที่เขียนไม่สำเร็จก่อนหน้า
+        part.set_len(session.part_len)?;
+        let written = match key {
+            Some(key) if !data.is_empty() => {
+                let nonce = random_nonce()?;
+                let mut chunk = Vec::with_capacity(PART_CHUNK_HEADER_LEN + data.len());
+                chunk.extend_from_slice(&nonce);
+                chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                chunk.extend_from_slice(data);
+                key.apply_keystream(&nonce, 0, &mut chunk[PART_CHUNK_HEADER_LEN..]);
+                part.write_all(&chunk)?;
+                chunk.len()
+            }
+            _ => {
+                part.write_all(data)?;
+                data.len()
+            }
+        };
+        drop(part);
+        session.offset += data.len() as u64;
+        session.part_len += written as u64;
+        session.updated_at = SystemTime::now();
+        if session.offset < session.size {
+            self.save_metadata(upload_id, &session)?;
+            return Ok(session.status(upload_id));
+        }
+
+        match self.finish(upload_id, &session, key, storage) {
+            Ok(()) => {}
+            Err(e @ UploadError::ChecksumMismatch { .. }) => {
+                session.removed = true;
+                self.sessions.lock().unwrap().remove(upload_id);
+                self.remove_files(upload_id);
+                return Err(e);
+            }
+            // ส่ง chunk ว่างที่ `offset` เท่ากับ `size` เพื่อลองย้ายไฟล์อีกครั้ง
+            Err(e) => return Err(e),
+        }
+        session.complete = true;
+        let _ = std::fs::remove_file(self.metadata_path(upload_id));
+        println!(
+            "Upload {} complete: {} ({} bytes)",
+            upload_id, session.filename, session.size
+        );
+        Ok(session.status(upload_id))
+    }
+
+    /// ตรวจ SHA-256 ของไฟล์ `.part` ที่ได้รับครบแล้วและย้ายไฟล์ไปเก็บใน `storage`
+    /// โดยอ่านทีละ `UPLOAD_READ_BUFFER` (เมื่อมี `key` จะถอดรหัสทีละ chunk แล้วเข้ารหัสใหม่ทั้งไฟล์ลงไฟล์ `.sealed` ก่อนย้าย
+    /// chunk ในไฟล์ `.part` ไม่มี tag แต่ข้อมูลที่ถอดรหัสแล้วต้องมี SHA-256 ตรงกับที่ client แจ้งไว้)
+    fn finish(
+        &self,
+        upload_id: &str,
+        session: &UploadSession,
+        key: Option<&AtRestKey>,
+        storage: &dyn Storage,
+    ) -> Result<(), UploadError> {
+        let part_path = self.part_path(upload_id);
+        let sealed_path = self.dir.join(format!("{}.sealed", upload_id));
+        let result = (|| {
+            let mut part = std::fs::File::open(&part_path)?.take(session.part_len);
+            let mut sealed = match key {
+                Some(key) => Some(SealWriter::new(
+                    key,
+                    std::io::BufWriter::new(std::fs::File::create(&sealed_path)?),
+                )?),
+                None => None,
+            };
+            let truncated = || {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{} has an invalid chunk", part_path.display()),
+                )
+            };
+            let mut hasher = Sha256::new();
+            let mut buffer = vec![0u8; UPLOAD_READ_BUFFER];
+            let mut remaining = session.part_len;
+            while remaining > 0 {
+                // ไฟล์ที่ไม่เข้ารหัสคือข้อมูลต่อกันทั้งไฟล์
+                let (nonce, len) = match key {
+                    Some(_) => {
+                        let mut header = [0u8; PART_CHUNK_HEADER_LEN];
+                        part.read_exact(&mut header)?;
+                        remaining = remaining
+                            .checked_sub(PART_CHUNK_HEADER_LEN as u64)
+                            .ok_or_else(truncated)?;
+                        let (nonce, len) = header.split_at(GCM_NONCE_LEN);
+                        let nonce: [u8; GCM_NONCE_LEN] = nonce.try_into().unwrap();
+                        (
+                            Some(nonce),
+                            u32::from_be_bytes(len.try_into().unwrap()) as u64,
+                        )
+                    }
+                    None => (None, remaining),
+                };
+                remaining = remaining.checked_sub(len).ok_or_else(truncated)?;
+                let mut position = 0;
+                while position < len {
+                    let chunk =
+                        &mut buffer[..(len - position).min(UPLOAD_READ_BUFFER as u64) as usize];
+                    part.read_exact(chunk)?;
+                    if let (Some(key), Some(nonce)) = (key, &nonce) {
+                        key.apply_keystream(nonce, position, chunk);
+                    }
+                    hasher.update(&*chunk);
+                    if let Some(sealed) = &mut sealed {
+                        sealed.write_all(chunk)?;
+                    }
+                    position += chunk.len() as u64;
+                }
+            }
+
+            let actual = hex_string(&hasher.finalize());
+            if actual != session.sha256 {
+                return Err(UploadError::ChecksumMismatch { actual });
+            }
+            let tenant = session.tenant.as_deref();
+            match sealed {
+                Some(sealed) => {
+                    sealed
+                        .finish()?
+                        .into_inner()
+                        .map_err(|e| e.into_error())?
+                        .sync_all()?;
+                    storage.persist(tenant, &session.filename, &sealed_path)?;
+                    std::fs::remove_file(&part_path)?;
+                }
+                None => storage.persist(tenant, &session.filename, &part_path)?,
+            }
+            Ok(())
+        })();
+        let _ = std::fs::remove_file(&sealed_path);
+        result
+    }
+
+    /// ยกเลิก upload และลบไฟล์ที่อัปโหลดไว้บางส่วน คืนค่า `false` หากไม่พบ upload id
+    pub(crate) fn cancel(&self, upload_id: &str, tenant: Option<&str>) -> bool {
+        let Some(session) = self.session(upload_id) else {
+            return false;
+        };
+        let mut session = session.lock().unwrap();
+        if session.removed || session.tenant.as_deref() != tenant {
+            return false;
+        }
+        session.removed = true;
+        self.sessions.lock().unwrap().remove(upload_id);
+        if !session.complete {
+            self.remove_files(upload_id);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{load_at_rest_key, open_job_data, SEALED_FILE_MAGIC};
+    use crate::storage::MemoryStorage;
+    use base64::Engine;
+
+    /// โฟลเดอร์ใหม่ในโฟลเดอร์ชั่วคราวของเครื่องที่ไม่ซ้ำกับ test อื่น
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "rust-print-api-test-{}-uploads",
+            new_upload_id().unwrap()
+        ))
+    }
+
+    fn test_key() -> AtRestKey {
+        let key = base64::engine::general_purpose::STANDARD.encode([7u8; 32]);
+        load_at_rest_key(&serde_json::from_value(serde_json::json!({ "key": key })).unwrap())
+            .unwrap()
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn unfinished_uploads_continue_after_restart() {
+        let key = test_key();
+        let data: Vec<u8> = (0..3000u32).map(|i| (i % 251) as u8).collect();
+        let sha256 = hex_string(&Sha256::digest(&data));
+        for key in [None, Some(&key)] {
+            let dir = temp_dir();
+            let storage = MemoryStorage::default();
+            let store = UploadStore::open(&dir);
+            let upload_id = store
+                .create("scan.pdf", 3000, &sha256, Some("branch"), key, &storage)
+                .unwrap()
+                .upload_id;
+            store
+                .append(&upload_id, Some("branch"), 0, &data[..1000], key, &storage)
+                .unwrap();
+            drop(store);
+
+            let store = UploadStore::open(&dir);
+            let status = store.status(&upload_id, Some("branch")).unwrap();
+            assert_eq!((status.offset, status.complete), (1000, false));
+            assert!(store.status(&upload_id, None).is_none());
+            let status = store
+                .append(
+                    &upload_id,
+                    Some("branch"),
+                    1000,
+                    &data[1000..],
+                    key,
+                    &storage,
+                )
+                .unwrap();
+            assert!(status.complete);
+
+            let stored = storage.read(Some("branch"), "scan.pdf").unwrap();
+            assert_eq!(stored.starts_with(SEALED_FILE_MAGIC), key.is_some());
+            assert_eq!(open_job_data(key, stored, &"scan.pdf").unwrap(), data);
+            assert!(file_names(&dir).is_empty());
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn rewritten_chunks_are_encrypted_with_a_fresh_nonce() {
+        let key = test_key();
+        let dir = temp_dir();
+        let storage = MemoryStorage::default();
+        let data = vec![0x42u8; 96];
+        let sha256 = hex_string(&Sha256::digest(&data));
+        let store = UploadStore::open(&dir);
+        let upload_id = store
+            .create("scan.pdf", 96, &sha256, None, Some(&key), &storage)
+            .unwrap()
+            .upload_id;
+        let part_path = store.part_path(&upload_id);
+        store
+            .append(&upload_id, None, 0, &data[..32], Some(&key), &storage)
+            .unwrap();
+        let first = std::fs::read(&part_path).unwrap();
+        assert_eq!(first.len(), PART_CHUNK_HEADER_LEN + 32);
+
+        // chunk ถัดไปที่เขียนลงดิสก์แล้วแต่ไม่สำเร็จ และถูกส่งซ้ำด้วยข้อมูลเดิม
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&part_path)
+            .unwrap()
+            .write_all(&first)
+            .unwrap();
+        store
+            .append(&upload_id, None, 32, &data[32..64], Some(&key), &storage)
+            .unwrap();
+        let part = std::fs::read(&part_path).unwrap();
+        assert_eq!(part.len(), 2 * first.len());
+        let (kept, rewritten) = part.split_at(first.len());
+        assert_eq!(kept, first.as_slice());
+        assert_ne!(rewritten[..GCM_NONCE_LEN], first[..GCM_NONCE_LEN]);
+        assert_ne!(
+            rewritten[PART_CHUNK_HEADER_LEN..],
+            first[PART_CHUNK_HEADER_LEN..]
+        );
+
+        let status = store
+            .append(&upload_id, None, 64, &data[64..], Some(&key), &storage)
+            .unwrap();
+        assert!(status.complete);
+        let stored = storage.read(None, "scan.pdf").unwrap();
+        assert_eq!(
+            open_job_data(Some(&key), stored, &"scan.pdf").unwrap(),
+            data
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn startup_removes_orphaned_and_expired_files() {
+        let dir = temp_dir();
+        let storage = MemoryStorage::default();
+        let sha256 = "0".repeat(64);
+        let store = UploadStore::open(&dir);
+        let kept = store
+            .create("kept.pdf", 10, &sha256, None, None, &storage)
+            .unwrap()
+            .upload_id;
+        let expired = store
+            .create("old.pdf", 10, &sha256, None, None, &storage)
+            .unwrap()
+            .upload_id;
+        drop(store);
+
+        let metadata_path = dir.join(format!("{}.json", expired));
+        let mut metadata: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&metadata_path).unwrap()).unwrap();
+        metadata["updated_at"] = serde_json::to_value(SystemTime::UNIX_EPOCH).unwrap();
+        std::fs::write(&metadata_path, metadata.to_string()).unwrap();
+        let orphan = new_upload_id().unwrap();
+        for name in [
+            format!("{}.part", orphan),
+            format!("{}.sealed", kept),
+            format!("{}.json.tmp", kept),
+            "notes.txt".to_string(),
+        ] {
+            std::fs::write(dir.join(name), b"x").unwrap();
+        }
+
+        let store = UploadStore::open(&dir);
+        assert!(store.status(&kept, None).is_some());
+        assert!(store.status(&expired, None).is_none());
+        assert_eq!(
+            file_names(&dir),
+            [format!("{}.json", kept), format!("{}.part", kept)]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn upload_ids_are_random_and_sizes_are_limited() {
+        let dir = temp_dir();
+        let storage = MemoryStorage::default();
+        let sha256 = "0".repeat(64);
+        let store = UploadStore::open(&dir);
+        let first = store
+            .create("a.pdf", 1, &sha256, None, None, &storage)
+            .unwrap()
+            .upload_id;
+        let second = store
+            .create("b.pdf", 1, &sha256, None, None, &storage)
+            .unwrap()
+            .upload_id;
+        assert!(is_upload_id(&first) && is_upload_id(&second));
+        assert_ne!(first, second);
+
+        let too_large = store.create(
+            "big.pdf",
+            MAX_UPLOAD_BYTES as u64 + 1,
+            &sha256,
+            None,
+            None,
+            &storage,
+        );
+        assert!(matches!(too_large, Err(UploadError::SizeLimit)));
+        assert_eq!(store.active_count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_busy_upload_does_not_block_other_uploads() {
+        let dir = temp_dir();
+        let storage = MemoryStorage::default();
+        let data = b"second file";
+        let sha256 = hex_string(&Sha256::digest(data));
+        let store = UploadStore::open(&dir);
+        let busy = store
+            .create("a.pdf", 100, &sha256, None, None, &storage)
+            .unwrap()
+            .upload_id;
+        let other = store
+            .create("b.pdf", data.len() as u64, &sha256, None, None, &storage)
+            .unwrap()
+            .upload_id;
+
+        let session = store.session(&busy).unwrap();
+        let _busy = session.lock().unwrap();
+        let status = store.append(&other, None, 0, data, None, &storage).unwrap();
+        assert!(status.complete);
+        assert_eq!(store.active_count(), 1);
+        assert_eq!(storage.read(None, "b.pdf").unwrap(), data);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
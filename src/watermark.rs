@@ -0,0 +1,138 @@
+// ----------------------------------------------------------------------
+//                          WATERMARK POLICY
+// ----------------------------------------------------------------------
+
+use crate::{
+    config::AppConfig,
+    transform::{validate_transform_steps, TransformStep},
+};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+/// ขั้นตอนที่ผู้ดูแลบังคับใช้กับทุกงานของเครื่องพิมพ์ `printers` หรือ tenant `tenants`
+///
+/// เช่น ทุกหน้าที่พิมพ์ที่ `HR_Printer` ต้องมี footer `CONFIDENTIAL - {user} - {date} {time}`
+#[derive(Deserialize, Clone)]
+pub(crate) struct WatermarkPolicy {
+    /// เครื่องพิมพ์ที่ใช้นโยบายนี้ (ไม่ระบุ = ทุกเครื่องของ `tenants`)
+    #[serde(default)]
+    printers: Vec<String>,
+    /// tenant ที่ใช้นโยบายนี้ (ไม่ระบุ = ทุก tenant ที่พิมพ์ที่ `printers`)
+    #[serde(default)]
+    tenants: Vec<String>,
+    /// ขั้นตอน `stamp`, `text`, `page_numbers` หรือ `grayscale` ที่ทำต่อจากการแปลงตาม request
+    transforms: Vec<TransformStep>,
+}
+
+impl WatermarkPolicy {
+    fn applies_to(&self, printer: Option<&str>, tenant: Option<&str>) -> bool {
+        let printer_matches = self.printers.is_empty()
+            || printer.is_some_and(|printer| self.printers.iter().any(|p| p == printer));
+        let tenant_matches = self.tenants.is_empty()
+            || tenant.is_some_and(|tenant| self.tenants.iter().any(|t| t == tenant));
+        printer_matches && tenant_matches
+    }
+}
+
+/// ตรวจว่าทุกนโยบายระบุเครื่องพิมพ์หรือ tenant และใช้เฉพาะขั้นตอนที่ไม่เปลี่ยนขนาดหน้า
+pub(crate) fn validate_watermarks(watermarks: &[WatermarkPolicy]) -> Result<()> {
+    for (position, policy) in watermarks.iter().enumerate() {
+        if policy.printers.is_empty() && policy.tenants.is_empty() {
+            bail!("watermarks[{}] must list printers or tenants", position);
+        }
+        if policy.transforms.is_empty() {
+            bail!("watermarks[{}] has no transforms", position);
+        }
+        if policy.transforms.iter().any(|step| {
+            matches!(
+                step,
+                TransformStep::Rotate { .. } | TransformStep::Resize { .. }
+            )
+        }) {
+            bail!(
+                "watermarks[{}] may only use stamp, text, page_numbers and grayscale",
+                position
+            );
+        }
+        if let Err(e) = validate_transform_steps(&policy.transforms) {
+            bail!("watermarks[{}].{}", position, e);
+        }
+    }
+    Ok(())
+}
+
+/// ขั้นตอนของทุกนโยบายที่ตรงกับงาน โดยแทน `{user}`, `{tenant}`, `{printer}` และ `{time}` ด้วยค่าของงาน
+///
+/// ค่าที่ไม่ใช่ ASCII (เช่น ชื่อภาษาไทย) จะถูกแทนด้วย `?` เพราะฟอนต์ที่ใช้พิมพ์รองรับเฉพาะ ASCII
+pub(crate) fn watermark_steps(
+    config: &AppConfig,
+    printer: Option<&str>,
+    tenant: Option<&str>,
+    user: Option<&str>,
+) -> Vec<TransformStep> {
+    let ascii = |value: &str| -> String {
+        value
+            .chars()
+            .map(|c| match c.is_ascii() && !c.is_ascii_control() {
+                true => c,
+                false => '?',
+            })
+            .collect()
+    };
+    let user = ascii(user.or(tenant).unwrap_or("unknown"));
+    let tenant_name = ascii(tenant.unwrap_or("-"));
+    let printer_name = ascii(printer.unwrap_or("-"));
+    let time = chrono::Local::now().format("%H:%M").to_string();
+    let fill = |text: &str| {
+        text.replace("{user}", &user)
+            .replace("{tenant}", &tenant_name)
+            .replace("{printer}", &printer_name)
+            .replace("{time}", &time)
+    };
+    config
+        .watermarks
+        .iter()
+        .filter(|policy| policy.applies_to(printer, tenant))
+        .flat_map(|policy| policy.transforms.iter().cloned())
+        .map(|step| match step {
+            TransformStep::Stamp {
+                text,
+                font_size,
+                angle,
+                locale,
+            } => TransformStep::Stamp {
+                text: fill(&text),
+                font_size,
+                angle,
+                locale,
+            },
+            TransformStep::Text {
+                text,
+                position,
+                font_size,
+                margin_mm,
+                locale,
+            } => TransformStep::Text {
+                text: fill(&text),
+                position,
+                font_size,
+                margin_mm,
+                locale,
+            },
+            TransformStep::PageNumbers {
+                format,
+                position,
+                font_size,
+                margin_mm,
+                locale,
+            } => TransformStep::PageNumbers {
+                format: fill(&format),
+                position,
+                font_size,
+                margin_mm,
+                locale,
+            },
+            step => step,
+        })
+        .collect()
+}
@@ -0,0 +1,905 @@
+//! ทดสอบ API ทั้งชุดกับเครื่องพิมพ์จำลอง (`print_backend` แบบ `mock`) โดยไม่ต้องมีเครื่องพิมพ์จริง
+//!
+//! แต่ละ test เริ่ม Service ใน `--console` ในโฟลเดอร์ชั่วคราวของตัวเองที่มี config.json และไฟล์ PDF ตัวอย่าง
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// Service ที่ทำงานอยู่ระหว่าง test และถูกหยุดพร้อมลบโฟลเดอร์เมื่อจบ test
+struct TestServer {
+    child: Child,
+    port: u16,
+    dir: PathBuf,
+}
+
+impl TestServer {
+    /// เริ่ม Service ด้วย `print_backend` ที่กำหนด
+    fn start(print_backend: Value) -> TestServer {
+        TestServer::start_with(json!({ "print_backend": print_backend }))
+    }
+
+    /// เริ่ม Service ด้วย config.json ที่กำหนด (ใส่ `server.port` ให้) และรอจนรับการเชื่อมต่อได้
+    fn start_with(mut config: Value) -> TestServer {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "rust-print-api-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("printable_files")).unwrap();
+        std::fs::write(dir.join("printable_files/sample.pdf"), sample_pdf()).unwrap();
+
+        let port = free_port();
+        config["server"] = json!({ "port": port });
+        std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+
+        let log = std::fs::File::create(dir.join("server.log")).unwrap();
+        let child = Command::new(env!("CARGO_BIN_EXE_rust-print-api"))
+            .arg("--console")
+            .current_dir(&dir)
+            .stdout(log.try_clone().unwrap())
+            .stderr(log)
+            .stdin(Stdio::null())
+            .spawn()
+            .expect("Failed to start rust-print-api");
+        let mut server = TestServer { child, port, dir };
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            if let Ok(Some(status)) = server.child.try_wait() {
+                panic!("Server exited with {}: {}", status, server.log());
+            }
+            assert!(
+                Instant::now() < deadline,
+                "Server did not start: {}",
+                server.log()
+            );
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        server
+    }
+
+    fn log(&self) -> String {
+        std::fs::read_to_string(self.dir.join("server.log")).unwrap_or_default()
+    }
+
+    fn path(&self, relative: &str) -> PathBuf {
+        self.dir.join(relative)
+    }
+
+    fn get(&self, path: &str) -> (u16, Value) {
+        self.request("GET", path, None)
+    }
+
+    fn post(&self, path: &str, body: Value) -> (u16, Value) {
+        self.request("POST", path, Some(body))
+    }
+
+    fn request(&self, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+        let (status, _, body) = self.send(method, path, body);
+        (status, body)
+    }
+
+    fn send(&self, method: &str, path: &str, body: Option<Value>) -> (u16, String, Value) {
+        self.send_as(None, method, path, body)
+    }
+
+    /// ส่ง request ด้วย API key ของ tenant (`Authorization: Bearer <api_key>`)
+    fn post_as(&self, api_key: &str, path: &str, body: Value) -> (u16, Value) {
+        let (status, _, body) = self.send_as(Some(api_key), "POST", path, Some(body));
+        (status, body)
+    }
+
+    /// ส่ง request HTTP/1.1 แบบปิดการเชื่อมต่อหลังตอบ คืนค่า status, header และ body ที่เป็น JSON
+    fn send_as(
+        &self,
+        api_key: Option<&str>,
+        method: &str,
+        path: &str,
+        body: Option<Value>,
+    ) -> (u16, String, Value) {
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let authorization = api_key
+            .map(|api_key| format!("Authorization: Bearer {}\r\n", api_key))
+            .unwrap_or_default();
+        let headers = format!(
+            "{}Content-Type: application/json\r\nContent-Length: {}\r\n",
+            authorization,
+            body.len()
+        );
+        let (status, head, body) = self.send_raw(method, path, &headers, body.as_bytes());
+        let body = serde_json::from_str(&body)
+            .unwrap_or_else(|e| panic!("Response is not JSON ({}): {}", e, body));
+        (status, head, body)
+    }
+
+    /// ส่ง request ที่กำหนด header เอง (`headers` ต้องมี `Content-Length`) คืนค่า status, header และ body
+    fn send_raw(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &str,
+        body: &[u8],
+    ) -> (u16, String, String) {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .unwrap();
+        write!(
+            stream,
+            "{} /api/v1{} HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n{}\r\n",
+            method, path, headers
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let response = String::from_utf8_lossy(&response);
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .expect("Malformed HTTP response");
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .expect("Missing HTTP status");
+        (status, head.to_lowercase(), body.to_string())
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// พอร์ตว่างที่ระบบเลือกให้
+fn free_port() -> u16 {
+    TcpListener::bind(("127.0.0.1", 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// PDF ขนาด A4 หนึ่งหน้าที่มีสี่เหลี่ยมหนึ่งรูป
+fn sample_pdf() -> Vec<u8> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let content = Content {
+        operations: vec![
+            Operation::new("re", vec![100.into(), 100.into(), 300.into(), 200.into()]),
+            Operation::new("f", vec![]),
+        ],
+    };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "Contents" => content_id,
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).unwrap();
+    buffer
+}
+
+fn sha256_file(path: &Path) -> String {
+    Sha256::digest(std::fs::read(path).unwrap())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[test]
+fn print_records_submitted_bytes() {
+    let server = TestServer::start(json!({
+        "type": "mock",
+        "printers": ["Mock A4"],
+        "record_dir": "mock_jobs",
+    }));
+
+    let (status, body) = server.post("/print", json!({"filename": "sample.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["printer_name"], "Mock A4");
+    let job_id = body["job_id"].as_u64().expect("job_id");
+
+    let recorded = server.path(&format!("mock_jobs/{}.prn", job_id));
+    let data = std::fs::read(&recorded).expect("Mock backend did not record the job");
+    assert!(data.starts_with(b"%PDF"));
+
+    let (status, body) = server.get("/admin/print-backend");
+    assert_eq!(status, 200, "{}", body);
+    let job = &body["jobs"][0];
+    assert_eq!(job["id"], job_id);
+    assert_eq!(job["printer_name"], "Mock A4");
+    assert_eq!(job["status"], "printed");
+    assert_eq!(job["size"], data.len() as u64);
+    assert_eq!(job["sha256"], sha256_file(&recorded));
+}
+
+#[test]
+fn simulated_failures_return_spooler_error() {
+    let server = TestServer::start(json!({
+        "type": "mock",
+        "printers": ["Mock A4", "Broken"],
+        "fail_every": 2,
+        "fail_printers": ["Broken"],
+    }));
+
+    let (status, body) = server.post("/print", json!({"filename": "sample.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = server.post("/print", json!({"filename": "sample.pdf"}));
+    assert_eq!(status, 500, "{}", body);
+    assert_eq!(body["error_code"], "SPOOLER_ERROR");
+
+    let (status, body) = server.post(
+        "/print",
+        json!({"filename": "sample.pdf", "printer_name": "Broken"}),
+    );
+    assert_eq!(status, 500, "{}", body);
+    assert_eq!(body["error_code"], "SPOOLER_ERROR");
+
+    let (_, body) = server.get("/admin/print-backend");
+    let statuses: Vec<&str> = body["jobs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|job| job["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["failed", "failed", "printed"]);
+}
+
+#[test]
+fn mock_printers_answer_printer_endpoints() {
+    let server = TestServer::start(json!({
+        "type": "mock",
+        "printers": ["Mock A4", "Mock Label"],
+        "offline_printers": ["Mock Label"],
+        "print_ms": 60000,
+    }));
+
+    let (status, body) = server.get("/printers/Mock%20A4/capabilities");
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["duplex"], true);
+
+    let (status, body) = server.get("/printers/Nope/capabilities");
+    assert_eq!(status, 404, "{}", body);
+    assert_eq!(body["error_code"], "PRINTER_NOT_FOUND");
+
+    let (status, body) = server.post("/printers/Mock%20A4/pause", json!({}));
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = server.post("/print", json!({"filename": "sample.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = server.get("/admin/overview");
+    assert_eq!(status, 200, "{}", body);
+    let printers = body["printers"].as_array().unwrap();
+    let state = |name: &str| {
+        printers
+            .iter()
+            .find(|printer| printer["name"] == name)
+            .map(|printer| (printer["state"].clone(), printer["active_jobs"].clone()))
+            .unwrap()
+    };
+    assert_eq!(state("Mock A4"), (json!("paused"), json!(1)));
+    assert_eq!(state("Mock Label"), (json!("offline"), json!(0)));
+
+    let (status, body) = server.post("/printers/Mock%20A4/purge", json!({}));
+    assert_eq!(status, 200, "{}", body);
+    let (_, body) = server.get("/admin/print-backend");
+    assert_eq!(body["jobs"][0]["status"], "cancelled");
+}
+
+#[test]
+fn printer_purge_only_cancels_the_callers_jobs() {
+    let server = TestServer::start_with(json!({
+        "print_backend": {"type": "mock", "printers": ["Mock A4"], "print_ms": 60000},
+        "tenants": {
+            "a": {"api_keys": ["key-a"]},
+            "b": {"api_keys": ["key-b"]},
+        },
+        "admin_token": "admin-secret",
+    }));
+    for (tenant, key) in [("a", "key-a"), ("b", "key-b")] {
+        std::fs::create_dir_all(server.path(&format!("printable_files/{}", tenant))).unwrap();
+        std::fs::write(
+            server.path(&format!("printable_files/{}/sample.pdf", tenant)),
+            sample_pdf(),
+        )
+        .unwrap();
+        let (status, body) = server.post_as(key, "/print", json!({"filename": "sample.pdf"}));
+        assert_eq!(status, 200, "{}", body);
+    }
+    let cancelled = || {
+        let (status, _, body) =
+            server.send_as(Some("admin-secret"), "GET", "/admin/print-backend", None);
+        assert_eq!(status, 200, "{}", body);
+        body["jobs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|job| job["status"] == "cancelled")
+            .count()
+    };
+
+    let (status, body) = server.post_as("key-a", "/printers/Mock%20A4/purge", json!({}));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        body["message"],
+        "Purged 1 jobs from the spooler queue of printer Mock A4"
+    );
+    assert_eq!(cancelled(), 1);
+
+    // ยกเลิกงานของ tenant อื่นต้องใช้สิทธิ์ผู้ดูแลระบบ
+    let (status, body) = server.post_as("key-a", "/printers/Mock%20A4/purge?all=true", json!({}));
+    assert_eq!(status, 401, "{}", body);
+    assert_eq!(cancelled(), 1);
+    let (status, body) =
+        server.post_as("admin-secret", "/printers/Mock%20A4/purge?all=true", json!({}));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(cancelled(), 2);
+}
+
+#[test]
+fn full_printer_backlog_returns_retry_after() {
+    let server = TestServer::start_with(json!({
+        "print_backend": {
+            "type": "mock",
+            "printers": ["Mock A4", "Mock Label"],
+            "print_ms": 60000,
+        },
+        "backpressure": {"max_printer_backlog": 2, "seconds_per_job": 30},
+    }));
+
+    for _ in 0..2 {
+        let (status, body) = server.post("/print", json!({"filename": "sample.pdf"}));
+        assert_eq!(status, 200, "{}", body);
+    }
+    let (status, head, body) =
+        server.send("POST", "/print", Some(json!({"filename": "sample.pdf"})));
+    assert_eq!(status, 429, "{}", body);
+    assert_eq!(body["error_code"], "QUEUE_FULL");
+    assert!(head.contains("\r\nretry-after: 30\r\n"), "{}", head);
+
+    // เครื่องพิมพ์อื่นยังรับงานได้
+    let (status, body) = server.post(
+        "/print",
+        json!({"filename": "sample.pdf", "printer_name": "Mock Label"}),
+    );
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = server.get("/printers/queue");
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["max_printer_backlog"], 2);
+    assert_eq!(
+        body["printers"][0],
+        json!({
+            "printer_name": "Mock A4",
+            "waiting": 0,
+            "spooler_jobs": 2,
+            "accepting": false,
+            "retry_after_secs": 30,
+        })
+    );
+    assert_eq!(body["printers"][1]["accepting"], true);
+}
+
+#[test]
+fn job_detail_shows_timeline() {
+    let server = TestServer::start(json!({
+        "type": "mock",
+        "printers": ["Mock A4", "Broken"],
+        "fail_printers": ["Broken"],
+        "print_ms": 3000,
+    }));
+    let stages = |body: &Value| -> Vec<String> {
+        body["timeline"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| event["stage"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let (status, body) = server.post("/print", json!({"filename": "sample.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    let (status, jobs) = server.get("/jobs");
+    assert_eq!(status, 200, "{}", jobs);
+    let id = jobs[0]["id"].as_u64().unwrap();
+
+    let (status, body) = server.get(&format!("/jobs/{}", id));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["id"], id);
+    assert_eq!(body["status"], "printed");
+    assert_eq!(
+        stages(&body)[..4],
+        ["received", "validated", "transformed", "submitted"]
+    );
+
+    // งานอยู่ใน spooler ของเครื่องพิมพ์จำลอง 3 วินาทีแล้วออกจากคิว
+    let deadline = Instant::now() + Duration::from_secs(15);
+    let body = loop {
+        let (_, body) = server.get(&format!("/jobs/{}", id));
+        if stages(&body).last().map(String::as_str) == Some("completed") {
+            break body;
+        }
+        assert!(Instant::now() < deadline, "Job did not complete: {}", body);
+        std::thread::sleep(Duration::from_millis(500));
+    };
+    assert_eq!(
+        stages(&body),
+        [
+            "received",
+            "validated",
+            "transformed",
+            "submitted",
+            "spooler",
+            "completed"
+        ]
+    );
+    let timeline = body["timeline"].as_array().unwrap();
+    assert_eq!(timeline[0]["elapsed_ms"], 0);
+    assert_eq!(
+        timeline[4]["detail"],
+        json!(format!("Spooler job {} is pending", body["job_id"]))
+    );
+    let total: u64 = timeline
+        .iter()
+        .map(|event| event["duration_ms"].as_u64().unwrap())
+        .sum();
+    assert_eq!(total, timeline[5]["elapsed_ms"].as_u64().unwrap());
+    assert!(total >= 2000, "{}", body);
+
+    let (status, body) = server.post(
+        "/print",
+        json!({"filename": "sample.pdf", "printer_name": "Broken"}),
+    );
+    assert_eq!(status, 500, "{}", body);
+    let (_, jobs) = server.get("/jobs");
+    let (status, body) = server.get(&format!("/jobs/{}", jobs[0]["id"]));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        stages(&body),
+        ["received", "validated", "transformed", "failed"]
+    );
+    assert_eq!(body["timeline"][3]["detail"], body["message"]);
+
+    let (status, body) = server.get("/jobs/999");
+    assert_eq!(status, 404, "{}", body);
+    assert_eq!(body["error_code"], "JOB_NOT_FOUND");
+}
+
+/// Service ที่มี tenant `a` และ `b` (API key `key-a` และ `key-b`) และไฟล์ของแต่ละ tenant
+fn start_tenants() -> TestServer {
+    let server = TestServer::start_with(json!({
+        "print_backend": {"type": "mock", "record_dir": "mock_jobs"},
+        "tenants": {
+            "a": {"api_keys": ["key-a"]},
+            "b": {"api_keys": ["key-b"]},
+        },
+    }));
+    for tenant in ["a", "b"] {
+        std::fs::create_dir_all(server.path(&format!("printable_files/{}", tenant))).unwrap();
+        std::fs::write(
+            server.path(&format!("printable_files/{}/secret.pdf", tenant)),
+            sample_pdf(),
+        )
+        .unwrap();
+    }
+    server
+}
+
+/// ชื่อไฟล์ที่พาออกจากโฟลเดอร์ของ tenant
+const TRAVERSAL_NAMES: [&str; 3] = ["../b/secret.pdf", "../../config.json", "/etc/hostname"];
+
+#[test]
+fn print_rejects_filenames_outside_tenant_folder() {
+    let server = start_tenants();
+
+    for filename in TRAVERSAL_NAMES {
+        let (status, body) = server.post_as("key-a", "/print", json!({"filename": filename}));
+        assert_eq!(status, 400, "{}: {}", filename, body);
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+    }
+    assert!(!server.path("mock_jobs").exists());
+    assert!(!server.path("printable_files/b/secret_a6.pdf").exists());
+
+    let (status, body) = server.post_as("key-a", "/print", json!({"filename": "secret.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    assert!(server.path("printable_files/a/secret_a6.pdf").exists());
+    assert!(!server.path("printable_files/b/secret_a6.pdf").exists());
+}
+
+#[test]
+fn pdf_text_rejects_filenames_outside_tenant_folder() {
+    let server = start_tenants();
+
+    for filename in TRAVERSAL_NAMES {
+        let (status, body) = server.post_as("key-a", "/pdf/text", json!({"filename": filename}));
+        assert_eq!(status, 400, "{}: {}", filename, body);
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+        assert!(body.get("pages").is_none(), "{}", body);
+    }
+    let (status, body) = server.post_as("key-a", "/pdf/text", json!({"filename": "secret.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["page_count"], 1);
+}
+
+#[test]
+fn pdf_validate_rejects_filenames_outside_tenant_folder() {
+    let server = start_tenants();
+
+    // ตอบเหมือนกันทั้งไฟล์ที่มีอยู่และไม่มี จึงใช้หาไฟล์นอก ./printable_files ไม่ได้
+    for filename in TRAVERSAL_NAMES.iter().chain(&["../../missing.pdf"]) {
+        let (status, body) =
+            server.post_as("key-a", "/pdf/validate", json!({"filename": filename}));
+        assert_eq!(status, 400, "{}: {}", filename, body);
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+        assert_eq!(body["message"], format!("Invalid filename: {}", filename));
+    }
+    let (status, body) =
+        server.post_as("key-a", "/pdf/validate", json!({"filename": "secret.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["valid"], true);
+}
+
+#[test]
+fn print_tokens_reject_filenames_outside_tenant_folder() {
+    let server = start_tenants();
+
+    for filename in TRAVERSAL_NAMES {
+        let (status, body) =
+            server.post_as("key-a", "/print/tokens", json!({"filename": filename}));
+        assert_eq!(status, 400, "{}: {}", filename, body);
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+        assert!(body.get("token").is_none(), "{}", body);
+    }
+
+    let (status, body) =
+        server.post_as("key-a", "/print/tokens", json!({"filename": "secret.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = server.post("/print/token", json!({"token": body["token"]}));
+    assert_eq!(status, 200, "{}", body);
+    assert!(server.path("printable_files/a/secret_a6.pdf").exists());
+}
+
+#[test]
+fn windows_user_requires_tenant_allowlist() {
+    let server = TestServer::start_with(json!({
+        "print_backend": {"type": "mock", "record_dir": "mock_jobs"},
+        "user_context_printing": true,
+        "tenants": {
+            "a": {"api_keys": ["key-a"], "windows_users": ["CONTOSO\\somchai"]},
+            "b": {"api_keys": ["key-b"]},
+        },
+    }));
+    std::fs::create_dir_all(server.path("printable_files/a")).unwrap();
+    std::fs::write(server.path("printable_files/a/sample.pdf"), sample_pdf()).unwrap();
+
+    for (api_key, user) in [
+        ("key-a", "CONTOSO\\malee"),
+        ("key-a", "somchai"),
+        ("key-b", "CONTOSO\\somchai"),
+    ] {
+        let (status, body) = server.post_as(
+            api_key,
+            "/print",
+            json!({"filename": "sample.pdf", "windows_user": user}),
+        );
+        assert_eq!(status, 403, "{} {}: {}", api_key, user, body);
+        assert_eq!(body["error_code"], "WINDOWS_USER_NOT_ALLOWED");
+    }
+    assert!(!server.path("mock_jobs").exists());
+
+    // ผู้ใช้ในรายชื่อผ่านการตรวจ แล้วจึงถูกปฏิเสธเพราะพิมพ์ในนามของผู้ใช้ได้เฉพาะบน Windows
+    let (status, body) = server.post_as(
+        "key-a",
+        "/print",
+        json!({"filename": "sample.pdf", "windows_user": "contoso\\SOMCHAI"}),
+    );
+    if cfg!(windows) {
+        assert_ne!(status, 403, "{}", body);
+    } else {
+        assert_eq!(status, 400, "{}", body);
+        assert_eq!(body["message"], "windows_user is only supported on Windows");
+    }
+}
+
+/// ส่ง `body` แบบ `multipart/form-data` ไปยัง `POST /print/upload`
+fn upload(server: &TestServer, body: &[u8]) -> (u16, Value) {
+    let headers = format!(
+        "Content-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: {}\r\n",
+        body.len()
+    );
+    let (status, _, body) = server.send_raw("POST", "/print/upload", &headers, body);
+    (
+        status,
+        serde_json::from_str(&body).expect("Response is not JSON"),
+    )
+}
+
+#[test]
+fn upload_print_parses_multipart_bodies() {
+    let server = TestServer::start(json!({
+        "type": "mock",
+        "printers": ["Mock A4"],
+        "record_dir": "mock_jobs",
+    }));
+
+    let mut body = b"--XyZ\r\nContent-Disposition: form-data; name=\"options\"\r\n\r\n\
+        {\"printer_name\":\"Mock A4\"}\r\n--XyZ\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"order.pdf\"\r\n\
+        Content-Type: application/pdf\r\n\r\n"
+        .to_vec();
+    body.extend_from_slice(&sample_pdf());
+    body.extend_from_slice(b"\r\n--XyZ--\r\n");
+    let (status, response) = upload(&server, &body);
+    assert_eq!(status, 200, "{}", response);
+    assert_eq!(response["printer_name"], "Mock A4");
+
+    // ตัดทิ้งก่อน boundary ปิด และตัด header ของ part กลางคัน
+    for (body, message) in [
+        (&body[..body.len() - 10], "Multipart body is not terminated"),
+        (
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"file\"\r\n--XyZ--".as_slice(),
+            "Multipart part has no header",
+        ),
+        (b"", "Multipart body has no boundary"),
+    ] {
+        let (status, response) = upload(&server, body);
+        assert_eq!(status, 400, "{}", response);
+        assert_eq!(response["error_code"], "INVALID_REQUEST");
+        assert_eq!(response["message"], message);
+    }
+}
+
+#[test]
+fn upload_print_rejects_oversized_bodies() {
+    let server = TestServer::start(json!({"type": "mock", "printers": ["Mock A4"]}));
+    // ตอบกลับจาก Content-Length โดยไม่ต้องรอรับ body
+    let headers = format!(
+        "Content-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: {}\r\n",
+        64 * 1024 * 1024 + 1
+    );
+    let (status, _, body) = server.send_raw("POST", "/print/upload", &headers, b"--XyZ\r\n");
+    assert_eq!(status, 413, "{}", body);
+    assert!(!server
+        .path("printable_files")
+        .read_dir()
+        .unwrap()
+        .any(|entry| entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with("upload_")));
+}
+
+/// ส่ง chunk ของ upload (`offset` = `None` ไม่ส่ง header `Upload-Offset`)
+fn send_chunk(
+    server: &TestServer,
+    upload_id: &str,
+    offset: Option<&str>,
+    data: &[u8],
+) -> (u16, Value) {
+    let offset = offset
+        .map(|offset| format!("Upload-Offset: {}\r\n", offset))
+        .unwrap_or_default();
+    let headers = format!(
+        "{}Content-Type: application/offset+octet-stream\r\nContent-Length: {}\r\n",
+        offset,
+        data.len()
+    );
+    let path = format!("/uploads/{}", upload_id);
+    let (status, _, body) = server.send_raw("PATCH", &path, &headers, data);
+    (
+        status,
+        serde_json::from_str(&body).expect("Response is not JSON"),
+    )
+}
+
+#[test]
+fn resumable_uploads_check_offsets_sizes_and_checksums() {
+    let server = TestServer::start(json!({"type": "mock", "printers": ["Mock A4"]}));
+    let file = sample_pdf();
+    let sha256 = Sha256::digest(&file)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let create = |filename: &str, sha256: &str| {
+        server.post(
+            "/uploads",
+            json!({"filename": filename, "size": file.len(), "sha256": sha256}),
+        )
+    };
+
+    for (filename, sha256) in [("../scan.pdf", sha256.as_str()), ("scan.pdf", "abc")] {
+        let (status, body) = create(filename, sha256);
+        assert_eq!(status, 400, "{}", body);
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+    }
+
+    let (status, body) = create("scan.pdf", &sha256);
+    assert_eq!(status, 201, "{}", body);
+    let upload_id = body["upload_id"].as_str().unwrap().to_string();
+    let half = file.len() / 2;
+
+    for offset in [None, Some("abc"), Some("-1"), Some("")] {
+        let (status, body) = send_chunk(&server, &upload_id, offset, &file[..half]);
+        assert_eq!(status, 400, "{}", body);
+        assert_eq!(body["message"], "Missing or invalid Upload-Offset header");
+    }
+    let (status, body) = send_chunk(&server, &upload_id, Some("5"), &file[..half]);
+    assert_eq!(status, 409, "{}", body);
+    assert_eq!(body["error_code"], "UPLOAD_OFFSET_MISMATCH");
+
+    let (status, body) = send_chunk(&server, &upload_id, Some("0"), &file[..half]);
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        (body["offset"].as_u64(), body["complete"].as_bool()),
+        (Some(half as u64), Some(false))
+    );
+    // chunk เดิมที่ส่งซ้ำหลังการเชื่อมต่อหลุด
+    let (status, body) = send_chunk(&server, &upload_id, Some("0"), &file[..half]);
+    assert_eq!(status, 409, "{}", body);
+    assert_eq!(
+        body["message"],
+        format!("Upload-Offset does not match, expected {}", half)
+    );
+
+    // ข้อมูลเกินขนาดที่แจ้งไว้ไม่ถูกเขียน
+    let mut oversized = file[half..].to_vec();
+    oversized.push(b'\n');
+    let (status, body) = send_chunk(&server, &upload_id, Some(&half.to_string()), &oversized);
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(body["message"], "Chunk exceeds the declared upload size");
+    let (status, body) = server.get(&format!("/uploads/{}", upload_id));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["offset"], half);
+
+    let (status, body) = send_chunk(&server, &upload_id, Some(&half.to_string()), &file[half..]);
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["complete"], true);
+    assert_eq!(
+        sha256_file(&server.path("printable_files/scan.pdf")),
+        sha256
+    );
+    let (status, body) = send_chunk(&server, &upload_id, Some(&file.len().to_string()), b"");
+    assert_eq!(status, 409, "{}", body);
+
+    // SHA-256 ที่ไม่ตรงทำให้ upload ถูกลบ
+    let (status, body) = create("other.pdf", &"0".repeat(64));
+    assert_eq!(status, 201, "{}", body);
+    let upload_id = body["upload_id"].as_str().unwrap().to_string();
+    let (status, body) = send_chunk(&server, &upload_id, Some("0"), &file);
+    assert_eq!(status, 422, "{}", body);
+    assert_eq!(body["error_code"], "CHECKSUM_MISMATCH");
+    assert!(!server.path("printable_files/other.pdf").exists());
+    let (status, body) = server.get(&format!("/uploads/{}", upload_id));
+    assert_eq!(status, 404, "{}", body);
+    assert_eq!(body["error_code"], "UPLOAD_NOT_FOUND");
+}
+
+/// server ที่ตั้ง `language` เป็นภาษาไทย (request ที่ไม่มี `Accept-Language` ได้ข้อความภาษาไทย)
+fn start_thai() -> TestServer {
+    TestServer::start_with(json!({
+        "print_backend": {"type": "mock", "printers": ["Mock A4"]},
+        "agents": {"branch-01": "agent-secret"},
+        "language": "th",
+    }))
+}
+
+#[test]
+fn print_success_messages_follow_the_request_language() {
+    let server = start_thai();
+    let (status, body) = server.post("/print", json!({"filename": "sample.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["message"], "ส่งงานพิมพ์ไปยังเครื่องพิมพ์ Mock A4 แล้ว");
+    assert!(body["detail"]
+        .as_str()
+        .unwrap()
+        .ends_with("and sent to printer Mock A4"));
+
+    let (status, body) = server.post(
+        "/print",
+        json!({"filename": "sample.pdf", "agent_id": "branch-01"}),
+    );
+    assert_eq!(status, 202, "{}", body);
+    assert_eq!(
+        body["message"],
+        format!(
+            "เก็บงานพิมพ์ไว้ในคิวของสาขา branch-01 แล้ว (งานที่ {})",
+            body["job_id"]
+        )
+    );
+
+    // `Accept-Language` มาก่อน `language` ใน config.json
+    let request = json!({"filename": "sample.pdf", "force": true}).to_string();
+    let headers = format!(
+        "Accept-Language: en\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+        request.len()
+    );
+    let (status, _, body) = server.send_raw("POST", "/print", &headers, request.as_bytes());
+    assert_eq!(status, 200, "{}", body);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .ends_with("and sent to printer Mock A4"));
+    assert!(body.get("detail").is_none(), "{}", body);
+}
+
+#[test]
+fn printer_success_messages_follow_the_request_language() {
+    let server = start_thai();
+    for (action, message) in [
+        ("pause", "หยุดส่งงานไปยังเครื่องพิมพ์ Mock A4 แล้ว"),
+        ("resume", "เครื่องพิมพ์ Mock A4 ทำงานต่อแล้ว"),
+        ("purge", "ยกเลิกงานพิมพ์ 0 งานของเครื่องพิมพ์ Mock A4 แล้ว"),
+        ("test", "ส่งหน้าทดสอบไปยังเครื่องพิมพ์ Mock A4 แล้ว"),
+    ] {
+        let (status, body) = server.post(&format!("/printers/Mock%20A4/{}", action), json!({}));
+        assert_eq!(status, 200, "{}: {}", action, body);
+        assert_eq!(body["message"], message);
+        assert!(body["detail"].is_string(), "{}", body);
+    }
+    let (status, body) = server.post("/printers/Mock%20A4/pause?agent_id=branch-01", json!({}));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        body["message"],
+        "หยุดส่งงานไปยังเครื่องพิมพ์ Mock A4 ของสาขา branch-01 แล้ว"
+    );
+}
+
+#[test]
+fn upload_success_messages_follow_the_request_language() {
+    let server = start_thai();
+    let (status, body) = server.post(
+        "/uploads",
+        json!({"filename": "scan.pdf", "size": 10, "sha256": "0".repeat(64)}),
+    );
+    assert_eq!(status, 201, "{}", body);
+    let upload_id = body["upload_id"].as_str().unwrap();
+    let (status, body) = server.request("DELETE", &format!("/uploads/{}", upload_id), None);
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        body["message"],
+        format!("ยกเลิกการอัปโหลด {} แล้ว", upload_id)
+    );
+    assert_eq!(body["detail"], format!("Cancelled upload {}", upload_id));
+}
+
+#[test]
+fn redeemed_print_token_messages_follow_the_request_language() {
+    let server = start_thai();
+    let (status, body) = server.post("/print/tokens", json!({"filename": "sample.pdf"}));
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = server.post("/print/token", json!({"token": body["token"]}));
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["message"], "ส่งงานพิมพ์ไปยังเครื่องพิมพ์ Mock A4 แล้ว");
+    assert!(body["detail"].is_string(), "{}", body);
+}